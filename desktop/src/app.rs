@@ -177,6 +177,8 @@ pub struct InstallOptions {
     pub driver_action: DriverAction,
     /// 自定义无人值守文件绝对路径（空=使用内置生成）
     pub custom_unattend_path: String,
+    /// 自定义无人值守与内置生成的应答合并（见 `lr_core::unattend::Unattend::merge`）
+    pub custom_unattend_merge: bool,
     /// 目标镜像是否为 XP/2003（NT 5.x）
     pub is_xp: bool,
     /// 目标镜像为 XP/2003 的 i386 文本安装介质（无 install.wim，仅 \I386 文本安装结构）。
@@ -420,6 +422,7 @@ pub struct App {
     pub driver_backup_mode: crate::ui::tools::DriverBackupMode,
     pub driver_backup_target: Option<String>,
     pub driver_backup_path: String,
+    pub driver_backup_archive: bool,
     pub driver_backup_loading: bool,
     pub driver_backup_message: String,
     
@@ -528,6 +531,39 @@ pub struct App {
     pub hash_verify_progress_rx: Option<Receiver<u8>>,
     pub hash_verify_result_rx: Option<Receiver<crate::ui::tools::hash_verify::HashVerifyResult>>,
 
+    // 驱动库对话框
+    pub show_driver_repo_dialog: bool,
+    pub driver_repo_path: String,
+    pub driver_repo_filter: String,
+    pub driver_repo_show_redundant: bool,
+    pub driver_repo_confirm_prune: bool,
+    pub driver_repo_loading: bool,
+    pub driver_repo_message: String,
+    pub driver_repo_index: Option<lr_core::driver_repo::RepoIndex>,
+    pub driver_repo_rx: Option<Receiver<crate::ui::tools::driver_repo::DriverRepoResult>>,
+
+    // 驱动导入报告
+    pub show_driver_report_dialog: bool,
+    pub driver_report: Option<lr_core::driver_report::DriverImportReport>,
+    pub driver_report_failed_only: bool,
+    pub driver_report_checked: bool,
+
+    // 分区表还原对话框
+    pub show_partition_restore_dialog: bool,
+    pub partition_restore_backups: Vec<(std::path::PathBuf, lr_core::partition_backup::BackupDescription)>,
+    pub partition_restore_selected: Option<usize>,
+    pub partition_restore_target: Option<(u32, lr_core::partition_backup::TargetMatch)>,
+    pub partition_restore_allow_id_change: bool,
+    pub partition_restore_confirm: bool,
+    pub partition_restore_running: bool,
+    pub partition_restore_message: String,
+    pub partition_restore_rx: Option<Receiver<crate::ui::tools::partition_restore::PartitionRestoreResult>>,
+
+    // 执行计划确认页
+    pub show_execution_plan_dialog: bool,
+    pub execution_plan: Option<lr_core::plan::ExecutionPlan>,
+    pub execution_plan_for: Option<crate::ui::execution_plan::PlanConfirm>,
+
     // 离线密码重置对话框
     pub show_password_reset_dialog: bool,
     pub password_reset_partition: String,
@@ -572,6 +608,8 @@ pub struct App {
     // 无人值守检测相关
     /// 当前选中分区是否存在无人值守配置文件
     pub partition_has_unattend: bool,
+    /// 当前选中分区已有应答文件的校验结果（「文件: 第 x 行第 y 列：…」）
+    pub partition_unattend_issues: Vec<String>,
     /// 源镜像/安装介质是否自带无人值守应答（XP 的 winnt.sif / 介质根的 autounattend.xml 等）。
     /// 为真时默认取消勾选「无人值守」（仅改默认，不禁用，用户仍可手动勾上）。
     pub source_has_unattend: bool,
@@ -585,8 +623,18 @@ pub struct App {
     pub show_unattend_conflict_modal: bool,
     /// 用户选择的自定义无人值守文件路径（空=使用内置生成的）
     pub custom_unattend_path: String,
-    /// 自定义无人值守文件语法校验错误（Some=有错，禁用安装按钮并提示）
+    /// 自定义无人值守文件校验错误（Some=有错，禁用安装按钮并提示）
     pub custom_unattend_error: Option<String>,
+    /// 自定义应答文件的非致命提示（winnt.sif 的未知键、unattend.xml 的命名空间等），仅展示不阻止安装
+    pub custom_unattend_warnings: Vec<String>,
+    /// 上次校验自定义应答文件时所用的镜像架构；所选镜像架构变化时据此重新校验
+    pub custom_unattend_checked_arch: Option<String>,
+    /// 自定义 unattend.xml 与内置生成的应答合并（而非原样替换）
+    pub custom_unattend_merge: bool,
+    /// 合并结果预览（Some=显示预览窗口；Err 为合并失败原因）
+    pub custom_unattend_preview: Option<Result<String, String>>,
+    /// 预览窗口由「开始安装」触发：确认后继续安装
+    pub custom_unattend_preview_for_install: bool,
     
     // 安装时BitLocker解锁对话框
     /// 是否显示安装前BitLocker解锁对话框
@@ -729,6 +777,8 @@ pub struct UnattendCheckResult {
     pub has_unattend: bool,
     /// 检测到的配置文件路径（如果存在）
     pub detected_paths: Vec<String>,
+    /// 检测到的 unattend.xml 的语义校验结果（带文件名与行列号）
+    pub diagnostics: Vec<String>,
 }
 
 impl Default for App {
@@ -846,6 +896,7 @@ impl Default for App {
             driver_backup_mode: crate::ui::tools::DriverBackupMode::default(),
             driver_backup_target: None,
             driver_backup_path: String::new(),
+            driver_backup_archive: false,
             driver_backup_loading: false,
             driver_backup_message: String::new(),
             // 软件列表对话框
@@ -934,6 +985,32 @@ impl Default for App {
             hash_verify_progress: None,
             hash_verify_progress_rx: None,
             hash_verify_result_rx: None,
+            // 驱动库对话框
+            show_driver_repo_dialog: false,
+            driver_repo_path: String::new(),
+            driver_repo_filter: String::new(),
+            driver_repo_show_redundant: false,
+            driver_repo_confirm_prune: false,
+            driver_repo_loading: false,
+            driver_repo_message: String::new(),
+            driver_repo_index: None,
+            driver_repo_rx: None,
+            show_driver_report_dialog: false,
+            driver_report: None,
+            driver_report_failed_only: false,
+            driver_report_checked: false,
+            show_partition_restore_dialog: false,
+            partition_restore_backups: Vec::new(),
+            partition_restore_selected: None,
+            partition_restore_target: None,
+            partition_restore_allow_id_change: false,
+            partition_restore_confirm: false,
+            partition_restore_running: false,
+            partition_restore_message: String::new(),
+            partition_restore_rx: None,
+            show_execution_plan_dialog: false,
+            execution_plan: None,
+            execution_plan_for: None,
             // 离线密码重置对话框
             show_password_reset_dialog: false,
             password_reset_partition: String::new(),
@@ -964,9 +1041,15 @@ impl Default for App {
             embedded_assets: crate::ui::EmbeddedAssets::new(),
             // 无人值守检测相关
             partition_has_unattend: false,
+            partition_unattend_issues: Vec::new(),
             source_has_unattend: false,
             custom_unattend_path: String::new(),
             custom_unattend_error: None,
+            custom_unattend_warnings: Vec::new(),
+            custom_unattend_checked_arch: None,
+            custom_unattend_merge: false,
+            custom_unattend_preview: None,
+            custom_unattend_preview_for_install: false,
             unattend_check_loading: false,
             unattend_check_rx: None,
             last_unattend_check_partition: None,
@@ -1540,6 +1623,9 @@ impl eframe::App for App {
         
         // 检查工具箱异步操作结果
         self.check_tools_async_operations();
+
+        // 新系统首次启动：展示部署时写下的驱动导入报告
+        self.check_pending_driver_report();
         
         // 错误对话框
         if self.show_error_dialog {
@@ -1610,6 +1696,60 @@ impl eframe::App for App {
                 });
         }
 
+        // 自定义无人值守合并结果预览
+        if let Some(preview) = self.custom_unattend_preview.clone() {
+            let mut close = false;
+            let mut confirm = false;
+            egui::Window::new(tr!("无人值守合并预览"))
+                .collapsible(false)
+                .resizable(true)
+                .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+                .default_size([640.0, 480.0])
+                .show(ctx, |ui| {
+                    ui.label(
+                        egui::RichText::new(tr!("用户文件中已写的设置优先；内置的部署脚本、首次登录命令、账户等补入，列表项追加在用户项之后。")).small(),
+                    );
+                    ui.add_space(5.0);
+                    match &preview {
+                        Ok(xml) => {
+                            egui::ScrollArea::both().max_height(360.0).show(ui, |ui| {
+                                ui.add(
+                                    egui::TextEdit::multiline(&mut xml.as_str())
+                                        .code_editor()
+                                        .desired_width(f32::INFINITY),
+                                );
+                            });
+                        }
+                        Err(e) => {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(220, 50, 47),
+                                tr!("合并失败，将原样使用自定义文件：{}", e),
+                            );
+                        }
+                    }
+                    ui.add_space(10.0);
+                    ui.horizontal(|ui| {
+                        if self.custom_unattend_preview_for_install {
+                            if ui.button(tr!("确认并开始安装")).clicked() {
+                                confirm = true;
+                            }
+                            if ui.button(tr!("取消")).clicked() {
+                                close = true;
+                            }
+                        } else if ui.button(tr!("关闭")).clicked() {
+                            close = true;
+                        }
+                    });
+                });
+            if close || confirm {
+                self.custom_unattend_preview = None;
+                self.custom_unattend_preview_for_install = false;
+            }
+            if confirm {
+                self.start_installation();
+            }
+        }
+
         // 安装时BitLocker解锁对话框
         // 使用一个临时UI来渲染对话框
         egui::Area::new(egui::Id::new("install_bitlocker_dialog_area"))
@@ -1623,6 +1763,18 @@ impl eframe::App for App {
                 self.render_backup_bitlocker_dialog(ui);
             });
 
+        // 驱动导入报告（首次启动自动弹出，也可从工具箱打开，不依赖当前页面）
+        egui::Area::new(egui::Id::new("driver_report_dialog_area"))
+            .show(ctx, |ui| {
+                self.render_driver_report_dialog(ui);
+            });
+
+        // 安装 / 备份执行计划确认页
+        egui::Area::new(egui::Id::new("execution_plan_dialog_area"))
+            .show(ctx, |ui| {
+                self.render_execution_plan_dialog(ui);
+            });
+
         // 底部状态栏
         egui::TopBottomPanel::bottom("bottom_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
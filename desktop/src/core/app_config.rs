@@ -50,6 +50,10 @@ pub struct AppConfig {
     /// 「系统安装」页选项偏好（记住上次勾选状态，下次启动自动恢复）。
     #[serde(default)]
     pub install_prefs: crate::app::InstallPrefs,
+
+    /// 一键分区预设（可在一键分区对话框中选用，也可供 `--partition --preset` 命令行使用）
+    #[serde(default)]
+    pub partition_presets: Vec<lr_core::partition_preset::PartitionPreset>,
 }
 
 /// 日志默认启用
@@ -80,6 +84,7 @@ impl Default for AppConfig {
             wim_engine: 0,  // 默认 libwim
             enable_advanced_options: false,
             install_prefs: crate::app::InstallPrefs::default(),
+            partition_presets: Vec::new(),
         }
     }
 }
@@ -224,6 +229,14 @@ impl AppConfig {
         }
     }
 
+    /// 设置一键分区预设列表并保存
+    pub fn set_partition_presets(&mut self, presets: Vec<lr_core::partition_preset::PartitionPreset>) {
+        self.partition_presets = presets;
+        if let Err(e) = self.save() {
+            log::warn!("保存配置失败: {}", e);
+        }
+    }
+
     /// 设置界面语言并保存
     /// 
     /// # Arguments
@@ -2,9 +2,10 @@ use anyhow::Result;
 use std::path::Path;
 
 use crate::tr;
-use crate::utils::cmd::create_command;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
+use lr_core::diskpart_output;
 
 pub struct BootManager {
     bcdedit_path: String,
@@ -22,7 +23,7 @@ impl BootManager {
 
     /// 获取当前系统引导 GUID
     pub fn get_current_boot_guid(&self) -> Result<String> {
-        let output = create_command(&self.bcdedit_path).args(["/enum"]).output()?;
+        let output = command::run(&self.bcdedit_path, ["/enum"])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
@@ -42,6 +43,39 @@ impl BootManager {
         anyhow::bail!("Could not find current boot GUID")
     }
 
+    /// 运行一段 diskpart 查询脚本，返回标准输出
+    fn diskpart_query(script: &str, file_name: &str) -> Result<String> {
+        let script_path = std::env::temp_dir().join(file_name);
+        std::fs::write(&script_path, script)?;
+
+        let output = command::run("diskpart", ["/s", &script_path.to_string_lossy()])?;
+        let _ = std::fs::remove_file(&script_path);
+        Ok(gbk_to_utf8(&output.stdout))
+    }
+
+    /// 在指定磁盘上找 ESP 的分区号。
+    ///
+    /// `list partition` 的类型列是本地化文本（System / 系统 / Système），
+    /// 所以只取小分区逐个 `detail partition`，按分区类型 GUID 判断。
+    fn find_esp_partition_number(disk: u32) -> Result<Option<u32>> {
+        let list = Self::diskpart_query(
+            &format!("select disk {}\nlist partition\n", disk),
+            "list_part.txt",
+        )?;
+        let rows = diskpart_output::parse_list_partition(&list);
+
+        for number in diskpart_output::esp_candidates(&rows) {
+            let detail = Self::diskpart_query(
+                &format!("select disk {}\nselect partition {}\ndetail partition\n", disk, number),
+                "detail_part.txt",
+            )?;
+            if diskpart_output::parse_detail_partition(&detail).is_esp() {
+                return Ok(Some(number));
+            }
+        }
+        Ok(None)
+    }
+
     /// 查找目标 Windows 分区所在磁盘的 ESP 分区
     pub fn find_esp_on_same_disk(&self, windows_partition: &str) -> Result<String> {
         log::info!("[BOOT] 查找 {} 所在磁盘的 ESP 分区...", windows_partition);
@@ -49,89 +83,28 @@ impl BootManager {
         // 提取盘符（去掉冒号）
         let drive_letter = windows_partition.trim_end_matches(':').trim_end_matches('\\');
         
-        // Step 1: 使用 diskpart 获取该分区所在的磁盘号
-        let script1 = format!(r#"select volume {}
-detail volume
-"#, drive_letter);
-        
-        let script1_path = std::env::temp_dir().join("find_disk.txt");
-        std::fs::write(&script1_path, &script1)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script1_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
+        // Step 1: 选中该卷后 `list disk` 用 `*` 标出它所在的磁盘
+        let stdout = Self::diskpart_query(
+            &format!("select volume {}\nlist disk\n", drive_letter),
+            "find_disk.txt",
+        )?;
         log::info!("[BOOT] 查找磁盘号:\n{}", stdout);
-        
-        // 解析磁盘号
-        let mut disk_num: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            // 查找 "Disk 0" 或 "磁盘 0"
-            if line_lower.contains("disk") || line_lower.contains("磁盘") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("disk") || *part == "磁盘" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                disk_num = Some(num);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        
-        let disk_num = disk_num.ok_or_else(|| anyhow::anyhow!("{}", tr!("无法确定分区所在磁盘")))?;
+
+        let disk_num = diskpart_output::parse_list_disk(&stdout)
+            .into_iter()
+            .find(|d| d.selected)
+            .map(|d| d.number)
+            .ok_or_else(|| anyhow::anyhow!("{}", tr!("无法确定分区所在磁盘")))?;
         log::info!("[BOOT] 目标分区在磁盘 {}", disk_num);
-        
-        // Step 2: 查找该磁盘上的 ESP 分区（使用 GPT 类型）
-        let script2 = format!(r#"select disk {}
-list partition
-"#, disk_num);
-        
-        let script2_path = std::env::temp_dir().join("list_part.txt");
-        std::fs::write(&script2_path, &script2)?;
-        
-        let output = create_command("diskpart")
-            .args(["/s", &script2_path.to_string_lossy()])
-            .output()?;
-        
-        let stdout = gbk_to_utf8(&output.stdout);
-        log::info!("[BOOT] 分区列表:\n{}", stdout);
-        
-        // 查找 System/系统 类型的分区（ESP）
-        let mut esp_partition: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            // 查找 "System" 或 "系统" 类型的分区
-            if line_lower.contains("system") || line_lower.contains("系统") {
-                // 提取分区号
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("partition") || *part == "分区" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                esp_partition = Some(num);
-                                log::info!("[BOOT] 找到 ESP: 分区 {}", num);
-                                break;
-                            }
-                        }
-                    }
-                }
-                if esp_partition.is_some() {
-                    break;
-                }
-            }
-        }
-        
-        let esp_partition = esp_partition.ok_or_else(|| anyhow::anyhow!("{}", tr!("未找到 ESP 分区")))?;
-        
+
+        // Step 2: 按分区类型 GUID 查找该磁盘上的 ESP 分区
+        let esp_partition = Self::find_esp_partition_number(disk_num)?
+            .ok_or_else(|| anyhow::anyhow!("{}", tr!("未找到 ESP 分区")))?;
+        log::info!("[BOOT] 找到 ESP: 分区 {}", esp_partition);
+
         // Step 3: 为 ESP 分配盘符
         // 先尝试移除可能存在的旧盘符
-        let _ = create_command("mountvol").args(["S:", "/d"]).output();
+        let _ = command::run("mountvol", ["S:", "/d"]);
         std::thread::sleep(std::time::Duration::from_millis(200));
         
         let script3 = format!(r#"select disk {}
@@ -142,9 +115,7 @@ assign letter=S
         let script3_path = std::env::temp_dir().join("assign_esp.txt");
         std::fs::write(&script3_path, &script3)?;
         
-        let output = create_command("diskpart")
-            .args(["/s", &script3_path.to_string_lossy()])
-            .output()?;
+        let output = command::run("diskpart", ["/s", &script3_path.to_string_lossy()])?;
         
         let stdout = gbk_to_utf8(&output.stdout);
         log::info!("[BOOT] 分配 ESP 盘符:\n{}", stdout);
@@ -173,7 +144,7 @@ assign letter=S
         
         // 方法2: 使用 mountvol /s 挂载 ESP 到 S:
         log::info!("[BOOT] 尝试使用 mountvol /s 挂载 ESP");
-        let output = create_command("mountvol").args(["S:", "/s"]).output();
+        let output = command::run("mountvol", ["S:", "/s"]);
         if output.is_ok() {
             std::thread::sleep(std::time::Duration::from_millis(500));
             if Path::new("S:\\").exists() {
@@ -192,64 +163,35 @@ assign letter=S
         
         // 遍历磁盘0-3
         for disk in 0..4 {
-            let script = format!(r#"select disk {}
-list partition
-"#, disk);
-            
-            let script_path = std::env::temp_dir().join("check_disk.txt");
-            std::fs::write(&script_path, &script)?;
-            
-            let output = create_command("diskpart")
-                .args(["/s", &script_path.to_string_lossy()])
-                .output()?;
-            
-            let stdout = gbk_to_utf8(&output.stdout);
-            
-            // 查找 System 类型分区
-            for line in stdout.lines() {
-                let line_lower = line.to_lowercase();
-                if line_lower.contains("system") || line_lower.contains("系统") {
-                    // 提取分区号
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    for (i, part) in parts.iter().enumerate() {
-                        if part.to_lowercase().contains("partition") || *part == "分区" {
-                            if let Some(num_str) = parts.get(i + 1) {
-                                if let Ok(part_num) = num_str.parse::<usize>() {
-                                    // 找到了，分配盘符
-                                    let assign_script = format!(r#"select disk {}
+            let Some(part_num) = Self::find_esp_partition_number(disk)? else {
+                continue;
+            };
+
+            // 找到了，分配盘符
+            let assign_script = format!(r#"select disk {}
 select partition {}
 assign letter=S
 "#, disk, part_num);
-                                    
-                                    let assign_path = std::env::temp_dir().join("assign_esp2.txt");
-                                    std::fs::write(&assign_path, &assign_script)?;
-                                    
-                                    let _ = create_command("diskpart")
-                                        .args(["/s", &assign_path.to_string_lossy()])
-                                        .output();
-                                    
-                                    std::thread::sleep(std::time::Duration::from_millis(500));
-                                    
-                                    if Path::new("S:\\").exists() {
-                                        log::info!("[BOOT] 找到 ESP: 磁盘 {} 分区 {}", disk, part_num);
-                                        return Ok("S:".to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+
+            let assign_path = std::env::temp_dir().join("assign_esp2.txt");
+            std::fs::write(&assign_path, &assign_script)?;
+
+            let _ = command::run("diskpart", ["/s", &assign_path.to_string_lossy()]);
+
+            std::thread::sleep(std::time::Duration::from_millis(500));
+
+            if Path::new("S:\\").exists() {
+                log::info!("[BOOT] 找到 ESP: 磁盘 {} 分区 {}", disk, part_num);
+                return Ok("S:".to_string());
             }
         }
-        
+
         anyhow::bail!("{}", tr!("未找到 EFI 系统分区"))
     }
 
     /// 设置默认引导项
     pub fn set_default_boot(&self, guid: &str) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/default", guid])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/default", guid])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to set default boot entry");
@@ -259,9 +201,7 @@ assign letter=S
 
     /// 设置引导超时
     pub fn set_timeout(&self, seconds: u32) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/timeout", &seconds.to_string()])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/timeout", &seconds.to_string()])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to set boot timeout");
@@ -271,9 +211,7 @@ assign letter=S
 
     /// 删除引导项
     pub fn delete_boot_entry(&self, guid: &str) -> Result<()> {
-        let output = create_command(&self.bcdedit_path)
-            .args(["/delete", guid, "/f"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/delete", guid, "/f"])?;
 
         if !output.status.success() {
             anyhow::bail!("Failed to delete boot entry");
@@ -379,7 +317,7 @@ assign letter=S
         );
         let p = std::env::temp_dir().join("lr_bp_asg.txt");
         std::fs::write(&p, script.as_bytes())?;
-        let _ = create_command("diskpart").args(["/s", &p.to_string_lossy()]).output()?;
+        let _ = command::run("diskpart", ["/s", &p.to_string_lossy()])?;
         let _ = std::fs::remove_file(&p);
         std::thread::sleep(std::time::Duration::from_millis(600));
         let letter = format!("{}:", free);
@@ -397,7 +335,7 @@ assign letter=S
         );
         let p = std::env::temp_dir().join("lr_set_active.txt");
         std::fs::write(&p, script.as_bytes())?;
-        let out = create_command("diskpart").args(["/s", &p.to_string_lossy()]).output()?;
+        let out = command::run("diskpart", ["/s", &p.to_string_lossy()])?;
         let _ = std::fs::remove_file(&p);
         log::info!(
             "[BOOT] 设活动分区 磁盘{}:分区{}: {}",
@@ -415,7 +353,7 @@ assign letter=S
         let script = format!("select volume {}\r\nactive\r\n", vol);
         let p = std::env::temp_dir().join("lr_set_active_vol.txt");
         std::fs::write(&p, script.as_bytes())?;
-        let out = create_command("diskpart").args(["/s", &p.to_string_lossy()]).output()?;
+        let out = command::run("diskpart", ["/s", &p.to_string_lossy()])?;
         let _ = std::fs::remove_file(&p);
         log::info!("[BOOT] 设活动分区 卷{}: {}", vol, gbk_to_utf8(&out.stdout).trim());
         Ok(())
@@ -486,14 +424,12 @@ assign letter=S
                     // 使用 bcdboot 写入 UEFI 引导文件
                     // bcdboot C:\Windows /s S: /f UEFI /l zh-cn
                     log::info!("[BOOT] 执行: bcdboot {} /s {} /f UEFI /l zh-cn", windows_path, esp_letter);
-                    let output = create_command(&self.bcdboot_path)
-                        .args([
-                            &windows_path,
-                            "/s", &esp_letter,
-                            "/f", "UEFI",
-                            "/l", "zh-cn"
-                        ])
-                        .output()?;
+                    let output = command::run(&self.bcdboot_path, [
+                        &windows_path,
+                        "/s", &esp_letter,
+                        "/f", "UEFI",
+                        "/l", "zh-cn"
+                    ])?;
                     
                     let stdout = gbk_to_utf8(&output.stdout);
                     let stderr = gbk_to_utf8(&output.stderr);
@@ -504,14 +440,12 @@ assign letter=S
                     if !output.status.success() {
                         // 尝试使用 ALL 参数（同时创建 UEFI 和 BIOS 引导）
                         log::info!("[BOOT] 重试：使用 ALL 模式");
-                        let output = create_command(&self.bcdboot_path)
-                            .args([
-                                &windows_path,
-                                "/s", &esp_letter,
-                                "/f", "ALL",
-                                "/l", "zh-cn"
-                            ])
-                            .output()?;
+                        let output = command::run(&self.bcdboot_path, [
+                            &windows_path,
+                            "/s", &esp_letter,
+                            "/f", "ALL",
+                            "/l", "zh-cn"
+                        ])?;
                         
                         let stdout = gbk_to_utf8(&output.stdout);
                         let stderr = gbk_to_utf8(&output.stderr);
@@ -521,13 +455,11 @@ assign letter=S
                         if !output.status.success() {
                             // 最后尝试不指定 /f 参数
                             log::info!("[BOOT] 重试：不指定引导类型");
-                            let output = create_command(&self.bcdboot_path)
-                                .args([
-                                    &windows_path,
-                                    "/s", &esp_letter,
-                                    "/l", "zh-cn"
-                                ])
-                                .output()?;
+                            let output = command::run(&self.bcdboot_path, [
+                                &windows_path,
+                                "/s", &esp_letter,
+                                "/l", "zh-cn"
+                            ])?;
                             
                             let stderr = gbk_to_utf8(&output.stderr);
                             if !output.status.success() {
@@ -562,9 +494,7 @@ assign letter=S
                     log::warn!("[BOOT] 查找 ESP 失败: {}，尝试默认方式", e);
                     
                     // 尝试默认方式（让 bcdboot 自动处理）
-                    let output = create_command(&self.bcdboot_path)
-                        .args([&windows_path, "/f", "UEFI", "/l", "zh-cn"])
-                        .output()?;
+                    let output = command::run(&self.bcdboot_path, [&windows_path, "/f", "UEFI", "/l", "zh-cn"])?;
                     
                     let stdout = gbk_to_utf8(&output.stdout);
                     let stderr = gbk_to_utf8(&output.stderr);
@@ -594,9 +524,7 @@ assign letter=S
             log::info!("[BOOT] Legacy 引导分区: {} (磁盘{}:分区{})", boot_letter, boot_disk, boot_part);
 
             // 1) bcdboot W:\Windows /s <引导分区> /f BIOS /l zh-cn（/s 指定系统分区——关键差异）
-            let out = create_command(&self.bcdboot_path)
-                .args([windows_path.as_str(), "/s", boot_letter.as_str(), "/f", "BIOS", "/l", "zh-cn"])
-                .output()?;
+            let out = command::run(&self.bcdboot_path, [windows_path.as_str(), "/s", boot_letter.as_str(), "/f", "BIOS", "/l", "zh-cn"])?;
             log::info!(
                 "[BOOT] bcdboot /s {}: stdout={} stderr={}",
                 boot_letter,
@@ -605,14 +533,10 @@ assign letter=S
             );
             if !out.status.success() {
                 // 回退1：不带 /s（让 bcdboot 自己挑活动分区）
-                let out2 = create_command(&self.bcdboot_path)
-                    .args([windows_path.as_str(), "/f", "BIOS", "/l", "zh-cn"])
-                    .output()?;
+                let out2 = command::run(&self.bcdboot_path, [windows_path.as_str(), "/f", "BIOS", "/l", "zh-cn"])?;
                 if !out2.status.success() {
                     // 回退2：不带 /f
-                    let out3 = create_command(&self.bcdboot_path)
-                        .args([windows_path.as_str(), "/l", "zh-cn"])
-                        .output()?;
+                    let out3 = command::run(&self.bcdboot_path, [windows_path.as_str(), "/l", "zh-cn"])?;
                     if !out3.status.success() {
                         anyhow::bail!("{}", tr!("Legacy 引导修复失败: {}", gbk_to_utf8(&out3.stderr)));
                     }
@@ -622,9 +546,7 @@ assign letter=S
             // 2) bootsect /nt60 <引导分区> /force /mbr（写【引导分区】的引导扇区 + MBR 引导码）
             let bootsect_path = get_bin_dir().join("bootsect.exe");
             if bootsect_path.exists() {
-                let out = create_command(&bootsect_path)
-                    .args(["/nt60", boot_letter.as_str(), "/force", "/mbr"])
-                    .output()?;
+                let out = command::run(&bootsect_path, ["/nt60", boot_letter.as_str(), "/force", "/mbr"])?;
                 log::info!(
                     "[BOOT] bootsect /nt60 {} /force /mbr: {}",
                     boot_letter,
@@ -698,3 +620,59 @@ impl Default for BootManager {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lr_core::command::{with_runner, FakeReply, FakeRunner};
+    use std::sync::Arc;
+
+    const LIST_PARTITION: &str = "\r\n  Partition ###  Type              Size     Offset\r\n  -------------  ----------------  -------  -------\r\n  Partition 1    Recovery           499 MB  1024 KB\r\n  Partition 2    System             100 MB   500 MB\r\n  Partition 3    Reserved            16 MB   600 MB\r\n  Partition 4    Primary            237 GB   616 MB\r\n";
+
+    fn detail(number: u32, type_id: &str) -> FakeReply {
+        FakeReply::ok(&format!(
+            "\r\nPartition {}\r\nType    : {}\r\nHidden  : Yes\r\nRequired: No\r\nAttrib  : 0X8000000000000000\r\nOffset in Bytes: 1048576\r\n",
+            number, type_id
+        ))
+    }
+
+    #[test]
+    fn test_find_esp_partition_number_by_type_guid() {
+        let fake = Arc::new(
+            FakeRunner::new()
+                .on(
+                    "diskpart",
+                    &["list_part.txt"],
+                    FakeReply::ok(LIST_PARTITION),
+                )
+                .once(
+                    "diskpart",
+                    &["detail_part.txt"],
+                    detail(1, "de94bba4-06d1-4d40-a16a-bfd50179d6ac"),
+                )
+                .once(
+                    "diskpart",
+                    &["detail_part.txt"],
+                    detail(2, "c12a7328-f81f-11d2-ba4b-00a0c93ec93b"),
+                ),
+        );
+        let found =
+            with_runner(fake.clone(), || BootManager::find_esp_partition_number(0)).unwrap();
+
+        assert_eq!(found, Some(2));
+        let scripts: Vec<String> = fake
+            .calls()
+            .iter()
+            .map(|c| c.script.clone().unwrap_or_default())
+            .collect();
+        // 只对小分区做 detail，找到即停
+        assert_eq!(
+            scripts,
+            vec![
+                "select disk 0\nlist partition\n",
+                "select disk 0\nselect partition 1\ndetail partition\n",
+                "select disk 0\nselect partition 2\ndetail partition\n",
+            ]
+        );
+    }
+}
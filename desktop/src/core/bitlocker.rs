@@ -13,7 +13,7 @@
 //! - 使用RAII模式确保句柄正确释放
 
 use crate::tr;
-use std::os::windows::process::CommandExt;
+use crate::utils::command;
 #[cfg(windows)]
 use windows::core::PCWSTR;
 #[cfg(windows)]
@@ -261,11 +261,7 @@ impl BitLockerManager {
     /// 检查manage-bde是否可用
     #[cfg(windows)]
     fn is_manage_bde_available() -> bool {
-        std::process::Command::new("manage-bde")
-            .arg("-?")
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-            .is_ok()
+        command::run("manage-bde", ["-?"]).is_ok()
     }
 
     /// 获取指定驱动器的BitLocker状态
@@ -375,14 +371,8 @@ impl BitLockerManager {
     /// 使用manage-bde获取状态（回退方案）
     #[cfg(windows)]
     fn get_status_manage_bde(&self, drive_letter: char) -> VolumeStatus {
-        use std::process::Command;
-
         let drive = format!("{}:", drive_letter);
-        let output = match Command::new("manage-bde")
-            .args(["-status", &drive])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-        {
+        let output = match command::run("manage-bde", ["-status", &drive]) {
             Ok(o) => o,
             Err(_) => return VolumeStatus::Unknown,
         };
@@ -422,14 +412,8 @@ impl BitLockerManager {
     /// 使用manage-bde获取状态和百分比
     #[cfg(windows)]
     fn get_status_with_percentage_manage_bde(&self, drive_letter: char) -> (VolumeStatus, f32) {
-        use std::process::Command;
-
         let drive = format!("{}:", drive_letter);
-        let output = match Command::new("manage-bde")
-            .args(["-status", &drive])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-        {
+        let output = match command::run("manage-bde", ["-status", &drive]) {
             Ok(o) => o,
             Err(_) => return (VolumeStatus::Unknown, 0.0),
         };
@@ -459,14 +443,8 @@ impl BitLockerManager {
     /// 使用 manage-bde 获取恢复密钥
     #[cfg(windows)]
     fn get_recovery_key_manage_bde(&self, drive: &str) -> Result<String, String> {
-        use std::process::Command;
-
         // manage-bde -protectors -get C: -Type RecoveryPassword
-        let output = match Command::new("manage-bde")
-            .args(["-protectors", "-get", drive, "-Type", "RecoveryPassword"])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-        {
+        let output = match command::run("manage-bde", ["-protectors", "-get", drive, "-Type", "RecoveryPassword"]) {
             Ok(o) => o,
             Err(e) => return Err(tr!("执行命令失败: {}", e)),
         };
@@ -568,16 +546,10 @@ impl BitLockerManager {
     /// 使用manage-bde密码解锁
     #[cfg(windows)]
     fn unlock_with_password_manage_bde(&self, drive_letter: char, password: &str) -> UnlockResult {
-        use std::process::Command;
-
         let letter = format!("{}:", drive_letter);
         let drive = format!("{}:", drive_letter);
 
-        let output = match Command::new("manage-bde")
-            .args(["-unlock", &drive, "-password", password])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-        {
+        let output = match command::run("manage-bde", ["-unlock", &drive, "-password", password]) {
             Ok(o) => o,
             Err(e) => return UnlockResult::failure(&letter, &tr!("执行命令失败: {}", e), None),
         };
@@ -716,16 +688,10 @@ impl BitLockerManager {
         drive_letter: char,
         recovery_key: &str,
     ) -> UnlockResult {
-        use std::process::Command;
-
         let letter = format!("{}:", drive_letter);
         let drive = format!("{}:", drive_letter);
 
-        let output = match Command::new("manage-bde")
-            .args(["-unlock", &drive, "-recoverypassword", recovery_key])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
-        {
+        let output = match command::run("manage-bde", ["-unlock", &drive, "-recoverypassword", recovery_key]) {
             Ok(o) => o,
             Err(e) => return UnlockResult::failure(&letter, &tr!("执行命令失败: {}", e), None),
         };
@@ -932,20 +898,10 @@ impl BitLockerManager {
     /// 使用manage-bde解密
     #[cfg(windows)]
     fn decrypt_manage_bde(&self, drive_letter: char) -> DecryptResult {
-        use std::process::Command;
-
         let letter = format!("{}:", drive_letter);
         let drive = format!("{}:", drive_letter);
 
-        let output = match {
-            let mut cmd = Command::new("manage-bde");
-            cmd.args(["-off", &drive]);
-
-            #[cfg(windows)]
-            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-
-            cmd.output()
-        } {
+        let output = match command::run("manage-bde", ["-off", &drive]) {
             Ok(o) => o,
             Err(e) => {
                 return DecryptResult::failure(&letter, &tr!("执行命令失败: {}", e), None)
@@ -1004,13 +960,9 @@ impl BitLockerManager {
 
     #[cfg(windows)]
     fn run_protectors_cmd(&self, drive: &str, action: &str, ok_msg: &str) -> Result<String, String> {
-        use std::process::Command;
         let letter = drive.chars().next().unwrap_or('C');
         let d = format!("{}:", letter);
-        let output = Command::new("manage-bde")
-            .args(["-protectors", action, &d])
-            .creation_flags(0x08000000) // CREATE_NO_WINDOW
-            .output()
+        let output = command::run("manage-bde", ["-protectors", action, &d])
             .map_err(|e| tr!("执行 manage-bde 失败: {}", e))?;
         if output.status.success() {
             log::info!("BitLocker {} {} 成功", action, d);
@@ -1178,18 +1130,8 @@ impl BitLockerManager {
     /// 使用manage-bde获取卷详情
     #[cfg(windows)]
     fn get_volume_details_manage_bde(&self, drive_letter: char) -> (String, Option<u8>) {
-        use std::process::Command;
-
         let drive = format!("{}:", drive_letter);
-        let output = match {
-            let mut cmd = Command::new("manage-bde");
-            cmd.args(["-status", &drive]);
-
-            #[cfg(windows)]
-            cmd.creation_flags(0x08000000); // CREATE_NO_WINDOW
-
-            cmd.output()
-        } {
+        let output = match command::run("manage-bde", ["-status", &drive]) {
             Ok(o) => o,
             Err(_) => return ("密码/恢复密钥".to_string(), None),
         };
@@ -6,13 +6,13 @@
 //! 此实现不依赖 Windows SetupAPI，而是使用系统自带的 expand.exe 命令，
 //! 具有更好的兼容性和稳定性。
 
+use std::ffi::OsStr;
 use std::path::{Path, PathBuf};
-use std::process::Stdio;
 
 use anyhow::{bail, Context, Result};
 
 use crate::tr;
-use crate::utils::command::new_command;
+use crate::utils::command;
 
 /// Cabinet 文件解压器
 /// 
@@ -55,12 +55,7 @@ impl CabinetExtractor {
 
     /// 验证 expand.exe 是否可用
     fn verify_expand_available(expand_path: &Path) -> bool {
-        new_command(expand_path)
-            .arg("-?")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .is_ok()
+        command::run(expand_path, ["-?"]).is_ok()
     }
 
     /// 解压 .cab 文件到指定目录
@@ -89,14 +84,11 @@ impl CabinetExtractor {
 
         // 使用 expand.exe 解压
         // 命令格式: expand.exe -F:* <cab_file> <dest_dir>
-        let mut cmd = new_command(&self.expand_path);
-        cmd.arg("-F:*")
-            .arg(cab_path)
-            .arg(dest_dir)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let output = cmd.output().context(tr!("执行 expand.exe 失败"))?;
+        let output = command::run(
+            &self.expand_path,
+            [OsStr::new("-F:*"), cab_path.as_os_str(), dest_dir.as_os_str()],
+        )
+        .context(tr!("执行 expand.exe 失败"))?;
 
         // 处理输出
         let stdout = String::from_utf8_lossy(&output.stdout);
@@ -201,13 +193,8 @@ impl CabinetExtractor {
         }
 
         // 使用 expand.exe -D 列出内容
-        let mut cmd = new_command(&self.expand_path);
-        cmd.arg("-D")
-            .arg(cab_path)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped());
-
-        let output = cmd.output().context(tr!("执行 expand.exe 失败"))?;
+        let output = command::run(&self.expand_path, [OsStr::new("-D"), cab_path.as_os_str()])
+            .context(tr!("执行 expand.exe 失败"))?;
 
         if !output.status.success() {
             let stderr = String::from_utf8_lossy(&output.stderr);
@@ -7,11 +7,14 @@
 //!
 //! 用法：
 //! ```text
-//! LetRecovery.exe --install --config <install.json> [--advanced <advanced.json>]
+//! LetRecovery.exe --install --config <install.json> [--advanced <advanced.json>] [--plan [plan.json]]
 //! ```
 //!
+//! 带 `--plan` 时只输出执行计划（JSON，写入指定文件或标准输出），不做任何改动。
+//!
 //! 高级选项映射与 GUI 的 PE 安装路径完全一致（取 AdvancedOptions 的同一子集写入
-//! InstallConfig）；脚本/自定义文件/WiFi 等更丰富的选项不属于 PE 安装流程，故此处亦不涉及。
+//! InstallConfig，含 `unattend_*` 无人值守定制项：区域/时区/计算机名模板/密码/密钥/OOBE 页）；
+//! 脚本/自定义文件/WiFi 等更丰富的选项不属于 PE 安装流程，故此处亦不涉及。
 //!
 //! 注意：整条流程依赖真实重装环境（PE 启动 + 重启 + 部署），需真机回归。
 
@@ -23,6 +26,7 @@ use crate::core::disk::DiskManager;
 use crate::core::install_config::{ConfigFileManager, InstallConfig};
 use crate::core::pe::PeManager;
 use crate::ui::advanced_options::AdvancedOptions;
+use lr_core::plan::{plan_install, DriverSource, ExecutionPlan, InstallPlanInput, PePreparation};
 
 fn default_volume_index() -> u32 {
     1
@@ -59,6 +63,9 @@ struct CliInstallSpec {
     /// 自定义无人值守 XML 绝对路径（可选）。
     #[serde(default)]
     custom_unattend_path: String,
+    /// 自定义无人值守与内置生成的应答合并（默认 false=原样使用）。
+    #[serde(default)]
+    custom_unattend_merge: bool,
     /// 暂存配置/镜像的数据分区盘符（可选；缺省自动选一个空间足够、非目标盘的分区）。
     #[serde(default)]
     data_partition: Option<String>,
@@ -67,10 +74,18 @@ struct CliInstallSpec {
     pe_display_name: Option<String>,
 }
 
-/// 入口：`--install --config <json> [--advanced <json>]`。
-pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result<()> {
-    log::info!("[CLI INSTALL] ========== 命令行无人值守安装 ==========");
+/// 读取并校验后的命令行安装参数。
+struct CliInstall {
+    spec: CliInstallSpec,
+    advanced: AdvancedOptions,
+    is_gho: bool,
+    image_size: u64,
+    /// 镜像在数据目录中的文件名（InstallConfig.image_path 存相对文件名）
+    image_filename: String,
+}
 
+/// 步骤 1-3：读配置并校验（只读，不做任何改动）。
+fn load_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result<CliInstall> {
     // 1) 安装配置
     let spec: CliInstallSpec = {
         let text = std::fs::read_to_string(config_path)
@@ -110,35 +125,127 @@ pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result
     log::info!("[CLI INSTALL] 镜像: {}", spec.image_path);
     log::info!("[CLI INSTALL] PE: {}", spec.pe_path);
 
-    // 4) 数据分区（暂存配置 + 镜像）
     let image_size = std::fs::metadata(&spec.image_path).map(|m| m.len()).unwrap_or(0);
-    let data_partition = match &spec.data_partition {
-        Some(p) => p.clone(),
-        None => match DiskManager::find_suitable_data_partition(&spec.target_partition, image_size) {
-            Ok(Some((p, _auto))) => p,
-            Ok(None) => {
-                return Err(anyhow!(
-                    "{}",
-                    tr!(
-                        "未找到空间足够的数据分区来暂存镜像（需约 {} GB）",
-                        format!("{:.2}", image_size as f64 / 1024.0 / 1024.0 / 1024.0)
-                    )
-                ))
-            }
-            Err(e) => return Err(anyhow!("{}", tr!("查找数据分区失败: {}", e))),
+    let image_filename = std::path::Path::new(&spec.image_path)
+        .file_name()
+        .map(|s| s.to_string_lossy().to_string())
+        .ok_or_else(|| anyhow!("{}", tr!("无法取得镜像文件名: {}", spec.image_path)))?;
+
+    Ok(CliInstall {
+        spec,
+        advanced,
+        is_gho,
+        image_size,
+        image_filename,
+    })
+}
+
+/// 步骤 4：数据分区（暂存配置 + 镜像）。现有分区空间都不够时会从目标分区缩出临时分区。
+fn resolve_data_partition(cli: &CliInstall) -> Result<String> {
+    if let Some(p) = &cli.spec.data_partition {
+        return Ok(p.clone());
+    }
+    match DiskManager::find_suitable_data_partition(&cli.spec.target_partition, cli.image_size) {
+        Ok(Some((p, _auto))) => Ok(p),
+        Ok(None) => Err(anyhow!(
+            "{}",
+            tr!(
+                "未找到空间足够的数据分区来暂存镜像（需约 {} GB）",
+                format!("{:.2}", cli.image_size as f64 / 1024.0 / 1024.0 / 1024.0)
+            )
+        )),
+        Err(e) => Err(anyhow!("{}", tr!("查找数据分区失败: {}", e))),
+    }
+}
+
+/// `--install ... --plan`：按与实际安装相同的参数生成执行计划，不拷贝、不写配置、不重启。
+pub fn plan_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result<ExecutionPlan> {
+    let cli = load_cli_install(config_path, advanced_path)?;
+    let spec = &cli.spec;
+    let advanced = &cli.advanced;
+    // 只在现有分区里找；找不到时计划里记一步「缩小分区」，不真的去缩
+    let existing = spec.data_partition.clone().or_else(|| {
+        DiskManager::find_existing_data_partition(&spec.target_partition, cli.image_size)
+    });
+    let data_partition = existing
+        .clone()
+        .unwrap_or_else(|| tr!("（新建临时分区）"));
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let staged_image = format!("{}\\{}", data_dir, cli.image_filename);
+    let input = InstallPlanInput {
+        target_partition: spec.target_partition.clone(),
+        data_dir: data_dir.clone(),
+        image_path: staged_image.clone(),
+        volume_index: spec.volume_index,
+        is_gho: cli.is_gho,
+        // 引导方式由 PE 启动后探测
+        uefi: None,
+        volume_label: if advanced.custom_volume_label {
+            advanced.volume_label.clone()
+        } else {
+            String::new()
+        },
+        // CLI 不导出驱动：数据目录里没有驱动时 PE 端会跳过导入
+        drivers: if spec.driver_action_mode == 2 {
+            DriverSource::Dir(std::path::Path::new(&data_dir).join("drivers"))
+        } else {
+            DriverSource::None
         },
+        driver_match_devices: advanced.driver_match_devices,
+        tweaks: advanced.tweak_options(),
+        remove_uwp_apps: advanced.remove_uwp_apps,
+        import_storage_controller_drivers: advanced.import_storage_controller_drivers,
+        unattended: spec.unattended,
+        custom_unattend: spec.custom_unattend_path.clone(),
+        custom_unattend_merge: spec.custom_unattend_merge,
+        custom_username: if advanced.custom_username {
+            advanced.username.clone()
+        } else {
+            String::new()
+        },
+        // PE 中安装完成后总是重启
+        auto_reboot: true,
+        via_pe: Some(PePreparation {
+            data_partition,
+            shrink_from: existing
+                .is_none()
+                .then(|| spec.target_partition.clone()),
+            pe_name: spec
+                .pe_display_name
+                .clone()
+                .unwrap_or_else(|| "LetRecovery PE".to_string()),
+            copy_image_from: (!same_file(&staged_image, &spec.image_path))
+                .then(|| spec.image_path.clone()),
+        }),
+        ..Default::default()
     };
+    let mut plan = plan_install(&input);
+    if !spec.auto_reboot {
+        plan.warn(tr!("未启用自动重启，需手动重启进入 PE 才会继续执行"));
+    }
+    Ok(plan)
+}
+
+/// 入口：`--install --config <json> [--advanced <json>]`。
+pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result<()> {
+    log::info!("[CLI INSTALL] ========== 命令行无人值守安装 ==========");
+
+    let cli = load_cli_install(config_path, advanced_path)?;
+    let data_partition = resolve_data_partition(&cli)?;
     log::info!("[CLI INSTALL] 数据分区: {}", data_partition);
+    let CliInstall {
+        spec,
+        advanced,
+        is_gho,
+        image_filename,
+        ..
+    } = cli;
 
-    // 5) 把镜像放进数据目录（InstallConfig.image_path 存相对文件名）
+    // 5) 把镜像放进数据目录
     let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let staged_image = format!("{}\\{}", data_dir, image_filename);
     std::fs::create_dir_all(&data_dir)
         .with_context(|| tr!("创建数据目录失败: {}", data_dir))?;
-    let image_filename = std::path::Path::new(&spec.image_path)
-        .file_name()
-        .map(|s| s.to_string_lossy().to_string())
-        .ok_or_else(|| anyhow!("{}", tr!("无法取得镜像文件名: {}", spec.image_path)))?;
-    let staged_image = format!("{}\\{}", data_dir, image_filename);
     if same_file(&staged_image, &spec.image_path) {
         log::info!("[CLI INSTALL] 镜像已在数据目录，跳过拷贝");
     } else {
@@ -171,6 +278,7 @@ pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result
         disable_device_encryption: advanced.disable_device_encryption,
         remove_uwp_apps: advanced.remove_uwp_apps,
         import_storage_controller_drivers: advanced.import_storage_controller_drivers,
+        driver_match_devices: advanced.driver_match_devices,
         custom_username: if advanced.custom_username {
             advanced.username.clone()
         } else {
@@ -182,6 +290,7 @@ pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result
             String::new()
         },
         custom_unattend_path: spec.custom_unattend_path.clone(),
+        custom_unattend_merge: spec.custom_unattend_merge,
         win7_uefi_patch: advanced.win7_uefi_patch,
         win7_inject_usb3_driver: advanced.win7_inject_usb3_driver,
         win7_inject_nvme_driver: advanced.win7_inject_nvme_driver,
@@ -194,6 +303,8 @@ pub fn run_cli_install(config_path: &str, advanced_path: Option<&str>) -> Result
         xp_inject_usb3_driver: advanced.xp_inject_usb3_driver,
         xp_inject_nvme_driver: advanced.xp_inject_nvme_driver,
         run_diskpart_scripts: false,
+        unattend: advanced.unattend_customization(),
+        target_identity: Default::default(),
     };
 
     // 7) 写安装配置（含目标盘标记；自定义无人值守 XML 会被复制进数据目录）
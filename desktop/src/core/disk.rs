@@ -1,10 +1,12 @@
 use anyhow::Result;
 use std::path::Path;
 use crate::tr;
-use crate::utils::cmd::create_command;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 use crate::core::bitlocker::{BitLockerManager, VolumeStatus};
+use lr_core::diskpart_output;
+use lr_core::diskpart_script::{DiskpartScript, FileSystem, PartitionKind};
 
 #[cfg(windows)]
 use windows::{
@@ -363,9 +365,7 @@ impl DiskManager {
             "format.com".to_string()
         };
 
-        let output = create_command(&format_exe)
-            .args([partition, "/FS:NTFS", "/q", "/y"])
-            .output()?;
+        let output = command::run(&format_exe, [partition, "/FS:NTFS", "/q", "/y"])?;
 
         Ok(gbk_to_utf8(&output.stdout))
     }
@@ -376,21 +376,19 @@ impl DiskManager {
         new_letter: &str,
         size_mb: u64,
     ) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\nshrink desired={}\ncreate partition primary size={}\nformat fs=ntfs quick\nassign letter={}",
-            source_partition.chars().next().unwrap_or('C'),
-            size_mb,
-            size_mb,
-            new_letter.chars().next().unwrap_or('Y').to_ascii_lowercase()
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(source_partition.chars().next().unwrap_or('C'))
+            .shrink(size_mb, None)
+            .create_partition(PartitionKind::Primary, Some(size_mb), None)
+            .format(FileSystem::Ntfs, "", true)
+            .assign_letter(new_letter.chars().next().unwrap_or('Y'))
+            .build()?;
 
         let temp_dir = std::env::temp_dir();
         let script_path = temp_dir.join("dp_script.txt");
         std::fs::write(&script_path, &script_content)?;
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
         let _ = std::fs::remove_file(&script_path);
 
@@ -399,18 +397,16 @@ impl DiskManager {
 
     /// 删除指定分区
     pub fn delete_partition(partition_letter: &str) -> Result<String> {
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            partition_letter.chars().next().unwrap_or('Y')
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(partition_letter.chars().next().unwrap_or('Y'))
+            .delete_partition(true)
+            .build()?;
 
         let temp_dir = std::env::temp_dir();
         let script_path = temp_dir.join("dp_delete.txt");
         std::fs::write(&script_path, &script_content)?;
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
         let _ = std::fs::remove_file(&script_path);
 
@@ -602,10 +598,10 @@ impl DiskManager {
 
     /// 查询指定分区可缩小的最大空间（MB）
     pub fn query_shrink_max(letter: char) -> Result<u64> {
-        let script_content = format!(
-            "select volume {}\nshrink querymax",
-            letter
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(letter)
+            .shrink_querymax()
+            .build()?;
 
         let temp_dir = std::env::temp_dir();
         let script_path = temp_dir.join("lr_query_shrink.txt");
@@ -613,9 +609,7 @@ impl DiskManager {
 
         // 首先尝试使用内置 diskpart，如果失败则使用系统 diskpart
         let diskpart_path = get_diskpart_path();
-        let output = create_command(&diskpart_path)
-            .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-            .output()?;
+        let output = command::run(&diskpart_path, ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
         let output_text = gbk_to_utf8(&output.stdout);
         let error_text = gbk_to_utf8(&output.stderr);
@@ -631,9 +625,7 @@ impl DiskManager {
         let output_text = if output_text.trim().is_empty() || output.stdout.len() < 50 {
             log::warn!("[DISK] 内置 diskpart 输出异常，尝试使用系统 diskpart");
             
-            let sys_output = create_command("diskpart.exe")
-                .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-                .output()?;
+            let sys_output = command::run("diskpart.exe", ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
             
             let sys_output_text = gbk_to_utf8(&sys_output.stdout);
             log::info!("[DISK] 系统 diskpart stdout 长度: {} 字节", sys_output.stdout.len());
@@ -845,16 +837,24 @@ impl DiskManager {
 
         // 使用 diskpart 执行操作
         // 注意：shrink 之后的未分配空间会紧跟在当前卷之后
-        let script_content = format!(
-            "select volume {}\n\
-            shrink desired={}\n\
-            create partition primary\n\
-            format fs=ntfs quick label=\"LetRecovery\"\n\
-            assign letter={}",
-            source_letter,
-            actual_size_mb,
-            new_letter
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(source_letter)
+            .shrink(actual_size_mb, None)
+            .create_partition(PartitionKind::Primary, None, None)
+            .format(FileSystem::Ntfs, "LetRecovery", true)
+            .assign_letter(new_letter)
+            .build()?;
+
+        // 改动分区表前先备份（备份失败则不做任何改动）
+        let disk_number = lr_core::arc::volume_disk_extent(&format!("{}:", source_letter))
+            .map(|(disk, _)| disk)
+            .ok_or_else(|| anyhow::anyhow!("{}", tr!("无法定位分区 {}: 所在磁盘/偏移", source_letter)))?;
+        if let Some(dir) =
+            crate::core::quick_partition::backup_partition_table(disk_number, "shrink_create")
+                .map_err(|e| anyhow::anyhow!("{}", tr!("备份分区表失败，未做任何改动：{}", e)))?
+        {
+            log::info!("[DISK] 原分区表已备份到 {}", dir.display());
+        }
 
         let temp_dir = std::env::temp_dir();
         let script_path = temp_dir.join("lr_shrink_script.txt");
@@ -862,9 +862,7 @@ impl DiskManager {
 
         log::info!("[DISK] Diskpart 脚本内容:\n{}", script_content);
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
         let _ = std::fs::remove_file(&script_path);
 
@@ -876,13 +874,8 @@ impl DiskManager {
             log::error!("[DISK] Diskpart 错误: {}", error_text);
         }
 
-        // 检查输出是否包含错误信息
-        let output_lower = output_text.to_lowercase();
-        if output_lower.contains("error") || output_lower.contains("错误") 
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("无效") || output_lower.contains("invalid")
-            || output_lower.contains("不支持") || output_lower.contains("无法")
-            || output_lower.contains("拒绝") || output_lower.contains("denied") {
+        // 成败只看退出码（不同语言的系统提示文字不同）
+        if !diskpart_output::succeeded(&output) {
             anyhow::bail!("{}", tr!("Diskpart 执行失败: {}", output_text));
         }
 
@@ -947,18 +940,16 @@ impl DiskManager {
 
         log::info!("[DISK] 准备删除自动创建的分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(letter)
+            .delete_partition(true)
+            .build()?;
 
         let temp_dir = std::env::temp_dir();
         let script_path = temp_dir.join("lr_delete_script.txt");
         std::fs::write(&script_path, &script_content)?;
 
-        let output = create_command(&get_diskpart_path())
-            .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
         let _ = std::fs::remove_file(&script_path);
 
@@ -968,20 +959,8 @@ impl DiskManager {
         Ok(())
     }
 
-    /// 查找可用的数据分区（排除指定分区、光驱，检查空间）
-    /// 
-    /// # Arguments
-    /// * `exclude_partition` - 要排除的分区（通常是目标安装分区）
-    /// * `required_size_bytes` - 需要的最小空间（字节）
-    /// 
-    /// # Returns
-    /// * `Ok(Some((partition, is_auto_created)))` - 找到可用分区，返回分区盘符和是否是自动创建的
-    /// * `Ok(None)` - 没有找到可用分区，且无法自动创建
-    /// * `Err` - 发生错误
-    pub fn find_suitable_data_partition(
-        exclude_partition: &str,
-        required_size_bytes: u64,
-    ) -> Result<Option<(String, bool)>> {
+    /// 只在现有分区里找空间足够的数据分区（不创建分区，执行计划也用它）
+    pub fn find_existing_data_partition(exclude_partition: &str, required_size_bytes: u64) -> Option<String> {
         let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
         
         log::info!("[DISK] 查找数据分区，排除: {}, 需要空间: {} bytes ({:.2} GB)", 
@@ -1049,8 +1028,30 @@ impl DiskManager {
 
             let selected = candidates[0].0;
             log::info!("[DISK] 选择数据分区: {}:", selected);
-            return Ok(Some((format!("{}:", selected), false)));
+            Some(format!("{}:", selected))
+        } else {
+            None
+        }
+    }
+
+    /// 查找可用的数据分区（排除指定分区、光驱，检查空间）
+    /// 
+    /// # Arguments
+    /// * `exclude_partition` - 要排除的分区（通常是目标安装分区）
+    /// * `required_size_bytes` - 需要的最小空间（字节）
+    /// 
+    /// # Returns
+    /// * `Ok(Some((partition, is_auto_created)))` - 找到可用分区，返回分区盘符和是否是自动创建的
+    /// * `Ok(None)` - 没有找到可用分区，且无法自动创建
+    /// * `Err` - 发生错误
+    pub fn find_suitable_data_partition(
+        exclude_partition: &str,
+        required_size_bytes: u64,
+    ) -> Result<Option<(String, bool)>> {
+        if let Some(partition) = Self::find_existing_data_partition(exclude_partition, required_size_bytes) {
+            return Ok(Some((partition, false)));
         }
+        let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
 
         // ========================================================================
         // 没有找到满足条件的现有分区，尝试从目标安装分区创建新分区
@@ -40,6 +40,8 @@ pub struct ImageInfo {
     pub major_version: Option<u16>,
     /// Windows 次版本号 (如 Win7 为 1，对应版本 6.1)
     pub minor_version: Option<u16>,
+    /// 处理器架构（x86 / amd64 / arm64 ...，与 unattend.xml 的 processorArchitecture 一致）
+    pub architecture: Option<String>,
     /// 镜像类型 (标准安装/整盘备份/PE等)
     pub image_type: lr_core::image_meta::WimImageType,
     /// 是否已验证可安装
@@ -539,6 +541,7 @@ impl Dism {
                             installation_type: img.installation_type,
                             major_version: img.major_version,
                             minor_version: img.minor_version,
+                            architecture: img.architecture,
                             image_type: img.image_type,
                             verified_installable: img.verified_installable,
                         }).collect());
@@ -787,6 +790,11 @@ impl Dism {
                         .or_else(|| Self::extract_xml_tag(image_block, "MINOR"))
                         .and_then(|s| s.parse::<u16>().ok());
 
+                    let architecture = Self::extract_xml_tag(image_block, "ARCH")
+                        .and_then(|s| s.parse::<u32>().ok())
+                        .and_then(lr_core::image_meta::wim_arch_name)
+                        .map(str::to_string);
+
                     // 确定镜像类型
                     let image_type = Self::determine_image_type_from_info(
                         &name, &installation_type, major_version, size_bytes
@@ -800,6 +808,7 @@ impl Dism {
                             installation_type,
                             major_version,
                             minor_version,
+                            architecture,
                             image_type,
                             verified_installable: false,
                         });
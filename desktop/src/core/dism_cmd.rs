@@ -13,13 +13,12 @@
 
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Child, Stdio};
 use std::sync::mpsc::Sender;
 
 use anyhow::{bail, Context, Result};
 
 use crate::tr;
-use crate::utils::command::new_command;
+use crate::utils::command::{self, Process};
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_exe_dir;
 
@@ -129,12 +128,8 @@ impl DismCmd {
 
     /// 验证 DISM 是否可用
     fn verify_dism_available(dism_path: &Path) -> bool {
-        new_command(dism_path)
-            .arg("/?")
-            .stdout(Stdio::null())
-            .stderr(Stdio::null())
-            .status()
-            .map(|s| s.success())
+        command::run(dism_path, ["/?"])
+            .map(|o| o.status.success())
             .unwrap_or(false)
     }
 
@@ -613,10 +608,7 @@ impl DismCmd {
             args.join(" ")
         );
 
-        let mut cmd = new_command(&self.dism_path);
-        cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
-
-        let output = cmd.output().context(tr!("执行 DISM 命令失败"))?;
+        let output = command::run(&self.dism_path, args).context(tr!("执行 DISM 命令失败"))?;
 
         let stdout = if output.stdout.is_empty() {
             String::new()
@@ -669,14 +661,12 @@ impl DismCmd {
             args.join(" ")
         );
 
-        let mut cmd = new_command(&self.dism_path);
-        cmd.args(args).stdout(Stdio::piped()).stderr(Stdio::piped());
-
         // 启动进程
-        let mut child = cmd.spawn().context(tr!("启动 DISM 进程失败"))?;
+        let mut child =
+            command::spawn(&self.dism_path, args).context(tr!("启动 DISM 进程失败"))?;
 
         // 读取并处理输出
-        let result = self.process_output(&mut child, &progress_tx, operation_name);
+        let result = self.process_output(child.as_mut(), &progress_tx, operation_name);
 
         // 等待进程结束
         let status = child.wait().context(tr!("等待 DISM 进程失败"))?;
@@ -698,12 +688,12 @@ impl DismCmd {
     /// 处理进程输出流
     fn process_output(
         &self,
-        child: &mut Child,
+        child: &mut dyn Process,
         progress_tx: &Option<Sender<DismCmdProgress>>,
         operation_name: &str,
     ) -> Result<()> {
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
+        let stdout = child.take_stdout();
+        let stderr = child.take_stderr();
 
         let mut error_output = String::new();
         let mut last_progress: u8 = 0;
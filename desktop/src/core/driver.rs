@@ -6,6 +6,8 @@
 use std::path::Path;
 
 use anyhow::Result;
+use lr_core::driver_match::offline_platform;
+use lr_core::driver_report::{log_dir, report_from_driver_store, DriverImportReport, ImportOutcome};
 
 use crate::tr;
 
@@ -53,8 +55,11 @@ pub fn import_drivers_offline_dism_first(
             log::info!(
                 "[DriverManager] dism.exe 离线驱动导入成功"
             );
-            // DISM 成功时假设所有驱动都导入成功
-            Ok((inf_count.max(1), 0))
+            // DISM 不给出逐个 INF 的结果，对照目标驱动存储核实；报告写不出时沿用旧假设
+            match write_offline_import_report(offline_root, source_dir) {
+                Some(report) => Ok((report.succeeded(), report.failed())),
+                None => Ok((inf_count.max(1), 0)),
+            }
         }
         Err(e) => {
             log::warn!(
@@ -71,15 +76,49 @@ pub fn import_drivers_offline_dism_first(
 ///
 /// # 参数
 /// - `offline_root`: 离线系统根目录 (如 "D:\\")
-/// - `driver_path`: 驱动目录
+/// - `driver_path`: 驱动目录或驱动压缩包（先校验再导入）
 ///
 /// # 返回
 /// - (成功数, 失败数)
 pub fn import_drivers_offline(offline_root: &str, driver_path: &str) -> Result<(usize, usize)> {
     let manager = DriverManager::new()?;
+    let source =
+        lr_core::driver_archive::prepare_import_dir(Path::new(driver_path), Path::new(offline_root))?;
     import_drivers_offline_dism_first(
         &manager,
         Path::new(offline_root),
-        Path::new(driver_path),
+        &source,
     )
 }
+
+/// DISM 按目录注入后，逐个核实 `injected_dir` 里的驱动是否进了离线系统的驱动存储，
+/// 附上本机匹配到的设备，写入目标系统 `Windows\Logs\LetRecovery`（新系统首次启动后展示）。
+///
+/// 找不到 INF 时返回 `None`。
+pub fn write_offline_import_report(
+    offline_root: &Path,
+    injected_dir: &Path,
+) -> Option<DriverImportReport> {
+    let infs = DriverManager::find_inf_files(injected_dir).ok()?;
+    if infs.is_empty() {
+        return None;
+    }
+    let mut report = report_from_driver_store(&infs, injected_dir, offline_root);
+    let devices = lr_core::driver::list_present_devices().unwrap_or_default();
+    let platform = offline_platform(offline_root);
+    report.annotate_devices(&devices, platform.as_ref());
+
+    for r in report
+        .results
+        .iter()
+        .filter(|r| r.outcome == ImportOutcome::Failed)
+    {
+        log::warn!("[DriverReport] 驱动未导入: {} - {}", r.inf, r.message);
+    }
+    let dir = log_dir(offline_root);
+    match report.save(&dir) {
+        Ok(path) => log::info!("[DriverReport] 驱动导入报告已写入: {}", path.display()),
+        Err(e) => log::warn!("[DriverReport] 写入驱动导入报告失败 {:?}: {}", dir, e),
+    }
+    Some(report)
+}
@@ -11,7 +11,6 @@
 use anyhow::{Context, Result};
 use std::io::{BufRead, BufReader, Read};
 use std::path::Path;
-use std::process::{Child, Stdio};
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::mpsc::Sender;
 use std::sync::Arc;
@@ -19,7 +18,7 @@ use std::time::Duration;
 
 use crate::core::dism::DismProgress;
 use crate::tr;
-use crate::utils::cmd::create_command;
+use crate::utils::command::{self, Process};
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -288,14 +287,11 @@ impl Ghost {
 
         log::info!("[GHOST] 执行命令: {} {} -sure -fx -batch", self.ghost_path, clone_param);
 
-        let mut child = create_command(&self.ghost_path)
-            .args([&clone_param, "-sure", "-fx", "-batch"])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context(tr!("无法启动 Ghost 进程"))?;
+        let mut child =
+            command::spawn(&self.ghost_path, [clone_param.as_str(), "-sure", "-fx", "-batch"])
+                .context(tr!("无法启动 Ghost 进程"))?;
 
-        let result = self.monitor_ghost_process(&mut child, progress_tx, estimated_size);
+        let result = self.monitor_ghost_process(child.as_mut(), progress_tx, estimated_size);
 
         let _ = child.kill();
         let _ = child.wait();
@@ -351,14 +347,14 @@ impl Ghost {
     /// 监控 Ghost 进程并报告进度
     fn monitor_ghost_process(
         &self,
-        child: &mut Child,
+        child: &mut dyn Process,
         progress_tx: Option<Sender<DismProgress>>,
         estimated_size: u64,
     ) -> Result<()> {
         let cancel_flag = Arc::clone(&self.cancel_flag);
         
-        let stdout = child.stdout.take();
-        let stderr = child.stderr.take();
+        let stdout = child.take_stdout();
+        let stderr = child.take_stderr();
 
         let stdout_handle = if let Some(stdout) = stdout {
             let cancel = Arc::clone(&cancel_flag);
@@ -543,14 +539,14 @@ impl Ghost {
             source_partition, gho_file
         );
 
-        let mut child = create_command(&self.ghost_path)
-            .args([&clone_param, "-sure", "-fx", "-batch", &format!("-z{}", compression)])
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context(tr!("无法启动 Ghost 进程"))?;
+        let level = format!("-z{}", compression);
+        let mut child = command::spawn(
+            &self.ghost_path,
+            [clone_param.as_str(), "-sure", "-fx", "-batch", level.as_str()],
+        )
+        .context(tr!("无法启动 Ghost 进程"))?;
 
-        let result = self.monitor_ghost_process(&mut child, progress_tx, 0);
+        let result = self.monitor_ghost_process(child.as_mut(), progress_tx, 0);
 
         let _ = child.kill();
         let _ = child.wait();
@@ -1223,7 +1223,7 @@ impl HardwareInfo {
         get_bitlocker_status_wmi(&system_drive)
     }
 
-    fn get_system_serial_number() -> String {
+    pub(crate) fn get_system_serial_number() -> String {
         // 首先尝试从注册表获取
         if let Some(serial) = read_registry_string(HKEY_LOCAL_MACHINE, r"HARDWARE\DESCRIPTION\System\BIOS", "SystemSerialNumber") {
             if !serial.is_empty() && !is_placeholder(&serial) {
@@ -1311,94 +1311,36 @@ fn check_cpu_ai_support(cpu_name: &str) -> bool {
     false
 }
 
-/// 使用纯 WinAPI 获取所有物理磁盘的分区样式（GPT/MBR/RAW）
+/// 获取所有物理磁盘的分区样式（GPT/MBR/RAW）
 ///
-/// 通过 IOCTL_DISK_GET_DRIVE_LAYOUT_EX 获取磁盘分区布局信息，
-/// 从中提取分区样式字段。
+/// 直接读取磁盘上的 MBR/GPT（见 `lr_core::partition_table`），分区表损坏的磁盘标记为 "UNKNOWN"。
 ///
 /// # Returns
 /// HashMap<磁盘编号, 分区样式字符串>，分区样式为 "GPT"、"MBR" 或 "RAW"
 fn get_disk_partition_styles() -> HashMap<u32, String> {
-    use windows::Win32::System::Ioctl::{IOCTL_DISK_GET_DRIVE_LAYOUT_EX, PARTITION_STYLE_GPT, PARTITION_STYLE_MBR};
-
-    // PARTITION_STYLE_RAW = 2 (Windows SDK winioctl.h)
-    const PARTITION_STYLE_RAW_VALUE: u32 = 2;
-
-    /// DRIVE_LAYOUT_INFORMATION_EX 结构体头部
-    /// 我们只需要读取 partition_style 字段，不需要完整的分区信息
-    #[repr(C)]
-    #[allow(non_snake_case, dead_code)]
-    struct DriveLayoutInformationExHeader {
-        partition_style: u32,
-        partition_count: u32,
-    }
+    use lr_core::diskpart_script::TableStyle;
+    use lr_core::partition_table;
 
     let mut styles = HashMap::new();
 
     // 遍历物理磁盘 0-15（与 get_disk_info 保持一致）
     for disk_index in 0u32..16 {
-        let partition_style = unsafe {
-            // 构造物理磁盘路径
-            let disk_path = format!("\\\\.\\PhysicalDrive{}", disk_index);
-            let wide_path: Vec<u16> = disk_path.encode_utf16().chain(std::iter::once(0)).collect();
-
-            // 打开物理磁盘设备
-            // 注意：不需要读写权限（传入0），只需要发送 IOCTL 查询
-            let handle = match CreateFileW(
-                PCWSTR(wide_path.as_ptr()),
-                0,
-                FILE_SHARE_READ | FILE_SHARE_WRITE,
-                None,
-                OPEN_EXISTING,
-                FILE_FLAGS_AND_ATTRIBUTES(0),
-                HANDLE::default(),
-            ) {
-                Ok(h) if h != INVALID_HANDLE_VALUE => h,
-                _ => continue, // 磁盘不存在或无法访问，跳过
-            };
-
-            // 分配缓冲区用于接收 DRIVE_LAYOUT_INFORMATION_EX
-            // 该结构体大小可变，取决于分区数量，但我们只需要头部8字节
-            // 使用 4096 字节缓冲区以确保足够容纳最多 128 个分区的信息
-            let mut buffer = vec![0u8; 4096];
-            let mut bytes_returned: u32 = 0;
-
-            let result = DeviceIoControl(
-                handle,
-                IOCTL_DISK_GET_DRIVE_LAYOUT_EX,
-                None,
-                0,
-                Some(buffer.as_mut_ptr() as *mut std::ffi::c_void),
-                buffer.len() as u32,
-                Some(&mut bytes_returned),
-                None,
-            );
-
-            let _ = CloseHandle(handle);
-
-            // 检查 IOCTL 是否成功，且返回了足够的数据（至少8字节用于头部）
-            if result.is_ok() && bytes_returned >= size_of::<DriveLayoutInformationExHeader>() as u32 {
-                let layout_header = &*(buffer.as_ptr() as *const DriveLayoutInformationExHeader);
-
-                // 将分区样式常量转换为字符串
-                if layout_header.partition_style == PARTITION_STYLE_GPT.0 as u32 {
-                    Some("GPT".to_string())
-                } else if layout_header.partition_style == PARTITION_STYLE_MBR.0 as u32 {
-                    Some("MBR".to_string())
-                } else if layout_header.partition_style == PARTITION_STYLE_RAW_VALUE {
-                    Some("RAW".to_string())
-                } else {
-                    // 未知的分区样式值
-                    Some(format!("UNKNOWN({})", layout_header.partition_style))
-                }
-            } else {
-                None
+        // 磁盘不存在或无法访问，跳过
+        let Ok(layout) = partition_table::read_physical_drive(disk_index) else {
+            continue;
+        };
+        let style = match layout.table {
+            Ok(Some(table)) => match table.style {
+                TableStyle::Gpt => "GPT",
+                TableStyle::Mbr => "MBR",
+            },
+            Ok(None) => "RAW",
+            Err(e) => {
+                log::warn!("读取磁盘 {} 的分区表失败: {}", disk_index, e);
+                "UNKNOWN"
             }
         };
-
-        if let Some(style) = partition_style {
-            styles.insert(disk_index, style);
-        }
+        styles.insert(disk_index, style.to_string());
     }
 
     styles
@@ -28,6 +28,8 @@ pub struct InstallConfig {
     pub restore_drivers: bool,
     /// 驱动操作模式: 0=无, 1=仅保存, 2=自动导入
     pub driver_action_mode: u8,
+    /// 仅导入与本机硬件匹配的驱动（按硬件 ID 排名为每个设备选一个 INF）
+    pub driver_match_devices: bool,
     /// 立即重启
     pub auto_reboot: bool,
     /// 原系统引导GUID（用于删除旧引导项）
@@ -69,6 +71,8 @@ pub struct InstallConfig {
     /// 自定义无人值守文件：UI 选择时为源文件绝对路径；
     /// 经 write_install_config 复制到数据目录后，写入 INI 的是相对文件名。
     pub custom_unattend_path: String,
+    /// 自定义无人值守文件与内置生成的应答合并（false=原样使用用户文件）
+    pub custom_unattend_merge: bool,
     
     // Win7 专用选项
     /// Win7 UEFI 补丁（使用 UefiSeven）
@@ -96,6 +100,14 @@ pub struct InstallConfig {
 
     /// 是否在释放镜像前运行 diskpart 脚本（程序目录\diskpart\ 下所有脚本）。
     pub run_diskpart_scripts: bool,
+
+    /// 内置无人值守的定制项（区域/时区/计算机名/密码/密钥/OOBE 页），写入 INI 的 `[Unattend]` 节。
+    /// 计算机名模板在本机展开后再写入（PE 端看到的是最终名称）。
+    pub unattend: lr_core::unattend::UnattendCustomization,
+
+    /// 目标分区的磁盘/分区标识，写入 INI 的 `[Target]` 节，PE 端据此找回目标分区（盘符在 PE 里会变）。
+    /// 由 write_install_config 按 `target_partition` 读取，调用方无需填写。
+    pub target_identity: lr_core::target_identity::TargetIdentity,
 }
 
 impl InstallConfig {
@@ -240,8 +252,17 @@ impl ConfigFileManager {
         std::fs::write(&marker_path, "LetRecovery Install Marker")
             .context(tr!("写入安装标记文件失败"))?;
 
-        // 处理自定义无人值守文件：把用户选择的 XML 复制到数据目录，INI 里只存相对文件名
+        // 记下目标分区的磁盘/分区标识，PE 端靠它而不是盘符认目标
         let mut config = config.clone();
+        match lr_core::target_identity::identify_volume(target_partition) {
+            Some(identity) => {
+                log::info!("[CONFIG] 目标分区 {} 标识: {}", target_partition, identity);
+                config.target_identity = identity;
+            }
+            None => log::warn!("[CONFIG] 无法读取目标分区 {} 的磁盘/分区标识，PE 端将按标记文件定位", target_partition),
+        }
+
+        // 处理自定义无人值守文件：把用户选择的 XML 复制到数据目录，INI 里只存相对文件名
         if !config.custom_unattend_path.is_empty() {
             const CUSTOM_UNATTEND_NAME: &str = "custom_unattend.xml";
             let dst = format!("{}\\{}", data_dir, CUSTOM_UNATTEND_NAME);
@@ -275,6 +296,18 @@ impl ConfigFileManager {
         log::info!("[CONFIG] 安装配置已写入: {}", config_path);
         log::info!("[CONFIG] 安装标记已写入: {}", marker_path);
 
+        // 硬件清单：本机在位设备的硬件 ID 等，供 PE 端按硬件挑驱动并报告缺驱动的设备（失败不影响安装）
+        match crate::core::driver::DriverManager::new().and_then(|m| m.enumerate_present_devices()) {
+            Ok(devices) => {
+                let count = devices.len();
+                match lr_core::hw_manifest::HardwareManifest::new(devices).save(Path::new(&data_dir)) {
+                    Ok(path) => log::info!("[CONFIG] 硬件清单已写入: {} ({} 个设备)", path.display(), count),
+                    Err(e) => log::warn!("[CONFIG] 写入硬件清单失败: {}", e),
+                }
+            }
+            Err(e) => log::warn!("[CONFIG] 枚举设备失败，跳过硬件清单: {}", e),
+        }
+
         Ok(())
     }
 
@@ -408,11 +441,12 @@ impl ConfigFileManager {
 
     /// 序列化安装配置为INI格式
     fn serialize_install_config(config: &InstallConfig) -> String {
-        format!(
+        let mut ini = format!(
             r#"[Install]
 Unattended={}
 RestoreDrivers={}
 DriverActionMode={}
+DriverMatchDevices={}
 AutoReboot={}
 OriginalGUID={}
 VolumeIndex={}
@@ -438,6 +472,7 @@ ImportStorageControllerDrivers={}
 CustomUsername={}
 VolumeLabel={}
 CustomUnattendFile={}
+CustomUnattendMerge={}
 
 [Win7]
 Win7UefiPatch={}
@@ -453,6 +488,7 @@ XpInjectNvmeDriver={}
             config.unattended,
             config.restore_drivers,
             config.driver_action_mode,
+            config.driver_match_devices,
             config.auto_reboot,
             config.original_guid,
             config.volume_index,
@@ -476,6 +512,7 @@ XpInjectNvmeDriver={}
             config.custom_username,
             config.volume_label,
             config.custom_unattend_path,
+            config.custom_unattend_merge,
             config.win7_uefi_patch,
             config.win7_inject_usb3_driver,
             config.win7_inject_nvme_driver,
@@ -483,7 +520,14 @@ XpInjectNvmeDriver={}
             config.win7_fix_storage_bsod,
             config.xp_inject_usb3_driver,
             config.xp_inject_nvme_driver,
-        )
+        );
+        ini.push('\n');
+        ini.push_str(&config.unattend.to_ini());
+        if config.target_identity.is_known() {
+            ini.push('\n');
+            ini.push_str(&config.target_identity.to_ini());
+        }
+        ini
     }
 
     /// 序列化备份配置为INI格式
@@ -530,6 +574,7 @@ Language={}
                     "Unattended" => config.unattended = value.parse().unwrap_or(false),
                     "RestoreDrivers" => config.restore_drivers = value.parse().unwrap_or(false),
                     "DriverActionMode" => config.driver_action_mode = value.parse().unwrap_or(0),
+                    "DriverMatchDevices" => config.driver_match_devices = value.parse().unwrap_or(false),
                     "AutoReboot" => config.auto_reboot = value.parse().unwrap_or(false),
                     "OriginalGUID" => config.original_guid = value.to_string(),
                     "VolumeIndex" => config.volume_index = value.parse().unwrap_or(1),
@@ -552,6 +597,7 @@ Language={}
                     "CustomUsername" => config.custom_username = value.to_string(),
                     "VolumeLabel" => config.volume_label = value.to_string(),
                     "CustomUnattendFile" => config.custom_unattend_path = value.to_string(),
+                    "CustomUnattendMerge" => config.custom_unattend_merge = value.parse().unwrap_or(false),
                     "Win7UefiPatch" => config.win7_uefi_patch = value.parse().unwrap_or(false),
                     "Win7InjectUsb3Driver" => config.win7_inject_usb3_driver = value.parse().unwrap_or(false),
                     "Win7InjectNvmeDriver" => config.win7_inject_nvme_driver = value.parse().unwrap_or(false),
@@ -559,6 +605,8 @@ Language={}
                     "Win7FixStorageBsod" => config.win7_fix_storage_bsod = value.parse().unwrap_or(false),
                     "XpInjectUsb3Driver" => config.xp_inject_usb3_driver = value.parse().unwrap_or(false),
                     "XpInjectNvmeDriver" => config.xp_inject_nvme_driver = value.parse().unwrap_or(false),
+                    k if config.unattend.set_ini_key(k, value) => {}
+                    k if config.target_identity.set_ini_key(k, value) => {}
                     _ => {}
                 }
             }
@@ -599,51 +647,56 @@ Language={}
     }
 }
 
-/// unattend.xml 语法校验，基于 roxmltree 做完整 XML 解析。
-///
-/// 相比手写扫描器，roxmltree 能完整检查标签配对、嵌套、属性引号、实体、
-/// 命名空间等，并在出错时给出行列号，便于用户定位。
-/// 返回 Ok(()) 表示语法合法；Err(msg) 给出可展示给用户的错误原因。
-pub fn validate_unattend_xml(xml: &str) -> Result<(), String> {
+/// unattend.xml 校验：roxmltree 完整解析语法（标签配对、引号、实体等，出错给行列号），
+/// 再按 [`lr_core::unattend_check::check`] 做语义检查——组件架构与所选镜像 `image_arch`
+/// 是否一致、组件/设置是否放对了配置阶段、重复组件、缺少 publicKeyToken。
+/// 有错误级问题返回 Err(msg)(可展示,禁用安装);否则 Ok(提示列表),提示不阻止安装。
+pub fn validate_unattend_xml(xml: &str, image_arch: Option<&str>) -> Result<Vec<String>, String> {
+    use lr_core::unattend_check::{self, UnattendSeverity};
+
     let s = xml.trim_start_matches('\u{feff}');
     if s.trim().is_empty() {
         return Err(tr!("文件内容为空"));
     }
 
-    // 完整 XML 解析：标签未闭合/未配对、引号未闭合、非法嵌套等都会在此报错。
-    let doc = roxmltree::Document::parse(s).map_err(|e| tr!("XML 语法错误：{}", e))?;
-
-    // 根元素必须是 <unattend>
-    let root = doc.root_element();
-    let root_name = root.tag_name().name();
-    if root_name != "unattend" {
-        return Err(tr!(
-            "不是有效的无人值守文件（根元素应为 <unattend>，实际为 <{}>）",
-            if root_name.is_empty() { "?" } else { root_name }
-        ));
+    let (errors, warnings): (Vec<_>, Vec<_>) = unattend_check::check(s, image_arch)
+        .into_iter()
+        .partition(|i| i.severity == UnattendSeverity::Error);
+    if !errors.is_empty() {
+        let msgs: Vec<String> = errors.iter().map(|i| i.to_string()).collect();
+        return Err(msgs.join("；"));
     }
-
-    Ok(())
+    Ok(warnings.iter().map(|i| i.to_string()).collect())
 }
 
-/// XP/2003 的 winnt.sif 应答轻校验。
+/// XP/2003 的 winnt.sif 应答校验。
 ///
-/// winnt.sif 是 INI 风格(不是 XML),只做基本健全性检查:非空、且至少含一个
-/// XP 应答常见节(`[Unattended]` / `[Data]` / `[GuiUnattended]` / `[UserData]`)。
-/// 返回 Ok(()) 表示看起来是有效的 winnt.sif；Err(msg) 给出可展示的原因。
-pub fn validate_winnt_sif(content: &str) -> Result<(), String> {
-    let s = content.trim_start_matches('\u{feff}');
-    if s.trim().is_empty() {
+/// winnt.sif 是 INI 风格(不是 XML),按原始字节读(中文应答多为 GBK)。先做健全性检查:非空、
+/// 且至少含一个 XP 应答常见节(`[Unattended]` / `[Data]` / `[GuiUnattended]` / `[UserData]`);
+/// 再按 [`lr_core::winnt_sif::validate`] 逐键检查(拼错的键、非法取值、产品密钥格式、
+/// 与本地源硬盘安装冲突的取值)。
+/// 有错误级问题返回 Err(msg)(可展示,禁用安装);否则 Ok(提示列表),提示不阻止安装。
+pub fn validate_winnt_sif(raw: &[u8]) -> Result<Vec<String>, String> {
+    use lr_core::winnt_sif::{self, SifSeverity};
+
+    let doc = lr_core::sif::SifDocument::parse_bytes(raw);
+    if doc.to_text().trim().is_empty() {
         return Err(tr!("文件内容为空"));
     }
-    let lower = s.to_ascii_lowercase();
-    let has_section = ["[unattended]", "[data]", "[guiunattended]", "[userdata]"]
+    let has_section = ["Unattended", "Data", "GuiUnattended", "UserData"]
         .iter()
-        .any(|sec| lower.contains(sec));
+        .any(|sec| doc.has_section(sec));
     if !has_section {
         return Err(
             tr!("不像有效的 winnt.sif(缺少 [Unattended]/[Data]/[GuiUnattended] 等节)。XP/2003 应答文件为 INI 格式的 winnt.sif,不是 XML。")
         );
     }
-    Ok(())
+    let (errors, warnings): (Vec<_>, Vec<_>) = winnt_sif::validate(&doc)
+        .into_iter()
+        .partition(|i| i.severity == SifSeverity::Error);
+    if !errors.is_empty() {
+        let msgs: Vec<String> = errors.iter().map(|i| i.to_string()).collect();
+        return Err(msgs.join("；"));
+    }
+    Ok(warnings.iter().map(|i| i.to_string()).collect())
 }
@@ -695,18 +695,35 @@ pub fn uninstall_nvidia_drivers_offline(target_partition: &str) -> Result<Uninst
     Ok(result)
 }
 
-/// 检查 INF 文件是否为英伟达驱动
+/// 检查 INF 文件是否为英伟达驱动：Provider / 厂商 / 型号描述含 NVIDIA 品牌，
+/// 型号硬件 ID 为 `VEN_10DE`，或安装的服务是 `nvlddmkm`。
 fn is_nvidia_inf_file(path: &Path) -> bool {
-    if let Ok(content) = std::fs::read_to_string(path) {
-        let content_lower = content.to_lowercase();
-        return content_lower.contains("nvidia")
-            || content_lower.contains("ven_10de")
-            || content_lower.contains("nvlddmkm")
-            || content_lower.contains("nvdisplay")
-            || content_lower.contains("geforce")
-            || content_lower.contains("quadro");
+    let Ok(raw) = std::fs::read(path) else {
+        return false;
+    };
+    let inf = lr_core::inf::InfFile::parse_bytes(&raw);
+    let is_brand = |s: &str| {
+        let s = s.to_lowercase();
+        s.contains("nvidia") || s.contains("geforce") || s.contains("quadro")
+    };
+    if inf.version().provider.as_deref().is_some_and(is_brand)
+        || inf.manufacturers().iter().any(|m| is_brand(&m.name))
+    {
+        return true;
     }
-    false
+    inf.models(None).iter().any(|m| {
+        is_brand(&m.description)
+            || std::iter::once(&m.hardware_id)
+                .chain(&m.compatible_ids)
+                .any(|id| id.to_ascii_uppercase().contains("VEN_10DE"))
+            || inf
+                .ddinstall(&m.install_section, Some(lr_core::inf::InfArch::Amd64))
+                .is_some_and(|dd| {
+                    dd.services
+                        .iter()
+                        .any(|s| s.name.eq_ignore_ascii_case("nvlddmkm"))
+                })
+    })
 }
 
 /// 递归删除目录
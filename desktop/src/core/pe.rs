@@ -2,6 +2,7 @@ use anyhow::Result;
 use std::path::Path;
 use crate::tr;
 use crate::utils::cmd::create_command;
+use crate::utils::command;
 
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::{get_bin_dir, get_exe_dir};
@@ -51,9 +52,7 @@ impl PeManager {
                 .unwrap_or(false)
             || {
                 // 通过 bcdedit 检查
-                let output = create_command("bcdedit")
-                    .args(["/enum", "{current}"])
-                    .output();
+                let output = command::run("bcdedit", ["/enum", "{current}"]);
                 if let Ok(out) = output {
                     let stdout = gbk_to_utf8(&out.stdout);
                     stdout.contains("winload.efi")
@@ -413,9 +412,7 @@ impl PeManager {
 
         // 1. 创建ramdisk设备
         log::info!("[PE] 创建 ramdisk 设备");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/create", "/d", &format!("{} RAM", display_name), "/device"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/create", "/d", &format!("{} RAM", display_name), "/device"])?;
         
         let stdout = gbk_to_utf8(&output.stdout);
         log::info!("[PE] bcdedit output: {}", stdout);
@@ -429,15 +426,13 @@ impl PeManager {
         ];
 
         for cmd in &cmds {
-            let output = create_command(&self.bcdedit_path).args(cmd).output()?;
+            let output = command::run(&self.bcdedit_path, cmd)?;
             log::info!("[PE] bcdedit {:?}: {}", cmd, gbk_to_utf8(&output.stdout));
         }
 
         // 2. 创建osloader
         log::info!("[PE] 创建 osloader");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/create", "/d", display_name, "/application", "osloader"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/create", "/d", display_name, "/application", "osloader"])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         log::info!("[PE] bcdedit output: {}", stdout);
@@ -464,7 +459,7 @@ impl PeManager {
         ];
 
         for cmd in &cmds {
-            let output = create_command(&self.bcdedit_path).args(cmd).output()?;
+            let output = command::run(&self.bcdedit_path, cmd)?;
             let out_str = gbk_to_utf8(&output.stdout);
             let err_str = gbk_to_utf8(&output.stderr);
             log::info!("[PE] bcdedit {:?}: {} {}", cmd, out_str, err_str);
@@ -472,15 +467,11 @@ impl PeManager {
 
         // 3. 添加到启动菜单
         log::info!("[PE] 添加到启动菜单");
-        let output = create_command(&self.bcdedit_path)
-            .args(["/displayorder", &loader_guid, "/addfirst"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/displayorder", &loader_guid, "/addfirst"])?;
         log::info!("[PE] displayorder: {}", gbk_to_utf8(&output.stdout));
 
         // 4. 设置超时
-        let output = create_command(&self.bcdedit_path)
-            .args(["/timeout", "5"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/timeout", "5"])?;
         log::info!("[PE] timeout: {}", gbk_to_utf8(&output.stdout));
 
         // 5. 保存GUID用于清理
@@ -500,9 +491,7 @@ impl PeManager {
                 let loader_guid = lines[1];
                 log::info!("[PE] 设置下次启动: {}", loader_guid);
                 
-                let output = create_command(&self.bcdedit_path)
-                    .args(["/bootsequence", loader_guid])
-                    .output()?;
+                let output = command::run(&self.bcdedit_path, ["/bootsequence", loader_guid])?;
                 log::info!("[PE] bootsequence: {}", gbk_to_utf8(&output.stdout));
             }
         }
@@ -516,9 +505,7 @@ impl PeManager {
             for guid in content.lines() {
                 if !guid.is_empty() {
                     log::info!("[PE] 清理旧引导项: {}", guid);
-                    let _ = create_command(&self.bcdedit_path)
-                        .args(["/delete", guid, "/f"])
-                        .output();
+                    let _ = command::run(&self.bcdedit_path, ["/delete", guid, "/f"]);
                 }
             }
         }
@@ -3,30 +3,25 @@
 //! 提供磁盘分区的底层操作功能，使用 diskpart 和 Windows API 实现
 
 use anyhow::Result;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-#[cfg(windows)]
-use windows::{
-    core::PCWSTR,
-    Win32::Foundation::{CloseHandle, INVALID_HANDLE_VALUE},
-    Win32::Storage::FileSystem::{
-        CreateFileW, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
-    },
-    Win32::System::IO::DeviceIoControl,
-    Win32::System::Ioctl::{
-        IOCTL_DISK_GET_DRIVE_GEOMETRY_EX, IOCTL_DISK_GET_DRIVE_LAYOUT_EX, PARTITION_STYLE_GPT,
-        PARTITION_STYLE_MBR, PARTITION_STYLE_RAW,
-    },
+use lr_core::diskpart_output;
+use lr_core::diskpart_script::{
+    DiskpartScript, FileSystem, PartitionId, PartitionKind, TableStyle,
+};
+use lr_core::partition_plan::{
+    self, DataPartition, Firmware, LayoutPlan, LayoutRequest, PartitionRole, PlanError,
 };
+use lr_core::partition_backup;
+use lr_core::partition_preset::{PartitionPreset, PresetError, PresetSize};
+#[cfg(windows)]
+use lr_core::partition_table::{self, PartitionTable};
 
-/// IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS 常量
-/// CTL_CODE(IOCTL_VOLUME_BASE, 0, METHOD_BUFFERED, FILE_ANY_ACCESS)
-/// IOCTL_VOLUME_BASE = 0x56 ('V'), 所以值为 (0x56 << 16) | (0 << 14) | (0 << 2) | 0 = 0x00560000
 #[cfg(windows)]
-const IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS: u32 = 0x00560000;
+use windows::core::PCWSTR;
 
 use crate::tr;
-use crate::utils::cmd::create_command;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
 
@@ -50,6 +45,8 @@ pub struct PhysicalDisk {
     pub disk_number: u32,
     /// 磁盘大小（字节）
     pub size_bytes: u64,
+    /// 逻辑扇区大小（字节）
+    pub sector_size: u32,
     /// 磁盘型号/名称
     pub model: String,
     /// 分区表类型
@@ -166,8 +163,14 @@ pub struct PartitionLayout {
     pub label: String,
     /// 是否为 ESP 分区
     pub is_esp: bool,
+    /// 是否为 MSR 分区
+    pub is_msr: bool,
+    /// 是否为恢复分区
+    pub is_recovery: bool,
     /// 文件系统类型
     pub file_system: String,
+    /// 不指定大小，占用剩余空间（忽略 `size_gb`）
+    pub fill: bool,
 }
 
 impl Default for PartitionLayout {
@@ -177,7 +180,10 @@ impl Default for PartitionLayout {
             drive_letter: None,
             label: String::new(),
             is_esp: false,
+            is_msr: false,
+            is_recovery: false,
             file_system: "NTFS".to_string(),
+            fill: false,
         }
     }
 }
@@ -190,136 +196,6 @@ pub struct QuickPartitionResult {
     pub created_partitions: Vec<String>,
 }
 
-/// DISK_GEOMETRY_EX 结构
-/// 根据 Windows SDK 定义:
-/// struct DISK_GEOMETRY {
-///     LARGE_INTEGER Cylinders;        // 8 bytes
-///     MEDIA_TYPE    MediaType;        // 4 bytes
-///     DWORD         TracksPerCylinder; // 4 bytes
-///     DWORD         SectorsPerTrack;   // 4 bytes
-///     DWORD         BytesPerSector;    // 4 bytes
-/// }; // Total: 24 bytes
-/// struct DISK_GEOMETRY_EX {
-///     DISK_GEOMETRY Geometry;
-///     LARGE_INTEGER DiskSize;         // 8 bytes
-///     BYTE          Data[1];
-/// };
-#[cfg(windows)]
-#[repr(C)]
-#[derive(Default)]
-struct DiskGeometryEx {
-    // DISK_GEOMETRY 部分 (24 bytes)
-    geometry_cylinders: i64,           // 8 bytes - LARGE_INTEGER 必须在最前面！
-    geometry_media_type: u32,          // 4 bytes
-    geometry_tracks_per_cylinder: u32, // 4 bytes
-    geometry_sectors_per_track: u32,   // 4 bytes
-    geometry_bytes_per_sector: u32,    // 4 bytes
-    // DISK_GEOMETRY_EX 扩展部分
-    disk_size: i64,                    // 8 bytes - 这才是我们需要的磁盘大小
-}
-
-/// DRIVE_LAYOUT_INFORMATION_EX 结构头部
-#[cfg(windows)]
-#[repr(C)]
-#[derive(Default, Clone, Copy)]
-struct DriveLayoutInfoExHeader {
-    partition_style: u32,
-    partition_count: u32,
-}
-
-/// PARTITION_INFORMATION_EX 结构（GPT）
-#[cfg(windows)]
-#[repr(C)]
-#[derive(Clone, Copy)]
-struct PartitionInfoExGpt {
-    starting_offset: i64,
-    partition_length: i64,
-    partition_number: u32,
-    rewrite_partition: u8,
-    is_service_partition: u8,
-    _padding: [u8; 2],
-    partition_style: u32,
-    // GPT specific
-    partition_type_guid: [u8; 16],
-    partition_id_guid: [u8; 16],
-    attributes: u64,
-    name: [u16; 36],
-}
-
-#[cfg(windows)]
-impl Default for PartitionInfoExGpt {
-    fn default() -> Self {
-        Self {
-            starting_offset: 0,
-            partition_length: 0,
-            partition_number: 0,
-            rewrite_partition: 0,
-            is_service_partition: 0,
-            _padding: [0; 2],
-            partition_style: 0,
-            partition_type_guid: [0; 16],
-            partition_id_guid: [0; 16],
-            attributes: 0,
-            name: [0; 36],
-        }
-    }
-}
-
-/// PARTITION_INFORMATION_EX 结构（MBR）
-#[cfg(windows)]
-#[repr(C)]
-#[derive(Clone, Copy)]
-struct PartitionInfoExMbr {
-    starting_offset: i64,
-    partition_length: i64,
-    partition_number: u32,
-    rewrite_partition: u8,
-    is_service_partition: u8,
-    _padding: [u8; 2],
-    partition_style: u32,
-    // MBR specific
-    partition_type: u8,
-    boot_indicator: u8,
-    recognized_partition: u8,
-    hidden_sectors: u32,
-    _reserved: [u8; 100], // 填充到与 GPT 相同大小
-}
-
-#[cfg(windows)]
-impl Default for PartitionInfoExMbr {
-    fn default() -> Self {
-        Self {
-            starting_offset: 0,
-            partition_length: 0,
-            partition_number: 0,
-            rewrite_partition: 0,
-            is_service_partition: 0,
-            _padding: [0; 2],
-            partition_style: 0,
-            partition_type: 0,
-            boot_indicator: 0,
-            recognized_partition: 0,
-            hidden_sectors: 0,
-            _reserved: [0; 100],
-        }
-    }
-}
-
-/// ESP 分区类型 GUID
-const ESP_PARTITION_TYPE_GUID: [u8; 16] = [
-    0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9, 0x3b,
-];
-
-/// MSR 分区类型 GUID
-const MSR_PARTITION_TYPE_GUID: [u8; 16] = [
-    0x16, 0xe3, 0xc9, 0xe3, 0x5c, 0x0b, 0xb8, 0x4d, 0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15, 0xae,
-];
-
-/// Windows 恢复分区类型 GUID
-const RECOVERY_PARTITION_TYPE_GUID: [u8; 16] = [
-    0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6, 0xac,
-];
-
 /// 获取所有物理磁盘列表
 #[cfg(windows)]
 pub fn get_physical_disks() -> Vec<PhysicalDisk> {
@@ -342,7 +218,7 @@ pub fn get_physical_disks() -> Vec<PhysicalDisk> {
 
 /// 返回指定磁盘上活动（引导）分区的分区号（MBR BootIndicator=0x80）。
 ///
-/// 权威来源：经 IOCTL_DISK_GET_DRIVE_LAYOUT_EX 直接读 MBR 引导字节，不依赖 diskpart 文本输出
+/// 权威来源：直接读盘上 MBR 的引导字节，不依赖 diskpart 文本输出
 /// （新版 Windows 的 `detail partition` 可能不显示"活动"字段，`list partition` 的 `*` 只是焦点标记）。
 /// 无活动分区或非 MBR 盘返回 None。
 #[cfg(windows)]
@@ -360,233 +236,87 @@ pub fn get_active_partition_number(_disk_number: u32) -> Option<u32> {
 }
 
 /// 获取单个磁盘的详细信息
+///
+/// 分区布局直接读盘上的 MBR/GPT（见 `lr_core::partition_table`）；磁盘打不开返回 None，
+/// 分区表损坏时按未初始化处理。
 #[cfg(windows)]
 fn get_disk_info(disk_number: u32) -> Option<PhysicalDisk> {
-    unsafe {
-        let disk_path = format!("\\\\.\\PhysicalDrive{}", disk_number);
-        let wide_path: Vec<u16> = disk_path.encode_utf16().chain(std::iter::once(0)).collect();
-
-        let handle = CreateFileW(
-            PCWSTR::from_raw(wide_path.as_ptr()),
-            0,
-            FILE_SHARE_READ | FILE_SHARE_WRITE,
-            None,
-            OPEN_EXISTING,
-            Default::default(),
-            None,
-        );
-
-        let handle = match handle {
-            Ok(h) => h,
-            Err(_) => return None,
-        };
-
-        if handle == INVALID_HANDLE_VALUE {
-            return None;
+    let layout = partition_table::read_physical_drive(disk_number).ok()?;
+    let size_bytes = layout.disk_size;
+
+    let table = match layout.table {
+        Ok(table) => table,
+        Err(e) => {
+            log::warn!("读取磁盘 {} 的分区表失败: {}", disk_number, e);
+            None
         }
+    };
 
-        // 获取磁盘大小
-        let mut geometry = DiskGeometryEx::default();
-        let mut bytes_returned: u32 = 0;
-
-        let size_result = DeviceIoControl(
-            handle,
-            IOCTL_DISK_GET_DRIVE_GEOMETRY_EX,
-            None,
-            0,
-            Some(&mut geometry as *mut _ as *mut _),
-            std::mem::size_of::<DiskGeometryEx>() as u32,
-            Some(&mut bytes_returned),
-            None,
-        );
-
-        let size_bytes = if size_result.is_ok() {
-            geometry.disk_size as u64
-        } else {
-            let _ = CloseHandle(handle);
-            return None;
-        };
-
-        // 获取分区布局信息
-        let mut buffer = vec![0u8; 65536]; // 足够大的缓冲区
-        let mut bytes_returned: u32 = 0;
-
-        let layout_result = DeviceIoControl(
-            handle,
-            IOCTL_DISK_GET_DRIVE_LAYOUT_EX,
-            None,
-            0,
-            Some(buffer.as_mut_ptr() as *mut _),
-            buffer.len() as u32,
-            Some(&mut bytes_returned),
-            None,
-        );
-
-        let _ = CloseHandle(handle);
-
-        let (partition_style, is_initialized, partitions) = if layout_result.is_ok()
-            && bytes_returned >= std::mem::size_of::<DriveLayoutInfoExHeader>() as u32
-        {
-            let header = &*(buffer.as_ptr() as *const DriveLayoutInfoExHeader);
-
-            let style = match header.partition_style {
-                x if x == PARTITION_STYLE_MBR.0 as u32 => PartitionStyle::MBR,
-                x if x == PARTITION_STYLE_GPT.0 as u32 => PartitionStyle::GPT,
-                x if x == PARTITION_STYLE_RAW.0 as u32 => PartitionStyle::Unknown,
-                _ => PartitionStyle::Unknown,
+    let (partition_style, is_initialized, partitions) = match table {
+        Some(table) => {
+            let style = match table.style {
+                TableStyle::Mbr => PartitionStyle::MBR,
+                TableStyle::Gpt => PartitionStyle::GPT,
             };
+            let partitions = partition_infos(&table, disk_number);
+            (style, true, partitions)
+        }
+        None => (PartitionStyle::Unknown, false, Vec::new()),
+    };
 
-            let is_init = style != PartitionStyle::Unknown;
-
-            // 解析分区信息
-            let partitions = parse_partition_layout(&buffer, header, style, disk_number);
-
-            (style, is_init, partitions)
-        } else {
-            (PartitionStyle::Unknown, false, Vec::new())
-        };
-
-        // 计算未分配空间
-        let allocated: u64 = partitions.iter().map(|p| p.size_bytes).sum();
-        let unallocated = size_bytes.saturating_sub(allocated);
+    // 计算未分配空间
+    let allocated: u64 = partitions.iter().map(|p| p.size_bytes).sum();
+    let unallocated = size_bytes.saturating_sub(allocated);
 
-        // 获取磁盘型号
-        let model = get_disk_model(disk_number).unwrap_or_default();
+    // 获取磁盘型号
+    let model = get_disk_model(disk_number).unwrap_or_default();
 
-        Some(PhysicalDisk {
-            disk_number,
-            size_bytes,
-            model,
-            partition_style,
-            is_initialized,
-            partitions,
-            unallocated_bytes: unallocated,
-        })
-    }
+    Some(PhysicalDisk {
+        disk_number,
+        size_bytes,
+        sector_size: layout.sector_size,
+        model,
+        partition_style,
+        is_initialized,
+        partitions,
+        unallocated_bytes: unallocated,
+    })
 }
 
-/// 解析分区布局信息
+/// 把分区表项补上盘符与卷信息
 #[cfg(windows)]
-fn parse_partition_layout(
-    buffer: &[u8],
-    header: &DriveLayoutInfoExHeader,
-    style: PartitionStyle,
-    disk_number: u32,
-) -> Vec<DiskPartitionInfo> {
-    let mut partitions = Vec::new();
-
-    // PARTITION_INFORMATION_EX 结构大小固定为 144 字节
-    let partition_entry_size = 144;
-
-    // DRIVE_LAYOUT_INFORMATION_EX 头部大小 = FIELD_OFFSET(_, PartitionEntry)：
-    //   PartitionStyle(4) + PartitionCount(4) + union{Mbr,Gpt}(40) = 48。
-    // 关键：那个 union 是【定长】的——大小取最大成员 max(MBR=8, GPT=40)=40、按 8 对齐，
-    // 与磁盘实际是 MBR 还是 GPT 无关。所以分区数组对 MBR 和 GPT 都从偏移 48 起。
-    // （旧代码对 MBR 用 16 是把 union 当成只占 8 字节，导致 MBR 盘每个分区项整体前移 32 字节、
-    //   字段全部错位——partition_length 落进 union 区读成 ~0 被跳过，MBR 盘解析出 0 个分区，
-    //   进而 is_active/盘符匹配全失效，Legacy 引导回退、开机 0x7B。GPT 一直用 48 故正常。）
-    let header_size = 8 + 40; // = 48，MBR/GPT 一致
-
-    for i in 0..header.partition_count {
-        let offset = header_size + (i as usize * partition_entry_size);
-        if offset + partition_entry_size > buffer.len() {
-            break;
-        }
-
-        let partition_data = &buffer[offset..offset + partition_entry_size];
-
-        // PARTITION_INFORMATION_EX 结构布局:
-        // offset 0:  PartitionStyle (4 bytes)
-        // offset 4:  padding (4 bytes) - 为了 8 字节对齐
-        // offset 8:  StartingOffset (8 bytes, LARGE_INTEGER)
-        // offset 16: PartitionLength (8 bytes, LARGE_INTEGER)
-        // offset 24: PartitionNumber (4 bytes)
-        // offset 28: RewritePartition (1 byte)
-        // offset 29: IsServicePartition (1 byte)
-        // offset 30: padding (2 bytes)
-        // offset 32: Union start (MBR or GPT specific data)
-
-        let starting_offset = i64::from_le_bytes(partition_data[8..16].try_into().unwrap_or([0; 8]));
-        let partition_length = i64::from_le_bytes(partition_data[16..24].try_into().unwrap_or([0; 8]));
-        let partition_number = u32::from_le_bytes(partition_data[24..28].try_into().unwrap_or([0; 4]));
-
-        // 跳过大小为0的分区
-        if partition_length <= 0 {
-            continue;
-        }
-
-        let (is_esp, is_msr, is_recovery, partition_type) = if style == PartitionStyle::GPT {
-            // GPT: 分区类型 GUID 在 union 开始处 (offset 32)
-            // PARTITION_INFORMATION_GPT 结构:
-            // offset 0 (32): PartitionType GUID (16 bytes)
-            // offset 16 (48): PartitionId GUID (16 bytes)
-            // offset 32 (64): Attributes (8 bytes)
-            // offset 40 (72): Name (72 bytes, 36 wchars)
-            let mut type_guid = [0u8; 16];
-            type_guid.copy_from_slice(&partition_data[32..48]);
-
-            let is_esp = type_guid == ESP_PARTITION_TYPE_GUID;
-            let is_msr = type_guid == MSR_PARTITION_TYPE_GUID;
-            let is_recovery = type_guid == RECOVERY_PARTITION_TYPE_GUID;
-
-            let type_str = format!(
-                "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}{:02X}{:02X}{:02X}{:02X}",
-                type_guid[3], type_guid[2], type_guid[1], type_guid[0],
-                type_guid[5], type_guid[4],
-                type_guid[7], type_guid[6],
-                type_guid[8], type_guid[9],
-                type_guid[10], type_guid[11], type_guid[12], type_guid[13], type_guid[14], type_guid[15]
-            );
-
-            (is_esp, is_msr, is_recovery, type_str)
-        } else {
-            // MBR: 分区类型 ID 在 union 开始处 (offset 32)
-            // PARTITION_INFORMATION_MBR 结构:
-            // offset 0 (32): PartitionType (1 byte)
-            // offset 1 (33): BootIndicator (1 byte)
-            // offset 2 (34): RecognizedPartition (1 byte)
-            // offset 4 (36): HiddenSectors (4 bytes)
-            let type_id = partition_data[32];
-            let type_str = format!("0x{:02X}", type_id);
-            (false, false, false, type_str)
-        };
-
-        // 活动分区标志（仅 MBR 有意义）：BootIndicator(union 偏移 1 -> partition_data[33]) 高位置位即活动分区。
-        // 直接读 MBR 引导字节，是判断活动分区的权威来源——diskpart `detail partition` 在新版 Windows
-        // 上可能根本不显示"活动"字段，`list partition` 的 `*` 又只表示焦点而非活动，都不可靠。
-        let is_active = style == PartitionStyle::MBR && (partition_data[33] & 0x80) != 0;
-
-        // 获取盘符（按【磁盘号 + 偏移】匹配，避免多盘机器上两盘同偏移分区被错配同一盘符）
-        let drive_letter = get_drive_letter_for_partition(disk_number, starting_offset as u64);
-
-        // 获取卷标、文件系统和空间使用信息
-        let (label, file_system, used_bytes, free_bytes) = if let Some(letter) = drive_letter {
-            get_volume_info(letter)
-        } else {
-            (String::new(), String::new(), 0, 0)
-        };
-
-        partitions.push(DiskPartitionInfo {
-            partition_number,
-            size_bytes: partition_length as u64,
-            offset_bytes: starting_offset as u64,
-            drive_letter,
-            label,
-            file_system,
-            is_esp,
-            is_msr,
-            is_recovery,
-            partition_type,
-            used_bytes,
-            free_bytes,
-            is_active,
-        });
-    }
-
-    // 按偏移量排序
-    partitions.sort_by_key(|p| p.offset_bytes);
+fn partition_infos(table: &PartitionTable, disk_number: u32) -> Vec<DiskPartitionInfo> {
+    table
+        .volumes()
+        .map(|p| {
+            // 获取盘符（按【磁盘号 + 偏移】匹配，避免多盘机器上两盘同偏移分区被错配同一盘符）
+            let drive_letter = get_drive_letter_for_partition(disk_number, p.offset);
+
+            // 获取卷标、文件系统和空间使用信息
+            let (label, file_system, used_bytes, free_bytes) = if let Some(letter) = drive_letter {
+                get_volume_info(letter)
+            } else {
+                (String::new(), String::new(), 0, 0)
+            };
 
-    partitions
+            DiskPartitionInfo {
+                partition_number: p.number,
+                size_bytes: p.length,
+                offset_bytes: p.offset,
+                drive_letter,
+                label,
+                file_system,
+                is_esp: p.is_esp(),
+                is_msr: p.is_msr(),
+                is_recovery: p.is_recovery(),
+                partition_type: p.partition_type.to_string(),
+                used_bytes,
+                free_bytes,
+                // 活动分区标志直接取自 MBR 引导字节，比 diskpart `detail partition` 的"活动"字段可靠
+                is_active: p.active,
+            }
+        })
+        .collect()
 }
 
 /// 根据【磁盘号 + 分区偏移量】获取对应的盘符。
@@ -602,7 +332,7 @@ fn get_drive_letter_for_partition(disk_number: u32, offset: u64) -> Option<char>
         }
 
         // 检查这个卷的磁盘号与偏移量是否都匹配
-        if let Some((vol_disk, vol_offset)) = get_volume_offset(c) {
+        if let Some((vol_disk, vol_offset)) = lr_core::arc::volume_disk_extent(&c.to_string()) {
             if vol_disk == disk_number
                 && (vol_offset as i64 - offset as i64).unsigned_abs() < 1024 * 1024
             {
@@ -613,75 +343,6 @@ fn get_drive_letter_for_partition(disk_number: u32, offset: u64) -> Option<char>
     None
 }
 
-/// 获取卷所在的【磁盘号 + 起始偏移量】（DISK_EXTENT.DiskNumber / StartingOffset）。
-/// DiskNumber 即 \\.\PhysicalDriveN 的 N，与 get_disk_info 用的磁盘号同义。
-#[cfg(windows)]
-fn get_volume_offset(letter: char) -> Option<(u32, u64)> {
-    unsafe {
-        let volume_path = format!("\\\\.\\{}:", letter);
-        let wide_path: Vec<u16> = volume_path.encode_utf16().chain(std::iter::once(0)).collect();
-
-        let handle = CreateFileW(
-            PCWSTR::from_raw(wide_path.as_ptr()),
-            0,
-            FILE_SHARE_READ | FILE_SHARE_WRITE,
-            None,
-            OPEN_EXISTING,
-            Default::default(),
-            None,
-        );
-
-        let handle = match handle {
-            Ok(h) => h,
-            Err(_) => return None,
-        };
-
-        if handle == INVALID_HANDLE_VALUE {
-            return None;
-        }
-
-        // VOLUME_DISK_EXTENTS 结构
-        #[repr(C)]
-        struct DiskExtent {
-            disk_number: u32,
-            starting_offset: i64,
-            extent_length: i64,
-        }
-
-        #[repr(C)]
-        struct VolumeDiskExtents {
-            number_of_disk_extents: u32,
-            extents: [DiskExtent; 1],
-        }
-
-        let mut buffer = [0u8; 256];
-        let mut bytes_returned: u32 = 0;
-
-        let result = DeviceIoControl(
-            handle,
-            IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
-            None,
-            0,
-            Some(buffer.as_mut_ptr() as *mut _),
-            buffer.len() as u32,
-            Some(&mut bytes_returned),
-            None,
-        );
-
-        let _ = CloseHandle(handle);
-
-        if result.is_ok() {
-            let extents = &*(buffer.as_ptr() as *const VolumeDiskExtents);
-            if extents.number_of_disk_extents > 0 {
-                let e = &extents.extents[0];
-                return Some((e.disk_number, e.starting_offset as u64));
-            }
-        }
-
-        None
-    }
-}
-
 /// 获取卷信息（卷标、文件系统、已用空间、空闲空间）
 #[cfg(windows)]
 fn get_volume_info(letter: char) -> (String, String, u64, u64) {
@@ -743,19 +404,15 @@ fn get_volume_info(letter: char) -> (String, String, u64, u64) {
 /// 获取磁盘型号
 #[cfg(windows)]
 fn get_disk_model(disk_number: u32) -> Option<String> {
-    use crate::utils::cmd::create_command;
-
     // 使用 PowerShell 获取磁盘型号
-    let output = create_command("powershell")
-        .args([
-            "-NoProfile",
-            "-Command",
-            &format!(
-                "Get-Disk -Number {} | Select-Object -ExpandProperty FriendlyName",
-                disk_number
-            ),
-        ])
-        .output()
+    let output = command::run("powershell", [
+        "-NoProfile",
+        "-Command",
+        &format!(
+        "Get-Disk -Number {} | Select-Object -ExpandProperty FriendlyName",
+        disk_number
+        ),
+    ])
         .ok()?;
 
     if output.status.success() {
@@ -766,16 +423,14 @@ fn get_disk_model(disk_number: u32) -> Option<String> {
     }
 
     // 备选：使用 WMIC
-    let output = create_command("wmic")
-        .args([
-            "diskdrive",
-            "where",
-            &format!("Index={}", disk_number),
-            "get",
-            "Model",
-            "/format:list",
-        ])
-        .output()
+    let output = command::run("wmic", [
+        "diskdrive",
+        "where",
+        &format!("Index={}", disk_number),
+        "get",
+        "Model",
+        "/format:list",
+    ])
         .ok()?;
 
     let text = gbk_to_utf8(&output.stdout);
@@ -804,23 +459,10 @@ pub fn execute_quick_partition(
         layouts.len()
     );
 
-    // 构建 diskpart 脚本
-    let mut script = String::new();
-
-    // 选择磁盘
-    script.push_str(&format!("select disk {}\n", disk_number));
-
-    // 清除磁盘（删除所有分区）
-    script.push_str("clean\n");
-
-    // 转换分区表类型
-    match partition_style {
-        PartitionStyle::GPT => {
-            script.push_str("convert gpt\n");
-        }
-        PartitionStyle::MBR => {
-            script.push_str("convert mbr\n");
-        }
+    // 构建 diskpart 脚本：选择磁盘 → 清除（删除所有分区）→ 转换分区表类型
+    let style = match partition_style {
+        PartitionStyle::GPT => TableStyle::Gpt,
+        PartitionStyle::MBR => TableStyle::Mbr,
         _ => {
             return QuickPartitionResult {
                 success: false,
@@ -828,80 +470,103 @@ pub fn execute_quick_partition(
                 created_partitions: Vec::new(),
             };
         }
-    }
+    };
+    let mut script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .clean()
+        .convert(style);
 
     let mut created_partitions = Vec::new();
 
     // 创建分区
     for (i, layout) in layouts.iter().enumerate() {
-        let is_last = i == layouts.len() - 1;
-
         if layout.is_esp {
             // 创建 ESP 分区
             let size_mb = (layout.size_gb * 1024.0) as u64;
-            script.push_str(&format!("create partition efi size={}\n", size_mb));
-            script.push_str("format fs=fat32 quick label=\"EFI\"\n");
+            script = script
+                .create_partition(PartitionKind::Efi, Some(size_mb), None)
+                .format(FileSystem::Fat32, "EFI", true);
             created_partitions.push("ESP".to_string());
-        } else {
-            // 创建普通分区
-            if is_last {
-                // 最后一个分区使用剩余空间
-                script.push_str("create partition primary\n");
+        } else if layout.is_msr {
+            // MSR 分区不格式化、不分配盘符
+            let size_mb = (layout.size_gb * 1024.0) as u64;
+            script = script.create_partition(PartitionKind::Msr, Some(size_mb), None);
+            created_partitions.push("MSR".to_string());
+        } else if layout.is_recovery {
+            // 恢复分区：格式化后改类型，不分配盘符
+            let size_mb = (!layout.fill).then(|| (layout.size_gb * 1024.0) as u64);
+            let label = if layout.label.is_empty() {
+                "Recovery"
             } else {
-                let size_mb = (layout.size_gb * 1024.0) as u64;
-                script.push_str(&format!("create partition primary size={}\n", size_mb));
-            }
+                layout.label.as_str()
+            };
+            script = script
+                .create_partition(PartitionKind::Primary, size_mb, None)
+                .format(FileSystem::Ntfs, label, true);
+            script = match style {
+                TableStyle::Gpt => script
+                    .set_id(PartitionId::Gpt(PartitionId::GPT_RECOVERY.to_string()), true)
+                    .gpt_attributes(partition_plan::RECOVERY_GPT_ATTRIBUTES),
+                TableStyle::Mbr => script.set_id(PartitionId::Mbr(0x27), true),
+            };
+            created_partitions.push(tr!("恢复分区"));
+        } else {
+            // 创建普通分区，fill 分区使用剩余空间
+            let size_mb = (!layout.fill).then(|| (layout.size_gb * 1024.0) as u64);
+            script = script.create_partition(PartitionKind::Primary, size_mb, None);
 
             // 格式化
             let label = if layout.label.is_empty() {
-                "新加卷".to_string()
+                "新加卷"
             } else {
-                layout.label.clone()
+                layout.label.as_str()
             };
             let fs = if layout.file_system.is_empty() {
-                "NTFS"
+                Some(FileSystem::Ntfs)
             } else {
-                &layout.file_system
+                FileSystem::parse(&layout.file_system)
             };
-            script.push_str(&format!("format fs={} quick label=\"{}\"\n", fs, label));
+            let Some(fs) = fs else {
+                return QuickPartitionResult {
+                    success: false,
+                    message: tr!("不支持的文件系统: {}", layout.file_system),
+                    created_partitions: Vec::new(),
+                };
+            };
+            script = script.format(fs, label, true);
 
             // 分配盘符
             if let Some(letter) = layout.drive_letter {
-                script.push_str(&format!("assign letter={}\n", letter));
+                script = script.assign_letter(letter);
                 created_partitions.push(format!("{}:", letter));
             } else {
-                script.push_str("assign\n");
+                script = script.assign();
                 created_partitions.push(tr!("分区 {}", i + 1));
             }
         }
     }
 
+    let script = match script.build() {
+        Ok(script) => script,
+        Err(e) => {
+            return QuickPartitionResult {
+                success: false,
+                message: tr!("分区参数无效: {}", e),
+                created_partitions: Vec::new(),
+            };
+        }
+    };
+
     // 执行脚本
     match execute_diskpart_script(&script) {
-        Ok(output) => {
-            // 检查输出是否包含错误
-            let output_lower = output.to_lowercase();
-            if output_lower.contains("错误")
-                || output_lower.contains("error")
-                || output_lower.contains("失败")
-                || output_lower.contains("failed")
-            {
-                QuickPartitionResult {
-                    success: false,
-                    message: tr!("分区操作失败: {}", output),
-                    created_partitions: Vec::new(),
-                }
-            } else {
-                QuickPartitionResult {
-                    success: true,
-                    message: tr!("分区操作完成"),
-                    created_partitions,
-                }
-            }
-        }
+        Ok(_) => QuickPartitionResult {
+            success: true,
+            message: tr!("分区操作完成"),
+            created_partitions,
+        },
         Err(e) => QuickPartitionResult {
             success: false,
-            message: tr!("执行 diskpart 失败: {}", e),
+            message: tr!("分区操作失败: {}", e),
             created_partitions: Vec::new(),
         },
     }
@@ -916,9 +581,7 @@ fn execute_diskpart_script(script: &str) -> Result<String> {
 
     std::fs::write(&script_path, script)?;
 
-    let output = create_command(&get_diskpart_path())
-        .args(["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])
-        .output()?;
+    let output = command::run(&get_diskpart_path(), ["/s", script_path.to_str().ok_or_else(|| anyhow::anyhow!("script path is not valid UTF-8"))?])?;
 
     let _ = std::fs::remove_file(&script_path);
 
@@ -930,13 +593,20 @@ fn execute_diskpart_script(script: &str) -> Result<String> {
         log::warn!("Diskpart 错误: {}", error_text);
     }
 
-    if !error_text.is_empty() && !output.status.success() {
-        anyhow::bail!("{}", error_text);
+    // 成败只看退出码，不在输出里找「错误 / error」之类的词（各语言 PE 措辞不同）
+    if !diskpart_output::succeeded(&output) {
+        let detail = if error_text.trim().is_empty() { &output_text } else { &error_text };
+        anyhow::bail!("{}", detail.trim());
     }
 
     Ok(output_text)
 }
 
+/// 校验并生成脚本后执行
+fn run_diskpart(script: DiskpartScript) -> Result<String> {
+    execute_diskpart_script(&script.build()?)
+}
+
 /// 检查磁盘是否可以安全分区（没有系统盘）
 pub fn can_safely_partition(disk: &PhysicalDisk) -> (bool, String) {
     // 检查是否包含系统盘
@@ -979,11 +649,225 @@ pub fn can_safely_partition(disk: &PhysicalDisk) -> (bool, String) {
     (true, String::new())
 }
 
+/// 启动模式对应的固件类型
+pub fn firmware_for(boot_mode: &BootMode) -> Firmware {
+    match boot_mode {
+        BootMode::UEFI => Firmware::Uefi,
+        BootMode::Legacy => Firmware::Bios,
+    }
+}
+
 /// 根据启动模式获取推荐的分区表类型
 pub fn get_recommended_partition_style(boot_mode: &BootMode) -> PartitionStyle {
-    match boot_mode {
-        BootMode::UEFI => PartitionStyle::GPT,
-        BootMode::Legacy => PartitionStyle::MBR,
+    match firmware_for(boot_mode).table_style() {
+        TableStyle::Gpt => PartitionStyle::GPT,
+        TableStyle::Mbr => PartitionStyle::MBR,
+    }
+}
+
+/// 按 Windows 推荐布局规划整盘分区
+///
+/// `layouts` 为用户规划的普通分区：第一个作为 Windows 分区，其余作为数据分区，
+/// 最后一个占用剩余空间；ESP / MSR / 系统保留 / 恢复分区由规划器按启动模式补齐。
+pub fn plan_recommended_layout(
+    disk: &PhysicalDisk,
+    boot_mode: &BootMode,
+    layouts: &[PartitionLayout],
+) -> Result<LayoutPlan, PlanError> {
+    let to_bytes = |size_gb: f64| (size_gb * 1024.0) as u64 * 1024 * 1024;
+    let sector_size = if disk.sector_size == 0 { 512 } else { disk.sector_size };
+    let mut request = LayoutRequest::new(disk.size_bytes, sector_size, firmware_for(boot_mode));
+
+    let user: Vec<&PartitionLayout> = layouts
+        .iter()
+        .filter(|l| !l.is_esp && !l.is_msr && !l.is_recovery)
+        .collect();
+    let last = user.len().saturating_sub(1);
+    if let Some(windows) = user.first() {
+        request.windows_letter = windows.drive_letter;
+        if last > 0 {
+            request.windows_size = Some(to_bytes(windows.size_gb));
+        }
+    }
+    for (i, layout) in user.iter().enumerate().skip(1) {
+        request.data_partitions.push(DataPartition {
+            size: (i != last).then(|| to_bytes(layout.size_gb)),
+            label: layout.label.clone(),
+            file_system: FileSystem::parse(&layout.file_system).unwrap_or(FileSystem::Ntfs),
+            letter: layout.drive_letter,
+        });
+    }
+
+    partition_plan::plan_windows_layout(&request)
+}
+
+/// 按预设换算出一键分区布局
+///
+/// 分区表类型优先取预设中的设置，未指定时用 `default_style`（通常为启动模式推荐值）。
+pub fn preset_layouts(
+    preset: &PartitionPreset,
+    disk: &PhysicalDisk,
+    default_style: PartitionStyle,
+) -> Result<(PartitionStyle, Vec<PartitionLayout>), PresetError> {
+    let style = match preset.style {
+        Some(TableStyle::Gpt) => PartitionStyle::GPT,
+        Some(TableStyle::Mbr) => PartitionStyle::MBR,
+        None => default_style,
+    };
+    if style == PartitionStyle::MBR {
+        if let Some(i) = preset.partitions.iter().position(|p| p.is_esp || p.is_msr) {
+            return Err(PresetError::GptOnly(i + 1));
+        }
+    }
+
+    let sizes = preset.resolve_sizes(disk.size_bytes)?;
+    let layouts = preset
+        .partitions
+        .iter()
+        .zip(sizes)
+        .map(|(p, bytes)| PartitionLayout {
+            size_gb: bytes as f64 / 1024.0 / 1024.0 / 1024.0,
+            drive_letter: p.drive_letter.map(|c| c.to_ascii_uppercase()),
+            label: p.label.clone(),
+            is_esp: p.is_esp,
+            is_msr: p.is_msr,
+            is_recovery: p.is_recovery,
+            file_system: p.file_system.clone(),
+            fill: p.size == PresetSize::Fill,
+        })
+        .collect();
+    Ok((style, layouts))
+}
+
+/// 按预设对指定磁盘执行一键分区（命令行无人值守分区用）
+///
+/// 与界面一致：包含当前系统盘或 Windows 系统的磁盘拒绝执行。
+pub fn run_preset_partition(
+    disk_number: u32,
+    preset: &PartitionPreset,
+    default_style: PartitionStyle,
+) -> Result<QuickPartitionResult> {
+    let disk = get_physical_disks()
+        .into_iter()
+        .find(|d| d.disk_number == disk_number)
+        .ok_or_else(|| anyhow::anyhow!(tr!("找不到磁盘 {}", disk_number)))?;
+
+    let (safe, reason) = can_safely_partition(&disk);
+    if !safe {
+        anyhow::bail!("{}", reason);
+    }
+
+    let (style, layouts) = preset_layouts(preset, &disk, default_style)?;
+    log::info!(
+        "按预设 \"{}\" 分区: 磁盘 {}, 分区表类型: {:?}",
+        preset.name,
+        disk_number,
+        style
+    );
+    Ok(with_table_backup(disk_number, "quick_partition", || {
+        execute_quick_partition(disk_number, style, &layouts)
+    }))
+}
+
+/// 破坏性分区操作前备份磁盘 `disk_number` 的分区表
+///
+/// 备份存到其他磁盘（优先可移动磁盘）的 `LetRecovery_PartitionBackup` 目录，见
+/// `lr_core::partition_backup`；磁盘未初始化时无需备份，返回 `None`。
+pub fn backup_partition_table(disk_number: u32, reason: &str) -> Result<Option<PathBuf>> {
+    Ok(partition_backup::backup_physical_drive(disk_number, reason, None)?)
+}
+
+/// 可由 [`with_table_backup`] 包装的分区操作结果
+pub trait TableBackupReport {
+    /// 备份失败、操作未执行时的结果
+    fn backup_failed(message: String) -> Self;
+    /// 附加备份位置说明
+    fn note_backup(&mut self, note: String);
+}
+
+impl TableBackupReport for QuickPartitionResult {
+    fn backup_failed(message: String) -> Self {
+        QuickPartitionResult {
+            success: false,
+            message,
+            created_partitions: Vec::new(),
+        }
+    }
+
+    fn note_backup(&mut self, note: String) {
+        self.message = format!("{}\n{}", self.message, note);
+    }
+}
+
+impl TableBackupReport for ResizePartitionResult {
+    fn backup_failed(message: String) -> Self {
+        ResizePartitionResult {
+            success: false,
+            message,
+            new_size_mb: 0,
+        }
+    }
+
+    fn note_backup(&mut self, note: String) {
+        self.message = format!("{}\n{}", self.message, note);
+    }
+}
+
+/// 先备份分区表再执行破坏性分区操作；备份失败时不触碰磁盘
+pub fn with_table_backup<R: TableBackupReport>(
+    disk_number: u32,
+    reason: &str,
+    op: impl FnOnce() -> R,
+) -> R {
+    let backup = match backup_partition_table(disk_number, reason) {
+        Ok(backup) => backup,
+        Err(e) => {
+            log::error!("备份磁盘 {} 的分区表失败: {:#}", disk_number, e);
+            return R::backup_failed(tr!("备份分区表失败，已取消分区操作: {}", e));
+        }
+    };
+
+    let mut result = op();
+    if let Some(dir) = backup {
+        result.note_backup(tr!("原分区表已备份到: {}", dir.display()));
+    }
+    result
+}
+
+/// 按规划结果执行整盘分区
+pub fn execute_layout_plan(disk_number: u32, plan: &LayoutPlan) -> QuickPartitionResult {
+    log::info!(
+        "开始按推荐布局分区: 磁盘 {}, 分区表类型: {:?}, 分区数量: {}",
+        disk_number,
+        plan.style,
+        plan.partitions.len()
+    );
+
+    let created_partitions = plan
+        .partitions
+        .iter()
+        .enumerate()
+        .map(|(i, p)| match (p.role, p.letter) {
+            (PartitionRole::Esp, _) => "ESP".to_string(),
+            (PartitionRole::Msr, _) => "MSR".to_string(),
+            (PartitionRole::System, _) => tr!("系统保留分区"),
+            (PartitionRole::Recovery, _) => tr!("恢复分区"),
+            (_, Some(letter)) => format!("{}:", letter),
+            (_, None) => tr!("分区 {}", i + 1),
+        })
+        .collect();
+
+    match run_diskpart(plan.diskpart_script(disk_number)) {
+        Ok(_) => QuickPartitionResult {
+            success: true,
+            message: tr!("分区操作完成"),
+            created_partitions,
+        },
+        Err(e) => QuickPartitionResult {
+            success: false,
+            message: tr!("分区操作失败: {}", e),
+            created_partitions: Vec::new(),
+        },
     }
 }
 
@@ -1020,81 +904,60 @@ pub fn create_single_partition(
     drive_letter: Option<char>,
     label: &str,
 ) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    
-    if size_mb > 0 {
-        script.push_str(&format!("create partition primary size={}\n", size_mb));
-    } else {
-        // 使用所有剩余空间
-        script.push_str("create partition primary\n");
-    }
-
+    // size_mb 为 0 时使用所有剩余空间
     let vol_label = if label.is_empty() { "OS" } else { label };
-    script.push_str(&format!("format fs=ntfs quick label=\"{}\"\n", vol_label));
-
-    if let Some(letter) = drive_letter {
-        script.push_str(&format!("assign letter={}\n", letter));
-    } else {
-        script.push_str("assign\n");
-    }
-
-    execute_diskpart_script(&script)
+    let script = DiskpartScript::new()
+        .select_disk(disk_number)
+        .create_partition(PartitionKind::Primary, (size_mb > 0).then_some(size_mb), None)
+        .format(FileSystem::Ntfs, vol_label, true);
+    run_diskpart(match drive_letter {
+        Some(letter) => script.assign_letter(letter),
+        None => script.assign(),
+    })
 }
 
 /// 创建 ESP 分区
 pub fn create_esp_partition(disk_number: u32, size_mb: u64) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("create partition efi size={}\n", size_mb));
-    script.push_str("format fs=fat32 quick label=\"EFI\"\n");
-
-    execute_diskpart_script(&script)
+    run_diskpart(
+        DiskpartScript::new()
+            .select_disk(disk_number)
+            .create_partition(PartitionKind::Efi, Some(size_mb), None)
+            .format(FileSystem::Fat32, "EFI", true),
+    )
 }
 
 /// 删除指定分区
 pub fn delete_partition(disk_number: u32, partition_number: u32) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-    script.push_str("delete partition override\n");
-
-    execute_diskpart_script(&script)
+    run_diskpart(
+        DiskpartScript::new()
+            .select_disk(disk_number)
+            .select_partition(partition_number)
+            .delete_partition(true),
+    )
 }
 
 /// 缩小分区
 pub fn shrink_partition(disk_number: u32, partition_number: u32, shrink_mb: u64) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-    script.push_str(&format!("shrink desired={}\n", shrink_mb));
-
-    execute_diskpart_script(&script)
+    run_diskpart(
+        DiskpartScript::new()
+            .select_disk(disk_number)
+            .select_partition(partition_number)
+            .shrink(shrink_mb, None),
+    )
 }
 
-/// 扩展分区
+/// 扩展分区（extend_mb 为 None 时使用所有可用空间）
 pub fn extend_partition(
     disk_number: u32,
     partition_number: u32,
     extend_mb: Option<u64>,
 ) -> Result<String> {
-    let mut script = String::new();
-
-    script.push_str(&format!("select disk {}\n", disk_number));
-    script.push_str(&format!("select partition {}\n", partition_number));
-
-    if let Some(size) = extend_mb {
-        script.push_str(&format!("extend size={}\n", size));
-    } else {
-        // 使用所有可用空间
-        script.push_str("extend\n");
-    }
-
-    execute_diskpart_script(&script)
+    run_diskpart(
+        DiskpartScript::new()
+            .select_disk(disk_number)
+            .select_partition(partition_number)
+            .extend(extend_mb),
+    )
 }
 
 /// 调整已有分区大小的结果
@@ -1165,6 +1028,29 @@ pub fn resize_existing_partition(
         };
     }
 
+    let mut result = with_table_backup(disk_number, "resize_partition", || {
+        resize_partition_unchecked(
+            disk_number,
+            partition_number,
+            drive_letter,
+            current_size_mb,
+            new_size_mb,
+        )
+    });
+    if !result.success {
+        result.new_size_mb = current_size_mb;
+    }
+    result
+}
+
+/// 用 diskpart 缩小或扩大分区（参数已校验）
+fn resize_partition_unchecked(
+    disk_number: u32,
+    partition_number: u32,
+    drive_letter: Option<char>,
+    current_size_mb: u64,
+    new_size_mb: u64,
+) -> ResizePartitionResult {
     // 判断是缩小还是扩大
     if new_size_mb < current_size_mb {
         // 缩小分区
@@ -1176,45 +1062,30 @@ pub fn resize_existing_partition(
         // 注意：diskpart 的 shrink 命令需要通过卷来选择，而不是分区
         let result = if let Some(letter) = drive_letter {
             // 通过盘符选择卷进行缩小
-            let mut script = String::new();
-            script.push_str(&format!("select volume {}\n", letter));
-            script.push_str(&format!("shrink desired={} minimum={}\n", shrink_amount_mb, shrink_amount_mb));
-            execute_diskpart_script(&script)
+            run_diskpart(
+                DiskpartScript::new()
+                    .select_volume_letter(letter)
+                    .shrink(shrink_amount_mb, Some(shrink_amount_mb)),
+            )
         } else {
             // 没有盘符的分区使用分区编号
-            let mut script = String::new();
-            script.push_str(&format!("select disk {}\n", disk_number));
-            script.push_str(&format!("select partition {}\n", partition_number));
-            script.push_str(&format!("shrink desired={} minimum={}\n", shrink_amount_mb, shrink_amount_mb));
-            execute_diskpart_script(&script)
+            run_diskpart(
+                DiskpartScript::new()
+                    .select_disk(disk_number)
+                    .select_partition(partition_number)
+                    .shrink(shrink_amount_mb, Some(shrink_amount_mb)),
+            )
         };
 
         match result {
-            Ok(output) => {
-                let output_lower = output.to_lowercase();
-                if output_lower.contains("错误")
-                    || output_lower.contains("error")
-                    || output_lower.contains("失败")
-                    || output_lower.contains("failed")
-                    || output_lower.contains("没有足够")
-                    || output_lower.contains("insufficient")
-                {
-                    ResizePartitionResult {
-                        success: false,
-                        message: tr!("缩小分区失败: {}", output.trim()),
-                        new_size_mb: current_size_mb,
-                    }
-                } else {
-                    ResizePartitionResult {
-                        success: true,
-                        message: tr!("分区已成功缩小 {} MB", shrink_amount_mb),
-                        new_size_mb,
-                    }
-                }
-            }
+            Ok(_) => ResizePartitionResult {
+                success: true,
+                message: tr!("分区已成功缩小 {} MB", shrink_amount_mb),
+                new_size_mb,
+            },
             Err(e) => ResizePartitionResult {
                 success: false,
-                message: tr!("执行 diskpart 失败: {}", e),
+                message: tr!("缩小分区失败: {}", e),
                 new_size_mb: current_size_mb,
             },
         }
@@ -1227,45 +1098,30 @@ pub fn resize_existing_partition(
         // 使用 diskpart extend 命令
         let result = if let Some(letter) = drive_letter {
             // 通过盘符选择卷进行扩展
-            let mut script = String::new();
-            script.push_str(&format!("select volume {}\n", letter));
-            script.push_str(&format!("extend size={}\n", extend_amount_mb));
-            execute_diskpart_script(&script)
+            run_diskpart(
+                DiskpartScript::new()
+                    .select_volume_letter(letter)
+                    .extend(Some(extend_amount_mb)),
+            )
         } else {
             // 没有盘符的分区使用分区编号
-            let mut script = String::new();
-            script.push_str(&format!("select disk {}\n", disk_number));
-            script.push_str(&format!("select partition {}\n", partition_number));
-            script.push_str(&format!("extend size={}\n", extend_amount_mb));
-            execute_diskpart_script(&script)
+            run_diskpart(
+                DiskpartScript::new()
+                    .select_disk(disk_number)
+                    .select_partition(partition_number)
+                    .extend(Some(extend_amount_mb)),
+            )
         };
 
         match result {
-            Ok(output) => {
-                let output_lower = output.to_lowercase();
-                if output_lower.contains("错误")
-                    || output_lower.contains("error")
-                    || output_lower.contains("失败")
-                    || output_lower.contains("failed")
-                    || output_lower.contains("没有足够")
-                    || output_lower.contains("insufficient")
-                {
-                    ResizePartitionResult {
-                        success: false,
-                        message: tr!("扩展分区失败: {}", output.trim()),
-                        new_size_mb: current_size_mb,
-                    }
-                } else {
-                    ResizePartitionResult {
-                        success: true,
-                        message: tr!("分区已成功扩展 {} MB", extend_amount_mb),
-                        new_size_mb,
-                    }
-                }
-            }
+            Ok(_) => ResizePartitionResult {
+                success: true,
+                message: tr!("分区已成功扩展 {} MB", extend_amount_mb),
+                new_size_mb,
+            },
             Err(e) => ResizePartitionResult {
                 success: false,
-                message: tr!("执行 diskpart 失败: {}", e),
+                message: tr!("扩展分区失败: {}", e),
                 new_size_mb: current_size_mb,
             },
         }
@@ -1276,11 +1132,11 @@ pub fn resize_existing_partition(
 /// 
 /// 使用 diskpart 的 shrink querymax 命令获取
 pub fn query_shrink_max(drive_letter: char) -> Result<u64> {
-    let mut script = String::new();
-    script.push_str(&format!("select volume {}\n", drive_letter));
-    script.push_str("shrink querymax\n");
-
-    let output = execute_diskpart_script(&script)?;
+    let output = run_diskpart(
+        DiskpartScript::new()
+            .select_volume_letter(drive_letter)
+            .shrink_querymax(),
+    )?;
     
     // 解析输出，查找可缩小的最大值
     // 输出格式通常为 "可回收的最大字节数:  XXX MB" 或 "The maximum number of reclaimable bytes is: XXX MB"
@@ -1451,4 +1307,264 @@ mod tests {
         assert!(next.is_some());
         assert!(!used.contains(&next.unwrap()));
     }
+
+    #[test]
+    fn test_execute_quick_partition_with_fake_diskpart() {
+        use lr_core::command::{with_runner, FakeRunner};
+        use std::sync::Arc;
+
+        let layouts = vec![
+            PartitionLayout {
+                size_gb: 0.5,
+                is_esp: true,
+                ..Default::default()
+            },
+            PartitionLayout {
+                size_gb: 100.0,
+                drive_letter: Some('S'),
+                label: "System".to_string(),
+                ..Default::default()
+            },
+            PartitionLayout {
+                label: "Data".to_string(),
+                fill: true,
+                ..Default::default()
+            },
+        ];
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || {
+            execute_quick_partition(2, PartitionStyle::GPT, &layouts)
+        });
+
+        assert!(result.success);
+        assert_eq!(result.created_partitions, vec!["ESP", "S:", "分区 3"]);
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 1);
+        assert_eq!(calls[0].program, "diskpart");
+        let script = calls[0].script.as_deref().unwrap();
+        assert!(script.starts_with("select disk 2\nclean\nconvert gpt\n"));
+        assert!(script.contains("create partition efi size=512\n"));
+        assert!(script.contains("create partition primary size=102400\n"));
+        assert!(script.contains("assign letter=S\n"));
+        // 最后一个分区占用剩余空间
+        assert!(script.ends_with(
+            "create partition primary\nformat fs=ntfs quick label=\"Data\"\nassign\n"
+        ));
+    }
+
+    #[test]
+    fn test_execute_layout_plan_with_fake_diskpart() {
+        use lr_core::command::{with_runner, FakeRunner};
+        use std::sync::Arc;
+
+        let disk = PhysicalDisk {
+            disk_number: 1,
+            size_bytes: 256 * 1024 * 1024 * 1024,
+            sector_size: 512,
+            model: String::new(),
+            partition_style: PartitionStyle::Unknown,
+            is_initialized: false,
+            partitions: Vec::new(),
+            unallocated_bytes: 256 * 1024 * 1024 * 1024,
+        };
+        let layouts = vec![
+            PartitionLayout {
+                size_gb: 100.0,
+                drive_letter: Some('C'),
+                ..Default::default()
+            },
+            PartitionLayout {
+                drive_letter: Some('D'),
+                label: "Data".to_string(),
+                ..Default::default()
+            },
+        ];
+        let plan = plan_recommended_layout(&disk, &BootMode::UEFI, &layouts).unwrap();
+        let roles: Vec<PartitionRole> = plan.partitions.iter().map(|p| p.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                PartitionRole::Esp,
+                PartitionRole::Msr,
+                PartitionRole::Windows,
+                PartitionRole::Data,
+                PartitionRole::Recovery
+            ]
+        );
+        assert_eq!(plan.partitions[2].length, 100 * 1024 * 1024 * 1024);
+
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || execute_layout_plan(1, &plan));
+        assert!(result.success);
+        assert_eq!(
+            result.created_partitions,
+            vec!["ESP", "MSR", "C:", "D:", "恢复分区"]
+        );
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 1);
+        let script = calls[0].script.as_deref().unwrap();
+        assert!(script.starts_with("select disk 1\nclean\nconvert gpt\n"));
+        assert!(script.contains("create partition efi size=300 offset=1024\n"));
+        assert!(script.contains("assign letter=D\n"));
+
+        // Legacy 启动：MBR + 系统保留分区
+        let plan = plan_recommended_layout(&disk, &BootMode::Legacy, &layouts[..1]).unwrap();
+        assert_eq!(plan.style, TableStyle::Mbr);
+        assert_eq!(plan.partitions[0].role, PartitionRole::System);
+    }
+
+    #[test]
+    fn test_preset_layouts_and_recovery_script() {
+        use lr_core::command::{with_runner, FakeRunner};
+        use lr_core::partition_preset::import_presets;
+        use std::sync::Arc;
+
+        let presets = import_presets(
+            r#"{"name": "Office", "partitions": [
+                {"size": {"gb": 0.5}, "is_esp": true, "file_system": "FAT32"},
+                {"size": {"percent": 25}, "drive_letter": "c", "label": "Windows"},
+                {"size": "fill", "drive_letter": "D"},
+                {"size": {"gb": 1}, "is_recovery": true}
+            ]}"#,
+        )
+        .unwrap();
+        let disk = PhysicalDisk {
+            disk_number: 3,
+            size_bytes: 400 * 1024 * 1024 * 1024,
+            sector_size: 512,
+            model: String::new(),
+            partition_style: PartitionStyle::Unknown,
+            is_initialized: false,
+            partitions: Vec::new(),
+            unallocated_bytes: 0,
+        };
+
+        assert_eq!(
+            preset_layouts(&presets[0], &disk, PartitionStyle::MBR).unwrap_err(),
+            PresetError::GptOnly(1)
+        );
+        let (style, layouts) = preset_layouts(&presets[0], &disk, PartitionStyle::GPT).unwrap();
+        assert_eq!(style, PartitionStyle::GPT);
+        assert_eq!(layouts[1].size_gb, 100.0);
+        assert_eq!(layouts[1].drive_letter, Some('C'));
+        assert!(layouts[3].is_recovery);
+
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || execute_quick_partition(3, style, &layouts));
+        assert!(result.success);
+        assert_eq!(result.created_partitions, vec!["ESP", "C:", "D:", "恢复分区"]);
+        let script = fake.calls()[0].script.clone().unwrap();
+        assert!(script.contains("create partition primary size=102400\n"));
+        // 最后一个分区有明确大小，不占用剩余空间
+        assert!(script.contains("create partition primary size=1024\n"));
+        assert!(script.ends_with(
+            "format fs=ntfs quick label=\"Recovery\"\nset id=de94bba4-06d1-4d40-a16a-bfd50179d6ac override\ngpt attributes=0x8000000000000001\n"
+        ));
+    }
+
+    #[test]
+    fn test_preset_last_partition_keeps_its_size() {
+        use lr_core::command::{with_runner, FakeRunner};
+        use lr_core::partition_preset::import_presets;
+        use std::sync::Arc;
+
+        let presets = import_presets(
+            r#"{"name": "Split", "partitions": [
+                {"size": {"gb": 100}, "drive_letter": "C"},
+                {"size": {"percent": 25}, "drive_letter": "D"}
+            ]}"#,
+        )
+        .unwrap();
+        let disk = PhysicalDisk {
+            disk_number: 4,
+            size_bytes: 400 * 1024 * 1024 * 1024,
+            sector_size: 512,
+            model: String::new(),
+            partition_style: PartitionStyle::Unknown,
+            is_initialized: false,
+            partitions: Vec::new(),
+            unallocated_bytes: 0,
+        };
+        let (style, layouts) = preset_layouts(&presets[0], &disk, PartitionStyle::GPT).unwrap();
+        assert!(layouts.iter().all(|l| !l.fill));
+
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || execute_quick_partition(4, style, &layouts));
+        assert!(result.success);
+        let script = fake.calls()[0].script.clone().unwrap();
+        assert!(script.contains("create partition primary size=102400\n"));
+        assert!(script.contains("create partition primary size=102400\nformat fs=ntfs quick label=\"新加卷\"\nassign letter=D\n"));
+        assert!(!script.contains("create partition primary\n"));
+    }
+
+    #[test]
+    fn test_execute_quick_partition_uses_exit_code() {
+        use lr_core::command::{with_runner, FakeReply, FakeRunner};
+        use std::sync::Arc;
+
+        let layouts = vec![PartitionLayout {
+            fill: true,
+            ..Default::default()
+        }];
+        // 成功输出里出现 "errors" 不算失败
+        let ok = Arc::new(FakeRunner::new().on(
+            "diskpart",
+            &[],
+            FakeReply::ok("DiskPart successfully formatted the volume. 0 errors found.\r\n"),
+        ));
+        let result = with_runner(ok, || execute_quick_partition(1, PartitionStyle::MBR, &layouts));
+        assert!(result.success);
+
+        // 德文 PE 的失败输出没有任何中英文关键字，靠退出码识别
+        let failed = Arc::new(FakeRunner::new().on(
+            "diskpart",
+            &[],
+            FakeReply {
+                code: 5,
+                stdout: b"Der Datentraeger ist schreibgeschuetzt.\r\n".to_vec(),
+                stderr: Vec::new(),
+            },
+        ));
+        let result =
+            with_runner(failed, || execute_quick_partition(1, PartitionStyle::MBR, &layouts));
+        assert!(!result.success);
+        assert!(result.message.contains("schreibgeschuetzt"));
+    }
+
+    #[test]
+    fn test_execute_quick_partition_rejects_bad_label() {
+        use lr_core::command::{with_runner, FakeRunner};
+        use std::sync::Arc;
+
+        let layouts = vec![PartitionLayout {
+            label: "Data\" assign letter=C".to_string(),
+            ..Default::default()
+        }];
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || {
+            execute_quick_partition(2, PartitionStyle::GPT, &layouts)
+        });
+
+        assert!(!result.success);
+        assert!(fake.calls().is_empty());
+    }
+
+    #[test]
+    fn test_query_shrink_max_parses_fake_output() {
+        use lr_core::command::{with_runner, FakeReply, FakeRunner};
+        use std::sync::Arc;
+
+        let fake = Arc::new(FakeRunner::new().on(
+            "diskpart",
+            &[],
+            FakeReply::ok("The maximum number of reclaimable bytes is:   20 GB (20480 MB)\r\n"),
+        ));
+        let max = with_runner(fake.clone(), || query_shrink_max('D')).unwrap();
+
+        assert_eq!(max, 20 * 1024);
+        assert_eq!(
+            fake.calls()[0].script.as_deref(),
+            Some("select volume D\nshrink querymax\n")
+        );
+    }
 }
@@ -13,6 +13,7 @@ use std::path::Path;
 use anyhow::{bail, Result};
 
 use crate::tr;
+use crate::utils::command;
 
 #[cfg(windows)]
 use windows::core::PCWSTR;
@@ -517,9 +518,7 @@ pub fn cleanup_component_store() -> Result<()> {
     log::info!("[SystemUtils] 触发组件存储清理任务...");
 
     // 方法1: 使用 schtasks.exe 触发已有任务
-    let output = Command::new("schtasks.exe")
-        .args(["/Run", "/TN", "\\Microsoft\\Windows\\Servicing\\StartComponentCleanup"])
-        .output();
+    let output = command::run("schtasks.exe", ["/Run", "/TN", "\\Microsoft\\Windows\\Servicing\\StartComponentCleanup"]);
 
     match output {
         Ok(result) if result.status.success() => {
@@ -699,13 +698,9 @@ fn get_dir_size(path: &Path) -> Result<u64> {
 /// 使用 SFC (System File Checker) 扫描
 #[cfg(windows)]
 pub fn check_system_files() -> Result<bool> {
-    use std::process::Command;
-
     log::info!("[SystemUtils] 运行系统文件检查...");
 
-    let output = Command::new("sfc")
-        .args(["/scannow"])
-        .output()?;
+    let output = command::run("sfc", ["/scannow"])?;
 
     let stdout = String::from_utf8_lossy(&output.stdout);
     
@@ -68,14 +68,41 @@ fn main() -> eframe::Result<()> {
 
     log::info!("已获得管理员权限");
 
-    // 命令行无人值守安装：--install --config <install.json> [--advanced <advanced.json>]
+    // 命令行无人值守安装：--install --config <install.json> [--advanced <advanced.json>] [--plan [plan.json]]
     // 放在确认管理员权限之后、GUI 初始化之前；不进 GUI，准备好后（默认）重启进 PE 完成安装。
     if args.contains(&"/INSTALL".to_string()) || args.contains(&"--install".to_string()) {
         let config = arg_value(&args, &["--config", "/CONFIG"]);
         let advanced = arg_value(&args, &["--advanced", "/ADVANCED"]);
+        // --plan [plan.json]：只输出执行计划，不执行
+        if let Some(pos) = args
+            .iter()
+            .position(|a| a.eq_ignore_ascii_case("--plan") || a.eq_ignore_ascii_case("/PLAN"))
+        {
+            let out = args
+                .get(pos + 1)
+                .filter(|a| !a.starts_with('-') && !a.starts_with('/'))
+                .cloned();
+            attach_parent_console();
+            return run_cli_plan_entry(config.as_deref(), advanced.as_deref(), out.as_deref());
+        }
         return run_cli_install_entry(config.as_deref(), advanced.as_deref());
     }
 
+    // 命令行无人值守分区：--partition --disk <N> --preset <名称> [--presets <presets.json>]
+    // 预设默认取 config.json 中保存的，指定 --presets 时从导出的预设文件中查找。
+    if args.contains(&"/PARTITION".to_string()) || args.contains(&"--partition".to_string()) {
+        attach_parent_console();
+        let disk = arg_value(&args, &["--disk", "/DISK"]);
+        let preset = arg_value(&args, &["--preset", "/PRESET"]);
+        let presets_file = arg_value(&args, &["--presets", "/PRESETS"]);
+        return run_cli_partition_entry(
+            &app_config,
+            disk.as_deref(),
+            preset.as_deref(),
+            presets_file.as_deref(),
+        );
+    }
+
     // 记录本机配置信息，便于用户反馈问题时开发者排查
     if app_config.log_enabled {
         log_machine_info();
@@ -366,6 +393,124 @@ fn run_cli_install_entry(config: Option<&str>, advanced: Option<&str>) -> eframe
     Ok(())
 }
 
+/// 发布版是 GUI 子系统程序，没有自己的控制台：命令行模式下挂到父进程（cmd / PowerShell）的
+/// 控制台，让 println!/eprintln! 的输出可见。从资源管理器启动时没有父控制台，失败直接忽略。
+fn attach_parent_console() {
+    #[cfg(windows)]
+    unsafe {
+        use windows::Win32::System::Console::{AttachConsole, ATTACH_PARENT_PROCESS};
+        let _ = AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
+/// 命令行模式失败时以退出码 1 结束，供无人值守脚本判断
+fn exit_with_failure() -> ! {
+    log::logger().flush();
+    std::process::exit(1);
+}
+
+/// `--partition` 入口：按预设对指定磁盘执行一键分区；失败时退出码为 1。
+fn run_cli_partition_entry(
+    app_config: &core::app_config::AppConfig,
+    disk: Option<&str>,
+    preset: Option<&str>,
+    presets_file: Option<&str>,
+) -> eframe::Result<()> {
+    const USAGE: &str =
+        "用法: LetRecovery.exe --partition --disk <磁盘编号> --preset <预设名称> [--presets <presets.json>]";
+    let (Some(disk), Some(name)) = (disk.and_then(|d| d.trim().parse::<u32>().ok()), preset) else {
+        log::error!("[CLI PARTITION] 缺少或无效的 --disk / --preset 参数");
+        log::error!("{}", USAGE);
+        eprintln!("{}", USAGE);
+        exit_with_failure();
+    };
+
+    let presets = match presets_file {
+        Some(path) => {
+            let imported = std::fs::read_to_string(path)
+                .map_err(|e| e.to_string())
+                .and_then(|json| {
+                    lr_core::partition_preset::import_presets(&json).map_err(|e| e.to_string())
+                });
+            match imported {
+                Ok(presets) => presets,
+                Err(e) => {
+                    log::error!("[CLI PARTITION] 读取预设文件 {} 失败: {}", path, e);
+                    eprintln!("{}", crate::tr!("读取预设文件 {} 失败: {}", path, e));
+                    exit_with_failure();
+                }
+            }
+        }
+        None => app_config.partition_presets.clone(),
+    };
+    let Some(preset) = lr_core::partition_preset::find_preset(&presets, name) else {
+        log::error!("[CLI PARTITION] 找不到预设: {}", name);
+        eprintln!("{}", crate::tr!("找不到预设: {}", name));
+        exit_with_failure();
+    };
+
+    // 预设未指定分区表类型时按本机启动模式推荐
+    let default_style = core::system_info::SystemInfo::collect()
+        .map(|info| core::quick_partition::get_recommended_partition_style(&info.boot_mode))
+        .unwrap_or(core::disk::PartitionStyle::GPT);
+
+    match core::quick_partition::run_preset_partition(disk, preset, default_style) {
+        Ok(result) if result.success => {
+            log::info!(
+                "[CLI PARTITION] 完成: {} ({})",
+                result.message,
+                result.created_partitions.join(", ")
+            );
+            println!("{}", result.message);
+        }
+        Ok(result) => {
+            log::error!("[CLI PARTITION] 失败: {}", result.message);
+            eprintln!("{}", result.message);
+            exit_with_failure();
+        }
+        Err(e) => {
+            log::error!("[CLI PARTITION] 失败: {:#}", e);
+            eprintln!("{:#}", e);
+            exit_with_failure();
+        }
+    }
+    Ok(())
+}
+
+/// `--install ... --plan` 入口：输出执行计划 JSON（写入文件或父进程控制台），不做任何改动；
+/// 失败时退出码为 1。
+fn run_cli_plan_entry(
+    config: Option<&str>,
+    advanced: Option<&str>,
+    out: Option<&str>,
+) -> eframe::Result<()> {
+    let Some(config) = config.filter(|c| !c.is_empty()) else {
+        log::error!("[CLI PLAN] 缺少 --config <install.json>");
+        eprintln!("用法: LetRecovery.exe --install --config <install.json> [--advanced <advanced.json>] --plan [plan.json]");
+        exit_with_failure();
+    };
+    let plan = match core::cli_install::plan_cli_install(config, advanced) {
+        Ok(p) => p,
+        Err(e) => {
+            log::error!("[CLI PLAN] 生成执行计划失败: {:#}", e);
+            eprintln!("{:#}", e);
+            exit_with_failure();
+        }
+    };
+    log::info!("[CLI PLAN] 执行计划:\n{}", plan.to_text());
+    match out {
+        Some(path) => {
+            if let Err(e) = std::fs::write(path, plan.to_json()) {
+                log::error!("[CLI PLAN] 写入执行计划失败 {}: {}", path, e);
+                eprintln!("{}: {}", path, e);
+                exit_with_failure();
+            }
+        }
+        None => println!("{}", plan.to_json()),
+    }
+    Ok(())
+}
+
 fn run_pe_install() -> eframe::Result<()> {
     use core::install_config::ConfigFileManager;
     
@@ -509,9 +654,7 @@ fn execute_pe_install(
     
     log::info!("[PE INSTALL] Step 1: 格式化分区");
     // 格式化目标分区
-    let output = utils::cmd::create_command("cmd")
-        .args(["/c", &format!("format {} /FS:NTFS /Q /Y", target_partition)])
-        .output()
+    let output = utils::command::run("cmd", ["/c", &format!("format {} /FS:NTFS /Q /Y", target_partition)])
         .context("执行格式化命令失败")?;
     
     if !output.status.success() {
@@ -592,7 +735,7 @@ fn execute_pe_install(
     
     // 生成无人值守配置
     if config.unattended {
-        let _ = generate_unattend_xml_pe(target_partition, &config.custom_username);
+        let _ = generate_unattend_xml_pe(target_partition, &config);
     }
     
     log::info!("[PE INSTALL] Step 6: 清理临时文件");
@@ -699,92 +842,28 @@ fn detect_uefi_mode() -> bool {
 }
 
 /// 生成无人值守XML (PE版本)
-fn generate_unattend_xml_pe(target_partition: &str, username: &str) -> anyhow::Result<()> {
+fn generate_unattend_xml_pe(
+    target_partition: &str,
+    config: &crate::core::install_config::InstallConfig,
+) -> anyhow::Result<()> {
     use crate::core::system_utils::{get_file_version, get_system_architecture};
+    use lr_core::unattend::{DeployUnattend, WindowsGeneration};
     use std::path::Path;
     
-    let username = if username.is_empty() { "MyPc" } else { username };
-    
     // 检测目标系统架构
     let arch = get_system_architecture(target_partition);
     let arch_str = arch.as_unattend_str();
     
-    // 通过 ntdll.dll 文件版本检测目标系统版本
+    // 通过 ntdll.dll 文件版本检测目标系统版本（决定 OOBE 可用项）
     let ntdll_path = Path::new(target_partition).join("Windows").join("System32").join("ntdll.dll");
-    let (is_win7, is_win8) = match get_file_version(&ntdll_path) {
-        Some((major, minor, _, _)) => {
-            let is_win7 = major == 6 && minor == 1;
-            let is_win8 = major == 6 && (minor == 2 || minor == 3);
-            (is_win7, is_win8)
-        }
-        None => (false, false)
-    };
-    
-    // 根据系统版本生成不同的OOBE配置
-    // Win7: 移除HideOEMRegistrationScreen（家庭版不支持）
-    let oobe_section = if is_win7 {
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>"#
-    } else if is_win8 {
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>"#
-    } else {
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-            </OOBE>"#
+    let generation = match get_file_version(&ntdll_path) {
+        Some((major, minor, _, _)) => WindowsGeneration::from_nt_version(major.into(), minor.into()),
+        None => WindowsGeneration::Win10,
     };
     
-    let xml_content = format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            {oobe}
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{user}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{user}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <Username>{user}</Username>
-            </AutoLogon>
-        </component>
-    </settings>
-</unattend>"#, arch = arch_str, oobe = oobe_section, user = username);
+    let mut spec = DeployUnattend::new(arch_str, generation, &config.custom_username);
+    spec.custom = config.unattend.clone();
+    let xml_content = spec.to_xml();
 
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
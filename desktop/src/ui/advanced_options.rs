@@ -2,8 +2,13 @@ use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
 use crate::core::hardware_info::HardwareInfo;
+use lr_core::unattend::{
+    encode_password, expand_computer_name, normalize_product_key, OobePage, UnattendCustomization,
+};
+use lr_core::offline_tweaks::TweakOptions;
 use crate::core::registry::OfflineRegistry;
 use crate::tr;
+use crate::utils::command;
 use std::path::PathBuf;
 
 /// 系统安装高级选项
@@ -45,6 +50,8 @@ pub struct AdvancedOptions {
     pub import_custom_drivers: bool,
     pub custom_drivers_path: String,
     pub import_storage_controller_drivers: bool,
+    /// 自动导入驱动时只导入与本机硬件匹配的驱动（按硬件 ID 为每个设备选一个 INF）
+    pub driver_match_devices: bool,
     pub import_registry_file: bool,
     pub registry_file_path: String,
     pub import_custom_files: bool,
@@ -53,6 +60,33 @@ pub struct AdvancedOptions {
     // 用户设置
     pub custom_username: bool,
     pub username: String,
+
+    // 无人值守定制（仅对内置生成的 unattend.xml 生效；选了自定义应答文件时忽略）。空=不设置
+    /// 输入法（如 `zh-CN` 或 `0804:00000804`）
+    pub unattend_input_locale: String,
+    /// 非 Unicode 程序区域（如 `zh-CN`）
+    pub unattend_system_locale: String,
+    /// 界面语言（镜像须已含该语言包）
+    pub unattend_ui_language: String,
+    /// 用户区域（日期/货币格式）
+    pub unattend_user_locale: String,
+    /// Windows 时区 ID（如 `China Standard Time`）
+    pub unattend_time_zone: String,
+    /// 计算机名模板，支持 `{serial}`（设备序列号）与 `{rand}`（4 位随机）；空=随机
+    pub unattend_computer_name: String,
+    /// 本地账户密码（明文，仅从 advanced.json / 界面读入，不写回 config.json；
+    /// 写入安装配置与应答文件时均为编码形式）
+    #[serde(skip_serializing)]
+    pub unattend_password: String,
+    /// 产品密钥（XXXXX-XXXXX-XXXXX-XXXXX-XXXXX）
+    pub unattend_product_key: String,
+    /// 注册组织
+    pub unattend_organization: String,
+    /// 注册所有者
+    pub unattend_owner: String,
+    /// 需要【显示】的 OOBE 页：eula / local_account / online_account / wireless /
+    /// oem_registration / privacy；未列出的一律隐藏
+    pub unattend_show_oobe_pages: Vec<String>,
     
     // 系统盘设置
     pub custom_volume_label: bool,
@@ -82,7 +116,7 @@ pub struct AdvancedOptions {
 
 impl AdvancedOptions {
     /// 脚本目录名称（统一路径）
-    const SCRIPTS_DIR: &'static str = "LetRecovery_Scripts";
+    pub(crate) const SCRIPTS_DIR: &'static str = "LetRecovery_Scripts";
 
     /// 获取程序运行目录（exe 所在目录）
     fn get_program_dir() -> Option<PathBuf> {
@@ -244,21 +278,17 @@ log=0
     
     /// 查找 EFI 系统分区
     fn find_efi_partition() -> anyhow::Result<String> {
-        use std::process::Command;
-        
         // 使用 PowerShell 查找 EFI 分区
-        let output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                r#"
+        let output = command::run("powershell", [
+            "-NoProfile",
+            "-Command",
+            r#"
                 $efiPart = Get-Partition | Where-Object { $_.GptType -eq '{c12a7328-f81f-11d2-ba4b-00a0c93ec93b}' } | Select-Object -First 1
                 if ($efiPart) {
                     $efiPart.DiskNumber.ToString() + ':' + $efiPart.PartitionNumber.ToString()
                 }
-                "#
-            ])
-            .output()?;
+            "#
+        ])?;
         
         let result = String::from_utf8_lossy(&output.stdout).trim().to_string();
         
@@ -271,8 +301,6 @@ log=0
     
     /// 确保 EFI 分区已挂载，返回挂载点
     fn ensure_efi_mounted(efi_partition: &str) -> anyhow::Result<String> {
-        use std::process::Command;
-        
         // 解析磁盘号和分区号
         let parts: Vec<&str> = efi_partition.split(':').collect();
         if parts.len() != 2 {
@@ -283,18 +311,16 @@ log=0
         let part_num = parts[1];
         
         // 检查是否已经有挂载点
-        let check_output = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                &format!(r#"
+        let check_output = command::run("powershell", [
+            "-NoProfile",
+            "-Command",
+            &format!(r#"
                 $vol = Get-Partition -DiskNumber {} -PartitionNumber {} | Get-Volume -ErrorAction SilentlyContinue
                 if ($vol -and $vol.DriveLetter) {{
                     $vol.DriveLetter + ':'
                 }}
-                "#, disk_num, part_num)
-            ])
-            .output()?;
+            "#, disk_num, part_num)
+        ])?;
         
         let existing_mount = String::from_utf8_lossy(&check_output.stdout).trim().to_string();
         
@@ -303,17 +329,15 @@ log=0
         }
         
         // 查找可用盘符
-        let find_letter = Command::new("powershell")
-            .args([
-                "-NoProfile",
-                "-Command",
-                r#"
+        let find_letter = command::run("powershell", [
+            "-NoProfile",
+            "-Command",
+            r#"
                 $used = (Get-Volume).DriveLetter
                 $available = 90..65 | ForEach-Object { [char]$_ } | Where-Object { $_ -notin $used }
                 if ($available) { $available[0] }
-                "#
-            ])
-            .output()?;
+            "#
+        ])?;
         
         let letter = String::from_utf8_lossy(&find_letter.stdout).trim().to_string();
         
@@ -322,12 +346,10 @@ log=0
         }
         
         // 使用 mountvol 挂载 EFI 分区
-        let mount_result = Command::new("cmd")
-            .args([
-                "/c",
-                &format!("mountvol {}:\\ /s", letter)
-            ])
-            .output();
+        let mount_result = command::run("cmd", [
+            "/c",
+            &format!("mountvol {}:\\ /s", letter)
+        ]);
         
         match mount_result {
             Ok(output) if output.status.success() => {
@@ -343,9 +365,7 @@ log=0
                 let temp_script = std::env::temp_dir().join("efi_mount.txt");
                 std::fs::write(&temp_script, &diskpart_script)?;
                 
-                let diskpart_result = Command::new("diskpart")
-                    .args(["/s", &temp_script.to_string_lossy()])
-                    .output();
+                let diskpart_result = command::run("diskpart", ["/s", &temp_script.to_string_lossy()]);
                 
                 let _ = std::fs::remove_file(&temp_script);
                 
@@ -383,15 +403,7 @@ log=0
     /// 虚拟机/无无线网卡/未连接 WiFi 时返回 false（用于隐藏“迁移 WiFi”选项）。
     #[cfg(windows)]
     fn system_has_wifi() -> bool {
-        use std::os::windows::process::CommandExt;
-        use std::process::Command;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-
-        let out = match Command::new("netsh")
-            .args(["wlan", "show", "interfaces"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-        {
+        let out = match command::run("netsh", ["wlan", "show", "interfaces"]) {
             Ok(o) => o,
             Err(_) => return false,
         };
@@ -421,10 +433,6 @@ log=0
     }
 
     fn read_current_wifi() -> Option<(String, String)> {
-        use std::os::windows::process::CommandExt;
-        use std::process::Command;
-        const CREATE_NO_WINDOW: u32 = 0x08000000;
-
         let decode = |b: &[u8]| -> String {
             match String::from_utf8(b.to_vec()) {
                 Ok(s) if s.chars().filter(|&c| c == '\u{FFFD}').count() < 3 => s,
@@ -436,11 +444,7 @@ log=0
         };
 
         // 1) 当前连接的 SSID（排除 BSSID 行）
-        let out = Command::new("netsh")
-            .args(["wlan", "show", "interfaces"])
-            .creation_flags(CREATE_NO_WINDOW)
-            .output()
-            .ok()?;
+        let out = command::run("netsh", ["wlan", "show", "interfaces"]).ok()?;
         let text = decode(&out.stdout);
         let mut ssid = String::new();
         for line in text.lines() {
@@ -465,13 +469,17 @@ log=0
         // 2) 导出该 profile（key=clear 含明文密钥）到临时目录，读出唯一 xml
         let tmp = std::env::temp_dir().join(format!("lr_wifi_{}", std::process::id()));
         let _ = std::fs::create_dir_all(&tmp);
-        let _ = Command::new("netsh")
-            .args(["wlan", "export", "profile"])
-            .arg(format!("name={}", ssid))
-            .arg("key=clear")
-            .arg(format!("folder={}", tmp.display()))
-            .creation_flags(CREATE_NO_WINDOW)
-            .output();
+        let _ = command::run(
+            "netsh",
+            [
+                "wlan".to_string(),
+                "export".to_string(),
+                "profile".to_string(),
+                format!("name={}", ssid),
+                "key=clear".to_string(),
+                format!("folder={}", tmp.display()),
+            ],
+        );
         let xml = std::fs::read_dir(&tmp)
             .ok()?
             .filter_map(|e| e.ok())
@@ -486,6 +494,127 @@ log=0
         xml.map(|x| (ssid, x))
     }
 
+    /// 内置无人值守的定制项。计算机名模板在此用本机序列号展开；产品密钥格式不对时忽略并记日志。
+    pub fn unattend_customization(&self) -> UnattendCustomization {
+        let pattern = self.unattend_computer_name.trim();
+        let computer_name = if pattern.is_empty() {
+            String::new()
+        } else {
+            let serial = if pattern.contains("{serial}") {
+                HardwareInfo::get_system_serial_number()
+            } else {
+                String::new()
+            };
+            expand_computer_name(pattern, &serial)
+        };
+        let product_key = match self.unattend_product_key.trim() {
+            "" => String::new(),
+            k => normalize_product_key(k).unwrap_or_else(|e| {
+                log::warn!("[UNATTEND] {}，已忽略", e);
+                String::new()
+            }),
+        };
+        let encoded_password = if self.unattend_password.is_empty() {
+            String::new()
+        } else {
+            encode_password(&self.unattend_password, "Password")
+        };
+        UnattendCustomization {
+            input_locale: self.unattend_input_locale.trim().to_string(),
+            system_locale: self.unattend_system_locale.trim().to_string(),
+            ui_language: self.unattend_ui_language.trim().to_string(),
+            user_locale: self.unattend_user_locale.trim().to_string(),
+            time_zone: self.unattend_time_zone.trim().to_string(),
+            computer_name,
+            encoded_password,
+            product_key,
+            registered_organization: self.unattend_organization.trim().to_string(),
+            registered_owner: self.unattend_owner.trim().to_string(),
+            show_oobe_pages: self
+                .unattend_show_oobe_pages
+                .iter()
+                .filter_map(|p| OobePage::parse(p))
+                .collect(),
+        }
+    }
+
+    /// 「无人值守定制」一栏（依赖无人值守；目标分区已有应答文件时整栏禁用）。
+    fn show_unattend_customization_ui(&mut self, ui: &mut egui::Ui, unattend_disabled: bool) {
+        egui::CollapsingHeader::new(tr!("无人值守定制（区域/时区/计算机名/密码/密钥）"))
+            .id_salt("unattend_customization")
+            .show(ui, |ui| {
+                ui.add_enabled_ui(!unattend_disabled, |ui| {
+                    egui::Grid::new("unattend_customization_grid")
+                        .num_columns(2)
+                        .spacing([10.0, 4.0])
+                        .show(ui, |ui| {
+                            fn row(ui: &mut egui::Ui, label: String, value: &mut String, hint: &str) {
+                                ui.label(label);
+                                ui.add(
+                                    egui::TextEdit::singleline(value)
+                                        .desired_width(220.0)
+                                        .hint_text(hint),
+                                );
+                                ui.end_row();
+                            }
+                            row(ui, tr!("输入法"), &mut self.unattend_input_locale, "zh-CN");
+                            row(ui, tr!("系统区域"), &mut self.unattend_system_locale, "zh-CN");
+                            row(ui, tr!("界面语言"), &mut self.unattend_ui_language, "zh-CN");
+                            row(ui, tr!("用户区域"), &mut self.unattend_user_locale, "zh-CN");
+                            row(ui, tr!("时区"), &mut self.unattend_time_zone, "China Standard Time");
+                            row(ui, tr!("计算机名"), &mut self.unattend_computer_name, "PC-{serial}");
+                            ui.label(tr!("账户密码"));
+                            ui.add(
+                                egui::TextEdit::singleline(&mut self.unattend_password)
+                                    .password(true)
+                                    .desired_width(220.0),
+                            );
+                            ui.end_row();
+                            row(
+                                ui,
+                                tr!("产品密钥"),
+                                &mut self.unattend_product_key,
+                                "XXXXX-XXXXX-XXXXX-XXXXX-XXXXX",
+                            );
+                            row(ui, tr!("注册组织"), &mut self.unattend_organization, "");
+                            row(ui, tr!("注册所有者"), &mut self.unattend_owner, "");
+                        });
+                    if !self.unattend_product_key.trim().is_empty() {
+                        if let Err(e) = normalize_product_key(&self.unattend_product_key) {
+                            ui.colored_label(egui::Color32::from_rgb(255, 165, 0), e);
+                        }
+                    }
+                    ui.label(
+                        egui::RichText::new(tr!("计算机名可用 {serial}（设备序列号）、{rand}（随机 4 位）；留空的项不设置"))
+                            .small()
+                            .color(egui::Color32::GRAY),
+                    );
+
+                    ui.add_space(5.0);
+                    ui.label(tr!("安装过程中显示以下 OOBE 页面（未勾选的将被跳过）："));
+                    ui.horizontal_wrapped(|ui| {
+                        for (page, label) in [
+                            (OobePage::Eula, tr!("许可协议")),
+                            (OobePage::LocalAccount, tr!("本地账户")),
+                            (OobePage::OnlineAccount, tr!("微软账户")),
+                            (OobePage::Wireless, tr!("无线网络")),
+                            (OobePage::OemRegistration, tr!("OEM 注册")),
+                            (OobePage::Privacy, tr!("隐私设置")),
+                        ] {
+                            let name = page.as_str();
+                            let mut shown = self.unattend_show_oobe_pages.iter().any(|p| p == name);
+                            if ui.checkbox(&mut shown, label).changed() {
+                                self.unattend_show_oobe_pages.retain(|p| p != name);
+                                if shown {
+                                    self.unattend_show_oobe_pages.push(name.to_string());
+                                }
+                            }
+                        }
+                    });
+                });
+            });
+    }
+
     pub fn apply_to_system(&self, target_partition: &str, is_xp: bool) -> anyhow::Result<()> {
         log::info!("[ADVANCED] 开始应用高级选项到: {} (is_xp={})", target_partition, is_xp);
 
@@ -508,45 +637,8 @@ log=0
 
         // ============ 系统优化选项 ============
 
-        // 1. 移除快捷方式小箭头
-        if self.remove_shortcut_arrow {
-            self.apply_remove_shortcut_arrow();
-        }
-
-        // 2. Win11恢复经典右键菜单
-        if self.restore_classic_context_menu {
-            self.apply_restore_classic_context_menu(default_loaded);
-        }
-
-        // 3. OOBE绕过强制联网
-        if self.bypass_nro {
-            self.apply_bypass_nro();
-        }
-
-        // 4. 禁用Windows更新
-        if self.disable_windows_update {
-            self.apply_disable_windows_update();
-        }
-
-        // 5. 禁用Windows安全中心/Defender
-        if self.disable_windows_defender {
-            self.apply_disable_windows_defender();
-        }
-
-        // 6. 禁用系统保留空间
-        if self.disable_reserved_storage {
-            self.apply_disable_reserved_storage();
-        }
-
-        // 7. 禁用UAC
-        if self.disable_uac {
-            self.apply_disable_uac();
-        }
-
-        // 8. 禁用自动设备加密 (BitLocker)
-        if self.disable_device_encryption {
-            self.apply_disable_device_encryption();
-        }
+        // 1-8. 纯注册表类优化（改动表见 lr_core::offline_tweaks）
+        self.tweak_options().apply(default_loaded);
 
         // 9. 删除预装UWP应用 - 通过删除 AppxProvisioned 配置
         if self.remove_uwp_apps {
@@ -649,159 +741,18 @@ log=0
 
     // ============ apply_to_system 各优化块的私有 helper（行为与内联版本逐字等价）============
 
-    /// 1. 移除快捷方式小箭头
-    fn apply_remove_shortcut_arrow(&self) {
-        log::info!("[ADVANCED] 移除快捷方式小箭头");
-        let _ = OfflineRegistry::set_string(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Shell Icons",
-            "29",
-            "%systemroot%\\system32\\imageres.dll,197",
-        );
-    }
-
-    /// 2. Win11恢复经典右键菜单
-    fn apply_restore_classic_context_menu(&self, default_loaded: bool) {
-        log::info!("[ADVANCED] 恢复经典右键菜单");
-        // 在 DEFAULT hive 中设置（影响所有新用户）
-        if default_loaded {
-            // 创建空的 InprocServer32 键，这会禁用新式右键菜单
-            let _ = OfflineRegistry::create_key(
-                "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
-            );
-            // 设置默认值为空字符串
-            let _ = OfflineRegistry::set_string(
-                "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
-                "",
-                "",
-            );
+    /// 注册表类优化项开关（供 apply_to_system 与执行计划共用）
+    pub fn tweak_options(&self) -> TweakOptions {
+        TweakOptions {
+            remove_shortcut_arrow: self.remove_shortcut_arrow,
+            restore_classic_context_menu: self.restore_classic_context_menu,
+            bypass_nro: self.bypass_nro,
+            disable_windows_update: self.disable_windows_update,
+            disable_windows_defender: self.disable_windows_defender,
+            disable_reserved_storage: self.disable_reserved_storage,
+            disable_uac: self.disable_uac,
+            disable_device_encryption: self.disable_device_encryption,
         }
-        // 同时在 SOFTWARE 中设置（系统级）
-        let _ = OfflineRegistry::create_key(
-            "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
-        );
-        let _ = OfflineRegistry::set_string(
-            "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
-            "",
-            "",
-        );
-    }
-
-    /// 3. OOBE绕过强制联网
-    fn apply_bypass_nro(&self) {
-        log::info!("[ADVANCED] 设置OOBE绕过联网");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE",
-            "BypassNRO",
-            1,
-        );
-    }
-
-    /// 4. 禁用Windows更新
-    fn apply_disable_windows_update(&self) {
-        log::info!("[ADVANCED] 禁用Windows更新服务");
-        // 禁用 Windows Update 服务
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\wuauserv",
-            "Start",
-            4, // 4 = Disabled
-        );
-        // 禁用 Update Orchestrator Service
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\UsoSvc",
-            "Start",
-            4,
-        );
-        // 设置策略禁用自动更新
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows\\WindowsUpdate\\AU",
-            "NoAutoUpdate",
-            1,
-        );
-    }
-
-    /// 5. 禁用Windows安全中心/Defender
-    fn apply_disable_windows_defender(&self) {
-        log::info!("[ADVANCED] 禁用Windows Defender");
-        // 禁用实时保护
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender",
-            "DisableAntiSpyware",
-            1,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender\\Real-Time Protection",
-            "DisableRealtimeMonitoring",
-            1,
-        );
-        // 禁用服务
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\WinDefend",
-            "Start",
-            4, // Disabled
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\WdNisSvc",
-            "Start",
-            4,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\SecurityHealthService",
-            "Start",
-            4,
-        );
-    }
-
-    /// 6. 禁用系统保留空间
-    fn apply_disable_reserved_storage(&self) {
-        log::info!("[ADVANCED] 禁用系统保留空间");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
-            "ShippedWithReserves",
-            0,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
-            "PassedPolicy",
-            0,
-        );
-    }
-
-    /// 7. 禁用UAC
-    fn apply_disable_uac(&self) {
-        log::info!("[ADVANCED] 禁用UAC");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
-            "EnableLUA",
-            0,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
-            "ConsentPromptBehaviorAdmin",
-            0,
-        );
-    }
-
-    /// 8. 禁用自动设备加密 (BitLocker)
-    fn apply_disable_device_encryption(&self) {
-        log::info!("[ADVANCED] 禁用自动设备加密");
-        // 禁用 BitLocker 自动加密
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Control\\BitLocker",
-            "PreventDeviceEncryption",
-            1,
-        );
-        // 禁用 MBAM (Microsoft BitLocker Administration and Monitoring)
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\FVE",
-            "OSRecovery",
-            0,
-        );
-        // 禁用设备加密
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\BDESVC",
-            "Start",
-            4, // Disabled
-        );
     }
 
     /// 9. 删除预装UWP应用 - 通过删除 AppxProvisioned 配置
@@ -1999,6 +1950,14 @@ Write-Host "UWP应用清理完成"
                 .small(),
             );
 
+            ui.checkbox(
+                &mut self.driver_match_devices,
+                tr!("仅导入与本机硬件匹配的驱动"),
+            )
+            .on_hover_text(tr!(
+                "自动导入驱动时按硬件 ID 为每个设备挑选最合适的一个驱动（排名、日期、版本），\n不再注入驱动目录里的全部 INF；枚举设备失败时仍导入全部驱动。"
+            ));
+
             ui.horizontal(|ui| {
                 ui.checkbox(&mut self.import_registry_file, tr!("导入注册表文件"));
                 if self.import_registry_file {
@@ -2062,6 +2021,8 @@ Write-Host "UWP应用清理完成"
                 }
             });
 
+            self.show_unattend_customization_ui(ui, unattend_disabled);
+
             ui.add_space(15.0);
             ui.heading(tr!("系统盘设置"));
             ui.separator();
@@ -0,0 +1,321 @@
+//! 执行计划确认页
+//!
+//! 点击「开始安装 / 开始备份」后先按当前选项生成执行计划（只读，见 `lr_core::plan`），
+//! 列出将要格式化的分区、写入的引导、修改的注册表键等，用户确认后才真正开始。
+
+use egui;
+
+use lr_core::plan::{
+    plan_backup, plan_install, ActionKind, BackupPlanInput, CaptureFormat, DriverSource,
+    ExecutionPlan, InstallPlanInput, PePreparation, PlannedAction,
+};
+
+use crate::app::{App, BackupFormat, BootModeSelection, DriverAction};
+use crate::core::disk::{DiskManager, PartitionStyle};
+use crate::core::install_config::ConfigFileManager;
+use crate::tr;
+
+/// 执行计划确认后要继续的流程
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlanConfirm {
+    Install,
+    Backup,
+}
+
+impl App {
+    /// 按安装页当前选项生成安装计划（与 `initialize_install_state` 的模式判断一致）
+    pub fn build_install_plan_for_gui(&self) -> Option<ExecutionPlan> {
+        let partition = self
+            .selected_partition
+            .and_then(|i| self.partitions.get(i))?;
+        let target = partition.letter.clone();
+        let via_pe = !self.is_pe_environment() && partition.is_system_partition;
+        let volume = self.selected_volume.and_then(|i| self.image_volumes.get(i));
+        let advanced = &self.advanced_options;
+        let image_path = self
+            .xp_i386_source
+            .clone()
+            .unwrap_or_else(|| self.local_image_path.clone());
+        let is_gho = self.local_image_path.to_lowercase().ends_with(".gho");
+
+        let mut input = InstallPlanInput {
+            target_partition: target.clone(),
+            image_path: image_path.clone(),
+            volume_index: volume.map(|v| v.index).unwrap_or(1),
+            image_name: volume.map(|v| v.name.clone()).unwrap_or_default(),
+            is_gho,
+            is_xp: self.xp_i386_source.is_some()
+                || volume.map(|v| v.major_version == Some(5)).unwrap_or(false),
+            volume_label: if advanced.custom_volume_label {
+                advanced.volume_label.clone()
+            } else {
+                String::new()
+            },
+            run_diskpart_scripts: self.app_config.enable_advanced_options
+                && self.run_diskpart_scripts,
+            diskpart_dir: Some(crate::utils::path::get_diskpart_scripts_dir()),
+            keep_partition: !self.format_partition,
+            skip_boot_repair: !self.repair_boot,
+            driver_match_devices: advanced.driver_match_devices,
+            tweaks: advanced.tweak_options(),
+            remove_uwp_apps: advanced.remove_uwp_apps,
+            import_storage_controller_drivers: advanced.import_storage_controller_drivers,
+            storage_controller_dir: Some(
+                crate::utils::path::get_drivers_dir().join("storage_controller"),
+            ),
+            unattended: self.unattended_install,
+            custom_unattend: if self.unattended_install {
+                self.custom_unattend_path.clone()
+            } else {
+                String::new()
+            },
+            custom_unattend_merge: self.unattended_install
+                && self.custom_unattend_merge
+                && self.custom_unattend_is_xml(),
+            custom_username: if advanced.custom_username {
+                advanced.username.clone()
+            } else {
+                String::new()
+            },
+            auto_reboot: self.auto_reboot,
+            ..Default::default()
+        };
+
+        if via_pe {
+            // 只在现有分区里找；找不到时计划里记一步「缩小分区」，确认后安装流程才会真的创建
+            let image_size = std::fs::metadata(&image_path).map(|m| m.len()).unwrap_or(0);
+            let existing = DiskManager::find_existing_data_partition(&target, image_size);
+            let data_partition = existing.clone().unwrap_or_else(|| tr!("（新建临时分区）"));
+            let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+            let file_name = std::path::Path::new(&image_path)
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            input.image_path = format!("{}\\{}", data_dir, file_name);
+            input.diskpart_dir = Some(std::path::Path::new(&data_dir).join("diskpart"));
+            input.storage_controller_dir = None;
+            input.drivers = if self.driver_action == DriverAction::AutoImport {
+                DriverSource::Dir(std::path::Path::new(&data_dir).join("drivers"))
+            } else {
+                DriverSource::None
+            };
+            input.uefi = None;
+            // PE 中安装完成后总是重启
+            input.auto_reboot = true;
+            input.data_dir = data_dir;
+            input.via_pe = Some(PePreparation {
+                data_partition,
+                shrink_from: existing.is_none().then(|| target.clone()),
+                pe_name: self
+                    .selected_pe_for_install
+                    .and_then(|i| self.config.as_ref().and_then(|c| c.pe_list.get(i)))
+                    .map(|pe| pe.display_name.clone())
+                    .unwrap_or_else(|| "LetRecovery PE".to_string()),
+                copy_image_from: Some(image_path),
+            });
+        } else {
+            input.direct_install = true;
+            input.drivers = if self.driver_action == DriverAction::AutoImport {
+                DriverSource::ExportCurrent
+            } else {
+                DriverSource::None
+            };
+            input.uefi = Some(match self.selected_boot_mode {
+                BootModeSelection::UEFI => true,
+                BootModeSelection::Legacy => false,
+                BootModeSelection::Auto => partition.partition_style == PartitionStyle::GPT,
+            });
+        }
+
+        let mut plan = plan_install(&input);
+        if via_pe {
+            if matches!(
+                self.driver_action,
+                DriverAction::SaveOnly | DriverAction::AutoImport
+            ) {
+                // 实际流程在写入 PE 启动项之后、复制镜像之前导出驱动
+                let at = plan
+                    .actions
+                    .iter()
+                    .take_while(|a| a.kind == ActionKind::Partition)
+                    .count();
+                plan.actions.insert(
+                    at,
+                    PlannedAction {
+                        step: "准备 PE 环境".to_string(),
+                        kind: ActionKind::Drivers,
+                        target: tr!("当前系统"),
+                        detail: tr!("导出第三方驱动到数据目录"),
+                    },
+                );
+            }
+            if !self.auto_reboot {
+                plan.warn(tr!("未启用自动重启，需手动重启进入 PE 才会继续执行"));
+            }
+        }
+        if partition.has_windows && !self.format_partition {
+            plan.warn(tr!("目标分区已有系统，建议勾选\"格式化分区\""));
+        }
+        Some(plan)
+    }
+
+    /// 按备份页当前选项生成备份计划（与 `continue_backup_after_bitlocker` 的模式判断一致）
+    pub fn build_backup_plan_for_gui(&self) -> Option<ExecutionPlan> {
+        let partition = self
+            .backup_source_partition
+            .and_then(|i| self.partitions.get(i))?;
+        let is_pe = self.is_pe_environment();
+        let via_pe = !is_pe && partition.is_system_partition;
+        let input = BackupPlanInput {
+            source_partition: partition.letter.clone(),
+            save_path: self.backup_save_path.clone(),
+            name: self.backup_name.clone(),
+            format: match self.backup_format {
+                BackupFormat::Wim => CaptureFormat::Wim,
+                BackupFormat::Esd => CaptureFormat::Esd,
+                BackupFormat::Swm => CaptureFormat::Swm,
+                BackupFormat::Gho => CaptureFormat::Gho,
+            },
+            incremental: self.backup_incremental,
+            swm_split_mb: self.backup_swm_split_size,
+            auto_reboot: via_pe,
+            runs_in_pe: false,
+            via_pe: via_pe.then(|| PePreparation {
+                data_partition: crate::ui::system_backup::find_backup_data_partition(
+                    &partition.letter,
+                ),
+                pe_name: self
+                    .selected_pe_for_backup
+                    .and_then(|i| self.config.as_ref().and_then(|c| c.pe_list.get(i)))
+                    .map(|pe| pe.display_name.clone())
+                    .unwrap_or_else(|| "LetRecovery PE".to_string()),
+                ..Default::default()
+            }),
+        };
+        Some(plan_backup(&input))
+    }
+
+    /// 生成安装计划并弹出确认页
+    pub fn request_install_plan(&mut self) {
+        self.execution_plan = self.build_install_plan_for_gui();
+        if self.execution_plan.is_some() {
+            self.execution_plan_for = Some(PlanConfirm::Install);
+            self.show_execution_plan_dialog = true;
+        }
+    }
+
+    /// 生成备份计划并弹出确认页
+    pub fn request_backup_plan(&mut self) {
+        self.execution_plan = self.build_backup_plan_for_gui();
+        if self.execution_plan.is_some() {
+            self.execution_plan_for = Some(PlanConfirm::Backup);
+            self.show_execution_plan_dialog = true;
+        }
+    }
+
+    pub fn render_execution_plan_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_execution_plan_dialog {
+            return;
+        }
+        let Some(plan) = self.execution_plan.clone() else {
+            self.show_execution_plan_dialog = false;
+            return;
+        };
+
+        let mut confirm = false;
+        let mut should_close = false;
+        let title = match self.execution_plan_for {
+            Some(PlanConfirm::Backup) => tr!("备份执行计划"),
+            _ => tr!("安装执行计划"),
+        };
+
+        egui::Window::new(title)
+            .collapsible(false)
+            .resizable(true)
+            .anchor(egui::Align2::CENTER_CENTER, [0.0, 0.0])
+            .default_width(760.0)
+            .default_height(480.0)
+            .show(ui.ctx(), |ui| {
+                ui.label(tr!(
+                    "以下操作将按顺序执行，标红的操作会抹掉目标上的数据。请确认无误后再继续。"
+                ));
+                ui.add_space(6.0);
+
+                egui::ScrollArea::both()
+                    .max_height(320.0)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        egui::Grid::new("execution_plan_grid")
+                            .striped(true)
+                            .num_columns(4)
+                            .show(ui, |ui| {
+                                ui.strong("#");
+                                ui.strong(tr!("步骤"));
+                                ui.strong(tr!("对象"));
+                                ui.strong(tr!("说明"));
+                                ui.end_row();
+
+                                for (i, action) in plan.actions.iter().enumerate() {
+                                    ui.label((i + 1).to_string());
+                                    ui.label(tr!(&action.step));
+                                    if action.kind.is_destructive() {
+                                        ui.colored_label(
+                                            egui::Color32::from_rgb(220, 50, 47),
+                                            &action.target,
+                                        );
+                                    } else {
+                                        ui.label(&action.target);
+                                    }
+                                    ui.label(&action.detail);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                if !plan.warnings.is_empty() {
+                    ui.add_space(6.0);
+                    for warning in &plan.warnings {
+                        ui.colored_label(egui::Color32::from_rgb(255, 165, 0), warning);
+                    }
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("确认执行")).clicked() {
+                        confirm = true;
+                    }
+                    if ui.button(tr!("导出 JSON")).clicked() {
+                        if let Some(path) = rfd::FileDialog::new()
+                            .add_filter("JSON", &["json"])
+                            .set_file_name(format!("LetRecovery_Plan_{}.json", plan.workflow))
+                            .save_file()
+                        {
+                            if let Err(e) = std::fs::write(&path, plan.to_json()) {
+                                log::error!("[PLAN] 导出执行计划失败: {}", e);
+                            } else {
+                                log::info!("[PLAN] 执行计划已导出至: {}", path.display());
+                            }
+                        }
+                    }
+                    if ui.button(tr!("取消")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if confirm || should_close {
+            self.show_execution_plan_dialog = false;
+            self.execution_plan = None;
+        }
+        if confirm {
+            log::info!("[PLAN] 用户确认执行计划:\n{}", plan.to_text());
+            match self.execution_plan_for.take() {
+                Some(PlanConfirm::Install) => self.begin_installation(),
+                Some(PlanConfirm::Backup) => self.start_backup(),
+                None => {}
+            }
+        } else if should_close {
+            self.execution_plan_for = None;
+        }
+    }
+}
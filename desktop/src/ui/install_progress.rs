@@ -529,7 +529,7 @@ impl App {
                 log::info!("[INSTALL STEP 4] 开始导入驱动 (AutoImport模式)");
                 send_step(&progress_tx, 4, &tr!("导入驱动"), 30);
                 
-                match import_drivers(&target_partition, &driver_backup_str) {
+                match import_drivers(&target_partition, &driver_backup_str, advanced_options.driver_match_devices) {
                     Ok(_) => {
                         log::info!("[INSTALL STEP 4] 驱动导入成功");
                         let _ = std::fs::remove_dir_all(&driver_backup_path);
@@ -923,6 +923,7 @@ impl App {
                 disable_device_encryption: advanced_options.disable_device_encryption,
                 remove_uwp_apps: advanced_options.remove_uwp_apps,
                 import_storage_controller_drivers: advanced_options.import_storage_controller_drivers,
+                driver_match_devices: advanced_options.driver_match_devices,
                 custom_username: if advanced_options.custom_username {
                     advanced_options.username.clone()
                 } else {
@@ -934,6 +935,7 @@ impl App {
                     String::new()
                 },
                 custom_unattend_path: options.custom_unattend_path.clone(),
+                custom_unattend_merge: options.custom_unattend_merge,
                 win7_uefi_patch: advanced_options.win7_uefi_patch,
                 win7_inject_usb3_driver: advanced_options.win7_inject_usb3_driver,
                 win7_inject_nvme_driver: advanced_options.win7_inject_nvme_driver,
@@ -944,6 +946,8 @@ impl App {
                 xp_inject_usb3_driver: advanced_options.xp_inject_usb3_driver,
                 xp_inject_nvme_driver: advanced_options.xp_inject_nvme_driver,
                 run_diskpart_scripts: options.run_diskpart_scripts,
+                unattend: advanced_options.unattend_customization(),
+                target_identity: Default::default(),
             };
             
             match ConfigFileManager::write_install_config(&target_partition, &data_partition, &install_config) {
@@ -1124,10 +1128,7 @@ fn resolve_install_target(
     let tmp = std::env::temp_dir().join("lr_assign_target.txt");
     std::fs::write(&tmp, script.as_bytes()).map_err(|e| format!("写分配盘符脚本失败: {}", e))?;
     let tmp_str = tmp.to_string_lossy().into_owned();
-    let dp_out = match crate::utils::cmd::create_command("diskpart")
-        .args(["/s", tmp_str.as_str()])
-        .output()
-    {
+    let dp_out = match crate::utils::command::run("diskpart", ["/s", tmp_str.as_str()]) {
         Ok(o) => crate::utils::encoding::gbk_to_utf8(&o.stdout),
         Err(e) => {
             let _ = std::fs::remove_file(&tmp);
@@ -1177,9 +1178,7 @@ fn ensure_mbr_disk_signature(disk: u32) -> Result<String, String> {
         let tmp = std::env::temp_dir().join(format!("lr_sig_{}.txt", tag));
         std::fs::write(&tmp, script.as_bytes()).map_err(|e| format!("写{}脚本失败: {}", tag, e))?;
         let tmp_str = tmp.to_string_lossy().into_owned();
-        let out = crate::utils::cmd::create_command("diskpart")
-            .args(["/s", tmp_str.as_str()])
-            .output();
+        let out = crate::utils::command::run("diskpart", ["/s", tmp_str.as_str()]);
         let _ = std::fs::remove_file(&tmp);
         match out {
             Ok(o) => Ok(crate::utils::encoding::gbk_to_utf8(&o.stdout)),
@@ -1239,7 +1238,7 @@ fn ensure_mbr_disk_signature(disk: u32) -> Result<String, String> {
 
 /// 格式化分区（用 diskpart，而不是 format.com）
 fn format_partition(partition: &str) -> anyhow::Result<()> {
-    use crate::utils::cmd::create_command;
+    use crate::utils::command;
 
     let letter = partition.trim_end_matches('\\').trim_end_matches(':');
     log::info!("[FORMAT] 用 diskpart 格式化分区: {} (volume {})", partition, letter);
@@ -1253,9 +1252,7 @@ fn format_partition(partition: &str) -> anyhow::Result<()> {
     let tmp = std::env::temp_dir().join("lr_xp_format.txt");
     std::fs::write(&tmp, script.as_bytes())?;
     let tmp_str = tmp.to_string_lossy().into_owned();
-    let output = create_command("diskpart")
-        .args(["/s", tmp_str.as_str()])
-        .output()?;
+    let output = command::run("diskpart", ["/s", tmp_str.as_str()])?;
     let _ = std::fs::remove_file(&tmp);
 
     let stdout = crate::utils::encoding::gbk_to_utf8(&output.stdout);
@@ -1316,9 +1313,7 @@ fn deactivate_sibling_active_partitions(target: &str, partitions: &[Partition])
                 continue;
             }
             let tmp_str = tmp.to_string_lossy().into_owned();
-            let out = crate::utils::cmd::create_command("diskpart")
-                .args(["/s", tmp_str.as_str()])
-                .output();
+            let out = crate::utils::command::run("diskpart", ["/s", tmp_str.as_str()]);
             let _ = std::fs::remove_file(&tmp);
             match out {
                 Ok(o) => log::info!(
@@ -1372,14 +1367,38 @@ fn export_drivers(destination: &str) -> anyhow::Result<()> {
     }
 }
 
-/// 导入驱动到目标系统
-fn import_drivers(target_partition: &str, driver_path: &str) -> anyhow::Result<()> {
+/// 导入驱动到目标系统（`match_devices` 时只导入与本机硬件匹配的驱动，匹配失败回退全部导入）
+fn import_drivers(target_partition: &str, driver_path: &str, match_devices: bool) -> anyhow::Result<()> {
     log::info!("[DRIVER IMPORT] 目标分区: {}, 驱动路径: {}", target_partition, driver_path);
     
     let dism = crate::core::dism::Dism::new();
     let image_path = format!("{}\\", target_partition);
+
+    if match_devices {
+        use std::path::Path;
+        let staging = format!("{}_Matched", driver_path);
+        let staged = crate::core::driver::DriverManager::new().and_then(|m| {
+            m.stage_drivers_for_present_devices(Path::new(driver_path), Path::new(&image_path), Path::new(&staging))
+        });
+        match staged {
+            Ok(r) => {
+                log::info!(
+                    "[DRIVER IMPORT] 按硬件匹配: 选中 {} 个驱动，{} 个设备无匹配驱动",
+                    r.selected_infs().len(),
+                    r.unmatched.len()
+                );
+                let result = dism.add_drivers_offline(&image_path, &staging);
+                crate::core::driver::write_offline_import_report(Path::new(&image_path), Path::new(&staging));
+                let _ = std::fs::remove_dir_all(&staging);
+                return result;
+            }
+            Err(e) => log::warn!("[DRIVER IMPORT] 按硬件匹配驱动失败，导入全部驱动: {}", e),
+        }
+    }
     
-    dism.add_drivers_offline(&image_path, driver_path)
+    let result = dism.add_drivers_offline(&image_path, driver_path);
+    crate::core::driver::write_offline_import_report(Path::new(&image_path), Path::new(driver_path));
+    result
 }
 
 /// 递归复制目录
@@ -1418,9 +1437,31 @@ fn copy_dir_recursive(src: &str, dst: &str) -> anyhow::Result<()> {
     Ok(())
 }
 
+/// 内置部署应答：部署脚本（specialize 的 deploy.bat、首次登录脚本、UWP 删除与清理）
+/// + 按代别裁剪的 OOBE + 高级选项里的账户与定制项。安装与「合并预览」共用。
+pub(crate) fn deploy_unattend(
+    arch: &str,
+    generation: lr_core::unattend::WindowsGeneration,
+    options: &AdvancedOptions,
+) -> lr_core::unattend::DeployUnattend {
+    let username = if options.custom_username && !options.username.is_empty() {
+        options.username.as_str()
+    } else {
+        "MyPc"
+    };
+    let mut spec = lr_core::unattend::DeployUnattend::new(arch, generation, username);
+    spec.computer_name = Some("*".to_string());
+    spec.scripts_dir = Some(AdvancedOptions::SCRIPTS_DIR.to_string());
+    spec.remove_uwp_apps = options.remove_uwp_apps;
+    spec.auto_logon_count = Some(1);
+    spec.custom = options.unattend_customization();
+    spec
+}
+
 /// 生成无人值守 XML 文件
 fn generate_unattend_xml(target_partition: &str, options: &AdvancedOptions) -> anyhow::Result<()> {
     use crate::core::system_utils::{get_file_version, get_system_architecture};
+    use lr_core::unattend::WindowsGeneration;
     use std::path::Path;
     // 检查是否已存在 unattend.xml，如果存在则跳过生成
     let existing_unattend = Path::new(target_partition)
@@ -1433,12 +1474,6 @@ fn generate_unattend_xml(target_partition: &str, options: &AdvancedOptions) -> a
     }
     
     log::info!("[UNATTEND] 生成无人值守配置文件");
-    
-    let username = if options.custom_username && !options.username.is_empty() {
-        options.username.clone()
-    } else {
-        "MyPc".to_string()
-    };
 
     // 检测目标系统架构
     let arch = get_system_architecture(target_partition);
@@ -1448,137 +1483,18 @@ fn generate_unattend_xml(target_partition: &str, options: &AdvancedOptions) -> a
     // 通过 ntdll.dll 文件版本检测目标系统版本
     // Windows 7: 6.1.x, Windows 8: 6.2.x, Windows 8.1: 6.3.x, Windows 10/11: 10.0.x
     let ntdll_path = Path::new(target_partition).join("Windows").join("System32").join("ntdll.dll");
-    let (is_win7, is_win8) = match get_file_version(&ntdll_path) {
+    let generation = match get_file_version(&ntdll_path) {
         Some((major, minor, build, _)) => {
             log::info!("[UNATTEND] 检测到目标系统版本 (ntdll.dll): {}.{}.{}", major, minor, build);
-            
-            let is_win7 = major == 6 && minor == 1;
-            let is_win8 = major == 6 && (minor == 2 || minor == 3);
-            (is_win7, is_win8)
+            WindowsGeneration::from_nt_version(major.into(), minor.into())
         }
         None => {
             log::warn!("[UNATTEND] 无法读取 ntdll.dll 版本: {:?}, 默认使用 Win10/11 配置", ntdll_path);
-            (false, false)
+            WindowsGeneration::Win10
         }
     };
 
-    // 构建 FirstLogonCommands
-    let mut first_logon_commands = String::new();
-    let mut order = 1;
-
-    // 首次登录脚本
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c if exist %SystemDrive%\LetRecovery_Scripts\firstlogon.bat call %SystemDrive%\LetRecovery_Scripts\firstlogon.bat</CommandLine>
-                    <Description>Run first login script</Description>
-                </SynchronousCommand>"#, order));
-    order += 1;
-
-    // 如果需要删除UWP应用（仅Win10/11支持）
-    if options.remove_uwp_apps && !is_win7 && !is_win8 {
-        first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>powershell -ExecutionPolicy Bypass -File %SystemDrive%\LetRecovery_Scripts\remove_uwp.ps1</CommandLine>
-                    <Description>Remove preinstalled UWP apps</Description>
-                </SynchronousCommand>"#, order));
-        order += 1;
-    }
-
-    // 清理脚本目录（最后执行）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c rd /s /q %SystemDrive%\LetRecovery_Scripts</CommandLine>
-                    <Description>Cleanup scripts directory</Description>
-                </SynchronousCommand>"#, order));
-    
-    // 根据系统版本生成不同的OOBE配置
-    // Win7: 移除HideOEMRegistrationScreen（家庭版不支持）
-    let oobe_section = if is_win7 {
-        // Windows 7: 不支持 HideOnlineAccountScreens, HideWirelessSetupInOOBE, SkipMachineOOBE, SkipUserOOBE, HideLocalAccountScreen, HideOEMRegistrationScreen(家庭版)
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>"#.to_string()
-    } else if is_win8 {
-        // Windows 8/8.1: 支持 HideLocalAccountScreen，不支持其他新选项
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>"#.to_string()
-    } else {
-        // Windows 10/11: 完整支持所有OOBE选项
-        r#"<OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-            </OOBE>"#.to_string()
-    };
-    
-    let xml_content = format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\LetRecovery_Scripts\deploy.bat call %SystemDrive%\LetRecovery_Scripts\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            {oobe_section}
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch_str, oobe_section = oobe_section, username = username, first_logon_commands = first_logon_commands);
+    let xml_content = deploy_unattend(arch_str, generation, options).to_xml();
 
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
@@ -2,6 +2,7 @@ pub mod about;
 pub mod advanced_options;
 pub mod download_progress;
 pub mod easy_mode;
+pub mod execution_plan;
 pub mod embedded_assets;
 pub mod hardware_info;
 pub mod install_progress;
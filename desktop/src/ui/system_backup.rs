@@ -279,7 +279,8 @@ impl App {
                 )
                 .clicked()
             {
-                self.start_backup();
+                // 先列出执行计划，确认后再开始备份
+                self.request_backup_plan();
             }
 
             // 显示备份模式提示
@@ -404,7 +405,7 @@ impl App {
         locked_partitions
     }
 
-    fn start_backup(&mut self) {
+    pub fn start_backup(&mut self) {
         let source_index = match self.backup_source_partition {
             Some(idx) => idx,
             None => {
@@ -813,7 +814,7 @@ impl App {
 }
 
 /// 查找可用的备份数据分区
-fn find_backup_data_partition(exclude_partition: &str) -> String {
+pub fn find_backup_data_partition(exclude_partition: &str) -> String {
     use crate::core::disk::DiskManager;
     
     let exclude_letter = exclude_partition.chars().next().unwrap_or('C').to_ascii_uppercase();
@@ -375,6 +375,17 @@ impl App {
             }
         });
 
+        // 目标分区已有应答文件（保留分区安装时会沿用）的校验结果
+        if self.partition_has_unattend
+            && !self.format_partition
+            && !self.partition_unattend_issues.is_empty()
+        {
+            ui.label(egui::RichText::new(tr!("目标分区现有无人值守文件存在以下问题：")).weak());
+            for issue in &self.partition_unattend_issues {
+                ui.colored_label(egui::Color32::from_rgb(200, 140, 0), issue);
+            }
+        }
+
         // 自定义无人值守文件 + 引导模式（启用无人值守时两者并列；否则引导模式单独一行）
         if self.unattended_install {
             ui.add_space(6.0);
@@ -395,29 +406,8 @@ impl App {
                             .add_filter(tr!("所有文件"), &["*"]);
                     }
                     if let Some(path) = dlg.pick_file() {
-                        let p = path.to_string_lossy().to_string();
-                        match std::fs::read_to_string(&path) {
-                            Ok(content) => {
-                                self.custom_unattend_path = p.clone();
-                                // 据【文件本身】判类型分发校验：扩展名 .sif，或内容是 INI 风格（去 BOM 后以
-                                // `[节]` 开头）→ winnt.sif(INI) 校验；否则按 unattend.xml 校验。
-                                // 不依赖介质检测状态——否则「先选应答文件、后认 XP 介质」会把 .sif 错当
-                                // XML 校验（报 unknown token at 1:1），且切介质后不会重新校验、错误赖着不走。
-                                let body = content.trim_start_matches('\u{feff}');
-                                let is_sif = p.to_ascii_lowercase().ends_with(".sif")
-                                    || body.trim_start().starts_with('[');
-                                self.custom_unattend_error = if is_sif {
-                                    crate::core::install_config::validate_winnt_sif(&content).err()
-                                } else {
-                                    crate::core::install_config::validate_unattend_xml(&content)
-                                        .err()
-                                };
-                            }
-                            Err(e) => {
-                                self.custom_unattend_path = p;
-                                self.custom_unattend_error = Some(tr!("无法读取文件: {}", e));
-                            }
-                        }
+                        self.custom_unattend_path = path.to_string_lossy().to_string();
+                        self.validate_custom_unattend();
                     }
                 }
                 if !self.custom_unattend_path.is_empty()
@@ -425,6 +415,8 @@ impl App {
                 {
                     self.custom_unattend_path.clear();
                     self.custom_unattend_error = None;
+                    self.custom_unattend_warnings.clear();
+                    self.custom_unattend_checked_arch = None;
                 }
 
                 // 引导模式与“自定义无人值守”并列在同一行
@@ -465,24 +457,49 @@ impl App {
                     egui::RichText::new(tr!("未选使用内置无人值守配置,系统已有此配置文件优先")).weak(),
                 );
             } else {
+                // 组件架构要与所选镜像一致：换了镜像/卷就按新架构重新校验
+                if self.custom_unattend_checked_arch != self.selected_image_arch() {
+                    self.validate_custom_unattend();
+                }
                 ui.horizontal(|ui| {
                     ui.label(tr!("已选:"));
                     ui.monospace(self.custom_unattend_path.clone());
                 });
+                // unattend.xml 可与内置配置合并（winnt.sif 另有 xp_i386 的合并逻辑）
+                if self.custom_unattend_is_xml() {
+                    ui.horizontal(|ui| {
+                        ui.checkbox(&mut self.custom_unattend_merge, tr!("与内置配置合并"))
+                            .on_hover_text(tr!("保留文件中已写的设置，补入内置的部署脚本、首次登录命令（删除 UWP 等）与自定义用户名。\n不勾选则原样使用该文件。"));
+                        if self.custom_unattend_merge
+                            && self.custom_unattend_error.is_none()
+                            && ui.button(tr!("预览合并结果")).clicked()
+                        {
+                            self.custom_unattend_preview = Some(self.merged_unattend_preview());
+                            self.custom_unattend_preview_for_install = false;
+                        }
+                    });
+                }
                 match &self.custom_unattend_error {
                     Some(err) => {
                         ui.colored_label(
                             egui::Color32::from_rgb(220, 50, 47),
-                            tr!("无人值守文件语法错误：{}（已禁用安装）", err),
+                            tr!("无人值守文件校验未通过：{}（已禁用安装）", err),
                         );
                     }
                     None => {
                         ui.colored_label(
                             egui::Color32::from_rgb(0, 160, 0),
-                            tr!("无人值守文件语法校验通过"),
+                            tr!("无人值守文件校验通过"),
                         );
                     }
                 }
+                // 非致命提示（winnt.sif 的未知键、unattend.xml 的可疑取值等），不阻止安装
+                for w in &self.custom_unattend_warnings {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(200, 140, 0),
+                        tr!("提示：{}", w),
+                    );
+                }
             }
             ui.add_space(6.0);
         } else {
@@ -607,7 +624,8 @@ impl App {
                 )
                 .clicked()
             {
-                self.start_installation();
+                // 先列出执行计划，确认后再进入 begin_installation
+                self.request_install_plan();
             }
 
             // 显示安装模式提示
@@ -1050,6 +1068,7 @@ impl App {
                     // 有多个或没有系统分区，不默认选择
                     self.selected_partition = None;
                     self.partition_has_unattend = false;
+                    self.partition_unattend_issues.clear();
                 }
             } else {
                 // 非PE环境，选择当前系统分区
@@ -1222,6 +1241,20 @@ impl App {
         decryption_started
     }
 
+    /// 执行计划确认后开始安装
+    pub fn begin_installation(&mut self) {
+        // 合并模式：安装前先展示合并后的应答，确认后再开始（见 app.rs 的预览窗口）
+        if self.unattended_install
+            && self.custom_unattend_merge
+            && self.custom_unattend_is_xml()
+        {
+            self.custom_unattend_preview = Some(self.merged_unattend_preview());
+            self.custom_unattend_preview_for_install = true;
+        } else {
+            self.start_installation();
+        }
+    }
+
     pub fn start_installation(&mut self) {
         let selected_index = match self.selected_partition {
             Some(idx) => idx,
@@ -1342,6 +1375,9 @@ impl App {
             } else {
                 String::new()
             },
+            custom_unattend_merge: self.unattended_install
+                && self.custom_unattend_merge
+                && self.custom_unattend_is_xml(),
             // XP/2003 检测：选中镜像主版本号为 5（WIM/ESD 可识别；GHO 由 PE 端按 \Windows\Boot 兜底判断），
             // 或 i386 文本安装介质（selected_is_xp 已并入 is_xp_i386）。
             is_xp: selected_is_xp,
@@ -1487,6 +1523,71 @@ impl App {
         self.install_step = 0;
     }
     
+    /// 当前所选镜像卷的处理器架构（unattend.xml 的 processorArchitecture 取值），未知时为 None。
+    fn selected_image_arch(&self) -> Option<String> {
+        self.selected_volume
+            .and_then(|idx| self.image_volumes.get(idx))
+            .and_then(|v| v.architecture.clone())
+    }
+
+    /// 所选自定义应答是否为 unattend.xml（可与内置配置合并；winnt.sif 不走这里）。
+    fn custom_unattend_is_xml(&self) -> bool {
+        self.custom_unattend_path.to_ascii_lowercase().ends_with(".xml")
+    }
+
+    /// 按当前所选镜像（架构/版本）与高级选项生成内置应答，并叠加到自定义 unattend.xml 上，
+    /// 供安装前预览。实际安装时 PE 端按释放后的系统重新探测架构/版本再合并。
+    fn merged_unattend_preview(&self) -> Result<String, String> {
+        use lr_core::unattend::WindowsGeneration;
+
+        let content = std::fs::read(&self.custom_unattend_path)
+            .map_err(|e| tr!("无法读取文件: {}", e))?;
+        let volume = self.selected_volume.and_then(|idx| self.image_volumes.get(idx));
+        let generation = volume
+            .and_then(|v| Some((v.major_version?, v.minor_version.unwrap_or(0))))
+            .map(|(major, minor)| WindowsGeneration::from_nt_version(major.into(), minor.into()))
+            .unwrap_or(WindowsGeneration::Win10);
+        let arch = self.selected_image_arch().unwrap_or_else(|| "amd64".to_string());
+        let ours = crate::ui::install_progress::deploy_unattend(&arch, generation, &self.advanced_options)
+            .build();
+        lr_core::unattend::merge_xml(&lr_core::unattend::decode_xml(&content), &ours)
+    }
+
+    /// 读取并校验 `custom_unattend_path`，结果写入 custom_unattend_error / custom_unattend_warnings。
+    /// 据【文件本身】判类型分发校验：扩展名 .sif，或内容是 INI 风格（去 BOM 后以 `[节]` 开头）
+    /// → winnt.sif(INI) 校验；否则按 unattend.xml 校验（含组件架构与所选镜像是否一致）。
+    /// 不依赖介质检测状态——否则「先选应答文件、后认 XP 介质」会把 .sif 错当 XML 校验
+    /// （报 unknown token at 1:1），且切介质后不会重新校验、错误赖着不走。
+    /// winnt.sif 按原始字节校验（中文应答多为 GBK，不能按 UTF-8 读）。
+    fn validate_custom_unattend(&mut self) {
+        let image_arch = self.selected_image_arch();
+        self.custom_unattend_checked_arch = image_arch.clone();
+        self.custom_unattend_warnings.clear();
+        let raw = match std::fs::read(&self.custom_unattend_path) {
+            Ok(raw) => raw,
+            Err(e) => {
+                self.custom_unattend_error = Some(tr!("无法读取文件: {}", e));
+                return;
+            }
+        };
+        let content = lr_core::unattend::decode_xml(&raw);
+        let body = content.trim_start_matches('\u{feff}');
+        let is_sif = self.custom_unattend_path.to_ascii_lowercase().ends_with(".sif")
+            || body.trim_start().starts_with('[');
+        let result = if is_sif {
+            crate::core::install_config::validate_winnt_sif(&raw)
+        } else {
+            crate::core::install_config::validate_unattend_xml(&content, image_arch.as_deref())
+        };
+        match result {
+            Ok(warnings) => {
+                self.custom_unattend_error = None;
+                self.custom_unattend_warnings = warnings;
+            }
+            Err(e) => self.custom_unattend_error = Some(e),
+        }
+    }
+
     /// 开始异步检测分区中的无人值守配置文件
     fn start_unattend_check_for_partition(&mut self, partition_index: usize) {
         let partition = match self.partitions.get(partition_index) {
@@ -1497,6 +1598,7 @@ impl App {
         // 如果分区没有 Windows 系统，不需要检测
         if !partition.has_windows {
             self.partition_has_unattend = false;
+            self.partition_unattend_issues.clear();
             self.last_unattend_check_partition = Some(partition.letter.clone());
             // 默认勾选无人值守
             self.unattended_install = true;
@@ -1519,15 +1621,21 @@ impl App {
         *UNATTEND_CHECK_RESULT_RX.lock().unwrap() = Some(rx);
 
         let partition_letter = partition_id;
+        let image_arch = self.selected_image_arch();
         
         std::thread::spawn(move || {
-            let result = Self::check_unattend_files_in_partition(&partition_letter);
+            let result =
+                Self::check_unattend_files_in_partition(&partition_letter, image_arch.as_deref());
             let _ = tx.send(result);
         });
     }
     
-    /// 检查分区中的无人值守配置文件（在后台线程执行）
-    fn check_unattend_files_in_partition(partition_letter: &str) -> UnattendCheckResult {
+    /// 检查分区中的无人值守配置文件（在后台线程执行）。
+    /// 找到的 .xml 应答同时按 `image_arch` 做语义校验，结果放入 `diagnostics`。
+    fn check_unattend_files_in_partition(
+        partition_letter: &str,
+        image_arch: Option<&str>,
+    ) -> UnattendCheckResult {
         use std::path::Path;
         
         // 常见的无人值守配置文件位置
@@ -1559,6 +1667,21 @@ impl App {
         }
         
         let has_unattend = !detected_paths.is_empty();
+
+        // 分区文件系统大小写不敏感，unattend.xml / Unattend.xml 可能指向同一文件，只校验一次
+        let mut diagnostics = Vec::new();
+        let mut checked = std::collections::HashSet::new();
+        for path in &detected_paths {
+            if !checked.insert(path.to_lowercase()) {
+                continue;
+            }
+            let Ok(raw) = std::fs::read(path) else { continue };
+            let content = lr_core::unattend::decode_xml(&raw);
+            for issue in lr_core::unattend_check::check(&content, image_arch) {
+                log::warn!("[UNATTEND CHECK] {}: {}", path, issue);
+                diagnostics.push(format!("{}: {}", path, issue));
+            }
+        }
         
         if has_unattend {
             log::info!("[UNATTEND CHECK] 分区 {} 存在 {} 个无人值守配置文件",
@@ -1571,6 +1694,7 @@ impl App {
             partition_letter: partition_letter.to_string(),
             has_unattend,
             detected_paths,
+            diagnostics,
         }
     }
     
@@ -1606,6 +1730,7 @@ impl App {
 
             if current_partition.as_ref() == Some(&result.partition_letter) {
                 self.partition_has_unattend = result.has_unattend;
+                self.partition_unattend_issues = result.diagnostics;
                 // 目标分区或源镜像任一自带无人值守 → 默认取消勾选。
                 self.apply_unattend_default();
             }
@@ -195,7 +195,7 @@ fn get_partition_info(_drive: &str) -> Option<FormatablePartition> {
 /// 使用 format.com 格式化分区
 #[cfg(windows)]
 pub fn format_partition(letter: &str, label: &str, file_system: &str) -> Result<(), String> {
-    use crate::utils::cmd::create_command;
+    use crate::utils::command;
     use crate::utils::encoding::gbk_to_utf8;
     
     // 确保盘符格式正确
@@ -232,9 +232,7 @@ pub fn format_partition(letter: &str, label: &str, file_system: &str) -> Result<
     
     log::info!("执行命令: cmd /c {}", cmd_args);
 
-    let output = create_command("cmd")
-        .args(["/c", &cmd_args])
-        .output()
+    let output = command::run("cmd", ["/c", &cmd_args])
         .map_err(|e| tr!("执行 format 命令失败: {}", e))?;
 
     let stdout = gbk_to_utf8(&output.stdout);
@@ -279,7 +277,7 @@ pub fn format_partition_with_progress<F>(
 where
     F: Fn(u8, &str) + Send + 'static,
 {
-    use crate::utils::cmd::create_command;
+    use crate::utils::command;
     use crate::utils::encoding::gbk_to_utf8;
     
     // 确保盘符格式正确
@@ -320,9 +318,7 @@ where
 
     progress_callback(20, &tr!("正在格式化..."));
 
-    let output = create_command("cmd")
-        .args(["/c", &cmd_args])
-        .output()
+    let output = command::run("cmd", ["/c", &cmd_args])
         .map_err(|e| tr!("执行 format 命令失败: {}", e))?;
 
     let stdout = gbk_to_utf8(&output.stdout);
@@ -140,9 +140,15 @@ impl App {
         // 检查文件哈希校验状态
         self.check_hash_verify_status();
 
+        // 检查驱动库后台任务
+        self.check_driver_repo_status();
+
         // 检查离线密码重置状态
         self.check_password_reset_status();
         self.check_password_reset_users_status();
+
+        // 检查分区表还原状态
+        self.check_partition_restore_status();
     }
 
     /// 启动后台加载Windows分区信息
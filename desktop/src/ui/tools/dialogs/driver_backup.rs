@@ -70,14 +70,30 @@ impl App {
                             });
 
                             ui.add_space(5.0);
+                            ui.checkbox(
+                                &mut self.driver_backup_archive,
+                                tr!("导出为单个压缩包（含驱动清单与文件校验值）"),
+                            );
                             ui.horizontal(|ui| {
-                                ui.label(tr!("保存目录:"));
+                                if self.driver_backup_archive {
+                                    ui.label(tr!("保存文件:"));
+                                } else {
+                                    ui.label(tr!("保存目录:"));
+                                }
                                 ui.add(
                                     egui::TextEdit::singleline(&mut self.driver_backup_path)
                                         .desired_width(300.0),
                                 );
                                 if ui.button(tr!("浏览...")).clicked() {
-                                    if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                                    let picked = if self.driver_backup_archive {
+                                        rfd::FileDialog::new()
+                                            .add_filter(tr!("驱动压缩包"), &["zip"])
+                                            .set_file_name("drivers_backup.zip")
+                                            .save_file()
+                                    } else {
+                                        rfd::FileDialog::new().pick_folder()
+                                    };
+                                    if let Some(path) = picked {
                                         self.driver_backup_path = path.to_string_lossy().to_string();
                                     }
                                 }
@@ -124,6 +140,14 @@ impl App {
                                         self.driver_backup_path = path.to_string_lossy().to_string();
                                     }
                                 }
+                                if ui.button(tr!("选择压缩包...")).clicked() {
+                                    if let Some(path) = rfd::FileDialog::new()
+                                        .add_filter(tr!("驱动压缩包"), &["zip"])
+                                        .pick_file()
+                                    {
+                                        self.driver_backup_path = path.to_string_lossy().to_string();
+                                    }
+                                }
                             });
                         }
                     }
@@ -188,6 +212,7 @@ impl App {
 
         let path = self.driver_backup_path.clone();
         let mode = self.driver_backup_mode;
+        let as_archive = self.driver_backup_archive;
 
         self.driver_backup_loading = true;
         self.driver_backup_message = match mode {
@@ -202,9 +227,30 @@ impl App {
             let dism = crate::core::dism::Dism::new();
             
             let result = match mode {
+                DriverBackupMode::Export if as_archive => {
+                    // 先导出到临时目录，再打包为单个压缩包（含清单与每个文件的 SHA-256）
+                    match lr_core::driver_archive::export_to_archive(
+                        std::path::Path::new(&path),
+                        |staging| dism.export_drivers_from_system(&target, &staging.to_string_lossy()),
+                    ) {
+                        Ok(manifest) => Ok(tr!(
+                            "驱动已导出为压缩包: {} 个驱动包 -> {}",
+                            manifest.packages.len(),
+                            path
+                        )),
+                        Err(e) => Err(tr!("驱动导出失败: {}", e)),
+                    }
+                }
                 DriverBackupMode::Export => {
                     match dism.export_drivers_from_system(&target, &path) {
-                        Ok(_) => Ok(tr!("驱动导出成功: {} -> {}", target, path)),
+                        Ok(_) => {
+                            // 导出目录即驱动库：顺带建立索引，供还原 / 离线导入直接使用
+                            let index = lr_core::driver_repo::RepoIndex::update(
+                                std::path::Path::new(&path),
+                            );
+                            log::info!("驱动库索引: {} 个 INF", index.packages.len());
+                            Ok(tr!("驱动导出成功: {} -> {}", target, path))
+                        }
                         Err(e) => Err(tr!("驱动导出失败: {}", e)),
                     }
                 }
@@ -213,8 +259,19 @@ impl App {
                     if !std::path::Path::new(&path).exists() {
                         Err(tr!("驱动目录不存在: {}", path))
                     } else {
-                        match dism.add_drivers_offline(&target, &path) {
-                            Ok(_) => Ok(tr!("驱动导入成功！")),
+                        // 驱动压缩包先解压并逐个文件校验，校验不通过不导入
+                        // 解压到目标分区，不占用系统临时目录
+                        match lr_core::driver_archive::prepare_import_dir(
+                            std::path::Path::new(&path),
+                            std::path::Path::new(&target),
+                        ) {
+                            Ok(dir) => {
+                                let import_dir = Self::driver_import_dir(&dir.to_string_lossy());
+                                match dism.add_drivers_offline(&target, &import_dir) {
+                                    Ok(_) => Ok(tr!("驱动导入成功！")),
+                                    Err(e) => Err(tr!("驱动导入失败: {}", e)),
+                                }
+                            }
                             Err(e) => Err(tr!("驱动导入失败: {}", e)),
                         }
                     }
@@ -224,4 +281,25 @@ impl App {
             let _ = tx.send(result);
         });
     }
+
+    /// 有驱动库索引时只导入有效包（跳过重复与被新版本取代的包），返回实际导入的目录
+    fn driver_import_dir(path: &str) -> String {
+        match lr_core::driver_repo::active_infs(std::path::Path::new(path)) {
+            Some(infs) => {
+                let staging = std::env::temp_dir().join("LetRecovery_driver_repo");
+                let _ = std::fs::remove_dir_all(&staging);
+                match lr_core::driver_match::stage_packages(&infs, &staging) {
+                    Ok(n) => {
+                        log::info!("按驱动库索引导入 {} 个有效驱动包", n);
+                        staging.to_string_lossy().to_string()
+                    }
+                    Err(e) => {
+                        log::warn!("暂存有效驱动包失败，改为导入整个目录: {}", e);
+                        path.to_string()
+                    }
+                }
+            }
+            None => path.to_string(),
+        }
+    }
 }
@@ -19,15 +19,18 @@ pub fn export_drivers_online(export_dir: &str) -> Result<(), String> {
         .map_err(|e| e.to_string())
 }
 
-/// 导入驱动到离线系统
+/// 导入驱动到离线系统（`driver_dir` 也可以是驱动压缩包，先校验再导入）
 pub fn import_drivers_offline(target_partition: &str, driver_dir: &str) -> Result<(), String> {
     // 检查驱动目录是否存在
     if !Path::new(driver_dir).exists() {
         return Err(tr!("驱动目录不存在: {}", driver_dir));
     }
 
+    let source =
+        lr_core::driver_archive::prepare_import_dir(Path::new(driver_dir), Path::new(target_partition))
+            .map_err(|e| e.to_string())?;
     let dism = crate::core::dism::Dism::new();
-    dism.add_drivers_offline(target_partition, driver_dir)
+    dism.add_drivers_offline(target_partition, &source.to_string_lossy())
         .map_err(|e| e.to_string())
 }
 
@@ -0,0 +1,307 @@
+//! 驱动库浏览对话框
+//!
+//! 为驱动目录建立 / 刷新索引（见 `lr_core::driver_repo`），按关键字或硬件 ID 浏览
+//! 其中的驱动包，并可清理重复与被新版本取代的包。
+
+use egui;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use lr_core::driver_repo::{EntryStatus, RepoEntry, RepoIndex};
+
+use crate::app::App;
+use crate::tr;
+
+/// 后台操作结果：刷新后的索引与提示信息
+pub type DriverRepoResult = Result<(RepoIndex, String), String>;
+
+impl App {
+    /// 打开驱动库对话框（默认指向程序目录下的驱动导出目录）
+    pub fn init_driver_repo_dialog(&mut self) {
+        self.show_driver_repo_dialog = true;
+        if self.driver_repo_path.is_empty() {
+            self.driver_repo_path = crate::utils::path::get_exe_dir()
+                .join("drivers_backup")
+                .to_string_lossy()
+                .to_string();
+        }
+        self.driver_repo_filter.clear();
+        self.driver_repo_message.clear();
+        self.driver_repo_confirm_prune = false;
+        self.driver_repo_index = RepoIndex::load(&PathBuf::from(&self.driver_repo_path));
+    }
+
+    /// 渲染驱动库对话框
+    pub fn render_driver_repo_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_driver_repo_dialog {
+            return;
+        }
+
+        let mut should_close = false;
+        let mut start_build = false;
+        let mut start_prune = false;
+        let mut reload: Option<PathBuf> = None;
+        // 渲染期间取出索引，避免与对话框状态字段的可变借用冲突
+        let index = self.driver_repo_index.take();
+
+        egui::Window::new(tr!("驱动库"))
+            .resizable(true)
+            .default_width(820.0)
+            .default_height(520.0)
+            .show(ui.ctx(), |ui| {
+                ui.label(tr!(
+                    "为驱动目录建立索引，查找重复的驱动包，只保留每个驱动包的最新版本。"
+                ));
+                ui.add_space(10.0);
+
+                ui.horizontal(|ui| {
+                    ui.label(tr!("驱动目录:"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.driver_repo_path).desired_width(420.0),
+                    );
+                    let idle = !self.driver_repo_loading;
+                    if ui
+                        .add_enabled(idle, egui::Button::new(tr!("浏览...")))
+                        .clicked()
+                    {
+                        if let Some(path) = rfd::FileDialog::new().pick_folder() {
+                            self.driver_repo_path = path.to_string_lossy().to_string();
+                            self.driver_repo_confirm_prune = false;
+                            reload = Some(path);
+                        }
+                    }
+                    let can_build = idle && !self.driver_repo_path.is_empty();
+                    if ui
+                        .add_enabled(can_build, egui::Button::new(tr!("建立/刷新索引")))
+                        .clicked()
+                    {
+                        start_build = true;
+                    }
+                    if self.driver_repo_loading {
+                        ui.spinner();
+                    }
+                });
+
+                ui.add_space(8.0);
+
+                let Some(index) = index.as_ref() else {
+                    ui.colored_label(egui::Color32::GRAY, tr!("该目录尚未建立索引"));
+                    ui.add_space(10.0);
+                    if !self.driver_repo_message.is_empty() {
+                        ui.label(&self.driver_repo_message);
+                    }
+                    ui.add_space(10.0);
+                    if ui.button(tr!("关闭")).clicked() {
+                        should_close = true;
+                    }
+                    return;
+                };
+
+                let summary = index.summary();
+                ui.label(tr!(
+                    "共 {} 个 INF：有效 {}，重复 {}，已被新版本取代 {}",
+                    index.packages.len(),
+                    summary.active,
+                    summary.duplicate,
+                    summary.superseded
+                ));
+
+                ui.horizontal(|ui| {
+                    ui.label(tr!("筛选:"));
+                    ui.add(
+                        egui::TextEdit::singleline(&mut self.driver_repo_filter)
+                            .hint_text(tr!("路径、提供商、类或硬件 ID"))
+                            .desired_width(300.0),
+                    );
+                    ui.checkbox(
+                        &mut self.driver_repo_show_redundant,
+                        tr!("显示重复/过时的包"),
+                    );
+                });
+
+                ui.add_space(6.0);
+
+                let rows: Vec<&RepoEntry> = index
+                    .packages
+                    .iter()
+                    .filter(|e| self.driver_repo_show_redundant || e.is_active())
+                    .filter(|e| e.matches_query(&self.driver_repo_filter))
+                    .collect();
+
+                egui::ScrollArea::both()
+                    .max_height(300.0)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        egui::Grid::new("driver_repo_grid")
+                            .striped(true)
+                            .num_columns(7)
+                            .show(ui, |ui| {
+                                ui.strong("INF");
+                                ui.strong(tr!("提供商"));
+                                ui.strong(tr!("类"));
+                                ui.strong(tr!("版本"));
+                                ui.strong(tr!("架构"));
+                                ui.strong(tr!("硬件 ID"));
+                                ui.strong(tr!("状态"));
+                                ui.end_row();
+
+                                for e in rows {
+                                    ui.label(&e.inf);
+                                    ui.label(&e.provider);
+                                    ui.label(&e.class);
+                                    ui.label(&e.driver_ver);
+                                    ui.label(e.archs.join(", "));
+                                    ui.label(e.hardware_ids.len().to_string())
+                                        .on_hover_text(Self::driver_repo_ids_tooltip(e));
+                                    Self::render_driver_repo_status(ui, &e.status);
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.add_space(10.0);
+
+                let redundant = summary.duplicate + summary.superseded;
+                if self.driver_repo_confirm_prune {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        tr!("将从磁盘删除 {} 个重复/过时的驱动包，确定继续？", redundant),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button(tr!("确定删除")).clicked() {
+                            start_prune = true;
+                        }
+                        if ui.button(tr!("取消")).clicked() {
+                            self.driver_repo_confirm_prune = false;
+                        }
+                    });
+                    ui.add_space(6.0);
+                }
+
+                if !self.driver_repo_message.is_empty() {
+                    ui.label(&self.driver_repo_message);
+                    ui.add_space(6.0);
+                }
+
+                ui.horizontal(|ui| {
+                    let can_prune = redundant > 0
+                        && !self.driver_repo_loading
+                        && !self.driver_repo_confirm_prune;
+                    if ui
+                        .add_enabled(
+                            can_prune,
+                            egui::Button::new(tr!("清理重复/过时的包 ({})", redundant)),
+                        )
+                        .clicked()
+                    {
+                        self.driver_repo_confirm_prune = true;
+                    }
+                    if ui.button(tr!("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        self.driver_repo_index = match reload {
+            Some(path) => RepoIndex::load(&path),
+            None => index,
+        };
+        if start_build {
+            self.start_driver_repo_task(false);
+        }
+        if start_prune {
+            self.driver_repo_confirm_prune = false;
+            self.start_driver_repo_task(true);
+        }
+        if should_close {
+            self.show_driver_repo_dialog = false;
+        }
+    }
+
+    fn render_driver_repo_status(ui: &mut egui::Ui, status: &EntryStatus) {
+        match status {
+            EntryStatus::Active => {
+                ui.colored_label(egui::Color32::from_rgb(0, 200, 0), tr!("有效"));
+            }
+            EntryStatus::Duplicate { of } => {
+                ui.colored_label(egui::Color32::GRAY, tr!("重复"))
+                    .on_hover_text(tr!("与 {} 相同", of));
+            }
+            EntryStatus::Superseded { by } => {
+                ui.colored_label(egui::Color32::from_rgb(255, 165, 0), tr!("已过时"))
+                    .on_hover_text(tr!("已被 {} 取代", by));
+            }
+        }
+    }
+
+    fn driver_repo_ids_tooltip(entry: &RepoEntry) -> String {
+        const MAX_IDS: usize = 20;
+        let mut text = entry
+            .hardware_ids
+            .iter()
+            .take(MAX_IDS)
+            .cloned()
+            .collect::<Vec<_>>()
+            .join("\n");
+        if entry.hardware_ids.len() > MAX_IDS {
+            text.push_str(&format!("\n... (+{})", entry.hardware_ids.len() - MAX_IDS));
+        }
+        text
+    }
+
+    /// 后台建立/刷新索引，或清理重复/过时的包
+    fn start_driver_repo_task(&mut self, prune: bool) {
+        if self.driver_repo_loading {
+            return;
+        }
+        let root = PathBuf::from(&self.driver_repo_path);
+        if !root.is_dir() {
+            self.driver_repo_message = tr!("驱动目录不存在: {}", self.driver_repo_path);
+            return;
+        }
+
+        self.driver_repo_loading = true;
+        self.driver_repo_message = if prune {
+            tr!("正在清理驱动库...")
+        } else {
+            tr!("正在建立驱动库索引...")
+        };
+
+        let (tx, rx) = mpsc::channel::<DriverRepoResult>();
+        self.driver_repo_rx = Some(rx);
+
+        std::thread::spawn(move || {
+            let mut index = RepoIndex::update(&root);
+            let result = if prune {
+                match index.prune(&root) {
+                    Ok(n) => Ok((index, tr!("已删除 {} 个重复/过时的驱动包", n))),
+                    Err(e) => Err(tr!("清理失败: {}", e)),
+                }
+            } else {
+                let message = tr!(
+                    "索引已更新: {}",
+                    root.join(lr_core::driver_repo::INDEX_FILE).display()
+                );
+                Ok((index, message))
+            };
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 轮询驱动库后台任务（在主循环中调用）
+    pub fn check_driver_repo_status(&mut self) {
+        if let Some(ref rx) = self.driver_repo_rx {
+            if let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok((index, message)) => {
+                        self.driver_repo_index = Some(index);
+                        self.driver_repo_message = message;
+                    }
+                    Err(e) => self.driver_repo_message = e,
+                }
+                self.driver_repo_loading = false;
+                self.driver_repo_rx = None;
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! 驱动导入报告对话框
+//!
+//! 展示部署时写入目标系统 `Windows\Logs\LetRecovery` 的驱动导入报告（见
+//! `lr_core::driver_report`）。新系统首次启动后自动弹出一次，之后可从工具箱再次打开。
+
+use egui;
+use std::path::PathBuf;
+
+use lr_core::driver_report::{
+    describe_error, log_dir, mark_seen, pending_report, DriverImportReport, ImportOutcome,
+};
+
+use crate::app::App;
+use crate::tr;
+
+/// 当前系统的报告目录
+fn current_log_dir() -> PathBuf {
+    let system_drive = std::env::var("SystemDrive").unwrap_or_else(|_| "C:".to_string());
+    log_dir(&PathBuf::from(format!("{}\\", system_drive)))
+}
+
+impl App {
+    /// 启动后检查一次是否有尚未查看的驱动导入报告（PE 环境下不检查）
+    pub fn check_pending_driver_report(&mut self) {
+        if self.driver_report_checked || self.system_info.is_none() {
+            return;
+        }
+        self.driver_report_checked = true;
+        if self.is_pe_environment() {
+            return;
+        }
+        if let Some(report) = pending_report(&current_log_dir()) {
+            log::info!(
+                "[DriverReport] 发现未查看的驱动导入报告: 成功 {}, 失败 {}",
+                report.succeeded(),
+                report.failed()
+            );
+            self.driver_report = Some(report);
+            self.show_driver_report_dialog = true;
+        }
+    }
+
+    /// 从工具箱打开驱动导入报告
+    pub fn init_driver_report_dialog(&mut self) {
+        self.driver_report = DriverImportReport::load(&current_log_dir());
+        self.driver_report_failed_only = false;
+        self.show_driver_report_dialog = true;
+    }
+
+    /// 渲染驱动导入报告对话框
+    pub fn render_driver_report_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_driver_report_dialog {
+            return;
+        }
+
+        let mut should_close = false;
+
+        egui::Window::new(tr!("驱动导入报告"))
+            .resizable(true)
+            .default_width(820.0)
+            .default_height(480.0)
+            .show(ui.ctx(), |ui| {
+                let Some(report) = self.driver_report.as_ref() else {
+                    ui.colored_label(egui::Color32::GRAY, tr!("当前系统没有驱动导入报告"));
+                    ui.add_space(10.0);
+                    if ui.button(tr!("关闭")).clicked() {
+                        should_close = true;
+                    }
+                    return;
+                };
+
+                let created = chrono::DateTime::from_timestamp(report.created as i64, 0)
+                    .map(|t| {
+                        t.with_timezone(&chrono::Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string()
+                    })
+                    .unwrap_or_default();
+                ui.label(tr!("部署时间: {}  方式: {}", created, report.method));
+                ui.label(tr!("驱动来源: {}", report.source));
+                ui.add_space(6.0);
+
+                let failed = report.failed();
+                ui.horizontal(|ui| {
+                    ui.label(tr!(
+                        "共 {} 个驱动：成功 {}，仅加入驱动存储 {}，失败 {}，跳过 {}",
+                        report.results.len(),
+                        report.count(ImportOutcome::Installed),
+                        report.count(ImportOutcome::StoreOnly),
+                        failed,
+                        report.count(ImportOutcome::Skipped)
+                    ));
+                });
+                if failed > 0 {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(220, 50, 47),
+                        tr!("有 {} 个驱动未能导入，相关设备可能无法正常工作。", failed),
+                    );
+                }
+                if report.reboot_required() {
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        tr!("部分驱动需要重启后才能生效。"),
+                    );
+                }
+
+                ui.add_space(6.0);
+                ui.checkbox(&mut self.driver_report_failed_only, tr!("只显示失败的驱动"));
+                ui.add_space(6.0);
+
+                egui::ScrollArea::both()
+                    .max_height(300.0)
+                    .auto_shrink([false, false])
+                    .show(ui, |ui| {
+                        egui::Grid::new("driver_report_grid")
+                            .striped(true)
+                            .num_columns(6)
+                            .show(ui, |ui| {
+                                ui.strong("INF");
+                                ui.strong(tr!("提供商"));
+                                ui.strong(tr!("版本"));
+                                ui.strong(tr!("结果"));
+                                ui.strong(tr!("匹配设备"));
+                                ui.strong(tr!("说明"));
+                                ui.end_row();
+
+                                for r in report.results.iter().filter(|r| {
+                                    !self.driver_report_failed_only
+                                        || r.outcome == ImportOutcome::Failed
+                                }) {
+                                    ui.label(&r.inf);
+                                    ui.label(&r.provider);
+                                    ui.label(&r.driver_ver);
+                                    let color = match r.outcome {
+                                        ImportOutcome::Installed => {
+                                            egui::Color32::from_rgb(0, 200, 0)
+                                        }
+                                        ImportOutcome::StoreOnly => {
+                                            egui::Color32::from_rgb(100, 150, 255)
+                                        }
+                                        ImportOutcome::Failed => {
+                                            egui::Color32::from_rgb(220, 50, 47)
+                                        }
+                                        ImportOutcome::Skipped => egui::Color32::GRAY,
+                                    };
+                                    ui.colored_label(color, tr!(r.outcome.label()));
+                                    ui.label(r.matched_devices.len().to_string())
+                                        .on_hover_text(r.matched_devices.join("\n"));
+                                    ui.label(Self::driver_report_detail(
+                                        r.error_code,
+                                        &r.message,
+                                        r.reboot_required,
+                                    ));
+                                    ui.end_row();
+                                }
+                            });
+                    });
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    if ui.button(tr!("打开报告目录")).clicked() {
+                        let _ = std::process::Command::new("explorer.exe")
+                            .arg(current_log_dir())
+                            .spawn();
+                    }
+                    if ui.button(tr!("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if should_close {
+            self.show_driver_report_dialog = false;
+            if self.driver_report.is_some() {
+                let _ = mark_seen(&current_log_dir());
+            }
+        }
+    }
+
+    fn driver_report_detail(error_code: Option<u32>, message: &str, reboot: bool) -> String {
+        let mut parts = Vec::new();
+        if let Some(code) = error_code {
+            match describe_error(code) {
+                Some(text) => parts.push(format!("0x{:08X} {}", code, tr!(text))),
+                None => parts.push(format!("0x{:08X}", code)),
+            }
+        }
+        if !message.is_empty() {
+            parts.push(message.to_string());
+        }
+        if reboot {
+            parts.push(tr!("需要重启"));
+        }
+        parts.join("; ")
+    }
+}
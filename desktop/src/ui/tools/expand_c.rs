@@ -10,6 +10,10 @@ use crate::tr;
 use crate::app::App;
 use crate::core::install_config::{ConfigFileManager, ExpandConfig};
 use crate::core::quick_partition::{get_physical_disks, query_shrink_max};
+use lr_core::expand_plan::{expand_limits, plan_expand, DiskLayout, ExpandPlan};
+use lr_core::partition_table;
+
+const MIB: u64 = 1024 * 1024;
 
 /// 异步加载 C 盘信息的结果
 #[derive(Debug, Clone)]
@@ -31,6 +35,8 @@ pub struct ExpandCLoadResult {
     pub can_expand: bool,
     /// 不可扩容（或提示）的原因
     pub reason: String,
+    /// C 盘所在磁盘的布局与 C 的分区号（用于预览扩容方案）
+    pub layout: Option<(DiskLayout, u32)>,
 }
 
 impl Default for ExpandCLoadResult {
@@ -44,6 +50,7 @@ impl Default for ExpandCLoadResult {
             no_move_max_mb: 0,
             can_expand: false,
             reason: String::new(),
+            layout: None,
         }
     }
 }
@@ -77,6 +84,8 @@ pub struct ExpandCDialogState {
     pub target_size_mb: u64,
     /// 是否显示确认对话框
     pub show_confirm_dialog: bool,
+    /// C 盘所在磁盘的布局与 C 的分区号
+    pub layout: Option<(DiskLayout, u32)>,
 }
 
 impl Default for ExpandCDialogState {
@@ -95,6 +104,7 @@ impl Default for ExpandCDialogState {
             target_size_text: String::new(),
             target_size_mb: 0,
             show_confirm_dialog: false,
+            layout: None,
         }
     }
 }
@@ -106,6 +116,12 @@ impl ExpandCDialogState {
         let safe_floor = self.used_mb + 1024;
         self.current_size_mb.max(safe_floor)
     }
+
+    /// 当前目标大小对应的扩容方案（与 PE 端执行的方案一致）
+    pub fn plan(&self) -> Option<ExpandPlan> {
+        let (layout, number) = self.layout.as_ref()?;
+        plan_expand(layout, *number, self.target_size_mb * MIB).ok()
+    }
 }
 
 impl App {
@@ -144,6 +160,7 @@ impl App {
                 self.expand_c_state.no_move_max_mb = result.no_move_max_mb;
                 self.expand_c_state.can_expand = result.can_expand;
                 self.expand_c_state.reason = result.reason.clone();
+                self.expand_c_state.layout = result.layout.clone();
 
                 if !result.found {
                     self.expand_c_state.message = tr!("未找到当前系统 C 盘");
@@ -317,18 +334,31 @@ impl App {
                         tr!("若本机没有 WinPE，将先自动下载 WinPE；随后会安装 PE 引导并重启进入 WinPE 完成扩容。"),
                     );
 
-                    // 当目标超过“相邻未分配空间”可达上限时，需要移动后方分区(搬数据)，给出醒目警告。
+                    // 当目标超过“相邻未分配空间”可达上限时，需要移动分区(搬数据)，给出醒目警告。
                     let no_move_max = self.expand_c_state.no_move_max_mb;
-                    if self.expand_c_state.target_size_mb > no_move_max && no_move_max > 0 {
+                    if self.expand_c_state.target_size_mb > no_move_max {
                         ui.add_space(6.0);
                         ui.colored_label(
                             egui::Color32::from_rgb(231, 76, 60),
                             tr!(
-                                "⚠ 超过 {} GB 的部分需要移动 C 盘后方分区的数据来腾挪空间：\n· 该过程会搬移后方分区(如 D:)的数据，耗时较长；\n· 进行中切勿断电/强制关机，否则可能损坏后方分区；\n· 此为实验性功能，建议先在测试机/虚拟机验证。\n若只想要稳妥的纯扩展，请把目标控制在 {} GB 以内。",
+                                "⚠ 超过 {} GB 的部分需要移动分区的数据来腾挪空间：\n· 该过程会搬移后方分区(如 D:、恢复分区)的数据，耗时较长；\n· 进行中切勿断电/强制关机，否则可能损坏被移动的分区；\n· 此为实验性功能，建议先在测试机/虚拟机验证。\n若只想要稳妥的纯扩展，请把目标控制在 {} GB 以内。",
                                 format!("{:.1}", no_move_max as f64 / 1024.0),
                                 format!("{:.1}", no_move_max as f64 / 1024.0),
                             ),
                         );
+                        if let Some(plan) = self.expand_c_state.plan() {
+                            ui.add_space(6.0);
+                            ui.label(egui::RichText::new(tr!("扩容方案:")).strong());
+                            for line in plan.describe() {
+                                ui.label(egui::RichText::new(line).small());
+                            }
+                            if plan.moves_target() {
+                                ui.colored_label(
+                                    egui::Color32::from_rgb(241, 196, 15),
+                                    tr!("C 盘本身也会移动，完成后将自动重建引导。"),
+                                );
+                            }
+                        }
                     }
 
                     // 状态消息
@@ -460,8 +490,9 @@ impl App {
         self.expand_c_state.message = tr!("正在准备扩容环境...");
 
         let target_size_mb = if self.expand_c_state.target_size_mb >= self.expand_c_state.max_size_mb
+            && self.expand_c_state.max_size_mb <= self.expand_c_state.no_move_max_mb
         {
-            // 用户选择了最大值，则写 0 表示扩容到最大可用
+            // 用户选择了最大值且无需移动分区，则写 0 表示并入全部相邻未分配空间
             0
         } else {
             self.expand_c_state.target_size_mb
@@ -573,68 +604,63 @@ fn compute_expand_c_info() -> ExpandCLoadResult {
     };
 
     let c_part = &disk.partitions[c_idx];
-    let bytes_per_mb: u64 = 1024 * 1024;
 
     result.found = true;
-    result.current_size_mb = c_part.size_bytes / bytes_per_mb;
-    result.used_mb = c_part.used_bytes / bytes_per_mb;
-    result.free_mb = c_part.free_bytes / bytes_per_mb;
-
-    // C 盘结束位置（字节）
-    let c_end = c_part.offset_bytes + c_part.size_bytes;
-
-    // 1) 计算 C 盘正后方紧邻的未分配空间
-    //    找到所有 offset >= c_end 的分区，按 offset 排序，取最靠近 C 盘的那个。
-    let mut following: Vec<&crate::core::quick_partition::DiskPartitionInfo> = disk
-        .partitions
-        .iter()
-        .filter(|p| p.offset_bytes >= c_end)
-        .collect();
-    following.sort_by_key(|p| p.offset_bytes);
-
-    // 紧邻 C 盘之后的未分配空间（C 盘结束到下一个分区起点之间）
-    let unallocated_after_bytes: u64 = match following.first() {
-        Some(next) => next.offset_bytes.saturating_sub(c_end),
-        None => {
-            // C 盘是磁盘上最后一个分区：使用磁盘尾部的未分配空间
-            disk.size_bytes.saturating_sub(c_end)
+    result.current_size_mb = c_part.size_bytes / MIB;
+    result.used_mb = c_part.used_bytes / MIB;
+    result.free_mb = c_part.free_bytes / MIB;
+
+    // 读分区表，按 PE 端同一套规划算出各代价下的上限
+    let table = match partition_table::read_physical_drive(disk.disk_number).map(|l| l.table) {
+        Ok(Ok(Some(table))) => table,
+        _ => {
+            result.reason = tr!("读取磁盘 {} 的分区表失败", disk.disk_number);
+            return result;
         }
     };
-    let unallocated_after_mb = unallocated_after_bytes / bytes_per_mb;
-
-    // 2) 紧邻 C 盘之后的“下一个分区可缩小让出的空间”。PE 端 Case 2 会把该分区整体右移、
-    //    在 C 之后腾出未分配空间再 extend，因此该部分**计入**可扩上限——但需要搬移数据。
-    //    只有紧贴 C 之后(中间无未分配空间)的、有盘符的基础数据分区才可移动。
-    let mut next_shrinkable_mb: u64 = 0;
-    if let Some(next) = following.first() {
-        // 紧贴：下一个分区起点≈C 盘结束（允许极小对齐间隙）。否则中间是未分配空间，归 Case 1。
-        let adjacent = next.offset_bytes.saturating_sub(c_end) < (2 * 1024 * 1024);
-        let movable = adjacent && !next.is_esp && !next.is_msr && !next.is_recovery;
-        if movable {
-            if let Some(letter) = next.drive_letter {
-                if let Ok(mb) = query_shrink_max(letter) {
-                    next_shrinkable_mb = mb;
-                    log::info!("[EXPAND] 后方分区 {}: 可让出 {} MB（需移动数据，Case 2）", letter, mb);
-                }
-            }
+    let Some(number) = table.partition_at(c_part.offset_bytes).map(|p| p.number) else {
+        result.reason = tr!("分区表中找不到 C 盘");
+        return result;
+    };
+    let layout = DiskLayout::from_table(&table, |off| {
+        disk.partitions
+            .iter()
+            .find(|p| p.offset_bytes == off)
+            .and_then(|p| p.drive_letter)
+    });
+    let limits = match expand_limits(&layout, number) {
+        Ok(limits) => limits,
+        Err(e) => {
+            result.reason = tr!("无法规划扩容：{}", e);
+            return result;
+        }
+    };
+
+    // 空间不够时收缩后方最后一个数据分区；能让出多少由文件系统决定（无盘符时无法查询，按不可收缩估算）
+    let mut shrinkable_mb: u64 = 0;
+    if let Some((_, Some(letter))) = limits.shrink_candidate {
+        if let Ok(mb) = query_shrink_max(letter) {
+            shrinkable_mb = mb;
+            log::info!("[EXPAND] 分区 {}: 可让出 {} MB（需移动数据，Case 2）", letter, mb);
         }
     }
 
     // 不移动数据即可达到的上限 = 当前 + 相邻未分配空间（Case 1，纯 extend）。
-    let no_move_max_mb = result.current_size_mb + unallocated_after_mb;
-    // 总可扩上限 = Case 1 + 后方分区可让出空间（Case 2，搬数据）。
-    let max_size_mb = no_move_max_mb + next_shrinkable_mb;
+    let no_move_max_mb = limits.no_move / MIB;
+    // 总可扩上限 = 搬移分区可并入的全部空闲 + 收缩数据分区让出的空间（Case 2，搬数据）。
+    let max_size_mb = limits.without_shrink / MIB + shrinkable_mb;
     result.no_move_max_mb = no_move_max_mb;
     result.max_size_mb = max_size_mb;
+    result.layout = Some((layout, number));
 
     // 至少要能扩 1GB 才认为可以扩容
     if max_size_mb > result.current_size_mb + 1024 {
         result.can_expand = true;
-        if next_shrinkable_mb > 1024 {
+        if max_size_mb > no_move_max_mb + 1024 {
             result.reason = tr!(
-                "可无损并入：相邻未分配约 {} GB（直接扩）+ 后方分区可让出约 {} GB（需移动该分区的数据）。",
-                format!("{:.1}", unallocated_after_mb as f64 / 1024.0),
-                format!("{:.1}", next_shrinkable_mb as f64 / 1024.0),
+                "可无损并入：相邻未分配约 {} GB（直接扩）+ 移动分区后可再并入约 {} GB（需移动分区的数据）。",
+                format!("{:.1}", (no_move_max_mb - result.current_size_mb) as f64 / 1024.0),
+                format!("{:.1}", (max_size_mb - no_move_max_mb) as f64 / 1024.0),
             );
         }
     } else {
@@ -20,7 +20,10 @@ pub mod quick_partition;
 pub mod expand_c;
 pub mod image_verify;
 pub mod hash_verify;
+pub mod driver_repo;
+pub mod driver_report;
 pub mod password_reset;
+pub mod partition_restore;
 
 // 重新导出常用类型
 pub use types::{DriverBackupMode, AppxPackageInfo, InstalledSoftware, WindowsPartitionInfo, ImageVerifyResult};
@@ -257,6 +260,27 @@ impl App {
                     self.hash_verify_loading = false;
                 }
 
+                if ui
+                    .add(egui::Button::new(tr!("驱动库")).min_size(button_size))
+                    .clicked()
+                {
+                    self.init_driver_repo_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new(tr!("驱动导入报告")).min_size(button_size))
+                    .clicked()
+                {
+                    self.init_driver_report_dialog();
+                }
+
+                if ui
+                    .add(egui::Button::new(tr!("分区表还原")).min_size(button_size))
+                    .clicked()
+                {
+                    self.init_partition_restore_dialog();
+                }
+
                 if ui
                     .add(egui::Button::new(tr!("密码重置")).min_size(button_size))
                     .clicked()
@@ -307,7 +331,9 @@ impl App {
         self.render_repair_boot_dialog(ui);
         self.render_bitlocker_manage_dialog(ui);
         self.render_hash_verify_dialog(ui);
+        self.render_driver_repo_dialog(ui);
         self.render_password_reset_dialog(ui);
+        self.render_partition_restore_dialog(ui);
 
         // 显示工具状态
         if !self.tool_message.is_empty() {
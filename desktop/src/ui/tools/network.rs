@@ -3,7 +3,7 @@
 //! 提供网络信息获取和网络重置等功能
 
 use crate::tr;
-use crate::utils::cmd::create_command;
+use crate::utils::command;
 
 /// 使用 Windows API 获取详细的网络信息
 pub fn get_detailed_network_info() -> Vec<crate::core::hardware_info::NetworkAdapterInfo> {
@@ -264,7 +264,7 @@ pub fn reset_network() -> (usize, usize) {
     let mut fail_count = 0;
 
     for (cmd, args) in &commands {
-        match create_command(cmd).args(*args).output() {
+        match command::run(cmd, *args) {
             Ok(output) => {
                 if output.status.success() {
                     success_count += 1;
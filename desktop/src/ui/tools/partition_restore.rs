@@ -0,0 +1,348 @@
+//! 分区表还原对话框
+//!
+//! 一键分区、无损扩容等破坏性操作前会自动备份分区表（见 `lr_core::partition_backup`）。
+//! 这里列出各分区上保存的备份，按磁盘签名/GUID 与容量找到原磁盘，把分区表原样写回。
+
+use egui;
+use std::path::PathBuf;
+use std::sync::mpsc;
+
+use lr_core::partition_backup::{
+    find_all_backups, find_restore_target, restore_physical_drive, BackupDescription,
+    PartitionBackup, TargetMatch, DESCRIPTION_FILE,
+};
+
+use crate::app::App;
+use crate::tr;
+
+/// 后台还原结果
+pub type PartitionRestoreResult = Result<String, String>;
+
+impl App {
+    /// 从工具箱打开分区表还原对话框
+    pub fn init_partition_restore_dialog(&mut self) {
+        self.show_partition_restore_dialog = true;
+        self.partition_restore_message.clear();
+        self.partition_restore_selected = None;
+        self.partition_restore_target = None;
+        self.partition_restore_allow_id_change = false;
+        self.partition_restore_confirm = false;
+        self.partition_restore_backups = find_all_backups();
+    }
+
+    /// 选中一份备份，查找对应的目标磁盘
+    fn select_partition_restore_backup(&mut self, index: usize) {
+        self.partition_restore_selected = Some(index);
+        self.partition_restore_allow_id_change = false;
+        self.partition_restore_confirm = false;
+        self.partition_restore_target = self
+            .partition_restore_backups
+            .get(index)
+            .and_then(|(_, d)| find_restore_target(d));
+    }
+
+    /// 渲染分区表还原对话框
+    pub fn render_partition_restore_dialog(&mut self, ui: &mut egui::Ui) {
+        if !self.show_partition_restore_dialog {
+            return;
+        }
+
+        let mut should_close = false;
+        let mut should_refresh = false;
+        let mut should_select: Option<usize> = None;
+        let mut should_restore = false;
+        let mut browsed: Option<PathBuf> = None;
+
+        egui::Window::new(tr!("分区表还原"))
+            .resizable(true)
+            .default_width(820.0)
+            .default_height(480.0)
+            .show(ui.ctx(), |ui| {
+                ui.label(tr!(
+                    "一键分区、无损扩容等操作前会自动备份分区表。选择一份备份，把分区表写回原磁盘。"
+                ));
+                ui.label(tr!("只还原分区表，不还原分区中的数据。"));
+                ui.add_space(8.0);
+
+                ui.horizontal(|ui| {
+                    let idle = !self.partition_restore_running;
+                    if ui
+                        .add_enabled(idle, egui::Button::new(tr!("刷新")))
+                        .clicked()
+                    {
+                        should_refresh = true;
+                    }
+                    if ui
+                        .add_enabled(idle, egui::Button::new(tr!("浏览...")))
+                        .clicked()
+                    {
+                        browsed = rfd::FileDialog::new().pick_folder();
+                    }
+                    if self.partition_restore_running {
+                        ui.spinner();
+                    }
+                });
+
+                ui.add_space(6.0);
+
+                if self.partition_restore_backups.is_empty() {
+                    ui.colored_label(egui::Color32::GRAY, tr!("没有找到分区表备份"));
+                } else {
+                    egui::ScrollArea::both()
+                        .max_height(220.0)
+                        .auto_shrink([false, false])
+                        .show(ui, |ui| {
+                            egui::Grid::new("partition_restore_grid")
+                                .striped(true)
+                                .num_columns(6)
+                                .show(ui, |ui| {
+                                    ui.strong(tr!("备份时间"));
+                                    ui.strong(tr!("磁盘"));
+                                    ui.strong(tr!("分区表"));
+                                    ui.strong(tr!("磁盘标识"));
+                                    ui.strong(tr!("分区数"));
+                                    ui.strong(tr!("位置"));
+                                    ui.end_row();
+
+                                    for (i, (dir, d)) in
+                                        self.partition_restore_backups.iter().enumerate()
+                                    {
+                                        let selected = self.partition_restore_selected == Some(i);
+                                        if ui
+                                            .selectable_label(selected, format_created(d.created))
+                                            .clicked()
+                                        {
+                                            should_select = Some(i);
+                                        }
+                                        ui.label(tr!(
+                                            "磁盘 {} ({} GB)",
+                                            d.disk_number,
+                                            format!("{:.1}", d.disk_size as f64 / 1024f64.powi(3))
+                                        ));
+                                        let style = format!("{:?}", d.style).to_uppercase();
+                                        if d.raw {
+                                            ui.label(tr!("{}（原始扇区）", style));
+                                        } else {
+                                            ui.label(style);
+                                        }
+                                        ui.label(&d.disk_id);
+                                        ui.label(d.partitions.len().to_string());
+                                        ui.label(dir.display().to_string());
+                                        ui.end_row();
+                                    }
+                                });
+                        });
+                }
+
+                ui.add_space(8.0);
+
+                if let Some((_, d)) = self
+                    .partition_restore_selected
+                    .and_then(|i| self.partition_restore_backups.get(i))
+                {
+                    for p in &d.partitions {
+                        ui.label(format!(
+                            "#{}  {}  {} MB @ {} MB{}",
+                            p.number,
+                            p.partition_type,
+                            p.length / 1024 / 1024,
+                            p.offset / 1024 / 1024,
+                            if p.active { "  *" } else { "" }
+                        ));
+                    }
+                    ui.add_space(6.0);
+
+                    match self.partition_restore_target {
+                        Some((disk, TargetMatch::Same)) => {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(0, 200, 0),
+                                tr!("目标磁盘: 磁盘 {}（签名/GUID 与容量一致）", disk),
+                            );
+                        }
+                        Some((disk, TargetMatch::Blank)) => {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(255, 165, 0),
+                                tr!("目标磁盘: 磁盘 {}（容量一致，当前没有分区表）", disk),
+                            );
+                        }
+                        Some((disk, _)) => {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(220, 50, 47),
+                                tr!(
+                                    "磁盘 {} 容量一致，但签名/GUID 已变化（可能已被重新分区）",
+                                    disk
+                                ),
+                            );
+                            ui.checkbox(
+                                &mut self.partition_restore_allow_id_change,
+                                tr!("确认是同一块磁盘，仍然还原"),
+                            );
+                        }
+                        None => {
+                            ui.colored_label(
+                                egui::Color32::from_rgb(220, 50, 47),
+                                tr!("没有找到与备份匹配的磁盘（需容量与扇区大小一致）"),
+                            );
+                        }
+                    }
+                }
+
+                if self.partition_restore_confirm {
+                    ui.add_space(6.0);
+                    ui.colored_label(
+                        egui::Color32::from_rgb(255, 165, 0),
+                        tr!("将覆盖目标磁盘当前的分区表，确定继续？"),
+                    );
+                    ui.horizontal(|ui| {
+                        if ui.button(tr!("确定还原")).clicked() {
+                            should_restore = true;
+                        }
+                        if ui.button(tr!("取消")).clicked() {
+                            self.partition_restore_confirm = false;
+                        }
+                    });
+                }
+
+                if !self.partition_restore_message.is_empty() {
+                    ui.add_space(6.0);
+                    ui.label(&self.partition_restore_message);
+                }
+
+                ui.add_space(10.0);
+                ui.horizontal(|ui| {
+                    let can_restore = !self.partition_restore_running
+                        && !self.partition_restore_confirm
+                        && match self.partition_restore_target {
+                            Some((_, TargetMatch::Same | TargetMatch::Blank)) => true,
+                            Some((_, TargetMatch::IdChanged)) => {
+                                self.partition_restore_allow_id_change
+                            }
+                            _ => false,
+                        };
+                    if ui
+                        .add_enabled(can_restore, egui::Button::new(tr!("还原分区表")))
+                        .clicked()
+                    {
+                        self.partition_restore_confirm = true;
+                    }
+                    if ui.button(tr!("关闭")).clicked() {
+                        should_close = true;
+                    }
+                });
+            });
+
+        if should_refresh {
+            self.init_partition_restore_dialog();
+        }
+        if let Some(dir) = browsed {
+            self.add_partition_restore_backup(dir);
+        }
+        if let Some(i) = should_select {
+            self.select_partition_restore_backup(i);
+        }
+        if should_restore {
+            self.partition_restore_confirm = false;
+            self.start_partition_restore();
+        }
+        if should_close {
+            self.show_partition_restore_dialog = false;
+        }
+    }
+
+    /// 手动选择的备份目录加入列表并选中
+    fn add_partition_restore_backup(&mut self, dir: PathBuf) {
+        let description = std::fs::read_to_string(dir.join(DESCRIPTION_FILE))
+            .ok()
+            .and_then(|json| serde_json::from_str::<BackupDescription>(&json).ok());
+        let Some(description) = description else {
+            self.partition_restore_message = tr!("所选目录不是分区表备份: {}", dir.display());
+            return;
+        };
+        let index = match self
+            .partition_restore_backups
+            .iter()
+            .position(|(d, _)| *d == dir)
+        {
+            Some(i) => i,
+            None => {
+                self.partition_restore_backups.insert(0, (dir, description));
+                0
+            }
+        };
+        self.select_partition_restore_backup(index);
+    }
+
+    /// 后台校验备份并写回目标磁盘
+    fn start_partition_restore(&mut self) {
+        if self.partition_restore_running {
+            return;
+        }
+        let Some((dir, _)) = self
+            .partition_restore_selected
+            .and_then(|i| self.partition_restore_backups.get(i))
+            .cloned()
+        else {
+            return;
+        };
+        let Some((disk, _)) = self.partition_restore_target else {
+            return;
+        };
+        let allow_id_change = self.partition_restore_allow_id_change;
+
+        self.partition_restore_running = true;
+        self.partition_restore_message = tr!("正在还原分区表...");
+
+        let (tx, rx) = mpsc::channel::<PartitionRestoreResult>();
+        self.partition_restore_rx = Some(rx);
+
+        std::thread::spawn(move || {
+            let result = PartitionBackup::load(&dir)
+                .and_then(|backup| restore_physical_drive(disk, &backup, allow_id_change))
+                .map(|table| match table {
+                    Some(table) => tr!(
+                        "分区表已还原到磁盘 {}，共 {} 个分区",
+                        disk,
+                        table.volumes().count()
+                    ),
+                    None => tr!(
+                        "原始扇区已写回磁盘 {}（备份时分区表已损坏，仍无法识别）",
+                        disk
+                    ),
+                })
+                .map_err(|e| {
+                    log::error!("还原分区表失败 {}: {}", dir.display(), e);
+                    tr!("还原分区表失败: {}", e)
+                });
+            let _ = tx.send(result);
+        });
+    }
+
+    /// 轮询分区表还原后台任务（在主循环中调用）
+    pub fn check_partition_restore_status(&mut self) {
+        if let Some(ref rx) = self.partition_restore_rx {
+            if let Ok(result) = rx.try_recv() {
+                match result {
+                    Ok(message) => self.partition_restore_message = message,
+                    Err(e) => self.partition_restore_message = e,
+                }
+                self.partition_restore_running = false;
+                self.partition_restore_rx = None;
+                // 还原后磁盘标识可能已变化，重新匹配
+                if let Some(i) = self.partition_restore_selected {
+                    self.select_partition_restore_backup(i);
+                }
+            }
+        }
+    }
+}
+
+/// 备份时间（本地时间）
+fn format_created(created: u64) -> String {
+    chrono::DateTime::from_timestamp(created as i64, 0)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M:%S")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
@@ -344,13 +344,11 @@ impl App {
 
 /// 在线列出当前运行系统的本地账户（PowerShell `Get-LocalUser`）。
 fn online_list_accounts() -> Result<Vec<lr_core::sam::SamAccount>, String> {
-    let out = lr_core::command::new_command("powershell")
-        .args([
-            "-NoProfile",
-            "-Command",
-            "[Console]::OutputEncoding=[System.Text.Encoding]::UTF8; Get-LocalUser | ForEach-Object { \"$($_.Name)|$($_.Enabled)\" }",
-        ])
-        .output()
+    let out = lr_core::command::run("powershell", [
+        "-NoProfile",
+        "-Command",
+        "[Console]::OutputEncoding=[System.Text.Encoding]::UTF8; Get-LocalUser | ForEach-Object { \"$($_.Name)|$($_.Enabled)\" }",
+    ])
         .map_err(|e| tr!("执行 Get-LocalUser 失败：{}", e))?;
     if !out.status.success() {
         return Err(lr_core::encoding::gbk_to_utf8(&out.stderr));
@@ -380,9 +378,7 @@ fn online_list_accounts() -> Result<Vec<lr_core::sam::SamAccount>, String> {
 
 /// 在线清除当前系统某本地账户的密码（`net user "<name>" ""`），并启用账户。
 fn online_clear_password(username: &str) -> Result<bool, String> {
-    let out = lr_core::command::new_command("net")
-        .args(["user", username, ""])
-        .output()
+    let out = lr_core::command::run("net", ["user", username, ""])
         .map_err(|e| tr!("执行 net user 失败：{}", e))?;
     if !out.status.success() {
         let err = lr_core::encoding::gbk_to_utf8(&out.stderr);
@@ -391,8 +387,6 @@ fn online_clear_password(username: &str) -> Result<bool, String> {
         return Err(msg.trim().to_string());
     }
     // 顺带启用被禁用的账户（失败忽略）
-    let _ = lr_core::command::new_command("net")
-        .args(["user", username, "/active:yes"])
-        .output();
+    let _ = lr_core::command::run("net", ["user", username, "/active:yes"]);
     Ok(true)
 }
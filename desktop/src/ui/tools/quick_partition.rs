@@ -8,10 +8,16 @@ use crate::tr;
 use crate::app::App;
 use crate::core::disk::PartitionStyle;
 use crate::core::quick_partition::{
-    execute_quick_partition, get_next_available_drive_letter, get_physical_disks,
-    get_recommended_partition_style, get_unallocated_space_after_partition_with_disk,
-    get_used_drive_letters, resize_existing_partition, PartitionLayout, PhysicalDisk,
-    ResizePartitionResult,
+    execute_layout_plan, execute_quick_partition, get_next_available_drive_letter,
+    get_physical_disks, get_recommended_partition_style,
+    get_unallocated_space_after_partition_with_disk, get_used_drive_letters,
+    plan_recommended_layout, preset_layouts, resize_existing_partition, with_table_backup,
+    PartitionLayout, PhysicalDisk, ResizePartitionResult,
+};
+use lr_core::diskpart_script::TableStyle;
+use lr_core::partition_plan::{LayoutPlan, PartitionRole};
+use lr_core::partition_preset::{
+    export_presets, import_presets, merge_presets, PartitionPreset, PresetPartition, PresetSize,
 };
 
 /// 分区编辑器状态
@@ -53,6 +59,12 @@ pub struct PartitionEditorState {
     pub resize_existing_min_gb: f64,
     /// 调整已有分区大小的最大值（GB）
     pub resize_existing_max_gb: f64,
+    /// 最近一次生成的 Windows 推荐布局（编辑器内容与之不符时按普通一键分区执行）
+    pub planned_layout: Option<LayoutPlan>,
+    /// 预设下拉框中选中的预设索引
+    pub selected_preset: Option<usize>,
+    /// 「保存为预设」的名称输入
+    pub preset_name_text: String,
 }
 
 impl Default for PartitionEditorState {
@@ -76,6 +88,9 @@ impl Default for PartitionEditorState {
             resize_existing_index: None,
             resize_existing_min_gb: 0.0,
             resize_existing_max_gb: 0.0,
+            planned_layout: None,
+            selected_preset: None,
+            preset_name_text: String::new(),
         }
     }
 }
@@ -176,7 +191,10 @@ impl EditablePartition {
             drive_letter: self.drive_letter,
             label: self.label.clone(),
             is_esp: self.is_esp,
+            is_msr: self.is_msr,
+            is_recovery: self.is_recovery,
             file_system: self.file_system.clone(),
+            fill: false,
         }
     }
     
@@ -498,6 +516,221 @@ impl App {
         self.quick_partition_state.message = tr!("无法创建 ESP 分区：没有足够的可用空间");
     }
 
+    /// 按 Windows 推荐布局重新规划整盘
+    ///
+    /// 已规划的普通分区依次作为 Windows 分区和数据分区，ESP / MSR / 恢复分区按启动模式自动补齐。
+    fn apply_recommended_layout(&mut self) {
+        let Some(info) = &self.system_info else {
+            self.quick_partition_state.message = tr!("无法获取启动模式");
+            return;
+        };
+        let boot_mode = info.boot_mode.clone();
+
+        let disk = match self
+            .quick_partition_state
+            .editor
+            .selected_disk_index
+            .and_then(|idx| self.quick_partition_state.physical_disks.get(idx).cloned())
+        {
+            Some(d) => d,
+            None => return,
+        };
+
+        let layouts: Vec<PartitionLayout> = self
+            .quick_partition_state
+            .editor
+            .partition_layouts
+            .iter()
+            .filter(|p| !p.is_existing && !p.is_esp && !p.is_msr && !p.is_recovery)
+            .map(|p| p.to_layout())
+            .collect();
+
+        let plan = match plan_recommended_layout(&disk, &boot_mode, &layouts) {
+            Ok(plan) => plan,
+            Err(e) => {
+                self.quick_partition_state.message = tr!("无法生成推荐布局: {}", e);
+                return;
+            }
+        };
+
+        let editor = &mut self.quick_partition_state.editor;
+        editor.partition_style = match plan.style {
+            TableStyle::Gpt => PartitionStyle::GPT,
+            TableStyle::Mbr => PartitionStyle::MBR,
+        };
+        editor.show_esp_button = plan.style == TableStyle::Gpt;
+
+        // 一键分区会清除整个磁盘，推荐布局直接替换编辑器中的全部分区
+        editor.partition_layouts.clear();
+        for planned in &plan.partitions {
+            self.quick_partition_state.partition_id_counter += 1;
+            let size_gb = planned.length as f64 / 1024.0 / 1024.0 / 1024.0;
+            let mut partition = EditablePartition::new(
+                self.quick_partition_state.partition_id_counter,
+                size_gb,
+                planned.letter,
+            );
+            partition.label = planned.label.clone();
+            partition.is_esp = planned.role == PartitionRole::Esp;
+            partition.is_msr = planned.role == PartitionRole::Msr;
+            partition.is_recovery = planned.role == PartitionRole::Recovery;
+            partition.file_system = planned
+                .file_system
+                .map(|fs| fs.as_str().to_ascii_uppercase())
+                .unwrap_or_default();
+            self.quick_partition_state
+                .editor
+                .partition_layouts
+                .push(partition);
+        }
+
+        self.quick_partition_state.message = tr!("已生成 Windows 推荐布局");
+        self.quick_partition_state.editor.planned_layout = Some(plan);
+    }
+
+    /// 应用预设：按所选磁盘大小换算后替换编辑器中的全部分区
+    fn apply_partition_preset(&mut self, index: usize) {
+        let Some(preset) = self.app_config.partition_presets.get(index).cloned() else {
+            return;
+        };
+        let disk = match self
+            .quick_partition_state
+            .editor
+            .selected_disk_index
+            .and_then(|idx| self.quick_partition_state.physical_disks.get(idx).cloned())
+        {
+            Some(d) => d,
+            None => return,
+        };
+        let default_style = match &self.system_info {
+            Some(info) => get_recommended_partition_style(&info.boot_mode),
+            None => self.quick_partition_state.editor.partition_style,
+        };
+
+        let (style, layouts) = match preset_layouts(&preset, &disk, default_style) {
+            Ok(r) => r,
+            Err(e) => {
+                self.quick_partition_state.message = tr!("无法应用预设 \"{}\": {}", preset.name, e);
+                return;
+            }
+        };
+
+        let state = &mut self.quick_partition_state;
+        state.editor.partition_style = style;
+        state.editor.show_esp_button = style == PartitionStyle::GPT;
+        state.editor.planned_layout = None;
+        state.editor.partition_layouts.clear();
+        for layout in layouts {
+            state.partition_id_counter += 1;
+            let mut partition =
+                EditablePartition::new(state.partition_id_counter, layout.size_gb, layout.drive_letter);
+            partition.label = layout.label;
+            partition.is_esp = layout.is_esp;
+            partition.is_msr = layout.is_msr;
+            partition.is_recovery = layout.is_recovery;
+            partition.file_system = layout.file_system;
+            state.editor.partition_layouts.push(partition);
+        }
+        state.message = tr!("已应用预设 \"{}\"", preset.name);
+    }
+
+    /// 把编辑器中新规划的分区保存为预设（同名覆盖，最后一个分区占用剩余空间）
+    fn save_current_as_preset(&mut self) {
+        let editor = &self.quick_partition_state.editor;
+        let name = editor.preset_name_text.trim().to_string();
+        let new_partitions: Vec<&EditablePartition> = editor
+            .partition_layouts
+            .iter()
+            .filter(|p| !p.is_existing)
+            .collect();
+        let last = new_partitions.len().saturating_sub(1);
+        let preset = PartitionPreset {
+            name: name.clone(),
+            style: match editor.partition_style {
+                PartitionStyle::GPT => Some(TableStyle::Gpt),
+                PartitionStyle::MBR => Some(TableStyle::Mbr),
+                _ => None,
+            },
+            partitions: new_partitions
+                .iter()
+                .enumerate()
+                .map(|(i, p)| PresetPartition {
+                    size: if i == last {
+                        PresetSize::Fill
+                    } else {
+                        PresetSize::Gb(p.size_gb)
+                    },
+                    label: p.label.clone(),
+                    drive_letter: p.drive_letter,
+                    file_system: p.file_system.clone(),
+                    is_esp: p.is_esp,
+                    is_msr: p.is_msr,
+                    is_recovery: p.is_recovery,
+                })
+                .collect(),
+        };
+
+        if let Err(e) = preset.validate() {
+            self.quick_partition_state.message = tr!("无法保存预设: {}", e);
+            return;
+        }
+
+        let mut presets = self.app_config.partition_presets.clone();
+        merge_presets(&mut presets, vec![preset]);
+        self.quick_partition_state.editor.selected_preset =
+            presets.iter().position(|p| p.name == name);
+        self.app_config.set_partition_presets(presets);
+        self.quick_partition_state.message = tr!("已保存预设 \"{}\"", name);
+    }
+
+    /// 从 JSON 文件导入预设
+    fn import_partition_presets(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(tr!("分区预设"), &["json"])
+            .pick_file()
+        else {
+            return;
+        };
+
+        let imported = std::fs::read_to_string(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| import_presets(&json).map_err(|e| e.to_string()));
+        match imported {
+            Ok(imported) => {
+                let mut presets = self.app_config.partition_presets.clone();
+                let count = merge_presets(&mut presets, imported);
+                self.app_config.set_partition_presets(presets);
+                self.quick_partition_state.message = tr!("已导入 {} 个预设", count);
+            }
+            Err(e) => {
+                log::error!("导入分区预设失败 {}: {}", path.display(), e);
+                self.quick_partition_state.message = tr!("导入预设失败: {}", e);
+            }
+        }
+    }
+
+    /// 把全部预设导出为 JSON 文件
+    fn export_partition_presets(&mut self) {
+        let Some(path) = rfd::FileDialog::new()
+            .add_filter(tr!("分区预设"), &["json"])
+            .set_file_name("partition_presets.json")
+            .save_file()
+        else {
+            return;
+        };
+
+        match std::fs::write(&path, export_presets(&self.app_config.partition_presets)) {
+            Ok(()) => {
+                log::info!("分区预设已导出至: {}", path.display());
+                self.quick_partition_state.message = tr!("预设已导出");
+            }
+            Err(e) => {
+                log::error!("导出分区预设失败: {}", e);
+                self.quick_partition_state.message = tr!("导出预设失败: {}", e);
+            }
+        }
+    }
+
     /// 删除指定分区
     fn delete_partition(&mut self, index: usize) {
         let layouts = &mut self.quick_partition_state.editor.partition_layouts;
@@ -549,15 +782,25 @@ impl App {
             return;
         }
 
-        // 转换分区布局
-        let layouts: Vec<PartitionLayout> = new_partitions
+        // 转换分区布局，最后一个分区使用剩余空间
+        let mut layouts: Vec<PartitionLayout> = new_partitions
             .iter()
             .map(|p| p.to_layout())
             .collect();
+        if let Some(last) = layouts.last_mut() {
+            last.fill = true;
+        }
 
         let partition_style = state.editor.partition_style;
         let disk_number = disk.disk_number;
 
+        // 推荐布局生成后未被改动过时，按规划结果执行（带 MSR / 恢复分区及对齐偏移）
+        let plan = state
+            .editor
+            .planned_layout
+            .clone()
+            .filter(|plan| plan_matches(plan, &new_partitions));
+
         self.quick_partition_state.executing = true;
         self.quick_partition_state.show_confirm_dialog = false;
         self.quick_partition_state.message = tr!("正在执行分区操作...");
@@ -566,7 +809,10 @@ impl App {
         self.quick_partition_result_rx = Some(rx);
 
         std::thread::spawn(move || {
-            let result = execute_quick_partition(disk_number, partition_style, &layouts);
+            let result = with_table_backup(disk_number, "quick_partition", || match plan {
+                Some(plan) => execute_layout_plan(disk_number, &plan),
+                None => execute_quick_partition(disk_number, partition_style, &layouts),
+            });
             let _ = tx.send(result);
         });
     }
@@ -586,6 +832,11 @@ impl App {
         let mut should_close = false;
         let mut should_add_partition = false;
         let mut should_add_esp = false;
+        let mut should_apply_recommended = false;
+        let mut should_apply_preset: Option<usize> = None;
+        let mut should_save_preset = false;
+        let mut should_import_presets = false;
+        let mut should_export_presets = false;
         let mut should_delete_partition: Option<usize> = None;
         let mut should_execute = false;
         let mut should_show_confirm = false;
@@ -726,6 +977,69 @@ impl App {
                                         should_add_esp = true;
                                     }
                                 }
+
+                                if ui
+                                    .add_enabled(self.system_info.is_some(), egui::Button::new(tr!("Windows 推荐布局")))
+                                    .on_hover_text(tr!("按当前启动模式自动规划 ESP / MSR / 系统 / 恢复分区"))
+                                    .clicked()
+                                {
+                                    should_apply_recommended = true;
+                                }
+                            });
+
+                            ui.add_space(5.0);
+
+                            // 分区预设
+                            ui.horizontal(|ui| {
+                                ui.label(tr!("预设:"));
+
+                                let presets = &self.app_config.partition_presets;
+                                let editor = &mut self.quick_partition_state.editor;
+                                if editor.selected_preset.is_some_and(|i| i >= presets.len()) {
+                                    editor.selected_preset = None;
+                                }
+                                let selected_text = editor
+                                    .selected_preset
+                                    .map(|i| presets[i].name.clone())
+                                    .unwrap_or_else(|| tr!("选择预设"));
+                                egui::ComboBox::from_id_salt("quick_partition_preset")
+                                    .selected_text(selected_text)
+                                    .show_ui(ui, |ui| {
+                                        for (i, preset) in presets.iter().enumerate() {
+                                            ui.selectable_value(&mut editor.selected_preset, Some(i), &preset.name);
+                                        }
+                                    });
+
+                                if ui
+                                    .add_enabled(editor.selected_preset.is_some(), egui::Button::new(tr!("应用预设")))
+                                    .clicked()
+                                {
+                                    should_apply_preset = editor.selected_preset;
+                                }
+
+                                ui.add_space(10.0);
+                                ui.add(
+                                    egui::TextEdit::singleline(&mut editor.preset_name_text)
+                                        .hint_text(tr!("预设名称"))
+                                        .desired_width(120.0),
+                                );
+                                if ui
+                                    .add_enabled(!editor.preset_name_text.trim().is_empty(), egui::Button::new(tr!("保存为预设")))
+                                    .clicked()
+                                {
+                                    should_save_preset = true;
+                                }
+
+                                ui.add_space(10.0);
+                                if ui.button(tr!("导入...")).clicked() {
+                                    should_import_presets = true;
+                                }
+                                if ui
+                                    .add_enabled(!presets.is_empty(), egui::Button::new(tr!("导出...")))
+                                    .clicked()
+                                {
+                                    should_export_presets = true;
+                                }
                             });
 
                             ui.add_space(15.0);
@@ -1298,6 +1612,26 @@ impl App {
             self.add_esp_partition();
         }
 
+        if should_apply_recommended {
+            self.apply_recommended_layout();
+        }
+
+        if let Some(idx) = should_apply_preset {
+            self.apply_partition_preset(idx);
+        }
+
+        if should_save_preset {
+            self.save_current_as_preset();
+        }
+
+        if should_import_presets {
+            self.import_partition_presets();
+        }
+
+        if should_export_presets {
+            self.export_partition_presets();
+        }
+
         if let Some(idx) = should_delete_partition {
             self.delete_partition(idx);
         }
@@ -1484,3 +1818,14 @@ impl App {
         }
     }
 }
+
+/// 编辑器中的新分区是否仍与推荐布局一致（数量、大小、盘符、卷标）
+fn plan_matches(plan: &LayoutPlan, partitions: &[&EditablePartition]) -> bool {
+    plan.partitions.len() == partitions.len()
+        && plan.partitions.iter().zip(partitions).all(|(planned, p)| {
+            let planned_gb = planned.length as f64 / 1024.0 / 1024.0 / 1024.0;
+            (planned_gb - p.size_gb).abs() < 0.01
+                && planned.letter == p.drive_letter
+                && planned.label == p.label
+        })
+}
@@ -3,7 +3,7 @@
 //! 提供对离线Windows分区的精确版本检测
 
 use std::path::Path;
-use crate::utils::cmd::create_command;
+use crate::utils::command;
 
 /// Windows版本详细信息
 #[derive(Debug, Clone)]
@@ -186,9 +186,7 @@ fn read_version_from_registry(partition: &str) -> Option<WindowsVersionInfo> {
     let reg_path = format!("HKLM\\{}\\Microsoft\\Windows NT\\CurrentVersion", temp_key);
 
     // 尝试加载注册表
-    let load_result = create_command("reg.exe")
-        .args(["load", &format!("HKLM\\{}", temp_key), &software_hive])
-        .output();
+    let load_result = command::run("reg.exe", ["load", &format!("HKLM\\{}", temp_key), &software_hive]);
 
     if load_result.is_err() {
         return None;
@@ -197,14 +195,10 @@ fn read_version_from_registry(partition: &str) -> Option<WindowsVersionInfo> {
     let load_output = load_result.unwrap();
     if !load_output.status.success() {
         // 注册表可能已被加载，尝试先卸载再加载
-        let _ = create_command("reg.exe")
-            .args(["unload", &format!("HKLM\\{}", temp_key)])
-            .output();
+        let _ = command::run("reg.exe", ["unload", &format!("HKLM\\{}", temp_key)]);
         
         // 重试加载
-        let retry_load = create_command("reg.exe")
-            .args(["load", &format!("HKLM\\{}", temp_key), &software_hive])
-            .output();
+        let retry_load = command::run("reg.exe", ["load", &format!("HKLM\\{}", temp_key), &software_hive]);
         
         if retry_load.is_err() || !retry_load.unwrap().status.success() {
             return None;
@@ -220,9 +214,7 @@ fn read_version_from_registry(partition: &str) -> Option<WindowsVersionInfo> {
     let edition_id = query_reg_value(&reg_path, "EditionID");
 
     // 卸载注册表
-    let _ = create_command("reg.exe")
-        .args(["unload", &format!("HKLM\\{}", temp_key)])
-        .output();
+    let _ = command::run("reg.exe", ["unload", &format!("HKLM\\{}", temp_key)]);
 
     Some(WindowsVersionInfo {
         product_name,
@@ -254,16 +246,14 @@ fn read_version_from_kernel32(partition: &str) -> Option<WindowsVersionInfo> {
             kernel32_path.replace('\'', "''")
         );
 
-        let output = create_command("powershell")
-            .args([
-                "-NoProfile",
-                "-NonInteractive",
-                "-ExecutionPolicy",
-                "Bypass",
-                "-Command",
-                &ps_script,
-            ])
-            .output()
+        let output = command::run("powershell", [
+            "-NoProfile",
+            "-NonInteractive",
+            "-ExecutionPolicy",
+            "Bypass",
+            "-Command",
+            &ps_script,
+        ])
             .ok()?;
 
         if !output.status.success() {
@@ -309,9 +299,7 @@ fn read_version_from_kernel32(partition: &str) -> Option<WindowsVersionInfo> {
 
 /// 查询注册表值
 fn query_reg_value(key_path: &str, value_name: &str) -> Option<String> {
-    let output = create_command("reg.exe")
-        .args(["query", key_path, "/v", value_name])
-        .output()
+    let output = command::run("reg.exe", ["query", key_path, "/v", value_name])
         .ok()?;
 
     if !output.status.success() {
@@ -29,7 +29,7 @@ pub fn run_command<S: AsRef<OsStr>>(program: S, args: &[&str]) -> std::io::Resul
         log::debug!("[CMD] {} {}", program_str, args.join(" "));
     }
 
-    let output = create_command(program).args(args).output()?;
+    let output = lr_core::command::run(program, args)?;
 
     #[cfg(debug_assertions)]
     {
@@ -76,7 +76,7 @@ pub fn run_command_with_args<S: AsRef<OsStr>>(program: S, args: Vec<String>) ->
         log::debug!("[CMD] {} {}", program_str, args.join(" "));
     }
 
-    let output = create_command(program).args(&args).output()?;
+    let output = lr_core::command::run(program, &args)?;
 
     #[cfg(debug_assertions)]
     {
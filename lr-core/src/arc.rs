@@ -0,0 +1,571 @@
+//! XP/2003 `boot.ini` 的 ARC 路径计算（`multi(0)disk(0)rdisk(n)partition(m)`）。
+//!
+//! NT5 的 ntldr 按 ARC 路径定位系统分区，规则（KB102873）：
+//! - `rdisk(n)`：BIOS 磁盘序号（0x80 = 0）。BIOS 从哪块盘启动，那块盘就是 `rdisk(0)`，
+//!   其余磁盘按顺序顺延；
+//! - `partition(m)`：从 1 开始，**先**按分区表槽位顺序给主分区编号，**再**按 EBR 链顺序给
+//!   逻辑分区编号；扩展分区本身与空槽不计数（隐藏/厂商工具分区照样计数——这正是 Dell 等
+//!   机器 boot.ini 写 `partition(2)` 的原因）。
+//!
+//! 布局可直接从磁盘的 MBR + EBR 链读出（[`read_mbr_layout`]，适用任何 `Read + Seek`，
+//! 如 `\\.\PhysicalDriveN` 或磁盘镜像文件），不依赖 diskpart 输出。
+
+use std::fmt;
+use std::io::{Read, Seek, SeekFrom};
+
+/// MBR 分区表项的种类。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MbrPartKind {
+    /// 主分区表中的普通分区。
+    Primary,
+    /// 主分区表中的扩展分区（容器，不参与 ARC 编号）。
+    Extended,
+    /// 扩展分区内 EBR 链上的逻辑分区。
+    Logical,
+}
+
+/// 一个 MBR 分区（起始/长度以扇区计，均为磁盘绝对 LBA）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MbrPart {
+    pub kind: MbrPartKind,
+    /// 分区类型字节（0x07 NTFS、0x0C FAT32 LBA、0x05/0x0F 扩展……）。
+    pub part_type: u8,
+    /// 是否为活动分区（仅主分区有意义）。
+    pub active: bool,
+    pub start_lba: u64,
+    pub sectors: u64,
+    /// 盘上位置：主分区/扩展分区为主分区表槽位（0..4），逻辑分区为 EBR 链序号。
+    pub slot: u32,
+}
+
+/// 一块 MBR 磁盘的布局。`parts` 按盘上顺序：先主分区表 4 槽（跳过空槽），再 EBR 链顺序。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MbrDiskLayout {
+    /// 物理磁盘号（`\\.\PhysicalDriveN` 的 N）。
+    pub disk_number: u32,
+    /// 扇区大小（字节），通常 512。
+    pub sector_size: u32,
+    pub parts: Vec<MbrPart>,
+}
+
+/// 一个 ARC 路径（`multi(0)disk(0)` 部分对 BIOS 磁盘恒为 0）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ArcPath {
+    pub rdisk: u32,
+    pub partition: u32,
+}
+
+impl fmt::Display for ArcPath {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "multi(0)disk(0)rdisk({})partition({})",
+            self.rdisk, self.partition
+        )
+    }
+}
+
+/// 是否为扩展分区类型（CHS 扩展 0x05、LBA 扩展 0x0F、Linux 扩展 0x85）。
+pub fn is_extended_type(t: u8) -> bool {
+    matches!(t, 0x05 | 0x0F | 0x85)
+}
+
+impl MbrDiskLayout {
+    /// 按 ARC 规则排好序的可编号分区（主分区在前，逻辑分区在后），下标 + 1 即 `partition(m)`。
+    pub fn arc_ordered(&self) -> Vec<&MbrPart> {
+        let primaries = self.parts.iter().filter(|p| p.kind == MbrPartKind::Primary);
+        let logicals = self.parts.iter().filter(|p| p.kind == MbrPartKind::Logical);
+        primaries.chain(logicals).collect()
+    }
+
+    /// 起始于 `offset`（字节）的分区的 ARC 分区号；不存在或落在扩展分区容器上返回 `None`。
+    pub fn arc_partition_number(&self, offset: u64) -> Option<u32> {
+        let ss = u64::from(self.sector_size.max(1));
+        let lba = offset / ss;
+        if lba * ss != offset {
+            return None;
+        }
+        self.arc_ordered()
+            .iter()
+            .position(|p| p.start_lba == lba)
+            .map(|i| i as u32 + 1)
+    }
+}
+
+/// BIOS 磁盘序号：启动盘为 0，其余磁盘按物理磁盘号升序顺延。`target_disk` 不在 `disks` 中返回 `None`。
+pub fn bios_rdisk(disks: &[u32], boot_disk: u32, target_disk: u32) -> Option<u32> {
+    if !disks.contains(&target_disk) {
+        return None;
+    }
+    if target_disk == boot_disk {
+        return Some(0);
+    }
+    let mut others: Vec<u32> = disks.iter().copied().filter(|&d| d != boot_disk).collect();
+    others.sort_unstable();
+    others.dedup();
+    let base = u32::from(disks.contains(&boot_disk));
+    others
+        .iter()
+        .position(|&d| d == target_disk)
+        .map(|i| i as u32 + base)
+}
+
+/// 计算位于 `target_disk` 上、起始于 `offset`（字节）的分区的完整 ARC 路径。
+///
+/// `boot_disk` 为 BIOS 启动盘（放 ntldr/boot.ini、被 `bootsect /mbr` 写过引导码的那块）。
+pub fn arc_path_for(
+    disks: &[MbrDiskLayout],
+    boot_disk: u32,
+    target_disk: u32,
+    offset: u64,
+) -> Result<ArcPath, String> {
+    let numbers: Vec<u32> = disks.iter().map(|d| d.disk_number).collect();
+    let layout = disks
+        .iter()
+        .find(|d| d.disk_number == target_disk)
+        .ok_or_else(|| format!("未提供磁盘 {} 的分区布局", target_disk))?;
+    let partition = layout.arc_partition_number(offset).ok_or_else(|| {
+        format!(
+            "磁盘 {} 上找不到起始于偏移 {} 的主分区/逻辑分区",
+            target_disk, offset
+        )
+    })?;
+    let rdisk = bios_rdisk(&numbers, boot_disk, target_disk)
+        .ok_or_else(|| format!("无法确定磁盘 {} 的 BIOS 序号", target_disk))?;
+    Ok(ArcPath { rdisk, partition })
+}
+
+/// 解析 MBR/EBR 扇区中的 4 个分区表项：（类型, 活动, 相对起始 LBA, 扇区数）。空槽类型为 0。
+fn parse_table(sector: &[u8]) -> Result<[(u8, bool, u64, u64); 4], String> {
+    if sector.len() < 512 || sector[510] != 0x55 || sector[511] != 0xAA {
+        return Err("扇区缺少 0x55AA 签名，不是有效的 MBR/EBR".to_string());
+    }
+    let mut out = [(0u8, false, 0u64, 0u64); 4];
+    for (i, slot) in out.iter_mut().enumerate() {
+        let e = &sector[446 + i * 16..446 + (i + 1) * 16];
+        let lba = u32::from_le_bytes([e[8], e[9], e[10], e[11]]);
+        let len = u32::from_le_bytes([e[12], e[13], e[14], e[15]]);
+        *slot = (e[4], e[0] == 0x80, u64::from(lba), u64::from(len));
+    }
+    Ok(out)
+}
+
+fn read_sector<R: Read + Seek>(r: &mut R, lba: u64, sector_size: u32) -> Result<Vec<u8>, String> {
+    let mut buf = vec![0u8; sector_size as usize];
+    r.seek(SeekFrom::Start(lba * u64::from(sector_size)))
+        .map_err(|e| format!("定位到扇区 {} 失败: {}", lba, e))?;
+    r.read_exact(&mut buf)
+        .map_err(|e| format!("读取扇区 {} 失败: {}", lba, e))?;
+    Ok(buf)
+}
+
+/// 从磁盘（或磁盘镜像）读 MBR + EBR 链，得到按盘上顺序排列的分区布局。
+///
+/// 保护性 MBR（类型 0xEE，即 GPT 盘）返回 `Err`——ARC/boot.ini 只适用于 MBR 盘。
+pub fn read_mbr_layout<R: Read + Seek>(
+    r: &mut R,
+    disk_number: u32,
+    sector_size: u32,
+) -> Result<MbrDiskLayout, String> {
+    let mbr = read_sector(r, 0, sector_size)?;
+    let table = parse_table(&mbr)?;
+    if table.iter().any(|e| e.0 == 0xEE) {
+        return Err("磁盘是 GPT（保护性 MBR），boot.ini/ARC 路径不适用".to_string());
+    }
+
+    let mut parts = Vec::new();
+    let mut ext_base: Option<u64> = None;
+    for (slot, &(t, active, lba, len)) in table.iter().enumerate() {
+        if t == 0 || len == 0 {
+            continue;
+        }
+        let kind = if is_extended_type(t) {
+            if ext_base.is_none() {
+                ext_base = Some(lba);
+            }
+            MbrPartKind::Extended
+        } else {
+            MbrPartKind::Primary
+        };
+        parts.push(MbrPart {
+            kind,
+            part_type: t,
+            active,
+            start_lba: lba,
+            sectors: len,
+            slot: slot as u32,
+        });
+    }
+
+    // EBR 链：每个 EBR 的第 1 项是逻辑分区（相对本 EBR），第 2 项指向下一个 EBR（相对扩展分区起点）。
+    if let Some(base) = ext_base {
+        let mut ebr = base;
+        let mut seen = std::collections::HashSet::new();
+        let mut index = 0;
+        while seen.insert(ebr) {
+            let sector = read_sector(r, ebr, sector_size)?;
+            let t = parse_table(&sector)?;
+            let (lt, _, rel, len) = t[0];
+            if lt != 0 && len != 0 && !is_extended_type(lt) {
+                parts.push(MbrPart {
+                    kind: MbrPartKind::Logical,
+                    part_type: lt,
+                    active: false,
+                    start_lba: ebr + rel,
+                    sectors: len,
+                    slot: index,
+                });
+                index += 1;
+            }
+            let (nt, _, next_rel, _) = t[1];
+            if !is_extended_type(nt) || next_rel == 0 {
+                break;
+            }
+            ebr = base + next_rel;
+        }
+    }
+
+    Ok(MbrDiskLayout {
+        disk_number,
+        sector_size,
+        parts,
+    })
+}
+
+/// 扩展分区内 EBR 链上每个 EBR 所在的 LBA（按链顺序）；没有扩展分区时为空。
+pub fn ebr_chain<R: Read + Seek>(r: &mut R, sector_size: u32) -> Result<Vec<u64>, String> {
+    let table = parse_table(&read_sector(r, 0, sector_size)?)?;
+    let Some(base) = table
+        .iter()
+        .find(|e| is_extended_type(e.0) && e.3 != 0)
+        .map(|e| e.2)
+    else {
+        return Ok(Vec::new());
+    };
+
+    let mut chain = Vec::new();
+    let mut ebr = base;
+    while !chain.contains(&ebr) {
+        chain.push(ebr);
+        let t = parse_table(&read_sector(r, ebr, sector_size)?)?;
+        let (nt, _, next_rel, _) = t[1];
+        if !is_extended_type(nt) || next_rel == 0 {
+            break;
+        }
+        ebr = base + next_rel;
+    }
+    Ok(chain)
+}
+
+/// 读取物理磁盘 `disk_number` 的 MBR 布局（`\\.\PhysicalDriveN`，需管理员权限），按磁盘实际扇区大小解析。
+pub fn read_physical_disk_layout(disk_number: u32) -> Result<MbrDiskLayout, String> {
+    let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+    let mut f = std::fs::File::open(&path).map_err(|e| format!("打开 {} 失败: {}", path, e))?;
+    let (sector_size, _) = crate::partition_table::drive_geometry(&mut f)
+        .map_err(|e| format!("查询 {} 的扇区大小失败: {}", path, e))?;
+    read_mbr_layout(&mut f, disk_number, sector_size)
+}
+
+/// 系统中能打开的物理磁盘号（`\\.\PhysicalDrive0..63`，中间可能有空缺）。
+fn physical_disk_numbers() -> Vec<u32> {
+    (0..64)
+        .filter(|n| std::fs::File::open(format!("\\\\.\\PhysicalDrive{}", n)).is_ok())
+        .collect()
+}
+
+/// 盘符（`"C"`、`"C:"`、`"C:\"`）对应的卷设备路径 `\\.\C:`。
+pub fn volume_device(win_partition: &str) -> String {
+    format!("\\\\.\\{}:", win_partition.trim_end_matches(['\\', ':']))
+}
+
+/// 查询盘符所在的物理磁盘号与分区起始偏移（字节）。跨盘卷/查询失败返回 `None`。
+pub fn volume_disk_extent(win_partition: &str) -> Option<(u32, u64)> {
+    volume_extent(&volume_device(win_partition)).map(|(disk, offset, _)| (disk, offset))
+}
+
+/// 查询卷设备（`\\.\C:` 或 `\\?\Volume{GUID}`，结尾反斜杠可有可无）所在的物理磁盘号、
+/// 分区起始偏移与长度（字节）。跨盘卷/查询失败返回 `None`。
+#[cfg(windows)]
+pub fn volume_extent(device: &str) -> Option<(u32, u64, u64)> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::Storage::FileSystem::{
+        CreateFileW, FILE_SHARE_READ, FILE_SHARE_WRITE, OPEN_EXISTING,
+    };
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    const IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS: u32 = 0x0056_0000;
+
+    // 打开卷设备本身时不能带结尾反斜杠（带了打开的是根目录）
+    let wide: Vec<u16> = device
+        .trim_end_matches('\\')
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    unsafe {
+        let handle = CreateFileW(
+            PCWSTR::from_raw(wide.as_ptr()),
+            0,
+            FILE_SHARE_READ | FILE_SHARE_WRITE,
+            None,
+            OPEN_EXISTING,
+            Default::default(),
+            None,
+        )
+        .ok()?;
+        // VOLUME_DISK_EXTENTS：u32 个数 + 对齐填充 + { u32 磁盘号, i64 起始, i64 长度 }[]
+        let mut buf = [0u8; 256];
+        let mut returned = 0u32;
+        let res = DeviceIoControl(
+            handle,
+            IOCTL_VOLUME_GET_VOLUME_DISK_EXTENTS,
+            None,
+            0,
+            Some(buf.as_mut_ptr() as *mut _),
+            buf.len() as u32,
+            Some(&mut returned),
+            None,
+        );
+        let _ = CloseHandle(handle);
+        res.ok()?;
+        let count = u32::from_le_bytes(buf[0..4].try_into().ok()?);
+        if count != 1 {
+            return None;
+        }
+        let disk = u32::from_le_bytes(buf[8..12].try_into().ok()?);
+        let offset = i64::from_le_bytes(buf[16..24].try_into().ok()?);
+        let length = i64::from_le_bytes(buf[24..32].try_into().ok()?);
+        Some((disk, offset as u64, length as u64))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn volume_extent(_device: &str) -> Option<(u32, u64, u64)> {
+    None
+}
+
+/// 计算盘符（如 `"C:"`）对应分区的 ARC 路径。
+///
+/// `boot_partition` 是放 ntldr/boot.ini 的分区：`bootsect /nt52 <盘> /mbr` 写的是它所在磁盘的 MBR，
+/// BIOS 从这块盘启动，它即 `rdisk(0)`，其余磁盘按物理磁盘号顺延（见 [`bios_rdisk`]）。
+pub fn arc_path_for_volume(win_partition: &str, boot_partition: &str) -> Result<ArcPath, String> {
+    let (disk, offset) = volume_disk_extent(win_partition)
+        .ok_or_else(|| format!("无法查询 {} 所在的物理磁盘/偏移", win_partition))?;
+    let (boot_disk, _) = volume_disk_extent(boot_partition)
+        .ok_or_else(|| format!("无法查询 {} 所在的物理磁盘", boot_partition))?;
+    let layout = read_physical_disk_layout(disk)?;
+    if disk == boot_disk {
+        return arc_path_for(&[layout], boot_disk, disk, offset);
+    }
+    let rdisk = bios_rdisk(&physical_disk_numbers(), boot_disk, disk)
+        .ok_or_else(|| format!("无法确定磁盘 {} 的 BIOS 序号", disk))?;
+    let partition = layout.arc_partition_number(offset).ok_or_else(|| {
+        format!(
+            "磁盘 {} 上找不到起始于偏移 {} 的主分区/逻辑分区",
+            disk, offset
+        )
+    })?;
+    Ok(ArcPath { rdisk, partition })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::put_mbr_entry;
+    use std::io::Cursor;
+
+    const SS: u64 = 512;
+
+    /// 在镜像 `img` 的 `lba` 扇区依次写入分区表项（类型、活动、相对 LBA、扇区数），类型为 0 的项留空。
+    fn put_table(img: &mut [u8], lba: u64, entries: &[(u8, bool, u32, u32)]) {
+        for (slot, &(t, active, start, len)) in entries.iter().enumerate() {
+            put_mbr_entry(img, lba, slot, t, start, len);
+            if active {
+                img[(lba * SS) as usize + 446 + slot * 16] = 0x80;
+            }
+        }
+    }
+
+    fn image(sectors: u64) -> Vec<u8> {
+        vec![0u8; (sectors * SS) as usize]
+    }
+
+    #[test]
+    fn volume_device_from_letter() {
+        assert_eq!(volume_device("C"), "\\\\.\\C:");
+        assert_eq!(volume_device("d:"), "\\\\.\\d:");
+        assert_eq!(volume_device("E:\\"), "\\\\.\\E:");
+    }
+
+    #[test]
+    fn arc_path_display() {
+        let p = ArcPath {
+            rdisk: 1,
+            partition: 3,
+        };
+        assert_eq!(p.to_string(), "multi(0)disk(0)rdisk(1)partition(3)");
+    }
+
+    #[test]
+    fn primary_only_layout() {
+        let mut img = image(64);
+        put_table(
+            &mut img,
+            0,
+            &[
+                (0x07, true, 2, 10),
+                (0x07, false, 12, 10),
+                (0x0C, false, 22, 10),
+            ],
+        );
+        let l = read_mbr_layout(&mut Cursor::new(img), 0, 512).unwrap();
+        assert_eq!(l.parts.len(), 3);
+        assert!(l.parts[0].active);
+        assert_eq!(l.arc_partition_number(2 * SS), Some(1));
+        assert_eq!(l.arc_partition_number(12 * SS), Some(2));
+        assert_eq!(l.arc_partition_number(22 * SS), Some(3));
+        assert_eq!(l.arc_partition_number(5 * SS), None);
+    }
+
+    #[test]
+    fn empty_slot_and_hidden_partition() {
+        // 槽 0 是厂商隐藏工具分区（0xDE，照样计数），槽 1 空，槽 2 是 XP
+        let mut img = image(64);
+        put_table(
+            &mut img,
+            0,
+            &[(0xDE, false, 2, 8), (0, false, 0, 0), (0x07, true, 10, 20)],
+        );
+        let l = read_mbr_layout(&mut Cursor::new(img), 0, 512).unwrap();
+        assert_eq!(l.arc_partition_number(10 * SS), Some(2));
+    }
+
+    #[test]
+    fn extended_with_logicals_numbered_after_primaries() {
+        // 槽 0 主分区 C，槽 1 扩展分区 [20, 60)，槽 2 主分区（表序在扩展之后，但仍先于逻辑分区编号）
+        // 扩展内：EBR@20 → 逻辑 D@21，链接 EBR@40 → 逻辑 E@41
+        let mut img = image(96);
+        put_table(
+            &mut img,
+            0,
+            &[
+                (0x07, true, 2, 18),
+                (0x0F, false, 20, 40),
+                (0x07, false, 60, 20),
+            ],
+        );
+        put_table(&mut img, 20, &[(0x07, false, 1, 19), (0x05, false, 20, 20)]);
+        put_table(&mut img, 40, &[(0x07, false, 1, 19)]);
+        let l = read_mbr_layout(&mut Cursor::new(img), 0, 512).unwrap();
+
+        let kinds: Vec<MbrPartKind> = l.parts.iter().map(|p| p.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                MbrPartKind::Primary,
+                MbrPartKind::Extended,
+                MbrPartKind::Primary,
+                MbrPartKind::Logical,
+                MbrPartKind::Logical,
+            ]
+        );
+        assert_eq!(l.arc_partition_number(2 * SS), Some(1));
+        assert_eq!(l.arc_partition_number(60 * SS), Some(2));
+        assert_eq!(l.arc_partition_number(21 * SS), Some(3));
+        assert_eq!(l.arc_partition_number(41 * SS), Some(4));
+        // 扩展分区容器本身不编号
+        assert_eq!(l.arc_partition_number(20 * SS), None);
+    }
+
+    #[test]
+    fn xp_on_logical_partition() {
+        let mut img = image(64);
+        put_table(&mut img, 0, &[(0x0F, false, 2, 60)]);
+        put_table(&mut img, 2, &[(0x07, false, 1, 29), (0x05, false, 30, 30)]);
+        put_table(&mut img, 32, &[(0x07, false, 1, 29)]);
+        let l = read_mbr_layout(&mut Cursor::new(img), 0, 512).unwrap();
+        let p = arc_path_for(&[l], 0, 0, 33 * SS).unwrap();
+        assert_eq!(p.to_string(), "multi(0)disk(0)rdisk(0)partition(2)");
+    }
+
+    #[test]
+    fn ebr_loop_terminates() {
+        let mut img = image(32);
+        put_table(&mut img, 0, &[(0x05, false, 2, 20)]);
+        // EBR 的链接项指回自己
+        put_table(&mut img, 2, &[(0x07, false, 1, 9), (0x05, false, 0, 20)]);
+        let l = read_mbr_layout(&mut Cursor::new(img), 0, 512).unwrap();
+        assert_eq!(l.arc_ordered().len(), 1);
+    }
+
+    #[test]
+    fn gpt_protective_mbr_rejected() {
+        let mut img = image(4);
+        put_table(&mut img, 0, &[(0xEE, false, 1, 3)]);
+        assert!(read_mbr_layout(&mut Cursor::new(img), 0, 512).is_err());
+    }
+
+    #[test]
+    fn missing_signature_rejected() {
+        let img = image(4);
+        assert!(read_mbr_layout(&mut Cursor::new(img), 0, 512).is_err());
+    }
+
+    #[test]
+    fn rdisk_boot_disk_first() {
+        let disks = [0, 1, 2];
+        assert_eq!(bios_rdisk(&disks, 0, 0), Some(0));
+        assert_eq!(bios_rdisk(&disks, 0, 2), Some(2));
+        // 从磁盘 1 启动：1→0，0→1，2→2
+        assert_eq!(bios_rdisk(&disks, 1, 1), Some(0));
+        assert_eq!(bios_rdisk(&disks, 1, 0), Some(1));
+        assert_eq!(bios_rdisk(&disks, 1, 2), Some(2));
+        assert_eq!(bios_rdisk(&disks, 1, 5), None);
+    }
+
+    #[test]
+    fn arc_path_across_disks() {
+        let d0 = MbrDiskLayout {
+            disk_number: 0,
+            sector_size: 512,
+            parts: vec![MbrPart {
+                kind: MbrPartKind::Primary,
+                part_type: 0x07,
+                active: true,
+                start_lba: 2048,
+                sectors: 1000,
+                slot: 0,
+            }],
+        };
+        let d1 = MbrDiskLayout {
+            disk_number: 1,
+            sector_size: 512,
+            parts: vec![
+                MbrPart {
+                    kind: MbrPartKind::Primary,
+                    part_type: 0x07,
+                    active: false,
+                    start_lba: 2048,
+                    sectors: 1000,
+                    slot: 0,
+                },
+                MbrPart {
+                    kind: MbrPartKind::Primary,
+                    part_type: 0x07,
+                    active: false,
+                    start_lba: 4096,
+                    sectors: 1000,
+                    slot: 1,
+                },
+            ],
+        };
+        let p = arc_path_for(&[d0, d1], 0, 1, 4096 * SS).unwrap();
+        assert_eq!(
+            p,
+            ArcPath {
+                rdisk: 1,
+                partition: 2
+            }
+        );
+    }
+}
@@ -1,17 +1,20 @@
 //! XP 引导写入 + 可编辑修复引导脚本（两端共享）。
 //!
 //! - [`write_xp_boot`]：为已释放的 XP/2003 系统写入引导（ntldr/boot.ini + MBR，仅 Legacy）。
+//! - [`BootIni`]：boot.ini 的读写模型（多启动项，合并时保留已有项）。
 //! - [`run_repair_script`]：执行用户可编辑的 `bin\repair_boot.txt`，覆盖默认修复引导逻辑。
 
 use std::path::Path;
 
-use crate::command::new_command;
+use crate::arc::ArcPath;
+use crate::command;
 use crate::encoding::gbk_to_utf8;
+use crate::sif::SifDocument;
 
 /// 为应用好的 XP/2003 系统写入引导（仅 Legacy/MBR）。
 ///
 /// 步骤：`bootsect /nt52 <盘> /mbr` 写 XP 引导码 → 校验 ntldr/ntdetect.com →
-/// 缺失 boot.ini 时写入一份默认（不覆盖镜像自带的）。返回执行日志。
+/// 按分区实际位置计算 ARC 路径，合并写入 boot.ini（保留已有启动项）。返回执行日志。
 pub fn write_xp_boot(bin_dir: &Path, win_partition: &str) -> Result<String, String> {
     let win = win_partition.trim_end_matches('\\'); // 形如 "C:"
     let mut log = String::new();
@@ -20,7 +23,7 @@ pub fn write_xp_boot(bin_dir: &Path, win_partition: &str) -> Result<String, Stri
     let bootsect = bin_dir.join("bootsect.exe");
     if bootsect.exists() {
         log.push_str(&format!("执行: bootsect /nt52 {} /mbr\n", win));
-        match new_command(&bootsect).args(["/nt52", win, "/mbr"]).output() {
+        match command::run(&bootsect, ["/nt52", win, "/mbr"]) {
             Ok(o) => {
                 log.push_str(&gbk_to_utf8(&o.stdout));
                 log.push_str(&gbk_to_utf8(&o.stderr));
@@ -43,25 +46,209 @@ pub fn write_xp_boot(bin_dir: &Path, win_partition: &str) -> Result<String, Stri
         );
     }
 
-    // 3) boot.ini（仅在不存在时写入，避免覆盖镜像自带配置）
+    // 3) boot.ini：按目标分区的真实位置计算 ARC 路径（主分区在前、逻辑分区按 EBR 链顺延），
+    //    合并进已有 boot.ini——保留镜像/用户原有的其它启动项，只补上（或复用）指向本系统的项并设为默认。
     let boot_ini = format!("{}\\boot.ini", win);
-    if !Path::new(&boot_ini).exists() {
-        let content = "[boot loader]\r\n\
-timeout=10\r\n\
-default=multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS\r\n\
-[operating systems]\r\n\
-multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS=\"Windows XP\" /noexecute=optin /fastdetect\r\n";
-        match std::fs::write(&boot_ini, content) {
-            Ok(_) => log.push_str("已写入默认 boot.ini\n"),
-            Err(e) => log.push_str(&format!("写 boot.ini 失败: {}\n", e)),
+    //    ntldr/boot.ini 与 bootsect 写的 MBR 都在 win 所在磁盘上，它就是 BIOS 启动盘。
+    let arc = match crate::arc::arc_path_for_volume(win, win) {
+        Ok(p) => {
+            log.push_str(&format!("{} 的 ARC 路径: {}\n", win, p));
+            p
+        }
+        Err(e) => {
+            log.push_str(&format!(
+                "警告: 无法计算 {} 的 ARC 路径（{}），回退 rdisk(0)partition(1)\n",
+                win, e
+            ));
+            ArcPath {
+                rdisk: 0,
+                partition: 1,
+            }
         }
+    };
+    let os_path = format!("{}\\{}", arc, xp_windows_dir_name(win));
+
+    let old = std::fs::read(&boot_ini).ok();
+    let mut ini = match &old {
+        Some(raw) => BootIni::parse_bytes(raw),
+        None => BootIni::new(),
+    };
+    let switches = ini
+        .default_path()
+        .and_then(|d| ini.find_entry(d))
+        .map(|e| e.switches)
+        .unwrap_or_else(|| "/noexecute=optin /fastdetect".to_string());
+    let added = ini.upsert_entry(BootIniEntry {
+        path: os_path.clone(),
+        description: "Windows XP".to_string(),
+        switches,
+    });
+    ini.set_default(&os_path);
+    let bytes = ini.to_bytes();
+
+    if old.as_deref() == Some(bytes.as_slice()) {
+        log.push_str("boot.ini 已指向本系统，无需修改\n");
     } else {
-        log.push_str("boot.ini 已存在，保留镜像自带配置\n");
+        // boot.ini 通常带 +r+s+h，直接覆盖写会拒绝访问；写完（无论成败）再加回
+        crate::xp_i386::clear_file_attrs(&boot_ini);
+        let written = std::fs::write(&boot_ini, bytes);
+        crate::xp_i386::set_system_file_attrs(&boot_ini);
+        match written {
+            Ok(_) => log.push_str(&format!(
+                "已写入 boot.ini（{}启动项 {}，共 {} 项）\n",
+                if added { "新增" } else { "复用" },
+                os_path,
+                ini.entries().len()
+            )),
+            Err(e) => log.push_str(&format!("写 boot.ini 失败: {}\n", e)),
+        }
     }
 
     Ok(log)
 }
 
+/// XP 系统目录名：`WINDOWS`（XP/2003）或 `WINNT`（2000 及部分 2003 定制）。
+fn xp_windows_dir_name(win: &str) -> &'static str {
+    let winnt = format!("{}\\WINNT\\system32", win);
+    let windows = format!("{}\\WINDOWS\\system32", win);
+    if !Path::new(&windows).exists() && Path::new(&winnt).exists() {
+        "WINNT"
+    } else {
+        "WINDOWS"
+    }
+}
+
+/// boot.ini `[operating systems]` 中的一个启动项：`路径="描述" 开关`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BootIniEntry {
+    /// ARC 路径 + 系统目录（如 `multi(0)disk(0)rdisk(0)partition(2)\WINDOWS`），
+    /// 或 `C:\CMDCONS\BOOTSECT.DAT` 这类引导扇区文件。
+    pub path: String,
+    pub description: String,
+    /// 启动开关原文（如 `/noexecute=optin /fastdetect`），可为空。
+    pub switches: String,
+}
+
+impl BootIniEntry {
+    /// 由 `路径=右侧` 的两部分解析（右侧为 `"描述" 开关`）。
+    fn parse(path: &str, rhs: &str) -> Option<Self> {
+        let path = path.trim();
+        if path.is_empty() {
+            return None;
+        }
+        let rhs = rhs.trim();
+        let (description, switches) = match rhs.strip_prefix('"') {
+            Some(rest) => match rest.find('"') {
+                Some(end) => (&rest[..end], rest[end + 1..].trim()),
+                None => (rest, ""),
+            },
+            None => match rhs.find(" /") {
+                Some(i) => (&rhs[..i], rhs[i..].trim()),
+                None => (rhs, ""),
+            },
+        };
+        Some(Self {
+            path: path.to_string(),
+            description: description.to_string(),
+            switches: switches.to_string(),
+        })
+    }
+
+    fn render(&self) -> String {
+        if self.switches.is_empty() {
+            format!("{}=\"{}\"", self.path, self.description)
+        } else {
+            format!("{}=\"{}\" {}", self.path, self.description, self.switches)
+        }
+    }
+}
+
+/// boot.ini 读写模型：`[boot loader]` 的键值（`timeout`/`default`/`redirect` 等）
+/// + `[operating systems]` 的多个启动项。
+///
+/// 基于无损的 [`SifDocument`]：注释、未知节、无法识别的行与原编码（ANSI/UTF-8/UTF-16）都原样保留，
+/// 修改只作用于 `default=` 与新插入的启动项。
+#[derive(Debug, Clone)]
+pub struct BootIni {
+    doc: SifDocument,
+}
+
+const LOADER: &str = "boot loader";
+const SYSTEMS: &str = "operating systems";
+
+impl Default for BootIni {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl BootIni {
+    /// 空 boot.ini（`timeout=10`，无启动项，CRLF）。
+    pub fn new() -> Self {
+        Self::parse("[boot loader]\r\ntimeout=10\r\n[operating systems]\r\n")
+    }
+
+    /// 解析 boot.ini 文本。
+    pub fn parse(text: &str) -> Self {
+        Self {
+            doc: SifDocument::parse(text),
+        }
+    }
+
+    /// 从文件原始字节解析，自动识别编码，写回时保持原编码。
+    pub fn parse_bytes(raw: &[u8]) -> Self {
+        Self {
+            doc: SifDocument::parse_bytes(raw),
+        }
+    }
+
+    /// `[operating systems]` 中的启动项（按文件顺序）。
+    pub fn entries(&self) -> Vec<BootIniEntry> {
+        self.doc
+            .entries(SYSTEMS)
+            .into_iter()
+            .filter_map(|(path, rhs)| BootIniEntry::parse(path, rhs))
+            .collect()
+    }
+
+    /// 当前 `default=` 指向的路径。
+    pub fn default_path(&self) -> Option<&str> {
+        self.doc.get(LOADER, "default")
+    }
+
+    /// 设置 `default=`（已有则原位替换，否则追加到 `[boot loader]` 节末尾）。
+    pub fn set_default(&mut self, path: &str) {
+        self.doc.set(LOADER, "default", path);
+    }
+
+    /// 按路径（大小写不敏感）查找启动项。
+    pub fn find_entry(&self, path: &str) -> Option<BootIniEntry> {
+        self.entries()
+            .into_iter()
+            .find(|e| e.path.eq_ignore_ascii_case(path))
+    }
+
+    /// 确保存在指向 `entry.path` 的启动项：已有则原样保留（描述/开关以已有为准）并返回 `false`；
+    /// 没有则插到列表最前并返回 `true`。
+    pub fn upsert_entry(&mut self, entry: BootIniEntry) -> bool {
+        if self.find_entry(&entry.path).is_some() {
+            return false;
+        }
+        self.doc.prepend_lines(SYSTEMS, &[entry.render()]);
+        true
+    }
+
+    /// 输出 boot.ini 文本（未修改的行逐字保留）。
+    pub fn render(&self) -> String {
+        self.doc.to_text()
+    }
+
+    /// 按原编码输出字节。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.doc.to_bytes()
+    }
+}
+
 /// 执行用户可编辑的修复引导脚本 `bin\repair_boot.txt`。
 ///
 /// 文件支持 `[UEFI]` / `[Legacy]` 分节（无分节则全部命令通用）；按当前引导模式选取命令，
@@ -125,7 +312,7 @@ pub fn run_repair_script(
             .replace("{ESP}", &esp)
             .replace("{BIN}", &bin);
         log.push_str(&format!(">>> {}\n", cmd_line));
-        match new_command("cmd").args(["/c", &cmd_line]).output() {
+        match command::run("cmd", ["/c", &cmd_line]) {
             Ok(o) => {
                 log.push_str(&gbk_to_utf8(&o.stdout));
                 log.push_str(&gbk_to_utf8(&o.stderr));
@@ -144,3 +331,177 @@ pub fn run_repair_script(
     }
     Ok(log)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{with_runner, FakeReply, FakeRunner};
+    use std::sync::Arc;
+
+    const XP_ARC: &str = "multi(0)disk(0)rdisk(0)partition(2)\\WINDOWS";
+
+    #[test]
+    fn boot_ini_parse_entries_and_loader() {
+        let ini = BootIni::parse(
+            "[boot loader]\r\ntimeout=30\r\ndefault=multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS\r\n\
+             [operating systems]\r\n\
+             multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS=\"Microsoft Windows XP Professional\" /noexecute=optin /fastdetect\r\n\
+             C:\\CMDCONS\\BOOTSECT.DAT=\"Microsoft Windows Recovery Console\" /cmdcons\r\n",
+        );
+        assert_eq!(
+            ini.default_path(),
+            Some("multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS")
+        );
+        let entries = ini.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].description, "Microsoft Windows XP Professional");
+        assert_eq!(entries[0].switches, "/noexecute=optin /fastdetect");
+        assert_eq!(entries[1].path, "C:\\CMDCONS\\BOOTSECT.DAT");
+        assert_eq!(entries[1].switches, "/cmdcons");
+    }
+
+    #[test]
+    fn boot_ini_upsert_keeps_existing_entries() {
+        let mut ini = BootIni::parse(
+            "[boot loader]\r\ntimeout=5\r\ndefault=multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS\r\n\
+             [operating systems]\r\nmulti(0)disk(0)rdisk(0)partition(1)\\WINDOWS=\"Old XP\" /fastdetect\r\n",
+        );
+        let added = ini.upsert_entry(BootIniEntry {
+            path: XP_ARC.to_string(),
+            description: "Windows XP".to_string(),
+            switches: "/fastdetect".to_string(),
+        });
+        ini.set_default(XP_ARC);
+        assert!(added);
+        let entries = ini.entries();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].path, XP_ARC);
+        assert_eq!(entries[1].description, "Old XP");
+        let out = ini.render();
+        assert!(out.starts_with("[boot loader]\r\ntimeout=5\r\ndefault=multi(0)disk(0)rdisk(0)partition(2)\\WINDOWS\r\n"));
+        assert_eq!(out.matches("default=").count(), 1);
+    }
+
+    #[test]
+    fn boot_ini_upsert_existing_path_is_noop() {
+        let mut ini = BootIni::new();
+        assert!(ini.upsert_entry(BootIniEntry {
+            path: XP_ARC.to_string(),
+            description: "自定义描述".to_string(),
+            switches: String::new(),
+        }));
+        assert!(!ini.upsert_entry(BootIniEntry {
+            path: XP_ARC.to_ascii_uppercase(),
+            description: "Windows XP".to_string(),
+            switches: "/fastdetect".to_string(),
+        }));
+        let entries = ini.entries();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].description, "自定义描述");
+    }
+
+    #[test]
+    fn boot_ini_new_gets_default_after_timeout() {
+        let mut ini = BootIni::new();
+        ini.set_default(XP_ARC);
+        assert_eq!(
+            ini.render(),
+            format!("[boot loader]\r\ntimeout=10\r\ndefault={XP_ARC}\r\n[operating systems]\r\n")
+        );
+    }
+
+    #[test]
+    fn boot_ini_roundtrip_is_stable() {
+        let mut ini = BootIni::new();
+        ini.upsert_entry(BootIniEntry {
+            path: XP_ARC.to_string(),
+            description: "Windows XP".to_string(),
+            switches: "/noexecute=optin /fastdetect".to_string(),
+        });
+        ini.set_default(XP_ARC);
+        let text = ini.render();
+        assert_eq!(BootIni::parse(&text).render(), text);
+    }
+
+    #[test]
+    fn boot_ini_keeps_comments_unknown_sections_and_encoding() {
+        let original = "; 由 OEM 预装\r\n[boot loader]\r\ntimeout=30 ; 秒\r\n\
+             default=multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS\r\n\
+             [operating systems]\r\n\
+             multi(0)disk(0)rdisk(0)partition(1)\\WINDOWS=\"Windows XP 专业版\" /fastdetect\r\n\
+             这一行无法识别\r\n\
+             [debug]\r\nport=com1\r\n";
+        let raw = crate::encoding::utf8_to_gbk(original);
+        assert_eq!(BootIni::parse_bytes(&raw).to_bytes(), raw);
+
+        let mut ini = BootIni::parse_bytes(&raw);
+        assert!(ini.upsert_entry(BootIniEntry {
+            path: XP_ARC.to_string(),
+            description: "Windows XP".to_string(),
+            switches: "/fastdetect".to_string(),
+        }));
+        ini.set_default(XP_ARC);
+        let out = ini.to_bytes();
+        // 仍是 GBK，且只改了 default 并插入一行
+        assert_eq!(
+            gbk_to_utf8(&out),
+            original
+                .replace(
+                    "default=multi(0)disk(0)rdisk(0)partition(1)",
+                    "default=multi(0)disk(0)rdisk(0)partition(2)"
+                )
+                .replace(
+                    "[operating systems]\r\n",
+                    &format!("[operating systems]\r\n{XP_ARC}=\"Windows XP\" /fastdetect\r\n")
+                )
+        );
+    }
+
+    fn write_script(name: &str, content: &str) -> std::path::PathBuf {
+        let path = std::env::temp_dir().join(format!("lr_{}_{}.txt", name, std::process::id()));
+        std::fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn repair_script_runs_section_with_placeholders() {
+        let script = write_script(
+            "repair_uefi",
+            "# 注释\n[Legacy]\nbootsect /nt60 {WIN} /mbr\n[UEFI]\nbcdboot {WINDIR} /s {ESP} /f UEFI\n{BIN}\\fix.cmd {WIN}\n",
+        );
+        let fake = Arc::new(FakeRunner::new());
+        let result = with_runner(fake.clone(), || {
+            run_repair_script(&script, Path::new("X:\\bin"), "D:\\", true, Some("S:"))
+        });
+        let _ = std::fs::remove_file(&script);
+
+        assert!(result.is_ok());
+        assert_eq!(
+            fake.lines(),
+            vec![
+                "cmd /c bcdboot D:\\Windows /s S: /f UEFI",
+                "cmd /c X:\\bin\\fix.cmd D:",
+            ]
+        );
+    }
+
+    #[test]
+    fn repair_script_failure_falls_back() {
+        let script = write_script(
+            "repair_fail",
+            "bcdboot {WINDIR} /f BIOS\nbcdboot {WINDIR} /s {ESP}\n",
+        );
+        let fake =
+            Arc::new(FakeRunner::new().on("cmd", &["/f bios"], FakeReply::fail(1, "拒绝访问")));
+        let err = with_runner(fake.clone(), || {
+            run_repair_script(&script, Path::new("X:\\bin"), "C:", false, None)
+        })
+        .unwrap_err();
+        let _ = std::fs::remove_file(&script);
+
+        assert!(err.contains("[命令返回非 0]"));
+        assert!(err.contains("[跳过：ESP 未挂载]"));
+        // 第二条因 ESP 未挂载被跳过，没有真正执行
+        assert_eq!(fake.calls().len(), 1);
+    }
+}
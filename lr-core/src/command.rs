@@ -1,6 +1,19 @@
-//! 命令执行辅助：创建隐藏控制台窗口的 Command（两端共享）。
+//! 命令执行辅助（两端共享）：创建隐藏控制台窗口的 Command，以及可替换的外部命令执行器。
+//!
+//! 调用 `diskpart` / `bcdedit` / `bcdboot` / `reg` / `dism` / `ghost64` / `manage-bde` 等外部工具
+//! 并等待结果的地方统一走 [`run`]，由当前的 [`CommandRunner`] 实际执行：
+//! 默认是 [`SystemRunner`]（真正启动进程）；测试里用 [`with_runner`] 换成 [`FakeRunner`]，
+//! 按预设回放输出与退出码并记录每次调用，从而在非 Windows 环境下测试引导修复、分区等流程。
+//!
+//! 需要实时读取输出的长时间进程（DISM 进度、Ghost 等）走 [`spawn`]，拿到可逐行读取输出、
+//! 轮询或终止的 [`Process`]；[`FakeRunner`] 同样回放这类调用，预设输出一次性可读、进程立即结束。
 
-use std::process::Command;
+use std::cell::RefCell;
+use std::ffi::{OsStr, OsString};
+use std::io::{self, Read};
+use std::path::Path;
+use std::process::{Child, Command, ExitStatus, Output, Stdio};
+use std::sync::{Arc, Mutex};
 
 #[cfg(windows)]
 use std::os::windows::process::CommandExt;
@@ -13,6 +26,7 @@ const CREATE_NO_WINDOW: u32 = 0x08000000;
 ///
 /// 在 Windows 上设置 CREATE_NO_WINDOW 防止弹出控制台窗口；其它平台返回普通 Command。
 pub fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
+    #[cfg_attr(not(windows), allow(unused_mut))]
     let mut cmd = Command::new(program);
 
     #[cfg(windows)]
@@ -23,14 +37,427 @@ pub fn new_command<S: AsRef<std::ffi::OsStr>>(program: S) -> Command {
     cmd
 }
 
+/// 已启动的外部进程，标准输出与标准错误均为管道。
+pub trait Process: Send {
+    /// 取走标准输出（只能取一次）
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>>;
+    /// 取走标准错误（只能取一次）
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>>;
+    /// 进程已结束时返回退出状态，否则返回 `None`
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>>;
+    fn wait(&mut self) -> io::Result<ExitStatus>;
+    fn kill(&mut self) -> io::Result<()>;
+}
+
+impl Process for Child {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr
+            .take()
+            .map(|r| Box::new(r) as Box<dyn Read + Send>)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Child::try_wait(self)
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Child::wait(self)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Child::kill(self)
+    }
+}
+
+/// 外部命令执行器：运行程序并等待其结束，返回完整输出；或启动程序后边运行边读取输出。
+pub trait CommandRunner: Send + Sync {
+    fn output(&self, program: &OsStr, args: &[OsString]) -> io::Result<Output>;
+    /// 启动程序并立即返回，标准输出与标准错误重定向为管道
+    fn spawn(&self, program: &OsStr, args: &[OsString]) -> io::Result<Box<dyn Process>>;
+}
+
+/// 真正启动进程的执行器（隐藏控制台窗口）
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemRunner;
+
+impl CommandRunner for SystemRunner {
+    fn output(&self, program: &OsStr, args: &[OsString]) -> io::Result<Output> {
+        new_command(program).args(args).output()
+    }
+
+    fn spawn(&self, program: &OsStr, args: &[OsString]) -> io::Result<Box<dyn Process>> {
+        let child = new_command(program)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()?;
+        Ok(Box::new(child))
+    }
+}
+
+thread_local! {
+    static RUNNER_OVERRIDE: RefCell<Option<Arc<dyn CommandRunner>>> = const { RefCell::new(None) };
+}
+
+/// 当前线程使用的执行器（未替换时为 [`SystemRunner`]）
+pub fn runner() -> Arc<dyn CommandRunner> {
+    RUNNER_OVERRIDE
+        .with(|r| r.borrow().clone())
+        .unwrap_or_else(|| Arc::new(SystemRunner))
+}
+
+/// 在 `f` 执行期间把当前线程的执行器替换为 `runner`，结束（含 panic）后恢复。
+///
+/// 只影响当前线程；流程内部另起的线程仍使用 [`SystemRunner`]。
+pub fn with_runner<R>(runner: Arc<dyn CommandRunner>, f: impl FnOnce() -> R) -> R {
+    struct Restore(Option<Arc<dyn CommandRunner>>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let prev = self.0.take();
+            RUNNER_OVERRIDE.with(|r| *r.borrow_mut() = prev);
+        }
+    }
+
+    let prev = RUNNER_OVERRIDE.with(|r| r.borrow_mut().replace(runner));
+    let _restore = Restore(prev);
+    f()
+}
+
+/// 经当前执行器运行外部程序并等待结束，等价于 `new_command(program).args(args).output()`。
+pub fn run<P, I, S>(program: P, args: I) -> io::Result<Output>
+where
+    P: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    runner().output(program.as_ref(), &collect_args(args))
+}
+
+/// 经当前执行器启动外部程序（输出为管道），用于需要实时读取进度的长时间进程。
+pub fn spawn<P, I, S>(program: P, args: I) -> io::Result<Box<dyn Process>>
+where
+    P: AsRef<OsStr>,
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    runner().spawn(program.as_ref(), &collect_args(args))
+}
+
+fn collect_args<I, S>(args: I) -> Vec<OsString>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<OsStr>,
+{
+    args.into_iter().map(|a| a.as_ref().to_owned()).collect()
+}
+
+/// 用退出码构造 `ExitStatus`（供 [`FakeRunner`] 回放）
+fn exit_status(code: i32) -> ExitStatus {
+    #[cfg(windows)]
+    {
+        use std::os::windows::process::ExitStatusExt;
+        ExitStatus::from_raw(code as u32)
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::process::ExitStatusExt;
+        ExitStatus::from_raw((code & 0xff) << 8)
+    }
+}
+
+/// 程序名归一化：去掉目录与扩展名并转小写（`C:\bin\bootsect.exe` → `bootsect`）
+fn program_key(program: &OsStr) -> String {
+    let text = program.to_string_lossy();
+    let name = text.rsplit(['\\', '/']).next().unwrap_or(&text);
+    let stem = match name.rsplit_once('.') {
+        Some((stem, ext)) if ext.eq_ignore_ascii_case("exe") => stem,
+        _ => name,
+    };
+    stem.to_lowercase()
+}
+
+/// [`FakeRunner`] 的一条预设回应
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct FakeReply {
+    pub code: i32,
+    pub stdout: Vec<u8>,
+    pub stderr: Vec<u8>,
+}
+
+impl FakeReply {
+    /// 退出码 0，带标准输出
+    pub fn ok(stdout: &str) -> Self {
+        Self {
+            stdout: stdout.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+
+    /// 非 0 退出码，带标准错误
+    pub fn fail(code: i32, stderr: &str) -> Self {
+        Self {
+            code,
+            stderr: stderr.as_bytes().to_vec(),
+            ..Default::default()
+        }
+    }
+}
+
+/// [`FakeRunner`] 记录的一次调用
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// 归一化后的程序名（小写、无目录与 `.exe`）
+    pub program: String,
+    pub args: Vec<String>,
+    /// `/s <文件>` 形式的脚本参数（diskpart 等）在调用时读入的内容
+    pub script: Option<String>,
+}
+
+impl Invocation {
+    /// 程序名与参数拼成一行，便于断言
+    pub fn line(&self) -> String {
+        std::iter::once(self.program.as_str())
+            .chain(self.args.iter().map(|a| a.as_str()))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+}
+
+struct FakeRule {
+    program: String,
+    /// 参数中须依次包含的片段（不区分大小写）
+    args_contain: Vec<String>,
+    reply: FakeReply,
+    once: bool,
+}
+
+impl FakeRule {
+    fn matches(&self, program: &str, line: &str) -> bool {
+        self.program == program
+            && self
+                .args_contain
+                .iter()
+                .all(|needle| line.contains(needle.as_str()))
+    }
+}
+
+/// 可编排的假执行器：按规则回放输出与退出码，并记录全部调用。
+///
+/// 规则按添加顺序匹配，先匹配者生效；`once` 规则命中一次后移除，便于编排同一命令的先后结果。
+/// 没有规则命中的调用返回退出码 0、无输出。
+#[derive(Default)]
+pub struct FakeRunner {
+    rules: Mutex<Vec<FakeRule>>,
+    calls: Mutex<Vec<Invocation>>,
+}
+
+impl FakeRunner {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn add(self, program: &str, args_contain: &[&str], reply: FakeReply, once: bool) -> Self {
+        self.rules.lock().unwrap().push(FakeRule {
+            program: program_key(OsStr::new(program)),
+            args_contain: args_contain.iter().map(|a| a.to_lowercase()).collect(),
+            reply,
+            once,
+        });
+        self
+    }
+
+    /// `program` 的参数包含 `args_contain` 全部片段时回应 `reply`（可多次命中）
+    pub fn on(self, program: &str, args_contain: &[&str], reply: FakeReply) -> Self {
+        self.add(program, args_contain, reply, false)
+    }
+
+    /// 同 [`on`](Self::on)，但只命中一次
+    pub fn once(self, program: &str, args_contain: &[&str], reply: FakeReply) -> Self {
+        self.add(program, args_contain, reply, true)
+    }
+
+    /// 至今记录的全部调用
+    pub fn calls(&self) -> Vec<Invocation> {
+        self.calls.lock().unwrap().clone()
+    }
+
+    /// 每次调用的 [`Invocation::line`]
+    pub fn lines(&self) -> Vec<String> {
+        self.calls().iter().map(Invocation::line).collect()
+    }
+}
+
+impl FakeRunner {
+    /// 记录一次调用并取出匹配的预设回应
+    fn respond(&self, program: &OsStr, args: &[OsString]) -> FakeReply {
+        let program = program_key(program);
+        let args: Vec<String> = args
+            .iter()
+            .map(|a| a.to_string_lossy().into_owned())
+            .collect();
+        let script = args
+            .windows(2)
+            .find(|w| w[0].eq_ignore_ascii_case("/s"))
+            .and_then(|w| std::fs::read(Path::new(&w[1])).ok())
+            .map(|b| String::from_utf8_lossy(&b).into_owned());
+        let line = args.join(" ").to_lowercase();
+
+        let reply = {
+            let mut rules = self.rules.lock().unwrap();
+            match rules.iter().position(|r| r.matches(&program, &line)) {
+                Some(i) if rules[i].once => rules.remove(i).reply,
+                Some(i) => rules[i].reply.clone(),
+                None => FakeReply::default(),
+            }
+        };
+        self.calls.lock().unwrap().push(Invocation {
+            program,
+            args,
+            script,
+        });
+        reply
+    }
+}
+
+impl CommandRunner for FakeRunner {
+    fn output(&self, program: &OsStr, args: &[OsString]) -> io::Result<Output> {
+        let reply = self.respond(program, args);
+        Ok(Output {
+            status: exit_status(reply.code),
+            stdout: reply.stdout,
+            stderr: reply.stderr,
+        })
+    }
+
+    fn spawn(&self, program: &OsStr, args: &[OsString]) -> io::Result<Box<dyn Process>> {
+        let reply = self.respond(program, args);
+        Ok(Box::new(FakeProcess {
+            stdout: Some(reply.stdout),
+            stderr: Some(reply.stderr),
+            status: exit_status(reply.code),
+        }))
+    }
+}
+
+/// [`FakeRunner::spawn`] 返回的进程：输出即预设内容，启动后即已结束
+struct FakeProcess {
+    stdout: Option<Vec<u8>>,
+    stderr: Option<Vec<u8>>,
+    status: ExitStatus,
+}
+
+impl Process for FakeProcess {
+    fn take_stdout(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stdout
+            .take()
+            .map(|b| Box::new(io::Cursor::new(b)) as Box<dyn Read + Send>)
+    }
+
+    fn take_stderr(&mut self) -> Option<Box<dyn Read + Send>> {
+        self.stderr
+            .take()
+            .map(|b| Box::new(io::Cursor::new(b)) as Box<dyn Read + Send>)
+    }
+
+    fn try_wait(&mut self) -> io::Result<Option<ExitStatus>> {
+        Ok(Some(self.status))
+    }
+
+    fn wait(&mut self) -> io::Result<ExitStatus> {
+        Ok(self.status)
+    }
+
+    fn kill(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use std::ffi::OsStr;
 
     #[test]
     fn keeps_program_name() {
         let cmd = new_command("reg.exe");
         assert_eq!(cmd.get_program(), OsStr::new("reg.exe"));
     }
+
+    #[test]
+    fn fake_runner_replays_and_records() {
+        let fake = Arc::new(
+            FakeRunner::new()
+                .once("bcdedit", &["/enum"], FakeReply::fail(1, "busy"))
+                .on("bcdedit", &["/enum"], FakeReply::ok("identifier {current}")),
+        );
+        let (first, second, other) = with_runner(fake.clone(), || {
+            (
+                run("bcdedit.exe", ["/enum", "all"]).unwrap(),
+                run("C:\\Windows\\System32\\BCDEDIT.EXE", ["/ENUM"]).unwrap(),
+                run("reg", ["query", "HKLM"]).unwrap(),
+            )
+        });
+        assert_eq!(first.status.code(), Some(1));
+        assert_eq!(first.stderr, b"busy");
+        assert!(second.status.success());
+        assert_eq!(second.stdout, b"identifier {current}");
+        assert!(other.status.success() && other.stdout.is_empty());
+        assert_eq!(
+            fake.lines(),
+            vec!["bcdedit /enum all", "bcdedit /ENUM", "reg query HKLM"]
+        );
+    }
+
+    #[test]
+    fn with_runner_restores_previous() {
+        let outer = Arc::new(FakeRunner::new());
+        let inner = Arc::new(FakeRunner::new());
+        with_runner(outer.clone(), || {
+            let _ = with_runner(inner.clone(), || run("diskpart", ["/s", "missing.txt"]));
+            let _ = run("reg", ["add"]);
+        });
+        assert_eq!(inner.lines(), vec!["diskpart /s missing.txt"]);
+        assert_eq!(inner.calls()[0].script, None);
+        assert_eq!(outer.lines(), vec!["reg add"]);
+    }
+
+    #[test]
+    fn fake_runner_replays_spawned_output() {
+        let fake = Arc::new(FakeRunner::new().on(
+            "dism",
+            &["/apply-image"],
+            FakeReply {
+                code: 2,
+                stdout: b"[==  10.0%  ]\r\n[=====100.0%=====]\r\n".to_vec(),
+                stderr: b"Error: 2".to_vec(),
+            },
+        ));
+        let (status, stdout, stderr) = with_runner(fake.clone(), || {
+            let mut process = spawn("X:\\Windows\\System32\\dism.exe", ["/Apply-Image"]).unwrap();
+            let mut stdout = String::new();
+            process
+                .take_stdout()
+                .unwrap()
+                .read_to_string(&mut stdout)
+                .unwrap();
+            assert!(process.take_stdout().is_none());
+            let mut stderr = String::new();
+            process
+                .take_stderr()
+                .unwrap()
+                .read_to_string(&mut stderr)
+                .unwrap();
+            (process.try_wait().unwrap(), stdout, stderr)
+        });
+        assert_eq!(status.and_then(|s| s.code()), Some(2));
+        assert!(stdout.contains("100.0%"));
+        assert_eq!(stderr, "Error: 2");
+        assert_eq!(fake.lines(), vec!["dism /Apply-Image"]);
+    }
 }
@@ -6,11 +6,39 @@
 //! 其余扩展名忽略；按文件名排序依次执行。用于装机前的分区准备
 //! （在 PE 中、格式化/释放镜像之前运行）。
 
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
-use crate::command::new_command;
+use crate::command;
 use crate::encoding::gbk_to_utf8;
 
+/// 列出目录下会被执行的脚本及其执行程序（`cmd` / `diskpart`），按文件名排序。
+/// 目录不存在或不可读时返回空列表。执行与执行计划（见 `plan`）共用同一规则。
+pub fn list_scripts(dir: &Path) -> Vec<(&'static str, PathBuf)> {
+    let mut entries: Vec<PathBuf> = match std::fs::read_dir(dir) {
+        Ok(rd) => rd
+            .filter_map(|e| e.ok().map(|e| e.path()))
+            .filter(|p| p.is_file())
+            .collect(),
+        Err(_) => return Vec::new(),
+    };
+    entries.sort();
+    entries
+        .into_iter()
+        .filter_map(|path| {
+            let ext = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            match ext.as_str() {
+                "cmd" | "bat" => Some(("cmd", path)),
+                "txt" => Some(("diskpart", path)),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
 /// 运行指定目录下的所有分区脚本，返回合并输出日志。
 ///
 /// - 目录不存在或没有可执行脚本：返回 `Ok(提示信息)`。
@@ -20,32 +48,23 @@ pub fn run_scripts_in_dir(dir: &Path) -> Result<String, String> {
         return Ok(format!("diskpart 脚本目录不存在，跳过：{}", dir.display()));
     }
 
-    let mut entries: Vec<std::path::PathBuf> = std::fs::read_dir(dir)
-        .map_err(|e| format!("读取脚本目录失败：{}", e))?
-        .filter_map(|e| e.ok().map(|e| e.path()))
-        .filter(|p| p.is_file())
-        .collect();
-    entries.sort();
+    if let Err(e) = std::fs::read_dir(dir) {
+        return Err(format!("读取脚本目录失败：{}", e));
+    }
 
     let mut log = String::new();
     let mut any = false;
 
-    for path in entries {
-        let ext = path
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
+    for (program, path) in list_scripts(dir) {
         let path_str = path.to_string_lossy().into_owned();
-        let (program, args): (&str, Vec<String>) = match ext.as_str() {
-            "cmd" | "bat" => ("cmd", vec!["/c".into(), path_str.clone()]),
-            "txt" => ("diskpart", vec!["/s".into(), path_str.clone()]),
-            _ => continue,
+        let args: Vec<String> = match program {
+            "cmd" => vec!["/c".into(), path_str],
+            _ => vec!["/s".into(), path_str],
         };
 
         any = true;
         log.push_str(&format!("\n>>> 执行脚本: {}\n", path.display()));
-        match new_command(program).args(&args).output() {
+        match command::run(program, &args) {
             Ok(out) => {
                 let so = gbk_to_utf8(&out.stdout);
                 let se = gbk_to_utf8(&out.stderr);
@@ -77,3 +96,42 @@ pub fn run_scripts_in_dir(dir: &Path) -> Result<String, String> {
     }
     Ok(log)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{with_runner, FakeReply, FakeRunner};
+    use std::sync::Arc;
+
+    #[test]
+    fn runs_scripts_in_order_and_stops_on_failure() {
+        let dir = std::env::temp_dir().join(format!("lr_diskpart_{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("01_clean.txt"), "select disk 1\nclean\n").unwrap();
+        std::fs::write(dir.join("02_fix.cmd"), "echo fix").unwrap();
+        std::fs::write(dir.join("03_never.txt"), "list disk").unwrap();
+        std::fs::write(dir.join("readme.md"), "ignored").unwrap();
+
+        let fake = Arc::new(
+            FakeRunner::new()
+                .on(
+                    "diskpart",
+                    &["01_clean"],
+                    FakeReply::ok("DiskPart succeeded in cleaning the disk."),
+                )
+                .on("cmd", &["02_fix"], FakeReply::fail(2, "fix failed")),
+        );
+        let result = with_runner(fake.clone(), || run_scripts_in_dir(&dir));
+        let _ = std::fs::remove_dir_all(&dir);
+
+        let log = result.unwrap_err();
+        assert!(log.contains("DiskPart succeeded in cleaning the disk."));
+        assert!(log.contains("fix failed"));
+        let calls = fake.calls();
+        assert_eq!(calls.len(), 2);
+        assert_eq!(calls[0].program, "diskpart");
+        assert_eq!(calls[0].script.as_deref(), Some("select disk 1\nclean\n"));
+        assert_eq!(calls[1].program, "cmd");
+        assert_eq!(calls[1].args[0], "/c");
+    }
+}
@@ -0,0 +1,520 @@
+//! 解析 diskpart 输出（两端共享），不依赖界面语言。
+//!
+//! 以前靠在输出里找「错误 / error / 失败 / failed」判断成败，用「磁盘 / DISK」之类的词抓数字，
+//! 换成英文、德文或日文 PE 就会误判。这里的做法是：
+//! - 成败只看 diskpart 的退出码（`/s` 模式下任一命令出错即以非 0 退出），见 [`succeeded`]；
+//! - `list disk / volume / partition` 等表格按表头下那一行 `---` 的列位置切分，
+//!   列的顺序与个数各语言一致，只有表头文字和状态词不同；
+//! - `detail disk / partition` 的键名是本地化的，只按取值形状识别（GUID、十六进制、纯数字）。
+//!
+//! 列位置按控制台显示宽度计算：中日韩等全角字符占两列。
+
+use std::process::Output;
+
+/// diskpart 是否执行成功（只看退出码）
+pub fn succeeded(output: &Output) -> bool {
+    output.status.success()
+}
+
+/// 字符在控制台中占的列数
+fn char_width(c: char) -> usize {
+    match c as u32 {
+        0x1100..=0x115F
+        | 0x2E80..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6 => 2,
+        _ => 1,
+    }
+}
+
+/// 解析 diskpart 的容量（`476 GB`、`1024 KB`、`0 B`、法文 `12 Go`、俄文 `12 Gбайт`），返回字节数
+pub fn parse_size(text: &str) -> Option<u64> {
+    let mut parts = text.split_whitespace();
+    let value: u64 = parts.next()?.parse().ok()?;
+    let shift = match parts.next().and_then(|u| u.chars().next()) {
+        None => 0,
+        Some(c) => match c.to_uppercase().next().unwrap_or(c) {
+            'K' | 'К' => 10,
+            'M' | 'М' => 20,
+            'G' | 'Г' => 30,
+            'T' | 'Т' => 40,
+            'P' => 50,
+            _ => 0,
+        },
+    };
+    value.checked_mul(1u64 << shift)
+}
+
+/// 单元格里的编号（`Disk 1`、`磁盘 1`、`Datenträger 1` → 1）
+fn trailing_number(cell: &str) -> Option<u32> {
+    cell.split_whitespace().last()?.parse().ok()
+}
+
+/// 表格中的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableRow {
+    /// 行首带 `*`（当前选中的对象）
+    pub selected: bool,
+    pub cells: Vec<String>,
+}
+
+/// 按列位置切分出的一张表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Table {
+    pub rows: Vec<TableRow>,
+}
+
+/// 分隔行里每段 `-` 的 [起始列, 结束列)；不是分隔行返回 None
+fn column_spans(line: &str) -> Option<Vec<(usize, usize)>> {
+    let trimmed = line.trim();
+    if trimmed.is_empty() || !trimmed.chars().all(|c| c == '-' || c == ' ') {
+        return None;
+    }
+    let mut spans = Vec::new();
+    let mut start = None;
+    for (col, c) in line.chars().enumerate() {
+        match (c, start) {
+            ('-', None) => start = Some(col),
+            ('-', Some(_)) => {}
+            (_, Some(s)) => {
+                spans.push((s, col));
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    if let Some(s) = start {
+        spans.push((s, line.chars().count()));
+    }
+    (spans.len() >= 2).then_some(spans)
+}
+
+/// 按列位置切分一行；行首 `*` 出现在第一列之前
+fn split_row(line: &str, spans: &[(usize, usize)]) -> TableRow {
+    let mut cells = vec![String::new(); spans.len()];
+    let mut selected = false;
+    let mut col = 0;
+    for c in line.chars() {
+        let w = char_width(c);
+        if col < spans[0].0 {
+            selected |= c == '*';
+        } else if let Some(i) = spans
+            .iter()
+            .rposition(|&(start, end)| col >= start && col < end.max(start + 1))
+        {
+            cells[i].push(c);
+        } else if let Some(i) = spans.iter().rposition(|&(start, _)| col >= start) {
+            // 落在两列之间的空隙：归到左边一列（超长内容溢出时不丢字）
+            cells[i].push(c);
+        }
+        col += w;
+    }
+    TableRow {
+        selected,
+        cells: cells.iter().map(|c| c.trim().to_string()).collect(),
+    }
+}
+
+/// 找出输出中所有表格（表头行 + `---` 分隔行 + 数据行，遇空行结束）
+pub fn parse_tables(text: &str) -> Vec<Table> {
+    let lines: Vec<&str> = text.lines().map(|l| l.trim_end_matches('\r')).collect();
+    let mut tables = Vec::new();
+    let mut i = 0;
+    while i < lines.len() {
+        let Some(spans) = column_spans(lines[i]) else {
+            i += 1;
+            continue;
+        };
+        let mut rows = Vec::new();
+        i += 1;
+        while i < lines.len() && !lines[i].trim().is_empty() {
+            rows.push(split_row(lines[i], &spans));
+            i += 1;
+        }
+        tables.push(Table { rows });
+    }
+    tables
+}
+
+/// 第一张列数为 `columns` 的表的所有行
+fn rows_with_columns(text: &str, columns: usize) -> Vec<TableRow> {
+    parse_tables(text)
+        .into_iter()
+        .find(|t| t.rows.first().map(|r| r.cells.len()) == Some(columns))
+        .map(|t| t.rows)
+        .unwrap_or_default()
+}
+
+/// `list disk` 的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskRow {
+    pub number: u32,
+    /// 状态（本地化文本，如 `Online` / `联机`）
+    pub status: String,
+    pub size: u64,
+    pub free: u64,
+    pub dynamic: bool,
+    pub gpt: bool,
+    pub selected: bool,
+}
+
+/// 解析 `list disk`（`detail volume` 里的磁盘表格式相同）
+pub fn parse_list_disk(text: &str) -> Vec<DiskRow> {
+    rows_with_columns(text, 6)
+        .into_iter()
+        .filter_map(|r| {
+            Some(DiskRow {
+                number: trailing_number(&r.cells[0])?,
+                status: r.cells[1].clone(),
+                size: parse_size(&r.cells[2]).unwrap_or(0),
+                free: parse_size(&r.cells[3]).unwrap_or(0),
+                dynamic: !r.cells[4].is_empty(),
+                gpt: !r.cells[5].is_empty(),
+                selected: r.selected,
+            })
+        })
+        .collect()
+}
+
+/// `list volume` 的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VolumeRow {
+    pub number: u32,
+    pub letter: Option<char>,
+    pub label: String,
+    pub file_system: String,
+    /// 类型（本地化文本，如 `Partition` / `磁盘分区` / `Removable`）
+    pub kind: String,
+    pub size: u64,
+    /// 状态（本地化文本）
+    pub status: String,
+    /// 附加信息（本地化文本，如 `Boot` / `启动`）
+    pub info: String,
+    pub selected: bool,
+}
+
+/// 解析 `list volume`（`detail disk` / `detail partition` 末尾的卷表格式相同）
+pub fn parse_list_volume(text: &str) -> Vec<VolumeRow> {
+    rows_with_columns(text, 8)
+        .into_iter()
+        .filter_map(|r| {
+            Some(VolumeRow {
+                number: trailing_number(&r.cells[0])?,
+                letter: r.cells[1]
+                    .chars()
+                    .next()
+                    .filter(|c| c.is_ascii_alphabetic())
+                    .map(|c| c.to_ascii_uppercase()),
+                label: r.cells[2].clone(),
+                file_system: r.cells[3].clone(),
+                kind: r.cells[4].clone(),
+                size: parse_size(&r.cells[5]).unwrap_or(0),
+                status: r.cells[6].clone(),
+                info: r.cells[7].clone(),
+                selected: r.selected,
+            })
+        })
+        .collect()
+}
+
+/// `list partition` 的一行
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionRow {
+    pub number: u32,
+    /// 类型（本地化文本，如 `Primary` / `主要` / `System` / `系统`）
+    pub kind: String,
+    pub size: u64,
+    pub offset: u64,
+    pub selected: bool,
+}
+
+/// 解析 `list partition`
+pub fn parse_list_partition(text: &str) -> Vec<PartitionRow> {
+    rows_with_columns(text, 4)
+        .into_iter()
+        .filter_map(|r| {
+            Some(PartitionRow {
+                number: trailing_number(&r.cells[0])?,
+                kind: r.cells[1].clone(),
+                size: parse_size(&r.cells[2]).unwrap_or(0),
+                offset: parse_size(&r.cells[3]).unwrap_or(0),
+                selected: r.selected,
+            })
+        })
+        .collect()
+}
+
+/// `detail disk / partition` 的键值区：返回键值区前一行（型号 / `Partition 3`）与各行的值。
+///
+/// 跳过开头的版权横幅（含 `On computer: 名称` 这一键值行）和 `select` 的提示行，
+/// 到第一张表格为止；键名本地化，不使用。
+fn detail_section(text: &str) -> (Option<&str>, Vec<&str>) {
+    let lines: Vec<&str> = text.lines().map(|l| l.trim()).collect();
+    let mut start = 0;
+    if let Some(i) = lines
+        .iter()
+        .position(|l| l.contains("Microsoft Corporation"))
+    {
+        // 横幅下一行非空行是「在计算机上: 名称」
+        start = lines[i + 1..]
+            .iter()
+            .position(|l| !l.is_empty())
+            .map_or(lines.len(), |j| i + 2 + j);
+    }
+    let lines: Vec<&str> = lines[start..]
+        .iter()
+        .copied()
+        .take_while(|l| column_spans(l).is_none())
+        .collect();
+    let is_kv = |l: &&str| l.contains([':', '：']);
+    let Some(first) = lines.iter().position(is_kv) else {
+        return (None, Vec::new());
+    };
+    let header = lines[..first].iter().rev().find(|l| !l.is_empty()).copied();
+    let values = lines[first..]
+        .iter()
+        .filter_map(|l| l.split_once([':', '：']))
+        .map(|(_, v)| v.trim())
+        .collect();
+    (header, values)
+}
+
+fn is_hex(s: &str, len: usize) -> bool {
+    s.len() == len && s.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_guid(s: &str) -> bool {
+    let s = s.trim_start_matches('{').trim_end_matches('}');
+    let parts: Vec<&str> = s.split('-').collect();
+    parts.len() == 5
+        && parts
+            .iter()
+            .zip([8, 4, 4, 4, 12])
+            .all(|(p, n)| is_hex(p, n))
+}
+
+/// 分区表类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskStyle {
+    Gpt,
+    Mbr,
+}
+
+/// `detail disk` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskDetail {
+    /// 第一行的型号
+    pub model: String,
+    /// 磁盘 ID：GPT 为 `{GUID}`，MBR 为 8 位十六进制签名
+    pub disk_id: String,
+    /// 由磁盘 ID 的形状判断；读不到 ID 时为 None
+    pub style: Option<DiskStyle>,
+    pub volumes: Vec<VolumeRow>,
+}
+
+/// 解析 `detail disk`
+pub fn parse_detail_disk(text: &str) -> DiskDetail {
+    let (header, values) = detail_section(text);
+    let model = header.unwrap_or_default().to_string();
+    let disk_id = values
+        .iter()
+        .find(|v| is_guid(v) || is_hex(v, 8))
+        .copied()
+        .unwrap_or_default()
+        .to_string();
+    let style = if is_guid(&disk_id) {
+        Some(DiskStyle::Gpt)
+    } else if disk_id.is_empty() {
+        None
+    } else {
+        Some(DiskStyle::Mbr)
+    };
+    DiskDetail {
+        model,
+        disk_id,
+        style,
+        volumes: parse_list_volume(text),
+    }
+}
+
+/// `detail partition` 的结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionDetail {
+    pub number: Option<u32>,
+    /// 分区类型：GPT 为类型 GUID（小写、无花括号），MBR 为两位十六进制（如 `07`）
+    pub type_id: String,
+    /// GPT 属性（MBR 分区没有）
+    pub attributes: Option<u64>,
+    /// 起始偏移（字节）
+    pub offset: Option<u64>,
+    pub volumes: Vec<VolumeRow>,
+}
+
+impl PartitionDetail {
+    /// 是否为 EFI 系统分区（GPT 类型 GUID 或 MBR 类型 `EF`）
+    pub fn is_esp(&self) -> bool {
+        self.type_id == crate::diskpart_script::PartitionId::GPT_EFI || self.type_id == "ef"
+    }
+}
+
+/// `list partition` 中可能是 ESP 的分区号（不超过 1 GB，按分区号顺序），
+/// 供逐个 `detail partition` 比对类型；类型列是本地化文本，不能直接用来判断。
+pub fn esp_candidates(rows: &[PartitionRow]) -> Vec<u32> {
+    rows.iter()
+        .filter(|r| r.size > 0 && r.size <= 1 << 30)
+        .map(|r| r.number)
+        .collect()
+}
+
+/// 解析 `detail partition`
+pub fn parse_detail_partition(text: &str) -> PartitionDetail {
+    let (header, values) = detail_section(text);
+    let number = header.and_then(trailing_number);
+    let type_id = values
+        .iter()
+        .find(|v| is_guid(v) || is_hex(v, 2))
+        .map(|v| v.trim_matches(['{', '}']).to_ascii_lowercase())
+        .unwrap_or_default();
+    let attributes = values.iter().find_map(|v| {
+        let hex = v.strip_prefix("0x").or_else(|| v.strip_prefix("0X"))?;
+        is_hex(hex, 16)
+            .then(|| u64::from_str_radix(hex, 16).ok())
+            .flatten()
+    });
+    // 偏移是键值区里唯一的多位纯数字
+    let offset = values
+        .iter()
+        .rfind(|v| v.len() > 2 && v.chars().all(|c| c.is_ascii_digit()))
+        .and_then(|v| v.parse().ok());
+    PartitionDetail {
+        number,
+        type_id,
+        attributes,
+        offset,
+        volumes: parse_list_volume(text),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LIST_DISK_EN: &str = include_str!("diskpart_fixtures/list_disk_en.txt");
+    const LIST_DISK_ZH: &str = include_str!("diskpart_fixtures/list_disk_zh.txt");
+    const LIST_DISK_DE: &str = include_str!("diskpart_fixtures/list_disk_de.txt");
+    const LIST_DISK_JA: &str = include_str!("diskpart_fixtures/list_disk_ja.txt");
+    const LIST_VOLUME_EN: &str = include_str!("diskpart_fixtures/list_volume_en.txt");
+    const LIST_VOLUME_ZH: &str = include_str!("diskpart_fixtures/list_volume_zh.txt");
+    const LIST_VOLUME_DE: &str = include_str!("diskpart_fixtures/list_volume_de.txt");
+    const LIST_PARTITION_EN: &str = include_str!("diskpart_fixtures/list_partition_en.txt");
+    const LIST_PARTITION_ZH: &str = include_str!("diskpart_fixtures/list_partition_zh.txt");
+    const LIST_PARTITION_FR: &str = include_str!("diskpart_fixtures/list_partition_fr.txt");
+    const DETAIL_DISK_EN: &str = include_str!("diskpart_fixtures/detail_disk_en.txt");
+    const DETAIL_DISK_ZH: &str = include_str!("diskpart_fixtures/detail_disk_zh.txt");
+    const DETAIL_PARTITION_EN: &str = include_str!("diskpart_fixtures/detail_partition_en.txt");
+    const DETAIL_PARTITION_JA: &str = include_str!("diskpart_fixtures/detail_partition_ja.txt");
+
+    #[test]
+    fn parses_size_units() {
+        assert_eq!(parse_size("476 GB"), Some(476 << 30));
+        assert_eq!(parse_size("1024 KB"), Some(1 << 20));
+        assert_eq!(parse_size("0 B"), Some(0));
+        assert_eq!(parse_size("12 Go"), Some(12 << 30));
+        assert_eq!(parse_size("300 Мбайт"), Some(300 << 20));
+        assert_eq!(parse_size(""), None);
+    }
+
+    #[test]
+    fn list_disk_same_in_all_languages() {
+        for text in [LIST_DISK_EN, LIST_DISK_ZH, LIST_DISK_DE, LIST_DISK_JA] {
+            let disks = parse_list_disk(text);
+            assert_eq!(disks.len(), 2, "{}", text);
+            assert_eq!(
+                (disks[0].number, disks[0].size, disks[0].free),
+                (0, 476 << 30, 0)
+            );
+            assert!(disks[0].gpt && disks[0].selected && !disks[0].dynamic);
+            assert_eq!((disks[1].number, disks[1].free), (1, 1024 << 10));
+            assert!(!disks[1].gpt && !disks[1].selected);
+        }
+    }
+
+    #[test]
+    fn list_volume_same_in_all_languages() {
+        for text in [LIST_VOLUME_EN, LIST_VOLUME_ZH, LIST_VOLUME_DE] {
+            let volumes = parse_list_volume(text);
+            assert_eq!(volumes.len(), 4, "{}", text);
+            assert_eq!(volumes[0].letter, Some('E'));
+            assert_eq!(volumes[1].letter, Some('C'));
+            assert_eq!(volumes[1].file_system, "NTFS");
+            assert_eq!(volumes[1].size, 237 << 30);
+            assert!(volumes[1].selected);
+            assert_eq!(volumes[2].letter, None);
+            assert_eq!(volumes[2].file_system, "FAT32");
+            assert_eq!(volumes[2].size, 100 << 20);
+            assert_eq!((volumes[3].letter, volumes[3].size), (Some('D'), 931 << 30));
+        }
+        assert_eq!(parse_list_volume(LIST_VOLUME_EN)[3].label, "Data Disk");
+        assert_eq!(parse_list_volume(LIST_VOLUME_ZH)[3].label, "数据盘");
+    }
+
+    #[test]
+    fn list_partition_same_in_all_languages() {
+        for text in [LIST_PARTITION_EN, LIST_PARTITION_ZH, LIST_PARTITION_FR] {
+            let parts = parse_list_partition(text);
+            assert_eq!(esp_candidates(&parts), vec![1, 2, 4]);
+            let summary: Vec<(u32, u64, u64, bool)> = parts
+                .iter()
+                .map(|p| (p.number, p.size, p.offset, p.selected))
+                .collect();
+            assert_eq!(
+                summary,
+                vec![
+                    (1, 100 << 20, 1 << 20, false),
+                    (2, 16 << 20, 101 << 20, false),
+                    (3, 237 << 30, 117 << 20, true),
+                    (4, 768 << 20, 238 << 30, false),
+                ],
+                "{}",
+                text
+            );
+        }
+    }
+
+    #[test]
+    fn detail_disk_style_from_disk_id() {
+        let gpt = parse_detail_disk(DETAIL_DISK_EN);
+        assert_eq!(gpt.model, "Samsung SSD 970 EVO Plus 500GB");
+        assert_eq!(gpt.disk_id, "{6A1F3C2B-9D4E-4B7A-8C11-2F0E5D3A9B77}");
+        assert_eq!(gpt.style, Some(DiskStyle::Gpt));
+        assert_eq!(gpt.volumes.len(), 2);
+
+        let mbr = parse_detail_disk(DETAIL_DISK_ZH);
+        assert_eq!(mbr.disk_id, "5C3A9E21");
+        assert_eq!(mbr.style, Some(DiskStyle::Mbr));
+        assert_eq!(mbr.volumes[0].letter, Some('D'));
+
+        assert_eq!(parse_detail_disk("").style, None);
+    }
+
+    #[test]
+    fn detail_partition_by_value_shape() {
+        let gpt = parse_detail_partition(DETAIL_PARTITION_EN);
+        assert_eq!(gpt.number, Some(3));
+        assert_eq!(gpt.type_id, "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7");
+        assert_eq!(gpt.attributes, Some(0));
+        assert_eq!(gpt.offset, Some(122683392));
+        assert_eq!(gpt.volumes[0].letter, Some('C'));
+        assert!(!gpt.is_esp());
+
+        let mbr = parse_detail_partition(DETAIL_PARTITION_JA);
+        assert_eq!(mbr.number, Some(1));
+        assert_eq!(mbr.type_id, "07");
+        assert_eq!(mbr.attributes, None);
+        assert_eq!(mbr.offset, Some(1048576));
+        assert_eq!(mbr.volumes[0].letter, Some('D'));
+    }
+}
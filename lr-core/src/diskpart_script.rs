@@ -0,0 +1,573 @@
+//! 类型化的 diskpart 脚本构造器（两端共享）。
+//!
+//! 以前一键分区、PE 临时数据分区、无损扩容等处各自用 `format!` 拼脚本，卷标里带引号、
+//! 文件系统写错或盘符不合法都会让整段脚本跑偏。这里把常用命令做成带校验的方法：
+//! 链式调用时记录第一处错误，[`DiskpartScript::build`] 统一返回，保证不会生成半截脚本。
+//!
+//! diskpart 不支持转义，卷标一律加引号输出，引号与控制字符直接判为非法。
+//! 大小单位与 diskpart 一致：`size` / `desired` 为 MB，`offset` 为 KB。
+
+use std::fmt;
+
+/// 文件系统
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileSystem {
+    Ntfs,
+    Fat32,
+    ExFat,
+    Refs,
+}
+
+impl FileSystem {
+    /// 按名称解析（不区分大小写，如 `"NTFS"`、`"exFAT"`）
+    pub fn parse(name: &str) -> Option<Self> {
+        match name.trim().to_ascii_lowercase().as_str() {
+            "ntfs" => Some(FileSystem::Ntfs),
+            "fat32" => Some(FileSystem::Fat32),
+            "exfat" => Some(FileSystem::ExFat),
+            "refs" => Some(FileSystem::Refs),
+            _ => None,
+        }
+    }
+
+    /// diskpart `format fs=` 的取值
+    pub fn as_str(self) -> &'static str {
+        match self {
+            FileSystem::Ntfs => "ntfs",
+            FileSystem::Fat32 => "fat32",
+            FileSystem::ExFat => "exfat",
+            FileSystem::Refs => "refs",
+        }
+    }
+
+    /// 卷标最大长度：FAT32/exFAT 为 OEM 代码页下的字节数，NTFS/ReFS 为字符数
+    pub fn max_label_len(self) -> usize {
+        match self {
+            FileSystem::Fat32 | FileSystem::ExFat => 11,
+            FileSystem::Ntfs | FileSystem::Refs => 32,
+        }
+    }
+
+    /// 卷标是否超长。FAT32/exFAT 的卷标按 OEM 代码页（中文系统为 GBK，一个汉字 2 字节）存放，
+    /// 按编码后的字节数判断；无法编码的字符会被替换成更长的转义序列，同样判为超长。
+    pub fn label_too_long(self, label: &str) -> bool {
+        match self {
+            FileSystem::Fat32 | FileSystem::ExFat => {
+                crate::encoding::utf8_to_gbk(label).len() > self.max_label_len()
+            }
+            FileSystem::Ntfs | FileSystem::Refs => label.chars().count() > self.max_label_len(),
+        }
+    }
+}
+
+/// 分区表类型（`convert`）
+#[derive(Debug, Clone, Copy, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    Gpt,
+    Mbr,
+}
+
+/// `create partition` 的分区类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionKind {
+    Efi,
+    Msr,
+    Primary,
+    Extended,
+    Logical,
+}
+
+impl PartitionKind {
+    fn as_str(self) -> &'static str {
+        match self {
+            PartitionKind::Efi => "efi",
+            PartitionKind::Msr => "msr",
+            PartitionKind::Primary => "primary",
+            PartitionKind::Extended => "extended",
+            PartitionKind::Logical => "logical",
+        }
+    }
+}
+
+/// `set id=` 的分区类型标识
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PartitionId {
+    /// MBR 类型字节，如 `0x07`（NTFS/IFS）、`0x27`（隐藏恢复分区）
+    Mbr(u8),
+    /// GPT 类型 GUID（不带花括号）
+    Gpt(String),
+}
+
+impl PartitionId {
+    /// 基本数据分区
+    pub const GPT_BASIC_DATA: &'static str = "ebd0a0a2-b9e5-4433-87c0-68b6b72699c7";
+    /// 恢复分区（WinRE）
+    pub const GPT_RECOVERY: &'static str = "de94bba4-06d1-4d40-a16a-bfd50179d6ac";
+    /// EFI 系统分区
+    pub const GPT_EFI: &'static str = "c12a7328-f81f-11d2-ba4b-00a0c93ec93b";
+}
+
+/// 构造脚本时的校验错误
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ScriptError {
+    /// 盘符不是 A-Z
+    InvalidLetter(char),
+    /// 卷标含引号 / 控制字符，或超出文件系统允许的长度
+    InvalidLabel(String),
+    /// 大小为 0
+    ZeroSize(&'static str),
+    /// GPT 类型 GUID 格式不对
+    InvalidGuid(String),
+    /// 未选中对象就执行需要焦点的命令
+    NoFocus(&'static str),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::InvalidLetter(c) => write!(f, "非法盘符: {:?}", c),
+            ScriptError::InvalidLabel(l) => write!(f, "非法卷标: {:?}", l),
+            ScriptError::ZeroSize(cmd) => write!(f, "{} 的大小不能为 0", cmd),
+            ScriptError::InvalidGuid(g) => write!(f, "非法的分区类型 GUID: {}", g),
+            ScriptError::NoFocus(cmd) => write!(f, "{} 之前没有选中磁盘/分区/卷", cmd),
+        }
+    }
+}
+
+impl std::error::Error for ScriptError {}
+
+/// 当前选中的对象（决定哪些命令可用）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    None,
+    Disk,
+    Partition,
+}
+
+/// diskpart 脚本构造器
+#[derive(Debug, Clone)]
+pub struct DiskpartScript {
+    lines: Vec<String>,
+    focus: Focus,
+    error: Option<ScriptError>,
+}
+
+impl Default for DiskpartScript {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn valid_guid(guid: &str) -> bool {
+    let parts: Vec<&str> = guid.split('-').collect();
+    let lens = [8, 4, 4, 4, 12];
+    parts.len() == lens.len()
+        && parts
+            .iter()
+            .zip(lens)
+            .all(|(p, n)| p.len() == n && p.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// 规范化盘符（接受 `'d'`），非 A-Z 返回 None
+fn normalize_letter(letter: char) -> Option<char> {
+    let upper = letter.to_ascii_uppercase();
+    upper.is_ascii_uppercase().then_some(upper)
+}
+
+impl DiskpartScript {
+    pub fn new() -> Self {
+        Self {
+            lines: Vec::new(),
+            focus: Focus::None,
+            error: None,
+        }
+    }
+
+    fn push(mut self, line: String) -> Self {
+        if self.error.is_none() {
+            self.lines.push(line);
+        }
+        self
+    }
+
+    fn fail(mut self, error: ScriptError) -> Self {
+        if self.error.is_none() {
+            self.error = Some(error);
+        }
+        self
+    }
+
+    fn require(self, focus: Focus, cmd: &'static str) -> Result<Self, Self> {
+        let ok = match focus {
+            Focus::Disk => self.focus != Focus::None,
+            Focus::Partition => self.focus == Focus::Partition,
+            Focus::None => true,
+        };
+        if ok {
+            Ok(self)
+        } else {
+            Err(self.fail(ScriptError::NoFocus(cmd)))
+        }
+    }
+
+    fn letter_or_fail(self, letter: char) -> Result<(Self, char), Self> {
+        match normalize_letter(letter) {
+            Some(l) => Ok((self, l)),
+            None => Err(self.fail(ScriptError::InvalidLetter(letter))),
+        }
+    }
+
+    pub fn select_disk(mut self, disk: u32) -> Self {
+        self.focus = Focus::Disk;
+        self.push(format!("select disk {}", disk))
+    }
+
+    pub fn select_partition(self, partition: u32) -> Self {
+        match self.require(Focus::Disk, "select partition") {
+            Ok(mut s) => {
+                s.focus = Focus::Partition;
+                s.push(format!("select partition {}", partition))
+            }
+            Err(s) => s,
+        }
+    }
+
+    /// 按盘符选中卷（同时视为选中其所在分区）
+    pub fn select_volume_letter(self, letter: char) -> Self {
+        match self.letter_or_fail(letter) {
+            Ok((mut s, l)) => {
+                s.focus = Focus::Partition;
+                s.push(format!("select volume {}", l))
+            }
+            Err(s) => s,
+        }
+    }
+
+    /// 按卷号选中卷
+    pub fn select_volume(mut self, volume: u32) -> Self {
+        self.focus = Focus::Partition;
+        self.push(format!("select volume {}", volume))
+    }
+
+    pub fn rescan(self) -> Self {
+        self.push("rescan".to_string())
+    }
+
+    /// 清除整个磁盘（删除全部分区）
+    pub fn clean(self) -> Self {
+        match self.require(Focus::Disk, "clean") {
+            Ok(s) => s.push("clean".to_string()),
+            Err(s) => s,
+        }
+    }
+
+    pub fn convert(self, style: TableStyle) -> Self {
+        let style = match style {
+            TableStyle::Gpt => "gpt",
+            TableStyle::Mbr => "mbr",
+        };
+        match self.require(Focus::Disk, "convert") {
+            Ok(s) => s.push(format!("convert {}", style)),
+            Err(s) => s,
+        }
+    }
+
+    /// 创建分区；`size_mb` 为 None 时占用剩余空间，`offset_kb` 为 None 时由 diskpart 决定位置
+    pub fn create_partition(
+        self,
+        kind: PartitionKind,
+        size_mb: Option<u64>,
+        offset_kb: Option<u64>,
+    ) -> Self {
+        if size_mb == Some(0) {
+            return self.fail(ScriptError::ZeroSize("create partition"));
+        }
+        let mut line = format!("create partition {}", kind.as_str());
+        if let Some(size) = size_mb {
+            line.push_str(&format!(" size={}", size));
+        }
+        if let Some(offset) = offset_kb {
+            line.push_str(&format!(" offset={}", offset));
+        }
+        match self.require(Focus::Disk, "create partition") {
+            Ok(mut s) => {
+                s.focus = Focus::Partition;
+                s.push(line)
+            }
+            Err(s) => s,
+        }
+    }
+
+    /// 快速或完整格式化；卷标为空时不写 `label=`
+    pub fn format(self, fs: FileSystem, label: &str, quick: bool) -> Self {
+        if fs.label_too_long(label) || label.chars().any(|c| c == '"' || c.is_control()) {
+            return self.fail(ScriptError::InvalidLabel(label.to_string()));
+        }
+        let mut line = format!("format fs={}", fs.as_str());
+        if quick {
+            line.push_str(" quick");
+        }
+        if !label.is_empty() {
+            line.push_str(&format!(" label=\"{}\"", label));
+        }
+        match self.require(Focus::Partition, "format") {
+            Ok(s) => s.push(line),
+            Err(s) => s,
+        }
+    }
+
+    /// 分配指定盘符
+    pub fn assign_letter(self, letter: char) -> Self {
+        match self.letter_or_fail(letter) {
+            Ok((s, l)) => match s.require(Focus::Partition, "assign") {
+                Ok(s) => s.push(format!("assign letter={}", l)),
+                Err(s) => s,
+            },
+            Err(s) => s,
+        }
+    }
+
+    /// 由系统自动分配盘符
+    pub fn assign(self) -> Self {
+        match self.require(Focus::Partition, "assign") {
+            Ok(s) => s.push("assign".to_string()),
+            Err(s) => s,
+        }
+    }
+
+    pub fn remove_letter(self, letter: char) -> Self {
+        match self.letter_or_fail(letter) {
+            Ok((s, l)) => match s.require(Focus::Partition, "remove") {
+                Ok(s) => s.push(format!("remove letter={}", l)),
+                Err(s) => s,
+            },
+            Err(s) => s,
+        }
+    }
+
+    /// 设置分区类型；`force` 对应 `override`
+    pub fn set_id(self, id: PartitionId, force: bool) -> Self {
+        let value = match id {
+            PartitionId::Mbr(b) => format!("{:02X}", b),
+            PartitionId::Gpt(guid) => {
+                let guid = guid
+                    .trim_matches(|c| c == '{' || c == '}')
+                    .to_ascii_lowercase();
+                if !valid_guid(&guid) {
+                    return self.fail(ScriptError::InvalidGuid(guid));
+                }
+                guid
+            }
+        };
+        let line = if force {
+            format!("set id={} override", value)
+        } else {
+            format!("set id={}", value)
+        };
+        match self.require(Focus::Partition, "set id") {
+            Ok(s) => s.push(line),
+            Err(s) => s,
+        }
+    }
+
+    /// 设置 GPT 分区属性（如 `0x8000000000000001` = 必需 + 不分配盘符）
+    pub fn gpt_attributes(self, attributes: u64) -> Self {
+        match self.require(Focus::Partition, "gpt attributes") {
+            Ok(s) => s.push(format!("gpt attributes=0x{:016x}", attributes)),
+            Err(s) => s,
+        }
+    }
+
+    /// MBR 活动分区
+    pub fn active(self) -> Self {
+        match self.require(Focus::Partition, "active") {
+            Ok(s) => s.push("active".to_string()),
+            Err(s) => s,
+        }
+    }
+
+    /// 缩小选中卷 `desired_mb`；`minimum_mb` 为可接受的最小缩小量
+    pub fn shrink(self, desired_mb: u64, minimum_mb: Option<u64>) -> Self {
+        if desired_mb == 0 {
+            return self.fail(ScriptError::ZeroSize("shrink"));
+        }
+        let mut line = format!("shrink desired={}", desired_mb);
+        if let Some(min) = minimum_mb {
+            line.push_str(&format!(" minimum={}", min));
+        }
+        match self.require(Focus::Partition, "shrink") {
+            Ok(s) => s.push(line),
+            Err(s) => s,
+        }
+    }
+
+    /// 查询选中卷可缩小的最大空间
+    pub fn shrink_querymax(self) -> Self {
+        match self.require(Focus::Partition, "shrink querymax") {
+            Ok(s) => s.push("shrink querymax".to_string()),
+            Err(s) => s,
+        }
+    }
+
+    /// 扩展选中卷；`size_mb` 为 None 时用尽其后的全部未分配空间
+    pub fn extend(self, size_mb: Option<u64>) -> Self {
+        if size_mb == Some(0) {
+            return self.fail(ScriptError::ZeroSize("extend"));
+        }
+        let line = match size_mb {
+            Some(size) => format!("extend size={}", size),
+            None => "extend".to_string(),
+        };
+        match self.require(Focus::Partition, "extend") {
+            Ok(s) => s.push(line),
+            Err(s) => s,
+        }
+    }
+
+    /// 删除选中分区；`force` 对应 `override`（允许删除受保护分区）
+    pub fn delete_partition(mut self, force: bool) -> Self {
+        let line = if force {
+            "delete partition override"
+        } else {
+            "delete partition"
+        };
+        match self.focus {
+            Focus::Partition => {
+                // 删除后焦点回到所在磁盘
+                self.focus = Focus::Disk;
+                self.push(line.to_string())
+            }
+            _ => self.fail(ScriptError::NoFocus("delete partition")),
+        }
+    }
+
+    /// 生成脚本文本（每行以 `\n` 结尾）
+    pub fn build(self) -> Result<String, ScriptError> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        Ok(self.lines.iter().map(|l| format!("{}\n", l)).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quick_partition_layout_script() {
+        let script = DiskpartScript::new()
+            .select_disk(1)
+            .clean()
+            .convert(TableStyle::Gpt)
+            .create_partition(PartitionKind::Efi, Some(300), None)
+            .format(FileSystem::Fat32, "EFI", true)
+            .create_partition(PartitionKind::Msr, Some(16), None)
+            .create_partition(PartitionKind::Primary, Some(102400), None)
+            .format(FileSystem::Ntfs, "系统", true)
+            .assign_letter('s')
+            .create_partition(PartitionKind::Primary, None, Some(1048576))
+            .format(FileSystem::parse("NTFS").unwrap(), "", false)
+            .assign()
+            .set_id(
+                PartitionId::Gpt(format!("{{{}}}", PartitionId::GPT_RECOVERY)),
+                true,
+            )
+            .gpt_attributes(0x8000000000000001)
+            .build()
+            .unwrap();
+        assert_eq!(
+            script,
+            "select disk 1\n\
+             clean\n\
+             convert gpt\n\
+             create partition efi size=300\n\
+             format fs=fat32 quick label=\"EFI\"\n\
+             create partition msr size=16\n\
+             create partition primary size=102400\n\
+             format fs=ntfs quick label=\"系统\"\n\
+             assign letter=S\n\
+             create partition primary offset=1048576\n\
+             format fs=ntfs\n\
+             assign\n\
+             set id=de94bba4-06d1-4d40-a16a-bfd50179d6ac override\n\
+             gpt attributes=0x8000000000000001\n"
+        );
+    }
+
+    #[test]
+    fn volume_commands_script() {
+        let script = DiskpartScript::new()
+            .select_volume_letter('D')
+            .shrink(20480, Some(10240))
+            .create_partition(PartitionKind::Primary, None, None)
+            .set_id(PartitionId::Mbr(0x07), false)
+            .select_volume_letter('E')
+            .delete_partition(true)
+            .select_volume(3)
+            .extend(None)
+            .remove_letter('z')
+            .rescan()
+            .build()
+            .unwrap();
+        assert_eq!(
+            script,
+            "select volume D\n\
+             shrink desired=20480 minimum=10240\n\
+             create partition primary\n\
+             set id=07\n\
+             select volume E\n\
+             delete partition override\n\
+             select volume 3\n\
+             extend\n\
+             remove letter=Z\n\
+             rescan\n"
+        );
+    }
+
+    #[test]
+    fn rejects_bad_inputs() {
+        let label = DiskpartScript::new()
+            .select_volume_letter('D')
+            .format(FileSystem::Ntfs, "Data\" assign letter=C", true)
+            .build();
+        assert!(matches!(label, Err(ScriptError::InvalidLabel(_))));
+
+        let fat_label = DiskpartScript::new()
+            .select_volume(2)
+            .format(FileSystem::Fat32, "TOO LONG LABEL", true)
+            .build();
+        assert!(matches!(fat_label, Err(ScriptError::InvalidLabel(_))));
+
+        // 6 个汉字在 GBK 下 12 字节，超过 FAT32 的 11 字节；5 个（10 字节）可以
+        assert!(FileSystem::Fat32.label_too_long("系统启动分区盘"));
+        assert!(FileSystem::ExFat.label_too_long("系统启动分区"));
+        assert!(!FileSystem::Fat32.label_too_long("系统启动区"));
+        assert!(!FileSystem::Ntfs.label_too_long("系统启动分区"));
+
+        assert_eq!(
+            DiskpartScript::new().select_volume_letter('1').build(),
+            Err(ScriptError::InvalidLetter('1'))
+        );
+        assert_eq!(
+            DiskpartScript::new()
+                .select_disk(0)
+                .create_partition(PartitionKind::Primary, Some(0), None)
+                .build(),
+            Err(ScriptError::ZeroSize("create partition"))
+        );
+        assert!(matches!(
+            DiskpartScript::new()
+                .select_volume(1)
+                .set_id(PartitionId::Gpt("not-a-guid".into()), false)
+                .build(),
+            Err(ScriptError::InvalidGuid(_))
+        ));
+        // 第一处错误之后的命令不再生效，也不会掩盖该错误
+        assert_eq!(
+            DiskpartScript::new().clean().select_disk(0).clean().build(),
+            Err(ScriptError::NoFocus("clean"))
+        );
+    }
+}
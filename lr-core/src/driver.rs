@@ -16,6 +16,9 @@ use std::ptr::null_mut;
 use anyhow::{bail, Context, Result};
 use libloading::Library;
 
+use crate::driver_match::{self, DeviceIds, MatchResult};
+use crate::driver_report::{DriverApiError, DriverImportReport, ImportOutcome, InfImportResult};
+
 #[cfg(windows)]
 use windows::Win32::Foundation::{GetLastError, BOOL, HWND};
 
@@ -30,6 +33,7 @@ const DIGCF_ALLCLASSES: u32 = 0x0000_0004;
 const SPDRP_DRIVER: u32 = 0x0000_0009;
 const SPDRP_INF_PATH: u32 = 0x0000_0010;
 const SPDRP_HARDWAREID: u32 = 0x0000_0001;
+const SPDRP_COMPATIBLEIDS: u32 = 0x0000_0002;
 const SPDRP_DEVICEDESC: u32 = 0x0000_0000;
 const SPDRP_MFG: u32 = 0x0000_000B;
 const SPDRP_CLASS: u32 = 0x0000_0007;
@@ -136,6 +140,14 @@ type FnSetupDiGetDeviceRegistryPropertyW = unsafe extern "system" fn(
 
 type FnSetupDiDestroyDeviceInfoList = unsafe extern "system" fn(dev_info: HDevInfo) -> BOOL;
 
+type FnSetupDiGetDeviceInstanceIdW = unsafe extern "system" fn(
+    dev_info: HDevInfo,
+    device_info_data: *const SpDevInfoData,
+    device_instance_id: *mut u16,
+    device_instance_id_size: u32,
+    required_size: *mut u32,
+) -> BOOL;
+
 type FnSetupCopyOEMInfW = unsafe extern "system" fn(
     source_inf_file_name: *const u16,
     oem_source_media_location: *const u16,
@@ -258,6 +270,7 @@ struct SetupApi {
     enum_device_info: FnSetupDiEnumDeviceInfo,
     get_device_registry_property: FnSetupDiGetDeviceRegistryPropertyW,
     destroy_device_info_list: FnSetupDiDestroyDeviceInfoList,
+    get_device_instance_id: FnSetupDiGetDeviceInstanceIdW,
     copy_oem_inf: FnSetupCopyOEMInfW,
     uninstall_oem_inf: FnSetupUninstallOEMInfW,
     get_inf_driver_store_location: Option<FnSetupGetInfDriverStoreLocationW>,
@@ -277,6 +290,8 @@ impl SetupApi {
                 *lib.get(b"SetupDiGetDeviceRegistryPropertyW")?;
             let destroy_device_info_list: FnSetupDiDestroyDeviceInfoList = 
                 *lib.get(b"SetupDiDestroyDeviceInfoList")?;
+            let get_device_instance_id: FnSetupDiGetDeviceInstanceIdW =
+                *lib.get(b"SetupDiGetDeviceInstanceIdW")?;
             let copy_oem_inf: FnSetupCopyOEMInfW = 
                 *lib.get(b"SetupCopyOEMInfW")?;
             let uninstall_oem_inf: FnSetupUninstallOEMInfW = 
@@ -294,6 +309,7 @@ impl SetupApi {
                 enum_device_info,
                 get_device_registry_property,
                 destroy_device_info_list,
+                get_device_instance_id,
                 copy_oem_inf,
                 uninstall_oem_inf,
                 get_inf_driver_store_location,
@@ -342,6 +358,133 @@ impl SetupApi {
         }
     }
 
+    /// 获取设备属性（REG_MULTI_SZ，如硬件 ID / 兼容 ID 列表）
+    fn get_device_property_multi_sz(
+        &self,
+        dev_info: HDevInfo,
+        dev_info_data: &SpDevInfoData,
+        property: u32,
+    ) -> Vec<String> {
+        let mut buffer = vec![0u8; 4096];
+        let mut required_size: u32 = 0;
+        let mut reg_type: u32 = 0;
+
+        let result = unsafe {
+            (self.get_device_registry_property)(
+                dev_info,
+                dev_info_data,
+                property,
+                &mut reg_type,
+                buffer.as_mut_ptr(),
+                buffer.len() as u32,
+                &mut required_size,
+            )
+        };
+
+        if result.0 == 0 || reg_type != REG_MULTI_SZ {
+            return Vec::new();
+        }
+
+        let wide_slice = unsafe {
+            std::slice::from_raw_parts(
+                buffer.as_ptr() as *const u16,
+                required_size as usize / 2,
+            )
+        };
+        wide_slice
+            .split(|&c| c == 0)
+            .filter(|s| !s.is_empty())
+            .map(|s| OsString::from_wide(s).to_string_lossy().into_owned())
+            .collect()
+    }
+
+    /// 获取设备实例 ID
+    fn get_device_instance_id(&self, dev_info: HDevInfo, dev_info_data: &SpDevInfoData) -> String {
+        let mut buffer = vec![0u16; 512];
+        let mut required_size: u32 = 0;
+
+        let result = unsafe {
+            (self.get_device_instance_id)(
+                dev_info,
+                dev_info_data,
+                buffer.as_mut_ptr(),
+                buffer.len() as u32,
+                &mut required_size,
+            )
+        };
+
+        if result.0 == 0 {
+            return String::new();
+        }
+        wide_to_string(&buffer)
+    }
+
+    /// 枚举所有在位设备的实例 ID / 硬件 ID / 兼容 ID / 类 / 当前 INF（含尚未安装驱动的设备）
+    fn enumerate_device_ids(&self) -> Result<Vec<DeviceIds>> {
+        let mut devices = Vec::new();
+
+        let dev_info = unsafe {
+            (self.get_class_devs)(
+                null_mut(),
+                null_mut(),
+                HWND::default(),
+                DIGCF_PRESENT | DIGCF_ALLCLASSES,
+            )
+        };
+
+        if dev_info.is_null() || dev_info == (-1isize as *mut c_void) {
+            bail!("SetupDiGetClassDevsW 失败: {}", get_last_error());
+        }
+
+        let mut index = 0u32;
+        loop {
+            let mut dev_info_data = SpDevInfoData::default();
+
+            let result = unsafe {
+                (self.enum_device_info)(dev_info, index, &mut dev_info_data)
+            };
+
+            if result.0 == 0 {
+                if get_last_error() == ERROR_NO_MORE_ITEMS {
+                    break;
+                }
+                index += 1;
+                continue;
+            }
+
+            let hardware_ids =
+                self.get_device_property_multi_sz(dev_info, &dev_info_data, SPDRP_HARDWAREID);
+            if !hardware_ids.is_empty() {
+                devices.push(DeviceIds {
+                    instance_id: self.get_device_instance_id(dev_info, &dev_info_data),
+                    description: self
+                        .get_device_property_string(dev_info, &dev_info_data, SPDRP_DEVICEDESC)
+                        .unwrap_or_default(),
+                    class: self
+                        .get_device_property_string(dev_info, &dev_info_data, SPDRP_CLASS)
+                        .unwrap_or_default(),
+                    hardware_ids,
+                    compatible_ids: self.get_device_property_multi_sz(
+                        dev_info,
+                        &dev_info_data,
+                        SPDRP_COMPATIBLEIDS,
+                    ),
+                    inf: self
+                        .get_device_property_string(dev_info, &dev_info_data, SPDRP_INF_PATH)
+                        .filter(|s| !s.is_empty()),
+                });
+            }
+
+            index += 1;
+        }
+
+        unsafe {
+            let _ = (self.destroy_device_info_list)(dev_info);
+        }
+
+        Ok(devices)
+    }
+
     /// 枚举所有设备的驱动信息
     fn enumerate_drivers(&self) -> Result<Vec<DriverInfo>> {
         let mut drivers = Vec::new();
@@ -448,8 +591,11 @@ impl SetupApi {
         };
 
         if result.0 == 0 {
-            let err = get_last_error();
-            bail!("SetupCopyOEMInf 失败: 错误码 {}", err);
+            return Err(DriverApiError {
+                api: "SetupCopyOEMInf",
+                code: get_last_error(),
+            }
+            .into());
         }
 
         Ok(wide_to_string(&dest_buffer))
@@ -550,8 +696,11 @@ impl NewDevApi {
         };
 
         if result.0 == 0 {
-            let err = get_last_error();
-            bail!("DiInstallDriverW 失败: 错误码 {}", err);
+            return Err(DriverApiError {
+                api: "DiInstallDriverW",
+                code: get_last_error(),
+            }
+            .into());
         }
 
         Ok(need_reboot.0 != 0)
@@ -620,6 +769,38 @@ impl DriverManager {
         self.setup_api.enumerate_drivers()
     }
 
+    /// 枚举本机在位设备的硬件 ID / 兼容 ID（按硬件匹配驱动用，见 `driver_match`）
+    pub fn enumerate_present_devices(&self) -> Result<Vec<DeviceIds>> {
+        self.setup_api.enumerate_device_ids()
+    }
+
+    /// 只挑出与本机在位设备匹配的驱动暂存到 `staging`（目标平台取自离线系统 `offline_root`），
+    /// 之后按目录注入 `staging` 即只导入选中的驱动。枚举不到设备时返回错误，由调用方回退。
+    pub fn stage_drivers_for_present_devices(
+        &self,
+        source_dir: &Path,
+        offline_root: &Path,
+        staging: &Path,
+    ) -> Result<MatchResult> {
+        let devices = self.enumerate_present_devices()?;
+        if devices.is_empty() {
+            bail!("未枚举到任何设备");
+        }
+        let platform = driver_match::offline_platform(offline_root);
+        log::info!("[DriverManager] 驱动匹配目标平台: {:?}", platform);
+        let result = driver_match::stage_matched(source_dir, &devices, platform.as_ref(), staging)
+            .with_context(|| format!("暂存匹配驱动失败: {:?}", staging))?;
+        for m in &result.matches {
+            log::info!(
+                "[DriverManager] {} -> {} (rank 0x{:04X})",
+                m.device.description,
+                m.inf_path.display(),
+                m.rank
+            );
+        }
+        Ok(result)
+    }
+
     /// 枚举第三方 (OEM) 驱动
     pub fn enumerate_oem_drivers(&self) -> Result<Vec<DriverInfo>> {
         let all_drivers = self.setup_api.enumerate_drivers()?;
@@ -782,9 +963,17 @@ impl DriverManager {
     /// # 返回
     /// - (成功数, 失败数, 是否需要重启)
     pub fn import_drivers(&self, source_dir: &Path, force: bool) -> Result<(usize, usize, bool)> {
-        let mut success_count = 0;
-        let mut fail_count = 0;
-        let mut need_reboot = false;
+        let report = self.import_drivers_with_report(source_dir, force)?;
+        Ok((report.succeeded(), report.failed(), report.reboot_required()))
+    }
+
+    /// 导入驱动并逐个 INF 记录结果（见 [`crate::driver_report`]）
+    pub fn import_drivers_with_report(
+        &self,
+        source_dir: &Path,
+        force: bool,
+    ) -> Result<DriverImportReport> {
+        let mut report = DriverImportReport::new("online", source_dir, None);
 
         // 递归查找所有 INF 文件
         let inf_files = Self::find_inf_files(source_dir)?;
@@ -793,42 +982,58 @@ impl DriverManager {
         for inf_path in inf_files {
             log::info!("[DriverManager] 正在安装: {:?}", inf_path);
 
-            match self.install_single_driver(&inf_path, force) {
-                Ok(reboot) => {
-                    success_count += 1;
-                    need_reboot = need_reboot || reboot;
-                    log::info!("[DriverManager] 安装成功: {:?}", inf_path);
-                }
-                Err(e) => {
-                    fail_count += 1;
-                    log::error!("[DriverManager] 安装失败: {:?} - {}", inf_path, e);
-                }
+            let mut result = InfImportResult::new(&inf_path);
+            self.install_single_driver(&inf_path, force, &mut result);
+            if result.is_success() {
+                log::info!("[DriverManager] 安装成功: {:?}", inf_path);
+            } else {
+                log::error!("[DriverManager] 安装失败: {:?} - {}", inf_path, result.message);
             }
+            report.results.push(result);
         }
 
         log::info!(
             "[DriverManager] 驱动导入完成: 成功 {}, 失败 {}, 需要重启: {}",
-            success_count, fail_count, need_reboot
+            report.succeeded(), report.failed(), report.reboot_required()
         );
 
-        Ok((success_count, fail_count, need_reboot))
+        Ok(report)
     }
 
-    /// 安装单个驱动
-    fn install_single_driver(&self, inf_path: &Path, force: bool) -> Result<bool> {
+    /// 安装单个驱动，结果写入 `result`
+    fn install_single_driver(&self, inf_path: &Path, force: bool, result: &mut InfImportResult) {
         // 首先尝试使用 NewDev API
         if let Some(ref newdev) = self.newdev_api {
             match newdev.install_driver(inf_path, force) {
-                Ok(reboot) => return Ok(reboot),
+                Ok(reboot) => {
+                    result.outcome = ImportOutcome::Installed;
+                    result.reboot_required = reboot;
+                    return;
+                }
                 Err(e) => {
                     log::warn!("[DriverManager] DiInstallDriver 失败: {}, 尝试 SetupCopyOEMInf", e);
+                    result.fail(&e);
                 }
             }
         }
 
         // 回退到 SetupCopyOEMInf（只添加到驱动存储，不实际安装）
-        self.setup_api.install_inf(inf_path)?;
-        Ok(false)
+        match self.setup_api.install_inf(inf_path) {
+            Ok(dest) => {
+                result.outcome = ImportOutcome::StoreOnly;
+                result.oem_inf = Path::new(&dest)
+                    .file_name()
+                    .map(|n| n.to_string_lossy().into_owned());
+            }
+            Err(e) => {
+                // 保留 DiInstallDriver 的失败原因
+                let first = std::mem::take(&mut result.message);
+                result.fail(&e);
+                if !first.is_empty() {
+                    result.note(first);
+                }
+            }
+        }
     }
 
     /// 递归查找目录中的所有 INF 文件（非目录会返回 Err）。
@@ -877,8 +1082,17 @@ impl DriverManager {
         offline_root: &Path,
         source_dir: &Path,
     ) -> Result<(usize, usize)> {
-        let mut success_count = 0;
-        let mut fail_count = 0;
+        let report = self.import_drivers_offline_with_report(offline_root, source_dir)?;
+        Ok((report.succeeded(), report.failed()))
+    }
+
+    /// 导入驱动到离线系统并逐个 INF 记录结果（见 [`crate::driver_report`]）
+    pub fn import_drivers_offline_with_report(
+        &self,
+        offline_root: &Path,
+        source_dir: &Path,
+    ) -> Result<DriverImportReport> {
+        let mut report = DriverImportReport::new("offline", source_dir, Some(offline_root));
 
         // 目标目录
         let driver_store = offline_root
@@ -909,6 +1123,8 @@ impl DriverManager {
         );
 
         for inf_path in inf_files {
+            let mut result = InfImportResult::new(&inf_path);
+
             // 获取 INF 所在目录
             let inf_source_dir = inf_path.parent().unwrap_or(source_dir);
             let inf_name = inf_path
@@ -924,13 +1140,15 @@ impl DriverManager {
             let target_store_dir = driver_store.join(format!("{}.inf_amd64_offline{:08x}", inf_name, oem_index));
             if let Err(e) = Self::copy_dir_recursive(inf_source_dir, &target_store_dir) {
                 log::error!("[DriverManager] 复制到DriverStore失败: {:?} - {}", inf_path, e);
-                fail_count += 1;
+                result.fail(&e.context("复制到 DriverStore 失败"));
+                report.results.push(result);
                 continue;
             }
 
             // 2. 解析 INF 文件并复制 .sys 文件到 System32\drivers
             if let Err(e) = Self::process_driver_files(&target_store_dir, &system_drivers) {
                 log::warn!("[DriverManager] 处理驱动文件失败: {:?} - {}", inf_path, e);
+                result.note(format!("处理驱动文件失败: {}", e));
                 // 继续，不算失败
             }
 
@@ -941,6 +1159,7 @@ impl DriverManager {
             if source_inf.exists() {
                 if let Err(e) = std::fs::copy(&source_inf, &oem_inf_path) {
                     log::warn!("[DriverManager] 复制INF到Windows\\INF失败: {} - {}", oem_inf_name, e);
+                    result.note(format!("复制 INF 到 Windows\\INF 失败: {}", e));
                 }
             }
 
@@ -952,20 +1171,23 @@ impl DriverManager {
                 &oem_inf_name,
             ) {
                 log::warn!("[DriverManager] 注册驱动服务失败: {:?} - {}", inf_path, e);
+                result.note(format!("注册驱动服务失败: {}", e));
                 // 继续，不算失败（文件已复制，可能在启动时自动识别）
             }
 
-            success_count += 1;
+            result.outcome = ImportOutcome::Installed;
+            result.oem_inf = Some(oem_inf_name.clone());
+            report.results.push(result);
             oem_index += 1;
             log::info!("[DriverManager] 离线安装成功: {:?} -> {}", inf_path, oem_inf_name);
         }
 
         log::info!(
             "[DriverManager] 离线驱动导入完成: 成功 {}, 失败 {}",
-            success_count, fail_count
+            report.succeeded(), report.failed()
         );
 
-        Ok((success_count, fail_count))
+        Ok(report)
     }
 
     /// 获取下一个可用的 OEM INF 编号
@@ -1369,6 +1591,23 @@ pub fn export_drivers(destination: &str) -> Result<usize> {
     manager.export_drivers(Path::new(destination), true)
 }
 
+/// 导出第三方驱动为单个驱动压缩包（见 [`crate::driver_archive`]）
+///
+/// # 参数
+/// - `archive_path`: 压缩包路径（`.zip`）
+///
+/// # 返回
+/// - 成功导出的驱动数量
+pub fn export_drivers_to_archive(archive_path: &str) -> Result<usize> {
+    let manager = DriverManager::new()?;
+    let mut count = 0;
+    crate::driver_archive::export_to_archive(Path::new(archive_path), |staging| {
+        count = manager.export_drivers(staging, true)?;
+        Ok(())
+    })?;
+    Ok(count)
+}
+
 /// 从指定系统分区导出驱动（PE环境下使用）
 ///
 /// # 参数
@@ -1388,32 +1627,43 @@ pub fn export_drivers_from_system(system_partition: &str, destination: &str) ->
 /// 导入驱动
 ///
 /// # 参数
-/// - `driver_path`: 驱动目录
+/// - `driver_path`: 驱动目录或驱动压缩包（先校验再导入）
 /// - `force`: 是否强制安装
 ///
 /// # 返回
 /// - (成功数, 失败数, 是否需要重启)
 pub fn import_drivers(driver_path: &str, force: bool) -> Result<(usize, usize, bool)> {
     let manager = DriverManager::new()?;
-    manager.import_drivers(Path::new(driver_path), force)
+    let source =
+        crate::driver_archive::prepare_import_dir(Path::new(driver_path), &std::env::temp_dir())?;
+    manager.import_drivers(&source, force)
 }
 
 /// 导入驱动到离线系统（PE环境下使用）
 ///
 /// # 参数
 /// - `offline_root`: 离线系统根目录 (如 "D:\\")
-/// - `driver_path`: 驱动目录
+/// - `driver_path`: 驱动目录或驱动压缩包（先校验再导入）
 ///
 /// # 返回
 /// - (成功数, 失败数)
 pub fn import_drivers_offline(offline_root: &str, driver_path: &str) -> Result<(usize, usize)> {
     let manager = DriverManager::new()?;
+    // 压缩包解压到目标分区：PE 的系统临时目录在 X: 内存盘上，放不下
+    let source =
+        crate::driver_archive::prepare_import_dir(Path::new(driver_path), Path::new(offline_root))?;
     manager.import_drivers_offline(
         Path::new(offline_root),
-        Path::new(driver_path),
+        &source,
     )
 }
 
+/// 枚举本机在位设备的硬件 ID / 兼容 ID
+pub fn list_present_devices() -> Result<Vec<DeviceIds>> {
+    let manager = DriverManager::new()?;
+    manager.enumerate_present_devices()
+}
+
 /// 枚举所有 OEM 驱动
 pub fn list_oem_drivers() -> Result<Vec<DriverInfo>> {
     let manager = DriverManager::new()?;
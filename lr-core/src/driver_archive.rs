@@ -0,0 +1,549 @@
+//! 驱动备份压缩包：把导出的驱动目录打成单个 zip 并附带清单。
+//!
+//! 成百上千个小文件在机器 / U 盘之间拷贝又慢又容易漏。这里把驱动目录打成一个 zip
+//! （Deflate，任何解压工具都能打开），根目录的 [`MANIFEST_ENTRY`] 记录每个驱动包
+//! （提供商 / 类 / `DriverVer` / 硬件 ID，同 [`crate::driver_repo::RepoEntry`]）以及
+//! 每个文件的大小与 SHA-256。
+//!
+//! 导入前 [`prepare_import_dir`] 边解压边校验：文件缺失、清单外的文件、大小或哈希不符、
+//! 路径越界都会拒绝导入，按目录导入的调用方因此可以直接接受压缩包。
+//!
+//! 导出与导入用的临时目录每次操作独占一个（进程号 + 序号），用完即删，同时进行的多个
+//! 导出 / 导入互不干扰。临时目录不放在系统临时目录：PE 里那是 X: 内存盘，放不下真实的驱动包。
+//! 导出放在压缩包旁边，导入由调用方指定（离线导入时一般是目标分区）。
+
+use std::collections::{HashMap, HashSet};
+use std::fs::File;
+use std::io::{Read, Write};
+use std::ops::Deref;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU32, Ordering};
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use zip::write::SimpleFileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+use crate::driver_repo::{RepoEntry, RepoIndex, INDEX_FILE};
+
+/// 压缩包内的清单文件名。
+pub const MANIFEST_ENTRY: &str = "driver_manifest.json";
+
+/// 清单格式版本；读到更高版本时拒绝导入。
+const FORMAT_VERSION: u32 = 1;
+
+/// 校验失败时最多列出的问题数。
+const MAX_REPORTED_PROBLEMS: usize = 10;
+
+/// 压缩包内的一个文件。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveFile {
+    /// 压缩包内路径（`/` 分隔）。
+    pub path: String,
+    pub size: u64,
+    pub sha256: String,
+}
+
+/// 驱动压缩包清单。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ArchiveManifest {
+    pub format: u32,
+    /// 打包时间（Unix 秒）。
+    pub created: u64,
+    /// 打包时的计算机名。
+    pub computer_name: String,
+    /// 驱动包（INF 路径相对压缩包根目录）。
+    pub packages: Vec<RepoEntry>,
+    pub files: Vec<ArchiveFile>,
+}
+
+impl ArchiveManifest {
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 解压后总大小。
+    pub fn total_size(&self) -> u64 {
+        self.files.iter().map(|f| f.size).sum()
+    }
+}
+
+/// `path` 是否为驱动压缩包（`.zip` 文件且含清单）。
+pub fn is_driver_archive(path: &Path) -> bool {
+    let is_zip = path
+        .extension()
+        .map(|x| x.eq_ignore_ascii_case("zip"))
+        .unwrap_or(false);
+    is_zip && path.is_file() && read_manifest(path).is_ok()
+}
+
+/// 把 `source_dir` 打包为 `archive_path`（先写临时文件，成功后再改名）。
+///
+/// `archive_path` 位于 `source_dir` 内时，压缩包本身（及其临时文件）不会被打包进去。
+pub fn create_archive(source_dir: &Path, archive_path: &Path) -> Result<ArchiveManifest> {
+    let index = RepoIndex::build(source_dir);
+
+    let tmp_path = archive_path.with_extension("zip.tmp");
+    let source_dir = &source_dir
+        .canonicalize()
+        .with_context(|| format!("无法访问 {:?}", source_dir))?;
+    let own_files: Vec<PathBuf> = [archive_path, tmp_path.as_path()]
+        .into_iter()
+        .filter_map(canonical_file_path)
+        .collect();
+
+    let mut paths: Vec<PathBuf> = walkdir::WalkDir::new(source_dir)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| !own_files.contains(p))
+        .collect();
+    paths.sort();
+
+    let mut zip = ZipWriter::new(
+        File::create(&tmp_path).with_context(|| format!("无法创建 {:?}", tmp_path))?,
+    );
+    let options = SimpleFileOptions::default()
+        .compression_method(CompressionMethod::Deflated)
+        .large_file(true);
+
+    let mut files = Vec::new();
+    for path in &paths {
+        let Ok(rel) = path.strip_prefix(source_dir) else {
+            continue;
+        };
+        let name = rel
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/");
+        if name == INDEX_FILE || name == MANIFEST_ENTRY {
+            continue;
+        }
+        zip.start_file(name.as_str(), options)?;
+        let mut src = File::open(path).with_context(|| format!("无法读取 {:?}", path))?;
+        let (size, sha256) = copy_hashed(&mut src, Some(&mut zip))?;
+        files.push(ArchiveFile {
+            path: name,
+            size,
+            sha256,
+        });
+    }
+
+    let manifest = ArchiveManifest {
+        format: FORMAT_VERSION,
+        created: std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0),
+        computer_name: std::env::var("COMPUTERNAME").unwrap_or_default(),
+        packages: index.packages,
+        files,
+    };
+    zip.start_file(MANIFEST_ENTRY, options)?;
+    zip.write_all(manifest.to_json().as_bytes())?;
+    zip.finish()?;
+
+    if archive_path.exists() {
+        std::fs::remove_file(archive_path)?;
+    }
+    std::fs::rename(&tmp_path, archive_path)?;
+    log::info!(
+        "[DriverArchive] 已打包 {} 个驱动包 / {} 个文件 -> {:?}",
+        manifest.packages.len(),
+        manifest.files.len(),
+        archive_path
+    );
+    Ok(manifest)
+}
+
+/// 文件的规范化路径（文件本身可以还不存在，只规范化所在目录）。
+fn canonical_file_path(path: &Path) -> Option<PathBuf> {
+    let parent = match path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    Some(parent.canonicalize().ok()?.join(path.file_name()?))
+}
+
+/// 在 `root` 下新建本次操作独占的目录（`<prefix>_<进程号>_<序号>`，已存在则顺延）。
+fn unique_staging_dir(root: &Path, prefix: &str) -> Result<PathBuf> {
+    static NEXT: AtomicU32 = AtomicU32::new(0);
+    // 只给了盘符（`D:`）时补上根目录，否则拼出来的是相对该盘当前目录的路径
+    let root = match root.to_str() {
+        Some(r) if r.len() == 2 && r.ends_with(':') => PathBuf::from(format!("{}\\", r)),
+        _ => root.to_path_buf(),
+    };
+    loop {
+        let dir = root.join(format!(
+            "{}_{}_{}",
+            prefix,
+            std::process::id(),
+            NEXT.fetch_add(1, Ordering::Relaxed)
+        ));
+        match std::fs::create_dir(&dir) {
+            Ok(()) => return Ok(dir),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists => continue,
+            Err(e) => return Err(e).with_context(|| format!("无法创建临时目录 {:?}", dir)),
+        }
+    }
+}
+
+/// 先让 `export` 把驱动导出到压缩包旁边独占的临时目录，再打包为 `archive_path`；临时目录随后删除。
+pub fn export_to_archive(
+    archive_path: &Path,
+    export: impl FnOnce(&Path) -> Result<()>,
+) -> Result<ArchiveManifest> {
+    let root = match archive_path.parent() {
+        Some(p) if !p.as_os_str().is_empty() => p,
+        _ => Path::new("."),
+    };
+    let staging = unique_staging_dir(root, "LetRecovery_driver_export")?;
+    let result = export(&staging).and_then(|_| create_archive(&staging, archive_path));
+    let _ = std::fs::remove_dir_all(&staging);
+    result
+}
+
+/// 只读取清单（不校验文件）。
+pub fn read_manifest(archive_path: &Path) -> Result<ArchiveManifest> {
+    let mut zip = ZipArchive::new(File::open(archive_path)?)?;
+    read_manifest_entry(&mut zip)
+}
+
+/// 逐个文件校验压缩包（不解压）。
+pub fn verify_archive(archive_path: &Path) -> Result<ArchiveManifest> {
+    process_archive(archive_path, None)
+}
+
+/// 解压到（已存在的）`dest` 并校验；校验失败时已解出的文件留给调用方清理。
+fn extract_archive(archive_path: &Path, dest: &Path) -> Result<ArchiveManifest> {
+    process_archive(archive_path, Some(dest))
+}
+
+/// [`prepare_import_dir`] 返回的导入目录；压缩包解压出的临时目录在 drop 时删除。
+#[derive(Debug)]
+pub struct ImportDir {
+    path: PathBuf,
+    temporary: bool,
+}
+
+impl ImportDir {
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Deref for ImportDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl AsRef<Path> for ImportDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for ImportDir {
+    fn drop(&mut self) {
+        if self.temporary {
+            let _ = std::fs::remove_dir_all(&self.path);
+        }
+    }
+}
+
+/// 按目录导入的入口：`path` 是驱动压缩包时解压校验到 `staging_root` 下独占的临时目录并返回该目录
+/// （导入完成、返回值 drop 后删除；校验失败时立即删除），否则原样返回。
+///
+/// `staging_root` 须有足够空间放下解压后的驱动，PE 里不要用系统临时目录（X: 内存盘）。
+pub fn prepare_import_dir(path: &Path, staging_root: &Path) -> Result<ImportDir> {
+    if !is_driver_archive(path) {
+        return Ok(ImportDir {
+            path: path.to_path_buf(),
+            temporary: false,
+        });
+    }
+    let dest = ImportDir {
+        path: unique_staging_dir(staging_root, "LetRecovery_drivers")?,
+        temporary: true,
+    };
+    let manifest = extract_archive(path, &dest)?;
+    log::info!(
+        "[DriverArchive] 驱动压缩包校验通过：{} 个驱动包 / {} 个文件 -> {:?}",
+        manifest.packages.len(),
+        manifest.files.len(),
+        dest.path()
+    );
+    Ok(dest)
+}
+
+fn read_manifest_entry(zip: &mut ZipArchive<File>) -> Result<ArchiveManifest> {
+    let mut text = String::new();
+    zip.by_name(MANIFEST_ENTRY)
+        .context("不是驱动备份压缩包（缺少清单）")?
+        .read_to_string(&mut text)?;
+    let manifest =
+        ArchiveManifest::from_json(&text).map_err(|e| anyhow::anyhow!("清单无法解析: {}", e))?;
+    if manifest.format > FORMAT_VERSION {
+        bail!(
+            "清单版本 {} 高于支持的版本 {}",
+            manifest.format,
+            FORMAT_VERSION
+        );
+    }
+    Ok(manifest)
+}
+
+fn process_archive(archive_path: &Path, dest: Option<&Path>) -> Result<ArchiveManifest> {
+    let mut zip = ZipArchive::new(
+        File::open(archive_path).with_context(|| format!("无法打开 {:?}", archive_path))?,
+    )?;
+    let manifest = read_manifest_entry(&mut zip)?;
+    let expected: HashMap<&str, &ArchiveFile> = manifest
+        .files
+        .iter()
+        .map(|f| (f.path.as_str(), f))
+        .collect();
+
+    let mut problems = Vec::new();
+    let mut seen = HashSet::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let name = entry.name().to_string();
+        if entry.is_dir() || name == MANIFEST_ENTRY {
+            continue;
+        }
+        let Some(rel) = entry.enclosed_name() else {
+            problems.push(format!("不安全的路径: {}", name));
+            continue;
+        };
+        let Some(want) = expected.get(name.as_str()) else {
+            problems.push(format!("清单外的文件: {}", name));
+            continue;
+        };
+        let (size, sha256) = match dest {
+            Some(dest) => {
+                let out = dest.join(rel);
+                if let Some(parent) = out.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                let mut file = File::create(&out)?;
+                copy_hashed(&mut entry, Some(&mut file))
+            }
+            None => copy_hashed(&mut entry, None),
+        }
+        .with_context(|| format!("读取 {} 失败", name))?;
+        if size != want.size || sha256 != want.sha256 {
+            problems.push(format!("文件已损坏: {}", name));
+        }
+        seen.insert(name);
+    }
+    for f in &manifest.files {
+        if !seen.contains(&f.path) {
+            problems.push(format!("缺少文件: {}", f.path));
+        }
+    }
+
+    if !problems.is_empty() {
+        let mut msg = format!("驱动压缩包校验失败（{} 处问题）", problems.len());
+        for p in problems.iter().take(MAX_REPORTED_PROBLEMS) {
+            msg.push_str("\n  ");
+            msg.push_str(p);
+        }
+        bail!(msg);
+    }
+    Ok(manifest)
+}
+
+/// 复制（`writer` 为 `None` 时只读取）并计算 SHA-256，返回（字节数, 哈希）。
+fn copy_hashed(
+    reader: &mut impl Read,
+    mut writer: Option<&mut dyn Write>,
+) -> std::io::Result<(u64, String)> {
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    let mut total = 0u64;
+    loop {
+        let n = reader.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+        if let Some(w) = writer.as_mut() {
+            w.write_all(&buf[..n])?;
+        }
+        total += n as u64;
+    }
+    Ok((total, crate::hash::to_hex(&hasher.finalize())))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tmp(name: &str) -> PathBuf {
+        let p = std::env::temp_dir().join(format!("lr_drvarc_{}_{}", name, std::process::id()));
+        let _ = std::fs::remove_dir_all(&p);
+        std::fs::create_dir_all(&p).unwrap();
+        p
+    }
+
+    fn sample_repo(root: &Path) {
+        let pkg = root.join("net.inf_amd64_1");
+        std::fs::create_dir_all(pkg.join("sub")).unwrap();
+        std::fs::write(
+            pkg.join("net.inf"),
+            "[Version]\nClass=Net\nDriverVer=01/01/2024,1.0.0.0\n\
+             [Manufacturer]\nM=Models,NTamd64\n[Models.NTamd64]\nd=I, PCI\\VEN_8086&DEV_15F3\n",
+        )
+        .unwrap();
+        std::fs::write(pkg.join("net.sys"), vec![0xAB; 100_000]).unwrap();
+        std::fs::write(pkg.join("sub").join("net.dll"), "dll").unwrap();
+        std::fs::write(root.join(INDEX_FILE), "{}").unwrap();
+    }
+
+    #[test]
+    fn round_trip_with_manifest() {
+        let dir = tmp("rt");
+        let src = dir.join("src");
+        sample_repo(&src);
+        let archive = dir.join("drivers.zip");
+
+        let m = create_archive(&src, &archive).unwrap();
+        assert_eq!(m.files.len(), 3); // 索引文件不打包
+        assert_eq!(m.packages.len(), 1);
+        assert_eq!(m.packages[0].hardware_ids, vec!["PCI\\VEN_8086&DEV_15F3"]);
+        assert!(is_driver_archive(&archive));
+        assert!(!dir.join("drivers.zip.tmp").exists());
+        assert_eq!(read_manifest(&archive).unwrap(), m);
+        assert_eq!(verify_archive(&archive).unwrap(), m);
+
+        let out = prepare_import_dir(&archive, &dir).unwrap();
+        let other = prepare_import_dir(&archive, &dir).unwrap();
+        assert_ne!(out.path(), other.path());
+        assert_eq!(out.parent(), Some(dir.as_path()));
+        assert_eq!(
+            std::fs::read(out.join("net.inf_amd64_1").join("net.sys")).unwrap(),
+            vec![0xAB; 100_000]
+        );
+        assert!(out
+            .join("net.inf_amd64_1")
+            .join("sub")
+            .join("net.dll")
+            .exists());
+        // 临时目录随导入结束删除
+        let out_path = out.to_path_buf();
+        drop(out);
+        assert!(!out_path.exists());
+        assert!(other.join("net.inf_amd64_1").join("net.inf").exists());
+        drop(other);
+        // 普通目录原样返回，且不会被删除
+        assert_eq!(prepare_import_dir(&src, &dir).unwrap().path(), src);
+        assert!(src.exists());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn tampered_archive_is_rejected() {
+        let dir = tmp("bad");
+        let src = dir.join("src");
+        sample_repo(&src);
+        let good = dir.join("good.zip");
+        let manifest = create_archive(&src, &good).unwrap();
+
+        // 同一清单，但一个文件内容被改、一个文件丢失、多了一个越界路径
+        let bad = dir.join("bad.zip");
+        let mut zip = ZipWriter::new(File::create(&bad).unwrap());
+        let opts = SimpleFileOptions::default();
+        zip.start_file("net.inf_amd64_1/net.sys", opts).unwrap();
+        zip.write_all(b"tampered").unwrap();
+        zip.start_file("../evil.dll", opts).unwrap();
+        zip.write_all(b"x").unwrap();
+        zip.start_file(MANIFEST_ENTRY, opts).unwrap();
+        zip.write_all(manifest.to_json().as_bytes()).unwrap();
+        zip.finish().unwrap();
+
+        let err = verify_archive(&bad).unwrap_err().to_string();
+        assert!(
+            err.contains("文件已损坏: net.inf_amd64_1/net.sys"),
+            "{}",
+            err
+        );
+        assert!(err.contains("不安全的路径: ../evil.dll"), "{}", err);
+        assert!(err.contains("缺少文件: net.inf_amd64_1/net.inf"), "{}", err);
+
+        // 校验失败时解出的临时目录被删掉，解压目录所在的位置本身不动
+        let staging = dir.join("staging");
+        std::fs::create_dir(&staging).unwrap();
+        std::fs::write(staging.join("keep.txt"), "x").unwrap();
+        assert!(prepare_import_dir(&bad, &staging).is_err());
+        let left: Vec<_> = std::fs::read_dir(&staging)
+            .unwrap()
+            .map(|e| e.unwrap().file_name())
+            .collect();
+        assert_eq!(left, vec!["keep.txt"]);
+
+        // 没有清单的普通 zip 不当作驱动压缩包
+        let plain = dir.join("plain.zip");
+        let mut zip = ZipWriter::new(File::create(&plain).unwrap());
+        zip.start_file("a.txt", opts).unwrap();
+        zip.finish().unwrap();
+        assert!(!is_driver_archive(&plain));
+        assert_eq!(prepare_import_dir(&plain, &dir).unwrap().path(), plain);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn archive_inside_source_is_not_packed() {
+        let dir = tmp("inside");
+        sample_repo(&dir);
+        let archive = dir.join("drivers.zip");
+        // 上一次打包留下的旧压缩包与临时文件
+        std::fs::write(&archive, "old").unwrap();
+        std::fs::write(dir.join("drivers.zip.tmp"), "old").unwrap();
+
+        let m = create_archive(&dir, &archive).unwrap();
+        assert_eq!(m.files.len(), 3);
+        assert!(m.files.iter().all(|f| !f.path.starts_with("drivers.zip")));
+        assert_eq!(verify_archive(&archive).unwrap(), m);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn exports_use_separate_staging_dirs() {
+        let archive = |name: &str| tmp(name).join("drivers.zip");
+        let (a, b) = (archive("exp_a"), archive("exp_b"));
+        let mut staging_a = PathBuf::new();
+        export_to_archive(&a, |staging| {
+            staging_a = staging.to_path_buf();
+            // 导出进行中再开始另一个导出，互不影响
+            export_to_archive(&b, |inner| {
+                assert_ne!(inner, staging);
+                sample_repo(inner);
+                Ok(())
+            })?;
+            assert!(staging.exists());
+            sample_repo(staging);
+            Ok(())
+        })
+        .unwrap();
+        assert!(!staging_a.exists());
+        assert_eq!(staging_a.parent(), a.parent());
+        assert_eq!(verify_archive(&a).unwrap().files.len(), 3);
+        assert_eq!(verify_archive(&b).unwrap().files.len(), 3);
+        let _ = std::fs::remove_dir_all(a.parent().unwrap());
+        let _ = std::fs::remove_dir_all(b.parent().unwrap());
+    }
+}
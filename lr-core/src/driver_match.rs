@@ -0,0 +1,481 @@
+//! 按硬件 ID 为设备挑选驱动（仿 Windows 即插即用的驱动排名）。
+//!
+//! 离线导入原先把驱动目录里的每个 INF 都注入，大驱动包会撑大 DriverStore，还可能让
+//! 设备装上错误的驱动。这里先用 [`crate::inf`] 解析驱动库里的 INF，再对每个设备选出
+//! 排名最好的一个型号，只注入被选中的 INF（见 [`stage_packages`]）。
+//!
+//! 排名（数值越小越好，与 Windows 的 driver rank 一致）：
+//! - 设备硬件 ID 命中 INF 硬件 ID：`0x0000 + 设备 ID 序号`
+//! - 设备硬件 ID 命中 INF 兼容 ID：`0x1000 + 0x100 × INF 兼容 ID 序号 + 设备 ID 序号`
+//! - 设备兼容 ID 命中 INF 硬件 ID：`0x2000 + 设备兼容 ID 序号`
+//! - 设备兼容 ID 命中 INF 兼容 ID：`0x3000 + 0x100 × INF 兼容 ID 序号 + 设备兼容 ID 序号`
+//! - INF 没有 `CatalogFile`（必然未签名）：再加 `0x8000`
+//!
+//! 排名相同时 `DriverVer` 日期更新者优先，其次版本更高者，再其次目标修饰更具体者
+//! （`NTamd64.10.0...19041` 优于 `NTamd64`）。
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::inf::{DriverVer, InfArch, InfFile, InfModel, InfPlatform};
+
+/// 未签名驱动的排名惩罚。
+const UNSIGNED_PENALTY: u32 = 0x8000;
+
+/// 一个待匹配的设备（也是硬件清单里的一项，见 [`crate::hw_manifest`]）。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DeviceIds {
+    /// 设备实例 ID，如 `PCI\VEN_8086&DEV_15F3&...\3&11583659&0&FE`。
+    pub instance_id: String,
+    /// 设备描述（日志 / 报告用）。
+    pub description: String,
+    /// 设备类，如 `Net`。
+    pub class: String,
+    /// 硬件 ID，按设备上报顺序（越靠前越具体）。
+    pub hardware_ids: Vec<String>,
+    /// 兼容 ID，按设备上报顺序。
+    pub compatible_ids: Vec<String>,
+    /// 当前系统里正在使用的 INF（如 `oem12.inf`）；未装驱动为 `None`。
+    pub inf: Option<String>,
+}
+
+/// 驱动库里的一个 INF（已按平台筛出适用的型号）。
+#[derive(Debug, Clone)]
+pub struct DriverPackage {
+    pub inf_path: PathBuf,
+    pub provider: Option<String>,
+    pub class: Option<String>,
+    pub driver_ver: Option<DriverVer>,
+    /// 有 `CatalogFile`（对应平台）视为已签名。
+    pub signed: bool,
+    pub models: Vec<InfModel>,
+}
+
+impl DriverPackage {
+    /// `platform` 为 `None` 时收录所有型号节（不按架构 / 系统版本过滤）。
+    pub fn from_inf(inf_path: &Path, inf: &InfFile, platform: Option<&InfPlatform>) -> Self {
+        let version = inf.version();
+        let signed = match platform {
+            Some(p) => inf.catalog_file(p.arch).is_some(),
+            None => version.catalog_file.is_some() || inf.catalog_file(InfArch::Amd64).is_some(),
+        };
+        DriverPackage {
+            inf_path: inf_path.to_path_buf(),
+            provider: version.provider,
+            class: version.class,
+            driver_ver: version.driver_ver,
+            signed,
+            models: inf.models(platform),
+        }
+    }
+
+    /// 读取并解析 INF；读不了或没有适用型号时返回 `None`。
+    pub fn load(inf_path: &Path, platform: Option<&InfPlatform>) -> Option<Self> {
+        let raw = std::fs::read(inf_path).ok()?;
+        let pkg = Self::from_inf(inf_path, &InfFile::parse_bytes(&raw), platform);
+        (!pkg.models.is_empty()).then_some(pkg)
+    }
+}
+
+/// 递归扫描目录下所有 `.inf`，解析为驱动包（无适用型号的跳过）。
+pub fn scan_packages(root: &Path, platform: Option<&InfPlatform>) -> Vec<DriverPackage> {
+    let mut infs: Vec<PathBuf> = walkdir::WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .map(|e| e.into_path())
+        .filter(|p| is_inf(p) && p.is_file())
+        .collect();
+    infs.sort();
+    infs.iter()
+        .filter_map(|p| DriverPackage::load(p, platform))
+        .collect()
+}
+
+/// 从离线系统的 `Windows\System32\ntdll.dll` 读出架构（PE 头 Machine）与文件版本
+/// （`VS_FIXEDFILEINFO`），作为挑选型号节的目标平台。读不出时返回 `None`。
+pub fn offline_platform(system_root: &Path) -> Option<InfPlatform> {
+    let ntdll = system_root
+        .join("Windows")
+        .join("System32")
+        .join("ntdll.dll");
+    platform_from_pe(&std::fs::read(ntdll).ok()?)
+}
+
+fn platform_from_pe(data: &[u8]) -> Option<InfPlatform> {
+    let u16_at = |o: usize| data.get(o..o + 2).map(|b| u16::from_le_bytes([b[0], b[1]]));
+    let u32_at = |o: usize| {
+        data.get(o..o + 4)
+            .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    };
+    let pe = u32_at(0x3C)? as usize;
+    if data.get(pe..pe + 4)? != b"PE\0\0" {
+        return None;
+    }
+    let arch = match u16_at(pe + 4)? {
+        0x014C => InfArch::X86,
+        0x8664 => InfArch::Amd64,
+        0xAA64 => InfArch::Arm64,
+        0x01C4 => InfArch::Arm,
+        0x0200 => InfArch::Ia64,
+        _ => return None,
+    };
+    let sig = 0xFEEF_04BDu32.to_le_bytes();
+    let at = data.windows(4).position(|w| w == sig)?;
+    let (ms, ls) = (u32_at(at + 8)?, u32_at(at + 12)?);
+    Some(InfPlatform::new(arch, ms >> 16, ms & 0xFFFF, ls >> 16))
+}
+
+/// 设备与型号的匹配排名；不匹配返回 `None`。
+pub fn id_rank(device: &DeviceIds, model: &InfModel) -> Option<u32> {
+    let find = |ids: &[String], id: &str| {
+        ids.iter()
+            .position(|d| d.eq_ignore_ascii_case(id))
+            .map(|i| i.min(0xFF) as u32)
+    };
+    let mut best: Option<u32> = None;
+    let mut consider = |rank: u32| best = Some(best.map_or(rank, |b| b.min(rank)));
+    if let Some(i) = find(&device.hardware_ids, &model.hardware_id) {
+        consider(i);
+    }
+    if let Some(i) = find(&device.compatible_ids, &model.hardware_id) {
+        consider(0x2000 + i);
+    }
+    for (j, cid) in model.compatible_ids.iter().enumerate() {
+        let j = (j.min(0xF) as u32) * 0x100;
+        if let Some(i) = find(&device.hardware_ids, cid) {
+            consider(0x1000 + j + i);
+        }
+        if let Some(i) = find(&device.compatible_ids, cid) {
+            consider(0x3000 + j + i);
+        }
+    }
+    best
+}
+
+/// 为某设备选中的驱动。
+#[derive(Debug, Clone)]
+pub struct DriverMatch {
+    pub device: DeviceIds,
+    pub inf_path: PathBuf,
+    pub model: InfModel,
+    pub rank: u32,
+    pub driver_ver: Option<DriverVer>,
+}
+
+/// 匹配结果。
+#[derive(Debug, Clone, Default)]
+pub struct MatchResult {
+    /// 每个有驱动的设备一项，顺序同输入。
+    pub matches: Vec<DriverMatch>,
+    /// 驱动库里没有任何匹配驱动的设备。
+    pub unmatched: Vec<DeviceIds>,
+}
+
+impl MatchResult {
+    /// 被选中的 INF（去重，保持首次出现的顺序）。
+    pub fn selected_infs(&self) -> Vec<PathBuf> {
+        let mut out: Vec<PathBuf> = Vec::new();
+        for m in &self.matches {
+            if !out.contains(&m.inf_path) {
+                out.push(m.inf_path.clone());
+            }
+        }
+        out
+    }
+}
+
+/// 为每个设备挑选最佳驱动（规则见模块文档）。
+pub fn select_drivers(devices: &[DeviceIds], packages: &[DriverPackage]) -> MatchResult {
+    let mut result = MatchResult::default();
+    for device in devices {
+        let mut best: Option<(u32, &DriverPackage, &InfModel)> = None;
+        for pkg in packages {
+            for model in &pkg.models {
+                let Some(mut rank) = id_rank(device, model) else {
+                    continue;
+                };
+                if !pkg.signed {
+                    rank += UNSIGNED_PENALTY;
+                }
+                let better = match best {
+                    None => true,
+                    Some((r, p, m)) => rank
+                        .cmp(&r)
+                        .then_with(|| p.driver_ver.cmp(&pkg.driver_ver))
+                        .then_with(|| target_specificity(m).cmp(&target_specificity(model)))
+                        .is_lt(),
+                };
+                if better {
+                    best = Some((rank, pkg, model));
+                }
+            }
+        }
+        match best {
+            Some((rank, pkg, model)) => result.matches.push(DriverMatch {
+                device: device.clone(),
+                inf_path: pkg.inf_path.clone(),
+                model: model.clone(),
+                rank,
+                driver_ver: pkg.driver_ver,
+            }),
+            None => result.unmatched.push(device.clone()),
+        }
+    }
+    result
+}
+
+fn target_specificity(model: &InfModel) -> (bool, u32, u32, u32) {
+    model
+        .target
+        .as_ref()
+        .map(|t| t.specificity())
+        .unwrap_or_default()
+}
+
+/// 把选中的 INF 各自连同所在目录复制到 `staging` 下的独立子目录，供按目录注入的
+/// DISM / 离线导入只看到选中的驱动。同目录的其他 `.inf` 不复制；子目录里若另有
+/// `.inf`（属于别的驱动包）则整个跳过。返回复制的驱动包数。
+pub fn stage_packages(infs: &[PathBuf], staging: &Path) -> std::io::Result<usize> {
+    std::fs::create_dir_all(staging)?;
+    for (i, inf) in infs.iter().enumerate() {
+        let src_dir = inf.parent().unwrap_or(Path::new("."));
+        let stem = inf
+            .file_stem()
+            .map(|s| s.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        let dst = staging.join(format!("{:03}_{}", i, stem));
+        copy_package_dir(src_dir, &dst, Some(inf))?;
+    }
+    Ok(infs.len())
+}
+
+/// 扫描 `source_dir`（有驱动库索引时只解析候选包，见 [`crate::driver_repo`]）、为 `devices`
+/// 选驱动，并把选中的驱动包暂存到 `staging`（先清空）。
+/// 返回匹配结果；没有任何选中驱动时 `staging` 为空目录。
+pub fn stage_matched(
+    source_dir: &Path,
+    devices: &[DeviceIds],
+    platform: Option<&InfPlatform>,
+    staging: &Path,
+) -> std::io::Result<MatchResult> {
+    let packages = crate::driver_repo::packages_for_devices(source_dir, devices, platform);
+    let result = select_drivers(devices, &packages);
+    if staging.exists() {
+        std::fs::remove_dir_all(staging)?;
+    }
+    stage_packages(&result.selected_infs(), staging)?;
+    log::info!(
+        "[DriverMatch] {} 个驱动包 / {} 个设备：选中 {} 个 INF，{} 个设备无匹配驱动",
+        packages.len(),
+        devices.len(),
+        result.selected_infs().len(),
+        result.unmatched.len()
+    );
+    Ok(result)
+}
+
+fn copy_package_dir(src: &Path, dst: &Path, keep_inf: Option<&Path>) -> std::io::Result<()> {
+    std::fs::create_dir_all(dst)?;
+    for entry in std::fs::read_dir(src)? {
+        let path = entry?.path();
+        if path.is_dir() {
+            if !dir_has_inf(&path) {
+                copy_package_dir(&path, &dst.join(path.file_name().unwrap_or_default()), None)?;
+            }
+        } else if !is_inf(&path) || keep_inf.is_some_and(|k| k == path) {
+            std::fs::copy(&path, dst.join(path.file_name().unwrap_or_default()))?;
+        }
+    }
+    Ok(())
+}
+
+fn dir_has_inf(dir: &Path) -> bool {
+    std::fs::read_dir(dir)
+        .map(|rd| rd.flatten().any(|e| is_inf(&e.path())))
+        .unwrap_or(false)
+}
+
+fn is_inf(p: &Path) -> bool {
+    p.extension()
+        .map(|x| x.eq_ignore_ascii_case("inf"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::net_inf;
+
+    fn inf(text: &str, path: &str, platform: Option<&InfPlatform>) -> DriverPackage {
+        DriverPackage::from_inf(Path::new(path), &InfFile::parse(text), platform)
+    }
+
+    fn device() -> DeviceIds {
+        DeviceIds {
+            instance_id: "PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086&REV_03\\3&11583659&0&FE".into(),
+            description: "Ethernet".into(),
+            class: "Net".into(),
+            hardware_ids: vec![
+                "PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086&REV_03".into(),
+                "PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086".into(),
+                "PCI\\VEN_8086&DEV_15F3".into(),
+            ],
+            compatible_ids: vec!["PCI\\VEN_8086&CC_020000".into(), "PCI\\CC_0200".into()],
+            inf: None,
+        }
+    }
+
+    #[test]
+    fn rank_follows_windows_categories() {
+        let model = |hwid: &str, compat: &[&str]| InfModel {
+            description: String::new(),
+            install_section: "x".into(),
+            hardware_id: hwid.into(),
+            compatible_ids: compat.iter().map(|s| s.to_string()).collect(),
+            models_section: String::new(),
+            target: None,
+        };
+        let d = device();
+        assert_eq!(id_rank(&d, &model("pci\\ven_8086&dev_15f3", &[])), Some(2));
+        assert_eq!(
+            id_rank(&d, &model("ACPI\\X", &["PCI\\VEN_8086&DEV_15F3"])),
+            Some(0x1002)
+        );
+        assert_eq!(id_rank(&d, &model("PCI\\CC_0200", &[])), Some(0x2001));
+        assert_eq!(
+            id_rank(&d, &model("ACPI\\X", &["ACPI\\Y", "PCI\\CC_0200"])),
+            Some(0x3101)
+        );
+        assert_eq!(id_rank(&d, &model("USB\\VID_1234", &[])), None);
+    }
+
+    #[test]
+    fn best_rank_then_newest_driver_wins() {
+        let p = InfPlatform::new(InfArch::Amd64, 10, 0, 19045);
+        let generic = inf(
+            &net_inf("01/01/2024,2.0.0.0", "d=I, PCI\\VEN_8086&DEV_15F3"),
+            "generic.inf",
+            Some(&p),
+        );
+        let exact_old = inf(
+            &net_inf(
+                "01/01/2020,1.0.0.0",
+                "d=I, PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086",
+            ),
+            "old.inf",
+            Some(&p),
+        );
+        let exact_new = inf(
+            &net_inf(
+                "06/01/2023,1.5.0.0",
+                "d=I, PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086",
+            ),
+            "new.inf",
+            Some(&p),
+        );
+        let r = select_drivers(&[device()], &[generic, exact_old, exact_new]);
+        assert_eq!(r.matches.len(), 1);
+        assert_eq!(r.matches[0].inf_path, Path::new("new.inf"));
+        assert_eq!(r.matches[0].rank, 1);
+        assert!(r.unmatched.is_empty());
+    }
+
+    #[test]
+    fn unsigned_and_wrong_arch_lose() {
+        let p = InfPlatform::new(InfArch::Amd64, 10, 0, 19045);
+        let unsigned = inf(
+            "[Version]\nDriverVer=01/01/2025\n[Manufacturer]\nM=Models,NTamd64\n\
+             [Models.NTamd64]\nd=I, PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086&REV_03\n",
+            "unsigned.inf",
+            Some(&p),
+        );
+        assert!(!unsigned.signed);
+        let x86_only = inf(
+            "[Version]\nCatalogFile=a.cat\n[Manufacturer]\nM=Models,NTx86\n\
+             [Models.NTx86]\nd=I, PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086&REV_03\n",
+            "x86.inf",
+            Some(&p),
+        );
+        assert!(x86_only.models.is_empty());
+        let signed = inf(
+            &net_inf("01/01/2019", "d=I, PCI\\CC_0200"),
+            "class.inf",
+            Some(&p),
+        );
+        let mut other = device();
+        other.hardware_ids = vec!["USB\\VID_0BDA&PID_8153".into()];
+        other.compatible_ids.clear();
+        let r = select_drivers(&[device(), other], &[unsigned, x86_only, signed]);
+        assert_eq!(r.matches[0].inf_path, Path::new("class.inf"));
+        assert_eq!(r.matches[0].rank, 0x2001);
+        assert_eq!(r.unmatched.len(), 1);
+        assert_eq!(r.selected_infs(), vec![PathBuf::from("class.inf")]);
+    }
+
+    #[test]
+    fn more_specific_target_breaks_tie() {
+        let text = "[Version]\nCatalogFile=x.cat\nDriverVer=01/01/2024\n\
+                    [Manufacturer]\nM=Models,NTamd64,NTamd64.10.0...19041\n\
+                    [Models.NTamd64]\nlegacy=Legacy, PCI\\VEN_8086&DEV_15F3\n\
+                    [Models.NTamd64.10.0...19041]\nmodern=Modern, PCI\\VEN_8086&DEV_15F3\n";
+        let all = inf(text, "a.inf", None);
+        assert_eq!(all.models.len(), 2);
+        let r = select_drivers(&[device()], &[all]);
+        assert_eq!(r.matches[0].model.install_section, "Modern");
+    }
+
+    #[test]
+    fn platform_from_pe_header_and_version_resource() {
+        let mut pe = vec![0u8; 0x200];
+        pe[0x3C] = 0x80;
+        pe[0x80..0x84].copy_from_slice(b"PE\0\0");
+        pe[0x84..0x86].copy_from_slice(&0x8664u16.to_le_bytes());
+        pe[0x100..0x104].copy_from_slice(&0xFEEF_04BDu32.to_le_bytes());
+        pe[0x108..0x10C].copy_from_slice(&(10u32 << 16).to_le_bytes());
+        pe[0x10C..0x110].copy_from_slice(&((19041u32 << 16) | 3636).to_le_bytes());
+        assert_eq!(
+            platform_from_pe(&pe),
+            Some(InfPlatform::new(InfArch::Amd64, 10, 0, 19041))
+        );
+        pe[0x84..0x86].copy_from_slice(&0x1234u16.to_le_bytes());
+        assert_eq!(platform_from_pe(&pe), None);
+        assert_eq!(platform_from_pe(b"MZ"), None);
+    }
+
+    #[test]
+    fn stage_copies_only_selected_inf() {
+        let root = std::env::temp_dir().join(format!("lr_drvmatch_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let pkg = root.join("src").join("net");
+        std::fs::create_dir_all(pkg.join("sub")).unwrap();
+        std::fs::create_dir_all(pkg.join("other")).unwrap();
+        std::fs::write(
+            pkg.join("net.inf"),
+            net_inf("01/01/2024", "d=I, PCI\\VEN_8086&DEV_15F3"),
+        )
+        .unwrap();
+        std::fs::write(
+            pkg.join("wifi.inf"),
+            net_inf("01/01/2024", "d=I, PCI\\VEN_8086&DEV_2723"),
+        )
+        .unwrap();
+        std::fs::write(pkg.join("net.sys"), "x").unwrap();
+        std::fs::write(pkg.join("sub").join("net.dll"), "x").unwrap();
+        std::fs::write(pkg.join("other").join("o.inf"), "x").unwrap();
+
+        let staging = root.join("staging");
+        let p = InfPlatform::new(InfArch::Amd64, 10, 0, 19045);
+        let r = stage_matched(&root.join("src"), &[device()], Some(&p), &staging).unwrap();
+        assert_eq!(r.selected_infs(), vec![pkg.join("net.inf")]);
+        let out = staging.join("000_net");
+        assert!(out.join("net.inf").exists());
+        assert!(out.join("net.sys").exists());
+        assert!(out.join("sub").join("net.dll").exists());
+        assert!(!out.join("wifi.inf").exists());
+        assert!(!out.join("other").exists());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
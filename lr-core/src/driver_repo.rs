@@ -0,0 +1,606 @@
+//! 驱动库索引：给「只增不减」的共享驱动目录建 JSON 索引并去重。
+//!
+//! 索引 [`INDEX_FILE`] 放在驱动库根目录，逐个 INF 记录提供商、类、`DriverVer`、
+//! 适用架构与支持的硬件 ID（型号的硬件 ID + 兼容 ID）。刷新时只重新解析大小或
+//! 修改时间变化的 INF，其余沿用旧记录。
+//!
+//! 每个 INF 有一个状态（[`EntryStatus`]）：
+//! - 内容哈希（INF + 其 `CatalogFile`）与更早的包相同：`Duplicate`
+//! - 同一驱动包（INF 文件名 + 提供商 + 类 + 架构相同）有更新的 `DriverVer`，且新包支持的硬件 ID
+//!   涵盖旧包的全部硬件 ID：`Superseded`（新版删掉了某些型号时旧包仍是这些设备唯一的驱动）
+//! - 其余为 `Active`
+//!
+//! 按硬件选驱动（[`crate::driver_match`]）有索引时只解析可能命中设备的有效包，
+//! 不再整库重新扫描；[`RepoIndex::prune`] 可删除重复 / 过时的包。
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::driver_match::{scan_packages, DeviceIds, DriverPackage};
+use crate::inf::{DriverVer, InfArch, InfFile, InfPlatform};
+
+/// 索引文件名（位于驱动库根目录）。
+pub const INDEX_FILE: &str = "driver_index.json";
+
+/// 索引格式版本；读到不同版本时整库重建。
+const INDEX_VERSION: u32 = 1;
+
+/// 索引项状态。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EntryStatus {
+    #[default]
+    Active,
+    /// 与 `of`（相对路径）内容完全相同。
+    Duplicate { of: String },
+    /// 被同一驱动包的更新版本 `by`（相对路径）取代。
+    Superseded { by: String },
+}
+
+/// 索引中的一个 INF。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoEntry {
+    /// 相对驱动库根目录的路径（`/` 分隔）。
+    pub inf: String,
+    pub provider: String,
+    pub class: String,
+    /// `mm/dd/yyyy,a.b.c.d`；INF 未声明时为空。
+    pub driver_ver: String,
+    /// 适用架构（`x86` / `amd64` / ...）。
+    pub archs: Vec<String>,
+    /// 支持的硬件 ID 与兼容 ID（大写、排序、去重）。
+    pub hardware_ids: Vec<String>,
+    /// INF 与其 `CatalogFile` 的 SHA-256。
+    pub hash: String,
+    /// INF 文件大小与修改时间（Unix 秒），刷新时据此判断是否需要重新解析。
+    pub size: u64,
+    pub modified: u64,
+    pub status: EntryStatus,
+}
+
+impl RepoEntry {
+    pub fn is_active(&self) -> bool {
+        self.status == EntryStatus::Active
+    }
+
+    pub fn driver_ver(&self) -> Option<DriverVer> {
+        DriverVer::parse(&self.driver_ver)
+    }
+
+    /// 绝对路径。
+    pub fn path(&self, root: &Path) -> PathBuf {
+        self.inf
+            .split('/')
+            .fold(root.to_path_buf(), |p, c| p.join(c))
+    }
+
+    /// 浏览时的关键字过滤：路径、提供商、类或任一硬件 ID 包含 `query`（不区分大小写）。
+    pub fn matches_query(&self, query: &str) -> bool {
+        let q = query.trim().to_uppercase();
+        q.is_empty()
+            || [&self.inf, &self.provider, &self.class]
+                .iter()
+                .any(|s| s.to_uppercase().contains(&q))
+            || self.hardware_ids.iter().any(|id| id.contains(&q))
+    }
+
+    /// 本包支持的硬件 ID 是否涵盖 `other` 的全部硬件 ID。
+    fn covers(&self, other: &RepoEntry) -> bool {
+        other
+            .hardware_ids
+            .iter()
+            .all(|id| self.hardware_ids.binary_search(id).is_ok())
+    }
+
+    /// 同一驱动包的判定键。
+    fn package_key(&self) -> (String, String, String, Vec<String>) {
+        let name = self.inf.rsplit('/').next().unwrap_or_default();
+        (
+            name.to_lowercase(),
+            self.provider.to_lowercase(),
+            self.class.to_lowercase(),
+            self.archs.clone(),
+        )
+    }
+}
+
+/// 各状态的计数。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RepoSummary {
+    pub active: usize,
+    pub duplicate: usize,
+    pub superseded: usize,
+}
+
+/// 驱动库索引。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RepoIndex {
+    pub version: u32,
+    /// 生成时间（Unix 秒）。
+    pub generated: u64,
+    /// 按相对路径排序。
+    pub packages: Vec<RepoEntry>,
+}
+
+impl RepoIndex {
+    /// 整库扫描建立索引（不写盘）。
+    pub fn build(root: &Path) -> Self {
+        Self::refresh(root, None)
+    }
+
+    /// 以 `previous` 为基础刷新：大小与修改时间未变的 INF 沿用旧记录，
+    /// 新增 / 变化的重新解析，已删除的移除；最后重新判定各项状态。
+    pub fn refresh(root: &Path, previous: Option<&RepoIndex>) -> Self {
+        let old: HashMap<&str, &RepoEntry> = previous
+            .filter(|p| p.version == INDEX_VERSION)
+            .map(|p| p.packages.iter().map(|e| (e.inf.as_str(), e)).collect())
+            .unwrap_or_default();
+        let mut reparsed = 0usize;
+        let mut packages: Vec<RepoEntry> = list_infs(root)
+            .into_iter()
+            .filter_map(|path| {
+                let rel = relative_path(root, &path)?;
+                let meta = std::fs::metadata(&path).ok()?;
+                let (size, modified) = (meta.len(), mtime_secs(&meta));
+                if let Some(e) = old
+                    .get(rel.as_str())
+                    .filter(|e| e.size == size && e.modified == modified)
+                {
+                    return Some((*e).clone());
+                }
+                reparsed += 1;
+                index_inf(&path, rel, size, modified)
+            })
+            .collect();
+        packages.sort_by(|a, b| a.inf.cmp(&b.inf));
+        classify(&mut packages);
+        log::info!(
+            "[DriverRepo] {:?}: {} 个 INF（重新解析 {} 个）",
+            root,
+            packages.len(),
+            reparsed
+        );
+        RepoIndex {
+            version: INDEX_VERSION,
+            generated: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            packages,
+        }
+    }
+
+    /// 读取已有索引、增量刷新并写回；写回失败（如只读介质）只记警告。
+    pub fn update(root: &Path) -> Self {
+        let index = Self::refresh(root, Self::load(root).as_ref());
+        if let Err(e) = index.save(root) {
+            log::warn!("[DriverRepo] 写入驱动库索引失败 {:?}: {}", root, e);
+        }
+        index
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 写入 `root`\[`INDEX_FILE`]，返回文件路径。
+    pub fn save(&self, root: &Path) -> std::io::Result<PathBuf> {
+        let path = root.join(INDEX_FILE);
+        std::fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+
+    /// 读取 `root`\[`INDEX_FILE`]；不存在或无法解析时返回 `None`（解析失败记警告）。
+    pub fn load(root: &Path) -> Option<Self> {
+        let path = root.join(INDEX_FILE);
+        let text = std::fs::read_to_string(&path).ok()?;
+        match Self::from_json(&text) {
+            Ok(i) => Some(i),
+            Err(e) => {
+                log::warn!("[DriverRepo] 驱动库索引解析失败 {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    pub fn summary(&self) -> RepoSummary {
+        let mut s = RepoSummary::default();
+        for e in &self.packages {
+            match e.status {
+                EntryStatus::Active => s.active += 1,
+                EntryStatus::Duplicate { .. } => s.duplicate += 1,
+                EntryStatus::Superseded { .. } => s.superseded += 1,
+            }
+        }
+        s
+    }
+
+    pub fn active(&self) -> impl Iterator<Item = &RepoEntry> {
+        self.packages.iter().filter(|e| e.is_active())
+    }
+
+    /// 有效包中支持任一设备硬件 ID / 兼容 ID 的项。
+    pub fn candidates(&self, devices: &[DeviceIds]) -> Vec<&RepoEntry> {
+        let wanted: HashSet<String> = devices
+            .iter()
+            .flat_map(|d| d.hardware_ids.iter().chain(&d.compatible_ids))
+            .map(|id| id.to_uppercase())
+            .collect();
+        self.active()
+            .filter(|e| e.hardware_ids.iter().any(|id| wanted.contains(id)))
+            .collect()
+    }
+
+    /// 解析候选包供 [`crate::driver_match::select_drivers`] 使用。
+    pub fn packages_for(
+        &self,
+        root: &Path,
+        devices: &[DeviceIds],
+        platform: Option<&InfPlatform>,
+    ) -> Vec<DriverPackage> {
+        self.candidates(devices)
+            .into_iter()
+            .filter_map(|e| DriverPackage::load(&e.path(root), platform))
+            .collect()
+    }
+
+    /// 删除重复 / 过时的包，返回删除的 INF 数，并写回刷新后的索引。
+    ///
+    /// 所在目录（不是驱动库根目录）与其他保留的 INF 互不包含时整个目录删除，
+    /// 否则只删除该 INF，以免误删别的驱动包的文件。
+    pub fn prune(&mut self, root: &Path) -> std::io::Result<usize> {
+        let keep_dirs: Vec<PathBuf> = self
+            .active()
+            .filter_map(|e| e.path(root).parent().map(Path::to_path_buf))
+            .collect();
+        let mut removed = 0;
+        for e in self.packages.iter().filter(|e| !e.is_active()) {
+            let path = e.path(root);
+            let dir = path.parent().unwrap_or(root);
+            let shared = dir == root
+                || keep_dirs
+                    .iter()
+                    .any(|k| k.starts_with(dir) || dir.starts_with(k));
+            if !shared && dir.exists() {
+                std::fs::remove_dir_all(dir)?;
+            } else if path.exists() {
+                std::fs::remove_file(&path)?;
+            } else {
+                continue;
+            }
+            log::info!("[DriverRepo] 已删除 {} ({:?})", e.inf, e.status);
+            removed += 1;
+        }
+        *self = Self::refresh(root, Some(self));
+        self.save(root)?;
+        Ok(removed)
+    }
+}
+
+/// 为设备准备候选驱动包：驱动库有索引时增量刷新索引并只解析候选包，
+/// 否则整库扫描。
+pub fn packages_for_devices(
+    root: &Path,
+    devices: &[DeviceIds],
+    platform: Option<&InfPlatform>,
+) -> Vec<DriverPackage> {
+    if root.join(INDEX_FILE).is_file() {
+        RepoIndex::update(root).packages_for(root, devices, platform)
+    } else {
+        scan_packages(root, platform)
+    }
+}
+
+/// 驱动库中应导入的 INF：有索引时只取有效包，否则为 `None`（按目录整体导入）。
+pub fn active_infs(root: &Path) -> Option<Vec<PathBuf>> {
+    if !root.join(INDEX_FILE).is_file() {
+        return None;
+    }
+    Some(
+        RepoIndex::update(root)
+            .active()
+            .map(|e| e.path(root))
+            .collect(),
+    )
+}
+
+fn list_infs(root: &Path) -> Vec<PathBuf> {
+    walkdir::WalkDir::new(root)
+        .follow_links(true)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .map(|x| x.eq_ignore_ascii_case("inf"))
+                .unwrap_or(false)
+        })
+        .collect()
+}
+
+fn relative_path(root: &Path, path: &Path) -> Option<String> {
+    let rel = path.strip_prefix(root).ok()?;
+    Some(
+        rel.components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+    )
+}
+
+fn mtime_secs(meta: &std::fs::Metadata) -> u64 {
+    meta.modified()
+        .ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+fn index_inf(path: &Path, rel: String, size: u64, modified: u64) -> Option<RepoEntry> {
+    let raw = std::fs::read(path).ok()?;
+    let inf = InfFile::parse_bytes(&raw);
+    let dir = path.parent().unwrap_or(Path::new("."));
+    Some(entry_from_inf(&inf, &raw, dir, rel, size, modified))
+}
+
+fn entry_from_inf(
+    inf: &InfFile,
+    raw: &[u8],
+    dir: &Path,
+    rel: String,
+    size: u64,
+    modified: u64,
+) -> RepoEntry {
+    let version = inf.version();
+
+    let mut archs: Vec<InfArch> = Vec::new();
+    for m in inf.manufacturers() {
+        if m.decorations.is_empty() {
+            archs.push(InfArch::X86);
+        }
+        for d in &m.decorations {
+            match d.arch {
+                Some(a) => archs.push(a),
+                None => archs.extend(InfArch::ALL),
+            }
+        }
+    }
+    let mut archs: Vec<String> = archs.iter().map(|a| a.as_str().to_string()).collect();
+    archs.sort();
+    archs.dedup();
+
+    let mut hardware_ids: Vec<String> = inf
+        .models(None)
+        .into_iter()
+        .flat_map(|m| std::iter::once(m.hardware_id).chain(m.compatible_ids))
+        .map(|id| id.to_uppercase())
+        .collect();
+    hardware_ids.sort();
+    hardware_ids.dedup();
+
+    // 内容哈希：INF 本身 + 各 CatalogFile（按名称排序；目录里不存在的跳过）
+    let catalogs: BTreeMap<String, ()> = inf
+        .lines("Version")
+        .iter()
+        .filter(|l| {
+            l.key
+                .as_deref()
+                .is_some_and(|k| k.to_ascii_lowercase().starts_with("catalogfile"))
+        })
+        .map(|l| (inf.expand(&l.raw_value).to_lowercase(), ()))
+        .collect();
+    let mut hasher = Sha256::new();
+    hasher.update(raw);
+    for name in catalogs.keys() {
+        if let Ok(data) = std::fs::read(dir.join(name)) {
+            hasher.update(name.as_bytes());
+            hasher.update(&data);
+        }
+    }
+    let hash = crate::hash::to_hex(&hasher.finalize());
+
+    RepoEntry {
+        inf: rel,
+        provider: version.provider.unwrap_or_default(),
+        class: version.class.unwrap_or_default(),
+        driver_ver: version
+            .driver_ver
+            .map(|v| v.to_string())
+            .unwrap_or_default(),
+        archs,
+        hardware_ids,
+        hash,
+        size,
+        modified,
+        status: EntryStatus::Active,
+    }
+}
+
+/// 重新判定状态（`packages` 已按路径排序）：先按内容哈希标重复，
+/// 再在剩余项里按驱动包分组，`DriverVer` 更新且硬件 ID 涵盖本包的同组包取代本包
+/// （有多个时取最新者，同为最新取路径靠前者）。
+fn classify(packages: &mut [RepoEntry]) {
+    let mut first_by_hash: HashMap<String, String> = HashMap::new();
+    for e in packages.iter_mut() {
+        e.status = match first_by_hash.get(&e.hash) {
+            Some(of) => EntryStatus::Duplicate { of: of.clone() },
+            None => {
+                first_by_hash.insert(e.hash.clone(), e.inf.clone());
+                EntryStatus::Active
+            }
+        };
+    }
+
+    let mut groups: HashMap<_, Vec<usize>> = HashMap::new();
+    for (i, e) in packages.iter().enumerate().filter(|(_, e)| e.is_active()) {
+        groups.entry(e.package_key()).or_default().push(i);
+    }
+    let mut superseded = Vec::new();
+    for members in groups.values() {
+        for &i in members {
+            let old = &packages[i];
+            let ver = old.driver_ver();
+            let mut by: Option<&RepoEntry> = None;
+            for new in members.iter().map(|&j| &packages[j]) {
+                if new.driver_ver() > ver
+                    && new.covers(old)
+                    && by.is_none_or(|b| new.driver_ver() > b.driver_ver())
+                {
+                    by = Some(new);
+                }
+            }
+            if let Some(by) = by {
+                superseded.push((i, by.inf.clone()));
+            }
+        }
+    }
+    for (i, by) in superseded {
+        packages[i].status = EntryStatus::Superseded { by };
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 带兼容 ID `PCI\CC_0200` 的单型号网卡 INF。
+    fn net_inf(date: &str, hwid: &str) -> String {
+        crate::test_util::net_inf(date, &format!("d=I, {}, PCI\\CC_0200", hwid))
+    }
+
+    fn write(path: &Path, text: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, text).unwrap();
+    }
+
+    fn status_of<'a>(index: &'a RepoIndex, inf: &str) -> &'a EntryStatus {
+        &index.packages.iter().find(|e| e.inf == inf).unwrap().status
+    }
+
+    #[test]
+    fn entry_records_metadata_and_ids() {
+        let inf = InfFile::parse(&net_inf("03/15/2024,12.19.1.37", "pci\\ven_8086&dev_15f3"));
+        let e = entry_from_inf(&inf, b"x", Path::new("."), "a/net.inf".into(), 1, 2);
+        assert_eq!(e.provider, "Intel");
+        assert_eq!(e.class, "Net");
+        assert_eq!(e.driver_ver, "03/15/2024,12.19.1.37");
+        assert_eq!(e.archs, vec!["amd64", "x86"]);
+        assert_eq!(
+            e.hardware_ids,
+            vec!["PCI\\CC_0200", "PCI\\VEN_8086&DEV_15F3"]
+        );
+        assert!(e.matches_query("dev_15f3"));
+        assert!(e.matches_query("intel"));
+        assert!(!e.matches_query("nvidia"));
+        assert_eq!(
+            e.path(Path::new("R")),
+            Path::new("R").join("a").join("net.inf")
+        );
+    }
+
+    #[test]
+    fn classify_marks_duplicates_and_superseded() {
+        let root = std::env::temp_dir().join(format!("lr_drvrepo_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let old = net_inf("01/01/2023,1.0.0.0", "PCI\\VEN_8086&DEV_15F3");
+        let new = net_inf("01/01/2024,2.0.0.0", "PCI\\VEN_8086&DEV_15F3");
+        write(&root.join("a").join("net.inf"), &old);
+        write(&root.join("a").join("net.cat"), "cat-a");
+        write(&root.join("b").join("net.inf"), &old);
+        write(&root.join("b").join("net.cat"), "cat-a");
+        // 同一 INF、不同 catalog：不是重复，但被 c 取代
+        write(&root.join("d").join("net.inf"), &old);
+        write(&root.join("d").join("net.cat"), "cat-d");
+        write(&root.join("c").join("net.inf"), &new);
+        write(&root.join("e").join("wifi.inf"), &old);
+
+        let index = RepoIndex::build(&root);
+        assert_eq!(index.packages.len(), 5);
+        assert_eq!(
+            status_of(&index, "a/net.inf"),
+            &EntryStatus::Superseded {
+                by: "c/net.inf".into()
+            }
+        );
+        assert_eq!(
+            status_of(&index, "b/net.inf"),
+            &EntryStatus::Duplicate {
+                of: "a/net.inf".into()
+            }
+        );
+        assert_eq!(status_of(&index, "c/net.inf"), &EntryStatus::Active);
+        assert!(matches!(
+            status_of(&index, "d/net.inf"),
+            EntryStatus::Superseded { .. }
+        ));
+        assert_eq!(status_of(&index, "e/wifi.inf"), &EntryStatus::Active);
+        assert_eq!(
+            index.summary(),
+            RepoSummary {
+                active: 2,
+                duplicate: 1,
+                superseded: 2
+            }
+        );
+
+        // 往返 + 增量刷新沿用未变化的记录
+        index.save(&root).unwrap();
+        let loaded = RepoIndex::load(&root).unwrap();
+        assert_eq!(loaded.packages, index.packages);
+        let refreshed = RepoIndex::refresh(&root, Some(&loaded));
+        assert_eq!(refreshed.packages, index.packages);
+
+        let dev = DeviceIds {
+            hardware_ids: vec!["PCI\\VEN_8086&DEV_15F3".into()],
+            ..Default::default()
+        };
+        let c: Vec<&str> = index
+            .candidates(&[dev])
+            .iter()
+            .map(|e| e.inf.as_str())
+            .collect();
+        assert_eq!(c, vec!["c/net.inf", "e/wifi.inf"]);
+
+        let mut index = index;
+        assert_eq!(index.prune(&root).unwrap(), 3);
+        assert!(!root.join("a").exists() && !root.join("b").exists());
+        assert!(root.join("c").join("net.inf").exists());
+        assert_eq!(index.packages.len(), 2);
+        assert_eq!(index.summary().active, 2);
+        let _ = std::fs::remove_dir_all(&root);
+    }
+
+    #[test]
+    fn newer_package_must_cover_old_hardware_ids() {
+        let entry = |path: &str, date: &str, hwid: &str| {
+            let text = net_inf(date, hwid);
+            let inf = InfFile::parse(&text);
+            entry_from_inf(&inf, text.as_bytes(), Path::new("."), path.into(), 1, 2)
+        };
+        let mut packages = vec![
+            entry("a/net.inf", "01/01/2023,1.0.0.0", "PCI\\VEN_8086&DEV_15F3"),
+            // 新版去掉了 DEV_15F3，不能取代 a
+            entry("b/net.inf", "01/01/2024,2.0.0.0", "PCI\\VEN_8086&DEV_1A1C"),
+            entry("c/net.inf", "01/01/2022,0.9.0.0", "PCI\\VEN_8086&DEV_1A1C"),
+        ];
+        classify(&mut packages);
+        assert_eq!(packages[0].status, EntryStatus::Active);
+        assert_eq!(packages[1].status, EntryStatus::Active);
+        assert_eq!(
+            packages[2].status,
+            EntryStatus::Superseded {
+                by: "b/net.inf".into()
+            }
+        );
+    }
+}
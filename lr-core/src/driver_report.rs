@@ -0,0 +1,516 @@
+//! 驱动导入报告：逐个 INF 记录导入结果，部署后留存在目标系统与数据分区。
+//!
+//! [`crate::driver::DriverManager`] 的导入只返回成功 / 失败计数，重装后没人知道哪个驱动
+//! 失败、为什么失败。这里为每个 INF 记录结果、错误码与说明、匹配到的设备以及是否需要
+//! 重启，以 JSON（[`REPORT_JSON`]）和可读文本（[`REPORT_TEXT`]）保存。
+//!
+//! DISM 按目录注入时拿不到逐个 INF 的结果，[`report_from_driver_store`] 在注入后对照离线
+//! 系统的 `DriverStore\FileRepository` 与 `Windows\INF\oem*.inf` 逐个核实。
+//! 新系统首次启动后，正常系统端用 [`pending_report`] 取出尚未查看的报告展示。
+
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::driver_match::{id_rank, DeviceIds, DriverPackage};
+use crate::inf::{InfFile, InfPlatform};
+
+/// JSON 报告文件名。
+pub const REPORT_JSON: &str = "driver_import_report.json";
+/// 文本报告文件名。
+pub const REPORT_TEXT: &str = "driver_import_report.txt";
+/// 报告已查看标记（新报告写入时删除）。
+const SEEN_MARKER: &str = "driver_import_report.seen";
+
+/// 驱动 API 失败（携带 Win32 / SetupAPI 错误码，报告据此填写错误码）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverApiError {
+    pub api: &'static str,
+    pub code: u32,
+}
+
+impl fmt::Display for DriverApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} 失败: 错误码 {}", self.api, self.code)
+    }
+}
+
+impl std::error::Error for DriverApiError {}
+
+/// 从错误链中取错误码：[`DriverApiError`] 或带系统错误码的 `io::Error`。
+pub fn error_code_of(err: &anyhow::Error) -> Option<u32> {
+    err.chain().find_map(|e| {
+        e.downcast_ref::<DriverApiError>()
+            .map(|a| a.code)
+            .or_else(|| {
+                e.downcast_ref::<std::io::Error>()
+                    .and_then(|io| io.raw_os_error())
+                    .map(|c| c as u32)
+            })
+    })
+}
+
+/// 常见驱动安装错误码的说明。
+pub fn describe_error(code: u32) -> Option<&'static str> {
+    Some(match code {
+        2 => "找不到文件",
+        3 => "找不到路径",
+        5 => "拒绝访问（需要管理员权限）",
+        32 => "文件被占用",
+        112 => "磁盘空间不足",
+        0xE000_0100 => "INF 格式不正确",
+        0xE000_0101 => "INF 中找不到所需的节",
+        0xE000_022F => "INF 没有对应的签名目录文件 (CatalogFile)",
+        0xE000_0235 => "32 位进程无法安装 64 位驱动",
+        0xE000_0247 => "无法加入驱动存储",
+        0x800B_0100 => "文件没有数字签名",
+        0x800B_0109 => "签名证书不受信任",
+        _ => return None,
+    })
+}
+
+/// 单个 INF 的导入结果。
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ImportOutcome {
+    /// 已安装（在线）/ 已加入离线系统的驱动存储。
+    #[default]
+    Installed,
+    /// 在线安装失败，退而只加入了驱动存储（设备插入时才会用上）。
+    StoreOnly,
+    Failed,
+    /// 不是设备驱动 INF（没有 `[Manufacturer]`），导入工具不处理。
+    Skipped,
+}
+
+impl ImportOutcome {
+    pub fn label(self) -> &'static str {
+        match self {
+            ImportOutcome::Installed => "成功",
+            ImportOutcome::StoreOnly => "仅加入驱动存储",
+            ImportOutcome::Failed => "失败",
+            ImportOutcome::Skipped => "跳过",
+        }
+    }
+}
+
+/// 报告中的一个 INF。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct InfImportResult {
+    /// 源 INF 路径。
+    pub inf: String,
+    /// 在目标系统中的名称（如 `oem12.inf`）。
+    pub oem_inf: Option<String>,
+    pub provider: String,
+    pub class: String,
+    pub driver_ver: String,
+    pub outcome: ImportOutcome,
+    pub error_code: Option<u32>,
+    /// 失败原因或附加说明（如服务注册失败）。
+    pub message: String,
+    /// 该 INF 能驱动的设备（`描述 [硬件 ID]`）。
+    pub matched_devices: Vec<String>,
+    pub reboot_required: bool,
+}
+
+impl InfImportResult {
+    /// 读取 INF 的 `[Version]` 填写提供商 / 类 / 版本（读不了时留空）。
+    pub fn new(inf_path: &Path) -> Self {
+        let mut r = InfImportResult {
+            inf: inf_path.to_string_lossy().into_owned(),
+            ..Default::default()
+        };
+        if let Ok(raw) = std::fs::read(inf_path) {
+            let v = InfFile::parse_bytes(&raw).version();
+            r.provider = v.provider.unwrap_or_default();
+            r.class = v.class.unwrap_or_default();
+            r.driver_ver = v.driver_ver.map(|d| d.to_string()).unwrap_or_default();
+        }
+        r
+    }
+
+    /// 记为失败，错误码取自错误链。
+    pub fn fail(&mut self, err: &anyhow::Error) {
+        self.outcome = ImportOutcome::Failed;
+        self.error_code = error_code_of(err);
+        self.message = err.to_string();
+    }
+
+    /// 追加一条说明（不改变结果）。
+    pub fn note(&mut self, msg: impl Into<String>) {
+        if !self.message.is_empty() {
+            self.message.push_str("; ");
+        }
+        self.message.push_str(&msg.into());
+    }
+
+    pub fn is_success(&self) -> bool {
+        matches!(
+            self.outcome,
+            ImportOutcome::Installed | ImportOutcome::StoreOnly
+        )
+    }
+}
+
+/// 驱动导入报告。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct DriverImportReport {
+    /// 生成时间（Unix 秒）。
+    pub created: u64,
+    /// 导入方式：`online` / `offline` / `dism`。
+    pub method: String,
+    pub source: String,
+    /// 目标系统根目录（在线导入为空）。
+    pub target: String,
+    pub results: Vec<InfImportResult>,
+}
+
+impl DriverImportReport {
+    pub fn new(method: &str, source: &Path, target: Option<&Path>) -> Self {
+        DriverImportReport {
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            method: method.to_string(),
+            source: source.to_string_lossy().into_owned(),
+            target: target
+                .map(|t| t.to_string_lossy().into_owned())
+                .unwrap_or_default(),
+            results: Vec::new(),
+        }
+    }
+
+    pub fn count(&self, outcome: ImportOutcome) -> usize {
+        self.results.iter().filter(|r| r.outcome == outcome).count()
+    }
+
+    /// 成功数（含仅加入驱动存储）。
+    pub fn succeeded(&self) -> usize {
+        self.results.iter().filter(|r| r.is_success()).count()
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(ImportOutcome::Failed)
+    }
+
+    pub fn reboot_required(&self) -> bool {
+        self.results.iter().any(|r| r.reboot_required)
+    }
+
+    /// 为每个 INF 填写它能驱动的设备（按 [`id_rank`] 判定）。
+    pub fn annotate_devices(&mut self, devices: &[DeviceIds], platform: Option<&InfPlatform>) {
+        for r in &mut self.results {
+            let Some(pkg) = DriverPackage::load(Path::new(&r.inf), platform) else {
+                continue;
+            };
+            r.matched_devices = devices
+                .iter()
+                .filter(|d| pkg.models.iter().any(|m| id_rank(d, m).is_some()))
+                .map(|d| {
+                    format!(
+                        "{} [{}]",
+                        d.description,
+                        d.hardware_ids.first().map(String::as_str).unwrap_or("")
+                    )
+                })
+                .collect();
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 可读文本：先列失败项，再列其余。
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "驱动导入报告（{}）\r\n源: {}\r\n目标: {}\r\n成功: {}，仅加入驱动存储: {}，失败: {}，跳过: {}，需要重启: {}\r\n",
+            self.method,
+            self.source,
+            self.target,
+            self.count(ImportOutcome::Installed),
+            self.count(ImportOutcome::StoreOnly),
+            self.failed(),
+            self.count(ImportOutcome::Skipped),
+            if self.reboot_required() { "是" } else { "否" }
+        );
+        let mut ordered: Vec<&InfImportResult> = self.results.iter().collect();
+        ordered.sort_by_key(|r| r.outcome != ImportOutcome::Failed);
+        for r in ordered {
+            out.push_str(&format!("\r\n[{}] {}\r\n", r.outcome.label(), r.inf));
+            if let Some(oem) = &r.oem_inf {
+                out.push_str(&format!("  目标 INF: {}\r\n", oem));
+            }
+            out.push_str(&format!(
+                "  提供商: {}  类: {}  版本: {}\r\n",
+                r.provider, r.class, r.driver_ver
+            ));
+            if let Some(code) = r.error_code {
+                out.push_str(&format!(
+                    "  错误码: 0x{:08X} {}\r\n",
+                    code,
+                    describe_error(code).unwrap_or("")
+                ));
+            }
+            if !r.message.is_empty() {
+                out.push_str(&format!("  信息: {}\r\n", r.message));
+            }
+            for d in &r.matched_devices {
+                out.push_str(&format!("  匹配设备: {}\r\n", d));
+            }
+            if r.reboot_required {
+                out.push_str("  需要重启\r\n");
+            }
+        }
+        out
+    }
+
+    /// 写入 `dir` 下的 JSON 与文本报告，并清除「已查看」标记。
+    pub fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(REPORT_JSON);
+        std::fs::write(&path, self.to_json())?;
+        std::fs::write(dir.join(REPORT_TEXT), self.to_text())?;
+        let _ = std::fs::remove_file(dir.join(SEEN_MARKER));
+        Ok(path)
+    }
+
+    /// 读取 `dir`\[`REPORT_JSON`]；不存在或无法解析时返回 `None`（解析失败记警告）。
+    pub fn load(dir: &Path) -> Option<Self> {
+        let path = dir.join(REPORT_JSON);
+        let text = std::fs::read_to_string(&path).ok()?;
+        match Self::from_json(&text) {
+            Ok(r) => Some(r),
+            Err(e) => {
+                log::warn!("[DriverReport] 驱动导入报告解析失败 {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+}
+
+/// 目标系统里存放 LetRecovery 报告的目录：`Windows\Logs\LetRecovery`。
+pub fn log_dir(system_root: &Path) -> PathBuf {
+    system_root.join("Windows").join("Logs").join("LetRecovery")
+}
+
+/// 尚未查看的报告（首次启动后展示用）。
+pub fn pending_report(dir: &Path) -> Option<DriverImportReport> {
+    if dir.join(SEEN_MARKER).exists() {
+        return None;
+    }
+    DriverImportReport::load(dir)
+}
+
+/// 标记报告已查看。
+pub fn mark_seen(dir: &Path) -> std::io::Result<()> {
+    std::fs::write(dir.join(SEEN_MARKER), b"")
+}
+
+/// DISM 按目录注入后逐个核实 `infs` 是否进了离线系统的驱动存储：
+/// `FileRepository\<inf 名>_*` 下有内容相同的 INF 即成功，并从 `Windows\INF\oem*.inf`
+/// 找出对应的 OEM 名。没有 `[Manufacturer]` 的 INF 记为跳过。
+pub fn report_from_driver_store(
+    infs: &[PathBuf],
+    source: &Path,
+    system_root: &Path,
+) -> DriverImportReport {
+    let mut report = DriverImportReport::new("dism", source, Some(system_root));
+    let windows = system_root.join("Windows");
+    let repository = windows
+        .join("System32")
+        .join("DriverStore")
+        .join("FileRepository");
+    let store_dirs: Vec<PathBuf> = std::fs::read_dir(&repository)
+        .map(|rd| rd.flatten().map(|e| e.path()).collect())
+        .unwrap_or_default();
+    let oem_infs: Vec<PathBuf> = std::fs::read_dir(windows.join("INF"))
+        .map(|rd| {
+            rd.flatten()
+                .map(|e| e.path())
+                .filter(|p| {
+                    let name = p
+                        .file_name()
+                        .map(|n| n.to_string_lossy().to_lowercase())
+                        .unwrap_or_default();
+                    name.starts_with("oem") && name.ends_with(".inf")
+                })
+                .collect()
+        })
+        .unwrap_or_default();
+
+    for inf in infs {
+        let mut r = InfImportResult::new(inf);
+        let Ok(raw) = std::fs::read(inf) else {
+            r.outcome = ImportOutcome::Failed;
+            r.message = "无法读取源 INF".to_string();
+            report.results.push(r);
+            continue;
+        };
+        if !InfFile::parse_bytes(&raw).has_section("Manufacturer") {
+            r.outcome = ImportOutcome::Skipped;
+            r.message = "不是设备驱动 INF".to_string();
+            report.results.push(r);
+            continue;
+        }
+        let name = inf
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let prefix = format!("{}_", name);
+        let in_store = store_dirs.iter().any(|d| {
+            d.file_name()
+                .map(|n| n.to_string_lossy().to_lowercase().starts_with(&prefix))
+                .unwrap_or(false)
+                && same_content(&find_file_ci(d, &name), &raw)
+        });
+        if in_store {
+            r.outcome = ImportOutcome::Installed;
+            r.oem_inf = oem_infs
+                .iter()
+                .find(|p| same_content(&Some(p.to_path_buf()), &raw))
+                .and_then(|p| p.file_name())
+                .map(|n| n.to_string_lossy().into_owned());
+        } else {
+            r.outcome = ImportOutcome::Failed;
+            r.message = "DISM 未将其加入驱动存储（详见 DISM 日志）".to_string();
+        }
+        report.results.push(r);
+    }
+    report
+}
+
+fn find_file_ci(dir: &Path, name_lower: &str) -> Option<PathBuf> {
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|e| e.path())
+        .find(|p| {
+            p.file_name()
+                .map(|n| n.to_string_lossy().to_lowercase() == name_lower)
+                .unwrap_or(false)
+        })
+}
+
+fn same_content(path: &Option<PathBuf>, raw: &[u8]) -> bool {
+    path.as_ref()
+        .filter(|p| {
+            std::fs::metadata(p)
+                .map(|m| m.len() == raw.len() as u64)
+                .unwrap_or(false)
+        })
+        .and_then(|p| std::fs::read(p).ok())
+        .is_some_and(|data| data == raw)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const NET_INF: &str = "[Version]\nClass=Net\nProvider=Intel\nDriverVer=01/01/2024,1.2.3.4\n\
+        [Manufacturer]\nM=Models,NTamd64\n[Models.NTamd64]\nd=I, PCI\\VEN_8086&DEV_15F3\n";
+
+    #[test]
+    fn error_codes_come_from_error_chain() {
+        let api: anyhow::Error = DriverApiError {
+            api: "SetupCopyOEMInf",
+            code: 0xE000_022F,
+        }
+        .into();
+        assert_eq!(
+            api.to_string(),
+            format!("SetupCopyOEMInf 失败: 错误码 {}", 0xE000_022Fu32)
+        );
+        assert_eq!(
+            error_code_of(
+                &anyhow::Error::new(DriverApiError {
+                    api: "DiInstallDriverW",
+                    code: 0xE000_022F
+                })
+                .context("安装 net.inf")
+            ),
+            Some(0xE000_022F)
+        );
+        let io: anyhow::Error = std::io::Error::from_raw_os_error(5).into();
+        assert_eq!(error_code_of(&io), Some(5));
+        assert_eq!(error_code_of(&anyhow::anyhow!("x")), None);
+
+        let mut r = InfImportResult::default();
+        r.fail(&api);
+        assert_eq!(r.outcome, ImportOutcome::Failed);
+        assert_eq!(r.error_code, Some(0xE000_022F));
+        r.note("附加");
+        assert!(r.message.ends_with("; 附加"));
+    }
+
+    #[test]
+    fn dism_result_verified_against_driver_store() {
+        let root = std::env::temp_dir().join(format!("lr_drvreport_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        let src = root.join("src");
+        std::fs::create_dir_all(&src).unwrap();
+        let ok = src.join("net.inf");
+        let bad = src.join("wifi.inf");
+        let misc = src.join("autorun.inf");
+        std::fs::write(&ok, NET_INF).unwrap();
+        std::fs::write(&bad, NET_INF.replace("15F3", "2723")).unwrap();
+        std::fs::write(&misc, "[autorun]\nopen=setup.exe\n").unwrap();
+
+        let sys = root.join("sys");
+        let store = log_dir(&sys)
+            .parent()
+            .unwrap()
+            .parent()
+            .unwrap()
+            .join("System32")
+            .join("DriverStore")
+            .join("FileRepository")
+            .join("net.inf_amd64_0123abcd");
+        std::fs::create_dir_all(&store).unwrap();
+        std::fs::write(store.join("NET.INF"), NET_INF).unwrap();
+        std::fs::create_dir_all(sys.join("Windows").join("INF")).unwrap();
+        std::fs::write(sys.join("Windows").join("INF").join("oem7.inf"), NET_INF).unwrap();
+
+        let mut report = report_from_driver_store(&[ok, bad, misc], &src, &sys);
+        let dev = DeviceIds {
+            description: "Ethernet".into(),
+            hardware_ids: vec!["PCI\\VEN_8086&DEV_15F3".into()],
+            ..Default::default()
+        };
+        report.annotate_devices(&[dev], None);
+        let r = &report.results;
+        assert_eq!(r[0].outcome, ImportOutcome::Installed);
+        assert_eq!(r[0].oem_inf.as_deref(), Some("oem7.inf"));
+        assert_eq!(r[0].provider, "Intel");
+        assert_eq!(r[0].driver_ver, "01/01/2024,1.2.3.4");
+        assert_eq!(
+            r[0].matched_devices,
+            vec!["Ethernet [PCI\\VEN_8086&DEV_15F3]"]
+        );
+        assert_eq!(r[1].outcome, ImportOutcome::Failed);
+        assert!(r[1].matched_devices.is_empty());
+        assert_eq!(r[2].outcome, ImportOutcome::Skipped);
+        assert_eq!((report.succeeded(), report.failed()), (1, 1));
+
+        // 失败项排在文本最前；保存后可读回，查看后不再待展示
+        let text = report.to_text();
+        assert!(text.find("[失败]").unwrap() < text.find("[成功]").unwrap());
+        let dir = log_dir(&sys);
+        report.save(&dir).unwrap();
+        assert!(dir.join(REPORT_TEXT).exists());
+        assert_eq!(pending_report(&dir), Some(report.clone()));
+        mark_seen(&dir).unwrap();
+        assert_eq!(pending_report(&dir), None);
+        report.save(&dir).unwrap();
+        assert!(pending_report(&dir).is_some());
+        let _ = std::fs::remove_dir_all(&root);
+    }
+}
@@ -0,0 +1,886 @@
+//! 无损扩大 C 盘的规划（纯计算，不碰磁盘）
+//!
+//! 输入是磁盘布局模型 [`DiskLayout`]，输出按顺序执行的步骤 [`ExpandStep`]：收缩、搬移、扩展。
+//! 正常系统端用它预览方案，PE 端（`expand_move`）按步骤落到磁盘上。
+//!
+//! 取空间的先后顺序（尽量少搬数据）：
+//! 1. C 之后紧邻的未分配空间——直接扩展；
+//! 2. C 之后到第一个不可移动分区（或可用区末尾）之间的其余未分配空间——把中间的数据分区、
+//!    恢复分区依次右移，每个只移到刚好让出空间的位置；
+//! 3. C 之前紧邻的未分配空间——把 C 整体左移（MBR 活动分区不移动）；
+//! 4. 仍不够时收缩上述区间里最后一个数据分区的文件系统。
+//!
+//! 所有参与计算的偏移与长度须按 1 MiB 对齐，保证 diskpart 按 MB/KB 重建表项时分毫不差。
+//!
+//! 方案只取决于分区表（类型、位置、活动标记），盘符仅用于展示：正常系统与 PE 里盘符可能不同，
+//! 两端算出的方案必须一致。
+
+use std::fmt;
+
+use crate::partition_table::{Guid, PartitionTable, PartitionType};
+
+/// 对齐粒度（1 MiB）
+pub const ALIGN: u64 = 1024 * 1024;
+
+/// 分区在扩容中的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartKind {
+    /// 基础数据分区（NTFS/FAT），可移动、可收缩
+    Data,
+    /// GPT 上的 Windows 恢复分区，可移动
+    Recovery,
+    /// ESP/MSR/MBR 扩展分区容器/MBR 恢复分区/未知类型等，不移动
+    Fixed,
+}
+
+/// 布局中的一个分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutPart {
+    pub number: u32,
+    pub offset: u64,
+    pub length: u64,
+    pub kind: PartKind,
+    /// 盘符（仅用于展示，不影响方案）
+    pub letter: Option<char>,
+    /// MBR 活动分区（引导扇区里记着自身位置，不移动）
+    pub active: bool,
+}
+
+impl LayoutPart {
+    pub fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+
+    /// 能否整体搬移
+    fn movable(&self) -> bool {
+        !self.active && self.kind != PartKind::Fixed
+    }
+}
+
+/// 一块磁盘的布局
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DiskLayout {
+    /// 分区可用区起点（字节）
+    pub usable_start: u64,
+    /// 分区可用区终点（字节，不含）
+    pub usable_end: u64,
+    pub partitions: Vec<LayoutPart>,
+}
+
+impl DiskLayout {
+    /// 由分区表生成布局；`letter_of` 按分区起始偏移查盘符（仅用于展示）
+    ///
+    /// MBR 上的恢复分区不移动：BCD 里 WinRE 的位置按磁盘签名 + 分区偏移记录，移动后就找不到了。
+    ///
+    /// 扩展分区容器（从首个 EBR 起，含其中的逻辑分区）整体作为一个不可移动分区，逻辑分区不单独列出；
+    /// 否则容器开头的 EBR 区域或空的扩展分区会被当成空闲空间，搬移时把 EBR 覆盖掉。
+    pub fn from_table(table: &PartitionTable, letter_of: impl Fn(u64) -> Option<char>) -> Self {
+        let (usable_start, usable_end) = table.usable_range();
+        let partitions = table
+            .partitions
+            .iter()
+            .filter(|p| !p.logical)
+            .map(|p| {
+                let kind = if p.extended {
+                    PartKind::Fixed
+                } else if p.is_recovery() {
+                    match p.partition_type {
+                        PartitionType::Gpt(_) => PartKind::Recovery,
+                        PartitionType::Mbr(_) => PartKind::Fixed,
+                    }
+                } else {
+                    match p.partition_type {
+                        PartitionType::Gpt(Guid::BASIC_DATA) => PartKind::Data,
+                        PartitionType::Mbr(0x07 | 0x0b | 0x0c) => PartKind::Data,
+                        _ => PartKind::Fixed,
+                    }
+                };
+                LayoutPart {
+                    number: p.number,
+                    offset: p.offset,
+                    length: p.length,
+                    kind,
+                    letter: letter_of(p.offset),
+                    active: p.active,
+                }
+            })
+            .collect();
+        DiskLayout {
+            usable_start,
+            usable_end,
+            partitions,
+        }
+    }
+}
+
+/// 扩容的一个步骤
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ExpandStep {
+    /// 收缩分区的文件系统与表项（起点不变，尾部让出 `by` 字节）
+    Shrink {
+        number: u32,
+        letter: Option<char>,
+        by: u64,
+    },
+    /// 把分区整体搬到新起点（数据与表项一起）
+    Move {
+        number: u32,
+        letter: Option<char>,
+        kind: PartKind,
+        from: u64,
+        to: u64,
+        length: u64,
+    },
+    /// 把目标分区扩展到 `length`
+    Extend {
+        number: u32,
+        letter: Option<char>,
+        length: u64,
+    },
+}
+
+/// 展示用的分区名：有盘符显示盘符，否则按类型
+fn part_name(letter: Option<char>, kind: PartKind) -> String {
+    match (letter, kind) {
+        (Some(l), _) => format!("{}:", l),
+        (None, PartKind::Recovery) => "恢复分区".to_string(),
+        (None, _) => "无盘符".to_string(),
+    }
+}
+
+impl fmt::Display for ExpandStep {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ExpandStep::Shrink { number, letter, by } => write!(
+                f,
+                "收缩分区 #{}（{}）{} MiB",
+                number,
+                part_name(*letter, PartKind::Data),
+                by / ALIGN
+            ),
+            ExpandStep::Move {
+                number,
+                letter,
+                kind,
+                from,
+                to,
+                length,
+            } => {
+                let name = part_name(*letter, *kind);
+                let (dir, d) = if to > from {
+                    ("右移", to - from)
+                } else {
+                    ("左移", from - to)
+                };
+                write!(
+                    f,
+                    "分区 #{}（{}）{} {} MiB（{} MiB -> {} MiB，搬移 {} MiB 数据）",
+                    number,
+                    name,
+                    dir,
+                    d / ALIGN,
+                    from / ALIGN,
+                    to / ALIGN,
+                    length / ALIGN
+                )
+            }
+            ExpandStep::Extend {
+                number,
+                letter,
+                length,
+            } => write!(
+                f,
+                "扩展分区 #{}（{}）到 {} MiB",
+                number,
+                part_name(*letter, PartKind::Data),
+                length / ALIGN
+            ),
+        }
+    }
+}
+
+/// 扩容方案
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandPlan {
+    pub number: u32,
+    pub letter: Option<char>,
+    /// 扩容前的长度
+    pub current_length: u64,
+    /// 扩容后的起点（C 左移时变小）
+    pub final_offset: u64,
+    /// 扩容后的长度
+    pub final_length: u64,
+    /// 按顺序执行的步骤；已达到目标大小时为空
+    pub steps: Vec<ExpandStep>,
+}
+
+impl ExpandPlan {
+    /// 是否需要搬移分区数据（有风险、耗时长）
+    pub fn moves_data(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|s| matches!(s, ExpandStep::Move { .. }))
+    }
+
+    /// 目标分区自身是否要移动
+    pub fn moves_target(&self) -> bool {
+        self.steps
+            .iter()
+            .any(|s| matches!(s, ExpandStep::Move { number, .. } if *number == self.number))
+    }
+
+    /// 供日志与界面展示的方案说明（每步一行）
+    pub fn describe(&self) -> Vec<String> {
+        self.steps
+            .iter()
+            .enumerate()
+            .map(|(i, s)| format!("{}. {}", i + 1, s))
+            .collect()
+    }
+}
+
+/// 不同代价下能扩到的最终大小
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExpandLimits {
+    /// 只并入紧邻的未分配空间（不搬数据）
+    pub no_move: u64,
+    /// 搬移分区但不收缩任何文件系统
+    pub without_shrink: u64,
+    /// 空间不够时会被收缩的数据分区（分区号与盘符）
+    pub shrink_candidate: Option<(u32, Option<char>)>,
+}
+
+/// 规划失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    TargetNotFound(u32),
+    /// 目标不是基础数据分区
+    TargetNotData(u32),
+    /// 分区互相重叠或超出可用区
+    BadLayout,
+    Unaligned {
+        what: &'static str,
+        value: u64,
+    },
+    /// 空间不足；`available` 为不收缩时可达到的最终大小
+    InsufficientSpace {
+        wanted: u64,
+        available: u64,
+    },
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::TargetNotFound(n) => write!(f, "磁盘上没有分区 #{}", n),
+            PlanError::TargetNotData(n) => write!(f, "分区 #{} 不是基础数据分区", n),
+            PlanError::BadLayout => write!(f, "分区布局异常（分区重叠或超出磁盘可用区）"),
+            PlanError::Unaligned { what, value } => write!(
+                f,
+                "{} 未按 1 MiB 对齐（{} 字节），为保证精确重建拒绝移动",
+                what, value
+            ),
+            PlanError::InsufficientSpace { wanted, available } => write!(
+                f,
+                "空间不足：目标 {} MiB，不收缩其他分区最多 {} MiB，且没有可收缩的数据分区",
+                wanted / ALIGN,
+                available / ALIGN
+            ),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+fn align_up(v: u64) -> u64 {
+    v.div_ceil(ALIGN) * ALIGN
+}
+
+fn align_down(v: u64) -> u64 {
+    v / ALIGN * ALIGN
+}
+
+fn check_aligned(what: &'static str, value: u64) -> Result<(), PlanError> {
+    if value.is_multiple_of(ALIGN) {
+        Ok(())
+    } else {
+        Err(PlanError::Unaligned { what, value })
+    }
+}
+
+/// 目标分区周围的空间
+struct Surroundings<'a> {
+    target: &'a LayoutPart,
+    /// C 之后连续可移动的分区（按偏移升序）
+    run: Vec<&'a LayoutPart>,
+    /// C 之后紧邻的未分配空间
+    adjacent: u64,
+    /// C 之后到边界的全部未分配空间
+    free_after: u64,
+    /// 可从 C 之前收回的空间
+    reclaimable: u64,
+}
+
+impl Surroundings<'_> {
+    /// 空间不够时收缩的分区：区间里最后一个数据分区
+    fn shrink_candidate(&self) -> Option<&LayoutPart> {
+        self.run
+            .iter()
+            .rev()
+            .find(|p| p.kind == PartKind::Data)
+            .copied()
+    }
+}
+
+fn surroundings(layout: &DiskLayout, number: u32) -> Result<Surroundings<'_>, PlanError> {
+    let mut parts: Vec<&LayoutPart> = layout.partitions.iter().collect();
+    parts.sort_by_key(|p| p.offset);
+    let mut prev_end = layout.usable_start;
+    for p in &parts {
+        if p.offset < prev_end || p.end() > layout.usable_end {
+            return Err(PlanError::BadLayout);
+        }
+        prev_end = p.end();
+    }
+
+    let idx = parts
+        .iter()
+        .position(|p| p.number == number)
+        .ok_or(PlanError::TargetNotFound(number))?;
+    let target = parts[idx];
+    if target.kind != PartKind::Data {
+        return Err(PlanError::TargetNotData(number));
+    }
+    check_aligned("C 起点", target.offset)?;
+    check_aligned("C 长度", target.length)?;
+
+    let mut run = Vec::new();
+    let mut boundary = align_down(layout.usable_end);
+    for p in &parts[idx + 1..] {
+        if !p.movable() {
+            boundary = p.offset;
+            break;
+        }
+        check_aligned("后方分区起点", p.offset)?;
+        check_aligned("后方分区长度", p.length)?;
+        run.push(*p);
+    }
+    let boundary = align_down(boundary);
+    let occupied: u64 = run.iter().map(|p| p.length).sum();
+    let free_after = boundary
+        .saturating_sub(target.end())
+        .saturating_sub(occupied);
+    let adjacent = run
+        .first()
+        .map_or(boundary, |p| p.offset)
+        .saturating_sub(target.end());
+
+    let before_start = match idx {
+        0 => layout.usable_start,
+        _ => parts[idx - 1].end(),
+    };
+    let reclaimable = if target.active {
+        0
+    } else {
+        target.offset.saturating_sub(align_up(before_start))
+    };
+
+    Ok(Surroundings {
+        target,
+        run,
+        adjacent,
+        free_after,
+        reclaimable,
+    })
+}
+
+/// 分区 `number` 在不同代价下能扩到的最终大小
+pub fn expand_limits(layout: &DiskLayout, number: u32) -> Result<ExpandLimits, PlanError> {
+    let s = surroundings(layout, number)?;
+    Ok(ExpandLimits {
+        no_move: s.target.length + s.adjacent,
+        without_shrink: s.target.length + s.free_after + s.reclaimable,
+        shrink_candidate: s.shrink_candidate().map(|p| (p.number, p.letter)),
+    })
+}
+
+/// 规划把分区 `number` 扩大到 `size` 字节（0 = 不收缩其他分区时的最大值）
+pub fn plan_expand(layout: &DiskLayout, number: u32, size: u64) -> Result<ExpandPlan, PlanError> {
+    let s = surroundings(layout, number)?;
+    let c = s.target;
+    let letter = c.letter;
+    let max_without_shrink = c.length + s.free_after + s.reclaimable;
+    let wanted = if size == 0 { max_without_shrink } else { size };
+    check_aligned("目标大小", wanted)?;
+
+    let mut plan = ExpandPlan {
+        number,
+        letter,
+        current_length: c.length,
+        final_offset: c.offset,
+        final_length: c.length.max(wanted),
+        steps: Vec::new(),
+    };
+    if wanted <= c.length {
+        return Ok(plan);
+    }
+    let need = wanted - c.length;
+    let extend = ExpandStep::Extend {
+        number,
+        letter,
+        length: wanted,
+    };
+    if need <= s.adjacent {
+        plan.steps.push(extend);
+        return Ok(plan);
+    }
+
+    // 依次取：C 之后的空闲 → C 之前的空闲 → 收缩数据分区
+    let from_after = need.min(s.free_after);
+    let reclaim = (need - from_after).min(s.reclaimable);
+    let shrink = need - from_after - reclaim;
+    let shrunk = match shrink {
+        0 => None,
+        _ => match s.shrink_candidate() {
+            Some(p) if shrink < p.length => Some(p),
+            _ => {
+                return Err(PlanError::InsufficientSpace {
+                    wanted,
+                    available: max_without_shrink,
+                })
+            }
+        },
+    };
+    if let Some(p) = shrunk {
+        plan.steps.push(ExpandStep::Shrink {
+            number: p.number,
+            letter: p.letter,
+            by: shrink,
+        });
+    }
+
+    // 后方分区依次右移，每个只移到刚好不压住前一个的位置；从最右边的开始搬，目标区域总是空的
+    let mut cursor = c.end() + from_after + shrink;
+    let mut moves = Vec::new();
+    for p in &s.run {
+        let length = match shrunk {
+            Some(sp) if sp.number == p.number => p.length - shrink,
+            _ => p.length,
+        };
+        let to = p.offset.max(cursor);
+        if to != p.offset {
+            moves.push(ExpandStep::Move {
+                number: p.number,
+                letter: p.letter,
+                kind: p.kind,
+                from: p.offset,
+                to,
+                length,
+            });
+        }
+        cursor = to + length;
+    }
+    plan.steps.extend(moves.into_iter().rev());
+
+    if reclaim > 0 {
+        plan.final_offset = c.offset - reclaim;
+        plan.steps.push(ExpandStep::Move {
+            number,
+            letter,
+            kind: c.kind,
+            from: c.offset,
+            to: plan.final_offset,
+            length: c.length,
+        });
+    }
+    plan.steps.push(extend);
+    Ok(plan)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition_table::PartitionEntry;
+
+    const G: u64 = 1024 * ALIGN;
+
+    fn data(number: u32, offset: u64, length: u64, letter: char) -> LayoutPart {
+        LayoutPart {
+            number,
+            offset,
+            length,
+            kind: PartKind::Data,
+            letter: Some(letter),
+            active: false,
+        }
+    }
+
+    fn part(number: u32, offset: u64, length: u64, kind: PartKind) -> LayoutPart {
+        LayoutPart {
+            number,
+            offset,
+            length,
+            kind,
+            letter: None,
+            active: false,
+        }
+    }
+
+    fn layout(parts: Vec<LayoutPart>) -> DiskLayout {
+        DiskLayout {
+            usable_start: ALIGN,
+            usable_end: 200 * G + ALIGN,
+            partitions: parts,
+        }
+    }
+
+    /// 按方案在布局上模拟执行，检查每步都不越界、不重叠，返回最终布局
+    fn simulate(layout: &DiskLayout, plan: &ExpandPlan) -> DiskLayout {
+        let mut out = layout.clone();
+        for step in &plan.steps {
+            match *step {
+                ExpandStep::Shrink { number, by, .. } => {
+                    let p = out
+                        .partitions
+                        .iter_mut()
+                        .find(|p| p.number == number)
+                        .unwrap();
+                    p.length -= by;
+                }
+                ExpandStep::Move {
+                    number,
+                    from,
+                    to,
+                    length,
+                    ..
+                } => {
+                    let p = out
+                        .partitions
+                        .iter_mut()
+                        .find(|p| p.number == number)
+                        .unwrap();
+                    assert_eq!((p.offset, p.length), (from, length));
+                    p.offset = to;
+                }
+                ExpandStep::Extend { number, length, .. } => {
+                    let p = out
+                        .partitions
+                        .iter_mut()
+                        .find(|p| p.number == number)
+                        .unwrap();
+                    p.length = length;
+                }
+            }
+            let mut sorted = out.partitions.clone();
+            sorted.sort_by_key(|p| p.offset);
+            let mut prev = out.usable_start;
+            for p in &sorted {
+                assert!(p.offset >= prev, "步骤 {} 后分区 #{} 重叠", step, p.number);
+                assert_eq!(p.offset % ALIGN, 0);
+                prev = p.end();
+            }
+            assert!(prev <= out.usable_end);
+        }
+        out
+    }
+
+    fn find(layout: &DiskLayout, number: u32) -> &LayoutPart {
+        layout
+            .partitions
+            .iter()
+            .find(|p| p.number == number)
+            .unwrap()
+    }
+
+    #[test]
+    fn adjacent_free_space_only_extends() {
+        let l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, 101 * ALIGN, 50 * G, 'C'),
+            data(3, 101 * ALIGN + 60 * G, 50 * G, 'D'),
+        ]);
+        let plan = plan_expand(&l, 2, 60 * G).unwrap();
+        assert_eq!(
+            plan.steps,
+            vec![ExpandStep::Extend {
+                number: 2,
+                letter: Some('C'),
+                length: 60 * G
+            }]
+        );
+        assert!(!plan.moves_data());
+
+        // 已达到目标：什么都不做
+        assert!(plan_expand(&l, 2, 40 * G).unwrap().steps.is_empty());
+        let limits = expand_limits(&l, 2).unwrap();
+        assert_eq!(limits.no_move, 60 * G);
+        assert_eq!(limits.shrink_candidate, Some((3, Some('D'))));
+    }
+
+    #[test]
+    fn shifts_several_partitions_right_in_order() {
+        // C | D | E | 空闲 30G
+        let c_off = 101 * ALIGN;
+        let l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, c_off, 50 * G, 'C'),
+            data(3, c_off + 50 * G, 40 * G, 'D'),
+            data(4, c_off + 90 * G, 40 * G, 'E'),
+        ]);
+        let plan = plan_expand(&l, 2, 70 * G).unwrap();
+        let kinds: Vec<u32> = plan
+            .steps
+            .iter()
+            .filter_map(|s| match s {
+                ExpandStep::Move { number, .. } => Some(*number),
+                _ => None,
+            })
+            .collect();
+        // 先搬最右边的 E，再搬 D
+        assert_eq!(kinds, vec![4, 3]);
+        let after = simulate(&l, &plan);
+        assert_eq!(find(&after, 2).length, 70 * G);
+        assert_eq!(find(&after, 3).offset, c_off + 70 * G);
+        assert_eq!(find(&after, 4).offset, c_off + 110 * G);
+        assert_eq!(plan.describe().len(), 3);
+
+        // 只需要一部分空间时，后面的分区能不动就不动
+        let l2 = layout(vec![
+            data(2, c_off, 50 * G, 'C'),
+            data(3, c_off + 50 * G, 40 * G, 'D'),
+            data(4, c_off + 100 * G, 40 * G, 'E'),
+        ]);
+        let plan = plan_expand(&l2, 2, 55 * G).unwrap();
+        let after = simulate(&l2, &plan);
+        assert_eq!(find(&after, 3).offset, c_off + 55 * G);
+        assert_eq!(find(&after, 4).offset, c_off + 100 * G);
+    }
+
+    #[test]
+    fn relocates_recovery_partition_between_c_and_d() {
+        let c_off = 101 * ALIGN;
+        let r_off = c_off + 50 * G;
+        let l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, c_off, 50 * G, 'C'),
+            part(3, r_off, 600 * ALIGN, PartKind::Recovery),
+            data(4, r_off + 600 * ALIGN, 40 * G, 'D'),
+        ]);
+        let plan = plan_expand(&l, 2, 60 * G).unwrap();
+        let after = simulate(&l, &plan);
+        assert_eq!(find(&after, 2).length, 60 * G);
+        assert_eq!(find(&after, 3).offset, c_off + 60 * G);
+        assert!(plan.steps.iter().any(|s| matches!(
+            s,
+            ExpandStep::Move {
+                kind: PartKind::Recovery,
+                letter: None,
+                ..
+            }
+        )));
+        assert!(plan.moves_data());
+    }
+
+    #[test]
+    fn reclaims_space_before_c() {
+        // ESP | 空闲 10G | C | 不可移动分区 | 磁盘尾
+        let c_off = 101 * ALIGN + 10 * G;
+        let mut l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, c_off, 50 * G, 'C'),
+            part(3, c_off + 50 * G, 600 * ALIGN, PartKind::Fixed),
+        ]);
+        let limits = expand_limits(&l, 2).unwrap();
+        assert_eq!(limits.no_move, 50 * G);
+        assert_eq!(limits.without_shrink, 60 * G);
+
+        let plan = plan_expand(&l, 2, 0).unwrap();
+        assert_eq!(plan.final_offset, 101 * ALIGN);
+        assert_eq!(plan.final_length, 60 * G);
+        assert!(plan.moves_target());
+        let after = simulate(&l, &plan);
+        assert_eq!(find(&after, 2).offset, 101 * ALIGN);
+        assert_eq!(find(&after, 2).end(), c_off + 50 * G);
+
+        // 活动分区（引导扇区记着位置）不左移
+        l.partitions[1].active = true;
+        assert_eq!(
+            plan_expand(&l, 2, 55 * G),
+            Err(PlanError::InsufficientSpace {
+                wanted: 55 * G,
+                available: 50 * G
+            })
+        );
+    }
+
+    #[test]
+    fn shrinks_last_data_partition_when_free_space_runs_out() {
+        // ESP | C | D | 恢复 | 空闲 5G | 可用区末尾
+        let c_off = 101 * ALIGN;
+        let d_off = c_off + 50 * G;
+        let r_off = 195 * G + ALIGN - 600 * ALIGN;
+        let l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, c_off, 50 * G, 'C'),
+            data(3, d_off, r_off - d_off, 'D'),
+            part(4, r_off, 600 * ALIGN, PartKind::Recovery),
+        ]);
+        let plan = plan_expand(&l, 2, 60 * G).unwrap();
+        assert_eq!(
+            plan.steps[0],
+            ExpandStep::Shrink {
+                number: 3,
+                letter: Some('D'),
+                by: 5 * G
+            }
+        );
+        let after = simulate(&l, &plan);
+        assert_eq!(find(&after, 2).length, 60 * G);
+        assert_eq!(find(&after, 3).offset, c_off + 60 * G);
+
+        assert_eq!(find(&after, 3).end(), r_off + 5 * G);
+        assert_eq!(find(&after, 4).end(), l.usable_end);
+
+        // 目标比 D 还大：收缩不了
+        assert!(matches!(
+            plan_expand(&l, 2, 200 * G),
+            Err(PlanError::InsufficientSpace { .. })
+        ));
+    }
+
+    #[test]
+    fn layout_from_table_ignores_letters_and_pins_mbr_recovery() {
+        use crate::partition_writer::NewPartition;
+
+        // C | 恢复分区 | 空闲
+        let build = |mut table: PartitionTable, data: PartitionType, recovery: PartitionType| {
+            let c = table
+                .add_partition(NewPartition::new(data).size(50 * G))
+                .unwrap();
+            table
+                .add_partition(NewPartition::new(recovery).size(600 * ALIGN))
+                .unwrap();
+            (table, c)
+        };
+        let steps = |l: &DiskLayout, c: u32| -> Vec<(u32, u64)> {
+            plan_expand(l, c, 60 * G)
+                .unwrap()
+                .steps
+                .iter()
+                .map(|s| match *s {
+                    ExpandStep::Move { number, to, .. } => (number, to),
+                    ExpandStep::Extend { number, length, .. } => (number, length),
+                    ExpandStep::Shrink { number, by, .. } => (number, by),
+                })
+                .collect()
+        };
+
+        let (gpt, c) = build(
+            PartitionTable::new_gpt(512, 100 * G).unwrap(),
+            PartitionType::Gpt(Guid::BASIC_DATA),
+            PartitionType::Gpt(Guid::RECOVERY),
+        );
+        let c_off = gpt.partition(c).unwrap().offset;
+        let with_letters = DiskLayout::from_table(&gpt, |off| (off == c_off).then_some('C'));
+        let without = DiskLayout::from_table(&gpt, |_| None);
+        assert_eq!(with_letters.partitions[1].kind, PartKind::Recovery);
+        assert_eq!(steps(&with_letters, c), steps(&without, c));
+        assert!(plan_expand(&without, c, 60 * G).unwrap().moves_data());
+
+        // MBR 恢复分区固定不动，挡住后面的空闲空间
+        let (mbr, c) = build(
+            PartitionTable::new_mbr(512, 100 * G).unwrap(),
+            PartitionType::Mbr(0x07),
+            PartitionType::Mbr(0x27),
+        );
+        let l = DiskLayout::from_table(&mbr, |_| None);
+        assert_eq!(l.partitions[1].kind, PartKind::Fixed);
+        assert!(matches!(
+            plan_expand(&l, c, 60 * G),
+            Err(PlanError::InsufficientSpace { .. })
+        ));
+    }
+
+    #[test]
+    fn extended_container_blocks_moves() {
+        // C | D | 扩展分区（开头 1 MiB 是 EBR，其后为逻辑分区）
+        let mut table = PartitionTable::new_mbr(512, 100 * G).unwrap();
+        let entry = |number, slot, t, offset, length| PartitionEntry {
+            number,
+            slot,
+            partition_type: PartitionType::Mbr(t),
+            offset,
+            length,
+            active: false,
+            logical: false,
+            extended: false,
+            unique_guid: None,
+            attributes: 0,
+            name: String::new(),
+        };
+        let c_off = ALIGN;
+        let d_off = c_off + 10 * G;
+        let e_off = d_off + 5 * G;
+        table.partitions = vec![
+            entry(1, 0, 0x07, c_off, 10 * G),
+            entry(2, 1, 0x07, d_off, 5 * G),
+            PartitionEntry {
+                extended: true,
+                ..entry(0, 2, 0x0F, e_off, 20 * G)
+            },
+            PartitionEntry {
+                logical: true,
+                ..entry(3, 0, 0x07, e_off + ALIGN, 20 * G - ALIGN)
+            },
+        ];
+        let l = DiskLayout::from_table(&table, |_| None);
+        assert_eq!(l.partitions.len(), 3);
+        assert_eq!(
+            (l.partitions[2].offset, l.partitions[2].kind),
+            (e_off, PartKind::Fixed)
+        );
+
+        // EBR 所在的 1 MiB 不算空闲：不收缩时一点也扩不了
+        assert_eq!(expand_limits(&l, 1).unwrap().without_shrink, 10 * G);
+        // 收缩 D 后右移，D 的末尾正好顶到扩展分区起点
+        let plan = plan_expand(&l, 1, 11 * G).unwrap();
+        let after = simulate(&l, &plan);
+        assert_eq!(find(&after, 2).end(), e_off);
+        assert_eq!(find(&after, 0).offset, e_off);
+
+        // 空的扩展分区同样不能被占用
+        table.partitions.pop();
+        let l = DiskLayout::from_table(&table, |_| None);
+        assert_eq!(expand_limits(&l, 1).unwrap().without_shrink, 10 * G);
+    }
+
+    #[test]
+    fn stops_at_fixed_partitions_and_rejects_bad_input() {
+        let c_off = 101 * ALIGN;
+        // 不可移动的分区挡住后面的空闲空间
+        let l = layout(vec![
+            part(1, ALIGN, 100 * ALIGN, PartKind::Fixed),
+            data(2, c_off, 50 * G, 'C'),
+            part(3, c_off + 50 * G, 10 * G, PartKind::Fixed),
+        ]);
+        assert_eq!(expand_limits(&l, 2).unwrap().without_shrink, 50 * G);
+        assert!(matches!(
+            plan_expand(&l, 2, 51 * G),
+            Err(PlanError::InsufficientSpace { .. })
+        ));
+
+        assert_eq!(plan_expand(&l, 9, G), Err(PlanError::TargetNotFound(9)));
+        assert_eq!(plan_expand(&l, 3, G), Err(PlanError::TargetNotData(3)));
+
+        let unaligned = layout(vec![data(2, c_off + 512, 50 * G, 'C')]);
+        assert!(matches!(
+            plan_expand(&unaligned, 2, 60 * G),
+            Err(PlanError::Unaligned { .. })
+        ));
+        let overlap = layout(vec![
+            data(2, c_off, 50 * G, 'C'),
+            data(3, c_off + 10 * G, 50 * G, 'D'),
+        ]);
+        assert_eq!(plan_expand(&overlap, 2, 60 * G), Err(PlanError::BadLayout));
+    }
+}
@@ -0,0 +1,189 @@
+//! 硬件清单：正常系统端 → PE 端的本机设备快照。
+//!
+//! 正常系统端在写安装配置时把本机在位设备（实例 ID / 硬件 ID / 兼容 ID / 类 / 当前 INF）
+//! 写成数据目录下的 [`MANIFEST_FILE`]。PE 端据此从驱动库挑选驱动
+//! （[`crate::driver_match`]），并在部署后对照驱动库与新系统自带的 INF，报告哪些设备
+//! 在新系统里没有驱动（[`coverage`]）。
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::driver_match::{scan_packages, select_drivers, DeviceIds, DriverMatch, DriverPackage};
+use crate::inf::InfPlatform;
+
+/// 清单文件名（与安装配置同在数据目录）。
+pub const MANIFEST_FILE: &str = "hardware_manifest.json";
+
+/// 硬件清单。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct HardwareManifest {
+    /// 采集时的计算机名。
+    pub computer_name: String,
+    /// 采集时间（Unix 秒）。
+    pub created: u64,
+    pub devices: Vec<DeviceIds>,
+}
+
+impl HardwareManifest {
+    /// 以当前计算机名与时间包装设备列表。
+    pub fn new(devices: Vec<DeviceIds>) -> Self {
+        Self {
+            computer_name: std::env::var("COMPUTERNAME").unwrap_or_default(),
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            devices,
+        }
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 写入 `dir`\[`MANIFEST_FILE`]，返回文件路径。
+    pub fn save(&self, dir: &Path) -> std::io::Result<PathBuf> {
+        let path = dir.join(MANIFEST_FILE);
+        std::fs::write(&path, self.to_json())?;
+        Ok(path)
+    }
+
+    /// 读取 `dir`\[`MANIFEST_FILE`]；不存在或无法解析时返回 `None`（解析失败记警告）。
+    pub fn load(dir: &Path) -> Option<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let text = std::fs::read_to_string(&path).ok()?;
+        match Self::from_json(&text) {
+            Ok(m) => Some(m),
+            Err(e) => {
+                log::warn!("[HwManifest] 硬件清单解析失败 {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+}
+
+/// 设备在新系统里的驱动覆盖情况。
+#[derive(Debug, Clone, Default)]
+pub struct DriverCoverage {
+    /// 由驱动库提供驱动的设备。
+    pub from_repository: Vec<DriverMatch>,
+    /// 驱动库没有、但新系统自带 INF 能驱动的设备。
+    pub inbox: Vec<DriverMatch>,
+    /// 两边都没有驱动的设备。
+    pub missing: Vec<DeviceIds>,
+}
+
+impl DriverCoverage {
+    /// 可读文本（日志 / 写入目标系统）。
+    pub fn to_text(&self) -> String {
+        let mut out = format!(
+            "驱动库提供: {}，系统自带: {}，缺少驱动: {}\r\n",
+            self.from_repository.len(),
+            self.inbox.len(),
+            self.missing.len()
+        );
+        for d in &self.missing {
+            out.push_str(&format!(
+                "\r\n[缺少驱动] {} ({})\r\n  实例 ID: {}\r\n",
+                d.description, d.class, d.instance_id
+            ));
+            for id in &d.hardware_ids {
+                out.push_str(&format!("  硬件 ID: {}\r\n", id));
+            }
+        }
+        for m in &self.from_repository {
+            out.push_str(&format!(
+                "\r\n[驱动库] {} -> {}\r\n",
+                m.device.description,
+                m.inf_path.display()
+            ));
+        }
+        out
+    }
+}
+
+/// 先用驱动库匹配，剩下的设备再用新系统自带 INF 匹配，两边都不匹配的即缺驱动。
+pub fn coverage(
+    devices: &[DeviceIds],
+    repository: &[DriverPackage],
+    inbox: &[DriverPackage],
+) -> DriverCoverage {
+    let repo = select_drivers(devices, repository);
+    let rest = select_drivers(&repo.unmatched, inbox);
+    DriverCoverage {
+        from_repository: repo.matches,
+        inbox: rest.matches,
+        missing: rest.unmatched,
+    }
+}
+
+/// 离线系统自带的驱动包（`Windows\INF` 下的 INF）。
+pub fn inbox_packages(system_root: &Path, platform: Option<&InfPlatform>) -> Vec<DriverPackage> {
+    scan_packages(&system_root.join("Windows").join("INF"), platform)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inf::InfFile;
+
+    fn dev(id: &str) -> DeviceIds {
+        DeviceIds {
+            instance_id: format!("{}\\0", id),
+            description: id.to_string(),
+            class: "System".into(),
+            hardware_ids: vec![id.to_string()],
+            compatible_ids: vec![],
+            inf: Some("oem3.inf".into()),
+        }
+    }
+
+    fn pkg(path: &str, hwid: &str) -> DriverPackage {
+        let text = format!(
+            "[Version]\nCatalogFile=a.cat\n[Manufacturer]\nM=Models\n[Models]\nd=I, {}\n",
+            hwid
+        );
+        DriverPackage::from_inf(Path::new(path), &InfFile::parse(&text), None)
+    }
+
+    #[test]
+    fn manifest_round_trip_and_missing_fields() {
+        let m = HardwareManifest {
+            computer_name: "PC".into(),
+            created: 1_700_000_000,
+            devices: vec![dev("PCI\\VEN_10DE&DEV_2684")],
+        };
+        assert_eq!(HardwareManifest::from_json(&m.to_json()).unwrap(), m);
+        // 旧版 / 手写清单缺字段时取默认值
+        let partial =
+            HardwareManifest::from_json(r#"{"devices":[{"hardware_ids":["USB\\VID_1"]}]}"#)
+                .unwrap();
+        assert_eq!(partial.devices[0].hardware_ids, vec!["USB\\VID_1"]);
+        assert_eq!(partial.devices[0].inf, None);
+        assert!(HardwareManifest::from_json("not json").is_err());
+    }
+
+    #[test]
+    fn coverage_splits_repository_inbox_and_missing() {
+        let devices = [dev("PCI\\A"), dev("PCI\\B"), dev("PCI\\C")];
+        let c = coverage(
+            &devices,
+            &[pkg("repo.inf", "PCI\\A")],
+            &[pkg("inbox.inf", "PCI\\A"), pkg("machine.inf", "PCI\\B")],
+        );
+        assert_eq!(c.from_repository.len(), 1);
+        assert_eq!(c.from_repository[0].inf_path, Path::new("repo.inf"));
+        assert_eq!(c.inbox.len(), 1);
+        assert_eq!(c.inbox[0].inf_path, Path::new("machine.inf"));
+        assert_eq!(c.missing, vec![dev("PCI\\C")]);
+        let text = c.to_text();
+        assert!(text.contains("缺少驱动: 1"));
+        assert!(text.contains("硬件 ID: PCI\\C"));
+    }
+}
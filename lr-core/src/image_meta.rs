@@ -50,6 +50,8 @@ pub struct ImageInfo {
     pub major_version: Option<u16>,
     /// Windows 次版本号
     pub minor_version: Option<u16>,
+    /// 处理器架构（unattend.xml 的 `processorArchitecture` 取值，见 [`wim_arch_name`]）
+    pub architecture: Option<String>,
     /// 镜像类型
     pub image_type: WimImageType,
     /// 是否已验证可安装
@@ -110,6 +112,10 @@ fn parse_image_info_roxml(xml: &str) -> Option<Vec<ImageInfo>> {
         let description = node_text(image, "DESCRIPTION").unwrap_or_default();
         let major_version = node_text(image, "MAJOR").and_then(|s| s.parse::<u16>().ok());
         let minor_version = node_text(image, "MINOR").and_then(|s| s.parse::<u16>().ok());
+        let architecture = node_text(image, "ARCH")
+            .and_then(|s| s.parse::<u32>().ok())
+            .and_then(wim_arch_name)
+            .map(str::to_string);
         let name = build_image_name_node(image, &description, index);
 
         images.push(ImageInfo {
@@ -120,6 +126,7 @@ fn parse_image_info_roxml(xml: &str) -> Option<Vec<ImageInfo>> {
             description,
             major_version,
             minor_version,
+            architecture,
             image_type: WimImageType::Unknown,
             verified_installable: false,
         });
@@ -132,6 +139,18 @@ fn parse_image_info_roxml(xml: &str) -> Option<Vec<ImageInfo>> {
     }
 }
 
+/// WIM XML `<ARCH>` 取值（PROCESSOR_ARCHITECTURE_*）→ unattend.xml 的 `processorArchitecture`。
+pub fn wim_arch_name(code: u32) -> Option<&'static str> {
+    match code {
+        0 => Some("x86"),
+        5 => Some("arm"),
+        6 => Some("ia64"),
+        9 => Some("amd64"),
+        12 => Some("arm64"),
+        _ => None,
+    }
+}
+
 /// 在某节点的所有后代里查找第一个指定标签元素的文本（去空白、过滤空串）。
 fn node_text(node: roxmltree::Node, tag: &str) -> Option<String> {
     node.descendants()
@@ -290,6 +309,10 @@ fn parse_image_info_fallback(xml: &str) -> Vec<ImageInfo> {
         let description = extract_xml_tag(image_block, "DESCRIPTION").unwrap_or_default();
         let major_version = extract_version_number(image_block, "MAJOR");
         let minor_version = extract_version_number(image_block, "MINOR");
+        let architecture = extract_xml_tag(image_block, "ARCH")
+            .and_then(|s| s.parse::<u32>().ok())
+            .and_then(wim_arch_name)
+            .map(str::to_string);
         let name = build_image_name(image_block, &description, parsed_index);
 
         images.push(ImageInfo {
@@ -300,6 +323,7 @@ fn parse_image_info_fallback(xml: &str) -> Vec<ImageInfo> {
             description,
             major_version,
             minor_version,
+            architecture,
             image_type: WimImageType::Unknown,
             verified_installable: false,
         });
@@ -412,6 +436,7 @@ mod tests {
         assert_eq!(v[0].name, "Windows 10 Pro");
         assert_eq!(v[0].installation_type, "Client");
         assert_eq!(v[0].major_version, Some(10));
+        assert_eq!(v[0].architecture.as_deref(), Some("amd64"));
         assert_eq!(v[0].size_bytes, 4_000_000_000);
         assert_eq!(v[0].image_type, WimImageType::StandardInstall);
     }
@@ -537,6 +562,7 @@ mod tests {
             description: String::new(),
             major_version: major,
             minor_version: None,
+            architecture: None,
             image_type: WimImageType::Unknown,
             verified_installable: false,
         };
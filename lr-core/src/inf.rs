@@ -0,0 +1,916 @@
+//! 驱动 `.inf` 解析（两端共享）。
+//!
+//! 按 INF 语法逐行解析为节 → 行（`键 = 值1, 值2, ...`），处理引号（`""` 转义、引号内的
+//! `,` `;` `=` 不分隔）、行尾 `;` 注释、行尾 `\` 续行，以及同名节合并。在此之上提供：
+//! - [`InfVersion`]：`[Version]` 的 Class / ClassGuid / Provider / DriverVer / CatalogFile；
+//! - `%字符串%` 替换（[`InfFile::expand`]），优先取本地化的 `[Strings.<LANGID>]`，
+//!   再回退 `[Strings]`；`%%` 为字面 `%`，`%12%` 等目录 ID 原样保留；
+//! - [`InfManufacturer`]：`[Manufacturer]` 与 `NTamd64` / `NTx86` / `NTarm64.10.0...17763`
+//!   等目标修饰（[`TargetDecoration`]），按 [`InfPlatform`] 选型号节；
+//! - [`InfModel`]：型号行的描述、安装节、硬件 ID 与兼容 ID；
+//! - [`DdInstall`]：安装节（按架构解析 `.NTamd64` / `.NT` 修饰）的 `CopyFiles` 与
+//!   `.Services` 节里的 `AddService`。
+//!
+//! 文件编码（UTF-16LE / UTF-8 / ANSI·GBK）由 [`InfFile::parse_bytes`] 自动识别。
+
+use std::fmt;
+
+use crate::sif::SifDocument;
+
+/// 处理器架构（INF 修饰里的 `NTx86` / `NTamd64` / `NTarm64` ...）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InfArch {
+    X86,
+    Amd64,
+    Arm,
+    Arm64,
+    Ia64,
+}
+
+impl InfArch {
+    pub const ALL: [InfArch; 5] = [
+        InfArch::X86,
+        InfArch::Amd64,
+        InfArch::Arm,
+        InfArch::Arm64,
+        InfArch::Ia64,
+    ];
+
+    /// 修饰里的写法（小写）。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            InfArch::X86 => "x86",
+            InfArch::Amd64 => "amd64",
+            InfArch::Arm => "arm",
+            InfArch::Arm64 => "arm64",
+            InfArch::Ia64 => "ia64",
+        }
+    }
+
+    /// 大小写不敏感。
+    pub fn parse(s: &str) -> Option<InfArch> {
+        Self::ALL
+            .into_iter()
+            .find(|a| a.as_str().eq_ignore_ascii_case(s))
+    }
+}
+
+/// 目标平台：按它挑选型号节与安装节修饰。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct InfPlatform {
+    pub arch: InfArch,
+    pub major: u32,
+    pub minor: u32,
+    pub build: u32,
+}
+
+impl InfPlatform {
+    pub fn new(arch: InfArch, major: u32, minor: u32, build: u32) -> Self {
+        Self {
+            arch,
+            major,
+            minor,
+            build,
+        }
+    }
+}
+
+/// `[Manufacturer]` 中的目标修饰：
+/// `NT[Architecture][.[OSMajorVersion][.[OSMinorVersion][.[ProductType][.[SuiteMask][.[BuildNumber]]]]]]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TargetDecoration {
+    /// 原文（拼到型号节名后面用）。
+    pub raw: String,
+    pub arch: Option<InfArch>,
+    pub major: Option<u32>,
+    pub minor: Option<u32>,
+    pub product_type: Option<u32>,
+    pub suite_mask: Option<u32>,
+    pub build: Option<u32>,
+}
+
+impl TargetDecoration {
+    /// 不以 `NT` 开头、架构未知或数字段非法时返回 `None`。
+    pub fn parse(s: &str) -> Option<TargetDecoration> {
+        let s = s.trim();
+        if s.len() < 2 || !s[..2].eq_ignore_ascii_case("NT") {
+            return None;
+        }
+        let mut parts = s[2..].split('.');
+        let arch = match parts.next().unwrap_or("") {
+            "" => None,
+            a => Some(InfArch::parse(a)?),
+        };
+        let mut nums = [None; 5];
+        for slot in nums.iter_mut() {
+            match parts.next() {
+                Some("") | None => {}
+                Some(p) => *slot = Some(parse_number(p)?),
+            }
+        }
+        Some(TargetDecoration {
+            raw: s.to_string(),
+            arch,
+            major: nums[0],
+            minor: nums[1],
+            product_type: nums[2],
+            suite_mask: nums[3],
+            build: nums[4],
+        })
+    }
+
+    /// 是否适用于平台：架构相同（或未写），且系统版本 / 内部版本不低于修饰要求。
+    /// ProductType / SuiteMask 不参与判断。
+    pub fn matches(&self, p: &InfPlatform) -> bool {
+        if self.arch.is_some_and(|a| a != p.arch) {
+            return false;
+        }
+        let want = (
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.build.unwrap_or(0),
+        );
+        want <= (p.major, p.minor, p.build)
+    }
+
+    /// 多个修饰同时适用时，越具体越优先：带架构 > 不带，版本高者优先。
+    pub(crate) fn specificity(&self) -> (bool, u32, u32, u32) {
+        (
+            self.arch.is_some(),
+            self.major.unwrap_or(0),
+            self.minor.unwrap_or(0),
+            self.build.unwrap_or(0),
+        )
+    }
+}
+
+/// `DriverVer = mm/dd/yyyy[,w.x.y.z]`。排序先比日期再比版本（与 Windows 选驱动一致）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct DriverVer {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub version: [u16; 4],
+}
+
+impl DriverVer {
+    pub fn parse(s: &str) -> Option<DriverVer> {
+        let mut fields = s.splitn(2, ',');
+        let mut date = fields.next()?.trim().split(['/', '-']);
+        let month = date.next()?.trim().parse().ok()?;
+        let day = date.next()?.trim().parse().ok()?;
+        let year = date.next()?.trim().parse().ok()?;
+        if date.next().is_some() || !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+            return None;
+        }
+        let mut version = [0u16; 4];
+        if let Some(v) = fields.next().map(str::trim).filter(|v| !v.is_empty()) {
+            let parts: Vec<&str> = v.split('.').collect();
+            if parts.len() > 4 {
+                return None;
+            }
+            for (slot, part) in version.iter_mut().zip(parts) {
+                *slot = part.trim().parse().ok()?;
+            }
+        }
+        Some(DriverVer {
+            year,
+            month,
+            day,
+            version,
+        })
+    }
+}
+
+impl fmt::Display for DriverVer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [a, b, c, d] = self.version;
+        write!(
+            f,
+            "{:02}/{:02}/{:04},{}.{}.{}.{}",
+            self.month, self.day, self.year, a, b, c, d
+        )
+    }
+}
+
+/// `[Version]` 节（已做 `%字符串%` 替换）。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InfVersion {
+    pub signature: Option<String>,
+    pub class: Option<String>,
+    pub class_guid: Option<String>,
+    pub provider: Option<String>,
+    pub driver_ver: Option<DriverVer>,
+    /// 未修饰的 `CatalogFile`；按架构取见 [`InfFile::catalog_file`]。
+    pub catalog_file: Option<String>,
+}
+
+/// `[Manufacturer]` 的一行：`%Mfg% = Models[, NTamd64[, NTx86.6.1 ...]]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfManufacturer {
+    pub name: String,
+    pub models_section: String,
+    pub decorations: Vec<TargetDecoration>,
+}
+
+impl InfManufacturer {
+    /// 为平台挑选型号节：在适用的修饰里取最具体的一个；都不适用时，只有 x86 回退到
+    /// 未修饰节（64 位 / ARM 平台必须有对应架构修饰，否则该厂商不适用）。
+    pub fn models_section_for(&self, p: &InfPlatform) -> Option<String> {
+        self.decoration_for(p).map(|d| self.section_name(d))
+    }
+
+    /// 全部型号节：未修饰节 + 每个修饰节（不区分平台时用）。
+    pub fn all_models_sections(&self) -> Vec<String> {
+        self.all_decorations()
+            .map(|d| self.section_name(d))
+            .collect()
+    }
+
+    /// 为平台选中的修饰；`Some(None)` 表示回退到未修饰节。
+    fn decoration_for(&self, p: &InfPlatform) -> Option<Option<&TargetDecoration>> {
+        if let Some(d) = self
+            .decorations
+            .iter()
+            .filter(|d| d.matches(p))
+            .max_by(|a, b| a.specificity().cmp(&b.specificity()))
+        {
+            return Some(Some(d));
+        }
+        (p.arch == InfArch::X86).then_some(None)
+    }
+
+    fn all_decorations(&self) -> impl Iterator<Item = Option<&TargetDecoration>> {
+        std::iter::once(None).chain(self.decorations.iter().map(Some))
+    }
+
+    fn section_name(&self, d: Option<&TargetDecoration>) -> String {
+        match d {
+            Some(d) => format!("{}.{}", self.models_section, d.raw),
+            None => self.models_section.clone(),
+        }
+    }
+}
+
+/// 型号行：`描述 = 安装节, 硬件ID[, 兼容ID...]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfModel {
+    pub description: String,
+    pub install_section: String,
+    pub hardware_id: String,
+    pub compatible_ids: Vec<String>,
+    /// 所在型号节（含修饰）。
+    pub models_section: String,
+    /// 型号节的目标修饰（未修饰节为 `None`）。
+    pub target: Option<TargetDecoration>,
+}
+
+/// `AddService = 服务名, 标志[, 服务安装节[, 事件日志安装节]]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddService {
+    pub name: String,
+    pub flags: u32,
+    pub install_section: Option<String>,
+    pub event_log_section: Option<String>,
+}
+
+impl AddService {
+    /// `SPSVCINST_ASSOCSERVICE`：该服务是设备的功能驱动。
+    pub const ASSOC_SERVICE: u32 = 0x2;
+}
+
+/// 解析后的 DDInstall 节。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DdInstall {
+    /// 实际选中的节名（含 `.NTamd64` 等修饰）。
+    pub section: String,
+    /// `CopyFiles` 的各项：文件列表节名，或 `@文件名`。
+    pub copy_files: Vec<String>,
+    /// `<section>.Services` 里的 `AddService`。
+    pub services: Vec<AddService>,
+}
+
+/// INF 的一行（节标题除外）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InfLine {
+    /// `=` 左侧（去引号、去空白）；没有 `=` 的行为 `None`。
+    pub key: Option<String>,
+    /// `=` 右侧（或整行）按 `,` 切分、去引号、去空白后的各字段。
+    pub values: Vec<String>,
+    /// `=` 右侧原文（去注释、去首尾空白，引号保留），`[Strings]` 取值用。
+    pub raw_value: String,
+    /// 1 起始的行号（续行取首行）。
+    pub line: usize,
+}
+
+/// 解析后的 INF 文件。
+#[derive(Debug, Clone, Default)]
+pub struct InfFile {
+    /// 同名节（大小写不敏感）已合并，顺序为首次出现的顺序。
+    sections: Vec<(String, Vec<InfLine>)>,
+    /// 本地化字符串节的 LANGID（如 0x0804），见 [`InfFile::with_language`]。
+    language: Option<u16>,
+}
+
+impl InfFile {
+    /// 从原始字节解析（自动识别 UTF-16LE / UTF-8 / ANSI·GBK）。
+    pub fn parse_bytes(raw: &[u8]) -> InfFile {
+        Self::parse(&SifDocument::parse_bytes(raw).to_text())
+    }
+
+    pub fn parse(text: &str) -> InfFile {
+        let mut inf = InfFile::default();
+        let mut cur: Option<usize> = None;
+        let mut pending = String::new();
+        let mut pending_line = 0;
+        for (i, raw) in text.lines().enumerate() {
+            let body = strip_comment(raw).trim_end();
+            if pending.is_empty() {
+                pending_line = i + 1;
+            }
+            // 行尾 `\` 续行
+            if let Some(head) = body.strip_suffix('\\') {
+                pending.push_str(head);
+                continue;
+            }
+            pending.push_str(body);
+            let line = std::mem::take(&mut pending);
+            let t = line.trim();
+            if t.is_empty() {
+                continue;
+            }
+            if let Some(rest) = t.strip_prefix('[') {
+                let name = rest.split(']').next().unwrap_or("").trim().to_string();
+                cur = Some(match inf.section_index(&name) {
+                    Some(idx) => idx,
+                    None => {
+                        inf.sections.push((name, Vec::new()));
+                        inf.sections.len() - 1
+                    }
+                });
+                continue;
+            }
+            if let Some(idx) = cur {
+                inf.sections[idx].1.push(parse_line(t, pending_line));
+            }
+        }
+        inf
+    }
+
+    /// 指定本地化字符串节的 LANGID（如简体中文 0x0804），[`InfFile::expand`] 优先取
+    /// `[Strings.0804]`，其中没有的键再回退 `[Strings]`。
+    pub fn with_language(mut self, langid: u16) -> InfFile {
+        self.language = Some(langid);
+        self
+    }
+
+    fn section_index(&self, name: &str) -> Option<usize> {
+        self.sections
+            .iter()
+            .position(|(n, _)| n.eq_ignore_ascii_case(name))
+    }
+
+    /// 所有节名（首次出现的写法）。
+    pub fn section_names(&self) -> impl Iterator<Item = &str> {
+        self.sections.iter().map(|(n, _)| n.as_str())
+    }
+
+    pub fn has_section(&self, name: &str) -> bool {
+        self.section_index(name).is_some()
+    }
+
+    /// 节内所有行（节名大小写不敏感；不存在时为空）。
+    pub fn lines(&self, section: &str) -> &[InfLine] {
+        self.section_index(section)
+            .map(|i| self.sections[i].1.as_slice())
+            .unwrap_or(&[])
+    }
+
+    /// 节内第一个键为 `key` 的行的第一个字段（未替换）。
+    pub fn value(&self, section: &str, key: &str) -> Option<&str> {
+        self.lines(section)
+            .iter()
+            .find(|l| {
+                l.key
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case(key))
+            })
+            .and_then(|l| l.values.first())
+            .map(String::as_str)
+    }
+
+    /// 查字符串表（键大小写不敏感）：本地化节优先，再 `[Strings]`。
+    pub fn string(&self, key: &str) -> Option<String> {
+        let localized = self.language.map(|l| format!("Strings.{:04x}", l));
+        localized
+            .iter()
+            .map(String::as_str)
+            .chain(std::iter::once("Strings"))
+            .find_map(|sec| {
+                self.lines(sec).iter().find(|l| {
+                    l.key
+                        .as_deref()
+                        .is_some_and(|k| k.eq_ignore_ascii_case(key))
+                })
+            })
+            .map(|l| unquote_string(&l.raw_value))
+    }
+
+    /// `%key%` 替换。`%%` → `%`；找不到的键（含 `%12%` 等目录 ID）原样保留。
+    pub fn expand(&self, s: &str) -> String {
+        let mut out = String::with_capacity(s.len());
+        let mut rest = s;
+        while let Some(start) = rest.find('%') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            let Some(end) = after.find('%') else {
+                out.push_str(&rest[start..]);
+                return out;
+            };
+            let key = &after[..end];
+            match key {
+                "" => out.push('%'),
+                _ => match self.string(key) {
+                    Some(v) => out.push_str(&v),
+                    None => {
+                        out.push('%');
+                        out.push_str(key);
+                        out.push('%');
+                    }
+                },
+            }
+            rest = &after[end + 1..];
+        }
+        out.push_str(rest);
+        out
+    }
+
+    /// `[Version]`。
+    pub fn version(&self) -> InfVersion {
+        let get = |key: &str| {
+            self.value("Version", key)
+                .map(|v| self.expand(v))
+                .filter(|v| !v.is_empty())
+        };
+        InfVersion {
+            signature: get("Signature"),
+            class: get("Class"),
+            class_guid: get("ClassGuid"),
+            provider: get("Provider"),
+            driver_ver: self.lines("Version").iter().find_map(|l| {
+                l.key
+                    .as_deref()
+                    .filter(|k| k.eq_ignore_ascii_case("DriverVer"))
+                    .and_then(|_| DriverVer::parse(&self.expand(&l.values.join(","))))
+            }),
+            catalog_file: get("CatalogFile"),
+        }
+    }
+
+    /// 按架构取 `CatalogFile.NT<arch>`，其次 `CatalogFile.NT`、`CatalogFile`。
+    pub fn catalog_file(&self, arch: InfArch) -> Option<String> {
+        [
+            format!("CatalogFile.NT{}", arch.as_str()),
+            "CatalogFile.NT".to_string(),
+            "CatalogFile".to_string(),
+        ]
+        .iter()
+        .find_map(|k| self.value("Version", k))
+        .map(|v| self.expand(v))
+    }
+
+    /// `[Manufacturer]` 各行。
+    pub fn manufacturers(&self) -> Vec<InfManufacturer> {
+        self.lines("Manufacturer")
+            .iter()
+            .filter_map(|l| {
+                let models_section = l.values.first().filter(|v| !v.is_empty())?.clone();
+                let name = self.expand(l.key.as_deref().unwrap_or(&models_section));
+                Some(InfManufacturer {
+                    name,
+                    decorations: l.values[1..]
+                        .iter()
+                        .filter_map(|d| TargetDecoration::parse(d))
+                        .collect(),
+                    models_section,
+                })
+            })
+            .collect()
+    }
+
+    /// 型号行。`platform` 为 `None` 时列出所有型号节（未修饰 + 全部修饰）里的型号；
+    /// 否则只取各厂商为该平台选中的型号节。
+    pub fn models(&self, platform: Option<&InfPlatform>) -> Vec<InfModel> {
+        let mut out = Vec::new();
+        for m in self.manufacturers() {
+            let targets: Vec<Option<&TargetDecoration>> = match platform {
+                Some(p) => m.decoration_for(p).into_iter().collect(),
+                None => m.all_decorations().collect(),
+            };
+            for target in targets {
+                let sec = m.section_name(target);
+                for l in self.lines(&sec) {
+                    let (Some(key), Some(install), Some(hwid)) =
+                        (l.key.as_deref(), l.values.first(), l.values.get(1))
+                    else {
+                        continue;
+                    };
+                    if install.is_empty() || hwid.is_empty() {
+                        continue;
+                    }
+                    out.push(InfModel {
+                        description: self.expand(key),
+                        install_section: install.clone(),
+                        hardware_id: self.expand(hwid),
+                        compatible_ids: l.values[2..]
+                            .iter()
+                            .filter(|c| !c.is_empty())
+                            .map(|c| self.expand(c))
+                            .collect(),
+                        models_section: sec.clone(),
+                        target: target.cloned(),
+                    });
+                }
+            }
+        }
+        out
+    }
+
+    /// 解析 DDInstall 节：依次尝试 `<name>.NT<arch>`、`<name>.NT`、`<name>`（`arch` 为 `None`
+    /// 时跳过第一项），取第一个存在（本身或其 `.Services` 节存在）的。都不存在返回 `None`。
+    pub fn ddinstall(&self, name: &str, arch: Option<InfArch>) -> Option<DdInstall> {
+        let candidates = arch
+            .map(|a| format!("{}.NT{}", name, a.as_str()))
+            .into_iter()
+            .chain([format!("{}.NT", name), name.to_string()]);
+        let section = candidates
+            .into_iter()
+            .find(|c| self.has_section(c) || self.has_section(&format!("{}.Services", c)))?;
+        let copy_files = self
+            .lines(&section)
+            .iter()
+            .filter(|l| {
+                l.key
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case("CopyFiles"))
+            })
+            .flat_map(|l| l.values.iter().filter(|v| !v.is_empty()).cloned())
+            .collect();
+        let services = self
+            .lines(&format!("{}.Services", section))
+            .iter()
+            .filter(|l| {
+                l.key
+                    .as_deref()
+                    .is_some_and(|k| k.eq_ignore_ascii_case("AddService"))
+            })
+            .filter_map(|l| {
+                let name = l.values.first().filter(|v| !v.is_empty())?.clone();
+                let field = |i: usize| l.values.get(i).filter(|v| !v.is_empty()).cloned();
+                Some(AddService {
+                    name,
+                    flags: l.values.get(1).and_then(|f| parse_number(f)).unwrap_or(0),
+                    install_section: field(2),
+                    event_log_section: field(3),
+                })
+            })
+            .collect();
+        Some(DdInstall {
+            section,
+            copy_files,
+            services,
+        })
+    }
+
+    /// `CopyFiles` 展开后的目标文件名：`@file` 直接取，其余按文件列表节逐行取首字段。
+    pub fn copy_file_names(&self, dd: &DdInstall) -> Vec<String> {
+        let mut out: Vec<String> = Vec::new();
+        for entry in &dd.copy_files {
+            let names: Vec<String> = match entry.strip_prefix('@') {
+                Some(file) => vec![file.trim().to_string()],
+                None => self
+                    .lines(entry)
+                    .iter()
+                    .filter_map(|l| l.values.first().or(l.key.as_ref()))
+                    .filter(|n| !n.is_empty())
+                    .map(|n| self.expand(n))
+                    .collect(),
+            };
+            for n in names {
+                if !out.iter().any(|x| x.eq_ignore_ascii_case(&n)) {
+                    out.push(n);
+                }
+            }
+        }
+        out
+    }
+
+    /// 服务安装节里 `ServiceBinary` 的文件名（去掉 `%12%\` 等目录前缀）。
+    pub fn service_binary(&self, svc: &AddService) -> Option<String> {
+        let sec = svc.install_section.as_deref()?;
+        let path = self.expand(self.value(sec, "ServiceBinary")?);
+        path.rsplit(['\\', '/'])
+            .next()
+            .map(str::trim)
+            .filter(|n| !n.is_empty())
+            .map(str::to_string)
+    }
+}
+
+/// 去掉引号外的 `;` 注释。
+fn strip_comment(line: &str) -> &str {
+    let mut in_quotes = false;
+    for (i, c) in line.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ';' if !in_quotes => return &line[..i],
+            _ => {}
+        }
+    }
+    line
+}
+
+/// 切分一行：引号外第一个 `=` 分键值，引号外的 `,` 分字段；字段去引号（`""` → `"`）、去空白。
+fn parse_line(text: &str, line: usize) -> InfLine {
+    let mut in_quotes = false;
+    let mut eq = None;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            '=' if !in_quotes => {
+                eq = Some(i);
+                break;
+            }
+            _ => {}
+        }
+    }
+    let (key, rhs) = match eq {
+        Some(i) => (Some(unquote_string(text[..i].trim())), text[i + 1..].trim()),
+        None => (None, text),
+    };
+    InfLine {
+        key,
+        values: split_fields(rhs),
+        raw_value: rhs.to_string(),
+        line,
+    }
+}
+
+fn split_fields(s: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut cur = String::new();
+    let mut in_quotes = false;
+    let mut chars = s.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '"' if in_quotes && chars.peek() == Some(&'"') => {
+                chars.next();
+                cur.push('"');
+            }
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => out.push(std::mem::take(&mut cur).trim().to_string()),
+            _ => cur.push(c),
+        }
+    }
+    out.push(cur.trim().to_string());
+    out
+}
+
+/// 整段去引号（`[Strings]` 的值可含 `,`，不能按字段切）。
+fn unquote_string(s: &str) -> String {
+    let s = s.trim();
+    if s.contains('"') {
+        split_fields(s).join(",")
+    } else {
+        s.to_string()
+    }
+}
+
+/// 十进制或 `0x` 十六进制。
+fn parse_number(s: &str) -> Option<u32> {
+    let s = s.trim();
+    match s.get(..2) {
+        Some(p) if p.eq_ignore_ascii_case("0x") => u32::from_str_radix(&s[2..], 16).ok(),
+        _ => s.parse().ok(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 仿 Intel 网卡 INF：多架构修饰、OS 版本目标、本地化字符串、续行、引号内逗号。
+    const NET_INF: &str = r#"; Intel network sample
+[Version]
+Signature   = "$WINDOWS NT$"
+Class       = Net
+ClassGUID   = {4d36e972-e325-11ce-bfc1-08002be10318}
+Provider    = %Intel%
+CatalogFile.NTamd64 = e1d68x64.cat
+CatalogFile = e1d6832.cat
+DriverVer   = 06/19/2023,12.19.2.45
+
+[Manufacturer]
+%Intel%     = Intel, NTx86, NTamd64, NTamd64.10.0...17763, NTarm64
+
+[Intel.NTx86]
+%E15F.DeviceDesc% = E15F.ndi, PCI\VEN_8086&DEV_15F3&SUBSYS_00008086, PCI\VEN_8086&DEV_15F3
+
+[Intel.NTamd64]
+%E15F.DeviceDesc% = E15F.ndi.Legacy, PCI\VEN_8086&DEV_15F3
+
+[Intel.NTamd64.10.0...17763]
+%E15F.DeviceDesc% = E15F.ndi, \
+    PCI\VEN_8086&DEV_15F3&SUBSYS_00008086, PCI\VEN_8086&DEV_15F3 ; trailing comment
+
+[E15F.ndi.NTamd64]
+CopyFiles = Win10.CopyFiles, @e1dmsg.dll
+
+[E15F.ndi.NTamd64.Services]
+AddService = e1dexpress, 0x00000002, e1d.Service, e1d.EventLog
+
+[E15F.ndi]
+CopyFiles = Legacy.CopyFiles
+
+[E15F.ndi.Services]
+AddService = e1dlegacy, 2, e1d.Service
+
+[Win10.CopyFiles]
+e1d68x64.sys,,,2
+e1d68x64.din
+
+[Legacy.CopyFiles]
+e1d6832.sys
+
+[e1d.Service]
+ServiceType   = 1
+ServiceBinary = %13%\e1d68x64.sys
+
+[Strings]
+Intel = "Intel Corporation"
+E15F.DeviceDesc = "Intel(R) Ethernet Connection (5) I219-LM, ""Gigabit"""
+
+[Strings.0804]
+E15F.DeviceDesc = "英特尔(R) 以太网连接 I219-LM"
+"#;
+
+    fn inf() -> InfFile {
+        InfFile::parse(NET_INF)
+    }
+
+    #[test]
+    fn version_section() {
+        let v = inf().version();
+        assert_eq!(v.signature.as_deref(), Some("$WINDOWS NT$"));
+        assert_eq!(v.class.as_deref(), Some("Net"));
+        assert_eq!(
+            v.class_guid.as_deref(),
+            Some("{4d36e972-e325-11ce-bfc1-08002be10318}")
+        );
+        assert_eq!(v.provider.as_deref(), Some("Intel Corporation"));
+        let dv = v.driver_ver.unwrap();
+        assert_eq!((dv.year, dv.month, dv.day), (2023, 6, 19));
+        assert_eq!(dv.version, [12, 19, 2, 45]);
+        assert_eq!(dv.to_string(), "06/19/2023,12.19.2.45");
+        assert_eq!(v.catalog_file.as_deref(), Some("e1d6832.cat"));
+        assert_eq!(
+            inf().catalog_file(InfArch::Amd64).as_deref(),
+            Some("e1d68x64.cat")
+        );
+        assert_eq!(
+            inf().catalog_file(InfArch::X86).as_deref(),
+            Some("e1d6832.cat")
+        );
+    }
+
+    #[test]
+    fn strings_localized_and_escaped() {
+        let f = inf();
+        assert_eq!(
+            f.string("e15f.devicedesc").as_deref(),
+            Some("Intel(R) Ethernet Connection (5) I219-LM, \"Gigabit\"")
+        );
+        let zh = inf().with_language(0x0804);
+        assert_eq!(
+            zh.string("E15F.DeviceDesc").as_deref(),
+            Some("英特尔(R) 以太网连接 I219-LM")
+        );
+        // 本地化节没有的键回退 [Strings]
+        assert_eq!(zh.expand("%Intel%").as_str(), "Intel Corporation");
+        assert_eq!(f.expand("100%% %13%\\x %nope%"), "100% %13%\\x %nope%");
+    }
+
+    #[test]
+    fn manufacturer_decorations() {
+        let m = &inf().manufacturers()[0];
+        assert_eq!(m.name, "Intel Corporation");
+        assert_eq!(m.decorations.len(), 4);
+        let d = &m.decorations[2];
+        assert_eq!(d.arch, Some(InfArch::Amd64));
+        assert_eq!(
+            (d.major, d.minor, d.build),
+            (Some(10), Some(0), Some(17763))
+        );
+        assert_eq!(d.product_type, None);
+
+        let win10 = InfPlatform::new(InfArch::Amd64, 10, 0, 19045);
+        let ltsb = InfPlatform::new(InfArch::Amd64, 10, 0, 14393);
+        let win7_32 = InfPlatform::new(InfArch::X86, 6, 1, 7601);
+        assert_eq!(
+            m.models_section_for(&win10).as_deref(),
+            Some("Intel.NTamd64.10.0...17763")
+        );
+        assert_eq!(
+            m.models_section_for(&ltsb).as_deref(),
+            Some("Intel.NTamd64")
+        );
+        assert_eq!(
+            m.models_section_for(&win7_32).as_deref(),
+            Some("Intel.NTx86")
+        );
+        assert!(TargetDecoration::parse("Win10").is_none());
+        assert!(TargetDecoration::parse("NTmips").is_none());
+    }
+
+    #[test]
+    fn models_with_continuation_and_compatible_ids() {
+        let f = inf();
+        let win10 = InfPlatform::new(InfArch::Amd64, 10, 0, 19045);
+        let models = f.models(Some(&win10));
+        assert_eq!(models.len(), 1);
+        let m = &models[0];
+        assert_eq!(m.install_section, "E15F.ndi");
+        assert_eq!(m.hardware_id, "PCI\\VEN_8086&DEV_15F3&SUBSYS_00008086");
+        assert_eq!(m.compatible_ids, vec!["PCI\\VEN_8086&DEV_15F3"]);
+        assert_eq!(m.target.as_ref().and_then(|t| t.build), Some(17763));
+        assert!(m.description.starts_with("Intel(R) Ethernet"));
+        // 不分平台：所有修饰节里的型号（NTarm64 节不存在，不报错）
+        assert_eq!(f.models(None).len(), 3);
+        // ARM64 声明了修饰但没有型号节
+        assert!(f
+            .models(Some(&InfPlatform::new(InfArch::Arm64, 10, 0, 22000)))
+            .is_empty());
+    }
+
+    #[test]
+    fn ddinstall_copy_files_and_services() {
+        let f = inf();
+        let dd = f.ddinstall("E15F.ndi", Some(InfArch::Amd64)).unwrap();
+        assert_eq!(dd.section, "E15F.ndi.NTamd64");
+        assert_eq!(dd.copy_files, vec!["Win10.CopyFiles", "@e1dmsg.dll"]);
+        assert_eq!(
+            f.copy_file_names(&dd),
+            vec!["e1d68x64.sys", "e1d68x64.din", "e1dmsg.dll"]
+        );
+        assert_eq!(dd.services.len(), 1);
+        let svc = &dd.services[0];
+        assert_eq!(svc.name, "e1dexpress");
+        assert_eq!(
+            svc.flags & AddService::ASSOC_SERVICE,
+            AddService::ASSOC_SERVICE
+        );
+        assert_eq!(svc.install_section.as_deref(), Some("e1d.Service"));
+        assert_eq!(svc.event_log_section.as_deref(), Some("e1d.EventLog"));
+        assert_eq!(f.service_binary(svc).as_deref(), Some("e1d68x64.sys"));
+
+        // x86 没有 .NTx86 修饰，回退到未修饰节
+        let legacy = f.ddinstall("E15F.ndi", Some(InfArch::X86)).unwrap();
+        assert_eq!(legacy.section, "E15F.ndi");
+        assert_eq!(legacy.services[0].name, "e1dlegacy");
+        assert!(f.ddinstall("Missing", None).is_none());
+    }
+
+    #[test]
+    fn quoting_comments_and_duplicate_sections() {
+        let f =
+            InfFile::parse("[A]\nKey = \"a;b\", \"c,d\" ; note\n[a]\nOther=1\n[B]\nbare, line\n");
+        assert_eq!(f.lines("A").len(), 2);
+        assert_eq!(f.lines("a")[0].values, vec!["a;b", "c,d"]);
+        assert_eq!(f.value("A", "other"), Some("1"));
+        let bare = &f.lines("B")[0];
+        assert_eq!(bare.key, None);
+        assert_eq!(bare.values, vec!["bare", "line"]);
+        assert_eq!(bare.line, 6);
+    }
+
+    #[test]
+    fn utf16_inf_is_decoded() {
+        let text = "[Version]\r\nClass=Display\r\nProvider=%NV%\r\n[Strings]\r\nNV=\"NVIDIA\"\r\n";
+        let mut raw = vec![0xFF, 0xFE];
+        raw.extend(text.encode_utf16().flat_map(u16::to_le_bytes));
+        let v = InfFile::parse_bytes(&raw).version();
+        assert_eq!(v.class.as_deref(), Some("Display"));
+        assert_eq!(v.provider.as_deref(), Some("NVIDIA"));
+    }
+
+    #[test]
+    fn driver_ver_ordering_and_errors() {
+        let a = DriverVer::parse("1/2/2020,1.0.0.5").unwrap();
+        let b = DriverVer::parse("01/02/2020, 1.0.0.10").unwrap();
+        let c = DriverVer::parse("12/31/2019,99.0.0.0").unwrap();
+        assert!(a < b && c < a);
+        assert_eq!(DriverVer::parse("06/21/2006").unwrap().version, [0; 4]);
+        assert!(DriverVer::parse("13/01/2020").is_none());
+        assert!(DriverVer::parse("1.2.3.4").is_none());
+    }
+}
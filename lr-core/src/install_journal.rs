@@ -0,0 +1,527 @@
+//! PE 安装日志（journal）：把安装流程拆成明确的步骤并把进度落盘，中断后可续做。
+//!
+//! 日志 [`JOURNAL_FILE`] 放在数据分区的数据目录里（目标分区会被格式化），每个步骤开始、
+//! 完成或失败都立即写盘（先写临时文件再改名，断电时不会留下半截 JSON）。日志同时记下
+//! 安装配置的哈希、镜像指纹（路径 + 大小 + 修改时间）与各步骤的输入，重启后只有这些都
+//! 对得上才允许续做（输入由 [`InstallJournal::check_inputs`] 核对）。
+//!
+//! 续做不是简单地「从第一个没做完的步骤开始」：释放镜像做了一半，目标分区里是半套系统，
+//! 必须回到格式化重来；驱动导入、修复引导等步骤可以原地重做（[`InstallPhase::restart_point`]）。
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+/// 日志文件名。
+pub const JOURNAL_FILE: &str = "install_journal.json";
+
+const JOURNAL_VERSION: u32 = 1;
+
+/// 安装步骤（按执行顺序）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InstallPhase {
+    VerifyImage,
+    Diskpart,
+    Format,
+    ApplyImage,
+    ImportDrivers,
+    InstallCab,
+    RepairBoot,
+    AdvancedOptions,
+    Unattend,
+    Cleanup,
+}
+
+impl InstallPhase {
+    pub const ALL: [InstallPhase; 10] = [
+        InstallPhase::VerifyImage,
+        InstallPhase::Diskpart,
+        InstallPhase::Format,
+        InstallPhase::ApplyImage,
+        InstallPhase::ImportDrivers,
+        InstallPhase::InstallCab,
+        InstallPhase::RepairBoot,
+        InstallPhase::AdvancedOptions,
+        InstallPhase::Unattend,
+        InstallPhase::Cleanup,
+    ];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            InstallPhase::VerifyImage => "校验镜像",
+            InstallPhase::Diskpart => "运行 Diskpart 脚本",
+            InstallPhase::Format => "格式化分区",
+            InstallPhase::ApplyImage => "释放镜像",
+            InstallPhase::ImportDrivers => "导入驱动",
+            InstallPhase::InstallCab => "安装更新包",
+            InstallPhase::RepairBoot => "修复引导",
+            InstallPhase::AdvancedOptions => "应用高级选项",
+            InstallPhase::Unattend => "生成无人值守配置",
+            InstallPhase::Cleanup => "清理临时文件",
+        }
+    }
+
+    /// 本次安装实际要执行的步骤：GHO 不做镜像校验，未启用时不跑 Diskpart 脚本。
+    pub fn planned(verify_image: bool, run_diskpart: bool) -> Vec<InstallPhase> {
+        InstallPhase::ALL
+            .into_iter()
+            .filter(|p| match p {
+                InstallPhase::VerifyImage => verify_image,
+                InstallPhase::Diskpart => run_diskpart,
+                _ => true,
+            })
+            .collect()
+    }
+
+    /// 本步骤没做完时，从哪一步重做才安全：释放镜像中断要回到格式化，其余原地重做。
+    pub fn restart_point(self) -> InstallPhase {
+        match self {
+            InstallPhase::ApplyImage => InstallPhase::Format,
+            other => other,
+        }
+    }
+
+    /// 是否在镜像释放之后（这些步骤依赖目标分区里已有完整系统）。
+    pub fn after_apply(self) -> bool {
+        self > InstallPhase::ApplyImage
+    }
+}
+
+/// 步骤状态。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StepState {
+    Running,
+    Done,
+    Failed,
+}
+
+/// 一个步骤的记录。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StepRecord {
+    pub phase: InstallPhase,
+    pub state: StepState,
+    /// 开始 / 结束时间（Unix 秒）。
+    pub started: u64,
+    pub finished: Option<u64>,
+    /// 步骤输入（如镜像卷索引、注入的驱动目录），续做时据此核对。
+    #[serde(default)]
+    pub inputs: BTreeMap<String, String>,
+    #[serde(default)]
+    pub message: String,
+}
+
+/// 文件指纹：路径 + 大小 + 修改时间（镜像动辄数 GB，不做全量哈希；完整性由校验步骤负责）。
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileFingerprint {
+    pub path: String,
+    pub size: u64,
+    pub modified: u64,
+}
+
+impl FileFingerprint {
+    pub fn of(path: &Path) -> std::io::Result<Self> {
+        let meta = std::fs::metadata(path)?;
+        Ok(FileFingerprint {
+            path: path.to_string_lossy().into_owned(),
+            size: meta.len(),
+            modified: meta
+                .modified()
+                .ok()
+                .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+        })
+    }
+}
+
+/// 安装日志。
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InstallJournal {
+    pub version: u32,
+    pub created: u64,
+    pub updated: u64,
+    /// 安装配置文件内容的 SHA-256。
+    pub config_hash: String,
+    pub image: FileFingerprint,
+    /// 目标分区（如 `C:`）。
+    pub target_partition: String,
+    /// 本次运行计划执行的步骤（未启用的步骤不在其中）。
+    pub plan: Vec<InstallPhase>,
+    pub steps: Vec<StepRecord>,
+    /// 续做次数。
+    #[serde(default)]
+    pub resumes: u32,
+}
+
+/// 中断的安装：从哪一步续做、上次停在哪一步。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumePoint {
+    pub from: InstallPhase,
+    /// 上次没做完（进行中 / 失败）的步骤。
+    pub interrupted: InstallPhase,
+}
+
+/// 续做时保留的步骤记下的输入与本次运行不一致（如目标分区换了），不能续做。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InputMismatch {
+    pub phase: InstallPhase,
+    pub key: String,
+    pub recorded: String,
+    pub current: String,
+}
+
+impl fmt::Display for InputMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "步骤「{}」记录的 {} 为 {}，本次为 {}",
+            self.phase.label(),
+            self.key,
+            self.recorded,
+            self.current
+        )
+    }
+}
+
+impl std::error::Error for InputMismatch {}
+
+fn now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+impl InstallJournal {
+    pub fn new(
+        config_hash: &str,
+        image: FileFingerprint,
+        target_partition: &str,
+        plan: &[InstallPhase],
+    ) -> Self {
+        let mut plan = plan.to_vec();
+        plan.sort();
+        plan.dedup();
+        let t = now();
+        InstallJournal {
+            version: JOURNAL_VERSION,
+            created: t,
+            updated: t,
+            config_hash: config_hash.to_string(),
+            image,
+            target_partition: target_partition.to_string(),
+            plan,
+            steps: Vec::new(),
+            resumes: 0,
+        }
+    }
+
+    /// 日志是否属于这份配置与镜像（配置改过或镜像换了就不能续做）。
+    pub fn matches(&self, config_hash: &str, image: &FileFingerprint) -> bool {
+        self.version == JOURNAL_VERSION
+            && self.config_hash.eq_ignore_ascii_case(config_hash)
+            && self.image == *image
+    }
+
+    pub fn record(&self, phase: InstallPhase) -> Option<&StepRecord> {
+        self.steps.iter().find(|s| s.phase == phase)
+    }
+
+    pub fn is_done(&self, phase: InstallPhase) -> bool {
+        self.record(phase)
+            .is_some_and(|s| s.state == StepState::Done)
+    }
+
+    /// 某步骤记录的输入。
+    pub fn input(&self, phase: InstallPhase, key: &str) -> Option<&str> {
+        self.record(phase)
+            .and_then(|s| s.inputs.get(key))
+            .map(String::as_str)
+    }
+
+    /// 步骤开始（重做时覆盖旧记录）。
+    pub fn begin(&mut self, phase: InstallPhase, inputs: &[(&str, &str)]) {
+        self.steps.retain(|s| s.phase != phase);
+        self.steps.push(StepRecord {
+            phase,
+            state: StepState::Running,
+            started: now(),
+            finished: None,
+            inputs: inputs
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            message: String::new(),
+        });
+        self.steps.sort_by_key(|s| s.phase);
+    }
+
+    /// 给进行中的步骤补记输入（如按硬件匹配后实际注入的驱动目录）。
+    pub fn set_input(&mut self, phase: InstallPhase, key: &str, value: &str) {
+        if let Some(s) = self.steps.iter_mut().find(|s| s.phase == phase) {
+            s.inputs.insert(key.to_string(), value.to_string());
+        }
+    }
+
+    pub fn complete(&mut self, phase: InstallPhase) {
+        self.finish(phase, StepState::Done, "");
+    }
+
+    pub fn fail(&mut self, phase: InstallPhase, message: &str) {
+        self.finish(phase, StepState::Failed, message);
+    }
+
+    fn finish(&mut self, phase: InstallPhase, state: StepState, message: &str) {
+        if let Some(s) = self.steps.iter_mut().find(|s| s.phase == phase) {
+            s.state = state;
+            s.finished = Some(now());
+            s.message = message.to_string();
+        }
+    }
+
+    /// 所有计划步骤是否都已完成。
+    pub fn is_complete(&self) -> bool {
+        self.plan.iter().all(|&p| self.is_done(p))
+    }
+
+    /// 中断后的续做点：第一个没完成的计划步骤，回退到它的安全重做点；全部完成或尚未开始
+    /// 任何步骤时返回 `None`（后者重新开始即可）。
+    pub fn resume_point(&self) -> Option<ResumePoint> {
+        if self.steps.is_empty() {
+            return None;
+        }
+        let interrupted = self.plan.iter().copied().find(|&p| !self.is_done(p))?;
+        let safe = interrupted.restart_point();
+        // 安全重做点可能不在计划里（如未启用的步骤），取计划中不早于它的第一步
+        let from = self
+            .plan
+            .iter()
+            .copied()
+            .find(|&p| p >= safe)
+            .unwrap_or(interrupted);
+        Some(ResumePoint { from, interrupted })
+    }
+
+    /// 核对从 `from` 续做时保留下来的步骤（`from` 之前）记下的输入与本次运行的取值 `current`
+    /// （步骤, 输入名, 取值；大小写不敏感）。步骤没记这项输入时不核对；`from` 及之后的步骤会重做，也不核对。
+    pub fn check_inputs(
+        &self,
+        from: InstallPhase,
+        current: &[(InstallPhase, &str, String)],
+    ) -> Result<(), InputMismatch> {
+        for (phase, key, value) in current {
+            if *phase >= from {
+                continue;
+            }
+            match self.input(*phase, key) {
+                Some(recorded) if !recorded.eq_ignore_ascii_case(value) => {
+                    return Err(InputMismatch {
+                        phase: *phase,
+                        key: key.to_string(),
+                        recorded: recorded.to_string(),
+                        current: value.clone(),
+                    })
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 从 `from` 续做：清掉 `from` 及之后步骤的旧记录，之前的步骤视为已完成。
+    pub fn prepare_resume(&mut self, from: InstallPhase) {
+        self.steps.retain(|s| s.phase < from);
+        self.resumes += 1;
+    }
+
+    /// `phase` 在本次运行中是否需要执行（在计划里且不早于起点）。
+    pub fn should_run(&self, phase: InstallPhase, from: InstallPhase) -> bool {
+        phase >= from && self.plan.contains(&phase)
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    pub fn from_json(text: &str) -> Result<Self, String> {
+        serde_json::from_str(text).map_err(|e| e.to_string())
+    }
+
+    /// 写入 `dir`\[`JOURNAL_FILE`]：先写临时文件并落盘，再改名替换。
+    pub fn save(&mut self, dir: &Path) -> std::io::Result<PathBuf> {
+        use std::io::Write;
+
+        self.updated = now();
+        std::fs::create_dir_all(dir)?;
+        let path = dir.join(JOURNAL_FILE);
+        let tmp = dir.join(format!("{}.tmp", JOURNAL_FILE));
+        {
+            let mut f = std::fs::File::create(&tmp)?;
+            f.write_all(self.to_json().as_bytes())?;
+            f.sync_all()?;
+        }
+        std::fs::rename(&tmp, &path)?;
+        Ok(path)
+    }
+
+    /// 读取 `dir`\[`JOURNAL_FILE`]；不存在或无法解析时返回 `None`（解析失败记警告）。
+    pub fn load(dir: &Path) -> Option<Self> {
+        let path = dir.join(JOURNAL_FILE);
+        let text = std::fs::read_to_string(&path).ok()?;
+        match Self::from_json(&text) {
+            Ok(j) => Some(j),
+            Err(e) => {
+                log::warn!("[Journal] 安装日志解析失败 {:?}: {}", path, e);
+                None
+            }
+        }
+    }
+
+    /// 删除 `dir` 里的日志。
+    pub fn remove(dir: &Path) {
+        let _ = std::fs::remove_file(dir.join(JOURNAL_FILE));
+        let _ = std::fs::remove_file(dir.join(format!("{}.tmp", JOURNAL_FILE)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn journal() -> InstallJournal {
+        let image = FileFingerprint {
+            path: "D:\\LetRecovery_Data\\install.wim".into(),
+            size: 4_000_000_000,
+            modified: 1_700_000_000,
+        };
+        InstallJournal::new(
+            "ABCD",
+            image,
+            "C:",
+            &[
+                InstallPhase::VerifyImage,
+                InstallPhase::Format,
+                InstallPhase::ApplyImage,
+                InstallPhase::ImportDrivers,
+                InstallPhase::RepairBoot,
+                InstallPhase::Cleanup,
+            ],
+        )
+    }
+
+    #[test]
+    fn resume_point_falls_back_to_safe_step() {
+        let mut j = journal();
+        assert_eq!(j.resume_point(), None);
+
+        for p in [
+            InstallPhase::VerifyImage,
+            InstallPhase::Format,
+            InstallPhase::ApplyImage,
+            InstallPhase::ImportDrivers,
+        ] {
+            j.begin(p, &[]);
+            j.complete(p);
+        }
+        j.begin(InstallPhase::RepairBoot, &[("uefi", "1")]);
+        // 修复引导中断：原地重做，不必重新释放镜像
+        let rp = j.resume_point().unwrap();
+        assert_eq!(rp.from, InstallPhase::RepairBoot);
+        assert_eq!(rp.interrupted, InstallPhase::RepairBoot);
+        assert_eq!(j.input(InstallPhase::RepairBoot, "uefi"), Some("1"));
+        assert!(j.should_run(InstallPhase::Cleanup, rp.from));
+        assert!(!j.should_run(InstallPhase::InstallCab, rp.from));
+        assert!(!j.should_run(InstallPhase::ApplyImage, rp.from));
+
+        // 释放镜像中断：回到格式化
+        let mut j = journal();
+        for p in [InstallPhase::VerifyImage, InstallPhase::Format] {
+            j.begin(p, &[]);
+            j.complete(p);
+        }
+        j.begin(InstallPhase::ApplyImage, &[("index", "1")]);
+        j.fail(InstallPhase::ApplyImage, "断电");
+        let rp = j.resume_point().unwrap();
+        assert_eq!(rp.from, InstallPhase::Format);
+        assert_eq!(rp.interrupted, InstallPhase::ApplyImage);
+
+        j.prepare_resume(rp.from);
+        assert!(j.is_done(InstallPhase::VerifyImage));
+        assert!(j.record(InstallPhase::Format).is_none());
+        assert_eq!(j.resumes, 1);
+
+        for p in j.plan.clone() {
+            j.begin(p, &[]);
+            j.complete(p);
+        }
+        assert!(j.is_complete());
+        assert_eq!(j.resume_point(), None);
+    }
+
+    #[test]
+    fn resume_checks_inputs_of_kept_steps() {
+        let mut j = journal();
+        j.begin(InstallPhase::Format, &[("target", "C:")]);
+        j.complete(InstallPhase::Format);
+        j.begin(InstallPhase::ApplyImage, &[("volume_index", "2")]);
+        j.complete(InstallPhase::ApplyImage);
+        j.begin(
+            InstallPhase::ImportDrivers,
+            &[("driver_dir", "D:\\Data\\drivers")],
+        );
+
+        let current = |target: &str, index: &str| {
+            vec![
+                (InstallPhase::Format, "target", target.to_string()),
+                (InstallPhase::ApplyImage, "volume_index", index.to_string()),
+                (
+                    InstallPhase::ImportDrivers,
+                    "driver_dir",
+                    "E:\\Data\\drivers".to_string(),
+                ),
+            ]
+        };
+        let from = InstallPhase::ImportDrivers;
+        assert_eq!(j.check_inputs(from, &current("c:", "2")), Ok(()));
+        // 目标分区换了：已释放的系统不在新目标上，不能续做
+        let err = j.check_inputs(from, &current("E:", "2")).unwrap_err();
+        assert_eq!(
+            (err.phase, err.key.as_str()),
+            (InstallPhase::Format, "target")
+        );
+        assert_eq!(err.recorded, "C:");
+        assert!(j.check_inputs(from, &current("C:", "3")).is_err());
+        // 要重做的步骤（驱动目录变了）与回到格式化重来时都不核对
+        assert_eq!(
+            j.check_inputs(InstallPhase::Format, &current("E:", "3")),
+            Ok(())
+        );
+    }
+
+    #[test]
+    fn save_load_and_match() {
+        let dir = std::env::temp_dir().join(format!("lr_journal_{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        let mut j = journal();
+        j.begin(InstallPhase::VerifyImage, &[]);
+        j.complete(InstallPhase::VerifyImage);
+        j.save(&dir).unwrap();
+        assert!(!dir.join(format!("{}.tmp", JOURNAL_FILE)).exists());
+
+        let loaded = InstallJournal::load(&dir).unwrap();
+        assert_eq!(loaded.steps, j.steps);
+        assert!(loaded.matches("abcd", &j.image));
+        let mut other = j.image.clone();
+        other.size += 1;
+        assert!(!loaded.matches("abcd", &other));
+        assert!(!loaded.matches("ffff", &j.image));
+
+        InstallJournal::remove(&dir);
+        assert!(InstallJournal::load(&dir).is_none());
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -6,26 +6,61 @@
 //!
 //! 后续计划收纳：镜像元数据类型 + XML 解析、wimlib FFI 封装等
 //! （见仓库 TESTING.md）。
+//!
+//! 直接封装 Win32 DLL 的模块（驱动管理、WIMGAPI / wimlib 及其引擎切换）只在 Windows 上编译，
+//! 其余模块在 Linux 上也能构建并运行单元测试。
 
+pub mod arc;
 pub mod bl_passthrough;
 pub mod boot;
 pub mod command;
 pub mod diskpart;
+pub mod diskpart_output;
+pub mod diskpart_script;
+#[cfg(windows)]
 pub mod driver;
+pub mod driver_archive;
+pub mod driver_match;
+pub mod driver_repo;
+pub mod driver_report;
 pub mod encoding;
+pub mod expand_plan;
 pub mod fveapi;
 pub mod hash;
+pub mod hw_manifest;
 pub mod image_meta;
+pub mod inf;
+pub mod install_journal;
+pub mod offline_tweaks;
+pub mod partition_backup;
+pub mod partition_plan;
+pub mod partition_preset;
+pub mod partition_table;
+pub mod partition_writer;
+pub mod plan;
+pub mod product_key;
 pub mod reboot;
 pub mod registry;
 pub mod sam;
+pub mod sif;
+pub mod target_identity;
+pub mod unattend;
+pub mod unattend_check;
+#[cfg(windows)]
 pub mod wim_engine;
+#[cfg(windows)]
 pub mod wimgapi;
+#[cfg(windows)]
 pub mod wimlib;
 pub mod wimlib_dll;
+pub mod winnt_sif;
 pub mod xp;
 pub mod xp_i386;
 pub mod xp_textmode_drv;
 
+#[cfg(test)]
+mod test_util;
+
+#[cfg(windows)]
 pub use wim_engine::{active_engine, set_active_engine, WimEngine, WimEngineManager};
 pub use wimlib_dll::ensure_dll_available;
@@ -0,0 +1,332 @@
+//! 离线系统优化（两端共享）：高级选项中纯注册表类优化项的改动表。
+//!
+//! 以前 PE 端与正常系统端各自内联一份 `reg add` 序列；现在统一由这里的静态表描述，
+//! 既用于实际写入离线注册表，也用于执行计划（见 `plan`）列出将要修改的键值。
+//!
+//! 约定的离线配置单元挂载名：`pc-soft`(SOFTWARE) / `pc-sys`(SYSTEM) / `pc-default`(DEFAULT)。
+
+use crate::registry::OfflineRegistry;
+
+/// DEFAULT 配置单元挂载点前缀（加载失败时跳过写入该配置单元的改动）
+const DEFAULT_HIVE_PREFIX: &str = "HKLM\\pc-default\\";
+
+const CLASSIC_MENU_KEY_DEFAULT: &str =
+    "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32";
+const CLASSIC_MENU_KEY_SOFT: &str =
+    "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32";
+
+/// 单条注册表改动的值
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RegValue {
+    /// 仅创建键
+    Key,
+    Dword(u32),
+    String(&'static str),
+}
+
+/// 单条注册表改动
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RegTweak {
+    pub key: &'static str,
+    /// 值名；空串表示默认值
+    pub name: &'static str,
+    pub value: RegValue,
+}
+
+impl RegTweak {
+    const fn dword(key: &'static str, name: &'static str, data: u32) -> Self {
+        Self {
+            key,
+            name,
+            value: RegValue::Dword(data),
+        }
+    }
+
+    const fn string(key: &'static str, name: &'static str, data: &'static str) -> Self {
+        Self {
+            key,
+            name,
+            value: RegValue::String(data),
+        }
+    }
+
+    const fn key(key: &'static str) -> Self {
+        Self {
+            key,
+            name: "",
+            value: RegValue::Key,
+        }
+    }
+
+    /// 是否写入 DEFAULT 配置单元
+    pub fn in_default_hive(&self) -> bool {
+        self.key.starts_with(DEFAULT_HIVE_PREFIX)
+    }
+
+    /// 人类可读描述，例如 `HKLM\pc-soft\...\OOBE [BypassNRO] = DWORD 1`
+    pub fn describe(&self) -> String {
+        let name = if self.name.is_empty() {
+            "(默认)"
+        } else {
+            self.name
+        };
+        match self.value {
+            RegValue::Key => format!("{} (创建键)", self.key),
+            RegValue::Dword(v) => format!("{} [{}] = DWORD {}", self.key, name, v),
+            RegValue::String(s) => format!("{} [{}] = \"{}\"", self.key, name, s),
+        }
+    }
+
+    /// 写入离线注册表（配置单元需已加载）
+    pub fn apply(&self) -> anyhow::Result<()> {
+        match self.value {
+            RegValue::Key => OfflineRegistry::create_key(self.key),
+            RegValue::Dword(v) => OfflineRegistry::set_dword(self.key, self.name, v),
+            RegValue::String(s) => OfflineRegistry::set_string(self.key, self.name, s),
+        }
+    }
+}
+
+const REMOVE_SHORTCUT_ARROW: &[RegTweak] = &[RegTweak::string(
+    "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Shell Icons",
+    "29",
+    "%systemroot%\\system32\\imageres.dll,197",
+)];
+
+const RESTORE_CLASSIC_CONTEXT_MENU: &[RegTweak] = &[
+    // 空的 InprocServer32 默认值会禁用 Win11 新式右键菜单
+    RegTweak::key(CLASSIC_MENU_KEY_DEFAULT),
+    RegTweak::string(CLASSIC_MENU_KEY_DEFAULT, "", ""),
+    RegTweak::key(CLASSIC_MENU_KEY_SOFT),
+    RegTweak::string(CLASSIC_MENU_KEY_SOFT, "", ""),
+];
+
+const BYPASS_NRO: &[RegTweak] = &[RegTweak::dword(
+    "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE",
+    "BypassNRO",
+    1,
+)];
+
+const DISABLE_WINDOWS_UPDATE: &[RegTweak] = &[
+    RegTweak::dword(
+        "HKLM\\pc-sys\\ControlSet001\\Services\\wuauserv",
+        "Start",
+        4,
+    ),
+    RegTweak::dword("HKLM\\pc-sys\\ControlSet001\\Services\\UsoSvc", "Start", 4),
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Policies\\Microsoft\\Windows\\WindowsUpdate\\AU",
+        "NoAutoUpdate",
+        1,
+    ),
+];
+
+const DISABLE_WINDOWS_DEFENDER: &[RegTweak] = &[
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender",
+        "DisableAntiSpyware",
+        1,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender\\Real-Time Protection",
+        "DisableRealtimeMonitoring",
+        1,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-sys\\ControlSet001\\Services\\WinDefend",
+        "Start",
+        4,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-sys\\ControlSet001\\Services\\WdNisSvc",
+        "Start",
+        4,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-sys\\ControlSet001\\Services\\SecurityHealthService",
+        "Start",
+        4,
+    ),
+];
+
+const DISABLE_RESERVED_STORAGE: &[RegTweak] = &[
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
+        "ShippedWithReserves",
+        0,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
+        "PassedPolicy",
+        0,
+    ),
+];
+
+const DISABLE_UAC: &[RegTweak] = &[
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
+        "EnableLUA",
+        0,
+    ),
+    RegTweak::dword(
+        "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
+        "ConsentPromptBehaviorAdmin",
+        0,
+    ),
+];
+
+const DISABLE_DEVICE_ENCRYPTION: &[RegTweak] = &[
+    RegTweak::dword(
+        "HKLM\\pc-sys\\ControlSet001\\Control\\BitLocker",
+        "PreventDeviceEncryption",
+        1,
+    ),
+    // MBAM (Microsoft BitLocker Administration and Monitoring)
+    RegTweak::dword("HKLM\\pc-soft\\Policies\\Microsoft\\FVE", "OSRecovery", 0),
+    RegTweak::dword("HKLM\\pc-sys\\ControlSet001\\Services\\BDESVC", "Start", 4),
+];
+
+/// 纯注册表类优化项
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tweak {
+    RemoveShortcutArrow,
+    RestoreClassicContextMenu,
+    BypassNro,
+    DisableWindowsUpdate,
+    DisableWindowsDefender,
+    DisableReservedStorage,
+    DisableUac,
+    DisableDeviceEncryption,
+}
+
+impl Tweak {
+    pub fn label(self) -> &'static str {
+        match self {
+            Tweak::RemoveShortcutArrow => "移除快捷方式小箭头",
+            Tweak::RestoreClassicContextMenu => "恢复经典右键菜单",
+            Tweak::BypassNro => "OOBE绕过强制联网",
+            Tweak::DisableWindowsUpdate => "禁用Windows更新",
+            Tweak::DisableWindowsDefender => "禁用Windows Defender",
+            Tweak::DisableReservedStorage => "禁用系统保留空间",
+            Tweak::DisableUac => "禁用UAC",
+            Tweak::DisableDeviceEncryption => "禁用自动设备加密",
+        }
+    }
+
+    /// 该优化项的全部注册表改动
+    pub fn changes(self) -> &'static [RegTweak] {
+        match self {
+            Tweak::RemoveShortcutArrow => REMOVE_SHORTCUT_ARROW,
+            Tweak::RestoreClassicContextMenu => RESTORE_CLASSIC_CONTEXT_MENU,
+            Tweak::BypassNro => BYPASS_NRO,
+            Tweak::DisableWindowsUpdate => DISABLE_WINDOWS_UPDATE,
+            Tweak::DisableWindowsDefender => DISABLE_WINDOWS_DEFENDER,
+            Tweak::DisableReservedStorage => DISABLE_RESERVED_STORAGE,
+            Tweak::DisableUac => DISABLE_UAC,
+            Tweak::DisableDeviceEncryption => DISABLE_DEVICE_ENCRYPTION,
+        }
+    }
+
+    /// 写入全部改动（尽力而为：单条失败只记日志）。
+    ///
+    /// `default_loaded` 为 false 时跳过写入 DEFAULT 配置单元的改动。
+    pub fn apply(self, default_loaded: bool) {
+        log::info!("[ADVANCED] {}", self.label());
+        for change in self.changes() {
+            if change.in_default_hive() && !default_loaded {
+                continue;
+            }
+            if let Err(e) = change.apply() {
+                log::warn!("[ADVANCED] 写入失败 {}: {}", change.describe(), e);
+            }
+        }
+    }
+}
+
+/// 高级选项中与注册表优化相关的开关
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TweakOptions {
+    pub remove_shortcut_arrow: bool,
+    pub restore_classic_context_menu: bool,
+    pub bypass_nro: bool,
+    pub disable_windows_update: bool,
+    pub disable_windows_defender: bool,
+    pub disable_reserved_storage: bool,
+    pub disable_uac: bool,
+    pub disable_device_encryption: bool,
+}
+
+impl TweakOptions {
+    /// 按固定顺序列出已勾选的优化项
+    pub fn selected(&self) -> Vec<Tweak> {
+        [
+            (self.remove_shortcut_arrow, Tweak::RemoveShortcutArrow),
+            (
+                self.restore_classic_context_menu,
+                Tweak::RestoreClassicContextMenu,
+            ),
+            (self.bypass_nro, Tweak::BypassNro),
+            (self.disable_windows_update, Tweak::DisableWindowsUpdate),
+            (self.disable_windows_defender, Tweak::DisableWindowsDefender),
+            (self.disable_reserved_storage, Tweak::DisableReservedStorage),
+            (self.disable_uac, Tweak::DisableUac),
+            (
+                self.disable_device_encryption,
+                Tweak::DisableDeviceEncryption,
+            ),
+        ]
+        .into_iter()
+        .filter_map(|(on, t)| on.then_some(t))
+        .collect()
+    }
+
+    /// 依次写入全部已勾选的优化项
+    pub fn apply(&self, default_loaded: bool) {
+        for tweak in self.selected() {
+            tweak.apply(default_loaded);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::command::{with_runner, FakeRunner};
+    use std::sync::Arc;
+
+    #[test]
+    fn selected_keeps_fixed_order() {
+        let opts = TweakOptions {
+            disable_uac: true,
+            bypass_nro: true,
+            ..Default::default()
+        };
+        assert_eq!(opts.selected(), vec![Tweak::BypassNro, Tweak::DisableUac]);
+        assert!(TweakOptions::default().selected().is_empty());
+    }
+
+    #[test]
+    fn classic_menu_touches_default_hive() {
+        let changes = Tweak::RestoreClassicContextMenu.changes();
+        assert_eq!(changes.iter().filter(|c| c.in_default_hive()).count(), 2);
+        assert!(changes[1].describe().contains("[(默认)] = \"\""));
+        assert_eq!(
+            Tweak::BypassNro.changes()[0].describe(),
+            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE [BypassNRO] = DWORD 1"
+        );
+    }
+
+    #[test]
+    fn apply_skips_unloaded_default_hive() {
+        let fake = Arc::new(FakeRunner::new());
+        with_runner(fake.clone(), || {
+            Tweak::RestoreClassicContextMenu.apply(false);
+        });
+        let lines = fake.lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines
+            .iter()
+            .all(|l| l.starts_with("reg add HKLM\\pc-soft\\Classes")));
+        assert!(lines[1].ends_with("/v  /t REG_SZ /d  /f"));
+    }
+}
@@ -0,0 +1,899 @@
+//! 分区表备份与还原（两端共享）。
+//!
+//! 一键分区会 `clean` 整块磁盘，无损扩容会改写分区表项；任何一步出错，原来的布局就找不回来了。
+//! 破坏性操作前先把分区表原样存下来：MBR 扇区、EBR 链、GPT 主表（LBA 1 起到第一个可用扇区前）
+//! 与备份表（最后一个可用扇区之后到盘尾），外加一份 `layout.json` 描述磁盘标识、容量和分区列表。
+//!
+//! 每份备份是一个目录：
+//!
+//! ```text
+//! <盘符>:\LetRecovery_PartitionBackup\disk<N>_<unix 时间>\
+//!     layout.json  mbr.bin  ebr_<lba>.bin ...  gpt_primary.bin  gpt_backup.bin
+//! ```
+//!
+//! 还原时只写回这些扇区，并按磁盘签名/GUID 与容量确认是同一块盘。
+//!
+//! 分区表已损坏（往往正是要清盘的原因）时解析不出布局，改为原样备份盘首、盘尾各 34 个扇区
+//! （`raw_head.bin` / `raw_tail.bin`，覆盖 MBR 与 GPT 主备两份），还原时不核对分区列表。
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::arc;
+use crate::diskpart_script::TableStyle;
+use crate::hash::sha256_bytes;
+use crate::partition_table::{read_partition_table, PartitionTable, TableError, GPT_SIGNATURE};
+
+/// 备份格式版本
+pub const BACKUP_VERSION: u32 = 1;
+/// 备份根目录名（位于各分区根目录下）
+pub const BACKUP_DIR_NAME: &str = "LetRecovery_PartitionBackup";
+/// 描述文件名
+pub const DESCRIPTION_FILE: &str = "layout.json";
+
+/// 备份中的一段原始扇区
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RegionKind {
+    Mbr,
+    Ebr,
+    GptPrimary,
+    GptBackup,
+    /// 分区表无法解析时原样备份的盘首扇区（含 MBR）
+    RawHead,
+    /// 分区表无法解析时原样备份的盘尾扇区
+    RawTail,
+}
+
+/// 一段原始扇区及其校验值
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupRegion {
+    pub kind: RegionKind,
+    /// 备份目录中的文件名
+    pub file: String,
+    pub lba: u64,
+    pub sectors: u64,
+    pub sha256: String,
+}
+
+/// 备份时的分区（仅供查看与还原后核对）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupPartition {
+    pub number: u32,
+    /// MBR 为 `0x07` 形式，GPT 为类型 GUID
+    pub partition_type: String,
+    pub offset: u64,
+    pub length: u64,
+    #[serde(default)]
+    pub active: bool,
+    #[serde(default)]
+    pub logical: bool,
+    #[serde(default)]
+    pub name: String,
+}
+
+/// `layout.json` 的内容
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BackupDescription {
+    pub version: u32,
+    /// 备份时间（Unix 秒）
+    pub created: u64,
+    /// 触发备份的操作（如 `quick_partition`、`expand_move`）
+    #[serde(default)]
+    pub reason: String,
+    pub disk_number: u32,
+    pub sector_size: u32,
+    pub disk_size: u64,
+    pub style: TableStyle,
+    /// MBR 磁盘签名（8 位十六进制）或 GPT 磁盘 GUID；原始备份为空
+    pub disk_id: String,
+    /// 分区表损坏时的原始扇区备份（没有分区列表，`style` 按保护性 MBR 推断）
+    #[serde(default)]
+    pub raw: bool,
+    pub partitions: Vec<BackupPartition>,
+    pub regions: Vec<BackupRegion>,
+}
+
+impl BackupDescription {
+    /// 判断目标磁盘是否就是备份时的那块盘
+    ///
+    /// `table` 为目标磁盘当前的分区表（未初始化或已损坏时传 `None`）。
+    pub fn match_target(
+        &self,
+        sector_size: u32,
+        disk_size: u64,
+        table: Option<&PartitionTable>,
+    ) -> TargetMatch {
+        if self.sector_size != sector_size || self.disk_size != disk_size {
+            return TargetMatch::SizeMismatch;
+        }
+        match table {
+            None => TargetMatch::Blank,
+            Some(t) if t.disk_id.to_string() == self.disk_id => TargetMatch::Same,
+            Some(_) => TargetMatch::IdChanged,
+        }
+    }
+}
+
+/// 一份完整的分区表备份：描述 + 与 `regions` 一一对应的原始数据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionBackup {
+    pub description: BackupDescription,
+    pub data: Vec<Vec<u8>>,
+}
+
+/// 目标磁盘与备份的匹配程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TargetMatch {
+    /// 容量与磁盘签名/GUID 都一致
+    Same,
+    /// 容量一致，磁盘上没有分区表（例如已被 `clean`）
+    Blank,
+    /// 容量一致，但磁盘签名/GUID 不同（可能已被重新分区，也可能是另一块同容量的盘）
+    IdChanged,
+    /// 扇区大小或容量不同，不能还原
+    SizeMismatch,
+}
+
+/// 备份 / 还原错误
+#[derive(Debug)]
+pub enum BackupError {
+    Io(io::Error),
+    /// 读取分区表失败
+    Table(TableError),
+    /// 磁盘没有分区表，无需备份
+    NoTable,
+    /// 备份文件缺失、大小不符或校验失败
+    Corrupt(String),
+    /// 不支持的备份格式版本
+    UnsupportedVersion(u32),
+    /// `layout.json` 解析失败
+    Json(String),
+    /// 目标磁盘与备份不匹配
+    Mismatch(TargetMatch),
+    /// 写回后重新读取的分区表与备份描述不符
+    Verify(String),
+    /// 找不到可以保存备份的位置
+    NoLocation,
+}
+
+impl fmt::Display for BackupError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BackupError::Io(e) => write!(f, "读写失败: {}", e),
+            BackupError::Table(e) => write!(f, "{}", e),
+            BackupError::NoTable => write!(f, "磁盘没有分区表"),
+            BackupError::Corrupt(e) => write!(f, "分区表备份已损坏: {}", e),
+            BackupError::UnsupportedVersion(v) => write!(f, "不支持的分区表备份版本: {}", v),
+            BackupError::Json(e) => write!(f, "分区表备份描述文件格式错误: {}", e),
+            BackupError::Mismatch(TargetMatch::SizeMismatch) => {
+                write!(f, "目标磁盘的容量或扇区大小与备份不符")
+            }
+            BackupError::Mismatch(_) => write!(f, "目标磁盘的签名/GUID 与备份不符"),
+            BackupError::Verify(e) => write!(f, "还原后校验失败: {}", e),
+            BackupError::NoLocation => write!(f, "找不到可以保存分区表备份的位置"),
+        }
+    }
+}
+
+impl std::error::Error for BackupError {}
+
+impl From<io::Error> for BackupError {
+    fn from(e: io::Error) -> Self {
+        BackupError::Io(e)
+    }
+}
+
+impl From<TableError> for BackupError {
+    fn from(e: TableError) -> Self {
+        BackupError::Table(e)
+    }
+}
+
+fn read_sectors<R: Read + Seek>(r: &mut R, lba: u64, sectors: u64, ss: u64) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; (sectors * ss) as usize];
+    r.seek(SeekFrom::Start(lba * ss))?;
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn describe_partitions(table: &PartitionTable) -> Vec<BackupPartition> {
+    table
+        .volumes()
+        .map(|p| BackupPartition {
+            number: p.number,
+            partition_type: p.partition_type.to_string(),
+            offset: p.offset,
+            length: p.length,
+            active: p.active,
+            logical: p.logical,
+            name: p.name.clone(),
+        })
+        .collect()
+}
+
+/// 读取磁盘（或磁盘镜像）上的分区表并备份到内存
+///
+/// 磁盘没有分区表时返回 [`BackupError::NoTable`]；分区表损坏时返回读取错误，由调用方决定是否继续。
+pub fn capture<R: Read + Seek>(
+    r: &mut R,
+    disk_number: u32,
+    sector_size: u32,
+    disk_size: u64,
+    reason: &str,
+) -> Result<PartitionBackup, BackupError> {
+    let table = read_partition_table(r, sector_size, disk_size)?.ok_or(BackupError::NoTable)?;
+    let ss = u64::from(sector_size);
+
+    let mut spans: Vec<(RegionKind, String, u64, u64)> =
+        vec![(RegionKind::Mbr, "mbr.bin".to_string(), 0, 1)];
+    match (table.style, table.gpt) {
+        (TableStyle::Gpt, Some(header)) => {
+            // 主表：LBA 1（主头）到第一个可用扇区之前；备份表：最后一个可用扇区之后到盘尾
+            let disk_sectors = disk_size / ss;
+            spans.push((
+                RegionKind::GptPrimary,
+                "gpt_primary.bin".to_string(),
+                1,
+                header.first_usable_lba - 1,
+            ));
+            let backup_start = header.last_usable_lba + 1;
+            if backup_start < disk_sectors {
+                spans.push((
+                    RegionKind::GptBackup,
+                    "gpt_backup.bin".to_string(),
+                    backup_start,
+                    disk_sectors - backup_start,
+                ));
+            }
+        }
+        _ => {
+            for lba in arc::ebr_chain(r, sector_size)
+                .map_err(|e| BackupError::Table(TableError::Mbr(e)))?
+            {
+                spans.push((RegionKind::Ebr, format!("ebr_{}.bin", lba), lba, 1));
+            }
+        }
+    }
+
+    let mut regions = Vec::with_capacity(spans.len());
+    let mut data = Vec::with_capacity(spans.len());
+    for (kind, file, lba, sectors) in spans {
+        let bytes = read_sectors(r, lba, sectors, ss)?;
+        regions.push(BackupRegion {
+            kind,
+            file,
+            lba,
+            sectors,
+            sha256: sha256_bytes(&bytes),
+        });
+        data.push(bytes);
+    }
+
+    Ok(PartitionBackup {
+        description: BackupDescription {
+            version: BACKUP_VERSION,
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            reason: reason.to_string(),
+            disk_number,
+            sector_size,
+            disk_size,
+            style: table.style,
+            disk_id: table.disk_id.to_string(),
+            raw: false,
+            partitions: describe_partitions(&table),
+            regions,
+        },
+        data,
+    })
+}
+
+/// 原始备份在盘首、盘尾各保存的扇区数（保护性 MBR + GPT 头 + 128 项分区数组）
+const RAW_SECTORS: u64 = 34;
+
+/// 分区表无法解析时，原样备份盘首与盘尾的扇区
+pub fn capture_raw<R: Read + Seek>(
+    r: &mut R,
+    disk_number: u32,
+    sector_size: u32,
+    disk_size: u64,
+    reason: &str,
+) -> Result<PartitionBackup, BackupError> {
+    let ss = u64::from(sector_size);
+    let disk_sectors = disk_size / ss;
+    let head = RAW_SECTORS.min(disk_sectors);
+    let mut spans = vec![(RegionKind::RawHead, "raw_head.bin", 0, head)];
+    if disk_sectors >= 2 * RAW_SECTORS {
+        spans.push((
+            RegionKind::RawTail,
+            "raw_tail.bin",
+            disk_sectors - RAW_SECTORS,
+            RAW_SECTORS,
+        ));
+    }
+
+    let mut regions = Vec::with_capacity(spans.len());
+    let mut data = Vec::with_capacity(spans.len());
+    for (kind, file, lba, sectors) in spans {
+        let bytes = read_sectors(r, lba, sectors, ss)?;
+        regions.push(BackupRegion {
+            kind,
+            file: file.to_string(),
+            lba,
+            sectors,
+            sha256: sha256_bytes(&bytes),
+        });
+        data.push(bytes);
+    }
+    // 保护性 MBR 的第一个分区类型为 0xEE
+    let style = if data[0].get(450) == Some(&0xEE) {
+        TableStyle::Gpt
+    } else {
+        TableStyle::Mbr
+    };
+
+    Ok(PartitionBackup {
+        description: BackupDescription {
+            version: BACKUP_VERSION,
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            reason: reason.to_string(),
+            disk_number,
+            sector_size,
+            disk_size,
+            style,
+            disk_id: String::new(),
+            raw: true,
+            partitions: Vec::new(),
+            regions,
+        },
+        data,
+    })
+}
+
+impl PartitionBackup {
+    /// 保存到 `root`（通常为 `<盘符>:\LetRecovery_PartitionBackup`）下的新目录，返回该目录
+    pub fn save(&self, root: &Path) -> io::Result<PathBuf> {
+        let d = &self.description;
+        let mut dir = root.join(format!("disk{}_{}", d.disk_number, d.created));
+        let mut n = 1;
+        while dir.exists() {
+            n += 1;
+            dir = root.join(format!("disk{}_{}_{}", d.disk_number, d.created, n));
+        }
+        std::fs::create_dir_all(&dir)?;
+        for (region, bytes) in d.regions.iter().zip(&self.data) {
+            std::fs::write(dir.join(&region.file), bytes)?;
+        }
+        let json = serde_json::to_string_pretty(d).map_err(io::Error::other)?;
+        std::fs::write(dir.join(DESCRIPTION_FILE), json)?;
+        Ok(dir)
+    }
+
+    /// 从备份目录加载，并逐个校验扇区文件的大小与 SHA-256
+    pub fn load(dir: &Path) -> Result<Self, BackupError> {
+        let description = read_description(dir)?;
+        let ss = u64::from(description.sector_size);
+        let mut data = Vec::with_capacity(description.regions.len());
+        for region in &description.regions {
+            let bytes = std::fs::read(dir.join(&region.file))
+                .map_err(|e| BackupError::Corrupt(format!("{}: {}", region.file, e)))?;
+            if bytes.len() as u64 != region.sectors * ss {
+                return Err(BackupError::Corrupt(format!("{}: 大小不符", region.file)));
+            }
+            if sha256_bytes(&bytes) != region.sha256 {
+                return Err(BackupError::Corrupt(format!("{}: 校验值不符", region.file)));
+            }
+            data.push(bytes);
+        }
+        if !description
+            .regions
+            .iter()
+            .any(|r| matches!(r.kind, RegionKind::Mbr | RegionKind::RawHead))
+        {
+            return Err(BackupError::Corrupt("缺少 MBR 扇区".to_string()));
+        }
+        Ok(Self { description, data })
+    }
+
+    /// 把备份的扇区写回磁盘（或磁盘镜像），并重新读取分区表核对分区列表
+    ///
+    /// 写入顺序：GPT 备份表 → GPT 主表 → EBR 链 → MBR，保证中途失败时盘上仍有一份可识别的表。
+    /// 还原 MBR 备份时先清掉盘上残留的 GPT 主头（LBA 1）与备份头（最后一个扇区），
+    /// 否则备份后被转成 GPT 的盘写回 MBR 后仍会被固件 / Windows 当作 GPT 盘。
+    /// 是否允许写入由调用方先用 [`BackupDescription::match_target`] 判断。
+    /// 原始备份写回后若仍读不出分区表（备份时就已损坏）返回 `None`。
+    pub fn restore<D: Read + Write + Seek>(
+        &self,
+        disk: &mut D,
+    ) -> Result<Option<PartitionTable>, BackupError> {
+        let d = &self.description;
+        let ss = u64::from(d.sector_size);
+        let order = [
+            RegionKind::GptBackup,
+            RegionKind::RawTail,
+            RegionKind::GptPrimary,
+            RegionKind::Ebr,
+            RegionKind::RawHead,
+            RegionKind::Mbr,
+        ];
+        if d.style == TableStyle::Mbr && !d.raw {
+            clear_gpt_headers(disk, ss, d.disk_size / ss)?;
+        }
+        for kind in order {
+            for (region, bytes) in d.regions.iter().zip(&self.data) {
+                if region.kind == kind {
+                    disk.seek(SeekFrom::Start(region.lba * ss))?;
+                    disk.write_all(bytes)?;
+                }
+            }
+        }
+        disk.flush()?;
+
+        if d.raw {
+            return Ok(read_partition_table(disk, d.sector_size, d.disk_size)
+                .ok()
+                .flatten());
+        }
+        let table = read_partition_table(disk, d.sector_size, d.disk_size)?
+            .ok_or_else(|| BackupError::Verify("写回后读不到分区表".to_string()))?;
+        if table.disk_id.to_string() != d.disk_id {
+            return Err(BackupError::Verify("磁盘签名/GUID 不符".to_string()));
+        }
+        if describe_partitions(&table) != d.partitions {
+            return Err(BackupError::Verify("分区列表与备份描述不符".to_string()));
+        }
+        Ok(Some(table))
+    }
+}
+
+/// 把 LBA 1 与最后一个扇区上带 `EFI PART` 签名的 GPT 头清零（不带签名的扇区可能属于分区，不动）
+fn clear_gpt_headers<D: Read + Write + Seek>(
+    disk: &mut D,
+    ss: u64,
+    disk_sectors: u64,
+) -> io::Result<()> {
+    for lba in [1, disk_sectors.saturating_sub(1)] {
+        if lba == 0 {
+            continue;
+        }
+        let sector = read_sectors(disk, lba, 1, ss)?;
+        if sector.starts_with(GPT_SIGNATURE) {
+            disk.seek(SeekFrom::Start(lba * ss))?;
+            disk.write_all(&vec![0u8; ss as usize])?;
+        }
+    }
+    Ok(())
+}
+
+fn read_description(dir: &Path) -> Result<BackupDescription, BackupError> {
+    let json = std::fs::read_to_string(dir.join(DESCRIPTION_FILE))?;
+    let description: BackupDescription =
+        serde_json::from_str(&json).map_err(|e| BackupError::Json(e.to_string()))?;
+    if description.version != BACKUP_VERSION {
+        return Err(BackupError::UnsupportedVersion(description.version));
+    }
+    Ok(description)
+}
+
+/// 列出 `root` 下的所有备份（只读描述文件，不校验扇区数据），按时间从新到旧
+pub fn list_backups(root: &Path) -> Vec<(PathBuf, BackupDescription)> {
+    let Ok(entries) = std::fs::read_dir(root) else {
+        return Vec::new();
+    };
+    let mut out: Vec<(PathBuf, BackupDescription)> = entries
+        .flatten()
+        .map(|e| e.path())
+        .filter(|p| p.is_dir())
+        .filter_map(|p| read_description(&p).ok().map(|d| (p, d)))
+        .collect();
+    out.sort_by_key(|b| std::cmp::Reverse(b.1.created));
+    out
+}
+
+/// 可以保存备份的分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BackupLocation {
+    /// 分区根目录（如 `E:\`）
+    pub root: PathBuf,
+    /// 所在物理磁盘号（查询失败为 `None`）
+    pub disk_number: Option<u32>,
+    pub removable: bool,
+    pub free_bytes: u64,
+}
+
+/// 备份至少需要的剩余空间
+const MIN_FREE_BYTES: u64 = 64 * 1024 * 1024;
+
+/// 选一个不在目标磁盘上的保存位置：优先可移动磁盘，其次剩余空间最大的固定磁盘分区
+///
+/// 不知道所在磁盘的固定分区（如 PE 的 X: 内存盘）不算安全位置。
+pub fn choose_backup_location(
+    locations: &[BackupLocation],
+    target_disk: u32,
+) -> Option<&BackupLocation> {
+    locations
+        .iter()
+        .filter(|l| l.free_bytes >= MIN_FREE_BYTES)
+        .filter(|l| match l.disk_number {
+            Some(disk) => disk != target_disk,
+            None => l.removable,
+        })
+        .max_by_key(|l| (l.removable, l.free_bytes))
+}
+
+/// 枚举本机所有固定 / 可移动分区（按盘符）
+#[cfg(windows)]
+pub fn backup_locations() -> Vec<BackupLocation> {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::{
+        GetDiskFreeSpaceExW, GetDriveTypeW, GetLogicalDrives,
+    };
+
+    const DRIVE_REMOVABLE: u32 = 2;
+    const DRIVE_FIXED: u32 = 3;
+
+    let mask = unsafe { GetLogicalDrives() };
+    let mut out = Vec::new();
+    for i in 0..26u8 {
+        if mask & (1 << i) == 0 {
+            continue;
+        }
+        let letter = (b'A' + i) as char;
+        let root = format!("{}:\\", letter);
+        let wide: Vec<u16> = root.encode_utf16().chain(std::iter::once(0)).collect();
+        let drive_type = unsafe { GetDriveTypeW(PCWSTR::from_raw(wide.as_ptr())) };
+        if drive_type != DRIVE_REMOVABLE && drive_type != DRIVE_FIXED {
+            continue;
+        }
+        let mut free = 0u64;
+        if unsafe {
+            GetDiskFreeSpaceExW(
+                PCWSTR::from_raw(wide.as_ptr()),
+                Some(&mut free as *mut u64),
+                None,
+                None,
+            )
+        }
+        .is_err()
+        {
+            continue;
+        }
+        out.push(BackupLocation {
+            root: PathBuf::from(root),
+            disk_number: arc::volume_disk_extent(&format!("{}:", letter)).map(|(d, _)| d),
+            removable: drive_type == DRIVE_REMOVABLE,
+            free_bytes: free,
+        });
+    }
+    out
+}
+
+#[cfg(not(windows))]
+pub fn backup_locations() -> Vec<BackupLocation> {
+    Vec::new()
+}
+
+/// 列出所有分区上保存的备份
+pub fn find_all_backups() -> Vec<(PathBuf, BackupDescription)> {
+    let mut out: Vec<(PathBuf, BackupDescription)> = backup_locations()
+        .iter()
+        .flat_map(|l| list_backups(&l.root.join(BACKUP_DIR_NAME)))
+        .collect();
+    out.sort_by_key(|b| std::cmp::Reverse(b.1.created));
+    out
+}
+
+/// 破坏性操作前备份物理磁盘 `disk_number` 的分区表
+///
+/// 保存位置见 [`choose_backup_location`]；`fallback` 为找不到安全位置时的备用根目录
+/// （例如 PE 端的数据分区），为 `None` 时返回 [`BackupError::NoLocation`]。
+/// 磁盘没有分区表时返回 `Ok(None)`；分区表损坏时改做原始扇区备份（见 [`capture_raw`]）。
+pub fn backup_physical_drive(
+    disk_number: u32,
+    reason: &str,
+    fallback: Option<&Path>,
+) -> Result<Option<PathBuf>, BackupError> {
+    let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+    let mut file = std::fs::File::open(&path)?;
+    let (sector_size, disk_size) = crate::partition_table::drive_geometry(&mut file)?;
+    let backup = match capture(&mut file, disk_number, sector_size, disk_size, reason) {
+        Ok(b) => b,
+        Err(BackupError::NoTable) => return Ok(None),
+        Err(BackupError::Table(e)) => {
+            log::warn!(
+                "磁盘 {} 的分区表无法解析（{}），改为备份原始扇区",
+                disk_number,
+                e
+            );
+            capture_raw(&mut file, disk_number, sector_size, disk_size, reason)?
+        }
+        Err(e) => return Err(e),
+    };
+
+    let locations = backup_locations();
+    let root = match choose_backup_location(&locations, disk_number) {
+        Some(l) => l.root.clone(),
+        None => fallback.ok_or(BackupError::NoLocation)?.to_path_buf(),
+    };
+    let dir = backup.save(&root.join(BACKUP_DIR_NAME))?;
+    log::info!("磁盘 {} 的分区表已备份到 {}", disk_number, dir.display());
+    Ok(Some(dir))
+}
+
+/// 在本机物理磁盘中查找备份对应的还原目标
+///
+/// 优先签名/GUID 一致的盘，其次容量一致、没有分区表的盘；签名/GUID 已变的盘只考虑原磁盘号。
+/// 匹配程度相同时优先原磁盘号。
+pub fn find_restore_target(description: &BackupDescription) -> Option<(u32, TargetMatch)> {
+    let rank = |m: TargetMatch| match m {
+        TargetMatch::Same => 3,
+        TargetMatch::Blank => 2,
+        TargetMatch::IdChanged => 1,
+        TargetMatch::SizeMismatch => 0,
+    };
+    let mut best: Option<(u32, TargetMatch)> = None;
+    for disk in 0..32 {
+        let Ok(layout) = crate::partition_table::read_physical_drive(disk) else {
+            continue;
+        };
+        let table = layout.table.ok().flatten();
+        let m = description.match_target(layout.sector_size, layout.disk_size, table.as_ref());
+        if m == TargetMatch::SizeMismatch
+            || (m == TargetMatch::IdChanged && disk != description.disk_number)
+        {
+            continue;
+        }
+        let better = match best {
+            None => true,
+            Some((_, b)) => {
+                rank(m) > rank(b) || (rank(m) == rank(b) && disk == description.disk_number)
+            }
+        };
+        if better {
+            best = Some((disk, m));
+        }
+    }
+    best
+}
+
+/// 把备份还原到物理磁盘 `disk_number`
+///
+/// 容量或扇区大小不同一律拒绝；磁盘签名/GUID 不同时只有 `allow_id_change` 为真才写入。
+pub fn restore_physical_drive(
+    disk_number: u32,
+    backup: &PartitionBackup,
+    allow_id_change: bool,
+) -> Result<Option<PartitionTable>, BackupError> {
+    let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)?;
+    let (sector_size, disk_size) = crate::partition_table::drive_geometry(&mut file)?;
+    let current = read_partition_table(&mut file, sector_size, disk_size)
+        .ok()
+        .flatten();
+    match backup
+        .description
+        .match_target(sector_size, disk_size, current.as_ref())
+    {
+        TargetMatch::Same | TargetMatch::Blank => {}
+        TargetMatch::IdChanged if allow_id_change => {}
+        other => return Err(BackupError::Mismatch(other)),
+    }
+    let table = backup.restore(&mut file)?;
+    crate::partition_writer::update_disk_properties(&file);
+    log::info!(
+        "已把 {} 的分区表备份还原到磁盘 {}",
+        backup.description.disk_id,
+        disk_number
+    );
+    Ok(table)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition_table::{Guid, PartitionType};
+    use crate::partition_writer::{write_partition_table, NewPartition, ALIGNMENT};
+    use crate::test_util::put_mbr_entry;
+    use std::io::Cursor;
+
+    const DISK: u64 = 16 * ALIGNMENT;
+
+    /// 主分区 + 扩展分区（两个逻辑分区）的 MBR 磁盘镜像
+    fn mbr_image() -> Cursor<Vec<u8>> {
+        let mut img = vec![0u8; DISK as usize];
+        img[440..444].copy_from_slice(&0x1234_5678u32.to_le_bytes());
+        put_mbr_entry(&mut img, 0, 0, 0x07, 2048, 2048);
+        img[446] = 0x80;
+        put_mbr_entry(&mut img, 0, 1, 0x0F, 4096, 8192);
+        put_mbr_entry(&mut img, 4096, 0, 0x07, 2048, 2048);
+        put_mbr_entry(&mut img, 4096, 1, 0x05, 4096, 4096);
+        put_mbr_entry(&mut img, 8192, 0, 0x07, 2048, 2048);
+        Cursor::new(img)
+    }
+
+    #[test]
+    fn mbr_with_ebr_chain_round_trip() {
+        let mut img = mbr_image();
+        let backup = capture(&mut img, 1, 512, DISK, "quick_partition").unwrap();
+        let kinds: Vec<(RegionKind, u64)> = backup
+            .description
+            .regions
+            .iter()
+            .map(|r| (r.kind, r.lba))
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (RegionKind::Mbr, 0),
+                (RegionKind::Ebr, 4096),
+                (RegionKind::Ebr, 8192)
+            ]
+        );
+        assert_eq!(backup.description.disk_id, "12345678");
+        assert_eq!(backup.description.partitions.len(), 3);
+
+        // 模拟 clean：清掉 MBR 与 EBR
+        let original = img.get_ref().clone();
+        for lba in [0usize, 4096, 8192] {
+            img.get_mut()[lba * 512..(lba + 1) * 512].fill(0);
+        }
+        assert!(read_partition_table(&mut img, 512, DISK).unwrap().is_none());
+        assert_eq!(
+            backup.description.match_target(512, DISK, None),
+            TargetMatch::Blank
+        );
+        assert_eq!(
+            backup.description.match_target(4096, DISK, None),
+            TargetMatch::SizeMismatch
+        );
+
+        let table = backup.restore(&mut img).unwrap().unwrap();
+        assert_eq!(table.active_partition(), Some(1));
+        assert_eq!(img.get_ref(), &original);
+    }
+
+    #[test]
+    fn mbr_restore_clears_stale_gpt_headers() {
+        let mut img = mbr_image();
+        let backup = capture(&mut img, 1, 512, DISK, "quick_partition").unwrap();
+        let original = img.get_ref().clone();
+
+        // 备份后整盘被转成 GPT
+        let gpt = PartitionTable::new_gpt(512, DISK).unwrap();
+        write_partition_table(&mut img, &gpt).unwrap();
+        let last = (DISK / 512 - 1) as usize;
+        assert!(img.get_ref()[512..].starts_with(GPT_SIGNATURE));
+        assert!(img.get_ref()[last * 512..].starts_with(GPT_SIGNATURE));
+
+        let table = backup.restore(&mut img).unwrap().unwrap();
+        assert_eq!(table.style, TableStyle::Mbr);
+        assert!(img.get_ref()[512..1024].iter().all(|&b| b == 0));
+        assert!(img.get_ref()[last * 512..].iter().all(|&b| b == 0));
+        // 除两个 GPT 头与 GPT 分区数组外，备份覆盖的扇区都回到原样
+        assert_eq!(img.get_ref()[..512], original[..512]);
+        assert_eq!(
+            img.get_ref()[4096 * 512..4097 * 512],
+            original[4096 * 512..4097 * 512]
+        );
+    }
+
+    #[test]
+    fn gpt_save_load_restore_both_copies() {
+        let mut table = PartitionTable::new_gpt(512, DISK).unwrap();
+        table
+            .add_partition(NewPartition::new(PartitionType::Gpt(Guid::ESP)).size(2 * ALIGNMENT))
+            .unwrap();
+        table
+            .add_partition(NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA)))
+            .unwrap();
+        let mut img = Cursor::new(vec![0u8; DISK as usize]);
+        write_partition_table(&mut img, &table).unwrap();
+
+        let backup = capture(&mut img, 2, 512, DISK, "expand_move").unwrap();
+        let regions = &backup.description.regions;
+        assert_eq!(regions[1].kind, RegionKind::GptPrimary);
+        assert_eq!(regions[1].sectors, 33);
+        assert_eq!(regions[2].kind, RegionKind::GptBackup);
+        assert_eq!(regions[2].lba + regions[2].sectors, DISK / 512);
+        assert_eq!(
+            backup.description.match_target(512, DISK, Some(&table)),
+            TargetMatch::Same
+        );
+        let other = PartitionTable::new_gpt(512, DISK).unwrap();
+        assert_eq!(
+            backup.description.match_target(512, DISK, Some(&other)),
+            TargetMatch::IdChanged
+        );
+
+        let root = std::env::temp_dir().join(format!("lr_ptbackup_{}", std::process::id()));
+        let dir = backup.save(&root).unwrap();
+        assert_eq!(PartitionBackup::load(&dir).unwrap(), backup);
+        assert_eq!(list_backups(&root).len(), 1);
+
+        // 用另一张表覆盖后还原，主备两份都回到原样
+        let original = img.get_ref().clone();
+        write_partition_table(&mut img, &other).unwrap();
+        let restored = PartitionBackup::load(&dir)
+            .unwrap()
+            .restore(&mut img)
+            .unwrap()
+            .unwrap();
+        assert_eq!(restored.partitions, table.partitions);
+        assert!(restored.gpt_health.unwrap().backup_ok);
+        assert_eq!(img.get_ref(), &original);
+
+        // 篡改扇区文件后加载失败
+        std::fs::write(dir.join("gpt_backup.bin"), vec![0u8; 33 * 512]).unwrap();
+        assert!(matches!(
+            PartitionBackup::load(&dir),
+            Err(BackupError::Corrupt(_))
+        ));
+        std::fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn corrupt_gpt_falls_back_to_raw_sectors() {
+        let table = PartitionTable::new_gpt(512, DISK).unwrap();
+        let mut img = Cursor::new(vec![0u8; DISK as usize]);
+        write_partition_table(&mut img, &table).unwrap();
+        // 主备两份 GPT 头都损坏
+        img.get_mut()[512 + 8] ^= 0xFF;
+        let last = (DISK / 512 - 1) as usize;
+        img.get_mut()[last * 512 + 8] ^= 0xFF;
+        assert!(matches!(
+            capture(&mut img, 3, 512, DISK, "quick_partition"),
+            Err(BackupError::Table(_))
+        ));
+
+        let backup = capture_raw(&mut img, 3, 512, DISK, "quick_partition").unwrap();
+        let d = &backup.description;
+        assert!(d.raw);
+        assert_eq!(d.style, TableStyle::Gpt);
+        assert_eq!(d.regions[0].kind, RegionKind::RawHead);
+        assert_eq!(d.regions[1].lba + d.regions[1].sectors, DISK / 512);
+
+        let original = img.get_ref().clone();
+        img.get_mut().fill(0);
+        assert_eq!(backup.restore(&mut img).unwrap(), None);
+        assert_eq!(img.get_ref(), &original);
+    }
+
+    #[test]
+    fn choose_location_prefers_removable_off_target() {
+        let loc = |root: &str, disk: Option<u32>, removable: bool, free: u64| BackupLocation {
+            root: PathBuf::from(root),
+            disk_number: disk,
+            removable,
+            free_bytes: free,
+        };
+        let gib = 1024 * 1024 * 1024;
+        let locations = vec![
+            loc("C:\\", Some(0), false, 100 * gib),
+            loc("D:\\", Some(1), false, 500 * gib),
+            loc("E:\\", Some(2), true, 8 * gib),
+            loc("X:\\", None, false, gib),
+        ];
+        assert_eq!(
+            choose_backup_location(&locations, 1).unwrap().root,
+            PathBuf::from("E:\\")
+        );
+        assert_eq!(
+            choose_backup_location(&locations[..2], 1).unwrap().root,
+            PathBuf::from("C:\\")
+        );
+        // 只剩目标盘和内存盘时没有安全位置
+        assert!(choose_backup_location(&[locations[1].clone(), locations[3].clone()], 1).is_none());
+    }
+}
@@ -0,0 +1,550 @@
+//! Windows 推荐分区布局规划（两端共享）。
+//!
+//! 一键分区原先只是让用户手工编辑一串大小，分区表类型也只按启动模式二选一，ESP/MSR/恢复分区
+//! 要不要建、建多大、放在哪全凭用户自己记。这里按微软推荐布局统一生成：
+//! - UEFI：GPT，ESP → MSR → Windows → 数据分区 → 恢复分区（末尾）；
+//! - BIOS：MBR，活动的系统保留分区 → Windows → 数据分区 → 恢复分区（末尾）。
+//!
+//! 所有分区起点与大小按 1 MiB 对齐。生成前先校验约束（4Kn 磁盘 ESP 最小值、MBR 容量上限、
+//! 主分区数），失败时返回可直接展示给用户的 [`PlanError`]。结果可转成 diskpart 脚本执行，
+//! 也可转成 [`PartitionTable`] 在镜像上验证。
+
+use std::fmt;
+
+use crate::diskpart_script::{DiskpartScript, FileSystem, PartitionId, PartitionKind, TableStyle};
+use crate::partition_table::{Guid, PartitionTable, PartitionType};
+use crate::partition_writer::{EditError, NewPartition, ALIGNMENT};
+
+const MIB: u64 = ALIGNMENT;
+const GIB: u64 = 1024 * MIB;
+
+/// 默认 ESP 大小（满足 4Kn 磁盘上 FAT32 的最小簇数要求）
+pub const DEFAULT_ESP_SIZE: u64 = 300 * MIB;
+/// ESP 最小值：512 字节扇区 100 MiB，4Kn 磁盘 260 MiB
+pub const MIN_ESP_SIZE: u64 = 100 * MIB;
+pub const MIN_ESP_SIZE_4KN: u64 = 260 * MIB;
+/// MSR 大小（Windows 固定 16 MiB）
+pub const MSR_SIZE: u64 = 16 * MIB;
+/// BIOS 模式下活动的系统保留分区大小
+pub const SYSTEM_RESERVED_SIZE: u64 = 100 * MIB;
+/// 默认恢复分区大小（为 WinRE 更新留出余量）
+pub const DEFAULT_RECOVERY_SIZE: u64 = GIB;
+/// Windows 分区最小值
+pub const MIN_WINDOWS_SIZE: u64 = 20 * GIB;
+/// GPT 恢复分区属性：必需 + 不自动分配盘符
+pub const RECOVERY_GPT_ATTRIBUTES: u64 = 0x8000_0000_0000_0001;
+
+/// 启动固件类型，决定分区表样式与引导分区
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Firmware {
+    Uefi,
+    Bios,
+}
+
+impl Firmware {
+    /// 推荐的分区表样式
+    pub fn table_style(self) -> TableStyle {
+        match self {
+            Firmware::Uefi => TableStyle::Gpt,
+            Firmware::Bios => TableStyle::Mbr,
+        }
+    }
+}
+
+/// 用户想要的数据分区
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataPartition {
+    /// 大小（字节）；`None` 表示占用剩余空间（整个布局里至多一个）
+    pub size: Option<u64>,
+    pub label: String,
+    pub file_system: FileSystem,
+    pub letter: Option<char>,
+}
+
+/// 规划输入
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutRequest {
+    pub disk_size: u64,
+    pub sector_size: u32,
+    pub firmware: Firmware,
+    /// ESP 大小（仅 UEFI），`None` 取 [`DEFAULT_ESP_SIZE`]
+    pub esp_size: Option<u64>,
+    /// Windows 分区大小；`None` 表示占用剩余空间
+    pub windows_size: Option<u64>,
+    pub windows_letter: Option<char>,
+    pub data_partitions: Vec<DataPartition>,
+    /// 恢复分区大小；`None` 表示不建
+    pub recovery_size: Option<u64>,
+}
+
+impl LayoutRequest {
+    /// 默认请求：Windows 占满、带恢复分区、无数据分区
+    pub fn new(disk_size: u64, sector_size: u32, firmware: Firmware) -> Self {
+        LayoutRequest {
+            disk_size,
+            sector_size,
+            firmware,
+            esp_size: None,
+            windows_size: None,
+            windows_letter: None,
+            data_partitions: Vec::new(),
+            recovery_size: Some(DEFAULT_RECOVERY_SIZE),
+        }
+    }
+}
+
+/// 分区在布局中的角色
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionRole {
+    Esp,
+    Msr,
+    /// BIOS 模式的活动系统保留分区
+    System,
+    Windows,
+    Data,
+    Recovery,
+}
+
+/// 规划出的一个分区（偏移与长度为字节，均按 1 MiB 对齐）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedPartition {
+    pub role: PartitionRole,
+    pub offset: u64,
+    pub length: u64,
+    /// MSR 不格式化，为 `None`
+    pub file_system: Option<FileSystem>,
+    pub label: String,
+    pub letter: Option<char>,
+}
+
+impl PlannedPartition {
+    /// 分区表中的类型
+    pub fn partition_type(&self, style: TableStyle) -> PartitionType {
+        match (style, self.role) {
+            (TableStyle::Gpt, PartitionRole::Esp) => PartitionType::Gpt(Guid::ESP),
+            (TableStyle::Gpt, PartitionRole::Msr) => PartitionType::Gpt(Guid::MSR),
+            (TableStyle::Gpt, PartitionRole::Recovery) => PartitionType::Gpt(Guid::RECOVERY),
+            (TableStyle::Gpt, _) => PartitionType::Gpt(Guid::BASIC_DATA),
+            (TableStyle::Mbr, PartitionRole::Esp) => PartitionType::Mbr(0xEF),
+            (TableStyle::Mbr, PartitionRole::Recovery) => PartitionType::Mbr(0x27),
+            (TableStyle::Mbr, _) => match self.file_system {
+                Some(FileSystem::Fat32) => PartitionType::Mbr(0x0C),
+                _ => PartitionType::Mbr(0x07),
+            },
+        }
+    }
+}
+
+/// 规划结果
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LayoutPlan {
+    pub style: TableStyle,
+    pub sector_size: u32,
+    pub disk_size: u64,
+    /// 按盘上顺序
+    pub partitions: Vec<PlannedPartition>,
+}
+
+/// 规划失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PlanError {
+    /// ESP 小于所需最小值（4Kn 磁盘要求更大）
+    EspTooSmall { size: u64, min: u64 },
+    /// 磁盘超出 MBR 可寻址范围
+    MbrTooLarge { disk_size: u64, limit: u64 },
+    /// MBR 主分区超过 4 个
+    TooManyPrimaries(usize),
+    /// 多个分区都要求占用剩余空间
+    MultipleFill,
+    /// 空间不足（字节）
+    NotEnoughSpace { needed: u64, available: u64 },
+    /// Windows 分区过小
+    WindowsTooSmall { size: u64, min: u64 },
+    /// 分区大小为 0
+    ZeroSize,
+    /// 磁盘太小，连分区表都放不下
+    DiskTooSmall,
+}
+
+fn gib(bytes: u64) -> String {
+    format!("{:.1} GB", bytes as f64 / GIB as f64)
+}
+
+impl fmt::Display for PlanError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PlanError::EspTooSmall { size, min } => write!(
+                f,
+                "ESP 分区 {} MB 过小，当前磁盘至少需要 {} MB",
+                size / MIB,
+                min / MIB
+            ),
+            PlanError::MbrTooLarge { disk_size, limit } => write!(
+                f,
+                "磁盘容量 {} 超过 MBR 上限 {}，请改用 UEFI 启动与 GPT 分区表",
+                gib(*disk_size),
+                gib(*limit)
+            ),
+            PlanError::TooManyPrimaries(n) => write!(
+                f,
+                "MBR 磁盘最多 4 个主分区，当前布局需要 {} 个，请减少数据分区或取消恢复分区",
+                n
+            ),
+            PlanError::MultipleFill => write!(f, "只能有一个分区设为「使用剩余空间」"),
+            PlanError::NotEnoughSpace { needed, available } => write!(
+                f,
+                "磁盘空间不足：布局需要 {}，可用 {}",
+                gib(*needed),
+                gib(*available)
+            ),
+            PlanError::WindowsTooSmall { size, min } => write!(
+                f,
+                "Windows 分区 {} 过小，至少需要 {}",
+                gib(*size),
+                gib(*min)
+            ),
+            PlanError::ZeroSize => write!(f, "分区大小不能为 0"),
+            PlanError::DiskTooSmall => write!(f, "磁盘容量太小，无法创建分区表"),
+        }
+    }
+}
+
+impl std::error::Error for PlanError {}
+
+fn align_down(v: u64) -> u64 {
+    v / ALIGNMENT * ALIGNMENT
+}
+
+/// 按推荐布局规划分区
+pub fn plan_windows_layout(req: &LayoutRequest) -> Result<LayoutPlan, PlanError> {
+    let style = req.firmware.table_style();
+    let sector_size = req.sector_size.max(512);
+
+    if style == TableStyle::Mbr {
+        let limit = u64::from(u32::MAX) * u64::from(sector_size);
+        if req.disk_size > limit {
+            return Err(PlanError::MbrTooLarge {
+                disk_size: req.disk_size,
+                limit,
+            });
+        }
+    }
+
+    // 先按顺序列出各分区与其请求大小（None = 占满剩余），偏移与长度稍后统一计算
+    let planned = |role, file_system, label: &str, letter| PlannedPartition {
+        role,
+        offset: 0,
+        length: 0,
+        file_system,
+        label: label.to_string(),
+        letter,
+    };
+    let mut specs: Vec<(Option<u64>, PlannedPartition)> = Vec::new();
+    match req.firmware {
+        Firmware::Uefi => {
+            let esp = req.esp_size.unwrap_or(DEFAULT_ESP_SIZE);
+            let min = if sector_size >= 4096 {
+                MIN_ESP_SIZE_4KN
+            } else {
+                MIN_ESP_SIZE
+            };
+            if esp < min {
+                return Err(PlanError::EspTooSmall { size: esp, min });
+            }
+            specs.push((
+                Some(esp),
+                planned(PartitionRole::Esp, Some(FileSystem::Fat32), "System", None),
+            ));
+            specs.push((Some(MSR_SIZE), planned(PartitionRole::Msr, None, "", None)));
+        }
+        Firmware::Bios => specs.push((
+            Some(SYSTEM_RESERVED_SIZE),
+            planned(
+                PartitionRole::System,
+                Some(FileSystem::Ntfs),
+                "System Reserved",
+                None,
+            ),
+        )),
+    }
+    specs.push((
+        req.windows_size,
+        planned(
+            PartitionRole::Windows,
+            Some(FileSystem::Ntfs),
+            "Windows",
+            req.windows_letter,
+        ),
+    ));
+    for d in &req.data_partitions {
+        specs.push((
+            d.size,
+            planned(PartitionRole::Data, Some(d.file_system), &d.label, d.letter),
+        ));
+    }
+    if let Some(size) = req.recovery_size {
+        specs.push((
+            Some(size),
+            planned(
+                PartitionRole::Recovery,
+                Some(FileSystem::Ntfs),
+                "Recovery",
+                None,
+            ),
+        ));
+    }
+
+    if style == TableStyle::Mbr && specs.len() > 4 {
+        return Err(PlanError::TooManyPrimaries(specs.len()));
+    }
+    if specs.iter().filter(|s| s.0.is_none()).count() > 1 {
+        return Err(PlanError::MultipleFill);
+    }
+    if specs
+        .iter()
+        .any(|s| s.0.is_some_and(|v| align_down(v) == 0))
+    {
+        return Err(PlanError::ZeroSize);
+    }
+
+    let empty = match style {
+        TableStyle::Gpt => PartitionTable::new_gpt(sector_size, req.disk_size),
+        TableStyle::Mbr => PartitionTable::new_mbr(sector_size, req.disk_size),
+    }
+    .map_err(|_| PlanError::DiskTooSmall)?;
+    let (start, end) = empty.usable_range();
+    let start = start.div_ceil(ALIGNMENT) * ALIGNMENT;
+    let available = align_down(end).saturating_sub(start);
+
+    let fixed: u64 = specs.iter().filter_map(|s| s.0).map(align_down).sum();
+    if fixed > available {
+        return Err(PlanError::NotEnoughSpace {
+            needed: fixed,
+            available,
+        });
+    }
+    let fill = available - fixed;
+    if specs.iter().any(|s| s.0.is_none()) && fill < ALIGNMENT {
+        return Err(PlanError::NotEnoughSpace {
+            needed: fixed + ALIGNMENT,
+            available,
+        });
+    }
+
+    let mut partitions = Vec::with_capacity(specs.len());
+    let mut cursor = start;
+    for (size, mut p) in specs {
+        let length = size.map(align_down).unwrap_or(fill);
+        if p.role == PartitionRole::Windows && length < MIN_WINDOWS_SIZE {
+            return Err(PlanError::WindowsTooSmall {
+                size: length,
+                min: MIN_WINDOWS_SIZE,
+            });
+        }
+        p.offset = cursor;
+        p.length = length;
+        cursor += length;
+        partitions.push(p);
+    }
+
+    // 恢复分区贴到磁盘末尾（中间若有未分配空间则留在恢复分区之前）
+    if let Some(last) = partitions.last_mut() {
+        if last.role == PartitionRole::Recovery {
+            last.offset = align_down(end) - last.length;
+        }
+    }
+
+    Ok(LayoutPlan {
+        style,
+        sector_size,
+        disk_size: req.disk_size,
+        partitions,
+    })
+}
+
+impl LayoutPlan {
+    /// 生成 diskpart 脚本：清盘、转换分区表，再按偏移逐个创建、格式化并设置类型
+    pub fn diskpart_script(&self, disk_number: u32) -> DiskpartScript {
+        let mut script = DiskpartScript::new()
+            .select_disk(disk_number)
+            .clean()
+            .convert(self.style);
+        for p in &self.partitions {
+            let kind = match p.role {
+                PartitionRole::Esp => PartitionKind::Efi,
+                PartitionRole::Msr => PartitionKind::Msr,
+                _ => PartitionKind::Primary,
+            };
+            script = script.create_partition(kind, Some(p.length / MIB), Some(p.offset / 1024));
+            if let Some(fs) = p.file_system {
+                script = script.format(fs, &p.label, true);
+            }
+            match p.role {
+                PartitionRole::System => script = script.active(),
+                PartitionRole::Recovery => {
+                    script = match self.style {
+                        TableStyle::Gpt => script
+                            .set_id(
+                                PartitionId::Gpt(PartitionId::GPT_RECOVERY.to_string()),
+                                true,
+                            )
+                            .gpt_attributes(RECOVERY_GPT_ATTRIBUTES),
+                        TableStyle::Mbr => script.set_id(PartitionId::Mbr(0x27), true),
+                    };
+                }
+                PartitionRole::Windows | PartitionRole::Data => {
+                    script = match p.letter {
+                        Some(letter) => script.assign_letter(letter),
+                        None => script.assign(),
+                    };
+                }
+                PartitionRole::Esp | PartitionRole::Msr => {}
+            }
+        }
+        script
+    }
+
+    /// 转成分区表（用于在磁盘镜像上写入/验证，或作为 diskpart 失败时的兜底）
+    pub fn to_table(&self) -> Result<PartitionTable, EditError> {
+        let mut table = match self.style {
+            TableStyle::Gpt => PartitionTable::new_gpt(self.sector_size, self.disk_size)?,
+            TableStyle::Mbr => PartitionTable::new_mbr(self.sector_size, self.disk_size)?,
+        };
+        for p in &self.partitions {
+            let mut spec = NewPartition::new(p.partition_type(self.style))
+                .offset(p.offset)
+                .size(p.length)
+                .name(&p.label);
+            if p.role == PartitionRole::Recovery && self.style == TableStyle::Gpt {
+                spec = spec.attributes(RECOVERY_GPT_ATTRIBUTES);
+            }
+            let number = table.add_partition(spec)?;
+            if p.role == PartitionRole::System {
+                table.set_active(number)?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TIB: u64 = 1024 * GIB;
+
+    fn data(size: Option<u64>, letter: char) -> DataPartition {
+        DataPartition {
+            size,
+            label: "Data".to_string(),
+            file_system: FileSystem::Ntfs,
+            letter: Some(letter),
+        }
+    }
+
+    #[test]
+    fn uefi_layout_order_and_alignment() {
+        let mut req = LayoutRequest::new(256 * GIB, 512, Firmware::Uefi);
+        req.windows_size = Some(100 * GIB);
+        req.windows_letter = Some('C');
+        req.data_partitions.push(data(None, 'D'));
+        let plan = plan_windows_layout(&req).unwrap();
+
+        let roles: Vec<PartitionRole> = plan.partitions.iter().map(|p| p.role).collect();
+        assert_eq!(
+            roles,
+            vec![
+                PartitionRole::Esp,
+                PartitionRole::Msr,
+                PartitionRole::Windows,
+                PartitionRole::Data,
+                PartitionRole::Recovery
+            ]
+        );
+        assert_eq!(plan.style, TableStyle::Gpt);
+        assert_eq!(plan.partitions[0].offset, MIB);
+        assert!(plan
+            .partitions
+            .iter()
+            .all(|p| p.offset % MIB == 0 && p.length % MIB == 0));
+        // 相邻分区首尾相接，恢复分区在末尾且不越过可用范围
+        for w in plan.partitions.windows(2) {
+            assert_eq!(w[0].offset + w[0].length, w[1].offset);
+        }
+        let table = plan.to_table().unwrap();
+        let recovery = table.partitions.last().unwrap();
+        assert!(recovery.is_recovery());
+        assert_eq!(recovery.attributes, RECOVERY_GPT_ATTRIBUTES);
+        assert!(recovery.end() <= table.usable_range().1);
+        assert!(table.free_extents().is_empty());
+
+        let script = plan.diskpart_script(1).build().unwrap();
+        assert!(script.contains("create partition efi size=300 offset=1024\n"));
+        assert!(script.contains("create partition msr size=16"));
+        assert!(script.contains("set id=de94bba4-06d1-4d40-a16a-bfd50179d6ac override\n"));
+        assert!(script.contains("assign letter=C\n"));
+    }
+
+    #[test]
+    fn bios_layout_has_active_system_partition() {
+        let mut req = LayoutRequest::new(120 * GIB, 512, Firmware::Bios);
+        req.recovery_size = None;
+        let plan = plan_windows_layout(&req).unwrap();
+        assert_eq!(plan.style, TableStyle::Mbr);
+        assert_eq!(plan.partitions.len(), 2);
+        assert_eq!(plan.partitions[0].role, PartitionRole::System);
+        let table = plan.to_table().unwrap();
+        assert_eq!(table.active_partition(), Some(1));
+        assert_eq!(table.partitions[1].partition_type, PartitionType::Mbr(0x07));
+        assert!(plan
+            .diskpart_script(0)
+            .build()
+            .unwrap()
+            .contains("active\n"));
+    }
+
+    #[test]
+    fn constraint_errors() {
+        let mut req = LayoutRequest::new(512 * GIB, 4096, Firmware::Uefi);
+        req.esp_size = Some(100 * MIB);
+        assert_eq!(
+            plan_windows_layout(&req),
+            Err(PlanError::EspTooSmall {
+                size: 100 * MIB,
+                min: 260 * MIB
+            })
+        );
+
+        let req = LayoutRequest::new(3 * TIB, 512, Firmware::Bios);
+        let err = plan_windows_layout(&req).unwrap_err();
+        assert!(matches!(err, PlanError::MbrTooLarge { .. }));
+        assert!(err.to_string().contains("GPT"));
+        // 4Kn 磁盘的 MBR 上限是 16 TiB
+        assert!(plan_windows_layout(&LayoutRequest::new(3 * TIB, 4096, Firmware::Bios)).is_ok());
+
+        let mut req = LayoutRequest::new(512 * GIB, 512, Firmware::Bios);
+        req.windows_size = Some(100 * GIB);
+        req.data_partitions = vec![data(Some(50 * GIB), 'D'), data(None, 'E')];
+        assert_eq!(
+            plan_windows_layout(&req),
+            Err(PlanError::TooManyPrimaries(5))
+        );
+
+        let mut req = LayoutRequest::new(512 * GIB, 512, Firmware::Uefi);
+        req.data_partitions.push(data(None, 'D'));
+        assert_eq!(plan_windows_layout(&req), Err(PlanError::MultipleFill));
+
+        let req = LayoutRequest::new(16 * GIB, 512, Firmware::Uefi);
+        assert!(matches!(
+            plan_windows_layout(&req),
+            Err(PlanError::WindowsTooSmall { .. })
+        ));
+
+        let mut req = LayoutRequest::new(64 * GIB, 512, Firmware::Uefi);
+        req.windows_size = Some(80 * GIB);
+        assert!(matches!(
+            plan_windows_layout(&req),
+            Err(PlanError::NotEnoughSpace { .. })
+        ));
+    }
+}
@@ -0,0 +1,365 @@
+//! 一键分区预设（两端共享）。
+//!
+//! 批量装机时每台机器按同一套布局分区，预设记录每个分区的大小（绝对 GB / 磁盘百分比 /
+//! 占满剩余空间）、卷标、盘符、文件系统以及 ESP / MSR / 恢复分区标记。
+//! 预设随 config.json 保存，也可单独导入导出为 JSON，供命令行无人值守分区使用。
+//!
+//! 这里只负责预设本身的校验与按磁盘大小换算，真正的分区操作由调用方完成。
+
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::diskpart_script::{FileSystem, TableStyle};
+use crate::partition_writer::ALIGNMENT;
+
+const GIB: u64 = 1024 * 1024 * 1024;
+
+/// 分区大小
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PresetSize {
+    /// 绝对大小（GB）
+    Gb(f64),
+    /// 占整盘的百分比（0-100）
+    Percent(f64),
+    /// 占用其余分区分配后的剩余空间（每个预设最多一个）
+    Fill,
+}
+
+/// 预设中的一个分区
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PresetPartition {
+    pub size: PresetSize,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default)]
+    pub drive_letter: Option<char>,
+    #[serde(default = "default_file_system")]
+    pub file_system: String,
+    #[serde(default)]
+    pub is_esp: bool,
+    #[serde(default)]
+    pub is_msr: bool,
+    #[serde(default)]
+    pub is_recovery: bool,
+}
+
+fn default_file_system() -> String {
+    "NTFS".to_string()
+}
+
+impl PresetPartition {
+    /// 按大小创建一个普通 NTFS 分区
+    pub fn new(size: PresetSize) -> Self {
+        Self {
+            size,
+            label: String::new(),
+            drive_letter: None,
+            file_system: default_file_system(),
+            is_esp: false,
+            is_msr: false,
+            is_recovery: false,
+        }
+    }
+}
+
+/// 一键分区预设
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PartitionPreset {
+    pub name: String,
+    /// 分区表类型；`None` 表示按启动模式推荐
+    #[serde(default)]
+    pub style: Option<TableStyle>,
+    pub partitions: Vec<PresetPartition>,
+}
+
+/// 导入导出用的文件格式
+#[derive(Debug, Serialize, Deserialize)]
+struct PresetFile {
+    presets: Vec<PartitionPreset>,
+}
+
+/// 预设校验 / 换算错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum PresetError {
+    /// 预设名称为空
+    EmptyName,
+    /// 预设中没有分区
+    NoPartitions,
+    /// 第 n 个分区（从 1 开始）大小非法
+    InvalidSize(usize),
+    /// 多个分区标记为占满剩余空间
+    MultipleFill,
+    /// 不支持的文件系统
+    UnknownFileSystem(String),
+    /// 盘符重复或非法
+    InvalidLetter(char),
+    /// ESP / MSR 只能用于 GPT
+    GptOnly(usize),
+    /// 同时标记了多种特殊分区
+    ConflictingFlags(usize),
+    /// 分区总大小超过磁盘可用空间
+    TooLarge { needed: u64, available: u64 },
+    /// 导入文件格式错误
+    Json(String),
+}
+
+impl fmt::Display for PresetError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PresetError::EmptyName => write!(f, "预设名称不能为空"),
+            PresetError::NoPartitions => write!(f, "预设中没有任何分区"),
+            PresetError::InvalidSize(n) => write!(f, "第 {} 个分区的大小无效", n),
+            PresetError::MultipleFill => write!(f, "只能有一个分区占用剩余空间"),
+            PresetError::UnknownFileSystem(fs) => write!(f, "不支持的文件系统: {}", fs),
+            PresetError::InvalidLetter(c) => write!(f, "盘符 {} 无效或重复", c),
+            PresetError::GptOnly(n) => write!(f, "第 {} 个分区为 ESP/MSR，只能用于 GPT 分区表", n),
+            PresetError::ConflictingFlags(n) => {
+                write!(f, "第 {} 个分区同时标记了多种特殊分区类型", n)
+            }
+            PresetError::TooLarge { needed, available } => write!(
+                f,
+                "分区总大小 {} MB 超过磁盘可用空间 {} MB",
+                needed / (1024 * 1024),
+                available / (1024 * 1024)
+            ),
+            PresetError::Json(e) => write!(f, "预设文件格式错误: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for PresetError {}
+
+impl PartitionPreset {
+    /// 校验与磁盘无关的部分（文件系统、盘符、特殊分区标记、大小取值）
+    pub fn validate(&self) -> Result<(), PresetError> {
+        if self.name.trim().is_empty() {
+            return Err(PresetError::EmptyName);
+        }
+        if self.partitions.is_empty() {
+            return Err(PresetError::NoPartitions);
+        }
+
+        let mut letters = Vec::new();
+        let mut fills = 0;
+        for (i, p) in self.partitions.iter().enumerate() {
+            let n = i + 1;
+            match p.size {
+                PresetSize::Gb(gb) if !(gb > 0.0 && gb.is_finite()) => {
+                    return Err(PresetError::InvalidSize(n))
+                }
+                PresetSize::Percent(pct) if !(pct > 0.0 && pct <= 100.0) => {
+                    return Err(PresetError::InvalidSize(n))
+                }
+                PresetSize::Fill => fills += 1,
+                _ => {}
+            }
+            if [p.is_esp, p.is_msr, p.is_recovery]
+                .iter()
+                .filter(|&&b| b)
+                .count()
+                > 1
+            {
+                return Err(PresetError::ConflictingFlags(n));
+            }
+            if (p.is_esp || p.is_msr) && self.style == Some(TableStyle::Mbr) {
+                return Err(PresetError::GptOnly(n));
+            }
+            if !p.is_msr && FileSystem::parse(&p.file_system).is_none() {
+                return Err(PresetError::UnknownFileSystem(p.file_system.clone()));
+            }
+            if let Some(letter) = p.drive_letter {
+                let upper = letter.to_ascii_uppercase();
+                if !('C'..='Z').contains(&upper) || letters.contains(&upper) {
+                    return Err(PresetError::InvalidLetter(letter));
+                }
+                letters.push(upper);
+            }
+        }
+        if fills > 1 {
+            return Err(PresetError::MultipleFill);
+        }
+        Ok(())
+    }
+
+    /// 按磁盘大小换算每个分区的字节数（按 1 MiB 向下取整）
+    ///
+    /// 磁盘首尾各留 1 MiB（对齐起点与 GPT 备份表），`Fill` 分区取其余分区分配后的剩余空间。
+    pub fn resolve_sizes(&self, disk_size: u64) -> Result<Vec<u64>, PresetError> {
+        self.validate()?;
+
+        let available = disk_size.saturating_sub(2 * ALIGNMENT);
+        let align = |bytes: f64| (bytes as u64) / ALIGNMENT * ALIGNMENT;
+        let mut sizes: Vec<Option<u64>> = Vec::with_capacity(self.partitions.len());
+        for (i, p) in self.partitions.iter().enumerate() {
+            let size = match p.size {
+                PresetSize::Gb(gb) => Some(align(gb * GIB as f64)),
+                PresetSize::Percent(pct) => Some(align(disk_size as f64 * pct / 100.0)),
+                PresetSize::Fill => None,
+            };
+            if size == Some(0) {
+                return Err(PresetError::InvalidSize(i + 1));
+            }
+            sizes.push(size);
+        }
+
+        let fixed: u64 = sizes.iter().flatten().sum();
+        let needed = fixed + if sizes.contains(&None) { ALIGNMENT } else { 0 };
+        if needed > available {
+            return Err(PresetError::TooLarge { needed, available });
+        }
+        let rest = (available - fixed) / ALIGNMENT * ALIGNMENT;
+        Ok(sizes.into_iter().map(|s| s.unwrap_or(rest)).collect())
+    }
+}
+
+/// 按名称查找预设（不区分大小写）
+pub fn find_preset<'a>(presets: &'a [PartitionPreset], name: &str) -> Option<&'a PartitionPreset> {
+    presets
+        .iter()
+        .find(|p| p.name.trim().eq_ignore_ascii_case(name.trim()))
+}
+
+/// 把导入的预设并入已有列表：同名的覆盖，其余追加；返回导入的数量
+pub fn merge_presets(presets: &mut Vec<PartitionPreset>, imported: Vec<PartitionPreset>) -> usize {
+    let count = imported.len();
+    for preset in imported {
+        match presets
+            .iter_mut()
+            .find(|p| p.name.trim().eq_ignore_ascii_case(preset.name.trim()))
+        {
+            Some(existing) => *existing = preset,
+            None => presets.push(preset),
+        }
+    }
+    count
+}
+
+/// 导出为 JSON（`{"presets": [...]}`）
+pub fn export_presets(presets: &[PartitionPreset]) -> String {
+    serde_json::to_string_pretty(&PresetFile {
+        presets: presets.to_vec(),
+    })
+    .unwrap_or_default()
+}
+
+/// 从 JSON 导入并逐个校验
+///
+/// 接受导出格式 `{"presets": [...]}`，也接受单个预设对象或预设数组。
+pub fn import_presets(json: &str) -> Result<Vec<PartitionPreset>, PresetError> {
+    let value: serde_json::Value =
+        serde_json::from_str(json).map_err(|e| PresetError::Json(e.to_string()))?;
+    let presets = if value.get("presets").is_some() {
+        serde_json::from_value::<PresetFile>(value).map(|f| f.presets)
+    } else if value.is_array() {
+        serde_json::from_value::<Vec<PartitionPreset>>(value)
+    } else {
+        serde_json::from_value::<PartitionPreset>(value).map(|p| vec![p])
+    }
+    .map_err(|e| PresetError::Json(e.to_string()))?;
+
+    for preset in &presets {
+        preset.validate()?;
+    }
+    Ok(presets)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MIB: u64 = 1024 * 1024;
+
+    fn workstation() -> PartitionPreset {
+        let mut esp = PresetPartition::new(PresetSize::Gb(0.3));
+        esp.is_esp = true;
+        esp.file_system = "FAT32".to_string();
+        let mut msr = PresetPartition::new(PresetSize::Gb(16.0 / 1024.0));
+        msr.is_msr = true;
+        msr.file_system = String::new();
+        let mut windows = PresetPartition::new(PresetSize::Percent(50.0));
+        windows.drive_letter = Some('C');
+        windows.label = "Windows".to_string();
+        let mut data = PresetPartition::new(PresetSize::Fill);
+        data.drive_letter = Some('D');
+        let mut recovery = PresetPartition::new(PresetSize::Gb(1.0));
+        recovery.is_recovery = true;
+        PartitionPreset {
+            name: "Workstation".to_string(),
+            style: Some(TableStyle::Gpt),
+            partitions: vec![esp, msr, windows, data, recovery],
+        }
+    }
+
+    #[test]
+    fn resolve_sizes_percent_and_fill() {
+        let disk = 256 * GIB;
+        let sizes = workstation().resolve_sizes(disk).unwrap();
+        assert_eq!(sizes[0], 307 * MIB);
+        assert_eq!(sizes[1], 16 * MIB);
+        assert_eq!(sizes[2], 128 * GIB);
+        assert_eq!(sizes[4], GIB);
+        assert!(sizes.iter().all(|s| s % MIB == 0));
+        assert_eq!(sizes.iter().sum::<u64>(), disk - 2 * MIB);
+
+        // 小盘上放不下 50% + 固定分区以外的剩余空间时报错
+        let err = workstation().resolve_sizes(2 * GIB).unwrap_err();
+        assert!(matches!(err, PresetError::TooLarge { .. }));
+    }
+
+    #[test]
+    fn validate_rejects_bad_presets() {
+        let mut p = workstation();
+        p.style = Some(TableStyle::Mbr);
+        assert_eq!(p.validate(), Err(PresetError::GptOnly(1)));
+
+        let mut p = workstation();
+        p.partitions[2].size = PresetSize::Fill;
+        assert_eq!(p.validate(), Err(PresetError::MultipleFill));
+
+        let mut p = workstation();
+        p.partitions[3].drive_letter = Some('c');
+        assert_eq!(p.validate(), Err(PresetError::InvalidLetter('c')));
+
+        let mut p = workstation();
+        p.partitions[2].file_system = "ext4".to_string();
+        assert_eq!(
+            p.validate(),
+            Err(PresetError::UnknownFileSystem("ext4".to_string()))
+        );
+
+        let mut p = workstation();
+        p.partitions[2].size = PresetSize::Percent(120.0);
+        assert_eq!(p.validate(), Err(PresetError::InvalidSize(3)));
+    }
+
+    #[test]
+    fn export_import_round_trip_and_merge() {
+        let json = export_presets(&[workstation()]);
+        assert!(json.contains("\"percent\": 50.0"));
+        assert!(json.contains("\"style\": \"gpt\""));
+        let imported = import_presets(&json).unwrap();
+        assert_eq!(imported, vec![workstation()]);
+
+        // 单个对象、缺省字段
+        let single = r#"{"name": "Simple", "partitions": [{"size": "fill", "drive_letter": "D"}]}"#;
+        let imported = import_presets(single).unwrap();
+        assert_eq!(imported[0].style, None);
+        assert_eq!(imported[0].partitions[0].file_system, "NTFS");
+
+        let mut presets = vec![workstation()];
+        let mut replaced = workstation();
+        replaced.name = "workstation".to_string();
+        replaced.partitions.pop();
+        assert_eq!(merge_presets(&mut presets, vec![replaced]), 1);
+        assert_eq!(presets.len(), 1);
+        assert_eq!(presets[0].partitions.len(), 4);
+        assert!(find_preset(&presets, " WORKSTATION ").is_some());
+
+        assert!(matches!(
+            import_presets("{\"presets\": 1}"),
+            Err(PresetError::Json(_))
+        ));
+    }
+}
@@ -0,0 +1,787 @@
+//! 纯 Rust 的 MBR/GPT 分区表读取（两端共享）。
+//!
+//! 以前一键分区、无损扩容、硬件信息三处各自用 `IOCTL_DISK_GET_DRIVE_LAYOUT_EX` 取布局，
+//! 再按手写的 `repr(C)` 偏移去抠 `PARTITION_INFORMATION_EX`，结构体和 GUID 常量复制了三份，
+//! 也没法在没有真实磁盘的环境里测试。这里直接读盘上的扇区：
+//! - 传统 MBR（含扩展分区的 EBR 链，复用 [`crate::arc::read_mbr_layout`]）；
+//! - 保护性 MBR（0xEE）+ GPT：主头与备份头都校验 CRC32，主头或其分区项数组损坏时回退到
+//!   磁盘末尾的备份头。
+//!
+//! 适用任何 `Read + Seek`：`\\.\PhysicalDriveN`（[`read_physical_drive`]）或磁盘镜像文件。
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom};
+
+use crate::arc::{self, MbrPartKind};
+use crate::diskpart_script::TableStyle;
+
+/// GPT 头签名
+pub(crate) const GPT_SIGNATURE: &[u8; 8] = b"EFI PART";
+/// GPT 头至少包含的字节数（UEFI 规范定义的字段长度）
+const GPT_HEADER_MIN: usize = 92;
+/// 分区项数组的上限，防止损坏的头让我们分配巨量内存
+const GPT_ENTRIES_MAX_BYTES: u64 = 16 * 1024 * 1024;
+
+/// CRC32（IEEE 802.3，反射多项式 0xEDB88320），GPT 头与分区项数组使用的校验算法。
+pub fn crc32(data: &[u8]) -> u32 {
+    const TABLE: [u32; 256] = {
+        let mut table = [0u32; 256];
+        let mut i = 0;
+        while i < 256 {
+            let mut c = i as u32;
+            let mut k = 0;
+            while k < 8 {
+                c = if c & 1 != 0 {
+                    0xEDB8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+                k += 1;
+            }
+            table[i] = c;
+            i += 1;
+        }
+        table
+    };
+    let mut crc = 0xFFFF_FFFFu32;
+    for &b in data {
+        crc = TABLE[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+/// GUID，按盘上字节序保存（前三段小端，与 `PARTITION_INFORMATION_GPT` 一致）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct Guid(pub [u8; 16]);
+
+impl Guid {
+    /// EFI 系统分区（ESP）`C12A7328-F81F-11D2-BA4B-00A0C93EC93B`
+    pub const ESP: Guid = Guid([
+        0x28, 0x73, 0x2a, 0xc1, 0x1f, 0xf8, 0xd2, 0x11, 0xba, 0x4b, 0x00, 0xa0, 0xc9, 0x3e, 0xc9,
+        0x3b,
+    ]);
+    /// Microsoft 保留分区（MSR）`E3C9E316-0B5C-4DB8-817D-F92DF00215AE`
+    pub const MSR: Guid = Guid([
+        0x16, 0xe3, 0xc9, 0xe3, 0x5c, 0x0b, 0xb8, 0x4d, 0x81, 0x7d, 0xf9, 0x2d, 0xf0, 0x02, 0x15,
+        0xae,
+    ]);
+    /// Windows 恢复分区 `DE94BBA4-06D1-4D40-A16A-BFD50179D6AC`
+    pub const RECOVERY: Guid = Guid([
+        0xa4, 0xbb, 0x94, 0xde, 0xd1, 0x06, 0x40, 0x4d, 0xa1, 0x6a, 0xbf, 0xd5, 0x01, 0x79, 0xd6,
+        0xac,
+    ]);
+    /// 基本数据分区 `EBD0A0A2-B9E5-4433-87C0-68B6B72699C7`
+    pub const BASIC_DATA: Guid = Guid([
+        0xa2, 0xa0, 0xd0, 0xeb, 0xe5, 0xb9, 0x33, 0x44, 0x87, 0xc0, 0x68, 0xb6, 0xb7, 0x26, 0x99,
+        0xc7,
+    ]);
+
+    /// 解析文本形式（`C12A7328-F81F-11D2-BA4B-00A0C93EC93B`，大小写均可，可带花括号）
+    pub fn parse(text: &str) -> Option<Self> {
+        let text = text.trim().trim_start_matches('{').trim_end_matches('}');
+        let groups: Vec<&str> = text.split('-').collect();
+        let lens = [8, 4, 4, 4, 12];
+        if groups.len() != 5 || groups.iter().zip(lens).any(|(g, n)| g.len() != n) {
+            return None;
+        }
+        let hex: String = groups.concat();
+        let mut be = [0u8; 16];
+        for (i, b) in be.iter_mut().enumerate() {
+            *b = u8::from_str_radix(hex.get(i * 2..i * 2 + 2)?, 16).ok()?;
+        }
+        let mut bytes = be;
+        bytes[0..4].reverse();
+        bytes[4..6].reverse();
+        bytes[6..8].reverse();
+        Some(Guid(bytes))
+    }
+
+    /// 生成随机（版本 4）GUID，用于新建的 GPT 磁盘与分区
+    pub fn generate() -> Self {
+        use sha2::{Digest, Sha256};
+        use std::sync::atomic::{AtomicU64, Ordering};
+
+        static COUNTER: AtomicU64 = AtomicU64::new(0);
+        let nanos = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let mut hasher = Sha256::new();
+        hasher.update(nanos.to_le_bytes());
+        hasher.update(COUNTER.fetch_add(1, Ordering::Relaxed).to_le_bytes());
+        hasher.update(std::process::id().to_le_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hasher.finalize()[..16]);
+        // 第三段小端存放，版本号在其高字节；变体位在第四段首字节
+        bytes[7] = (bytes[7] & 0x0F) | 0x40;
+        bytes[8] = (bytes[8] & 0x3F) | 0x80;
+        Guid(bytes)
+    }
+
+    /// 是否为全零 GUID（GPT 中表示空分区项）
+    pub fn is_nil(&self) -> bool {
+        self.0 == [0; 16]
+    }
+}
+
+impl fmt::Display for Guid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let g = &self.0;
+        write!(
+            f,
+            "{:02X}{:02X}{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-{:02X}{:02X}-",
+            g[3], g[2], g[1], g[0], g[5], g[4], g[7], g[6], g[8], g[9]
+        )?;
+        for b in &g[10..] {
+            write!(f, "{:02X}", b)?;
+        }
+        Ok(())
+    }
+}
+
+/// 分区类型：MBR 类型字节或 GPT 类型 GUID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PartitionType {
+    Mbr(u8),
+    Gpt(Guid),
+}
+
+impl PartitionType {
+    /// EFI 系统分区（GPT 类型 GUID，或 MBR 类型 0xEF）
+    pub fn is_esp(&self) -> bool {
+        matches!(
+            self,
+            PartitionType::Gpt(Guid::ESP) | PartitionType::Mbr(0xEF)
+        )
+    }
+
+    /// Microsoft 保留分区（仅 GPT）
+    pub fn is_msr(&self) -> bool {
+        matches!(self, PartitionType::Gpt(Guid::MSR))
+    }
+
+    /// Windows 恢复分区（GPT 类型 GUID，或 MBR 类型 0x27）
+    pub fn is_recovery(&self) -> bool {
+        matches!(
+            self,
+            PartitionType::Gpt(Guid::RECOVERY) | PartitionType::Mbr(0x27)
+        )
+    }
+}
+
+impl fmt::Display for PartitionType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PartitionType::Mbr(t) => write!(f, "0x{:02X}", t),
+            PartitionType::Gpt(g) => g.fmt(f),
+        }
+    }
+}
+
+/// 一个分区（偏移与长度均为字节）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionEntry {
+    /// 分区号，与 Windows/diskpart 一致：MBR 先主分区（按槽位顺序）后逻辑分区，GPT 按非空项顺序，
+    /// 均从 1 开始；MBR 扩展分区容器为 0
+    pub number: u32,
+    /// 盘上位置：MBR 主分区/扩展分区为主分区表槽位（0..4），逻辑分区为 EBR 链序号，GPT 为分区项下标
+    pub slot: u32,
+    pub partition_type: PartitionType,
+    pub offset: u64,
+    pub length: u64,
+    /// 是否为活动分区（仅 MBR 主分区有意义）
+    pub active: bool,
+    /// 是否为 EBR 链上的逻辑分区（仅 MBR）
+    pub logical: bool,
+    /// 是否为 MBR 扩展分区容器（范围内是 EBR 与逻辑分区，不是未分配空间，也不是可用的分区）
+    pub extended: bool,
+    /// 分区唯一 GUID（仅 GPT）
+    pub unique_guid: Option<Guid>,
+    /// GPT 属性位（如 0x8000000000000001 = 隐藏 + 必需）
+    pub attributes: u64,
+    /// GPT 分区名
+    pub name: String,
+}
+
+impl PartitionEntry {
+    pub fn is_esp(&self) -> bool {
+        self.partition_type.is_esp()
+    }
+
+    pub fn is_msr(&self) -> bool {
+        self.partition_type.is_msr()
+    }
+
+    pub fn is_recovery(&self) -> bool {
+        self.partition_type.is_recovery()
+    }
+
+    /// 结束位置（不含）
+    pub fn end(&self) -> u64 {
+        self.offset + self.length
+    }
+}
+
+/// 磁盘标识：MBR 磁盘签名或 GPT 磁盘 GUID
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiskId {
+    Mbr(u32),
+    Gpt(Guid),
+}
+
+impl fmt::Display for DiskId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DiskId::Mbr(sig) => write!(f, "{:08X}", sig),
+            DiskId::Gpt(g) => g.fmt(f),
+        }
+    }
+}
+
+/// GPT 头中与布局相关的字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GptHeader {
+    pub disk_guid: Guid,
+    /// 本头所在 LBA
+    pub current_lba: u64,
+    /// 另一份头所在 LBA
+    pub alternate_lba: u64,
+    pub first_usable_lba: u64,
+    pub last_usable_lba: u64,
+    pub entries_lba: u64,
+    pub entry_count: u32,
+    pub entry_size: u32,
+    pub entries_crc: u32,
+}
+
+/// GPT 主/备份两份头的校验结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct GptHealth {
+    /// 主头及其分区项数组完好
+    pub primary_ok: bool,
+    /// 备份头及其分区项数组完好（读不到磁盘末尾时为 `false`）
+    pub backup_ok: bool,
+}
+
+/// 一块磁盘的分区表
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PartitionTable {
+    pub style: TableStyle,
+    pub sector_size: u32,
+    /// 磁盘总字节数
+    pub disk_size: u64,
+    pub disk_id: DiskId,
+    /// 实际采用的 GPT 头（主头完好时为主头，否则为备份头）；MBR 为 `None`
+    pub gpt: Option<GptHeader>,
+    pub gpt_health: Option<GptHealth>,
+    /// 按偏移排序
+    pub partitions: Vec<PartitionEntry>,
+}
+
+impl PartitionTable {
+    /// 按分区号查找（不含扩展分区容器）
+    pub fn partition(&self, number: u32) -> Option<&PartitionEntry> {
+        self.volumes().find(|p| p.number == number)
+    }
+
+    /// 起始于 `offset`（字节）的分区（不含扩展分区容器）
+    pub fn partition_at(&self, offset: u64) -> Option<&PartitionEntry> {
+        self.volumes().find(|p| p.offset == offset)
+    }
+
+    /// 除扩展分区容器外的分区（能格式化、分配盘符的分区）
+    pub fn volumes(&self) -> impl Iterator<Item = &PartitionEntry> {
+        self.partitions.iter().filter(|p| !p.extended)
+    }
+
+    /// 活动分区号（MBR）
+    pub fn active_partition(&self) -> Option<u32> {
+        self.partitions.iter().find(|p| p.active).map(|p| p.number)
+    }
+}
+
+/// 读取分区表的错误
+#[derive(Debug)]
+pub enum TableError {
+    Io(io::Error),
+    /// MBR/EBR 链解析失败
+    Mbr(String),
+    /// 保护性 MBR 存在，但主、备份 GPT 头都不可用
+    NoValidGpt {
+        primary: String,
+        backup: String,
+    },
+}
+
+impl fmt::Display for TableError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TableError::Io(e) => write!(f, "读取磁盘失败: {}", e),
+            TableError::Mbr(e) => write!(f, "MBR 分区表无效: {}", e),
+            TableError::NoValidGpt { primary, backup } => write!(
+                f,
+                "GPT 主分区表与备份分区表均已损坏（主: {}；备份: {}）",
+                primary, backup
+            ),
+        }
+    }
+}
+
+impl std::error::Error for TableError {}
+
+impl From<io::Error> for TableError {
+    fn from(e: io::Error) -> Self {
+        TableError::Io(e)
+    }
+}
+
+fn read_at<R: Read + Seek>(r: &mut R, offset: u64, len: usize) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; len];
+    r.seek(SeekFrom::Start(offset))?;
+    r.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn u32_at(b: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(b[at..at + 4].try_into().unwrap())
+}
+
+fn u64_at(b: &[u8], at: usize) -> u64 {
+    u64::from_le_bytes(b[at..at + 8].try_into().unwrap())
+}
+
+fn guid_at(b: &[u8], at: usize) -> Guid {
+    Guid(b[at..at + 16].try_into().unwrap())
+}
+
+/// 解析并校验位于 `lba` 的 GPT 头（签名、长度、头 CRC、自身 LBA、分区项尺寸）。
+pub fn parse_gpt_header(sector: &[u8], lba: u64) -> Result<GptHeader, String> {
+    if sector.len() < GPT_HEADER_MIN || &sector[0..8] != GPT_SIGNATURE {
+        return Err("缺少 \"EFI PART\" 签名".to_string());
+    }
+    let header_size = u32_at(sector, 12) as usize;
+    if !(GPT_HEADER_MIN..=sector.len()).contains(&header_size) {
+        return Err(format!("头长度 {} 不合法", header_size));
+    }
+    let mut header = sector[..header_size].to_vec();
+    let stored = u32_at(&header, 16);
+    header[16..20].fill(0);
+    let actual = crc32(&header);
+    if stored != actual {
+        return Err(format!(
+            "头 CRC 不匹配（记录 {:08X}，实际 {:08X}）",
+            stored, actual
+        ));
+    }
+    let current_lba = u64_at(sector, 24);
+    if current_lba != lba {
+        return Err(format!("头记录的位置 {} 与实际 {} 不符", current_lba, lba));
+    }
+    let entry_count = u32_at(sector, 80);
+    let entry_size = u32_at(sector, 84);
+    if entry_size < 128 || !entry_size.is_multiple_of(8) {
+        return Err(format!("分区项长度 {} 不合法", entry_size));
+    }
+    if u64::from(entry_count) * u64::from(entry_size) > GPT_ENTRIES_MAX_BYTES {
+        return Err(format!("分区项数量 {} 过大", entry_count));
+    }
+    Ok(GptHeader {
+        disk_guid: guid_at(sector, 56),
+        current_lba,
+        alternate_lba: u64_at(sector, 32),
+        first_usable_lba: u64_at(sector, 40),
+        last_usable_lba: u64_at(sector, 48),
+        entries_lba: u64_at(sector, 72),
+        entry_count,
+        entry_size,
+        entries_crc: u32_at(sector, 88),
+    })
+}
+
+/// 读取并校验一份 GPT 头及其分区项数组
+fn read_gpt_copy<R: Read + Seek>(
+    r: &mut R,
+    lba: u64,
+    sector_size: u32,
+) -> Result<(GptHeader, Vec<u8>), String> {
+    let ss = u64::from(sector_size);
+    let sector = read_at(r, lba * ss, sector_size as usize)
+        .map_err(|e| format!("读取扇区 {} 失败: {}", lba, e))?;
+    let header = parse_gpt_header(&sector, lba)?;
+    let len = header.entry_count as usize * header.entry_size as usize;
+    // 按整扇区读取：物理磁盘句柄要求读长度对齐扇区
+    let aligned = len.div_ceil(sector_size as usize) * sector_size as usize;
+    let mut entries = read_at(r, header.entries_lba * ss, aligned)
+        .map_err(|e| format!("读取分区项数组失败: {}", e))?;
+    entries.truncate(len);
+    let actual = crc32(&entries);
+    if actual != header.entries_crc {
+        return Err(format!(
+            "分区项数组 CRC 不匹配（记录 {:08X}，实际 {:08X}）",
+            header.entries_crc, actual
+        ));
+    }
+    Ok((header, entries))
+}
+
+fn parse_gpt_entries(header: &GptHeader, entries: &[u8], sector_size: u32) -> Vec<PartitionEntry> {
+    let ss = u64::from(sector_size);
+    let mut parts = Vec::new();
+    for (index, raw) in entries.chunks_exact(header.entry_size as usize).enumerate() {
+        let type_guid = guid_at(raw, 0);
+        if type_guid.is_nil() {
+            continue;
+        }
+        let first = u64_at(raw, 32);
+        let last = u64_at(raw, 40);
+        if last < first {
+            continue;
+        }
+        let name_units: Vec<u16> = raw[56..128]
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .take_while(|&c| c != 0)
+            .collect();
+        parts.push(PartitionEntry {
+            number: parts.len() as u32 + 1,
+            slot: index as u32,
+            partition_type: PartitionType::Gpt(type_guid),
+            offset: first * ss,
+            length: (last - first + 1) * ss,
+            active: false,
+            logical: false,
+            extended: false,
+            unique_guid: Some(guid_at(raw, 16)),
+            attributes: u64_at(raw, 48),
+            name: String::from_utf16_lossy(&name_units),
+        });
+    }
+    parts
+}
+
+/// 读取 GPT：优先主头（LBA 1），主头或其分区项损坏时回退到备份头。
+///
+/// 备份头位置取主头记录的 `alternate_lba`，主头不可用时取磁盘最后一个扇区。
+fn read_gpt<R: Read + Seek>(
+    r: &mut R,
+    sector_size: u32,
+    disk_size: u64,
+) -> Result<PartitionTable, TableError> {
+    let last_lba = (disk_size / u64::from(sector_size)).saturating_sub(1);
+    let primary = read_gpt_copy(r, 1, sector_size);
+    let backup_lba = match &primary {
+        Ok((h, _)) if h.alternate_lba != 0 => h.alternate_lba,
+        _ => last_lba,
+    };
+    let backup = read_gpt_copy(r, backup_lba, sector_size);
+    let health = GptHealth {
+        primary_ok: primary.is_ok(),
+        backup_ok: backup.is_ok(),
+    };
+    let (header, entries) = match (primary, backup) {
+        (Ok(p), _) => p,
+        (Err(_), Ok(b)) => {
+            log::warn!("GPT 主分区表损坏，改用磁盘末尾的备份分区表");
+            b
+        }
+        (Err(primary), Err(backup)) => return Err(TableError::NoValidGpt { primary, backup }),
+    };
+    let mut partitions = parse_gpt_entries(&header, &entries, sector_size);
+    partitions.sort_by_key(|p| p.offset);
+    Ok(PartitionTable {
+        style: TableStyle::Gpt,
+        sector_size,
+        disk_size,
+        disk_id: DiskId::Gpt(header.disk_guid),
+        gpt: Some(header),
+        gpt_health: Some(health),
+        partitions,
+    })
+}
+
+/// 读取磁盘（或磁盘镜像）的分区表。
+///
+/// `sector_size` 为逻辑扇区大小（512 或 4096），`disk_size` 为磁盘总字节数（用于定位 GPT 备份头）。
+/// 扇区 0 没有 0x55AA 签名（未初始化的 RAW 盘）时返回 `Ok(None)`。
+pub fn read_partition_table<R: Read + Seek>(
+    r: &mut R,
+    sector_size: u32,
+    disk_size: u64,
+) -> Result<Option<PartitionTable>, TableError> {
+    let mbr = read_at(r, 0, sector_size as usize)?;
+    if mbr.len() < 512 || mbr[510] != 0x55 || mbr[511] != 0xAA {
+        return Ok(None);
+    }
+    // 保护性 MBR 或混合 MBR：任一项类型为 0xEE 即按 GPT 处理
+    if (0..4).any(|i| mbr[446 + i * 16 + 4] == 0xEE) {
+        return read_gpt(r, sector_size, disk_size).map(Some);
+    }
+
+    let layout = arc::read_mbr_layout(r, 0, sector_size).map_err(TableError::Mbr)?;
+    let ss = u64::from(sector_size);
+    let entry = |number: u32, p: &arc::MbrPart| PartitionEntry {
+        number,
+        slot: p.slot,
+        partition_type: PartitionType::Mbr(p.part_type),
+        offset: p.start_lba * ss,
+        length: p.sectors * ss,
+        active: p.active,
+        logical: p.kind == MbrPartKind::Logical,
+        extended: p.kind == MbrPartKind::Extended,
+        unique_guid: None,
+        attributes: 0,
+        name: String::new(),
+    };
+    // 扩展分区容器也保留在表里（编号 0，与 Windows 一致），否则 EBR 与其对齐零头会被当成未分配空间
+    let mut partitions: Vec<PartitionEntry> = layout
+        .arc_ordered()
+        .into_iter()
+        .enumerate()
+        .map(|(i, p)| entry(i as u32 + 1, p))
+        .chain(
+            layout
+                .parts
+                .iter()
+                .filter(|p| p.kind == MbrPartKind::Extended)
+                .map(|p| entry(0, p)),
+        )
+        .collect();
+    partitions.sort_by_key(|p| p.offset);
+    Ok(Some(PartitionTable {
+        style: TableStyle::Mbr,
+        sector_size,
+        disk_size,
+        disk_id: DiskId::Mbr(u32_at(&mbr, 440)),
+        gpt: None,
+        gpt_health: None,
+        partitions,
+    }))
+}
+
+/// 物理磁盘的几何信息与分区表
+#[derive(Debug)]
+pub struct DriveLayout {
+    pub sector_size: u32,
+    pub disk_size: u64,
+    /// 分区表；未初始化（RAW）时为 `Ok(None)`，损坏时为 `Err`（磁盘本身仍可用）
+    pub table: Result<Option<PartitionTable>, TableError>,
+}
+
+/// 读取物理磁盘 `disk_number` 的几何信息与分区表（`\\.\PhysicalDriveN`，需管理员权限）。
+///
+/// 磁盘打不开或查询几何信息失败时返回 `Err`。
+pub fn read_physical_drive(disk_number: u32) -> io::Result<DriveLayout> {
+    let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+    let mut file = std::fs::File::open(&path)?;
+    let (sector_size, disk_size) = drive_geometry(&mut file)?;
+    let table = read_partition_table(&mut file, sector_size, disk_size);
+    Ok(DriveLayout {
+        sector_size,
+        disk_size,
+        table,
+    })
+}
+
+/// 查询扇区大小与磁盘总字节数（`IOCTL_DISK_GET_DRIVE_GEOMETRY_EX`）。
+#[cfg(windows)]
+pub(crate) fn drive_geometry(file: &mut std::fs::File) -> io::Result<(u32, u64)> {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    const IOCTL_DISK_GET_DRIVE_GEOMETRY_EX: u32 = 0x0007_00A0;
+
+    // DISK_GEOMETRY_EX：DISK_GEOMETRY(24 字节，BytesPerSector 在偏移 20) + DiskSize(i64)
+    let mut buf = [0u8; 256];
+    let mut returned = 0u32;
+    unsafe {
+        DeviceIoControl(
+            HANDLE(file.as_raw_handle() as _),
+            IOCTL_DISK_GET_DRIVE_GEOMETRY_EX,
+            None,
+            0,
+            Some(buf.as_mut_ptr() as *mut _),
+            buf.len() as u32,
+            Some(&mut returned),
+            None,
+        )
+    }
+    .map_err(|e| io::Error::other(e.to_string()))?;
+    let sector_size = u32_at(&buf, 20);
+    let disk_size = u64_at(&buf, 24);
+    Ok((if sector_size == 0 { 512 } else { sector_size }, disk_size))
+}
+
+#[cfg(not(windows))]
+pub(crate) fn drive_geometry(file: &mut std::fs::File) -> io::Result<(u32, u64)> {
+    let size = file.seek(SeekFrom::End(0))?;
+    Ok((512, size))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::put_mbr_entry;
+    use std::io::Cursor;
+
+    const SS: u64 = 512;
+
+    /// 按规范写一份 GPT 头 + 分区项数组
+    fn put_gpt(
+        img: &mut [u8],
+        header_lba: u64,
+        entries_lba: u64,
+        alternate: u64,
+        parts: &[(Guid, u64, u64, &str)],
+    ) {
+        let mut entries = vec![0u8; 128 * 128];
+        for (i, (t, first, last, name)) in parts.iter().enumerate() {
+            let e = &mut entries[i * 128..(i + 1) * 128];
+            e[0..16].copy_from_slice(&t.0);
+            e[16] = i as u8 + 1;
+            e[32..40].copy_from_slice(&first.to_le_bytes());
+            e[40..48].copy_from_slice(&last.to_le_bytes());
+            for (j, u) in name.encode_utf16().enumerate() {
+                e[56 + j * 2..58 + j * 2].copy_from_slice(&u.to_le_bytes());
+            }
+        }
+        let eo = (entries_lba * SS) as usize;
+        img[eo..eo + entries.len()].copy_from_slice(&entries);
+
+        let mut h = vec![0u8; 92];
+        h[0..8].copy_from_slice(GPT_SIGNATURE);
+        h[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+        h[12..16].copy_from_slice(&92u32.to_le_bytes());
+        h[24..32].copy_from_slice(&header_lba.to_le_bytes());
+        h[32..40].copy_from_slice(&alternate.to_le_bytes());
+        h[40..48].copy_from_slice(&34u64.to_le_bytes());
+        h[48..56].copy_from_slice(&(img.len() as u64 / SS - 34).to_le_bytes());
+        h[56] = 0xAB;
+        h[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+        h[80..84].copy_from_slice(&128u32.to_le_bytes());
+        h[84..88].copy_from_slice(&128u32.to_le_bytes());
+        h[88..92].copy_from_slice(&crc32(&entries).to_le_bytes());
+        let crc = crc32(&h);
+        h[16..20].copy_from_slice(&crc.to_le_bytes());
+        let ho = (header_lba * SS) as usize;
+        img[ho..ho + 92].copy_from_slice(&h);
+    }
+
+    fn gpt_image() -> Vec<u8> {
+        let sectors = 4096u64;
+        let mut img = vec![0u8; (sectors * SS) as usize];
+        put_mbr_entry(&mut img, 0, 0, 0xEE, 1, (sectors - 1) as u32);
+        let parts = [
+            (Guid::ESP, 2048, 2559, "EFI system partition"),
+            (Guid::MSR, 2560, 2591, "Microsoft reserved partition"),
+            (Guid::BASIC_DATA, 2592, 3999, "Basic data partition"),
+        ];
+        put_gpt(&mut img, 1, 2, sectors - 1, &parts);
+        put_gpt(&mut img, sectors - 1, sectors - 33, 1, &parts);
+        img
+    }
+
+    #[test]
+    fn crc32_and_guid_text() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+        assert_eq!(
+            Guid::ESP.to_string(),
+            "C12A7328-F81F-11D2-BA4B-00A0C93EC93B"
+        );
+        assert_eq!(
+            Guid::parse("{de94bba4-06d1-4d40-a16a-bfd50179d6ac}"),
+            Some(Guid::RECOVERY)
+        );
+        assert_eq!(Guid::parse("not-a-guid"), None);
+    }
+
+    #[test]
+    fn mbr_with_ebr_chain() {
+        let mut img = vec![0u8; (4096 * SS) as usize];
+        img[440..444].copy_from_slice(&0x5C3A_9E21u32.to_le_bytes());
+        put_mbr_entry(&mut img, 0, 0, 0x07, 2048, 1024);
+        img[446] = 0x80;
+        put_mbr_entry(&mut img, 0, 1, 0x0F, 3072, 1024);
+        // 第一个 EBR：逻辑分区 + 指向下一个 EBR
+        put_mbr_entry(&mut img, 3072, 0, 0x07, 63, 400);
+        put_mbr_entry(&mut img, 3072, 1, 0x05, 512, 512);
+        put_mbr_entry(&mut img, 3584, 0, 0x27, 63, 400);
+
+        let table = read_partition_table(&mut Cursor::new(img), 512, 4096 * SS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.style, TableStyle::Mbr);
+        assert_eq!(table.disk_id.to_string(), "5C3A9E21");
+        assert_eq!(table.volumes().count(), 3);
+        assert_eq!(table.active_partition(), Some(1));
+        let p3 = table.partition(3).unwrap();
+        assert!(p3.logical && p3.is_recovery());
+        assert_eq!(p3.offset, (3584 + 63) * SS);
+        assert_eq!(p3.partition_type.to_string(), "0x27");
+        assert_eq!((p3.slot, table.partition(2).unwrap().slot), (1, 0));
+
+        // 扩展分区容器保留在表里（编号 0、槽位 1），EBR 所在的区域不算未分配空间
+        let ext = table.partitions.iter().find(|p| p.extended).unwrap();
+        assert_eq!((ext.number, ext.slot), (0, 1));
+        assert_eq!((ext.offset, ext.length), (3072 * SS, 1024 * SS));
+        assert!(table.partition_at(3072 * SS).is_none());
+        assert!(table
+            .free_extents()
+            .iter()
+            .all(|&(s, e)| e <= 3072 * SS || s >= 4096 * SS));
+    }
+
+    #[test]
+    fn gpt_primary_header() {
+        let table = read_partition_table(&mut Cursor::new(gpt_image()), 512, 4096 * SS)
+            .unwrap()
+            .unwrap();
+        assert_eq!(table.style, TableStyle::Gpt);
+        let health = table.gpt_health.unwrap();
+        assert!(health.primary_ok && health.backup_ok);
+        assert_eq!(table.gpt.unwrap().current_lba, 1);
+        let kinds: Vec<(bool, bool)> = table
+            .partitions
+            .iter()
+            .map(|p| (p.is_esp(), p.is_msr()))
+            .collect();
+        assert_eq!(kinds, vec![(true, false), (false, true), (false, false)]);
+        let data = table.partition(3).unwrap();
+        assert_eq!(data.name, "Basic data partition");
+        assert_eq!(data.offset, 2592 * SS);
+        assert_eq!(data.length, (3999 - 2592 + 1) * SS);
+    }
+
+    #[test]
+    fn gpt_falls_back_to_backup_when_primary_corrupt() {
+        let mut img = gpt_image();
+        // 破坏主分区项数组：头 CRC 仍对，但分区项 CRC 不匹配
+        img[2 * SS as usize + 100] ^= 0xFF;
+        let table = read_partition_table(&mut Cursor::new(img), 512, 4096 * SS)
+            .unwrap()
+            .unwrap();
+        let health = table.gpt_health.unwrap();
+        assert!(!health.primary_ok && health.backup_ok);
+        assert_eq!(table.gpt.unwrap().current_lba, 4095);
+        assert_eq!(table.partitions.len(), 3);
+        assert!(table.partitions[0].is_esp());
+    }
+
+    #[test]
+    fn gpt_both_copies_corrupt_is_error() {
+        let mut img = gpt_image();
+        img[SS as usize + 40] ^= 0xFF;
+        img[4095 * SS as usize + 40] ^= 0xFF;
+        let err = read_partition_table(&mut Cursor::new(img), 512, 4096 * SS).unwrap_err();
+        assert!(matches!(err, TableError::NoValidGpt { .. }));
+        assert!(err.to_string().contains("CRC"));
+    }
+
+    #[test]
+    fn raw_disk_has_no_table() {
+        let img = vec![0u8; (64 * SS) as usize];
+        assert!(read_partition_table(&mut Cursor::new(img), 512, 64 * SS)
+            .unwrap()
+            .is_none());
+    }
+}
@@ -0,0 +1,877 @@
+//! 纯 Rust 的 MBR/GPT 分区表编辑与写入（两端共享）。
+//!
+//! 与 [`crate::partition_table`] 的读取配套：在内存里的 [`PartitionTable`] 上初始化、增删分区、
+//! 调整大小/位置、设置类型与属性，再由 [`write_partition_table`] 一次性写回。GPT 的主头、
+//! 备份头与两份分区项数组同时写出并重算 CRC32，保证两份始终一致。
+//!
+//! 新分区起点一律按 1 MiB 对齐（与 diskpart 一致）。只改分区表，不碰文件系统——格式化仍交给
+//! diskpart/format。MBR 仅支持主分区（最多 4 个），含逻辑分区的磁盘拒绝编辑。
+//!
+//! 写回时每个分区留在读入时的槽位（MBR 主分区表槽位、GPT 分区项下标），删除只清空对应的槽位，
+//! 这样 diskpart 的 `select partition N` 与按分区号定位的代码仍指向同一个分区。
+
+use std::fmt;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+use crate::diskpart_script::TableStyle;
+use crate::partition_table::{
+    crc32, DiskId, GptHeader, GptHealth, Guid, PartitionEntry, PartitionTable, PartitionType,
+    GPT_SIGNATURE,
+};
+
+/// 分区起点对齐粒度
+pub const ALIGNMENT: u64 = 1024 * 1024;
+/// 新建 GPT 的分区项数量与长度（UEFI 规范最小值，Windows 同此）
+const GPT_ENTRY_COUNT: u32 = 128;
+const GPT_ENTRY_SIZE: u32 = 128;
+/// MBR 主分区表槽位数
+const MBR_SLOTS: usize = 4;
+
+/// 编辑或写入分区表的错误
+#[derive(Debug)]
+pub enum EditError {
+    Io(io::Error),
+    /// 磁盘太小，放不下分区表与至少一个对齐的分区
+    DiskTooSmall,
+    NoSuchPartition(u32),
+    /// 没有足够的连续未分配空间（字节）
+    NoSpace {
+        requested: u64,
+        available: u64,
+    },
+    /// 指定位置与其他分区重叠或超出可用范围
+    Overlap {
+        offset: u64,
+        length: u64,
+    },
+    /// 指定偏移未按 1 MiB 对齐
+    Misaligned(u64),
+    ZeroSize,
+    /// 分区数超出分区表容量
+    TooManyPartitions(usize),
+    /// 分区类型/属性与分区表样式不符（如在 MBR 上设 GPT 类型 GUID）
+    StyleMismatch,
+    Unsupported(&'static str),
+}
+
+impl fmt::Display for EditError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            EditError::Io(e) => write!(f, "写入磁盘失败: {}", e),
+            EditError::DiskTooSmall => write!(f, "磁盘容量太小，无法创建分区表"),
+            EditError::NoSuchPartition(n) => write!(f, "分区 {} 不存在", n),
+            EditError::NoSpace {
+                requested,
+                available,
+            } => write!(
+                f,
+                "未分配空间不足（需要 {} MiB，可用 {} MiB）",
+                requested / ALIGNMENT,
+                available / ALIGNMENT
+            ),
+            EditError::Overlap { offset, length } => write!(
+                f,
+                "范围 {}+{} 与其他分区重叠或超出磁盘可用范围",
+                offset, length
+            ),
+            EditError::Misaligned(v) => write!(f, "偏移 {} 未按 1 MiB 对齐", v),
+            EditError::ZeroSize => write!(f, "分区大小不能为 0"),
+            EditError::TooManyPartitions(max) => write!(f, "分区数超过上限 {}", max),
+            EditError::StyleMismatch => write!(f, "分区类型或属性与分区表样式（MBR/GPT）不符"),
+            EditError::Unsupported(what) => write!(f, "不支持的操作: {}", what),
+        }
+    }
+}
+
+impl std::error::Error for EditError {}
+
+impl From<io::Error> for EditError {
+    fn from(e: io::Error) -> Self {
+        EditError::Io(e)
+    }
+}
+
+fn align_up(v: u64) -> u64 {
+    v.div_ceil(ALIGNMENT) * ALIGNMENT
+}
+
+fn align_down(v: u64) -> u64 {
+    v / ALIGNMENT * ALIGNMENT
+}
+
+/// 新分区的参数（链式设置；未指定大小则占满所在的未分配区域）
+#[derive(Debug, Clone)]
+pub struct NewPartition {
+    partition_type: PartitionType,
+    size: Option<u64>,
+    offset: Option<u64>,
+    name: String,
+    attributes: u64,
+}
+
+impl NewPartition {
+    pub fn new(partition_type: PartitionType) -> Self {
+        NewPartition {
+            partition_type,
+            size: None,
+            offset: None,
+            name: String::new(),
+            attributes: 0,
+        }
+    }
+
+    /// 大小（字节，向下取整到 1 MiB）
+    pub fn size(mut self, bytes: u64) -> Self {
+        self.size = Some(bytes);
+        self
+    }
+
+    /// 起始偏移（字节，向上取整到 1 MiB）；不指定则取第一个放得下的未分配区域
+    pub fn offset(mut self, bytes: u64) -> Self {
+        self.offset = Some(bytes);
+        self
+    }
+
+    /// GPT 分区名
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = name.to_string();
+        self
+    }
+
+    /// GPT 属性位
+    pub fn attributes(mut self, attributes: u64) -> Self {
+        self.attributes = attributes;
+        self
+    }
+}
+
+impl PartitionTable {
+    /// 初始化一张空的 GPT（128 个分区项，主/备份各一份）
+    pub fn new_gpt(sector_size: u32, disk_size: u64) -> Result<Self, EditError> {
+        let ss = u64::from(sector_size);
+        let entries_sectors = (u64::from(GPT_ENTRY_COUNT) * u64::from(GPT_ENTRY_SIZE)).div_ceil(ss);
+        let last_lba = (disk_size / ss)
+            .checked_sub(1)
+            .ok_or(EditError::DiskTooSmall)?;
+        let first_usable_lba = 2 + entries_sectors;
+        let last_usable_lba = last_lba
+            .checked_sub(1 + entries_sectors)
+            .ok_or(EditError::DiskTooSmall)?;
+        if align_up(first_usable_lba * ss) + ALIGNMENT > (last_usable_lba + 1) * ss {
+            return Err(EditError::DiskTooSmall);
+        }
+        let disk_guid = Guid::generate();
+        Ok(PartitionTable {
+            style: TableStyle::Gpt,
+            sector_size,
+            disk_size,
+            disk_id: DiskId::Gpt(disk_guid),
+            gpt: Some(GptHeader {
+                disk_guid,
+                current_lba: 1,
+                alternate_lba: last_lba,
+                first_usable_lba,
+                last_usable_lba,
+                entries_lba: 2,
+                entry_count: GPT_ENTRY_COUNT,
+                entry_size: GPT_ENTRY_SIZE,
+                entries_crc: 0,
+            }),
+            gpt_health: Some(GptHealth {
+                primary_ok: true,
+                backup_ok: true,
+            }),
+            partitions: Vec::new(),
+        })
+    }
+
+    /// 初始化一张空的 MBR（随机磁盘签名）
+    pub fn new_mbr(sector_size: u32, disk_size: u64) -> Result<Self, EditError> {
+        if disk_size < 2 * ALIGNMENT {
+            return Err(EditError::DiskTooSmall);
+        }
+        let g = Guid::generate().0;
+        Ok(PartitionTable {
+            style: TableStyle::Mbr,
+            sector_size,
+            disk_size,
+            disk_id: DiskId::Mbr(u32::from_le_bytes([g[0], g[1], g[2], g[3]])),
+            gpt: None,
+            gpt_health: None,
+            partitions: Vec::new(),
+        })
+    }
+
+    /// 可放置分区的字节范围 `[start, end)`
+    pub fn usable_range(&self) -> (u64, u64) {
+        let ss = u64::from(self.sector_size);
+        match &self.gpt {
+            Some(h) => (h.first_usable_lba * ss, (h.last_usable_lba + 1) * ss),
+            // MBR 的起始 LBA 与扇区数都是 32 位
+            None => (ss, self.disk_size.min(u64::from(u32::MAX) * ss)),
+        }
+    }
+
+    /// 未分配区域 `[start, end)`，起点已按 1 MiB 对齐，按偏移排序；不足 1 MiB 的零头不计
+    pub fn free_extents(&self) -> Vec<(u64, u64)> {
+        let (usable_start, usable_end) = self.usable_range();
+        let mut sorted: Vec<&PartitionEntry> = self.partitions.iter().collect();
+        sorted.sort_by_key(|p| p.offset);
+        let mut extents = Vec::new();
+        let mut cursor = usable_start;
+        for (offset, end) in sorted
+            .iter()
+            .map(|p| (p.offset, p.end()))
+            .chain([(usable_end, usable_end)])
+        {
+            let start = align_up(cursor);
+            if start + ALIGNMENT <= offset {
+                extents.push((start, offset));
+            }
+            cursor = cursor.max(end);
+        }
+        extents
+    }
+
+    fn capacity(&self) -> usize {
+        match &self.gpt {
+            Some(h) => h.entry_count as usize,
+            None => MBR_SLOTS,
+        }
+    }
+
+    /// 第一个空闲的槽位（MBR 主分区表槽位或 GPT 分区项下标）
+    fn free_slot(&self) -> Option<u32> {
+        (0..self.capacity() as u32).find(|&slot| self.partitions.iter().all(|p| p.slot != slot))
+    }
+
+    /// 按 Windows 的规则重新编号：按槽位顺序从 1 开始（可编辑的表没有逻辑分区）
+    fn renumber(&mut self) {
+        let mut slots: Vec<u32> = self.partitions.iter().map(|p| p.slot).collect();
+        slots.sort_unstable();
+        for p in &mut self.partitions {
+            p.number = slots
+                .iter()
+                .position(|&s| s == p.slot)
+                .map_or(0, |i| i as u32 + 1);
+        }
+    }
+
+    fn check_editable(&self) -> Result<(), EditError> {
+        if self.partitions.iter().any(|p| p.logical || p.extended) {
+            return Err(EditError::Unsupported(
+                "编辑含扩展分区或逻辑分区的 MBR 磁盘",
+            ));
+        }
+        Ok(())
+    }
+
+    fn check_type(&self, partition_type: PartitionType) -> Result<(), EditError> {
+        match (self.style, partition_type) {
+            (TableStyle::Gpt, PartitionType::Gpt(_)) | (TableStyle::Mbr, PartitionType::Mbr(_)) => {
+                Ok(())
+            }
+            _ => Err(EditError::StyleMismatch),
+        }
+    }
+
+    fn index_of(&self, number: u32) -> Result<usize, EditError> {
+        self.partitions
+            .iter()
+            .position(|p| p.number == number)
+            .ok_or(EditError::NoSuchPartition(number))
+    }
+
+    /// 校验 `[offset, offset+length)` 落在可用范围内且不与其他分区（`skip` 除外）重叠
+    fn check_extent(&self, skip: Option<u32>, offset: u64, length: u64) -> Result<(), EditError> {
+        let (start, end) = self.usable_range();
+        let overlap = offset < start
+            || offset + length > end
+            || self
+                .partitions
+                .iter()
+                .filter(|p| Some(p.number) != skip)
+                .any(|p| offset < p.end() && p.offset < offset + length);
+        if overlap {
+            return Err(EditError::Overlap { offset, length });
+        }
+        Ok(())
+    }
+
+    /// 新建分区，放进第一个空闲的槽位，返回其分区号
+    pub fn add_partition(&mut self, spec: NewPartition) -> Result<u32, EditError> {
+        self.check_editable()?;
+        self.check_type(spec.partition_type)?;
+        let slot = self
+            .free_slot()
+            .ok_or(EditError::TooManyPartitions(self.capacity()))?;
+        let length = match spec.size {
+            Some(bytes) if align_down(bytes) == 0 => return Err(EditError::ZeroSize),
+            Some(bytes) => Some(align_down(bytes)),
+            None => None,
+        };
+        let extents = self.free_extents();
+        let (offset, length) = match spec.offset {
+            Some(wanted) => {
+                let start = align_up(wanted);
+                let (_, end) = extents
+                    .iter()
+                    .copied()
+                    .find(|&(s, e)| s <= start && start < e)
+                    .ok_or(EditError::Overlap {
+                        offset: start,
+                        length: length.unwrap_or(0),
+                    })?;
+                let length = length.unwrap_or(end - start);
+                if start + length > end {
+                    return Err(EditError::NoSpace {
+                        requested: length,
+                        available: end - start,
+                    });
+                }
+                (start, length)
+            }
+            None => {
+                let fits = extents
+                    .iter()
+                    .copied()
+                    .find(|&(s, e)| e - s >= length.unwrap_or(1));
+                let (start, end) = fits.ok_or(EditError::NoSpace {
+                    requested: length.unwrap_or(0),
+                    available: extents.iter().map(|(s, e)| e - s).max().unwrap_or(0),
+                })?;
+                (start, length.unwrap_or(end - start))
+            }
+        };
+
+        let gpt = self.style == TableStyle::Gpt;
+        self.partitions.push(PartitionEntry {
+            number: 0,
+            slot,
+            partition_type: spec.partition_type,
+            offset,
+            length,
+            active: false,
+            logical: false,
+            extended: false,
+            unique_guid: gpt.then(Guid::generate),
+            attributes: if gpt { spec.attributes } else { 0 },
+            name: if gpt { spec.name } else { String::new() },
+        });
+        self.partitions.sort_by_key(|p| p.offset);
+        self.renumber();
+        Ok(self
+            .partitions
+            .iter()
+            .find(|p| p.slot == slot)
+            .map_or(0, |p| p.number))
+    }
+
+    /// 删除分区，写回时只清空它所在的槽位，其他分区留在原槽位；
+    /// 其后的分区号依次前移（与重新读盘得到的编号一致）
+    pub fn delete_partition(&mut self, number: u32) -> Result<PartitionEntry, EditError> {
+        self.check_editable()?;
+        let removed = self.partitions.remove(self.index_of(number)?);
+        self.renumber();
+        Ok(removed)
+    }
+
+    /// 调整分区大小（起点不变）。`None` 表示扩展到紧随其后的未分配空间末尾
+    pub fn resize_partition(
+        &mut self,
+        number: u32,
+        new_length: Option<u64>,
+    ) -> Result<u64, EditError> {
+        self.check_editable()?;
+        let idx = self.index_of(number)?;
+        let offset = self.partitions[idx].offset;
+        let length = match new_length {
+            Some(bytes) if align_down(bytes) == 0 => return Err(EditError::ZeroSize),
+            Some(bytes) => align_down(bytes),
+            None => {
+                let (_, usable_end) = self.usable_range();
+                let limit = self
+                    .partitions
+                    .iter()
+                    .map(|p| p.offset)
+                    .filter(|&o| o > offset)
+                    .min()
+                    .unwrap_or(usable_end);
+                limit - offset
+            }
+        };
+        self.check_extent(Some(number), offset, length)?;
+        self.partitions[idx].length = length;
+        Ok(length)
+    }
+
+    /// 把分区表项移到新起点（长度不变，不搬移数据）
+    pub fn move_partition(&mut self, number: u32, new_offset: u64) -> Result<(), EditError> {
+        self.check_editable()?;
+        if !new_offset.is_multiple_of(ALIGNMENT) {
+            return Err(EditError::Misaligned(new_offset));
+        }
+        let idx = self.index_of(number)?;
+        self.check_extent(Some(number), new_offset, self.partitions[idx].length)?;
+        self.partitions[idx].offset = new_offset;
+        self.partitions.sort_by_key(|p| p.offset);
+        Ok(())
+    }
+
+    /// 设置分区类型（GPT 类型 GUID 或 MBR 类型字节，须与分区表样式一致）
+    pub fn set_partition_type(
+        &mut self,
+        number: u32,
+        partition_type: PartitionType,
+    ) -> Result<(), EditError> {
+        self.check_type(partition_type)?;
+        let idx = self.index_of(number)?;
+        self.partitions[idx].partition_type = partition_type;
+        Ok(())
+    }
+
+    /// 设置 GPT 属性位
+    pub fn set_attributes(&mut self, number: u32, attributes: u64) -> Result<(), EditError> {
+        if self.style != TableStyle::Gpt {
+            return Err(EditError::StyleMismatch);
+        }
+        let idx = self.index_of(number)?;
+        self.partitions[idx].attributes = attributes;
+        Ok(())
+    }
+
+    /// 设置 MBR 活动分区（同时清除其他分区的活动标志）
+    pub fn set_active(&mut self, number: u32) -> Result<(), EditError> {
+        if self.style != TableStyle::Mbr {
+            return Err(EditError::StyleMismatch);
+        }
+        self.index_of(number)?;
+        for p in &mut self.partitions {
+            p.active = p.number == number;
+        }
+        Ok(())
+    }
+}
+
+fn read_sector<D: Read + Seek>(disk: &mut D, lba: u64, ss: u64) -> io::Result<Vec<u8>> {
+    let mut buf = vec![0u8; ss as usize];
+    disk.seek(SeekFrom::Start(lba * ss))?;
+    disk.read_exact(&mut buf)?;
+    Ok(buf)
+}
+
+fn write_at<D: Write + Seek>(disk: &mut D, lba: u64, ss: u64, data: &[u8]) -> io::Result<()> {
+    disk.seek(SeekFrom::Start(lba * ss))?;
+    disk.write_all(data)
+}
+
+/// 填一个 16 字节的 MBR 分区项（CHS 一律写成 LBA 模式的占位值）
+fn put_mbr_entry(slot: &mut [u8], active: bool, part_type: u8, lba: u32, sectors: u32) {
+    slot.fill(0);
+    slot[0] = if active { 0x80 } else { 0 };
+    slot[1..4].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    slot[4] = part_type;
+    slot[5..8].copy_from_slice(&[0xFE, 0xFF, 0xFF]);
+    slot[8..12].copy_from_slice(&lba.to_le_bytes());
+    slot[12..16].copy_from_slice(&sectors.to_le_bytes());
+}
+
+fn to_u32(v: u64) -> Result<u32, EditError> {
+    u32::try_from(v).map_err(|_| EditError::Unsupported("MBR 分区超出 2 TiB 寻址范围"))
+}
+
+/// 按槽位排好的分区表项；槽位越界或重复时报错
+fn by_slot(
+    table: &PartitionTable,
+    slots: usize,
+) -> Result<Vec<Option<&PartitionEntry>>, EditError> {
+    let mut out = vec![None; slots];
+    for p in &table.partitions {
+        match out.get_mut(p.slot as usize) {
+            Some(e @ None) => *e = Some(p),
+            Some(Some(_)) => return Err(EditError::Unsupported("两个分区占用同一个分区表槽位")),
+            None => return Err(EditError::TooManyPartitions(slots)),
+        }
+    }
+    Ok(out)
+}
+
+fn gpt_header_sector(
+    h: &GptHeader,
+    current: u64,
+    alternate: u64,
+    entries_lba: u64,
+    entries_crc: u32,
+    ss: u64,
+) -> Vec<u8> {
+    let mut s = vec![0u8; ss as usize];
+    s[0..8].copy_from_slice(GPT_SIGNATURE);
+    s[8..12].copy_from_slice(&0x0001_0000u32.to_le_bytes());
+    s[12..16].copy_from_slice(&92u32.to_le_bytes());
+    s[24..32].copy_from_slice(&current.to_le_bytes());
+    s[32..40].copy_from_slice(&alternate.to_le_bytes());
+    s[40..48].copy_from_slice(&h.first_usable_lba.to_le_bytes());
+    s[48..56].copy_from_slice(&h.last_usable_lba.to_le_bytes());
+    s[56..72].copy_from_slice(&h.disk_guid.0);
+    s[72..80].copy_from_slice(&entries_lba.to_le_bytes());
+    s[80..84].copy_from_slice(&h.entry_count.to_le_bytes());
+    s[84..88].copy_from_slice(&h.entry_size.to_le_bytes());
+    s[88..92].copy_from_slice(&entries_crc.to_le_bytes());
+    let crc = crc32(&s[..92]);
+    s[16..20].copy_from_slice(&crc.to_le_bytes());
+    s
+}
+
+fn write_mbr<D: Read + Write + Seek>(
+    disk: &mut D,
+    table: &PartitionTable,
+) -> Result<(), EditError> {
+    let ss = u64::from(table.sector_size);
+    let parts = by_slot(table, MBR_SLOTS)?;
+    // 保留引导代码（前 440 字节）
+    let mut mbr = read_sector(disk, 0, ss)?;
+    let signature = match table.disk_id {
+        DiskId::Mbr(sig) => sig,
+        DiskId::Gpt(_) => 0,
+    };
+    mbr[440..444].copy_from_slice(&signature.to_le_bytes());
+    mbr[444..446].fill(0);
+    for slot in 0..MBR_SLOTS {
+        let entry = &mut mbr[446 + slot * 16..446 + (slot + 1) * 16];
+        match parts[slot] {
+            Some(p) => {
+                let PartitionType::Mbr(t) = p.partition_type else {
+                    return Err(EditError::StyleMismatch);
+                };
+                put_mbr_entry(
+                    entry,
+                    p.active,
+                    t,
+                    to_u32(p.offset / ss)?,
+                    to_u32(p.length / ss)?,
+                );
+            }
+            None => entry.fill(0),
+        }
+    }
+    mbr[510] = 0x55;
+    mbr[511] = 0xAA;
+    write_at(disk, 0, ss, &mbr)?;
+    Ok(())
+}
+
+fn write_gpt<D: Read + Write + Seek>(
+    disk: &mut D,
+    table: &PartitionTable,
+) -> Result<(), EditError> {
+    let ss = u64::from(table.sector_size);
+    let h = table
+        .gpt
+        .as_ref()
+        .ok_or(EditError::Unsupported("GPT 分区表缺少头信息"))?;
+    let parts = by_slot(table, h.entry_count as usize)?;
+
+    let entry_size = h.entry_size as usize;
+    let entries_len = h.entry_count as usize * entry_size;
+    let entries_sectors = (entries_len as u64).div_ceil(ss);
+    let mut entries = vec![0u8; (entries_sectors * ss) as usize];
+    for (i, p) in parts.iter().enumerate() {
+        let Some(p) = p else { continue };
+        let PartitionType::Gpt(type_guid) = p.partition_type else {
+            return Err(EditError::StyleMismatch);
+        };
+        let e = &mut entries[i * entry_size..(i + 1) * entry_size];
+        e[0..16].copy_from_slice(&type_guid.0);
+        e[16..32].copy_from_slice(&p.unique_guid.unwrap_or_else(Guid::generate).0);
+        e[32..40].copy_from_slice(&(p.offset / ss).to_le_bytes());
+        e[40..48].copy_from_slice(&((p.end() / ss) - 1).to_le_bytes());
+        e[48..56].copy_from_slice(&p.attributes.to_le_bytes());
+        for (j, unit) in p.name.encode_utf16().take(36).enumerate() {
+            e[56 + j * 2..58 + j * 2].copy_from_slice(&unit.to_le_bytes());
+        }
+    }
+    let entries_crc = crc32(&entries[..entries_len]);
+
+    let last_lba = (table.disk_size / ss)
+        .checked_sub(1)
+        .ok_or(EditError::DiskTooSmall)?;
+    let backup_entries_lba = last_lba
+        .checked_sub(entries_sectors)
+        .ok_or(EditError::DiskTooSmall)?;
+    if 2 + entries_sectors > h.first_usable_lba || h.last_usable_lba >= backup_entries_lba {
+        return Err(EditError::Unsupported("GPT 可用范围与分区项数组位置冲突"));
+    }
+
+    // 先写备份，再写主表：中途失败时至少有一份完整
+    write_at(disk, backup_entries_lba, ss, &entries)?;
+    let backup = gpt_header_sector(h, last_lba, 1, backup_entries_lba, entries_crc, ss);
+    write_at(disk, last_lba, ss, &backup)?;
+    write_at(disk, 2, ss, &entries)?;
+    let primary = gpt_header_sector(h, 1, last_lba, 2, entries_crc, ss);
+    write_at(disk, 1, ss, &primary)?;
+
+    // 保护性 MBR：保留引导代码，只留一个覆盖整盘的 0xEE 项
+    let mut mbr = read_sector(disk, 0, ss)?;
+    mbr[446..510].fill(0);
+    let protective = &mut mbr[446..462];
+    put_mbr_entry(
+        protective,
+        false,
+        0xEE,
+        1,
+        last_lba.min(u64::from(u32::MAX)) as u32,
+    );
+    protective[1..4].copy_from_slice(&[0x00, 0x02, 0x00]);
+    mbr[510] = 0x55;
+    mbr[511] = 0xAA;
+    write_at(disk, 0, ss, &mbr)?;
+    Ok(())
+}
+
+/// 把分区表写入磁盘（或磁盘镜像）。GPT 同时写主/备份头与分区项数组，MBR 保留引导代码。
+pub fn write_partition_table<D: Read + Write + Seek>(
+    disk: &mut D,
+    table: &PartitionTable,
+) -> Result<(), EditError> {
+    table.check_editable()?;
+    match table.style {
+        TableStyle::Mbr => write_mbr(disk, table)?,
+        TableStyle::Gpt => write_gpt(disk, table)?,
+    }
+    disk.flush()?;
+    Ok(())
+}
+
+/// 把分区表写入物理磁盘 `disk_number`，并通知系统重新读取分区布局（需管理员权限）。
+///
+/// 写前核对扇区大小与磁盘容量，防止把为另一块盘生成的分区表写错地方。
+pub fn write_physical_drive(disk_number: u32, table: &PartitionTable) -> Result<(), EditError> {
+    let path = format!("\\\\.\\PhysicalDrive{}", disk_number);
+    let mut file = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(&path)?;
+    let (sector_size, disk_size) = crate::partition_table::drive_geometry(&mut file)?;
+    if sector_size != table.sector_size || disk_size != table.disk_size {
+        return Err(EditError::Unsupported(
+            "分区表与目标磁盘的扇区大小或容量不符",
+        ));
+    }
+    write_partition_table(&mut file, table)?;
+    update_disk_properties(&file);
+    Ok(())
+}
+
+/// `IOCTL_DISK_UPDATE_PROPERTIES`：让系统丢弃缓存的分区布局并重新读盘。
+#[cfg(windows)]
+pub(crate) fn update_disk_properties(file: &std::fs::File) {
+    use std::os::windows::io::AsRawHandle;
+    use windows::Win32::Foundation::HANDLE;
+    use windows::Win32::System::IO::DeviceIoControl;
+
+    const IOCTL_DISK_UPDATE_PROPERTIES: u32 = 0x0007_0140;
+
+    let mut returned = 0u32;
+    let res = unsafe {
+        DeviceIoControl(
+            HANDLE(file.as_raw_handle() as _),
+            IOCTL_DISK_UPDATE_PROPERTIES,
+            None,
+            0,
+            None,
+            0,
+            Some(&mut returned),
+            None,
+        )
+    };
+    if let Err(e) = res {
+        log::warn!("通知系统刷新分区布局失败: {}", e);
+    }
+}
+
+#[cfg(not(windows))]
+pub(crate) fn update_disk_properties(_file: &std::fs::File) {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partition_table::read_partition_table;
+    use std::io::Cursor;
+
+    const DISK: u64 = 16 * ALIGNMENT;
+
+    fn roundtrip(img: &mut Cursor<Vec<u8>>, table: &PartitionTable) -> PartitionTable {
+        write_partition_table(img, table).unwrap();
+        read_partition_table(img, table.sector_size, table.disk_size)
+            .unwrap()
+            .unwrap()
+    }
+
+    #[test]
+    fn gpt_initialize_and_roundtrip() {
+        let mut table = PartitionTable::new_gpt(512, DISK).unwrap();
+        let esp = NewPartition::new(PartitionType::Gpt(Guid::ESP))
+            .size(4 * ALIGNMENT)
+            .name("EFI system partition");
+        assert_eq!(table.add_partition(esp).unwrap(), 1);
+        let msr = NewPartition::new(PartitionType::Gpt(Guid::MSR)).size(ALIGNMENT);
+        assert_eq!(table.add_partition(msr).unwrap(), 2);
+        let data = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA))
+            .name("Basic data partition")
+            .attributes(0x8000_0000_0000_0000);
+        assert_eq!(table.add_partition(data).unwrap(), 3);
+
+        let mut img = Cursor::new(vec![0u8; DISK as usize]);
+        let read = roundtrip(&mut img, &table);
+        let health = read.gpt_health.unwrap();
+        assert!(health.primary_ok && health.backup_ok);
+        assert_eq!(read.disk_id, table.disk_id);
+        assert_eq!(read.partitions, table.partitions);
+        assert_eq!(read.partitions[0].offset, ALIGNMENT);
+        assert_eq!(read.partitions[1].offset, 5 * ALIGNMENT);
+        // 数据分区占满到最后一个可用扇区
+        let (_, usable_end) = table.usable_range();
+        assert_eq!(read.partitions[2].end(), usable_end);
+        assert!(table.free_extents().is_empty());
+    }
+
+    #[test]
+    fn gpt_edit_keeps_backup_in_sync() {
+        let mut table = PartitionTable::new_gpt(512, DISK).unwrap();
+        for size in [2, 3, 4] {
+            let p = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA)).size(size * ALIGNMENT);
+            table.add_partition(p).unwrap();
+        }
+        let mut img = Cursor::new(vec![0u8; DISK as usize]);
+        roundtrip(&mut img, &table);
+
+        // 删掉中间的分区：后面的分区号前移，但仍留在原分区项；空出来的空间可被扩展
+        table.delete_partition(2).unwrap();
+        assert_eq!(table.partition(2).unwrap().length, 4 * ALIGNMENT);
+        assert_eq!(table.resize_partition(1, None).unwrap(), 5 * ALIGNMENT);
+        assert!(matches!(
+            table.resize_partition(1, Some(6 * ALIGNMENT)),
+            Err(EditError::Overlap { .. })
+        ));
+        table.move_partition(2, 8 * ALIGNMENT).unwrap();
+        assert!(matches!(
+            table.move_partition(2, 8 * ALIGNMENT + 512),
+            Err(EditError::Misaligned(_))
+        ));
+        table.set_attributes(2, 1).unwrap();
+
+        let read = roundtrip(&mut img, &table);
+        assert_eq!(read.partitions, table.partitions);
+        let slots: Vec<(u32, u32)> = read.partitions.iter().map(|p| (p.number, p.slot)).collect();
+        assert_eq!(slots, vec![(1, 0), (2, 2)]);
+        // 空出的分区项留给下一个新分区
+        let p = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA)).size(ALIGNMENT);
+        assert_eq!(table.clone().add_partition(p).unwrap(), 2);
+        // 破坏主表后从备份读到的仍是编辑后的布局
+        let mut raw = img.into_inner();
+        raw[512 + 20] ^= 0xFF;
+        let backup = read_partition_table(&mut Cursor::new(raw), 512, DISK)
+            .unwrap()
+            .unwrap();
+        assert!(!backup.gpt_health.unwrap().primary_ok);
+        assert_eq!(backup.partitions, table.partitions);
+    }
+
+    #[test]
+    fn mbr_keeps_boot_code_and_limits_slots() {
+        let mut table = PartitionTable::new_mbr(512, DISK).unwrap();
+        for _ in 0..3 {
+            let p = NewPartition::new(PartitionType::Mbr(0x07)).size(3 * ALIGNMENT);
+            table.add_partition(p).unwrap();
+        }
+        table
+            .add_partition(NewPartition::new(PartitionType::Mbr(0x0C)))
+            .unwrap();
+        assert!(matches!(
+            table.add_partition(NewPartition::new(PartitionType::Mbr(0x07))),
+            Err(EditError::TooManyPartitions(4))
+        ));
+        table.set_active(2).unwrap();
+        table
+            .set_partition_type(3, PartitionType::Mbr(0x27))
+            .unwrap();
+        assert!(matches!(
+            table.set_partition_type(3, PartitionType::Gpt(Guid::ESP)),
+            Err(EditError::StyleMismatch)
+        ));
+
+        let mut raw = vec![0u8; DISK as usize];
+        raw[..440].fill(0xCC);
+        let mut img = Cursor::new(raw);
+        let read = roundtrip(&mut img, &table);
+        assert_eq!(read.disk_id, table.disk_id);
+        assert_eq!(read.partitions, table.partitions);
+        assert_eq!(read.active_partition(), Some(2));
+        assert!(read.partition(3).unwrap().is_recovery());
+        assert!(img.get_ref()[..440].iter().all(|&b| b == 0xCC));
+    }
+
+    #[test]
+    fn mbr_delete_clears_only_its_slot() {
+        let mut table = PartitionTable::new_mbr(512, DISK).unwrap();
+        for _ in 0..3 {
+            let p = NewPartition::new(PartitionType::Mbr(0x07)).size(3 * ALIGNMENT);
+            table.add_partition(p).unwrap();
+        }
+        let mut img = Cursor::new(vec![0u8; DISK as usize]);
+        roundtrip(&mut img, &table);
+        let before = img.get_ref()[446..510].to_vec();
+
+        table.delete_partition(1).unwrap();
+        let read = roundtrip(&mut img, &table);
+        let after = &img.get_ref()[446..510];
+        assert!(after[..16].iter().all(|&b| b == 0));
+        assert_eq!(after[16..], before[16..]);
+        // 重新读盘：原 #2、#3 留在槽位 1、2，按 Windows 规则编号为 1、2
+        let slots: Vec<(u32, u32)> = read.partitions.iter().map(|p| (p.number, p.slot)).collect();
+        assert_eq!(slots, vec![(1, 1), (2, 2)]);
+        assert_eq!(read.partitions, table.partitions);
+
+        // 新分区进第一个空槽位，编号按槽位顺序排在最前
+        let p = NewPartition::new(PartitionType::Mbr(0x0C)).size(3 * ALIGNMENT);
+        assert_eq!(table.add_partition(p).unwrap(), 1);
+        let read = roundtrip(&mut img, &table);
+        assert_eq!(read.partitions, table.partitions);
+        assert_eq!(read.partition(1).unwrap().slot, 0);
+    }
+
+    #[test]
+    fn placement_errors() {
+        let mut table = PartitionTable::new_gpt(512, DISK).unwrap();
+        let big = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA)).size(64 * ALIGNMENT);
+        assert!(matches!(
+            table.add_partition(big),
+            Err(EditError::NoSpace { .. })
+        ));
+        let tiny = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA)).size(1000);
+        assert!(matches!(
+            table.add_partition(tiny),
+            Err(EditError::ZeroSize)
+        ));
+        let at = NewPartition::new(PartitionType::Gpt(Guid::BASIC_DATA))
+            .offset(3 * ALIGNMENT + 1)
+            .size(2 * ALIGNMENT);
+        table.add_partition(at).unwrap();
+        assert_eq!(table.partitions[0].offset, 4 * ALIGNMENT);
+        assert_eq!(
+            table.free_extents(),
+            vec![
+                (ALIGNMENT, 4 * ALIGNMENT),
+                (6 * ALIGNMENT, table.usable_range().1)
+            ]
+        );
+        assert!(matches!(
+            table.add_partition(NewPartition::new(PartitionType::Mbr(0x07))),
+            Err(EditError::StyleMismatch)
+        ));
+        assert!(matches!(
+            PartitionTable::new_gpt(512, ALIGNMENT),
+            Err(EditError::DiskTooSmall)
+        ));
+    }
+}
@@ -0,0 +1,671 @@
+//! 执行计划（dry-run）：在真正动手之前，按与安装 / 备份流程相同的规则列出将要执行的具体操作。
+//!
+//! 计划只做只读检查（列目录、看文件是否存在），不格式化、不写注册表、不改引导。
+//! 正常系统端用它在开始前给出确认页，命令行 `--plan` 输出 JSON，PE 端也可对落盘的配置出计划。
+//!
+//! 安装计划按 [`InstallPhase::planned`] 逐步展开，步骤取舍与 PE 安装流程一致；
+//! 注册表改动直接取自 [`offline_tweaks`](crate::offline_tweaks) 的改动表。
+
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::install_journal::InstallPhase;
+use crate::offline_tweaks::TweakOptions;
+
+/// 操作类别
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ActionKind {
+    Script,
+    Partition,
+    Format,
+    Verify,
+    ApplyImage,
+    CaptureImage,
+    Drivers,
+    Packages,
+    Registry,
+    Files,
+    Boot,
+    Bcd,
+    Unattend,
+    Cleanup,
+    Reboot,
+}
+
+impl ActionKind {
+    /// 是否会抹掉目标上的数据
+    pub fn is_destructive(self) -> bool {
+        matches!(
+            self,
+            ActionKind::Format
+                | ActionKind::ApplyImage
+                | ActionKind::Script
+                | ActionKind::Partition
+        )
+    }
+}
+
+/// 计划中的一项具体操作
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlannedAction {
+    /// 所属步骤（如「格式化分区」）
+    pub step: String,
+    pub kind: ActionKind,
+    /// 操作对象（分区、文件、注册表键等）
+    pub target: String,
+    #[serde(default)]
+    pub detail: String,
+}
+
+/// 一次完整流程的执行计划
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExecutionPlan {
+    /// "install" / "backup"
+    pub workflow: String,
+    /// 生成时间（Unix 秒）
+    pub created: u64,
+    pub actions: Vec<PlannedAction>,
+    /// 无法确定或可能出问题的地方（如脚本目录为空、驱动目录不存在）
+    #[serde(default)]
+    pub warnings: Vec<String>,
+}
+
+impl ExecutionPlan {
+    pub fn new(workflow: &str) -> Self {
+        Self {
+            workflow: workflow.to_string(),
+            created: std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs())
+                .unwrap_or(0),
+            ..Default::default()
+        }
+    }
+
+    pub fn push(
+        &mut self,
+        step: &str,
+        kind: ActionKind,
+        target: impl Into<String>,
+        detail: impl Into<String>,
+    ) {
+        self.actions.push(PlannedAction {
+            step: step.to_string(),
+            kind,
+            target: target.into(),
+            detail: detail.into(),
+        });
+    }
+
+    pub fn warn(&mut self, message: impl Into<String>) {
+        self.warnings.push(message.into());
+    }
+
+    /// 会抹掉数据的操作
+    pub fn destructive(&self) -> impl Iterator<Item = &PlannedAction> {
+        self.actions.iter().filter(|a| a.kind.is_destructive())
+    }
+
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap_or_default()
+    }
+
+    /// 纯文本形式（日志 / 控制台）
+    pub fn to_text(&self) -> String {
+        let mut out = String::new();
+        for (i, a) in self.actions.iter().enumerate() {
+            out.push_str(&format!("{:>3}. [{}] {}", i + 1, a.step, a.target));
+            if !a.detail.is_empty() {
+                out.push_str(&format!(" — {}", a.detail));
+            }
+            out.push('\n');
+        }
+        for w in &self.warnings {
+            out.push_str(&format!("  ! {}\n", w));
+        }
+        out
+    }
+}
+
+/// 经由 PE 执行时，正常系统端在重启前要做的准备
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PePreparation {
+    /// 存放配置 / 镜像 / 驱动的数据分区
+    pub data_partition: String,
+    /// 没有空间足够的现有分区时，从该分区缩小出临时数据分区
+    pub shrink_from: Option<String>,
+    /// PE 显示名
+    pub pe_name: String,
+    /// 镜像需先复制到数据目录时的源路径
+    pub copy_image_from: Option<String>,
+}
+
+/// 要导入的驱动从哪来
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub enum DriverSource {
+    /// 不导入
+    #[default]
+    None,
+    /// 已有的驱动目录
+    Dir(PathBuf),
+    /// 安装前从当前系统导出
+    ExportCurrent,
+}
+
+/// 安装计划的输入（各字段与安装配置一一对应；缺省值即 PE 安装流程的行为）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct InstallPlanInput {
+    pub target_partition: String,
+    /// 数据目录：diskpart 脚本、drivers、updates、自定义应答都从这里取
+    pub data_dir: String,
+    pub image_path: String,
+    pub volume_index: u32,
+    /// 镜像卷名（可为空）
+    pub image_name: String,
+    pub is_gho: bool,
+    pub is_xp: bool,
+    /// 引导方式；None 表示在 PE 中运行时探测
+    pub uefi: Option<bool>,
+    pub volume_label: String,
+    pub run_diskpart_scripts: bool,
+    /// Diskpart 脚本目录；None 表示数据目录下的 diskpart
+    pub diskpart_dir: Option<PathBuf>,
+    /// 正常系统端直接安装（不经 PE：不校验镜像，没有 PE 端的清理与离线登录兜底）
+    pub direct_install: bool,
+    /// 不格式化，直接覆盖释放（仅直接安装可选）
+    pub keep_partition: bool,
+    /// 不修复引导（仅直接安装可选）
+    pub skip_boot_repair: bool,
+    pub drivers: DriverSource,
+    pub driver_match_devices: bool,
+    pub install_cab_packages: bool,
+    pub tweaks: TweakOptions,
+    pub remove_uwp_apps: bool,
+    pub import_storage_controller_drivers: bool,
+    /// 磁盘控制器驱动目录；None 表示由 PE 在自身程序目录下定位
+    pub storage_controller_dir: Option<PathBuf>,
+    pub unattended: bool,
+    /// 自定义应答文件（空 = 内置生成）
+    pub custom_unattend: String,
+    pub custom_unattend_merge: bool,
+    pub custom_username: String,
+    /// 用户自定义修复引导脚本（bin\repair_boot.txt，存在时优先）
+    pub custom_boot_script: Option<PathBuf>,
+    pub auto_reboot: bool,
+    pub via_pe: Option<PePreparation>,
+}
+
+/// 备份格式
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CaptureFormat {
+    #[default]
+    Wim,
+    Esd,
+    Swm,
+    Gho,
+}
+
+/// 备份计划的输入
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackupPlanInput {
+    pub source_partition: String,
+    pub save_path: String,
+    pub name: String,
+    pub format: CaptureFormat,
+    pub incremental: bool,
+    pub swm_split_mb: u32,
+    pub auto_reboot: bool,
+    /// 由 PE 端备份流程执行（完成后删除 PE 启动项并清理标记）
+    pub runs_in_pe: bool,
+    pub via_pe: Option<PePreparation>,
+}
+
+/// 列出目录下（递归）指定扩展名的文件，按路径排序
+fn files_with_ext(dir: &Path, exts: &[&str]) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = walkdir::WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.into_path())
+        .filter(|p| {
+            p.extension()
+                .and_then(|e| e.to_str())
+                .map(|e| exts.iter().any(|x| e.eq_ignore_ascii_case(x)))
+                .unwrap_or(false)
+        })
+        .collect();
+    files.sort();
+    files
+}
+
+fn push_pe_preparation(plan: &mut ExecutionPlan, prep: &PePreparation) {
+    let step = "准备 PE 环境";
+    if let Some(from) = &prep.shrink_from {
+        plan.push(
+            step,
+            ActionKind::Partition,
+            from,
+            "缩小分区并创建临时数据分区",
+        );
+    }
+    if let Some(image) = &prep.copy_image_from {
+        plan.push(
+            step,
+            ActionKind::Files,
+            image,
+            format!("复制到数据分区 {}", prep.data_partition),
+        );
+    }
+    plan.push(
+        step,
+        ActionKind::Files,
+        &prep.data_partition,
+        "写入配置文件与分区标记",
+    );
+    plan.push(step, ActionKind::Bcd, &prep.pe_name, "添加一次性 PE 启动项");
+    plan.push(step, ActionKind::Reboot, "", "重启进入 PE 继续执行");
+}
+
+fn boot_mode_text(uefi: Option<bool>) -> &'static str {
+    match uefi {
+        Some(true) => "UEFI",
+        Some(false) => "Legacy",
+        None => "运行时探测",
+    }
+}
+
+fn plan_boot(plan: &mut ExecutionPlan, input: &InstallPlanInput) {
+    let step = InstallPhase::RepairBoot.label();
+    let target = &input.target_partition;
+    if input.is_xp {
+        let detail = match input.uefi {
+            Some(true) => "XP UEFI/GPT 引导（失败回退 ntldr/boot.ini）",
+            _ => "写入 ntldr / boot.ini",
+        };
+        plan.push(step, ActionKind::Boot, target, detail);
+        return;
+    }
+    plan.push(step, ActionKind::Bcd, "{current}", "删除当前 PE 启动项");
+    if let Some(script) = &input.custom_boot_script {
+        plan.push(
+            step,
+            ActionKind::Boot,
+            script.display().to_string(),
+            "自定义修复引导脚本（失败则回退默认逻辑）",
+        );
+    }
+    let win = format!("{}\\Windows", target);
+    match input.uefi {
+        Some(false) => {
+            plan.push(step, ActionKind::Boot, target, "bootsect /nt60 /mbr");
+            plan.push(
+                step,
+                ActionKind::Boot,
+                format!("bcdboot {} /f BIOS", win),
+                "",
+            );
+        }
+        uefi => {
+            if uefi.is_none() {
+                plan.warn("引导方式将在 PE 中探测：UEFI 写 ESP，Legacy 写 MBR");
+            }
+            plan.push(
+                step,
+                ActionKind::Boot,
+                format!("bcdboot {} /s <ESP> /f UEFI /l zh-cn", win),
+                boot_mode_text(uefi),
+            );
+            plan.push(
+                step,
+                ActionKind::Files,
+                "<ESP>\\EFI\\Boot\\bootx64.efi",
+                "复制 bootmgfw.efi 作为回退引导",
+            );
+        }
+    }
+}
+
+fn plan_drivers(plan: &mut ExecutionPlan, input: &InstallPlanInput) {
+    let step = InstallPhase::ImportDrivers.label();
+    let matching = if input.driver_match_devices {
+        "，仅导入与本机硬件匹配的驱动"
+    } else {
+        ""
+    };
+    let dir = match &input.drivers {
+        DriverSource::None => return,
+        DriverSource::ExportCurrent => {
+            plan.push(
+                step,
+                ActionKind::Drivers,
+                "当前系统",
+                format!("导出当前系统的第三方驱动后导入新系统{}", matching),
+            );
+            return;
+        }
+        DriverSource::Dir(dir) => dir,
+    };
+    if !dir.exists() {
+        plan.warn(format!("驱动目录不存在，将跳过驱动导入：{}", dir.display()));
+        return;
+    }
+    let infs = files_with_ext(dir, &["inf"]);
+    plan.push(
+        step,
+        ActionKind::Drivers,
+        dir.display().to_string(),
+        format!("{} 个 INF{}", infs.len(), matching),
+    );
+    for cab in files_with_ext(dir, &["cab"]) {
+        plan.push(
+            step,
+            ActionKind::Packages,
+            cab.display().to_string(),
+            "驱动目录中的 CAB 包",
+        );
+    }
+}
+
+fn plan_advanced(plan: &mut ExecutionPlan, input: &InstallPlanInput) {
+    let step = InstallPhase::AdvancedOptions.label();
+    for tweak in input.tweaks.selected() {
+        for change in tweak.changes() {
+            plan.push(step, ActionKind::Registry, change.describe(), tweak.label());
+        }
+    }
+    if input.remove_uwp_apps {
+        plan.push(
+            step,
+            ActionKind::Files,
+            "LetRecovery_Scripts\\remove_uwp.ps1",
+            "首次登录删除预装 UWP 应用",
+        );
+    }
+    if input.import_storage_controller_drivers {
+        match &input.storage_controller_dir {
+            Some(dir) if !dir.exists() => {
+                plan.warn(format!("磁盘控制器驱动目录不存在：{}", dir.display()));
+            }
+            Some(dir) => plan.push(
+                step,
+                ActionKind::Drivers,
+                dir.display().to_string(),
+                "磁盘控制器驱动",
+            ),
+            None => plan.push(
+                step,
+                ActionKind::Drivers,
+                "drivers\\storage_controller",
+                "磁盘控制器驱动（PE 程序目录）",
+            ),
+        }
+    }
+}
+
+/// 生成安装计划
+pub fn plan_install(input: &InstallPlanInput) -> ExecutionPlan {
+    let mut plan = ExecutionPlan::new("install");
+    if let Some(prep) = &input.via_pe {
+        push_pe_preparation(&mut plan, prep);
+    }
+
+    let target = input.target_partition.as_str();
+    let verify = !input.is_gho && !input.direct_install;
+    for phase in InstallPhase::planned(verify, input.run_diskpart_scripts) {
+        let step = phase.label();
+        match phase {
+            InstallPhase::Format if input.keep_partition => {}
+            InstallPhase::RepairBoot if input.skip_boot_repair => {}
+            InstallPhase::Cleanup if input.direct_install => {}
+            InstallPhase::VerifyImage => {
+                plan.push(step, ActionKind::Verify, &input.image_path, "");
+            }
+            InstallPhase::Diskpart => {
+                let dir = input
+                    .diskpart_dir
+                    .clone()
+                    .unwrap_or_else(|| Path::new(&input.data_dir).join("diskpart"));
+                let scripts = crate::diskpart::list_scripts(&dir);
+                if scripts.is_empty() {
+                    plan.warn(format!("没有可执行的 Diskpart 脚本：{}", dir.display()));
+                }
+                for (program, script) in scripts {
+                    plan.push(
+                        step,
+                        ActionKind::Script,
+                        script.display().to_string(),
+                        program,
+                    );
+                }
+            }
+            InstallPhase::Format => {
+                let detail = if input.volume_label.is_empty() {
+                    "NTFS".to_string()
+                } else {
+                    format!("NTFS，卷标 {}", input.volume_label)
+                };
+                plan.push(step, ActionKind::Format, target, detail);
+            }
+            InstallPhase::ApplyImage => {
+                let detail = if input.is_gho {
+                    "Ghost 还原".to_string()
+                } else if input.image_name.is_empty() {
+                    format!("卷索引 {}", input.volume_index)
+                } else {
+                    format!("卷索引 {}（{}）", input.volume_index, input.image_name)
+                };
+                plan.push(
+                    step,
+                    ActionKind::ApplyImage,
+                    format!("{} -> {}", input.image_path, target),
+                    detail,
+                );
+            }
+            InstallPhase::ImportDrivers => plan_drivers(&mut plan, input),
+            InstallPhase::InstallCab => {
+                if input.install_cab_packages {
+                    let dir = Path::new(&input.data_dir).join("updates");
+                    let cabs = files_with_ext(&dir, &["cab"]);
+                    if cabs.is_empty() {
+                        plan.warn(format!("更新包目录为空或不存在：{}", dir.display()));
+                    }
+                    for cab in cabs {
+                        plan.push(step, ActionKind::Packages, cab.display().to_string(), "");
+                    }
+                }
+            }
+            InstallPhase::RepairBoot => plan_boot(&mut plan, input),
+            InstallPhase::AdvancedOptions => plan_advanced(&mut plan, input),
+            InstallPhase::Unattend => {
+                if input.unattended {
+                    let detail = match (
+                        input.custom_unattend.is_empty(),
+                        input.custom_unattend_merge,
+                    ) {
+                        (true, _) => "内置生成".to_string(),
+                        (false, true) => format!("{}（与内置配置合并）", input.custom_unattend),
+                        (false, false) => input.custom_unattend.clone(),
+                    };
+                    plan.push(
+                        step,
+                        ActionKind::Unattend,
+                        format!("{}\\Windows\\Panther\\unattend.xml", target),
+                        detail,
+                    );
+                }
+                if input.direct_install {
+                    continue;
+                }
+                let detail = if input.custom_username.is_empty() {
+                    "离线登录兜底：允许空密码登录".to_string()
+                } else {
+                    format!("离线登录兜底：{} 空密码自动登录", input.custom_username)
+                };
+                plan.push(
+                    step,
+                    ActionKind::Registry,
+                    format!("{}\\Windows\\System32\\config\\SAM", target),
+                    detail,
+                );
+            }
+            InstallPhase::Cleanup => {
+                plan.push(
+                    step,
+                    ActionKind::Cleanup,
+                    &input.data_dir,
+                    "删除临时数据与安装标记",
+                );
+                plan.push(
+                    step,
+                    ActionKind::Cleanup,
+                    target,
+                    "删除自动创建的数据分区并扩展目标分区",
+                );
+            }
+        }
+    }
+
+    if input.auto_reboot {
+        plan.push("完成", ActionKind::Reboot, "", "安装完成后自动重启");
+    }
+    plan
+}
+
+/// 生成备份计划
+pub fn plan_backup(input: &BackupPlanInput) -> ExecutionPlan {
+    let mut plan = ExecutionPlan::new("backup");
+    if let Some(prep) = &input.via_pe {
+        push_pe_preparation(&mut plan, prep);
+    }
+
+    let step = "备份系统";
+    let append = input.incremental
+        && matches!(input.format, CaptureFormat::Wim | CaptureFormat::Esd)
+        && Path::new(&input.save_path).exists();
+    let detail = match input.format {
+        CaptureFormat::Gho => "Ghost 备份".to_string(),
+        CaptureFormat::Swm => format!("SWM 分卷，每卷 {} MB", input.swm_split_mb),
+        CaptureFormat::Esd if append => "ESD，追加到已有镜像".to_string(),
+        CaptureFormat::Esd => "ESD 高压缩".to_string(),
+        CaptureFormat::Wim if append => "WIM，追加到已有镜像".to_string(),
+        CaptureFormat::Wim => "WIM".to_string(),
+    };
+    let detail = if input.name.is_empty() {
+        detail
+    } else {
+        format!("{}，名称 {}", detail, input.name)
+    };
+    plan.push(
+        step,
+        ActionKind::CaptureImage,
+        format!("{} -> {}", input.source_partition, input.save_path),
+        detail,
+    );
+    if !append && Path::new(&input.save_path).exists() {
+        plan.warn(format!("目标文件已存在，将被覆盖：{}", input.save_path));
+    }
+    plan.push("验证备份", ActionKind::Verify, &input.save_path, "");
+    if input.via_pe.is_some() || input.runs_in_pe {
+        plan.push(
+            "恢复引导",
+            ActionKind::Bcd,
+            "{current}",
+            "删除当前 PE 启动项",
+        );
+        plan.push(
+            "清理",
+            ActionKind::Cleanup,
+            &input.source_partition,
+            "删除备份标记与临时数据",
+        );
+    }
+    if input.auto_reboot {
+        plan.push("完成", ActionKind::Reboot, "", "备份完成后自动重启");
+    }
+    plan
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn install_plan_follows_phases() {
+        let dir = std::env::temp_dir().join(format!("lr_plan_{}", std::process::id()));
+        std::fs::create_dir_all(dir.join("diskpart")).unwrap();
+        std::fs::write(dir.join("diskpart").join("01.txt"), "list disk").unwrap();
+
+        let input = InstallPlanInput {
+            target_partition: "D:".into(),
+            data_dir: dir.to_string_lossy().into_owned(),
+            image_path: "E:\\win.wim".into(),
+            volume_index: 3,
+            uefi: Some(true),
+            run_diskpart_scripts: true,
+            drivers: DriverSource::Dir(dir.join("drivers")),
+            tweaks: TweakOptions {
+                bypass_nro: true,
+                ..Default::default()
+            },
+            auto_reboot: true,
+            ..Default::default()
+        };
+        let plan = plan_install(&input);
+        let kinds: Vec<ActionKind> = plan.actions.iter().map(|a| a.kind).collect();
+        assert_eq!(
+            &kinds[..4],
+            &[
+                ActionKind::Verify,
+                ActionKind::Script,
+                ActionKind::Format,
+                ActionKind::ApplyImage
+            ]
+        );
+        assert_eq!(plan.actions[1].detail, "diskpart");
+        assert!(plan.actions[3].detail.contains("卷索引 3"));
+        assert!(plan
+            .actions
+            .iter()
+            .any(|a| a.kind == ActionKind::Registry && a.target.contains("BypassNRO")));
+        assert_eq!(plan.actions.last().unwrap().kind, ActionKind::Reboot);
+        // 驱动目录不存在只给警告，不列操作
+        assert!(plan.warnings.iter().any(|w| w.contains("驱动目录不存在")));
+        assert_eq!(plan.destructive().count(), 3);
+
+        let back: ExecutionPlan = serde_json::from_str(&plan.to_json()).unwrap();
+        assert_eq!(back, plan);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn backup_plan_via_pe() {
+        let input = BackupPlanInput {
+            source_partition: "C:".into(),
+            save_path: "D:\\__no_such_dir__\\b.wim".into(),
+            format: CaptureFormat::Wim,
+            incremental: true,
+            via_pe: Some(PePreparation {
+                data_partition: "D:".into(),
+                pe_name: "LetRecovery PE".into(),
+                shrink_from: None,
+                copy_image_from: None,
+            }),
+            ..Default::default()
+        };
+        let plan = plan_backup(&input);
+        assert_eq!(plan.workflow, "backup");
+        assert_eq!(plan.actions[0].kind, ActionKind::Files);
+        let capture = plan
+            .actions
+            .iter()
+            .find(|a| a.kind == ActionKind::CaptureImage)
+            .unwrap();
+        // 文件不存在时不追加
+        assert_eq!(capture.detail, "WIM");
+        assert!(plan.actions.iter().any(|a| a.kind == ActionKind::Bcd));
+        assert!(plan.warnings.is_empty());
+    }
+}
@@ -0,0 +1,69 @@
+//! Windows 产品密钥格式校验（`XXXXX-XXXXX-XXXXX-XXXXX-XXXXX`）。
+//!
+//! XP/2003 的 `winnt.sif`（[`crate::winnt_sif::ProductKey`]）与 Vista 起的 `unattend.xml`
+//! （[`crate::unattend::normalize_product_key`]）共用这一份规则，只是可用字符集不同。
+
+/// 密钥允许的字符集。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyCharset {
+    /// NT5 的 24 字符 Base24 字母表（去掉了易混淆的 A E I L N O S U Z 0 1 5）。
+    Base24,
+    /// Vista 起的密钥（含 N 等 Base24 以外的字符），只要求是字母或数字。
+    Alphanumeric,
+}
+
+/// NT5 产品密钥使用的 24 个字符。
+const BASE24_ALPHABET: &str = "BCDFGHJKMPQRTVWXY2346789";
+
+impl KeyCharset {
+    fn allows(self, c: char) -> bool {
+        match self {
+            KeyCharset::Base24 => BASE24_ALPHABET.contains(c),
+            KeyCharset::Alphanumeric => c.is_ascii_alphanumeric(),
+        }
+    }
+}
+
+/// 校验并规整产品密钥：去首尾空白、转大写，要求 5 组 × 5 位且字符都在 `charset` 内。
+pub fn normalize(key: &str, charset: KeyCharset) -> Result<String, String> {
+    let k = key.trim().to_ascii_uppercase();
+    let groups: Vec<&str> = k.split('-').collect();
+    if groups.len() != 5 || groups.iter().any(|g| g.chars().count() != 5) {
+        return Err(format!(
+            "产品密钥格式应为 XXXXX-XXXXX-XXXXX-XXXXX-XXXXX（5 组 × 5 位）：{}",
+            k
+        ));
+    }
+    if let Some(c) = k.chars().find(|&c| c != '-' && !charset.allows(c)) {
+        return Err(match charset {
+            KeyCharset::Base24 => format!(
+                "产品密钥含非法字符 '{}'（XP/2003 密钥不含 A E I L N O S U Z 0 1 5）",
+                c
+            ),
+            KeyCharset::Alphanumeric => format!("产品密钥含非法字符 '{}'", c),
+        });
+    }
+    Ok(k)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn charsets() {
+        let vista = "VK7JG-NPHTM-C97JM-9MPGT-3V66T";
+        assert_eq!(
+            normalize(&vista.to_lowercase(), KeyCharset::Alphanumeric).unwrap(),
+            vista
+        );
+        // N 不在 Base24 字母表里
+        assert!(normalize(vista, KeyCharset::Base24)
+            .unwrap_err()
+            .contains("'N'"));
+        assert!(normalize("BCDFG-HJKMP-QRTVW-XY234-6789B", KeyCharset::Base24).is_ok());
+        assert!(normalize("BCDFG-HJKMP", KeyCharset::Alphanumeric).is_err());
+        assert!(normalize("BCDFGH-JKMP-QRTVW-XY234-6789B", KeyCharset::Alphanumeric).is_err());
+        assert!(normalize("ABCD!-12345-ABCDE-12345-ABCDE", KeyCharset::Alphanumeric).is_err());
+    }
+}
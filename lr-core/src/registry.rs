@@ -2,7 +2,7 @@
 
 use anyhow::Result;
 
-use crate::command::new_command;
+use crate::command;
 use crate::encoding::gbk_to_utf8;
 
 pub struct OfflineRegistry;
@@ -11,9 +11,7 @@ impl OfflineRegistry {
     /// 加载离线注册表配置单元
     pub fn load_hive(hive_name: &str, hive_file: &str) -> Result<()> {
         let key_path = format!("HKLM\\{}", hive_name);
-        let output = new_command("reg.exe")
-            .args(["load", &key_path, hive_file])
-            .output()?;
+        let output = command::run("reg.exe", ["load", &key_path, hive_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -32,7 +30,7 @@ impl OfflineRegistry {
 
         // 尝试多次卸载，因为有时需要等待
         for _ in 0..3 {
-            let output = new_command("reg.exe").args(["unload", &key_path]).output()?;
+            let output = command::run("reg.exe", ["unload", &key_path])?;
 
             if output.status.success() {
                 return Ok(());
@@ -40,7 +38,7 @@ impl OfflineRegistry {
             std::thread::sleep(std::time::Duration::from_millis(500));
         }
 
-        let output = new_command("reg.exe").args(["unload", &key_path]).output()?;
+        let output = command::run("reg.exe", ["unload", &key_path])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -53,12 +51,10 @@ impl OfflineRegistry {
 
     /// 写入 DWORD 值
     pub fn set_dword(key_path: &str, value_name: &str, data: u32) -> Result<()> {
-        let output = new_command("reg.exe")
-            .args([
-                "add", key_path, "/v", value_name, "/t", "REG_DWORD", "/d",
-                &data.to_string(), "/f",
-            ])
-            .output()?;
+        let output = command::run("reg.exe", [
+            "add", key_path, "/v", value_name, "/t", "REG_DWORD", "/d",
+            &data.to_string(), "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -69,11 +65,9 @@ impl OfflineRegistry {
 
     /// 写入字符串值
     pub fn set_string(key_path: &str, value_name: &str, data: &str) -> Result<()> {
-        let output = new_command("reg.exe")
-            .args([
-                "add", key_path, "/v", value_name, "/t", "REG_SZ", "/d", data, "/f",
-            ])
-            .output()?;
+        let output = command::run("reg.exe", [
+            "add", key_path, "/v", value_name, "/t", "REG_SZ", "/d", data, "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -84,11 +78,9 @@ impl OfflineRegistry {
 
     /// 写入可扩展字符串值 (REG_EXPAND_SZ)
     pub fn set_expand_string(key_path: &str, value_name: &str, data: &str) -> Result<()> {
-        let output = new_command("reg.exe")
-            .args([
-                "add", key_path, "/v", value_name, "/t", "REG_EXPAND_SZ", "/d", data, "/f",
-            ])
-            .output()?;
+        let output = command::run("reg.exe", [
+            "add", key_path, "/v", value_name, "/t", "REG_EXPAND_SZ", "/d", data, "/f",
+        ])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -99,13 +91,13 @@ impl OfflineRegistry {
 
     /// 删除注册表键（忽略不存在）
     pub fn delete_key(key_path: &str) -> Result<()> {
-        let _ = new_command("reg.exe").args(["delete", key_path, "/f"]).output();
+        let _ = command::run("reg.exe", ["delete", key_path, "/f"]);
         Ok(())
     }
 
     /// 创建注册表键（如果不存在）
     pub fn create_key(key_path: &str) -> Result<()> {
-        let output = new_command("reg.exe").args(["add", key_path, "/f"]).output()?;
+        let output = command::run("reg.exe", ["add", key_path, "/f"])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
@@ -116,15 +108,13 @@ impl OfflineRegistry {
 
     /// 删除注册表值（忽略不存在）
     pub fn delete_value(key_path: &str, value_name: &str) -> Result<()> {
-        let _ = new_command("reg.exe")
-            .args(["delete", key_path, "/v", value_name, "/f"])
-            .output();
+        let _ = command::run("reg.exe", ["delete", key_path, "/v", value_name, "/f"]);
         Ok(())
     }
 
     /// 导入 .reg 文件
     pub fn import_reg_file(reg_file: &str) -> Result<()> {
-        let output = new_command("reg.exe").args(["import", reg_file]).output()?;
+        let output = command::run("reg.exe", ["import", reg_file])?;
 
         if !output.status.success() {
             let stderr = gbk_to_utf8(&output.stderr);
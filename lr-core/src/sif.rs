@@ -0,0 +1,713 @@
+//! 无损 INF/SIF 文档模型（`txtsetup.sif` / `winnt.sif` / 驱动 `.inf` 通用）。
+//!
+//! 与「拼字符串改节」不同，这里把文件按行建模，每行保留原文与换行符：
+//! - 节标题、`键=值`、无等号的裸行、`;` 注释、空行、带引号的字符串都原样保留、顺序不变；
+//! - 修改只作用于被改的那几行，其余行输出时**逐字节不变**（ANSI 文件保留每行原始字节，
+//!   连 GBK 里解不开的字节都不丢）；
+//! - 自动识别并保留原编码：ANSI（中文版即 GBK/CP936）、UTF-8（含/不含 BOM）、UTF-16LE。
+//!
+//! setupldr/setupdd 按 ANSI 读 SIF，改成 UTF-8 会在非 ASCII 行报「.SIF 文件第 N 行语法错误」，
+//! 所以「按原编码写回」是硬要求，而不是锦上添花。
+
+use crate::encoding::{gbk_to_utf8, utf8_to_gbk};
+
+/// 文档的文本编码。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SifEncoding {
+    /// 系统 ANSI 代码页（中文版 Windows 即 GBK）；纯 ASCII 文件也归为此类。
+    Ansi,
+    Utf8 {
+        bom: bool,
+    },
+    Utf16Le {
+        bom: bool,
+    },
+}
+
+/// 一行的语法类别（从行文本即时解析，不单独存储）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SifLineKind<'a> {
+    Blank,
+    /// 整行注释（`;` 开头）。
+    Comment,
+    /// 节标题，内容为去掉方括号、去首尾空白的节名（容忍行尾注释）。
+    Section(&'a str),
+    /// `键 = 值`：键去空白（保留引号），值去空白且不含行尾注释（保留引号）。
+    KeyValue {
+        key: &'a str,
+        value: &'a str,
+    },
+    /// 无 `=` 的数据行（如 `[SourceDisksNames]` 外的文件清单行），去空白、不含行尾注释。
+    Bare(&'a str),
+}
+
+#[derive(Debug, Clone)]
+struct Line {
+    text: String,
+    eol: &'static str,
+    /// 读入时的原始字节（仅 ANSI；含换行符）。行被修改后清空，输出时重新编码。
+    orig: Option<Vec<u8>>,
+}
+
+/// 无损 INF/SIF 文档。
+#[derive(Debug, Clone)]
+pub struct SifDocument {
+    encoding: SifEncoding,
+    lines: Vec<Line>,
+    /// 新增行使用的换行符（取自文件第一行，缺省 CRLF）。
+    eol: &'static str,
+}
+
+/// 去掉值两端成对的双引号（`"abc"` → `abc`）；不成对原样返回。
+pub fn unquote(s: &str) -> &str {
+    let t = s.trim();
+    if t.len() >= 2 && t.starts_with('"') && t.ends_with('"') {
+        &t[1..t.len() - 1]
+    } else {
+        t
+    }
+}
+
+/// 在引号外找第一个注释符 `;` 的位置。
+fn comment_start(text: &str) -> Option<usize> {
+    let mut in_quote = false;
+    for (i, c) in text.char_indices() {
+        match c {
+            '"' => in_quote = !in_quote,
+            ';' if !in_quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 引号外、注释前的第一个 `=` 的位置。
+fn equals_pos(text: &str) -> Option<usize> {
+    let end = comment_start(text).unwrap_or(text.len());
+    let mut in_quote = false;
+    for (i, c) in text[..end].char_indices() {
+        match c {
+            '"' => in_quote = !in_quote,
+            '=' if !in_quote => return Some(i),
+            _ => {}
+        }
+    }
+    None
+}
+
+/// 值在行文本中的字节区间（去掉两侧空白与行尾注释）。
+fn value_range(text: &str, eq: usize) -> (usize, usize) {
+    let end = comment_start(text).unwrap_or(text.len());
+    let rhs = &text[eq + 1..end];
+    let lead = rhs.len() - rhs.trim_start().len();
+    let start = eq + 1 + lead;
+    (start, start + rhs.trim().len())
+}
+
+fn classify(text: &str) -> SifLineKind<'_> {
+    let t = text.trim();
+    if t.is_empty() {
+        return SifLineKind::Blank;
+    }
+    if t.starts_with(';') {
+        return SifLineKind::Comment;
+    }
+    if let Some(rest) = t.strip_prefix('[') {
+        if let Some(close) = rest.find(']') {
+            return SifLineKind::Section(rest[..close].trim());
+        }
+    }
+    match equals_pos(text) {
+        Some(eq) => {
+            let (vs, ve) = value_range(text, eq);
+            SifLineKind::KeyValue {
+                key: text[..eq].trim(),
+                value: &text[vs..ve],
+            }
+        }
+        None => {
+            let end = comment_start(text).unwrap_or(text.len());
+            SifLineKind::Bare(text[..end].trim())
+        }
+    }
+}
+
+fn key_matches(key: &str, want: &str) -> bool {
+    unquote(key).eq_ignore_ascii_case(unquote(want))
+}
+
+/// 把字节流切成（行文本字节, 换行符）。只按 `\n` 切，GBK 双字节的尾字节不会是 0x0A，切分安全。
+fn split_lines_bytes(raw: &[u8]) -> Vec<(&[u8], &'static str)> {
+    let mut out = Vec::new();
+    let mut rest = raw;
+    while !rest.is_empty() {
+        match rest.iter().position(|&b| b == b'\n') {
+            Some(i) => {
+                let (body, eol) = if i > 0 && rest[i - 1] == b'\r' {
+                    (&rest[..i - 1], "\r\n")
+                } else {
+                    (&rest[..i], "\n")
+                };
+                out.push((body, eol));
+                rest = &rest[i + 1..];
+            }
+            None => {
+                out.push((rest, ""));
+                break;
+            }
+        }
+    }
+    out
+}
+
+fn split_lines_str(s: &str) -> Vec<(&str, &'static str)> {
+    let mut out = Vec::new();
+    for chunk in s.split_inclusive('\n') {
+        if let Some(body) = chunk.strip_suffix("\r\n") {
+            out.push((body, "\r\n"));
+        } else if let Some(body) = chunk.strip_suffix('\n') {
+            out.push((body, "\n"));
+        } else {
+            out.push((chunk, ""));
+        }
+    }
+    out
+}
+
+/// 无 BOM 的 UTF-16LE 粗判：前若干个奇数位字节大多为 0（ASCII 为主的 INF 即如此）。
+fn looks_like_utf16le(raw: &[u8]) -> bool {
+    if raw.len() < 4 || raw.len() & 1 == 1 {
+        return false;
+    }
+    let sample = &raw[..raw.len().min(512)];
+    let zeros = sample
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .filter(|&&b| b == 0)
+        .count();
+    zeros * 10 >= (sample.len() / 2) * 9
+}
+
+impl SifDocument {
+    /// 从文件原始字节解析，自动识别编码。
+    pub fn parse_bytes(raw: &[u8]) -> Self {
+        if let Some(body) = raw.strip_prefix(&[0xFF, 0xFE]) {
+            return Self::from_utf16le(body, true);
+        }
+        if looks_like_utf16le(raw) {
+            return Self::from_utf16le(raw, false);
+        }
+        if let Some(body) = raw.strip_prefix(&[0xEF, 0xBB, 0xBF]) {
+            let s = String::from_utf8_lossy(body);
+            return Self::from_text(&s, SifEncoding::Utf8 { bom: true });
+        }
+        if !raw.is_ascii() {
+            if let Ok(s) = std::str::from_utf8(raw) {
+                return Self::from_text(s, SifEncoding::Utf8 { bom: false });
+            }
+        }
+        let lines: Vec<Line> = split_lines_bytes(raw)
+            .into_iter()
+            .map(|(body, eol)| {
+                let mut orig = body.to_vec();
+                orig.extend_from_slice(eol.as_bytes());
+                Line {
+                    text: gbk_to_utf8(body),
+                    eol,
+                    orig: Some(orig),
+                }
+            })
+            .collect();
+        Self::with_lines(SifEncoding::Ansi, lines)
+    }
+
+    /// 从已解码文本构建（纯 ASCII 记为 ANSI，否则记为无 BOM 的 UTF-8）。
+    pub fn parse(text: &str) -> Self {
+        let text = text.strip_prefix('\u{feff}').unwrap_or(text);
+        let enc = if text.is_ascii() {
+            SifEncoding::Ansi
+        } else {
+            SifEncoding::Utf8 { bom: false }
+        };
+        Self::from_text(text, enc)
+    }
+
+    fn from_utf16le(body: &[u8], bom: bool) -> Self {
+        let u16s: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+            .collect();
+        let s = String::from_utf16_lossy(&u16s);
+        Self::from_text(&s, SifEncoding::Utf16Le { bom })
+    }
+
+    fn from_text(text: &str, encoding: SifEncoding) -> Self {
+        let lines = split_lines_str(text)
+            .into_iter()
+            .map(|(body, eol)| Line {
+                text: body.to_string(),
+                eol,
+                orig: None,
+            })
+            .collect();
+        Self::with_lines(encoding, lines)
+    }
+
+    fn with_lines(encoding: SifEncoding, lines: Vec<Line>) -> Self {
+        let eol = lines
+            .iter()
+            .map(|l| l.eol)
+            .find(|e| !e.is_empty())
+            .unwrap_or("\r\n");
+        Self {
+            encoding,
+            lines,
+            eol,
+        }
+    }
+
+    pub fn encoding(&self) -> SifEncoding {
+        self.encoding
+    }
+
+    /// 改变输出编码（如把 UTF-16 应答转成 setupldr 能读的 ANSI）。所有行将按新编码重新编码。
+    pub fn set_encoding(&mut self, encoding: SifEncoding) {
+        if encoding != self.encoding {
+            self.encoding = encoding;
+            for l in &mut self.lines {
+                l.orig = None;
+            }
+        }
+    }
+
+    /// 把所有行的换行统一为 CRLF（含最后一行）。已是 CRLF 的行不受影响。
+    pub fn use_crlf(&mut self) {
+        self.eol = "\r\n";
+        for l in &mut self.lines {
+            if l.eol != "\r\n" {
+                l.eol = "\r\n";
+                l.orig = None;
+            }
+        }
+    }
+
+    /// 按原编码输出字节。未修改的 ANSI 行原样输出读入时的字节。
+    pub fn to_bytes(&self) -> Vec<u8> {
+        match self.encoding {
+            SifEncoding::Ansi => {
+                let mut out = Vec::new();
+                for l in &self.lines {
+                    match &l.orig {
+                        Some(b) => out.extend_from_slice(b),
+                        None => {
+                            out.extend_from_slice(&utf8_to_gbk(&l.text));
+                            out.extend_from_slice(l.eol.as_bytes());
+                        }
+                    }
+                }
+                out
+            }
+            SifEncoding::Utf8 { bom } => {
+                let mut out = if bom {
+                    vec![0xEF, 0xBB, 0xBF]
+                } else {
+                    Vec::new()
+                };
+                out.extend_from_slice(self.to_text().as_bytes());
+                out
+            }
+            SifEncoding::Utf16Le { bom } => {
+                let mut out = if bom { vec![0xFF, 0xFE] } else { Vec::new() };
+                for u in self.to_text().encode_utf16() {
+                    out.extend_from_slice(&u.to_le_bytes());
+                }
+                out
+            }
+        }
+    }
+
+    /// 文档的文本形式（UTF-8 字符串，不含 BOM）。
+    pub fn to_text(&self) -> String {
+        let mut s = String::new();
+        for l in &self.lines {
+            s.push_str(&l.text);
+            s.push_str(l.eol);
+        }
+        s
+    }
+
+    /// 按顺序遍历每行的语法类别。
+    pub fn lines(&self) -> impl Iterator<Item = SifLineKind<'_>> {
+        self.lines.iter().map(|l| classify(&l.text))
+    }
+
+    /// 所有节名（按出现顺序，重复节会出现多次）。
+    pub fn section_names(&self) -> Vec<&str> {
+        self.lines()
+            .filter_map(|k| match k {
+                SifLineKind::Section(n) => Some(n),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn has_section(&self, section: &str) -> bool {
+        self.section_names()
+            .iter()
+            .any(|n| n.eq_ignore_ascii_case(section))
+    }
+
+    /// 某节（含同名重复节）内所有 `(键, 值)`，保留引号。
+    pub fn entries(&self, section: &str) -> Vec<(&str, &str)> {
+        let mut out = Vec::new();
+        let mut in_sec = false;
+        for k in self.lines() {
+            match k {
+                SifLineKind::Section(n) => in_sec = n.eq_ignore_ascii_case(section),
+                SifLineKind::KeyValue { key, value } if in_sec => out.push((key, value)),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// 某节内的裸行（无 `=` 的数据行）。
+    pub fn bare_lines(&self, section: &str) -> Vec<&str> {
+        let mut out = Vec::new();
+        let mut in_sec = false;
+        for k in self.lines() {
+            match k {
+                SifLineKind::Section(n) => in_sec = n.eq_ignore_ascii_case(section),
+                SifLineKind::Bare(b) if in_sec => out.push(b),
+                _ => {}
+            }
+        }
+        out
+    }
+
+    /// 取某节第一个 `key` 的值（保留引号；键名大小写不敏感、忽略引号）。
+    pub fn get(&self, section: &str, key: &str) -> Option<&str> {
+        self.entries(section)
+            .into_iter()
+            .find(|(k, _)| key_matches(k, key))
+            .map(|(_, v)| v)
+    }
+
+    pub fn has_key(&self, section: &str, key: &str) -> bool {
+        self.get(section, key).is_some()
+    }
+
+    /// 第一个名为 `section` 的节标题行下标。
+    fn header_index(&self, section: &str) -> Option<usize> {
+        self.lines
+            .iter()
+            .position(|l| matches!(classify(&l.text), SifLineKind::Section(n) if n.eq_ignore_ascii_case(section)))
+    }
+
+    /// 节 `header` 的结束位置（下一个节标题的下标，或文末）。
+    fn section_end(&self, header: usize) -> usize {
+        self.lines[header + 1..]
+            .iter()
+            .position(|l| matches!(classify(&l.text), SifLineKind::Section(_)))
+            .map(|i| header + 1 + i)
+            .unwrap_or(self.lines.len())
+    }
+
+    fn new_line(&self, text: String) -> Line {
+        Line {
+            text,
+            eol: self.eol,
+            orig: None,
+        }
+    }
+
+    /// 最后一行若没有换行符，补上（否则追加的行会粘在它后面）。
+    fn terminate_last_line(&mut self) {
+        let eol = self.eol;
+        if let Some(last) = self.lines.last_mut() {
+            if last.eol.is_empty() {
+                last.eol = eol;
+                last.orig = None;
+            }
+        }
+    }
+
+    /// 文末新建节，返回节标题行下标。
+    fn append_section(&mut self, section: &str) -> usize {
+        self.terminate_last_line();
+        let line = self.new_line(format!("[{}]", section));
+        self.lines.push(line);
+        self.lines.len() - 1
+    }
+
+    /// 把 `section` 节的 `key` 设为 `value`（原样写入，需要引号时由调用方带上）。
+    ///
+    /// - 键已存在：原位只替换值部分（键的写法、`=` 两侧空白、行尾注释都保留），同节后续重复键删除；
+    /// - 节存在但缺键：插到该节最后一个数据行之后；
+    /// - 节不存在：在文末新建节。
+    pub fn set(&mut self, section: &str, key: &str, value: &str) {
+        let mut found = false;
+        let mut in_sec = false;
+        let mut i = 0;
+        while i < self.lines.len() {
+            let text = &self.lines[i].text;
+            match classify(text) {
+                SifLineKind::Section(n) => {
+                    // 只改第一个同名节；后面的重复节原样不动
+                    if in_sec {
+                        break;
+                    }
+                    in_sec = n.eq_ignore_ascii_case(section);
+                }
+                SifLineKind::KeyValue { key: k, .. } if in_sec && key_matches(k, key) => {
+                    if found {
+                        self.lines.remove(i);
+                        continue;
+                    }
+                    let eq = equals_pos(text).unwrap_or(text.len());
+                    let (vs, ve) = value_range(text, eq);
+                    let new_text = format!("{}{}{}", &text[..vs], value, &text[ve..]);
+                    if new_text != *text {
+                        self.lines[i].text = new_text;
+                        self.lines[i].orig = None;
+                    }
+                    found = true;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        if !found {
+            self.append_lines(section, &[format!("{}={}", key, value)]);
+        }
+    }
+
+    /// 删除 `section` 节（含重复节）中所有名为 `key` 的行，返回删除行数。
+    pub fn remove_key(&mut self, section: &str, key: &str) -> usize {
+        let mut in_sec = false;
+        let before = self.lines.len();
+        self.lines.retain(|l| match classify(&l.text) {
+            SifLineKind::Section(n) => {
+                in_sec = n.eq_ignore_ascii_case(section);
+                true
+            }
+            SifLineKind::KeyValue { key: k, .. } => !(in_sec && key_matches(k, key)),
+            _ => true,
+        });
+        before - self.lines.len()
+    }
+
+    /// 在 `section` 节标题行之后（节内最前面）插入若干行；节不存在则在文末新建。
+    pub fn prepend_lines(&mut self, section: &str, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        let header = match self.header_index(section) {
+            Some(h) => {
+                if self.lines[h].eol.is_empty() {
+                    self.terminate_last_line();
+                }
+                h
+            }
+            None => self.append_section(section),
+        };
+        let new: Vec<Line> = lines.iter().map(|t| self.new_line(t.clone())).collect();
+        self.lines.splice(header + 1..header + 1, new);
+    }
+
+    /// 在 `section` 节最后一个数据行之后追加若干行（节尾的空行/注释保持在后）；节不存在则在文末新建。
+    pub fn append_lines(&mut self, section: &str, lines: &[String]) {
+        if lines.is_empty() {
+            return;
+        }
+        let header = match self.header_index(section) {
+            Some(h) => h,
+            None => self.append_section(section),
+        };
+        let end = self.section_end(header);
+        let at = (header + 1..end)
+            .rev()
+            .find(|&i| {
+                matches!(
+                    classify(&self.lines[i].text),
+                    SifLineKind::KeyValue { .. } | SifLineKind::Bare(_)
+                )
+            })
+            .map(|i| i + 1)
+            .unwrap_or(header + 1);
+        if at == self.lines.len() {
+            self.terminate_last_line();
+        } else if self.lines[at - 1].eol.is_empty() {
+            self.lines[at - 1].eol = self.eol;
+            self.lines[at - 1].orig = None;
+        }
+        let new: Vec<Line> = lines.iter().map(|t| self.new_line(t.clone())).collect();
+        self.lines.splice(at..at, new);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 含 GBK 中文注释/描述、CRLF 与 LF 混排、引号内分号、裸行、末行无换行。
+    fn gbk_sample() -> Vec<u8> {
+        let mut v = Vec::new();
+        v.extend_from_slice(b"; ");
+        v.extend_from_slice(&utf8_to_gbk("文本安装配置"));
+        v.extend_from_slice(b"\r\n[SCSI.Load]\r\natapi = atapi.sys,4\r\n\n[SCSI]\r\natapi = \"");
+        v.extend_from_slice(&utf8_to_gbk("标准 IDE;控制器"));
+        v.extend_from_slice(b"\" ; note\r\n[Files]\r\nntfs.sys\r\n   \r\nkey=1");
+        v
+    }
+
+    #[test]
+    fn ansi_roundtrip_is_byte_identical() {
+        let raw = gbk_sample();
+        let doc = SifDocument::parse_bytes(&raw);
+        assert_eq!(doc.encoding(), SifEncoding::Ansi);
+        assert_eq!(doc.to_bytes(), raw);
+    }
+
+    #[test]
+    fn undecodable_ansi_bytes_survive() {
+        // 0xFF 0x0D 不是合法 GBK，解码会变 U+FFFD，但未改动的行必须按原字节写回
+        let raw = b"[Data]\r\nx=\xFF\x81\r\ny=1\r\n".to_vec();
+        let mut doc = SifDocument::parse_bytes(&raw);
+        doc.set("Data", "y", "2");
+        assert_eq!(doc.to_bytes(), b"[Data]\r\nx=\xFF\x81\r\ny=2\r\n".to_vec());
+    }
+
+    #[test]
+    fn classify_quotes_and_comments() {
+        let doc = SifDocument::parse_bytes(&gbk_sample());
+        assert_eq!(doc.get("SCSI", "atapi"), Some("\"标准 IDE;控制器\""));
+        assert_eq!(
+            unquote(doc.get("SCSI", "atapi").unwrap()),
+            "标准 IDE;控制器"
+        );
+        assert_eq!(doc.bare_lines("Files"), vec!["ntfs.sys"]);
+        assert_eq!(doc.section_names(), vec!["SCSI.Load", "SCSI", "Files"]);
+        assert_eq!(doc.get("Files", "key"), Some("1"));
+    }
+
+    #[test]
+    fn set_in_place_keeps_spacing_and_comment() {
+        let mut doc = SifDocument::parse("[Data]\r\n  MsDosInitiated = \"0\"  ; keep\r\nb=2\r\n");
+        doc.set("Data", "msdosinitiated", "1");
+        assert_eq!(
+            doc.to_text(),
+            "[Data]\r\n  MsDosInitiated = 1  ; keep\r\nb=2\r\n"
+        );
+    }
+
+    #[test]
+    fn set_only_touches_edited_line() {
+        let raw = gbk_sample();
+        let mut doc = SifDocument::parse_bytes(&raw);
+        doc.set("SCSI.Load", "atapi", "atapi.sys,4,x");
+        let out = doc.to_bytes();
+        // 除被改行外，其余字节与原文一致
+        let needle = b"atapi = atapi.sys,4\r\n";
+        let pos = raw.windows(needle.len()).position(|w| w == needle).unwrap();
+        assert_eq!(&out[..pos], &raw[..pos]);
+        assert_eq!(&out[pos..pos + 23], b"atapi = atapi.sys,4,x\r\n");
+        assert_eq!(&out[pos + 23..], &raw[pos + needle.len()..]);
+    }
+
+    #[test]
+    fn set_appends_missing_key_and_section() {
+        let mut doc = SifDocument::parse("[Data]\r\na=1\r\n\r\n; tail\r\n[Other]\r\nz=9");
+        doc.set("Data", "b", "2");
+        doc.set("New", "k", "v");
+        assert_eq!(
+            doc.to_text(),
+            "[Data]\r\na=1\r\nb=2\r\n\r\n; tail\r\n[Other]\r\nz=9\r\n[New]\r\nk=v\r\n"
+        );
+    }
+
+    #[test]
+    fn set_dedups_within_first_section_only() {
+        let mut doc = SifDocument::parse("[Data]\r\nk=a\r\nk=b\r\n[X]\r\nk=c\r\n");
+        doc.set("Data", "k", "z");
+        assert_eq!(doc.to_text(), "[Data]\r\nk=z\r\n[X]\r\nk=c\r\n");
+    }
+
+    #[test]
+    fn header_with_comment_and_bom() {
+        let mut doc = SifDocument::parse_bytes(
+            b"\xEF\xBB\xBF[Data]  ; partition data\r\nMsDosInitiated=\"0\"\r\n",
+        );
+        assert_eq!(doc.encoding(), SifEncoding::Utf8 { bom: true });
+        doc.set("Data", "MsDosInitiated", "1");
+        assert_eq!(doc.section_names(), vec!["Data"]);
+        assert_eq!(
+            doc.to_bytes(),
+            b"\xEF\xBB\xBF[Data]  ; partition data\r\nMsDosInitiated=1\r\n".to_vec()
+        );
+    }
+
+    #[test]
+    fn utf16le_roundtrip_and_edit() {
+        let text =
+            "[Version]\r\nSignature=\"$Windows NT$\"\r\n[Strings]\r\nDesc=\"存储控制器\"\r\n";
+        let mut raw = vec![0xFF, 0xFE];
+        for u in text.encode_utf16() {
+            raw.extend_from_slice(&u.to_le_bytes());
+        }
+        let mut doc = SifDocument::parse_bytes(&raw);
+        assert_eq!(doc.encoding(), SifEncoding::Utf16Le { bom: true });
+        assert_eq!(doc.to_bytes(), raw);
+        doc.set("Strings", "Desc", "\"NVMe\"");
+        let reparsed = SifDocument::parse_bytes(&doc.to_bytes());
+        assert_eq!(reparsed.get("Strings", "desc"), Some("\"NVMe\""));
+        assert_eq!(reparsed.encoding(), SifEncoding::Utf16Le { bom: true });
+    }
+
+    #[test]
+    fn utf16le_without_bom_detected() {
+        let mut raw = Vec::new();
+        for u in "[A]\r\nx=1\r\n".encode_utf16() {
+            raw.extend_from_slice(&u.to_le_bytes());
+        }
+        let doc = SifDocument::parse_bytes(&raw);
+        assert_eq!(doc.encoding(), SifEncoding::Utf16Le { bom: false });
+        assert_eq!(doc.get("A", "x"), Some("1"));
+        assert_eq!(doc.to_bytes(), raw);
+    }
+
+    #[test]
+    fn prepend_after_header_and_create() {
+        let mut doc = SifDocument::parse(
+            "[SCSI]\r\natapi = \"IDE\"\r\n\r\n[SCSI.Load]\r\natapi = atapi.sys,4\r\n",
+        );
+        doc.prepend_lines("scsi.load", &["genahci = genahci.sys,4".to_string()]);
+        doc.prepend_lines("HardwareIdsDatabase", &["a = \"b\"".to_string()]);
+        assert_eq!(
+            doc.to_text(),
+            "[SCSI]\r\natapi = \"IDE\"\r\n\r\n[SCSI.Load]\r\ngenahci = genahci.sys,4\r\natapi = atapi.sys,4\r\n[HardwareIdsDatabase]\r\na = \"b\"\r\n"
+        );
+    }
+
+    #[test]
+    fn remove_key_and_has_key() {
+        let mut doc = SifDocument::parse("[D]\r\n\"Quoted Key\" = 1\r\nk=2\r\n");
+        assert!(doc.has_key("D", "quoted key"));
+        assert_eq!(doc.remove_key("D", "K"), 1);
+        assert_eq!(doc.to_text(), "[D]\r\n\"Quoted Key\" = 1\r\n");
+    }
+
+    #[test]
+    fn use_crlf_and_set_encoding() {
+        let mut doc = SifDocument::parse_bytes(b"[Data]\nAutoPartition=0");
+        doc.use_crlf();
+        assert_eq!(doc.to_bytes(), b"[Data]\r\nAutoPartition=0\r\n".to_vec());
+        doc.set("Data", "FullName", "\"张三\"");
+        doc.set_encoding(SifEncoding::Ansi);
+        let mut expected = b"[Data]\r\nAutoPartition=0\r\nFullName=\"".to_vec();
+        expected.extend_from_slice(&utf8_to_gbk("张三"));
+        expected.extend_from_slice(b"\"\r\n");
+        assert_eq!(doc.to_bytes(), expected);
+    }
+}
@@ -0,0 +1,356 @@
+//! 安装目标分区的稳定标识
+//!
+//! 正常系统里选的目标分区只是一个盘符（如 `C:`），重启进 PE 后盘符经常变，
+//! 光靠盘符或目标分区上的标记文件定位，一旦认错就会格式化别的分区。
+//! 这里在正常系统端记下磁盘签名/GPT 磁盘 GUID、GPT 分区 GUID、分区偏移与大小、卷序列号，
+//! PE 端按这些标识在所有卷里找回目标，找不到或不唯一就拒绝继续。
+
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::partition_table::{read_physical_drive, PartitionTable};
+
+/// 目标分区的标识（随安装配置写入 INI 的 `[Target]` 节）
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TargetIdentity {
+    /// MBR 磁盘签名（8 位十六进制）或 GPT 磁盘 GUID；读不到分区表（如动态磁盘）时为空
+    pub disk_id: String,
+    /// GPT 分区 GUID；MBR 为空
+    pub partition_guid: String,
+    /// 分区起始偏移（字节）
+    pub offset: u64,
+    /// 分区大小（字节）；0 表示没有记录标识
+    pub length: u64,
+    /// 卷序列号；0 表示未知。格式化后会变，只用来在多个候选中择一
+    pub volume_serial: u32,
+}
+
+/// 在所有卷中查找目标分区失败的原因
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolveError {
+    /// 没有记录标识（旧版本写的配置）
+    Unknown,
+    /// 没有任何卷与标识相符
+    NotFound,
+    /// 有多个卷与标识相符（如克隆出的同签名磁盘）
+    Ambiguous(Vec<String>),
+}
+
+impl fmt::Display for ResolveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ResolveError::Unknown => write!(f, "安装配置中没有目标分区标识"),
+            ResolveError::NotFound => write!(f, "没有找到与记录的磁盘/分区标识相符的分区"),
+            ResolveError::Ambiguous(letters) => {
+                write!(f, "有多个分区与记录的标识相符: {}", letters.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for ResolveError {}
+
+/// 候选卷与标识的相符程度
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum MatchLevel {
+    /// 只有卷序列号与大小相符（记录时没读到分区表）
+    Serial,
+    /// 磁盘与分区位置相符
+    Location,
+    /// 磁盘、分区位置与卷序列号都相符
+    Exact,
+}
+
+impl TargetIdentity {
+    /// 是否记录了标识
+    pub fn is_known(&self) -> bool {
+        self.length != 0
+    }
+
+    /// 按物理磁盘的分区表生成起始于 `offset` 的分区的标识
+    pub fn from_table(table: &PartitionTable, offset: u64, volume_serial: u32) -> Option<Self> {
+        let entry = table.partition_at(offset)?;
+        Some(TargetIdentity {
+            disk_id: table.disk_id.to_string(),
+            partition_guid: entry.unique_guid.map(|g| g.to_string()).unwrap_or_default(),
+            offset: entry.offset,
+            length: entry.length,
+            volume_serial,
+        })
+    }
+
+    /// 候选卷 `found` 与记录的标识相符到什么程度；大小不同一律不算
+    fn match_level(&self, found: &TargetIdentity) -> Option<MatchLevel> {
+        if !self.is_known() || self.length != found.length {
+            return None;
+        }
+        let serial_same = self.volume_serial != 0 && self.volume_serial == found.volume_serial;
+        if self.disk_id.is_empty() {
+            return serial_same.then_some(MatchLevel::Serial);
+        }
+        if !self.disk_id.eq_ignore_ascii_case(&found.disk_id) {
+            return None;
+        }
+        let same_partition = if self.partition_guid.is_empty() {
+            self.offset == found.offset
+        } else {
+            self.partition_guid
+                .eq_ignore_ascii_case(&found.partition_guid)
+        };
+        match (same_partition, serial_same) {
+            (true, true) => Some(MatchLevel::Exact),
+            (true, false) => Some(MatchLevel::Location),
+            (false, _) => None,
+        }
+    }
+
+    /// 在候选卷 `(盘符, 标识)` 中找回目标分区，只接受唯一的最佳匹配
+    pub fn resolve(&self, candidates: &[(String, TargetIdentity)]) -> Result<String, ResolveError> {
+        if !self.is_known() {
+            return Err(ResolveError::Unknown);
+        }
+        let matched: Vec<(MatchLevel, &String)> = candidates
+            .iter()
+            .filter_map(|(letter, found)| self.match_level(found).map(|l| (l, letter)))
+            .collect();
+        let Some(best) = matched.iter().map(|(l, _)| *l).max() else {
+            return Err(ResolveError::NotFound);
+        };
+        let letters: Vec<String> = matched
+            .into_iter()
+            .filter(|(l, _)| *l == best)
+            .map(|(_, letter)| letter.clone())
+            .collect();
+        match letters.as_slice() {
+            [one] => Ok(one.clone()),
+            _ => Err(ResolveError::Ambiguous(letters)),
+        }
+    }
+
+    /// 序列化为 INI 的 `[Target]` 节
+    pub fn to_ini(&self) -> String {
+        format!(
+            "[Target]\n\
+TargetDiskId={}\n\
+TargetPartitionGuid={}\n\
+TargetPartitionOffset={}\n\
+TargetPartitionLength={}\n\
+TargetVolumeSerial={:08X}\n",
+            self.disk_id, self.partition_guid, self.offset, self.length, self.volume_serial,
+        )
+    }
+
+    /// 读取 `[Target]` 节的一个键；不是本节的键返回 `false`
+    pub fn set_ini_key(&mut self, key: &str, value: &str) -> bool {
+        let v = value.trim();
+        match key {
+            "TargetDiskId" => self.disk_id = v.to_string(),
+            "TargetPartitionGuid" => self.partition_guid = v.to_string(),
+            "TargetPartitionOffset" => self.offset = v.parse().unwrap_or(0),
+            "TargetPartitionLength" => self.length = v.parse().unwrap_or(0),
+            "TargetVolumeSerial" => self.volume_serial = u32::from_str_radix(v, 16).unwrap_or(0),
+            _ => return false,
+        }
+        true
+    }
+}
+
+impl fmt::Display for TargetIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "磁盘 {} 分区 {} 偏移 {} 大小 {} 卷序列号 {:08X}",
+            if self.disk_id.is_empty() {
+                "?"
+            } else {
+                &self.disk_id
+            },
+            if self.partition_guid.is_empty() {
+                "-"
+            } else {
+                &self.partition_guid
+            },
+            self.offset,
+            self.length,
+            self.volume_serial
+        )
+    }
+}
+
+/// 查询卷序列号（`GetVolumeInformationW`），失败返回 0
+#[cfg(windows)]
+fn volume_serial(letter: char) -> u32 {
+    use windows::core::PCWSTR;
+    use windows::Win32::Storage::FileSystem::GetVolumeInformationW;
+
+    let wide: Vec<u16> = format!("{}:\\", letter)
+        .encode_utf16()
+        .chain(std::iter::once(0))
+        .collect();
+    let mut serial = 0u32;
+    let ok = unsafe {
+        GetVolumeInformationW(
+            PCWSTR::from_raw(wide.as_ptr()),
+            None,
+            Some(&mut serial as *mut u32),
+            None,
+            None,
+            None,
+        )
+    };
+    if ok.is_ok() {
+        serial
+    } else {
+        0
+    }
+}
+
+#[cfg(not(windows))]
+fn volume_serial(_letter: char) -> u32 {
+    0
+}
+
+/// 盘符 `letter` 所在分区的标识（`tables` 缓存各磁盘的分区表，同一磁盘只读一次）。
+///
+/// 读不到分区表（如动态磁盘）时只记偏移、大小与卷序列号。
+fn identify_with(
+    letter: char,
+    tables: &mut HashMap<u32, Option<PartitionTable>>,
+) -> Option<TargetIdentity> {
+    let (disk, offset, length) = crate::arc::volume_extent(&crate::arc::volume_device(&letter.to_string()))?;
+    let serial = volume_serial(letter);
+    let table = tables.entry(disk).or_insert_with(|| {
+        read_physical_drive(disk)
+            .ok()
+            .and_then(|layout| layout.table.ok().flatten())
+    });
+    match table {
+        Some(table) => TargetIdentity::from_table(table, offset, serial),
+        None => Some(TargetIdentity {
+            offset,
+            length,
+            volume_serial: serial,
+            ..Default::default()
+        }),
+    }
+}
+
+/// 读取盘符（如 `"C:"`）所在分区的标识；跨盘卷或查询失败时返回 `None`
+pub fn identify_volume(partition: &str) -> Option<TargetIdentity> {
+    let letter = partition.chars().next()?.to_ascii_uppercase();
+    identify_with(letter, &mut HashMap::new())
+}
+
+/// 列出所有带盘符的卷及其标识
+pub fn scan_volumes() -> Vec<(String, TargetIdentity)> {
+    let mut tables = HashMap::new();
+    ('A'..='Z')
+        .filter_map(|letter| {
+            identify_with(letter, &mut tables).map(|id| (format!("{}:", letter), id))
+        })
+        .collect()
+}
+
+/// 在本机所有卷中找回目标分区
+pub fn resolve_volume(identity: &TargetIdentity) -> Result<String, ResolveError> {
+    identity.resolve(&scan_volumes())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gpt_target() -> TargetIdentity {
+        TargetIdentity {
+            disk_id: "8C3D5F3A-1B2C-4D5E-8F90-0123456789AB".into(),
+            partition_guid: "11111111-2222-3333-4444-555555555555".into(),
+            offset: 0x1_0000_0000,
+            length: 0x20_0000_0000,
+            volume_serial: 0x1234_ABCD,
+        }
+    }
+
+    fn at(letter: &str, id: TargetIdentity) -> (String, TargetIdentity) {
+        (letter.to_string(), id)
+    }
+
+    #[test]
+    fn ini_round_trip() {
+        let id = gpt_target();
+        let mut back = TargetIdentity::default();
+        for line in id.to_ini().lines().skip(1) {
+            let (k, v) = line.split_once('=').unwrap();
+            assert!(back.set_ini_key(k, v));
+        }
+        assert_eq!(back, id);
+        assert!(!back.set_ini_key("TargetPartition", "C:"));
+        assert!(!TargetIdentity::default().is_known());
+    }
+
+    #[test]
+    fn resolves_by_location_after_letter_and_serial_change() {
+        let id = gpt_target();
+        // 盘符从 C: 变成了 D:，且已被格式化（卷序列号变了）
+        let mut formatted = id.clone();
+        formatted.volume_serial = 0x0BAD_F00D;
+        let mut other = id.clone();
+        other.partition_guid = "99999999-2222-3333-4444-555555555555".into();
+        let candidates = vec![at("C:", other), at("D:", formatted)];
+        assert_eq!(id.resolve(&candidates), Ok("D:".to_string()));
+
+        // 大小不同不算
+        let mut resized = id.clone();
+        resized.length -= 512;
+        assert_eq!(
+            id.resolve(&[at("C:", resized)]),
+            Err(ResolveError::NotFound)
+        );
+        assert_eq!(
+            TargetIdentity::default().resolve(&candidates),
+            Err(ResolveError::Unknown)
+        );
+    }
+
+    #[test]
+    fn mbr_uses_offset_and_serial_breaks_ties() {
+        let id = TargetIdentity {
+            disk_id: "5A5A0001".into(),
+            partition_guid: String::new(),
+            offset: 1024 * 1024,
+            length: 100 << 30,
+            volume_serial: 0xCAFE_0001,
+        };
+        // 克隆盘：两块盘签名相同、分区位置相同，只有卷序列号能区分
+        let mut clone = id.clone();
+        clone.volume_serial = 0xCAFE_0002;
+        assert_eq!(
+            id.resolve(&[at("C:", clone.clone()), at("E:", id.clone())]),
+            Ok("E:".to_string())
+        );
+        // 两块都格式化过：无法区分，拒绝
+        let mut formatted = id.clone();
+        formatted.volume_serial = 0xCAFE_0003;
+        assert_eq!(
+            id.resolve(&[at("C:", clone), at("E:", formatted)]),
+            Err(ResolveError::Ambiguous(vec!["C:".into(), "E:".into()]))
+        );
+        // 同一磁盘的另一个分区
+        let mut moved = id.clone();
+        moved.offset += 512;
+        assert_eq!(id.resolve(&[at("C:", moved)]), Err(ResolveError::NotFound));
+    }
+
+    #[test]
+    fn falls_back_to_serial_without_disk_id() {
+        let id = TargetIdentity {
+            disk_id: String::new(),
+            volume_serial: 0x0000_BEEF,
+            ..gpt_target()
+        };
+        let mut found = gpt_target();
+        found.volume_serial = 0x0000_BEEF;
+        assert_eq!(id.resolve(&[at("F:", found.clone())]), Ok("F:".to_string()));
+        found.volume_serial = 0;
+        assert_eq!(id.resolve(&[at("F:", found)]), Err(ResolveError::NotFound));
+    }
+}
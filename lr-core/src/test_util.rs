@@ -0,0 +1,24 @@
+//! 单元测试共用的夹具（仅 `cfg(test)` 编译）。
+
+/// 一个 Intel 网卡 INF：`Class=Net`、带 `CatalogFile`，声明 amd64 与 x86 两个平台，
+/// `[Models.NTamd64]` 里只有一行型号 `models_line`（如 `d=I, PCI\VEN_8086&DEV_15F3`）。
+pub(crate) fn net_inf(date: &str, models_line: &str) -> String {
+    format!(
+        "[Version]\nClass=Net\nProvider=%P%\nCatalogFile=net.cat\nDriverVer={}\n\
+         [Manufacturer]\n%P%=Models,NTamd64,NTx86\n\
+         [Models.NTamd64]\n{}\n\
+         [Strings]\nP=\"Intel\"\n",
+        date, models_line
+    )
+}
+
+/// 在 512 字节扇区镜像 `img` 的第 `lba` 扇区（MBR 或 EBR）写第 `slot` 个分区项并补上 0x55AA 签名。
+pub(crate) fn put_mbr_entry(img: &mut [u8], lba: u64, slot: usize, t: u8, start: u32, len: u32) {
+    let base = lba as usize * 512;
+    let e = base + 446 + slot * 16;
+    img[e + 4] = t;
+    img[e + 8..e + 12].copy_from_slice(&start.to_le_bytes());
+    img[e + 12..e + 16].copy_from_slice(&len.to_le_bytes());
+    img[base + 510] = 0x55;
+    img[base + 511] = 0xAA;
+}
@@ -0,0 +1,1510 @@
+//! Vista+ `unattend.xml` 的类型化模型与 XML 输出（两端共享）。
+//!
+//! 结构与应答文件一致：[`Unattend`] → 各配置阶段 [`PassSettings`]（`<settings pass=...>`）→
+//! [`Component`]（`<component name=... processorArchitecture=...>`）→ 设置树 [`Setting`]
+//! （列表项带 `wcm:action`）。输出经 [`XmlWriter`] 统一转义，用户名等含 `& < > "` 时也是合法 XML。
+//!
+//! [`DeployUnattend`] 是 LetRecovery 内置的部署应答（Win7 / Win8 / Win10+ 共用一个构建器，
+//! 按 [`WindowsGeneration`] 裁剪 OOBE 项），取代原先两端各自的 `format!` 模板。
+
+/// 应答文件根命名空间。
+pub const NS_UNATTEND: &str = "urn:schemas-microsoft-com:unattend";
+/// `wcm:` 命名空间（`wcm:action` 等）。
+pub const NS_WCM: &str = "http://schemas.microsoft.com/WMIConfig/2002/State";
+/// `xsi:` 命名空间。
+pub const NS_XSI: &str = "http://www.w3.org/2001/XMLSchema-instance";
+/// 微软组件统一的 publicKeyToken。
+pub const PUBLIC_KEY_TOKEN: &str = "31bf3856ad364e35";
+
+/// 配置阶段（`<settings pass="...">`）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Pass {
+    WindowsPE,
+    OfflineServicing,
+    Generalize,
+    Specialize,
+    AuditSystem,
+    AuditUser,
+    OobeSystem,
+}
+
+impl Pass {
+    /// 按 Setup 执行顺序排列。
+    pub const ALL: [Pass; 7] = [
+        Pass::WindowsPE,
+        Pass::OfflineServicing,
+        Pass::Generalize,
+        Pass::Specialize,
+        Pass::AuditSystem,
+        Pass::AuditUser,
+        Pass::OobeSystem,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Pass::WindowsPE => "windowsPE",
+            Pass::OfflineServicing => "offlineServicing",
+            Pass::Generalize => "generalize",
+            Pass::Specialize => "specialize",
+            Pass::AuditSystem => "auditSystem",
+            Pass::AuditUser => "auditUser",
+            Pass::OobeSystem => "oobeSystem",
+        }
+    }
+
+    /// 按属性值解析（大小写须与 Setup 一致，`windowspe` 之类 Setup 不认，这里也不认）。
+    pub fn parse(s: &str) -> Option<Pass> {
+        Pass::ALL.into_iter().find(|p| p.as_str() == s)
+    }
+}
+
+/// 列表项的 `wcm:action`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WcmAction {
+    Add,
+    Modify,
+    Remove,
+}
+
+impl WcmAction {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            WcmAction::Add => "add",
+            WcmAction::Modify => "modify",
+            WcmAction::Remove => "remove",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<WcmAction> {
+        [WcmAction::Add, WcmAction::Modify, WcmAction::Remove]
+            .into_iter()
+            .find(|a| a.as_str() == s)
+    }
+}
+
+/// 组件内的一个设置：叶子（`value`）或分组（`children`）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Setting {
+    pub name: String,
+    pub action: Option<WcmAction>,
+    /// 列表项的 `wcm:keyValue`（如 `PathAndCredentials`），内置应答不用，解析用户文件时保留。
+    pub key_value: Option<String>,
+    pub value: Option<String>,
+    pub children: Vec<Setting>,
+}
+
+impl Setting {
+    /// 叶子设置 `<name>value</name>`。
+    pub fn value(name: &str, value: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            action: None,
+            key_value: None,
+            value: Some(value.into()),
+            children: Vec::new(),
+        }
+    }
+
+    /// 分组设置 `<name>...</name>`。
+    pub fn group(name: &str, children: Vec<Setting>) -> Self {
+        Self {
+            name: name.to_string(),
+            action: None,
+            key_value: None,
+            value: None,
+            children,
+        }
+    }
+
+    /// 标记为列表项（`wcm:action="..."`）。
+    pub fn with_action(mut self, action: WcmAction) -> Self {
+        self.action = Some(action);
+        self
+    }
+
+    /// 第一个同名子设置。
+    pub fn child(&self, name: &str) -> Option<&Setting> {
+        self.children.iter().find(|c| c.name == name)
+    }
+}
+
+/// `<component>`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub name: String,
+    pub processor_architecture: String,
+    pub public_key_token: String,
+    pub language: String,
+    pub version_scope: String,
+    pub settings: Vec<Setting>,
+}
+
+impl Component {
+    /// 微软组件（neutral / nonSxS / 统一 publicKeyToken）。
+    pub fn new(name: &str, arch: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            processor_architecture: arch.to_string(),
+            public_key_token: PUBLIC_KEY_TOKEN.to_string(),
+            language: "neutral".to_string(),
+            version_scope: "nonSxS".to_string(),
+            settings: Vec::new(),
+        }
+    }
+
+    /// 第一个同名顶层设置。
+    pub fn setting(&self, name: &str) -> Option<&Setting> {
+        self.settings.iter().find(|s| s.name == name)
+    }
+
+    /// 设置顶层设置：已有同名的就替换，否则追加。
+    pub fn set(&mut self, setting: Setting) {
+        match self.settings.iter_mut().find(|s| s.name == setting.name) {
+            Some(s) => *s = setting,
+            None => self.settings.push(setting),
+        }
+    }
+}
+
+/// `<settings pass="...">`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PassSettings {
+    pub pass: Pass,
+    pub components: Vec<Component>,
+}
+
+/// 整个应答文件。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Unattend {
+    pub settings: Vec<PassSettings>,
+}
+
+impl Unattend {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 取某阶段的组件。
+    pub fn component(&self, pass: Pass, name: &str) -> Option<&Component> {
+        self.settings
+            .iter()
+            .find(|p| p.pass == pass)?
+            .components
+            .iter()
+            .find(|c| c.name == name)
+    }
+
+    /// 取某阶段的组件，没有就建（阶段按 Setup 执行顺序插入，组件追加到末尾）。
+    pub fn component_mut(&mut self, pass: Pass, name: &str, arch: &str) -> &mut Component {
+        let comps = &mut self.pass_mut(pass).components;
+        let ci = match comps.iter().position(|c| c.name == name) {
+            Some(i) => i,
+            None => {
+                comps.push(Component::new(name, arch));
+                comps.len() - 1
+            }
+        };
+        &mut comps[ci]
+    }
+
+    /// 取某阶段，没有就按 Setup 执行顺序插入一个空的。
+    fn pass_mut(&mut self, pass: Pass) -> &mut PassSettings {
+        let pi = match self.settings.iter().position(|p| p.pass == pass) {
+            Some(i) => i,
+            None => {
+                let rank = |p: Pass| Pass::ALL.iter().position(|x| *x == p);
+                let at = self
+                    .settings
+                    .iter()
+                    .position(|p| rank(p.pass) > rank(pass))
+                    .unwrap_or(self.settings.len());
+                self.settings.insert(
+                    at,
+                    PassSettings {
+                        pass,
+                        components: Vec::new(),
+                    },
+                );
+                at
+            }
+        };
+        &mut self.settings[pi]
+    }
+
+    /// 输出 UTF-8 XML 文本（4 空格缩进，LF 换行，与原模板一致）。
+    pub fn to_xml(&self) -> String {
+        let mut w = XmlWriter::new();
+        w.declaration();
+        w.open("unattend", &[("xmlns", NS_UNATTEND), ("xmlns:wcm", NS_WCM)]);
+        for ps in &self.settings {
+            w.open("settings", &[("pass", ps.pass.as_str())]);
+            for c in &ps.components {
+                // 用户应答里没写的属性（解析为空串）不输出，避免 `versionScope=""` 之类的非法值
+                let attrs: Vec<(&str, &str)> = [
+                    ("name", c.name.as_str()),
+                    ("processorArchitecture", &c.processor_architecture),
+                    ("publicKeyToken", &c.public_key_token),
+                    ("language", &c.language),
+                    ("versionScope", &c.version_scope),
+                    ("xmlns:wcm", NS_WCM),
+                    ("xmlns:xsi", NS_XSI),
+                ]
+                .into_iter()
+                .filter(|(_, v)| !v.is_empty())
+                .collect();
+                w.open("component", &attrs);
+                for s in &c.settings {
+                    write_setting(&mut w, s);
+                }
+                w.close("component");
+            }
+            w.close("settings");
+        }
+        w.close("unattend");
+        w.finish()
+    }
+}
+
+fn write_setting(w: &mut XmlWriter, s: &Setting) {
+    let mut attrs: Vec<(&str, &str)> = Vec::new();
+    if let Some(a) = s.action {
+        attrs.push(("wcm:action", a.as_str()));
+    }
+    if let Some(k) = &s.key_value {
+        attrs.push(("wcm:keyValue", k));
+    }
+    if s.children.is_empty() {
+        w.leaf(&s.name, &attrs, s.value.as_deref().unwrap_or(""));
+    } else {
+        w.open(&s.name, &attrs);
+        for c in &s.children {
+            write_setting(w, c);
+        }
+        w.close(&s.name);
+    }
+}
+
+/// XML 文本/属性值转义。
+pub fn escape_xml(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&apos;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+/// 极简缩进 XML 输出器（只需元素/属性/文本，足够应答文件用）。
+pub struct XmlWriter {
+    out: String,
+    depth: usize,
+}
+
+impl Default for XmlWriter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl XmlWriter {
+    pub fn new() -> Self {
+        Self {
+            out: String::new(),
+            depth: 0,
+        }
+    }
+
+    pub fn declaration(&mut self) {
+        self.out
+            .push_str("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n");
+    }
+
+    fn start_tag(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.out.push_str(&"    ".repeat(self.depth));
+        self.out.push('<');
+        self.out.push_str(name);
+        for (k, v) in attrs {
+            self.out.push_str(&format!(" {}=\"{}\"", k, escape_xml(v)));
+        }
+        self.out.push('>');
+    }
+
+    /// `<name attrs>` 并换行，后续内容缩进一级。
+    pub fn open(&mut self, name: &str, attrs: &[(&str, &str)]) {
+        self.start_tag(name, attrs);
+        self.out.push('\n');
+        self.depth += 1;
+    }
+
+    /// `</name>`。
+    pub fn close(&mut self, name: &str) {
+        self.depth = self.depth.saturating_sub(1);
+        self.out.push_str(&"    ".repeat(self.depth));
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    /// `<name attrs>text</name>`（空文本也写成成对标签，Setup 对 `<Value />` 有兼容问题）。
+    pub fn leaf(&mut self, name: &str, attrs: &[(&str, &str)], text: &str) {
+        self.start_tag(name, attrs);
+        self.out.push_str(&escape_xml(text));
+        self.out.push_str(&format!("</{}>\n", name));
+    }
+
+    /// 取输出（去掉末尾换行，与原模板一致）。
+    pub fn finish(mut self) -> String {
+        while self.out.ends_with('\n') {
+            self.out.pop();
+        }
+        self.out
+    }
+}
+
+// ───────────────────────── 解析与合并 ─────────────────────────
+
+impl Unattend {
+    /// 从 XML 解析（用于与用户自带的应答合并）。
+    ///
+    /// 保留阶段、组件属性、设置树及 `wcm:action` / `wcm:keyValue`；注释、`<cpi:offlineImage>`
+    /// 等不影响 Setup 行为的内容不保留。未知阶段视为错误（Setup 同样不认）。
+    pub fn parse(xml: &str) -> Result<Unattend, String> {
+        let s = xml.trim_start_matches('\u{feff}');
+        let doc = roxmltree::Document::parse(s).map_err(|e| format!("XML 语法错误：{}", e))?;
+        let root = doc.root_element();
+        if root.tag_name().name() != "unattend" {
+            return Err(format!(
+                "根元素应为 <unattend>，实际为 <{}>",
+                root.tag_name().name()
+            ));
+        }
+        let mut u = Unattend::new();
+        for settings in root
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "settings")
+        {
+            let raw = settings.attribute("pass").unwrap_or("");
+            let pass =
+                Pass::parse(raw).ok_or_else(|| format!("未知的配置阶段 pass=\"{}\"", raw))?;
+            let components = settings
+                .children()
+                .filter(|n| n.is_element() && n.tag_name().name() == "component")
+                .map(|c| Component {
+                    name: c.attribute("name").unwrap_or("").to_string(),
+                    processor_architecture: c
+                        .attribute("processorArchitecture")
+                        .unwrap_or("")
+                        .to_string(),
+                    public_key_token: c.attribute("publicKeyToken").unwrap_or("").to_string(),
+                    language: c.attribute("language").unwrap_or("").to_string(),
+                    version_scope: c.attribute("versionScope").unwrap_or("").to_string(),
+                    settings: parse_settings(c),
+                })
+                .collect();
+            u.settings.push(PassSettings { pass, components });
+        }
+        Ok(u)
+    }
+
+    /// 把 `ours`（LetRecovery 生成的应答）叠加到 `self`（用户的应答）上。优先级：
+    ///
+    /// 1. 阶段、组件按 (阶段, 组件名, 架构) 对应；只在一边出现的原样保留/补入；
+    /// 2. 同一组件内，两边都有的**单值设置以用户为准**（计算机名、OOBE 各项、自动登录账户等），
+    ///    只在内置里有的设置补入；分组设置逐层按同样规则合并；
+    /// 3. **列表**（带 `wcm:action` 的项：RunSynchronous、FirstLogonCommands、LocalAccounts 等）
+    ///    合并：用户的项在前，内置的项追加在后，`Order` 顺延到用户最大值之后；与用户已有项
+    ///    相同的（同 `Name` 的账户、同 `CommandLine` / `Path` 的命令）不重复添加。
+    ///
+    /// 即：用户明确写了的以用户为准，内置的部署脚本、首次登录命令、自定义账户照常补上。
+    pub fn merge(&mut self, ours: &Unattend) {
+        for ps in &ours.settings {
+            for comp in &ps.components {
+                let target = self.pass_mut(ps.pass);
+                match target.components.iter_mut().find(|c| {
+                    c.name.eq_ignore_ascii_case(&comp.name)
+                        && c.processor_architecture
+                            .eq_ignore_ascii_case(&comp.processor_architecture)
+                }) {
+                    Some(c) => merge_settings(&mut c.settings, &comp.settings),
+                    None => target.components.push(comp.clone()),
+                }
+            }
+        }
+    }
+}
+
+/// 按 BOM 解码应答文件原始字节：UTF-16LE/BE（有无 BOM 均可，按开头的 `<` 判断）、UTF-8（含 BOM）。
+/// 系统映像管理器（SIM）与不少编辑器保存的 unattend.xml 是 UTF-16，按 UTF-8 读会整份解析失败。
+pub fn decode_xml(raw: &[u8]) -> String {
+    let utf16 = |body: &[u8], be: bool| {
+        let units: Vec<u16> = body
+            .chunks_exact(2)
+            .map(|c| {
+                if be {
+                    u16::from_be_bytes([c[0], c[1]])
+                } else {
+                    u16::from_le_bytes([c[0], c[1]])
+                }
+            })
+            .collect();
+        String::from_utf16_lossy(&units)
+    };
+    match raw {
+        [0xFF, 0xFE, body @ ..] => utf16(body, false),
+        [b'<', 0, ..] => utf16(raw, false),
+        [0xFE, 0xFF, body @ ..] => utf16(body, true),
+        [0, b'<', ..] => utf16(raw, true),
+        [0xEF, 0xBB, 0xBF, body @ ..] => String::from_utf8_lossy(body).into_owned(),
+        _ => String::from_utf8_lossy(raw).into_owned(),
+    }
+}
+
+/// 解析用户应答，叠加内置生成的应答（优先级见 [`Unattend::merge`]），输出合并后的 XML。
+pub fn merge_xml(user_xml: &str, ours: &Unattend) -> Result<String, String> {
+    let mut merged = Unattend::parse(user_xml)?;
+    merged.merge(ours);
+    Ok(merged.to_xml())
+}
+
+fn parse_settings(node: roxmltree::Node) -> Vec<Setting> {
+    node.children()
+        .filter(|n| n.is_element())
+        .map(|n| {
+            let children = parse_settings(n);
+            let value = if children.is_empty() {
+                Some(
+                    n.children()
+                        .filter(|t| t.is_text())
+                        .filter_map(|t| t.text())
+                        .collect::<String>(),
+                )
+            } else {
+                None
+            };
+            Setting {
+                name: n.tag_name().name().to_string(),
+                action: n.attribute((NS_WCM, "action")).and_then(WcmAction::parse),
+                key_value: n.attribute((NS_WCM, "keyValue")).map(str::to_string),
+                value,
+                children,
+            }
+        })
+        .collect()
+}
+
+fn merge_settings(into: &mut Vec<Setting>, ours: &[Setting]) {
+    for o in ours {
+        if o.action.is_some() {
+            if into
+                .iter()
+                .any(|u| u.name == o.name && same_list_item(u, o))
+            {
+                continue;
+            }
+            let mut item = o.clone();
+            if let Some(order) = item.children.iter_mut().find(|c| c.name == "Order") {
+                let max = into
+                    .iter()
+                    .filter(|u| u.name == o.name)
+                    .filter_map(|u| {
+                        u.child("Order")?
+                            .value
+                            .as_deref()?
+                            .trim()
+                            .parse::<u32>()
+                            .ok()
+                    })
+                    .max()
+                    .unwrap_or(0);
+                order.value = Some((max + 1).to_string());
+            }
+            into.push(item);
+            continue;
+        }
+        match into
+            .iter_mut()
+            .find(|u| u.name == o.name && u.action.is_none())
+        {
+            // 空元素（如 `<FirstLogonCommands></FirstLogonCommands>`）解析时分不清是空值还是空分组，
+            // 内置的是分组时按空分组合并
+            Some(u) if o.value.is_none() && is_empty_element(u) => {
+                u.value = None;
+                merge_settings(&mut u.children, &o.children);
+            }
+            // 用户写成单值的，以用户为准
+            Some(u) if u.value.is_some() => {}
+            Some(u) => merge_settings(&mut u.children, &o.children),
+            None => into.push(o.clone()),
+        }
+    }
+}
+
+/// 没有子元素、文本为空白的元素。
+fn is_empty_element(s: &Setting) -> bool {
+    s.children.is_empty() && s.value.as_deref().is_some_and(|v| v.trim().is_empty())
+}
+
+/// 两个列表项是否相同：按标识子项（账户 `Name`、命令 `CommandLine` / `Path`）比较，
+/// 没有标识子项时比较除 `Order` 外的全部内容。
+fn same_list_item(a: &Setting, b: &Setting) -> bool {
+    for key in ["Name", "CommandLine", "Path"] {
+        if let (Some(x), Some(y)) = (a.child(key), b.child(key)) {
+            return x.value == y.value;
+        }
+    }
+    let strip = |s: &Setting| -> Vec<Setting> {
+        s.children
+            .iter()
+            .filter(|c| c.name != "Order")
+            .cloned()
+            .collect()
+    };
+    a.value == b.value && strip(a) == strip(b)
+}
+
+// ───────────────────────── 内置部署应答 ─────────────────────────
+
+/// 目标系统代别（决定 OOBE 可用项）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WindowsGeneration {
+    /// Windows 7（6.1）
+    Win7,
+    /// Windows 8 / 8.1（6.2 / 6.3）
+    Win8,
+    /// Windows 10 / 11（10.0）及未知
+    Win10,
+}
+
+impl WindowsGeneration {
+    /// 按 ntdll.dll 等系统文件版本号判断；读不到版本时调用方用 `Win10`。
+    pub fn from_nt_version(major: u32, minor: u32) -> Self {
+        match (major, minor) {
+            (6, 1) => WindowsGeneration::Win7,
+            (6, 2) | (6, 3) => WindowsGeneration::Win8,
+            _ => WindowsGeneration::Win10,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            WindowsGeneration::Win7 => "Win7配置",
+            WindowsGeneration::Win8 => "Win8配置",
+            WindowsGeneration::Win10 => "Win10/11配置",
+        }
+    }
+}
+
+/// 可单独显示/隐藏的 OOBE 页（目标系统不支持的项自动略过）。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OobePage {
+    /// 许可协议（`HideEULAPage`）
+    Eula,
+    /// 本地账户创建（`HideLocalAccountScreen`，Win8+）
+    LocalAccount,
+    /// 微软账户登录（`HideOnlineAccountScreens`，Win10+）
+    OnlineAccount,
+    /// 无线网络设置（`HideWirelessSetupInOOBE`，Win10+）
+    Wireless,
+    /// OEM 注册（`HideOEMRegistrationScreen`，Win10+；Win7 家庭版不支持故不写）
+    OemRegistration,
+    /// 隐私/更新设置（`ProtectYourPC=3`）
+    Privacy,
+}
+
+impl OobePage {
+    pub const ALL: [OobePage; 6] = [
+        OobePage::Eula,
+        OobePage::LocalAccount,
+        OobePage::OnlineAccount,
+        OobePage::Wireless,
+        OobePage::OemRegistration,
+        OobePage::Privacy,
+    ];
+
+    /// 配置文件中的名称。
+    pub fn as_str(self) -> &'static str {
+        match self {
+            OobePage::Eula => "eula",
+            OobePage::LocalAccount => "local_account",
+            OobePage::OnlineAccount => "online_account",
+            OobePage::Wireless => "wireless",
+            OobePage::OemRegistration => "oem_registration",
+            OobePage::Privacy => "privacy",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<OobePage> {
+        let s = s.trim();
+        OobePage::ALL
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+    }
+
+    /// 该页在目标代别上是否有对应的隐藏开关。
+    fn supported(self, generation: WindowsGeneration) -> bool {
+        match self {
+            OobePage::Eula | OobePage::Privacy => true,
+            OobePage::LocalAccount => generation != WindowsGeneration::Win7,
+            OobePage::OnlineAccount | OobePage::Wireless | OobePage::OemRegistration => {
+                generation == WindowsGeneration::Win10
+            }
+        }
+    }
+
+    /// 对应的 `<OOBE>` 子项名（`Privacy` 为 `ProtectYourPC`）。
+    fn setting_name(self) -> &'static str {
+        match self {
+            OobePage::Eula => "HideEULAPage",
+            OobePage::LocalAccount => "HideLocalAccountScreen",
+            OobePage::OnlineAccount => "HideOnlineAccountScreens",
+            OobePage::Wireless => "HideWirelessSetupInOOBE",
+            OobePage::OemRegistration => "HideOEMRegistrationScreen",
+            OobePage::Privacy => "ProtectYourPC",
+        }
+    }
+}
+
+/// 内置应答的可选定制（区域/时区/计算机名/密码/密钥/注册信息/OOBE 页）。
+///
+/// 空串 = 不写该项（沿用镜像默认或 OOBE 询问）。两端 INI 安装配置的 `[Unattend]` 节经
+/// [`UnattendCustomization::to_ini`] / [`UnattendCustomization::set_ini_key`] 往返，
+/// 密码只以编码后的形式落盘。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UnattendCustomization {
+    /// 输入法，如 `zh-CN` 或 `0804:00000804`。
+    pub input_locale: String,
+    /// 非 Unicode 程序的区域，如 `zh-CN`。
+    pub system_locale: String,
+    /// 界面语言（镜像须已含该语言包），如 `zh-CN`。
+    pub ui_language: String,
+    /// 用户区域（日期/货币格式），如 `zh-CN`。
+    pub user_locale: String,
+    /// Windows 时区 ID，如 `China Standard Time`。
+    pub time_zone: String,
+    /// 计算机名（已展开模板，见 [`expand_computer_name`]）；空 = 随机。
+    pub computer_name: String,
+    /// 本地账户密码，已按 [`encode_password`] 编码；空 = 空密码。
+    pub encoded_password: String,
+    /// 产品密钥（`XXXXX-XXXXX-XXXXX-XXXXX-XXXXX`）。
+    pub product_key: String,
+    pub registered_organization: String,
+    pub registered_owner: String,
+    /// 需要【显示】的 OOBE 页；未列出的（且目标系统支持的）一律隐藏。
+    pub show_oobe_pages: Vec<OobePage>,
+}
+
+impl UnattendCustomization {
+    /// 序列化为 INI `[Unattend]` 节（含节头，LF 换行，与安装配置其余各节一致）。
+    pub fn to_ini(&self) -> String {
+        let pages: Vec<&str> = self.show_oobe_pages.iter().map(|p| p.as_str()).collect();
+        format!(
+            "[Unattend]\n\
+UnattendInputLocale={}\n\
+UnattendSystemLocale={}\n\
+UnattendUILanguage={}\n\
+UnattendUserLocale={}\n\
+UnattendTimeZone={}\n\
+UnattendComputerName={}\n\
+UnattendPassword={}\n\
+UnattendProductKey={}\n\
+UnattendOrganization={}\n\
+UnattendOwner={}\n\
+UnattendShowOobePages={}\n",
+            self.input_locale,
+            self.system_locale,
+            self.ui_language,
+            self.user_locale,
+            self.time_zone,
+            self.computer_name,
+            self.encoded_password,
+            self.product_key,
+            self.registered_organization,
+            self.registered_owner,
+            pages.join(","),
+        )
+    }
+
+    /// 解析 INI 中的一个键；是本结构的键返回 `true`。
+    pub fn set_ini_key(&mut self, key: &str, value: &str) -> bool {
+        let v = value.trim().to_string();
+        match key {
+            "UnattendInputLocale" => self.input_locale = v,
+            "UnattendSystemLocale" => self.system_locale = v,
+            "UnattendUILanguage" => self.ui_language = v,
+            "UnattendUserLocale" => self.user_locale = v,
+            "UnattendTimeZone" => self.time_zone = v,
+            "UnattendComputerName" => self.computer_name = v,
+            "UnattendPassword" => self.encoded_password = v,
+            "UnattendProductKey" => self.product_key = v,
+            "UnattendOrganization" => self.registered_organization = v,
+            "UnattendOwner" => self.registered_owner = v,
+            "UnattendShowOobePages" => {
+                self.show_oobe_pages = v.split(',').filter_map(OobePage::parse).collect()
+            }
+            _ => return false,
+        }
+        true
+    }
+
+    /// 区域/语言四项是否都没填。
+    fn has_locale(&self) -> bool {
+        !(self.input_locale.is_empty()
+            && self.system_locale.is_empty()
+            && self.ui_language.is_empty()
+            && self.user_locale.is_empty())
+    }
+}
+
+/// 按 unattend 约定编码密码：`base64(UTF-16LE(明文 + 后缀))`，写入时配 `PlainText=false`。
+///
+/// 本地账户与自动登录的密码后缀都是 `Password`（管理员密码为 `AdministratorPassword`）。
+/// 这只是混淆、不是加密——应答文件本身仍需妥善保管。
+pub fn encode_password(plain: &str, suffix: &str) -> String {
+    let bytes: Vec<u8> = plain
+        .encode_utf16()
+        .chain(suffix.encode_utf16())
+        .flat_map(|u| u.to_le_bytes())
+        .collect();
+    base64_encode(&bytes)
+}
+
+fn base64_encode(data: &[u8]) -> String {
+    const T: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let b = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (u32::from(b[0]) << 16) | (u32::from(b[1]) << 8) | u32::from(b[2]);
+        out.push(T[(n >> 18) as usize & 63] as char);
+        out.push(T[(n >> 12) as usize & 63] as char);
+        out.push(if chunk.len() > 1 {
+            T[(n >> 6) as usize & 63] as char
+        } else {
+            '='
+        });
+        out.push(if chunk.len() > 2 {
+            T[n as usize & 63] as char
+        } else {
+            '='
+        });
+    }
+    out
+}
+
+/// 展开计算机名模板：`{serial}` = 设备序列号（去掉非字母数字），`{rand}` = 4 位随机十六进制。
+///
+/// 结果按 NetBIOS 规则规整：只留字母、数字、`-`，转大写，最长 15 个字符，不能全是数字。
+/// 展开后为空返回 `*`（由 Setup 随机生成）。
+pub fn expand_computer_name(pattern: &str, serial: &str) -> String {
+    let serial: String = serial
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .collect();
+    let rand = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.subsec_nanos() ^ (d.as_secs() as u32))
+        .unwrap_or(0)
+        & 0xFFFF;
+    let expanded = pattern
+        .replace("{serial}", &serial)
+        .replace("{rand}", &format!("{:04X}", rand));
+    let mut name: String = expanded
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || *c == '-')
+        .map(|c| c.to_ascii_uppercase())
+        .take(15)
+        .collect();
+    while name.ends_with('-') {
+        name.pop();
+    }
+    if name.is_empty() || name.chars().all(|c| c.is_ascii_digit()) {
+        return "*".to_string();
+    }
+    name
+}
+
+/// 校验并规整产品密钥（5 组 × 5 位字母数字，转大写）。
+pub fn normalize_product_key(key: &str) -> Result<String, String> {
+    use crate::product_key::{normalize, KeyCharset};
+    normalize(key, KeyCharset::Alphanumeric)
+}
+
+/// LetRecovery 内置部署应答的参数。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DeployUnattend {
+    /// `processorArchitecture`（x86 / amd64 / arm64）。
+    pub arch: String,
+    pub generation: WindowsGeneration,
+    /// 本地管理员用户名（空密码、自动登录）。
+    pub username: String,
+    /// specialize 阶段的计算机名（`*` = 随机）；`None` 不写。
+    pub computer_name: Option<String>,
+    /// 部署脚本目录名（位于系统盘根）；`Some` 时写入 `deploy.bat` / `firstlogon.bat` 调用与清理命令。
+    pub scripts_dir: Option<String>,
+    /// 首次登录删除预装 UWP 应用（仅 Win10/11，且需 `scripts_dir`）。
+    pub remove_uwp_apps: bool,
+    /// 自动登录次数；`None` 不写 `LogonCount`。
+    pub auto_logon_count: Option<u32>,
+    /// 区域/时区/计算机名/密码/密钥/注册信息/OOBE 页等可选定制。
+    pub custom: UnattendCustomization,
+}
+
+impl DeployUnattend {
+    /// 用户名为空时用 `MyPc`。
+    pub fn new(arch: &str, generation: WindowsGeneration, username: &str) -> Self {
+        let username = if username.is_empty() {
+            "MyPc"
+        } else {
+            username
+        };
+        Self {
+            arch: arch.to_string(),
+            generation,
+            username: username.to_string(),
+            computer_name: None,
+            scripts_dir: None,
+            remove_uwp_apps: false,
+            auto_logon_count: None,
+            custom: UnattendCustomization::default(),
+        }
+    }
+
+    /// OOBE 设置。按代别裁剪：
+    /// - Win7：不支持 HideLocalAccountScreen / HideOnlineAccountScreens / HideWirelessSetupInOOBE，
+    ///   HideOEMRegistrationScreen 家庭版不支持；需 NetworkLocation 跳过网络位置选择；
+    /// - Win8/8.1：支持 HideLocalAccountScreen，其余新项不支持；
+    /// - Win10/11：预置 LocalAccount + Hide* 跳过账户/隐私屏（SkipMachineOOBE/SkipUserOOBE
+    ///   已被微软弃用且在 Win11 上不可靠，不再使用）。
+    ///
+    /// 默认隐藏所有支持的页；`custom.show_oobe_pages` 中列出的页不写（即照常显示）。
+    fn oobe(&self) -> Setting {
+        let mut children: Vec<Setting> = OobePage::ALL
+            .into_iter()
+            .filter(|p| p.supported(self.generation))
+            .filter(|p| !self.custom.show_oobe_pages.contains(p))
+            .map(|p| match p {
+                OobePage::Privacy => Setting::value(p.setting_name(), "3"),
+                _ => Setting::value(p.setting_name(), "true"),
+            })
+            .collect();
+        if self.generation != WindowsGeneration::Win10 {
+            children.push(Setting::value("NetworkLocation", "Home"));
+        }
+        Setting::group("OOBE", children)
+    }
+
+    /// FirstLogonCommands：首次登录脚本 →（可选）删除 UWP → 清理脚本目录（最后执行）。
+    fn first_logon_commands(&self, dir: &str) -> Setting {
+        let mut cmds: Vec<(String, &str)> = vec![(
+            format!(
+                "cmd /c if exist %SystemDrive%\\{dir}\\firstlogon.bat call %SystemDrive%\\{dir}\\firstlogon.bat"
+            ),
+            "Run first login script",
+        )];
+        if self.remove_uwp_apps && self.generation == WindowsGeneration::Win10 {
+            cmds.push((
+                format!(
+                    "powershell -ExecutionPolicy Bypass -File %SystemDrive%\\{dir}\\remove_uwp.ps1"
+                ),
+                "Remove preinstalled UWP apps",
+            ));
+        }
+        cmds.push((
+            format!("cmd /c rd /s /q %SystemDrive%\\{dir}"),
+            "Cleanup scripts directory",
+        ));
+        let items = cmds
+            .into_iter()
+            .enumerate()
+            .map(|(i, (cmd, desc))| {
+                Setting::group(
+                    "SynchronousCommand",
+                    vec![
+                        Setting::value("Order", (i + 1).to_string()),
+                        Setting::value("CommandLine", cmd),
+                        Setting::value("Description", desc),
+                    ],
+                )
+                .with_action(WcmAction::Add)
+            })
+            .collect();
+        Setting::group("FirstLogonCommands", items)
+    }
+
+    /// 本地账户/自动登录的 `<Password>`：有密码写编码值（`PlainText=false`），否则空密码。
+    fn password(&self) -> Setting {
+        let (value, plain) = match self.custom.encoded_password.as_str() {
+            "" => ("", "true"),
+            enc => (enc, "false"),
+        };
+        Setting::group(
+            "Password",
+            vec![
+                Setting::value("Value", value),
+                Setting::value("PlainText", plain),
+            ],
+        )
+    }
+
+    /// 生成应答模型。
+    pub fn build(&self) -> Unattend {
+        let arch = self.arch.as_str();
+        let mut u = Unattend::new();
+
+        let c = &self.custom;
+
+        // windowsPE：接受 EULA，密钥页仅出错时显示
+        let mut key = Vec::new();
+        if !c.product_key.is_empty() {
+            key.push(Setting::value("Key", c.product_key.clone()));
+        }
+        key.push(Setting::value("WillShowUI", "OnError"));
+        u.component_mut(Pass::WindowsPE, "Microsoft-Windows-Setup", arch)
+            .set(Setting::group(
+                "UserData",
+                vec![
+                    Setting::group("ProductKey", key),
+                    Setting::value("AcceptEula", "true"),
+                ],
+            ));
+
+        // specialize：计算机名 / 产品密钥 + 部署脚本
+        let computer_name = match c.computer_name.as_str() {
+            "" => self.computer_name.clone(),
+            name => Some(name.to_string()),
+        };
+        if let Some(name) = computer_name {
+            u.component_mut(Pass::Specialize, "Microsoft-Windows-Shell-Setup", arch)
+                .set(Setting::value("ComputerName", name));
+        }
+        if !c.product_key.is_empty() {
+            u.component_mut(Pass::Specialize, "Microsoft-Windows-Shell-Setup", arch)
+                .set(Setting::value("ProductKey", c.product_key.clone()));
+        }
+        if let Some(dir) = &self.scripts_dir {
+            let cmd = Setting::group(
+                "RunSynchronousCommand",
+                vec![
+                    Setting::value("Order", "1"),
+                    Setting::value(
+                        "Path",
+                        format!(
+                            "cmd /c if exist %SystemDrive%\\{dir}\\deploy.bat call %SystemDrive%\\{dir}\\deploy.bat"
+                        ),
+                    ),
+                    Setting::value("Description", "Run custom deploy script"),
+                ],
+            )
+            .with_action(WcmAction::Add);
+            u.component_mut(Pass::Specialize, "Microsoft-Windows-Deployment", arch)
+                .set(Setting::group("RunSynchronous", vec![cmd]));
+        }
+
+        // oobeSystem：区域语言 + OOBE + 本地管理员 + 自动登录 + 时区/注册信息 + 首次登录命令
+        if c.has_locale() {
+            let intl = u.component_mut(
+                Pass::OobeSystem,
+                "Microsoft-Windows-International-Core",
+                arch,
+            );
+            for (name, value) in [
+                ("InputLocale", &c.input_locale),
+                ("SystemLocale", &c.system_locale),
+                ("UILanguage", &c.ui_language),
+                ("UserLocale", &c.user_locale),
+            ] {
+                if !value.is_empty() {
+                    intl.set(Setting::value(name, value.clone()));
+                }
+            }
+        }
+        let oobe = self.oobe();
+        let shell = u.component_mut(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup", arch);
+        shell.set(oobe);
+        shell.set(Setting::group(
+            "UserAccounts",
+            vec![Setting::group(
+                "LocalAccounts",
+                vec![Setting::group(
+                    "LocalAccount",
+                    vec![
+                        self.password(),
+                        Setting::value("Description", "Local User"),
+                        Setting::value("DisplayName", self.username.clone()),
+                        Setting::value("Group", "Administrators"),
+                        Setting::value("Name", self.username.clone()),
+                    ],
+                )
+                .with_action(WcmAction::Add)],
+            )],
+        ));
+        let mut auto_logon = vec![self.password(), Setting::value("Enabled", "true")];
+        if let Some(n) = self.auto_logon_count {
+            auto_logon.push(Setting::value("LogonCount", n.to_string()));
+        }
+        auto_logon.push(Setting::value("Username", self.username.clone()));
+        shell.set(Setting::group("AutoLogon", auto_logon));
+        for (name, value) in [
+            ("TimeZone", &c.time_zone),
+            ("RegisteredOrganization", &c.registered_organization),
+            ("RegisteredOwner", &c.registered_owner),
+        ] {
+            if !value.is_empty() {
+                shell.set(Setting::value(name, value.clone()));
+            }
+        }
+        if let Some(dir) = &self.scripts_dir {
+            let cmds = self.first_logon_commands(dir);
+            u.component_mut(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup", arch)
+                .set(cmds);
+        }
+        u
+    }
+
+    /// 直接输出 XML 文本。
+    pub fn to_xml(&self) -> String {
+        self.build().to_xml()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn full(generation: WindowsGeneration) -> DeployUnattend {
+        let mut d = DeployUnattend::new("amd64", generation, "Admin");
+        d.computer_name = Some("*".into());
+        d.scripts_dir = Some("LetRecovery_Scripts".into());
+        d.remove_uwp_apps = true;
+        d.auto_logon_count = Some(1);
+        d
+    }
+
+    #[test]
+    fn escapes_special_characters() {
+        assert_eq!(escape_xml(r#"a&b<c>"d'"#), "a&amp;b&lt;c&gt;&quot;d&apos;");
+        let xml = DeployUnattend::new("amd64", WindowsGeneration::Win10, "Tom & <Jerry>").to_xml();
+        assert!(xml.contains("<Name>Tom &amp; &lt;Jerry&gt;</Name>"));
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let name = doc
+            .descendants()
+            .find(|n| n.has_tag_name("Name"))
+            .and_then(|n| n.text());
+        assert_eq!(name, Some("Tom & <Jerry>"));
+    }
+
+    #[test]
+    fn empty_username_defaults_to_mypc() {
+        let d = DeployUnattend::new("x86", WindowsGeneration::Win10, "");
+        assert_eq!(d.username, "MyPc");
+    }
+
+    #[test]
+    fn generation_from_nt_version() {
+        assert_eq!(
+            WindowsGeneration::from_nt_version(6, 1),
+            WindowsGeneration::Win7
+        );
+        assert_eq!(
+            WindowsGeneration::from_nt_version(6, 3),
+            WindowsGeneration::Win8
+        );
+        assert_eq!(
+            WindowsGeneration::from_nt_version(10, 0),
+            WindowsGeneration::Win10
+        );
+    }
+
+    #[test]
+    fn oobe_is_trimmed_per_generation() {
+        let w7 = full(WindowsGeneration::Win7).to_xml();
+        assert!(w7.contains("<NetworkLocation>Home</NetworkLocation>"));
+        assert!(!w7.contains("HideLocalAccountScreen"));
+        assert!(!w7.contains("remove_uwp.ps1"));
+        let w8 = full(WindowsGeneration::Win8).to_xml();
+        assert!(w8.contains("HideLocalAccountScreen"));
+        assert!(!w8.contains("HideOnlineAccountScreens"));
+        let w10 = full(WindowsGeneration::Win10).to_xml();
+        assert!(w10.contains("<HideOnlineAccountScreens>true</HideOnlineAccountScreens>"));
+        assert!(!w10.contains("NetworkLocation"));
+        assert!(w10.contains("remove_uwp.ps1"));
+    }
+
+    #[test]
+    fn full_layout_and_command_order() {
+        let xml = full(WindowsGeneration::Win10).to_xml();
+        assert!(xml.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<unattend xmlns=\"urn:schemas-microsoft-com:unattend\""));
+        let doc = roxmltree::Document::parse(&xml).unwrap();
+        let passes: Vec<&str> = doc
+            .root_element()
+            .children()
+            .filter(|n| n.is_element())
+            .filter_map(|n| n.attribute("pass"))
+            .collect();
+        assert_eq!(passes, vec!["windowsPE", "specialize", "oobeSystem"]);
+        let orders: Vec<&str> = doc
+            .descendants()
+            .filter(|n| n.has_tag_name("SynchronousCommand"))
+            .filter_map(|n| n.children().find(|c| c.has_tag_name("Order")))
+            .filter_map(|n| n.text())
+            .collect();
+        assert_eq!(orders, vec!["1", "2", "3"]);
+        let action = doc
+            .descendants()
+            .find(|n| n.has_tag_name("LocalAccount"))
+            .and_then(|n| n.attribute((NS_WCM, "action")));
+        assert_eq!(action, Some("add"));
+        assert!(xml.contains("            <FirstLogonCommands>\n"));
+        assert!(xml.contains("<Value></Value>"));
+        assert!(xml.contains("<LogonCount>1</LogonCount>"));
+    }
+
+    #[test]
+    fn minimal_has_no_specialize() {
+        let xml = DeployUnattend::new("arm64", WindowsGeneration::Win10, "u").to_xml();
+        assert!(!xml.contains("specialize"));
+        assert!(!xml.contains("FirstLogonCommands"));
+        assert!(!xml.contains("LogonCount"));
+        assert!(xml.contains("processorArchitecture=\"arm64\""));
+    }
+
+    #[test]
+    fn component_mut_keeps_pass_order() {
+        let mut u = Unattend::new();
+        u.component_mut(Pass::OobeSystem, "A", "x86");
+        u.component_mut(Pass::WindowsPE, "B", "x86");
+        u.component_mut(Pass::Specialize, "C", "x86");
+        u.component_mut(Pass::OobeSystem, "A", "x86")
+            .set(Setting::value("K", "1"));
+        let order: Vec<Pass> = u.settings.iter().map(|p| p.pass).collect();
+        assert_eq!(
+            order,
+            vec![Pass::WindowsPE, Pass::Specialize, Pass::OobeSystem]
+        );
+        assert_eq!(u.settings[2].components.len(), 1);
+        assert_eq!(
+            u.component(Pass::OobeSystem, "A")
+                .unwrap()
+                .setting("K")
+                .unwrap()
+                .value
+                .as_deref(),
+            Some("1")
+        );
+    }
+
+    #[test]
+    fn password_encoding_matches_windows_sim() {
+        // Windows SIM 对 "P@ss" + "Password" 的输出
+        assert_eq!(
+            encode_password("P@ss", "Password"),
+            "UABAAHMAcwBQAGEAcwBzAHcAbwByAGQA"
+        );
+        assert_eq!(base64_encode(b"ab"), "YWI=");
+        assert_eq!(base64_encode(b"a"), "YQ==");
+    }
+
+    #[test]
+    fn computer_name_pattern() {
+        assert_eq!(expand_computer_name("PC-{serial}", "ab 12/34"), "PC-AB1234");
+        assert_eq!(expand_computer_name("{serial}", "1234567890"), "*");
+        assert_eq!(expand_computer_name("", "x"), "*");
+        assert_eq!(
+            expand_computer_name("LAB-{serial}", "0123456789ABCDEF"),
+            "LAB-0123456789A"
+        );
+        assert_eq!(expand_computer_name("PC-{rand}", "").len(), 7);
+        assert_eq!(expand_computer_name("PC-{serial}", ""), "PC");
+    }
+
+    #[test]
+    fn product_key_normalization() {
+        assert_eq!(
+            normalize_product_key(" vk7jg-nphtm-c97jm-9mpgt-3v66t ").unwrap(),
+            "VK7JG-NPHTM-C97JM-9MPGT-3V66T"
+        );
+        assert!(normalize_product_key("ABCDE-12345").is_err());
+        assert!(normalize_product_key("ABCD!-12345-ABCDE-12345-ABCDE").is_err());
+    }
+
+    #[test]
+    fn customization_is_rendered() {
+        let mut d = full(WindowsGeneration::Win10);
+        d.custom = UnattendCustomization {
+            input_locale: "0804:00000804".into(),
+            ui_language: "zh-CN".into(),
+            time_zone: "China Standard Time".into(),
+            computer_name: "PC-ABC".into(),
+            encoded_password: encode_password("pw", "Password"),
+            product_key: "VK7JG-NPHTM-C97JM-9MPGT-3V66T".into(),
+            registered_organization: "R&D".into(),
+            registered_owner: "Ops".into(),
+            show_oobe_pages: vec![OobePage::Privacy, OobePage::Wireless],
+            ..Default::default()
+        };
+        let u = d.build();
+        let spec = u
+            .component(Pass::Specialize, "Microsoft-Windows-Shell-Setup")
+            .unwrap();
+        assert_eq!(
+            spec.setting("ComputerName").unwrap().value.as_deref(),
+            Some("PC-ABC")
+        );
+        assert!(spec.setting("ProductKey").is_some());
+        let intl = u
+            .component(Pass::OobeSystem, "Microsoft-Windows-International-Core")
+            .unwrap();
+        assert!(intl.setting("SystemLocale").is_none());
+        assert_eq!(
+            intl.setting("UILanguage").unwrap().value.as_deref(),
+            Some("zh-CN")
+        );
+        let shell = u
+            .component(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup")
+            .unwrap();
+        let oobe = shell.setting("OOBE").unwrap();
+        assert!(oobe.child("ProtectYourPC").is_none());
+        assert!(oobe.child("HideWirelessSetupInOOBE").is_none());
+        assert!(oobe.child("HideEULAPage").is_some());
+        let pw = shell
+            .setting("AutoLogon")
+            .unwrap()
+            .child("Password")
+            .unwrap();
+        assert_eq!(
+            pw.child("PlainText").unwrap().value.as_deref(),
+            Some("false")
+        );
+        let xml = u.to_xml();
+        assert!(xml.contains("<RegisteredOrganization>R&amp;D</RegisteredOrganization>"));
+        assert!(xml.contains("<Key>VK7JG-NPHTM-C97JM-9MPGT-3V66T</Key>"));
+        roxmltree::Document::parse(&xml).unwrap();
+    }
+
+    #[test]
+    fn customization_ini_round_trip() {
+        let c = UnattendCustomization {
+            system_locale: "zh-CN".into(),
+            time_zone: "China Standard Time".into(),
+            encoded_password: encode_password("pw", "Password"),
+            show_oobe_pages: vec![OobePage::Eula, OobePage::Privacy],
+            ..Default::default()
+        };
+        let mut back = UnattendCustomization::default();
+        for line in c.to_ini().lines().skip(1) {
+            let (k, v) = line.split_once('=').unwrap();
+            assert!(back.set_ini_key(k, v));
+        }
+        assert_eq!(back, c);
+        assert!(!back.set_ini_key("CustomUsername", "x"));
+    }
+
+    #[test]
+    fn parse_round_trips_generated_answer() {
+        for generation in [WindowsGeneration::Win7, WindowsGeneration::Win10] {
+            let built = full(generation).build();
+            assert_eq!(Unattend::parse(&built.to_xml()).unwrap(), built);
+        }
+        assert!(Unattend::parse("<settings/>").is_err());
+        assert!(Unattend::parse(&format!(
+            "<unattend xmlns=\"{}\"><settings pass=\"OOBESystem\"/></unattend>",
+            NS_UNATTEND
+        ))
+        .is_err());
+    }
+
+    #[test]
+    fn merge_keeps_user_values_and_appends_lists() {
+        let user = format!(
+            r#"<?xml version="1.0" encoding="utf-8"?>
+<unattend xmlns="{ns}" xmlns:wcm="{wcm}">
+    <settings pass="specialize">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="AMD64" publicKeyToken="{tok}" language="neutral" versionScope="nonSxS">
+            <ComputerName>OFFICE-01</ComputerName>
+        </component>
+        <component name="Microsoft-Windows-Deployment" processorArchitecture="amd64" publicKeyToken="{tok}" language="neutral" versionScope="nonSxS">
+            <RunSynchronous>
+                <RunSynchronousCommand wcm:action="add">
+                    <Order>1</Order>
+                    <Path>cmd /c echo user</Path>
+                </RunSynchronousCommand>
+                <RunSynchronousCommand wcm:action="add">
+                    <Order>2</Order>
+                    <Path>cmd /c echo user2</Path>
+                </RunSynchronousCommand>
+            </RunSynchronous>
+        </component>
+    </settings>
+    <settings pass="oobeSystem">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="{tok}" language="neutral" versionScope="nonSxS">
+            <OOBE>
+                <ProtectYourPC>1</ProtectYourPC>
+            </OOBE>
+            <UserAccounts>
+                <LocalAccounts>
+                    <LocalAccount wcm:action="add">
+                        <Name>Admin</Name>
+                        <Group>Users</Group>
+                    </LocalAccount>
+                </LocalAccounts>
+            </UserAccounts>
+        </component>
+    </settings>
+</unattend>"#,
+            ns = NS_UNATTEND,
+            wcm = NS_WCM,
+            tok = PUBLIC_KEY_TOKEN
+        );
+        let ours = full(WindowsGeneration::Win10).build();
+        let merged = Unattend::parse(&merge_xml(&user, &ours).unwrap()).unwrap();
+
+        // 单值：用户优先
+        let shell = merged
+            .component(Pass::Specialize, "Microsoft-Windows-Shell-Setup")
+            .unwrap();
+        assert_eq!(shell.processor_architecture, "AMD64");
+        assert_eq!(
+            shell.setting("ComputerName").unwrap().value.as_deref(),
+            Some("OFFICE-01")
+        );
+        // 列表：用户两项在前，内置 deploy.bat 追加为第 3 项
+        let run = merged
+            .component(Pass::Specialize, "Microsoft-Windows-Deployment")
+            .unwrap()
+            .setting("RunSynchronous")
+            .unwrap();
+        assert_eq!(run.children.len(), 3);
+        assert!(run.children[2]
+            .child("Path")
+            .unwrap()
+            .value
+            .as_deref()
+            .unwrap()
+            .contains("deploy.bat"));
+        assert_eq!(
+            run.children[2].child("Order").unwrap().value.as_deref(),
+            Some("3")
+        );
+
+        let oobe_shell = merged
+            .component(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup")
+            .unwrap();
+        let oobe = oobe_shell.setting("OOBE").unwrap();
+        assert_eq!(
+            oobe.child("ProtectYourPC").unwrap().value.as_deref(),
+            Some("1")
+        );
+        assert!(oobe.child("HideEULAPage").is_some());
+        // 同名账户不重复，且保留用户的取值
+        let accounts = oobe_shell
+            .setting("UserAccounts")
+            .unwrap()
+            .child("LocalAccounts")
+            .unwrap();
+        assert_eq!(accounts.children.len(), 1);
+        assert_eq!(
+            accounts.children[0]
+                .child("Group")
+                .unwrap()
+                .value
+                .as_deref(),
+            Some("Users")
+        );
+        // 用户没有的：首次登录命令、AutoLogon、windowsPE 阶段整段补入
+        assert!(oobe_shell.setting("FirstLogonCommands").is_some());
+        assert!(oobe_shell.setting("AutoLogon").is_some());
+        assert!(merged
+            .component(Pass::WindowsPE, "Microsoft-Windows-Setup")
+            .is_some());
+        assert_eq!(merged.settings[0].pass, Pass::WindowsPE);
+    }
+
+    #[test]
+    fn decode_xml_by_bom() {
+        let xml = format!(
+            "<?xml version=\"1.0\" encoding=\"utf-16\"?>\r\n<unattend xmlns=\"{}\">\
+             <settings pass=\"oobeSystem\"><component name=\"Microsoft-Windows-Shell-Setup\" \
+             processorArchitecture=\"amd64\"><TimeZone>中国标准时间</TimeZone></component>\
+             </settings></unattend>",
+            NS_UNATTEND
+        );
+        let le: Vec<u8> = xml.encode_utf16().flat_map(u16::to_le_bytes).collect();
+        let be: Vec<u8> = xml.encode_utf16().flat_map(u16::to_be_bytes).collect();
+        for raw in [
+            [&[0xFF, 0xFE][..], &le].concat(),
+            le.clone(),
+            [&[0xFE, 0xFF][..], &be].concat(),
+            be,
+            [&[0xEF, 0xBB, 0xBF][..], xml.as_bytes()].concat(),
+            xml.clone().into_bytes(),
+        ] {
+            assert_eq!(decode_xml(&raw), xml);
+        }
+        // 声明为 utf-16 的文档解码后照常合并，输出 UTF-8
+        let merged = merge_xml(&decode_xml(&le), &Unattend::new()).unwrap();
+        assert!(merged.contains("中国标准时间"));
+        assert!(merged.starts_with("<?xml version=\"1.0\" encoding=\"utf-8\"?>"));
+    }
+
+    #[test]
+    fn merge_treats_empty_user_element_as_group() {
+        let user = format!(
+            r#"<unattend xmlns="{ns}" xmlns:wcm="{wcm}">
+    <settings pass="oobeSystem">
+        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64">
+            <FirstLogonCommands></FirstLogonCommands>
+            <TimeZone></TimeZone>
+        </component>
+    </settings>
+</unattend>"#,
+            ns = NS_UNATTEND,
+            wcm = NS_WCM
+        );
+        let mut ours = Unattend::new();
+        let shell = ours.component_mut(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup", "amd64");
+        shell.set(Setting::group(
+            "FirstLogonCommands",
+            vec![Setting::group(
+                "SynchronousCommand",
+                vec![
+                    Setting::value("Order", "1"),
+                    Setting::value("CommandLine", "cmd /c echo ours"),
+                ],
+            )
+            .with_action(WcmAction::Add)],
+        ));
+        shell.set(Setting::value("TimeZone", "China Standard Time"));
+
+        let xml = merge_xml(&user, &ours).unwrap();
+        // 用户没写的组件属性不输出为空值
+        assert!(!xml.contains("=\"\""));
+        assert!(!xml.contains("versionScope"));
+        let merged = Unattend::parse(&xml).unwrap();
+        let shell = merged
+            .component(Pass::OobeSystem, "Microsoft-Windows-Shell-Setup")
+            .unwrap();
+        let commands = shell.setting("FirstLogonCommands").unwrap();
+        assert_eq!(commands.value, None);
+        assert_eq!(commands.children.len(), 1);
+        assert_eq!(
+            commands.children[0]
+                .child("Order")
+                .unwrap()
+                .value
+                .as_deref(),
+            Some("1")
+        );
+        // 内置的是单值时，用户的空值仍以用户为准
+        assert_eq!(
+            shell.setting("TimeZone").unwrap().value.as_deref(),
+            Some("")
+        );
+    }
+}
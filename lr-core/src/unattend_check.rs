@@ -0,0 +1,504 @@
+//! `unattend.xml` 语义校验。
+//!
+//! 语法合法的应答文件仍可能被 Setup 静默忽略或直接中止安装，常见原因：
+//! - 组件的 `processorArchitecture` 与镜像架构不符（整个组件被忽略；同一阶段有别的组件匹配时
+//!   视为多架构应答文件，只提示）；
+//! - 组件放错了配置阶段（如 `Microsoft-Windows-Setup` 不在 windowsPE）；
+//! - 设置只在特定阶段有效（如 `UserAccounts` 只在 oobeSystem / auditSystem）；
+//! - 同一阶段重复声明同一组件；
+//! - 缺少 `publicKeyToken`（组件无法匹配，被忽略）。
+//!
+//! [`check`] 逐项给出带行列号的 [`UnattendIssue`]，供两端在安装前展示。
+
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::unattend::{Pass, NS_UNATTEND, PUBLIC_KEY_TOKEN};
+
+/// 校验结果的严重程度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnattendSeverity {
+    /// Setup 会中止或忽略整个组件，应阻止使用。
+    Error,
+    /// 可能不符合预期，提示即可。
+    Warning,
+}
+
+/// 一条校验结果。`line` / `column` 均为 1 起始。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnattendIssue {
+    pub line: u32,
+    pub column: u32,
+    pub severity: UnattendSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for UnattendIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "第 {} 行第 {} 列：{}",
+            self.line, self.column, self.message
+        )
+    }
+}
+
+/// `processorArchitecture` 的合法取值。
+const ARCHITECTURES: &[&str] = &["x86", "amd64", "ia64", "arm", "arm64", "wow64", "msil"];
+
+/// 常见组件可出现的阶段（来自 Windows Unattended Setup Reference；组件名大小写不敏感）。
+/// 不在表中的组件不做阶段检查。
+const COMPONENT_PASSES: &[(&str, &[Pass])] = &[
+    ("Microsoft-Windows-Setup", &[Pass::WindowsPE]),
+    (
+        "Microsoft-Windows-International-Core-WinPE",
+        &[Pass::WindowsPE],
+    ),
+    (
+        "Microsoft-Windows-PnpCustomizationsWinPE",
+        &[Pass::WindowsPE],
+    ),
+    (
+        "Microsoft-Windows-International-Core",
+        &[Pass::Specialize, Pass::OobeSystem],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        &[
+            Pass::OfflineServicing,
+            Pass::Generalize,
+            Pass::Specialize,
+            Pass::AuditSystem,
+            Pass::AuditUser,
+            Pass::OobeSystem,
+        ],
+    ),
+    (
+        "Microsoft-Windows-Deployment",
+        &[
+            Pass::Generalize,
+            Pass::Specialize,
+            Pass::AuditSystem,
+            Pass::AuditUser,
+            Pass::OobeSystem,
+        ],
+    ),
+    (
+        "Microsoft-Windows-PnpCustomizationsNonWinPE",
+        &[Pass::OfflineServicing, Pass::AuditSystem],
+    ),
+    ("Microsoft-Windows-PnpSysprep", &[Pass::Generalize]),
+    ("Microsoft-Windows-UnattendedJoin", &[Pass::Specialize]),
+    (
+        "Microsoft-Windows-Security-SPP",
+        &[Pass::Generalize, Pass::Specialize],
+    ),
+    ("Microsoft-Windows-Security-SPP-UX", &[Pass::Specialize]),
+    (
+        "Microsoft-Windows-LUA-Settings",
+        &[Pass::OfflineServicing, Pass::Specialize],
+    ),
+];
+
+/// 只在特定阶段生效的设置：(组件, 设置, 允许的阶段)。放错阶段时 Setup 报应答文件无效。
+const SETTING_PASSES: &[(&str, &str, &[Pass])] = &[
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "UserAccounts",
+        &[Pass::AuditSystem, Pass::OobeSystem],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "AutoLogon",
+        &[Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem],
+    ),
+    ("Microsoft-Windows-Shell-Setup", "OOBE", &[Pass::OobeSystem]),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "FirstLogonCommands",
+        &[Pass::OobeSystem],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "LogonCommands",
+        &[Pass::OobeSystem],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "ComputerName",
+        &[Pass::OfflineServicing, Pass::Specialize],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "ProductKey",
+        &[Pass::Specialize],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "CopyProfile",
+        &[Pass::Specialize],
+    ),
+    (
+        "Microsoft-Windows-Shell-Setup",
+        "TimeZone",
+        &[Pass::Specialize, Pass::AuditSystem, Pass::OobeSystem],
+    ),
+    (
+        "Microsoft-Windows-Deployment",
+        "RunSynchronous",
+        &[Pass::Generalize, Pass::Specialize, Pass::AuditUser],
+    ),
+];
+
+/// 校验应答文件。`image_arch` 为所选镜像的架构（`x86` / `amd64` / `arm64`，见
+/// [`crate::image_meta::wim_arch_name`]），未知时传 `None`，跳过架构比对。
+///
+/// XML 解析失败或根元素不是 `<unattend>` 时只返回这一条错误。
+pub fn check(xml: &str, image_arch: Option<&str>) -> Vec<UnattendIssue> {
+    let s = xml.trim_start_matches('\u{feff}');
+    let doc = match roxmltree::Document::parse(s) {
+        Ok(doc) => doc,
+        Err(e) => {
+            let pos = e.pos();
+            return vec![UnattendIssue {
+                line: pos.row,
+                column: pos.col,
+                severity: UnattendSeverity::Error,
+                message: format!("XML 语法错误：{}", e),
+            }];
+        }
+    };
+
+    let mut issues = Vec::new();
+    let mut push = |node: roxmltree::Node, severity: UnattendSeverity, message: String| {
+        let pos = doc.text_pos_at(node.range().start);
+        issues.push(UnattendIssue {
+            line: pos.row,
+            column: pos.col,
+            severity,
+            message,
+        });
+    };
+
+    let root = doc.root_element();
+    if root.tag_name().name() != "unattend" {
+        let name = root.tag_name().name();
+        push(
+            root,
+            UnattendSeverity::Error,
+            format!(
+                "不是有效的无人值守文件（根元素应为 <unattend>，实际为 <{}>）",
+                if name.is_empty() { "?" } else { name }
+            ),
+        );
+        return issues;
+    }
+    if root.tag_name().namespace() != Some(NS_UNATTEND) {
+        push(
+            root,
+            UnattendSeverity::Warning,
+            format!(
+                "<unattend> 的命名空间应为 {}，否则 Setup 可能不识别",
+                NS_UNATTEND
+            ),
+        );
+    }
+
+    let image_arch = image_arch.map(|a| a.to_ascii_lowercase());
+    let mut seen_passes: HashSet<Pass> = HashSet::new();
+    let mut seen_components: HashSet<(Pass, String, String)> = HashSet::new();
+
+    for settings in root
+        .children()
+        .filter(|n| n.is_element() && n.tag_name().name() == "settings")
+    {
+        let raw_pass = settings.attribute("pass").unwrap_or("");
+        let Some(pass) = Pass::parse(raw_pass) else {
+            let hint = Pass::ALL
+                .into_iter()
+                .find(|p| p.as_str().eq_ignore_ascii_case(raw_pass))
+                .map(|p| format!("，是否应为 {}？（大小写敏感）", p.as_str()))
+                .unwrap_or_else(|| "，该段会被 Setup 忽略".to_string());
+            push(
+                settings,
+                UnattendSeverity::Error,
+                format!("未知的配置阶段 pass=\"{}\"{}", raw_pass, hint),
+            );
+            continue;
+        };
+        if !seen_passes.insert(pass) {
+            push(
+                settings,
+                UnattendSeverity::Warning,
+                format!(
+                    "配置阶段 {} 出现了多次，建议合并到同一个 <settings>",
+                    pass.as_str()
+                ),
+            );
+        }
+
+        // 多架构应答文件会为每种架构各放一份组件：只要本阶段有组件匹配镜像架构，
+        // 不匹配的组件只是被忽略的副本
+        let mut arch_matched = false;
+        let mut arch_mismatches = Vec::new();
+
+        for component in settings
+            .children()
+            .filter(|n| n.is_element() && n.tag_name().name() == "component")
+        {
+            let Some(name) = component.attribute("name").filter(|n| !n.is_empty()) else {
+                push(
+                    component,
+                    UnattendSeverity::Error,
+                    "<component> 缺少 name 属性".to_string(),
+                );
+                continue;
+            };
+
+            let arch = component.attribute("processorArchitecture").unwrap_or("");
+            let arch_lower = arch.to_ascii_lowercase();
+            if arch.is_empty() {
+                push(
+                    component,
+                    UnattendSeverity::Error,
+                    format!("组件 {} 缺少 processorArchitecture，Setup 会忽略它", name),
+                );
+            } else if !ARCHITECTURES.contains(&arch_lower.as_str()) {
+                push(
+                    component,
+                    UnattendSeverity::Error,
+                    format!(
+                        "组件 {} 的 processorArchitecture=\"{}\" 不是合法取值",
+                        name, arch
+                    ),
+                );
+            } else if let Some(image) = image_arch.as_deref() {
+                // wow64 组件只对 64 位 x86 镜像有意义
+                let matches = arch_lower == image || (arch_lower == "wow64" && image == "amd64");
+                if matches {
+                    arch_matched = true;
+                } else {
+                    arch_mismatches.push((
+                        component,
+                        format!(
+                            "组件 {} 的架构为 {}，与镜像架构 {} 不符，Setup 会忽略该组件",
+                            name, arch, image
+                        ),
+                    ));
+                }
+            }
+
+            match component.attribute("publicKeyToken") {
+                None | Some("") => push(
+                    component,
+                    UnattendSeverity::Error,
+                    format!(
+                        "组件 {} 缺少 publicKeyToken（应为 {}）",
+                        name, PUBLIC_KEY_TOKEN
+                    ),
+                ),
+                Some(token) if !token.eq_ignore_ascii_case(PUBLIC_KEY_TOKEN) => push(
+                    component,
+                    UnattendSeverity::Warning,
+                    format!(
+                        "组件 {} 的 publicKeyToken=\"{}\" 不是微软组件的 {}",
+                        name, token, PUBLIC_KEY_TOKEN
+                    ),
+                ),
+                Some(_) => {}
+            }
+
+            if let Some((_, passes)) = COMPONENT_PASSES
+                .iter()
+                .find(|(c, _)| c.eq_ignore_ascii_case(name))
+            {
+                if !passes.contains(&pass) {
+                    push(
+                        component,
+                        UnattendSeverity::Error,
+                        format!(
+                            "组件 {} 不能用于 {} 阶段（可用：{}）",
+                            name,
+                            pass.as_str(),
+                            pass_list(passes)
+                        ),
+                    );
+                }
+            }
+
+            let key = (pass, name.to_ascii_lowercase(), arch_lower);
+            if !seen_components.insert(key) {
+                push(
+                    component,
+                    UnattendSeverity::Error,
+                    format!("{} 阶段重复声明了组件 {}（{}）", pass.as_str(), name, arch),
+                );
+            }
+
+            for setting in component.children().filter(|n| n.is_element()) {
+                let setting_name = setting.tag_name().name();
+                let Some((_, _, passes)) = SETTING_PASSES
+                    .iter()
+                    .find(|(c, s, _)| c.eq_ignore_ascii_case(name) && *s == setting_name)
+                else {
+                    continue;
+                };
+                if !passes.contains(&pass) {
+                    push(
+                        setting,
+                        UnattendSeverity::Error,
+                        format!(
+                            "{} 的设置 {} 不能放在 {} 阶段（可用：{}）",
+                            name,
+                            setting_name,
+                            pass.as_str(),
+                            pass_list(passes)
+                        ),
+                    );
+                }
+            }
+        }
+
+        let severity = if arch_matched {
+            UnattendSeverity::Warning
+        } else {
+            UnattendSeverity::Error
+        };
+        for (component, message) in arch_mismatches {
+            push(component, severity, message);
+        }
+    }
+
+    issues
+}
+
+fn pass_list(passes: &[Pass]) -> String {
+    passes
+        .iter()
+        .map(|p| p.as_str())
+        .collect::<Vec<_>>()
+        .join(" / ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::unattend::{DeployUnattend, WindowsGeneration};
+
+    fn wrap(body: &str) -> String {
+        format!(
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<unattend xmlns=\"{}\">\n{}\n</unattend>",
+            NS_UNATTEND, body
+        )
+    }
+
+    fn component(name: &str, arch: &str, inner: &str) -> String {
+        format!(
+            "<component name=\"{}\" processorArchitecture=\"{}\" publicKeyToken=\"{}\" language=\"neutral\" versionScope=\"nonSxS\">{}</component>",
+            name, arch, PUBLIC_KEY_TOKEN, inner
+        )
+    }
+
+    fn errors(issues: &[UnattendIssue]) -> Vec<&UnattendIssue> {
+        issues
+            .iter()
+            .filter(|i| i.severity == UnattendSeverity::Error)
+            .collect()
+    }
+
+    #[test]
+    fn generated_answer_files_are_clean() {
+        for generation in [
+            WindowsGeneration::Win7,
+            WindowsGeneration::Win8,
+            WindowsGeneration::Win10,
+        ] {
+            let xml = DeployUnattend::new("amd64", generation, "user").to_xml();
+            assert_eq!(check(&xml, Some("amd64")), vec![], "{:?}", generation);
+        }
+    }
+
+    #[test]
+    fn syntax_error_reports_position() {
+        let issues = check("<unattend>\n  <settings>\n</unattend>", None);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, UnattendSeverity::Error);
+        assert_eq!(issues[0].line, 3);
+    }
+
+    #[test]
+    fn architecture_mismatch_is_located() {
+        let xml = wrap(&format!(
+            "<settings pass=\"specialize\">\n{}\n</settings>",
+            component("Microsoft-Windows-Shell-Setup", "x86", "")
+        ));
+        let issues = check(&xml, Some("amd64"));
+        assert_eq!(errors(&issues).len(), 1);
+        assert_eq!((issues[0].line, issues[0].column), (4, 1));
+        assert!(issues[0].message.contains("x86"));
+        // wow64 组件在 64 位镜像上是合法的；架构未知时不比对
+        let xml = xml.replace("\"x86\"", "\"wow64\"");
+        assert!(check(&xml, Some("amd64")).is_empty());
+        assert!(check(&xml, None).is_empty());
+    }
+
+    #[test]
+    fn multi_arch_answer_file_only_warns() {
+        let xml = wrap(&format!(
+            "<settings pass=\"specialize\">{}{}</settings>",
+            component("Microsoft-Windows-Shell-Setup", "x86", ""),
+            component("Microsoft-Windows-Shell-Setup", "amd64", ""),
+        ));
+        let issues = check(&xml, Some("amd64"));
+        assert!(errors(&issues).is_empty(), "{:?}", issues);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, UnattendSeverity::Warning);
+        assert!(issues[0].message.contains("x86"));
+        // 换成 arm64 镜像后没有任何组件匹配
+        assert_eq!(errors(&check(&xml, Some("arm64"))).len(), 2);
+    }
+
+    #[test]
+    fn component_and_setting_passes() {
+        let xml = wrap(&format!(
+            "<settings pass=\"specialize\">{}{}</settings>",
+            component("Microsoft-Windows-Setup", "amd64", ""),
+            component(
+                "Microsoft-Windows-Shell-Setup",
+                "amd64",
+                "<UserAccounts></UserAccounts><ComputerName>PC</ComputerName>"
+            ),
+        ));
+        let issues = check(&xml, Some("amd64"));
+        let msgs: Vec<_> = errors(&issues).iter().map(|i| i.message.clone()).collect();
+        assert_eq!(msgs.len(), 2, "{:?}", msgs);
+        assert!(msgs[0].contains("Microsoft-Windows-Setup"));
+        assert!(msgs[1].contains("UserAccounts"));
+    }
+
+    #[test]
+    fn duplicates_token_and_pass_case() {
+        let dup = component("Microsoft-Windows-Shell-Setup", "amd64", "");
+        let no_token = "<component name=\"Microsoft-Windows-Deployment\" processorArchitecture=\"amd64\"></component>";
+        let xml = wrap(&format!(
+            "<settings pass=\"oobeSystem\">{}{}{}</settings><settings pass=\"OOBESystem\"></settings>",
+            dup, dup, no_token
+        ));
+        let issues = check(&xml, None);
+        let msgs: Vec<_> = issues.iter().map(|i| i.message.as_str()).collect();
+        assert_eq!(issues.len(), 3, "{:?}", msgs);
+        assert!(msgs[0].contains("重复"));
+        assert!(msgs[1].contains("publicKeyToken"));
+        assert!(msgs[2].contains("是否应为 oobeSystem"));
+    }
+
+    #[test]
+    fn wrong_root_and_namespace() {
+        let issues = check("<settings/>", None);
+        assert_eq!(issues.len(), 1);
+        assert!(issues[0].message.contains("<settings>"));
+        let issues = check("<unattend/>", None);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, UnattendSeverity::Warning);
+    }
+}
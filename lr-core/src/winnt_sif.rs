@@ -0,0 +1,930 @@
+//! XP/2003 `winnt.sif` 应答的类型化构建 + 结构校验。
+//!
+//! - [`WinntSif`]：按节建模（`[Data]` / `[Unattended]` / `[GuiUnattended]` / `[UserData]` /
+//!   `[RegionalSettings]` / `[Identification]` / `[Networking]`），[`WinntSif::local_source`]
+//!   给出本地源（`$WIN_NT$.~LS`）硬盘安装的默认值；
+//! - [`WinntSif::merge_into`]：把默认值补进用户自己的 winnt.sif（用户已写的键一律保留），
+//!   再由 [`apply_mandatory`] 强制硬盘安装必需的键——不再是「要么用户文件、要么内置」二选一；
+//! - [`validate`]：按已知键表检查未知/拼错的键、枚举值、产品密钥格式，以及与本地源安装
+//!   冲突的取值（如 `AutoPartition=1`、`Repartition=Yes`），结果带行号。
+
+use std::fmt;
+
+use crate::product_key::{self, KeyCharset};
+use crate::sif::{unquote, SifDocument, SifLineKind};
+
+/// 本地源硬盘安装必需的键（照搬 DSI 的 `NT5部署无人值守`）：（节, 键, 值）。
+/// 缺 `MsDosInitiated=1` 文本安装会去找光盘而失败；`AutoPartition=0` 防止自动选错盘抹数据。
+pub const MANDATORY_KEYS: [(&str, &str, &str); 5] = [
+    ("Data", "MsDosInitiated", "1"),
+    ("Data", "Floppyless", "1"),
+    ("Data", "AutoPartition", "0"),
+    ("Data", "UnattendedInstall", "Yes"),
+    ("Unattended", "OemPreinstall", "Yes"),
+];
+
+/// 强制写入 [`MANDATORY_KEYS`]：原位改值，用户文件的注释、其余键与顺序都不动。
+pub fn apply_mandatory(doc: &mut SifDocument) {
+    for (section, key, value) in MANDATORY_KEYS {
+        doc.set(section, key, value);
+    }
+}
+
+// ───────────────────────── 产品密钥 ─────────────────────────
+
+/// XP/2003 产品密钥（`XXXXX-XXXXX-XXXXX-XXXXX-XXXXX`，字符取自 24 字符的 Base24 字母表）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProductKey(String);
+
+impl ProductKey {
+    /// 校验并规整（去引号/空白、转大写）。
+    pub fn parse(s: &str) -> Result<Self, String> {
+        product_key::normalize(unquote(s), KeyCharset::Base24).map(Self)
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
+// ───────────────────────── 类型化各节 ─────────────────────────
+
+/// `[Unattended] UnattendMode`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnattendMode {
+    DefaultHide,
+    FullUnattended,
+    GuiAttended,
+    ProvideDefault,
+    ReadOnly,
+}
+
+impl UnattendMode {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::DefaultHide => "DefaultHide",
+            Self::FullUnattended => "FullUnattended",
+            Self::GuiAttended => "GuiAttended",
+            Self::ProvideDefault => "ProvideDefault",
+            Self::ReadOnly => "ReadOnly",
+        }
+    }
+}
+
+/// `[Unattended] DriverSigningPolicy`。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DriverSigningPolicy {
+    Ignore,
+    Warn,
+    Block,
+}
+
+impl DriverSigningPolicy {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Ignore => "Ignore",
+            Self::Warn => "Warn",
+            Self::Block => "Block",
+        }
+    }
+}
+
+/// `[Data]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DataSection {
+    pub auto_partition: bool,
+    pub msdos_initiated: bool,
+    pub unattended_install: bool,
+    pub floppyless: bool,
+}
+
+/// `[Unattended]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnattendedSection {
+    pub unattend_mode: UnattendMode,
+    pub unattend_switch: bool,
+    pub oem_preinstall: bool,
+    pub oem_skip_eula: bool,
+    pub target_path: String,
+    /// `true` = `FileSystem=ConvertNTFS`，`false` = `LeaveAlone`（沿用已格式化的目标盘）。
+    pub convert_ntfs: bool,
+    pub wait_for_reboot: bool,
+    pub driver_signing_policy: DriverSigningPolicy,
+}
+
+/// `[GuiUnattended]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GuiUnattendedSection {
+    /// 管理员密码；`None` = 空密码（写作 `*`）。
+    pub admin_password: Option<String>,
+    pub auto_logon: bool,
+    pub auto_logon_count: u32,
+    pub oem_skip_regional: bool,
+    pub oem_skip_welcome: bool,
+    /// 时区索引（如 210 = 中国标准时间，35 = 东部时间）。
+    pub time_zone: u32,
+}
+
+/// `[UserData]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UserDataSection {
+    pub full_name: String,
+    pub org_name: String,
+    /// 计算机名；`None` = 随机（写作 `*`）。
+    pub computer_name: Option<String>,
+    pub product_key: Option<ProductKey>,
+}
+
+/// `[RegionalSettings]`（全部为空时整节不输出）。
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RegionalSettingsSection {
+    /// 语言组编号（如 1 西欧、10 简体中文）。
+    pub language_group: Vec<u32>,
+    /// 区域 LCID（8 位十六进制，如 `00000804`），设置后覆盖下面三项的缺省值。
+    pub language: Option<String>,
+    pub system_locale: Option<String>,
+    pub user_locale: Option<String>,
+    /// 输入法/键盘，如 `0804:00000804`。
+    pub input_locale: Option<String>,
+}
+
+/// `[Identification]`（工作组与域二选一）。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdentificationSection {
+    Workgroup(String),
+    Domain(String),
+}
+
+/// `[Networking]`。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NetworkingSection {
+    pub install_default_components: bool,
+}
+
+/// 类型化的 winnt.sif。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WinntSif {
+    pub data: DataSection,
+    pub unattended: UnattendedSection,
+    pub gui_unattended: GuiUnattendedSection,
+    pub user_data: UserDataSection,
+    pub regional_settings: RegionalSettingsSection,
+    pub identification: IdentificationSection,
+    pub networking: NetworkingSection,
+}
+
+fn yes_no(b: bool) -> &'static str {
+    if b {
+        "Yes"
+    } else {
+        "No"
+    }
+}
+
+fn one_zero(b: bool) -> &'static str {
+    if b {
+        "1"
+    } else {
+        "0"
+    }
+}
+
+impl WinntSif {
+    /// 本地源硬盘安装的默认应答。
+    ///
+    /// - 有 `product_key`：`UnattendMode=FullUnattended` 全自动（文本+图形全程无停顿）；
+    /// - 无密钥：`UnattendMode=DefaultHide`（隐藏已答页，仅在「产品密钥」页停一下）。
+    ///
+    /// 统一项：跳过 EULA/区域/欢迎；`DriverSigningPolicy=Ignore`（不拦未签名/注入的存储驱动）；
+    /// 管理员空密码 + 首次自动登录；不分区/不格式化（沿用已格式化的目标盘）；目标 `\WINDOWS`。
+    /// 出于安全，文本阶段仍由用户确认安装分区（`AutoPartition=0`），避免自动选错盘抹掉数据。
+    pub fn local_source(product_key: Option<ProductKey>) -> Self {
+        let mode = if product_key.is_some() {
+            UnattendMode::FullUnattended
+        } else {
+            UnattendMode::DefaultHide
+        };
+        Self {
+            data: DataSection {
+                auto_partition: false,
+                msdos_initiated: true,
+                unattended_install: true,
+                floppyless: true,
+            },
+            unattended: UnattendedSection {
+                unattend_mode: mode,
+                unattend_switch: true,
+                oem_preinstall: true,
+                oem_skip_eula: true,
+                target_path: "\\WINDOWS".to_string(),
+                convert_ntfs: false,
+                wait_for_reboot: false,
+                driver_signing_policy: DriverSigningPolicy::Ignore,
+            },
+            gui_unattended: GuiUnattendedSection {
+                admin_password: None,
+                auto_logon: true,
+                auto_logon_count: 1,
+                oem_skip_regional: true,
+                oem_skip_welcome: true,
+                time_zone: 210,
+            },
+            user_data: UserDataSection {
+                full_name: "User".to_string(),
+                org_name: String::new(),
+                computer_name: None,
+                product_key,
+            },
+            regional_settings: RegionalSettingsSection::default(),
+            identification: IdentificationSection::Workgroup("WORKGROUP".to_string()),
+            networking: NetworkingSection {
+                install_default_components: true,
+            },
+        }
+    }
+
+    /// 按输出顺序列出（节, 键, 值）。
+    pub fn entries(&self) -> Vec<(&'static str, &'static str, String)> {
+        let mut v: Vec<(&'static str, &'static str, String)> = Vec::new();
+        let d = &self.data;
+        v.push(("Data", "AutoPartition", one_zero(d.auto_partition).into()));
+        v.push(("Data", "MsDosInitiated", one_zero(d.msdos_initiated).into()));
+        v.push((
+            "Data",
+            "UnattendedInstall",
+            yes_no(d.unattended_install).into(),
+        ));
+        v.push(("Data", "Floppyless", one_zero(d.floppyless).into()));
+
+        let u = &self.unattended;
+        v.push((
+            "Unattended",
+            "UnattendMode",
+            u.unattend_mode.as_str().into(),
+        ));
+        v.push((
+            "Unattended",
+            "UnattendSwitch",
+            yes_no(u.unattend_switch).into(),
+        ));
+        v.push((
+            "Unattended",
+            "OemPreinstall",
+            yes_no(u.oem_preinstall).into(),
+        ));
+        v.push(("Unattended", "OemSkipEula", yes_no(u.oem_skip_eula).into()));
+        v.push(("Unattended", "TargetPath", u.target_path.clone()));
+        let fs = if u.convert_ntfs {
+            "ConvertNTFS"
+        } else {
+            "LeaveAlone"
+        };
+        v.push(("Unattended", "FileSystem", fs.into()));
+        v.push((
+            "Unattended",
+            "WaitForReboot",
+            yes_no(u.wait_for_reboot).into(),
+        ));
+        v.push((
+            "Unattended",
+            "DriverSigningPolicy",
+            u.driver_signing_policy.as_str().into(),
+        ));
+
+        let g = &self.gui_unattended;
+        let pw = match &g.admin_password {
+            Some(p) if !p.is_empty() => format!("\"{}\"", p),
+            _ => "*".to_string(),
+        };
+        v.push(("GuiUnattended", "AdminPassword", pw));
+        v.push(("GuiUnattended", "EncryptedAdminPassword", "No".into()));
+        v.push(("GuiUnattended", "AutoLogon", yes_no(g.auto_logon).into()));
+        if g.auto_logon {
+            v.push((
+                "GuiUnattended",
+                "AutoLogonCount",
+                g.auto_logon_count.to_string(),
+            ));
+        }
+        v.push((
+            "GuiUnattended",
+            "OEMSkipRegional",
+            one_zero(g.oem_skip_regional).into(),
+        ));
+        v.push((
+            "GuiUnattended",
+            "OemSkipWelcome",
+            one_zero(g.oem_skip_welcome).into(),
+        ));
+        v.push(("GuiUnattended", "TimeZone", g.time_zone.to_string()));
+
+        let ud = &self.user_data;
+        v.push(("UserData", "FullName", format!("\"{}\"", ud.full_name)));
+        v.push(("UserData", "OrgName", format!("\"{}\"", ud.org_name)));
+        let cn = ud.computer_name.clone().unwrap_or_else(|| "*".to_string());
+        v.push(("UserData", "ComputerName", cn));
+        if let Some(k) = &ud.product_key {
+            v.push(("UserData", "ProductKey", format!("\"{}\"", k.as_str())));
+        }
+
+        let r = &self.regional_settings;
+        if !r.language_group.is_empty() {
+            let groups: Vec<String> = r.language_group.iter().map(|g| g.to_string()).collect();
+            v.push(("RegionalSettings", "LanguageGroup", groups.join(",")));
+        }
+        for (key, val) in [
+            ("Language", &r.language),
+            ("SystemLocale", &r.system_locale),
+            ("UserLocale", &r.user_locale),
+            ("InputLocale", &r.input_locale),
+        ] {
+            if let Some(val) = val {
+                v.push(("RegionalSettings", key, val.clone()));
+            }
+        }
+
+        match &self.identification {
+            IdentificationSection::Workgroup(w) => {
+                v.push(("Identification", "JoinWorkgroup", w.clone()))
+            }
+            IdentificationSection::Domain(d) => v.push(("Identification", "JoinDomain", d.clone())),
+        }
+
+        v.push((
+            "Networking",
+            "InstallDefaultComponents",
+            yes_no(self.networking.install_default_components).into(),
+        ));
+        v
+    }
+
+    /// 生成完整 winnt.sif 文本（CRLF，节间空一行，纯 ASCII 之外的内容按 ANSI 写出）。
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        let mut cur = "";
+        for (section, key, value) in self.entries() {
+            if section != cur {
+                if !cur.is_empty() {
+                    out.push_str("\r\n");
+                }
+                out.push_str(&format!("[{}]\r\n", section));
+                cur = section;
+            }
+            out.push_str(&format!("{}={}\r\n", key, value));
+        }
+        out
+    }
+
+    /// 生成 [`SifDocument`]（便于与用户文件同样的方式写出/再校验）。
+    pub fn to_document(&self) -> SifDocument {
+        SifDocument::parse(&self.render())
+    }
+
+    /// 把本应答的各键补进用户文件：用户已写的键原样保留，只补缺失的键（缺节则新建）。
+    /// 返回补入的键数。硬盘安装必需键由随后的 [`apply_mandatory`] 强制。
+    pub fn merge_into(&self, doc: &mut SifDocument) -> usize {
+        let mut added = 0;
+        for (section, key, value) in self.entries() {
+            // 工作组/域二选一：用户写了任一个，就不再补另一个
+            if section == "Identification"
+                && (doc.has_key("Identification", "JoinWorkgroup")
+                    || doc.has_key("Identification", "JoinDomain"))
+            {
+                continue;
+            }
+            if !doc.has_key(section, key) {
+                doc.set(section, key, &value);
+                added += 1;
+            }
+        }
+        added
+    }
+}
+
+// ───────────────────────── 校验 ─────────────────────────
+
+/// 校验结果的严重程度。
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SifSeverity {
+    /// 会导致安装失败/停顿，应阻止使用。
+    Error,
+    /// 会被忽略或被强制覆盖，提示即可。
+    Warning,
+}
+
+/// 一条校验结果。`line` 为 1 起始的行号。
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SifIssue {
+    pub line: usize,
+    pub severity: SifSeverity,
+    pub message: String,
+}
+
+impl fmt::Display for SifIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "第 {} 行：{}", self.line, self.message)
+    }
+}
+
+/// 已知节及其键（来自 XP/2003 部署工具 ref.chm；大小写不敏感）。
+const SCHEMA: &[(&str, &[&str])] = &[
+    (
+        "Data",
+        &[
+            "AutoPartition",
+            "MsDosInitiated",
+            "UnattendedInstall",
+            "Floppyless",
+            "UseBIOSToBoot",
+        ],
+    ),
+    (
+        "Unattended",
+        &[
+            "UnattendMode",
+            "UnattendSwitch",
+            "OemPreinstall",
+            "OemSkipEula",
+            "TargetPath",
+            "FileSystem",
+            "WaitForReboot",
+            "DriverSigningPolicy",
+            "NonDriverSigningPolicy",
+            "Repartition",
+            "ExtendOemPartition",
+            "OemPnPDriversPath",
+            "OemFilesPath",
+            "NoWaitAfterTextMode",
+            "NoWaitAfterGUIMode",
+            "KeyboardLayout",
+            "CrashDumpSetting",
+            "DisableVirtualOemDevices",
+            "Hibernation",
+            "ProgramFilesDir",
+            "CommonProgramFilesDir",
+            "ConfirmHardware",
+            "NtUpgrade",
+            "OverwriteOemFilesOnUpgrade",
+            "Win9xUpgrade",
+            "AutoActivate",
+            "ResetSourcePath",
+            "UpdateInstalledDrivers",
+            "UpdateHAL",
+            "UpdateUPHAL",
+            "DUDisable",
+            "DUShare",
+            "ImplicitInstallDir",
+        ],
+    ),
+    (
+        "GuiUnattended",
+        &[
+            "AdminPassword",
+            "EncryptedAdminPassword",
+            "AutoLogon",
+            "AutoLogonCount",
+            "OEMSkipRegional",
+            "OemSkipWelcome",
+            "TimeZone",
+            "ProfilesDir",
+            "DetachedProgram",
+            "Arguments",
+            "ServerWelcome",
+            "OEMDuplicatorString",
+            "EMSSkipUnattendProcessing",
+        ],
+    ),
+    (
+        "UserData",
+        &[
+            "FullName",
+            "OrgName",
+            "ComputerName",
+            "ProductKey",
+            "ProductID",
+        ],
+    ),
+    (
+        "RegionalSettings",
+        &[
+            "LanguageGroup",
+            "Language",
+            "SystemLocale",
+            "UserLocale",
+            "InputLocale",
+            "UserLocale_DefaultUser",
+            "InputLocale_DefaultUser",
+        ],
+    ),
+    (
+        "Identification",
+        &[
+            "JoinWorkgroup",
+            "JoinDomain",
+            "DomainAdmin",
+            "DomainAdminPassword",
+            "MachineObjectOU",
+            "DoOldStyleDomainJoin",
+        ],
+    ),
+    (
+        "Networking",
+        &["InstallDefaultComponents", "ProcessPageSections"],
+    ),
+];
+
+/// 枚举型键的合法取值（大小写不敏感）。
+const ENUMS: &[(&str, &str, &[&str])] = &[
+    ("Data", "AutoPartition", &["0", "1"]),
+    ("Data", "MsDosInitiated", &["0", "1"]),
+    ("Data", "Floppyless", &["0", "1"]),
+    ("Data", "UnattendedInstall", &["Yes", "No"]),
+    (
+        "Unattended",
+        "UnattendMode",
+        &[
+            "DefaultHide",
+            "FullUnattended",
+            "GuiAttended",
+            "ProvideDefault",
+            "ReadOnly",
+        ],
+    ),
+    ("Unattended", "UnattendSwitch", &["Yes", "No"]),
+    ("Unattended", "OemPreinstall", &["Yes", "No"]),
+    ("Unattended", "OemSkipEula", &["Yes", "No"]),
+    ("Unattended", "FileSystem", &["ConvertNTFS", "LeaveAlone"]),
+    ("Unattended", "WaitForReboot", &["Yes", "No"]),
+    (
+        "Unattended",
+        "DriverSigningPolicy",
+        &["Ignore", "Warn", "Block"],
+    ),
+    (
+        "Unattended",
+        "NonDriverSigningPolicy",
+        &["Ignore", "Warn", "Block"],
+    ),
+    ("Unattended", "Repartition", &["Yes", "No"]),
+    ("GuiUnattended", "AutoLogon", &["Yes", "No"]),
+    ("GuiUnattended", "EncryptedAdminPassword", &["Yes", "No"]),
+    ("GuiUnattended", "OEMSkipRegional", &["0", "1"]),
+    ("GuiUnattended", "OemSkipWelcome", &["0", "1"]),
+    ("Networking", "InstallDefaultComponents", &["Yes", "No"]),
+];
+
+/// 与本地源硬盘安装冲突的取值：（节, 键, 冲突值, 说明）。
+const CONFLICTS: &[(&str, &str, &str, &str)] = &[
+    (
+        "Data",
+        "AutoPartition",
+        "1",
+        "本地源安装要求 AutoPartition=0（自动分区可能选错盘抹掉数据），将被强制改为 0",
+    ),
+    (
+        "Data",
+        "MsDosInitiated",
+        "0",
+        "本地源安装要求 MsDosInitiated=1（否则文本安装会去找光盘），将被强制改为 1",
+    ),
+    (
+        "Data",
+        "Floppyless",
+        "0",
+        "本地源安装要求 Floppyless=1，将被强制改为 1",
+    ),
+    (
+        "Data",
+        "UnattendedInstall",
+        "No",
+        "将被强制改为 UnattendedInstall=Yes",
+    ),
+    (
+        "Unattended",
+        "OemPreinstall",
+        "No",
+        "将被强制改为 OemPreinstall=Yes",
+    ),
+    (
+        "Unattended",
+        "Repartition",
+        "Yes",
+        "Repartition=Yes 会删除目标盘所有分区，与本地源安装（源就在目标盘上）冲突",
+    ),
+    (
+        "Unattended",
+        "FileSystem",
+        "ConvertNTFS",
+        "目标盘已由 LetRecovery 格式化，ConvertNTFS 无意义，建议 LeaveAlone",
+    ),
+];
+
+/// 编辑距离（仅用于给拼错的键名找建议，字符串都很短）。
+fn edit_distance(a: &str, b: &str) -> usize {
+    let a: Vec<char> = a.to_ascii_lowercase().chars().collect();
+    let b: Vec<char> = b.to_ascii_lowercase().chars().collect();
+    let mut prev: Vec<usize> = (0..=b.len()).collect();
+    for i in 1..=a.len() {
+        let mut cur = vec![i; b.len() + 1];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            cur[j] = (prev[j] + 1).min(cur[j - 1] + 1).min(prev[j - 1] + cost);
+        }
+        prev = cur;
+    }
+    prev[b.len()]
+}
+
+/// 从候选里找最接近 `name` 的（距离 ≤ 2）。
+fn suggest<'a>(name: &str, candidates: impl Iterator<Item = &'a str>) -> Option<&'a str> {
+    candidates
+        .map(|c| (edit_distance(name, c), c))
+        .filter(|(d, _)| *d <= 2)
+        .min_by_key(|(d, _)| *d)
+        .map(|(_, c)| c)
+}
+
+/// 校验 winnt.sif。不认识的节（如 `[Components]`、`[GuiRunOnce]`）不检查其键；
+/// 但与已知节名只差一两个字母的节视为拼错。
+pub fn validate(doc: &SifDocument) -> Vec<SifIssue> {
+    let mut issues = Vec::new();
+    let mut section: Option<&'static str> = None;
+    let mut has_product_key = false;
+    let mut full_unattended_line: Option<usize> = None;
+    let mut identification: Vec<(usize, &str)> = Vec::new();
+
+    for (idx, kind) in doc.lines().enumerate() {
+        let line = idx + 1;
+        match kind {
+            SifLineKind::Section(name) => {
+                section = SCHEMA
+                    .iter()
+                    .find(|(s, _)| s.eq_ignore_ascii_case(name))
+                    .map(|(s, _)| *s);
+                if section.is_none() {
+                    if let Some(s) = suggest(name, SCHEMA.iter().map(|(s, _)| *s)) {
+                        issues.push(SifIssue {
+                            line,
+                            severity: SifSeverity::Error,
+                            message: format!(
+                                "未知节 [{}]，是否应为 [{}]？该节内容会被安装程序忽略",
+                                name, s
+                            ),
+                        });
+                    }
+                }
+            }
+            SifLineKind::KeyValue { key, value } => {
+                let Some(sec) = section else { continue };
+                let key = unquote(key);
+                let keys = SCHEMA
+                    .iter()
+                    .find(|(s, _)| *s == sec)
+                    .map(|(_, k)| *k)
+                    .unwrap_or(&[]);
+                let Some(&known) = keys.iter().find(|k| k.eq_ignore_ascii_case(key)) else {
+                    let (severity, message) = match suggest(key, keys.iter().copied()) {
+                        Some(s) => (
+                            SifSeverity::Error,
+                            format!("[{}] 中的键 {} 拼写有误，是否应为 {}？", sec, key, s),
+                        ),
+                        None => (
+                            SifSeverity::Warning,
+                            format!("[{}] 中的未知键 {}，安装程序会忽略它", sec, key),
+                        ),
+                    };
+                    issues.push(SifIssue {
+                        line,
+                        severity,
+                        message,
+                    });
+                    continue;
+                };
+                let v = unquote(value);
+
+                if let Some((_, _, allowed)) = ENUMS
+                    .iter()
+                    .find(|(s, k, _)| *s == sec && k.eq_ignore_ascii_case(known))
+                {
+                    if !allowed.iter().any(|a| a.eq_ignore_ascii_case(v)) {
+                        issues.push(SifIssue {
+                            line,
+                            severity: SifSeverity::Error,
+                            message: format!(
+                                "{}={} 不是合法取值（可选：{}）",
+                                known,
+                                v,
+                                allowed.join(" / ")
+                            ),
+                        });
+                    }
+                }
+                if let Some((_, _, _, why)) = CONFLICTS.iter().find(|(s, k, bad, _)| {
+                    *s == sec && k.eq_ignore_ascii_case(known) && bad.eq_ignore_ascii_case(v)
+                }) {
+                    let severity = if MANDATORY_KEYS
+                        .iter()
+                        .any(|(s, k, _)| *s == sec && k.eq_ignore_ascii_case(known))
+                    {
+                        SifSeverity::Warning
+                    } else {
+                        SifSeverity::Error
+                    };
+                    issues.push(SifIssue {
+                        line,
+                        severity,
+                        message: format!("{}={}：{}", known, v, why),
+                    });
+                }
+
+                match (sec, known) {
+                    ("UserData", "ProductKey") => {
+                        has_product_key = true;
+                        if let Err(e) = ProductKey::parse(v) {
+                            issues.push(SifIssue {
+                                line,
+                                severity: SifSeverity::Error,
+                                message: e,
+                            });
+                        }
+                    }
+                    ("UserData", "ComputerName") if v != "*" && v.chars().count() > 15 => {
+                        issues.push(SifIssue {
+                            line,
+                            severity: SifSeverity::Error,
+                            message: format!("计算机名 {} 超过 15 个字符（NetBIOS 限制）", v),
+                        });
+                    }
+                    ("GuiUnattended", "TimeZone") | ("GuiUnattended", "AutoLogonCount")
+                        if v.parse::<u32>().is_err() =>
+                    {
+                        issues.push(SifIssue {
+                            line,
+                            severity: SifSeverity::Error,
+                            message: format!("{}={} 应为数字", known, v),
+                        });
+                    }
+                    ("Unattended", "UnattendMode") if v.eq_ignore_ascii_case("FullUnattended") => {
+                        full_unattended_line = Some(line);
+                    }
+                    ("Identification", "JoinWorkgroup") | ("Identification", "JoinDomain") => {
+                        identification.push((line, known));
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    if let Some(line) = full_unattended_line {
+        if !has_product_key {
+            issues.push(SifIssue {
+                line,
+                severity: SifSeverity::Warning,
+                message: "UnattendMode=FullUnattended 但未提供 ProductKey，安装会停在密钥页"
+                    .to_string(),
+            });
+        }
+    }
+    let has_wg = identification.iter().any(|(_, k)| *k == "JoinWorkgroup");
+    let has_dom = identification.iter().any(|(_, k)| *k == "JoinDomain");
+    if has_wg && has_dom {
+        issues.push(SifIssue {
+            line: identification.iter().map(|(l, _)| *l).max().unwrap_or(0),
+            severity: SifSeverity::Error,
+            message: "JoinWorkgroup 与 JoinDomain 不能同时指定".to_string(),
+        });
+    }
+    issues.sort_by_key(|i| i.line);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const KEY: &str = "BCDFG-HJKMP-QRTVW-XY234-6789B";
+
+    #[test]
+    fn product_key_format() {
+        assert_eq!(
+            ProductKey::parse(&format!("\"{}\"", KEY.to_lowercase()))
+                .unwrap()
+                .as_str(),
+            KEY
+        );
+        assert!(ProductKey::parse("AAAAA-BBBBB").is_err());
+        assert!(ProductKey::parse("BCDFG-HJKMP-QRTVW-XY234-6789").is_err());
+        // 含 0/1/5/A 等 Base24 以外的字符
+        assert!(ProductKey::parse("AAAAA-BBBBB-CCCCC-DDDDD-EEEEE").is_err());
+        assert!(ProductKey::parse("BCDF0-HJKMP-QRTVW-XY234-6789B").is_err());
+    }
+
+    #[test]
+    fn local_source_render_matches_builtin_layout() {
+        let s = WinntSif::local_source(None).render();
+        assert!(s.starts_with("[Data]\r\nAutoPartition=0\r\nMsDosInitiated=1\r\nUnattendedInstall=Yes\r\nFloppyless=1\r\n\r\n[Unattended]\r\n"));
+        assert!(s.contains("UnattendMode=DefaultHide\r\n"));
+        assert!(s.contains("AdminPassword=*\r\n"));
+        assert!(s.contains("ComputerName=*\r\n"));
+        assert!(!s.contains("ProductKey"));
+        assert!(!s.contains("[RegionalSettings]"));
+        assert!(s.ends_with("[Networking]\r\nInstallDefaultComponents=Yes\r\n"));
+        assert!(validate(&SifDocument::parse(&s)).is_empty());
+    }
+
+    #[test]
+    fn full_unattended_with_key_and_regional() {
+        let mut w = WinntSif::local_source(Some(ProductKey::parse(KEY).unwrap()));
+        w.regional_settings.language_group = vec![1, 10];
+        w.regional_settings.language = Some("00000804".into());
+        w.identification = IdentificationSection::Domain("corp.local".into());
+        let s = w.render();
+        assert!(s.contains("UnattendMode=FullUnattended\r\n"));
+        assert!(s.contains(&format!("ProductKey=\"{}\"\r\n", KEY)));
+        assert!(s.contains("[RegionalSettings]\r\nLanguageGroup=1,10\r\nLanguage=00000804\r\n"));
+        assert!(s.contains("[Identification]\r\nJoinDomain=corp.local\r\n"));
+        assert!(validate(&SifDocument::parse(&s)).is_empty());
+    }
+
+    #[test]
+    fn merge_keeps_user_values_and_fills_gaps() {
+        let mut doc = SifDocument::parse(
+            "; my answers\r\n[UserData]\r\nFullName=\"Alice\"\r\nComputerName=LAB-01\r\n[Identification]\r\nJoinDomain=corp\r\n",
+        );
+        let added = WinntSif::local_source(None).merge_into(&mut doc);
+        apply_mandatory(&mut doc);
+        assert!(added > 0);
+        assert_eq!(doc.get("UserData", "FullName"), Some("\"Alice\""));
+        assert_eq!(doc.get("UserData", "ComputerName"), Some("LAB-01"));
+        assert_eq!(doc.get("UserData", "OrgName"), Some("\"\""));
+        assert_eq!(doc.get("Data", "MsDosInitiated"), Some("1"));
+        assert_eq!(doc.get("Unattended", "DriverSigningPolicy"), Some("Ignore"));
+        // 用户选了域，不能再补 JoinWorkgroup
+        assert!(!doc.has_key("Identification", "JoinWorkgroup"));
+        assert!(doc
+            .to_text()
+            .starts_with("; my answers\r\n[UserData]\r\nFullName=\"Alice\"\r\n"));
+    }
+
+    #[test]
+    fn validate_flags_misspelled_and_unknown_keys() {
+        let doc = SifDocument::parse("[Unattended]\r\nUnatendMode=DefaultHide\r\nFooBarBaz=1\r\n[GuiUnatended]\r\nx=1\r\n[Components]\r\nmsmsgs=Off\r\n");
+        let issues = validate(&doc);
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert_eq!(issues[0].line, 2);
+        assert_eq!(issues[0].severity, SifSeverity::Error);
+        assert!(issues[0].message.contains("UnattendMode"));
+        assert_eq!(issues[1].severity, SifSeverity::Warning);
+        assert_eq!(issues[2].line, 4);
+        assert!(issues[2].message.contains("[GuiUnattended]"));
+    }
+
+    #[test]
+    fn validate_flags_conflicts_with_local_source() {
+        let doc = SifDocument::parse("[Data]\r\nAutoPartition=1\r\n[Unattended]\r\nRepartition=Yes\r\nUnattendMode=Silent\r\n");
+        let issues = validate(&doc);
+        assert_eq!(issues.len(), 3, "{:?}", issues);
+        assert_eq!(
+            (issues[0].line, issues[0].severity),
+            (2, SifSeverity::Warning)
+        );
+        assert_eq!(
+            (issues[1].line, issues[1].severity),
+            (4, SifSeverity::Error)
+        );
+        assert_eq!(
+            (issues[2].line, issues[2].severity),
+            (5, SifSeverity::Error)
+        );
+    }
+
+    #[test]
+    fn validate_product_key_computer_name_and_identification() {
+        let doc = SifDocument::parse("[UserData]\r\nProductKey=\"12345-ABCDE\"\r\nComputerName=THIS-NAME-IS-TOO-LONG\r\n[Identification]\r\nJoinWorkgroup=WG\r\nJoinDomain=corp\r\n");
+        let issues = validate(&doc);
+        let lines: Vec<usize> = issues.iter().map(|i| i.line).collect();
+        assert_eq!(lines, vec![2, 3, 6]);
+        assert!(issues.iter().all(|i| i.severity == SifSeverity::Error));
+    }
+
+    #[test]
+    fn validate_full_unattended_without_key() {
+        let doc = SifDocument::parse("[Unattended]\r\nUnattendMode=FullUnattended\r\n");
+        let issues = validate(&doc);
+        assert_eq!(issues.len(), 1);
+        assert_eq!(issues[0].severity, SifSeverity::Warning);
+    }
+
+    #[test]
+    fn edit_distance_basics() {
+        assert_eq!(edit_distance("UnatendMode", "UnattendMode"), 1);
+        assert_eq!(edit_distance("data", "Data"), 0);
+        assert_eq!(
+            suggest("Flopyless", ["Floppyless", "AutoPartition"].into_iter()),
+            Some("Floppyless")
+        );
+        assert_eq!(suggest("Zzz", ["Floppyless"].into_iter()), None);
+    }
+}
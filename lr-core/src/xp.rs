@@ -22,7 +22,7 @@
 
 use std::path::{Path, PathBuf};
 
-use crate::command::new_command;
+use crate::command;
 use crate::encoding::gbk_to_utf8;
 use crate::registry::OfflineRegistry;
 
@@ -334,9 +334,7 @@ pub fn write_xp_uefi_gpt_boot(
         ("{0ef2423f-74c2-4688-b906-99c3dc77d8ba}", "custom:21000001", format!("partition={}", win)),
     ];
     for (obj, elem, val) in cmds {
-        let out = new_command(bcdedit_path)
-            .args(["/store", &store, "/set", obj, elem, val])
-            .output();
+        let out = command::run(bcdedit_path, ["/store", &store, "/set", obj, elem, val]);
         match out {
             Ok(o) => {
                 if o.status.success() {
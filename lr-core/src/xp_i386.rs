@@ -24,8 +24,10 @@ use std::path::Path;
 use std::thread::sleep;
 use std::time::Duration;
 
-use crate::command::new_command;
+use crate::command;
 use crate::encoding::gbk_to_utf8;
+use crate::sif::{SifDocument, SifEncoding};
+use crate::winnt_sif::{self, apply_mandatory, ProductKey, SifSeverity, WinntSif};
 
 /// `$WIN_NT$.~BT` 引导文件清单（编译期嵌入，照搬 DSI nt5\NT5.txt）。
 const NT5_BOOTFILES: &str = include_str!("xp_nt5_bootfiles.txt");
@@ -50,8 +52,9 @@ pub fn is_valid_i386(dir: &Path) -> bool {
 /// - `i386_src`：i386 目录（如挂载 ISO 的 `G:\I386`，或已复制到数据分区的副本）。
 /// - `win_partition`：目标系统盘（如 `"C:"`），需已格式化且为目标磁盘的主分区。
 /// - `bin_dir`：程序 bin 目录（取 `bootsect.exe`；可选 `bin\xp\productkey.txt` 提供产品密钥实现全自动）。
-/// - `custom_sif`：用户自定义的 winnt.sif 应答文件路径；`Some` 且存在时直接用它（原样写入，
-///   规整为 CRLF），否则用内置生成的应答（按是否有产品密钥决定 DefaultHide/FullUnattended）。
+/// - `custom_sif`：用户自定义的 winnt.sif 应答文件路径；`Some` 且存在时以它为准（用户写的键
+///   原样保留，缺的键用内置默认补齐，规整为 CRLF），否则用内置生成的应答（按是否有产品密钥
+///   决定 DefaultHide/FullUnattended）。
 pub fn install_from_i386(
     i386_src: &Path,
     win_partition: &str,
@@ -129,9 +132,7 @@ pub fn install_from_i386(
     // /C：单个文件出错（被占用/锁定等）也继续拷其余文件，不因一个非关键文件失败就整盘中止
     //    （照搬 DSI 的容错——它直接忽略 xcopy 退出码）。但比 DSI 更稳：拷完后在【目标】里复测核心
     //    文件是否到位，而不是去猜「xcopy 加 /C 后非 0 退出」到底是部分跳过还是整体失败。
-    let out = new_command("xcopy")
-        .args([src.as_str(), ls_src.as_str(), "/E", "/I", "/H", "/C", "/R", "/Y", "/Q"])
-        .output()
+    let out = command::run("xcopy", [src.as_str(), ls_src.as_str(), "/E", "/I", "/H", "/C", "/R", "/Y", "/Q"])
         .map_err(|e| format!("xcopy 执行失败: {e}"))?;
     log.push_str(&gbk_to_utf8(&out.stdout));
     if !out.status.success() {
@@ -166,10 +167,7 @@ pub fn install_from_i386(
                 let s = sib_i386.to_string_lossy().to_string();
                 let d = format!("{win}\\$WIN_NT$.~LS\\I386");
                 let _ = create_dir_all_retry(&d);
-                match new_command("xcopy")
-                    .args([s.as_str(), d.as_str(), "/E", "/I", "/H", "/R", "/Y", "/Q"])
-                    .output()
-                {
+                match command::run("xcopy", [s.as_str(), d.as_str(), "/E", "/I", "/H", "/R", "/Y", "/Q"]) {
                     Ok(o) if o.status.success() => {
                         log.push_str("已并拷同级 \\I386（32 位 WoW64 组件）→ $WIN_NT$.~LS\\I386\n")
                     }
@@ -199,9 +197,7 @@ pub fn install_from_i386(
     if sys32_src.exists() {
         let s = sys32_src.to_string_lossy().to_string();
         let d = format!("{bt}\\SYSTEM32");
-        let o = new_command("xcopy")
-            .args([s.as_str(), d.as_str(), "/E", "/I", "/H", "/R", "/Y", "/Q"])
-            .output()
+        let o = command::run("xcopy", [s.as_str(), d.as_str(), "/E", "/I", "/H", "/R", "/Y", "/Q"])
             .map_err(|e| format!("拷 SYSTEM32 → $WIN_NT$.~BT 失败: {e}"))?;
         if o.status.success() {
             log.push_str("已复制 <源>\\SYSTEM32 → $WIN_NT$.~BT\\SYSTEM32\n");
@@ -282,27 +278,16 @@ pub fn install_from_i386(
     //   否则非 ASCII 行全乱 → 蓝屏「安装程序用在第 N 行上的 .SIF 文件中有一个语法错误」。
     //   · 无驱动要集成（原版 32 位 i386 即此路）：原样写源文件字节，一个字节都不动（最稳，且 NT5.txt 清单
     //     已把同一份字节拷进 $WIN_NT$.~BT，这里只是覆盖成相同内容 + 落一份到根目录）。
-    //   · 有驱动要集成：解码→追加 ASCII 集成行→按【原编码】写回（原是 GBK 就编回 GBK；追加的都是 ASCII，无损）。
+    //   · 有驱动要集成：经 SifDocument 原位插入 ASCII 集成行，其余行逐字节不变、按原编码写回。
     let txtsetup_bytes: Vec<u8> = if drivers.is_empty() {
         log.push_str("文本期无驱动集成：TXTSETUP.SIF 原样写入（保持原 ANSI/GBK 编码不变）\n");
         raw.clone()
     } else {
-        let was_utf8 = std::str::from_utf8(&raw).is_ok();
-        let txt = if was_utf8 {
-            String::from_utf8_lossy(&raw).into_owned()
-        } else {
-            gbk_to_utf8(&raw)
-        };
-        let txt = normalize_crlf(&txt);
-        let (final_txtsetup, drvlog) =
-            crate::xp_textmode_drv::integrate(&txt, &drivers, &[Path::new(&ls_src), Path::new(&bt)]);
+        let mut doc = SifDocument::parse_bytes(&raw);
+        let drvlog =
+            crate::xp_textmode_drv::integrate(&mut doc, &drivers, &[Path::new(&ls_src), Path::new(&bt)]);
         log.push_str(&drvlog);
-        // 原是 GBK 就编回 GBK（集成追加的都是 ASCII，是 GBK 子集，无损）；原本就是纯 ASCII/UTF-8 才按 UTF-8 写。
-        if was_utf8 {
-            final_txtsetup.into_bytes()
-        } else {
-            crate::encoding::utf8_to_gbk(&final_txtsetup)
-        }
+        doc.to_bytes()
     };
     write_force(&format!("{bt}\\TXTSETUP.SIF"), &txtsetup_bytes)
         .map_err(|e| format!("写 $WIN_NT$.~BT\\TXTSETUP.SIF 失败: {e}"))?;
@@ -310,22 +295,34 @@ pub fn install_from_i386(
         .map_err(|e| format!("写根 TXTSETUP.SIF 失败: {e}"))?;
     log.push_str("已写入 TXTSETUP.SIF（$WIN_NT$.~BT 与根）\n");
 
-    // 4) winnt.sif 应答：优先用户自定义；否则内置生成。无论哪种，都【强制写入硬盘安装必需的键】
-    //    （照搬 DSI 的 NT5部署无人值守：MsDosInitiated=1 / Floppyless=1 / AutoPartition=0 /
-    //    UnattendedInstall=Yes / OemPreinstall=Yes）——缺它们文本安装会去找光盘而失败。
+    // 4) winnt.sif 应答：用户自定义的与内置默认【合并】——用户已写的键原样保留，缺的键用内置
+    //    默认补齐；无论哪种，最后都【强制写入硬盘安装必需的键】（照搬 DSI 的 NT5部署无人值守：
+    //    MsDosInitiated=1 / Floppyless=1 / AutoPartition=0 / UnattendedInstall=Yes /
+    //    OemPreinstall=Yes）——缺它们文本安装会去找光盘而失败。
     //    放在 $WIN_NT$.~BT\WINNT.SIF（文本安装阶段读这份）。
-    let (sif_raw, sif_was_utf8) = match custom_sif {
+    let mut sif = match custom_sif {
         Some(p) if p.exists() => {
             let raw = std::fs::read(p)
                 .map_err(|e| format!("读自定义 winnt.sif 失败 {}: {e}", p.display()))?;
-            let was_utf8 = std::str::from_utf8(&raw).is_ok();
-            let s = if was_utf8 {
-                String::from_utf8_lossy(&raw).into_owned()
-            } else {
-                gbk_to_utf8(&raw)
-            };
             log.push_str(&format!("使用自定义无人值守应答: {}\n", p.display()));
-            (normalize_crlf(&s), was_utf8)
+            let mut doc = SifDocument::parse_bytes(&raw);
+            for issue in winnt_sif::validate(&doc) {
+                let tag = match issue.severity {
+                    SifSeverity::Error => "错误",
+                    SifSeverity::Warning => "提示",
+                };
+                log.push_str(&format!("  winnt.sif {tag}：{issue}\n"));
+            }
+            // 用户文件里的密钥优先；没有再用 bin\xp\productkey.txt 的
+            let product_key = doc
+                .get("UserData", "ProductKey")
+                .and_then(|k| ProductKey::parse(k).ok())
+                .or_else(|| read_product_key(bin_dir));
+            let added = WinntSif::local_source(product_key).merge_into(&mut doc);
+            if added > 0 {
+                log.push_str(&format!("已用内置默认补齐自定义应答缺少的 {added} 个键\n"));
+            }
+            doc
         }
         _ => {
             let product_key = read_product_key(bin_dir);
@@ -337,18 +334,25 @@ pub fn install_from_i386(
                     "未提供产品密钥（可放 bin\\xp\\productkey.txt 实现全自动）→ 仅「密钥」页停顿，其余无人值守\n",
                 ),
             }
-            // 内置生成的应答是纯 ASCII，按 UTF-8(=ASCII) 写即可。
-            (winnt_sif(product_key.as_deref()), true)
+            // 内置生成的应答是纯 ASCII（ANSI）。
+            WinntSif::local_source(product_key).to_document()
         }
     };
-    let sif_content = force_winnt_keys(&sif_raw);
-    // 同 txtsetup：WINNT.SIF 也按 ANSI 读，原是 GBK（自定义中文应答）就编回 GBK，别改成 UTF-8。
-    let sif_bytes = if sif_was_utf8 {
-        sif_content.into_bytes()
-    } else {
-        crate::encoding::utf8_to_gbk(&sif_content)
-    };
-    write_force(&format!("{bt}\\WINNT.SIF"), &sif_bytes)
+    // 同 txtsetup：WINNT.SIF 也按 ANSI 读，原是 GBK（自定义中文应答）就保持 GBK，别改成 UTF-8。
+    // 但 BOM 必须去掉（setupldr 会把 `\u{feff}[Data]` 当成未知节，强制键落空）；UTF-16 应答
+    // 文本安装阶段读不了，转成 ANSI。
+    match sif.encoding() {
+        SifEncoding::Utf8 { bom: true } => sif.set_encoding(SifEncoding::Utf8 { bom: false }),
+        SifEncoding::Utf16Le { .. } => {
+            log.push_str("自定义 winnt.sif 为 UTF-16，已转为 ANSI（文本安装阶段只认 ANSI）\n");
+            sif.set_encoding(SifEncoding::Ansi);
+        }
+        _ => {}
+    }
+    // winnt.sif 应为 DOS 换行（用户自定义文件可能是 LF）。
+    sif.use_crlf();
+    apply_mandatory(&mut sif);
+    write_force(&format!("{bt}\\WINNT.SIF"), &sif.to_bytes())
         .map_err(|e| format!("写 $WIN_NT$.~BT\\WINNT.SIF 失败: {e}"))?;
     log.push_str("已写入 $WIN_NT$.~BT\\WINNT.SIF（已强制 MsDosInitiated=1 等硬盘安装必需键）\n");
 
@@ -380,9 +384,7 @@ pub fn install_from_i386(
             bootsect.display()
         ));
     }
-    let out = new_command(&bootsect)
-        .args(["/nt52", win, "/mbr", "/force"])
-        .output()
+    let out = command::run(&bootsect, ["/nt52", win, "/mbr", "/force"])
         .map_err(|e| format!("bootsect 执行失败: {e}"))?;
     log.push_str(&gbk_to_utf8(&out.stdout));
     log.push_str(&gbk_to_utf8(&out.stderr));
@@ -398,9 +400,16 @@ pub fn install_from_i386(
 
 /// 清掉目标文件的 只读/系统/隐藏 属性（若存在）。重装/修过引导的盘上根引导文件常带这些属性，
 /// 不清会让 `std::fs::copy`/`write` 抛 os error 5（拒绝访问）。失败忽略（文件不存在或本就无属性）。
-fn clear_file_attrs(path: &str) {
+pub(crate) fn clear_file_attrs(path: &str) {
+    if Path::new(path).exists() {
+        let _ = command::run("attrib", ["-R", "-S", "-H", path]);
+    }
+}
+
+/// 给引导文件加回 +r+s+h（boot.ini 等改写后恢复原有保护）。
+pub(crate) fn set_system_file_attrs(path: &str) {
     if Path::new(path).exists() {
-        let _ = new_command("attrib").args(["-R", "-S", "-H", path]).output();
+        let _ = command::run("attrib", ["+R", "+S", "+H", path]);
     }
 }
 
@@ -463,9 +472,10 @@ fn create_dir_all_retry(path: &str) -> std::io::Result<()> {
 
 /// 从 `bin\xp\productkey.txt`（或 `bin\xp_productkey.txt`）读取产品密钥。
 ///
-/// 取第一行非注释（`#`/`;` 开头为注释）、长度足够像密钥（≥20，形如 `XXXXX-XXXXX-XXXXX-XXXXX-XXXXX`）的内容。
+/// 取第一行非注释（`#`/`;` 开头为注释）且格式合法（`XXXXX-XXXXX-XXXXX-XXXXX-XXXXX`，见
+/// [`ProductKey::parse`]）的内容；格式不对的行记警告后跳过。
 /// 没有文件或没有合法行时返回 `None`（→ winnt.sif 用 DefaultHide，仅在密钥页停顿）。
-fn read_product_key(bin_dir: &Path) -> Option<String> {
+fn read_product_key(bin_dir: &Path) -> Option<ProductKey> {
     let candidates = [
         bin_dir.join("xp").join("productkey.txt"),
         bin_dir.join("xp_productkey.txt"),
@@ -477,8 +487,9 @@ fn read_product_key(bin_dir: &Path) -> Option<String> {
                 if t.is_empty() || t.starts_with('#') || t.starts_with(';') {
                     continue;
                 }
-                if t.len() >= 20 {
-                    return Some(t.to_string());
+                match ProductKey::parse(t) {
+                    Ok(k) => return Some(k),
+                    Err(e) => log::warn!("{}: {}", p.display(), e),
                 }
             }
         }
@@ -486,28 +497,6 @@ fn read_product_key(bin_dir: &Path) -> Option<String> {
     None
 }
 
-/// 把任意换行规整为 CRLF（winnt.sif 应为 DOS 换行；用户自定义文件可能是 LF）。同时去掉行首 UTF-8 BOM
-/// ——否则带 BOM 的自定义 .sif 首行会变成 `\u{feff}[Data]`，节头识别失败，强制键被追加到被忽略的重复节。
-fn normalize_crlf(s: &str) -> String {
-    let s = s.strip_prefix('\u{feff}').unwrap_or(s);
-    let mut out = String::with_capacity(s.len() + 16);
-    for line in s.split('\n') {
-        out.push_str(line.strip_suffix('\r').unwrap_or(line));
-        out.push_str("\r\n");
-    }
-    out
-}
-
-/// 取 INI 节标题行的节名（去首尾空白、容忍行尾注释/多余内容，如 `[Data]  ; 注释`）。非节标题返回 None。
-fn ini_header_name(line: &str) -> Option<&str> {
-    let t = line.trim();
-    if !t.starts_with('[') {
-        return None;
-    }
-    let close = t.find(']')?;
-    Some(t[1..close].trim())
-}
-
 /// 用 diskpart 把指定盘符（如 `"C"`）的卷标记为「活动分区」。仅 MBR 有意义。
 fn set_volume_active(letter: &str) -> Result<String, String> {
     use std::io::Write;
@@ -520,9 +509,7 @@ fn set_volume_active(letter: &str) -> Result<String, String> {
             .map_err(|e| format!("写 diskpart 脚本失败: {e}"))?;
     }
     let tmp_str = tmp.to_string_lossy().into_owned();
-    let out = new_command("diskpart")
-        .args(["/s", tmp_str.as_str()])
-        .output()
+    let out = command::run("diskpart", ["/s", tmp_str.as_str()])
         .map_err(|e| format!("diskpart 执行失败: {e}"))?;
     let _ = std::fs::remove_file(&tmp);
     let so = gbk_to_utf8(&out.stdout);
@@ -552,134 +539,16 @@ fn diskpart_reported_failure(output: &str) -> bool {
         || lo.contains("error")
 }
 
-/// 强制写入硬盘安装必需的应答键（照搬 DSI 的 `NT5部署无人值守`）。无论用户自定义 .sif 怎么写，
-/// 都把这 5 个键设成硬盘安装能跑通的值——尤其 `MsDosInitiated=1`（缺它文本安装会找光盘失败）。
-fn force_winnt_keys(content: &str) -> String {
-    let mut s = normalize_crlf(content);
-    for (section, key, value) in [
-        ("[Data]", "MsDosInitiated", "1"),
-        ("[Data]", "Floppyless", "1"),
-        ("[Data]", "AutoPartition", "0"),
-        ("[Data]", "UnattendedInstall", "Yes"),
-        ("[Unattended]", "OemPreinstall", "Yes"),
-    ] {
-        s = set_ini_key(&s, section, key, value);
-    }
-    s
-}
-
-/// 在 INI 文本里把 `section` 节的 `key` 设为 `value`（CRLF）：键存在则替换（并去重），
-/// 节存在但缺键则在节内补，节不存在则在文末新建节再补。大小写不敏感匹配节名/键名。
-fn set_ini_key(content: &str, section: &str, key: &str, value: &str) -> String {
-    let nl = "\r\n";
-    let kv = format!("{key}={value}{nl}");
-    // section 形如 "[Data]" → 取内部名 "Data" 与节标题名比对（容忍行尾注释/BOM）。
-    let sec_inner = section.trim().trim_start_matches('[').trim_end_matches(']');
-    let mut out = String::with_capacity(content.len() + 64);
-    let mut in_target = false;
-    let mut inserted = false;
-    let mut seen_section = false;
-    for line in content.split_inclusive('\n') {
-        let t = line.trim();
-        if let Some(name) = ini_header_name(t) {
-            if in_target && !inserted {
-                out.push_str(&kv);
-                inserted = true;
-            }
-            in_target = name.eq_ignore_ascii_case(sec_inner);
-            if in_target {
-                seen_section = true;
-                inserted = false;
-            }
-            out.push_str(line);
-            continue;
-        }
-        if in_target {
-            if let Some((k, _)) = t.split_once('=') {
-                if k.trim().eq_ignore_ascii_case(key) {
-                    if !inserted {
-                        out.push_str(&kv);
-                        inserted = true;
-                    }
-                    continue; // 丢弃原键行/重复键
-                }
-            }
-        }
-        out.push_str(line);
-    }
-    if in_target && !inserted {
-        if !out.ends_with('\n') {
-            out.push_str(nl);
-        }
-        out.push_str(&kv);
-    } else if !seen_section {
-        if !out.ends_with('\n') {
-            out.push_str(nl);
-        }
-        out.push_str(section);
-        out.push_str(nl);
-        out.push_str(&kv);
-    }
-    out
-}
-
-/// 生成 winnt.sif 应答文件。
-///
-/// - 有 `product_key`：`UnattendMode=FullUnattended` 全自动（文本+图形全程无停顿）。
-/// - 无密钥：`UnattendMode=DefaultHide`（隐藏已答页，仅在「产品密钥」页停一下，其余无人值守）。
-///
-/// 统一项：跳过 EULA/区域/欢迎；`DriverSigningPolicy=Ignore`（不拦未签名/注入的存储驱动）；
-/// 管理员空密码 + 首次自动登录；不分区/不格式化（沿用已格式化的目标盘）；目标 `\WINDOWS`。
-/// 出于安全，文本阶段仍由用户确认安装分区（`AutoPartition=0`），避免自动选错盘抹掉数据。
-fn winnt_sif(product_key: Option<&str>) -> String {
-    let (mode, key_line) = match product_key {
-        Some(k) => ("FullUnattended", format!("ProductKey=\"{k}\"\r\n")),
-        None => ("DefaultHide", String::new()),
-    };
-    format!(
-        "[Data]\r\n\
-AutoPartition=0\r\n\
-MsDosInitiated=1\r\n\
-UnattendedInstall=Yes\r\n\
-Floppyless=1\r\n\
-\r\n\
-[Unattended]\r\n\
-UnattendMode={mode}\r\n\
-UnattendSwitch=Yes\r\n\
-OemPreinstall=Yes\r\n\
-OemSkipEula=Yes\r\n\
-TargetPath=\\WINDOWS\r\n\
-FileSystem=LeaveAlone\r\n\
-WaitForReboot=No\r\n\
-DriverSigningPolicy=Ignore\r\n\
-\r\n\
-[GuiUnattended]\r\n\
-AdminPassword=*\r\n\
-EncryptedAdminPassword=No\r\n\
-AutoLogon=Yes\r\n\
-AutoLogonCount=1\r\n\
-OEMSkipRegional=1\r\n\
-OemSkipWelcome=1\r\n\
-TimeZone=210\r\n\
-\r\n\
-[UserData]\r\n\
-FullName=\"User\"\r\n\
-OrgName=\"\"\r\n\
-ComputerName=*\r\n\
-{key_line}\
-\r\n\
-[Identification]\r\n\
-JoinWorkgroup=WORKGROUP\r\n\
-\r\n\
-[Networking]\r\n\
-InstallDefaultComponents=Yes\r\n"
-    )
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    /// 内置 winnt.sif 应答文本（见 [`WinntSif::local_source`]）。
+    fn winnt_sif(product_key: Option<&str>) -> String {
+        let key = product_key.map(|k| ProductKey::parse(k).expect("测试用密钥应合法"));
+        WinntSif::local_source(key).render()
+    }
+
     #[test]
     fn diskpart_reported_failure_negative_detection() {
         // 成功串（中/英）→ 不算失败（关键：成功串里没有「无法/错误/cannot/is not/error」）
@@ -710,16 +579,18 @@ mod tests {
 
     #[test]
     fn winnt_sif_with_key_is_fullunattended() {
-        let s = winnt_sif(Some("AAAAA-BBBBB-CCCCC-DDDDD-EEEEE"));
+        let s = winnt_sif(Some("BCDFG-HJKMP-QRTVW-XY234-6789B"));
         assert!(s.contains("UnattendMode=FullUnattended"));
-        assert!(s.contains("ProductKey=\"AAAAA-BBBBB-CCCCC-DDDDD-EEEEE\""));
+        assert!(s.contains("ProductKey=\"BCDFG-HJKMP-QRTVW-XY234-6789B\""));
     }
 
     #[test]
     fn force_keys_overrides_msdosinitiated() {
         // 用户自定义 .sif 里 MsDosInitiated="0" → 必须被强制改成 1（照搬 DSI）
         let input = ";c\r\n[Data]\r\n    AutoPartition=1\r\n    MsDosInitiated=\"0\"\r\n    UnattendedInstall=\"Yes\"\r\n\r\n[Unattended]\r\n    OemPreinstall=No\r\n    TargetPath=\\WINDOWS\r\n";
-        let out = force_winnt_keys(input);
+        let mut sif = SifDocument::parse(input);
+        apply_mandatory(&mut sif);
+        let out = sif.to_text();
         assert!(out.contains("MsDosInitiated=1"));
         assert!(!out.contains("MsDosInitiated=\"0\""));
         assert!(out.contains("AutoPartition=0") && !out.contains("AutoPartition=1"));
@@ -731,8 +602,9 @@ mod tests {
     #[test]
     fn force_keys_handles_bom_and_commented_header() {
         // 带 UTF-8 BOM 的自定义 .sif + 节头带行尾注释：必须仍能命中 [Data]，强制键改对，不产生重复节
-        let input = "\u{feff}[Data]  ; partition data\r\nMsDosInitiated=\"0\"\r\n";
-        let out = force_winnt_keys(input);
+        let mut sif = SifDocument::parse_bytes(b"\xEF\xBB\xBF[Data]  ; partition data\r\nMsDosInitiated=\"0\"\r\n");
+        apply_mandatory(&mut sif);
+        let out = sif.to_text();
         assert!(out.contains("MsDosInitiated=1"));
         assert!(!out.contains("MsDosInitiated=\"0\""));
         // 不能追加出第二个 [Data]（否则 XP 只读第一个，强制键落到被忽略的尾节）
@@ -740,23 +612,29 @@ mod tests {
     }
 
     #[test]
-    fn ini_header_name_tolerates_comment() {
-        assert_eq!(ini_header_name("[Data]"), Some("Data"));
-        assert_eq!(ini_header_name("  [Unattended]  ; x"), Some("Unattended"));
-        assert_eq!(ini_header_name("Key=Val"), None);
+    fn force_keys_creates_missing_section() {
+        let mut sif = SifDocument::parse("[Foo]\r\nx=1\r\n");
+        apply_mandatory(&mut sif);
+        assert!(sif.to_text().contains("[Data]\r\nMsDosInitiated=1\r\n"));
+        assert!(sif.to_text().contains("[Unattended]\r\nOemPreinstall=Yes\r\n"));
     }
 
     #[test]
-    fn set_ini_key_creates_missing_section() {
-        let out = set_ini_key("[Foo]\r\nx=1\r\n", "[Data]", "MsDosInitiated", "1");
-        assert!(out.contains("[Data]\r\nMsDosInitiated=1\r\n"));
+    fn force_keys_dedups_existing_key() {
+        let mut sif = SifDocument::parse("[Data]\r\nFloppyless=0\r\nFloppyless=0\r\n");
+        apply_mandatory(&mut sif);
+        assert_eq!(sif.to_text().matches("Floppyless=").count(), 1);
+        assert!(sif.to_text().contains("Floppyless=1"));
     }
 
     #[test]
-    fn set_ini_key_dedups_existing_key() {
-        let out = set_ini_key("[Data]\r\nk=a\r\nk=b\r\n", "[Data]", "k", "z");
-        assert_eq!(out.matches("k=").count(), 1);
-        assert!(out.contains("k=z"));
+    fn force_keys_keeps_gbk_comments_byte_identical() {
+        let mut raw = b"; ".to_vec();
+        raw.extend_from_slice(&crate::encoding::utf8_to_gbk("中文应答"));
+        raw.extend_from_slice(b"\r\n[Data]\r\nMsDosInitiated=1\r\nFloppyless=1\r\nAutoPartition=0\r\nUnattendedInstall=Yes\r\n[Unattended]\r\nOemPreinstall=Yes\r\n");
+        let mut sif = SifDocument::parse_bytes(&raw);
+        apply_mandatory(&mut sif);
+        assert_eq!(sif.to_bytes(), raw);
     }
 
     #[test]
@@ -770,10 +648,10 @@ mod tests {
     }
 
     #[test]
-    fn normalize_crlf_converts_lf_and_keeps_crlf() {
-        assert_eq!(normalize_crlf("a\nb"), "a\r\nb\r\n");
-        assert_eq!(normalize_crlf("a\r\nb\r\n"), "a\r\nb\r\n\r\n");
-        assert_eq!(normalize_crlf("[Data]\nAutoPartition=0"), "[Data]\r\nAutoPartition=0\r\n");
+    fn lf_sif_is_converted_to_crlf() {
+        let mut sif = SifDocument::parse_bytes(b"[Data]\nAutoPartition=0");
+        sif.use_crlf();
+        assert_eq!(sif.to_text(), "[Data]\r\nAutoPartition=0\r\n");
     }
 
     #[test]
@@ -783,6 +661,8 @@ mod tests {
         assert!(s.contains("MsDosInitiated=1"));
         assert!(s.contains("UnattendSwitch=Yes"));
         assert!(s.contains("FileSystem=LeaveAlone"));
-        assert!(force_winnt_keys(&s).contains("MsDosInitiated=1"));
+        let mut sif = SifDocument::parse(&s);
+        apply_mandatory(&mut sif);
+        assert!(sif.to_text().contains("MsDosInitiated=1"));
     }
 }
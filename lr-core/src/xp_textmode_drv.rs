@@ -10,6 +10,9 @@
 
 use std::path::{Path, PathBuf};
 
+use crate::inf::{AddService, InfFile};
+use crate::sif::SifDocument;
+
 /// 解析出的一个文本期存储驱动。
 #[derive(Debug, Clone)]
 pub struct TxtmodeDriver {
@@ -74,8 +77,7 @@ fn collect_infs(dir: &Path, out: &mut Vec<TxtmodeDriver>, depth: usize) {
 /// 解析单个 `.inf` + 收集同目录下的 `.sys`。信息不全返回 `None`。
 pub fn parse_driver_inf(inf: &Path) -> Option<TxtmodeDriver> {
     let raw = std::fs::read(inf).ok()?;
-    let text = decode_inf(&raw);
-    let parsed = parse_inf_text(&text)?;
+    let parsed = parse_inf(&InfFile::parse_bytes(&raw))?;
     let dir = inf.parent()?;
     let mut sys_files = Vec::new();
     for e in std::fs::read_dir(dir).ok()?.flatten() {
@@ -101,14 +103,14 @@ pub fn parse_driver_inf(inf: &Path) -> Option<TxtmodeDriver> {
 }
 
 /// 把驱动拷进各目标目录（`copy_dirs`，如源 `$WIN_NT$.~LS\I386` 与引导 `$WIN_NT$.~BT`）
-/// 并合并进 `txtsetup`。文本期 miniport 既要在源里（GUI 阶段取文件）也要在 `$WIN_NT$.~BT`
-/// 里（文本启动阶段加载认盘），故 `.sys` 拷进所有 `copy_dirs`。
-/// 返回（合并后的 txtsetup.sif 内容, 日志）。
-pub fn integrate(txtsetup: &str, drivers: &[TxtmodeDriver], copy_dirs: &[&Path]) -> (String, String) {
+/// 并原位合并进 `txtsetup`（集成行插在各节最前，其余行逐字节不动、编码不变）。文本期 miniport
+/// 既要在源里（GUI 阶段取文件）也要在 `$WIN_NT$.~BT` 里（文本启动阶段加载认盘），故 `.sys`
+/// 拷进所有 `copy_dirs`。返回日志。
+pub fn integrate(txtsetup: &mut SifDocument, drivers: &[TxtmodeDriver], copy_dirs: &[&Path]) -> String {
     let mut log = String::new();
     if drivers.is_empty() {
         log.push_str("[TXTDRV] 未发现可集成的文本期存储驱动（跳过）\n");
-        return (txtsetup.to_string(), log);
+        return log;
     }
 
     let mut source_disks_files: Vec<String> = Vec::new();
@@ -145,7 +147,7 @@ pub fn integrate(txtsetup: &str, drivers: &[TxtmodeDriver], copy_dirs: &[&Path])
             // [SourceDisksFiles]：原 txtsetup 没有该键、且本次还没为同名文件加过行时才加
             //（既避免与原版 storport.sys 等重复键，也避免多个驱动共享依赖 .sys 时重复键）。
             if sdf_seen.insert(name.to_ascii_lowercase())
-                && !section_has_key(txtsetup, "[SourceDisksFiles]", &name)
+                && !txtsetup.has_key("SourceDisksFiles", &name)
             {
                 source_disks_files.push(format!("{} = 1,,,,,,4_,4,1,,,1,4", name));
             }
@@ -164,129 +166,51 @@ pub fn integrate(txtsetup: &str, drivers: &[TxtmodeDriver], copy_dirs: &[&Path])
         ));
     }
 
-    let mut out = txtsetup.to_string();
-    out = append_to_section(&out, "[SourceDisksFiles]", &source_disks_files);
-    out = append_to_section(&out, "[SCSI.Load]", &scsi_load);
-    out = append_to_section(&out, "[SCSI]", &scsi);
-    out = append_to_section(&out, "[HardwareIdsDatabase]", &hwid_db);
-    (out, log)
+    txtsetup.prepend_lines("SourceDisksFiles", &source_disks_files);
+    txtsetup.prepend_lines("SCSI.Load", &scsi_load);
+    txtsetup.prepend_lines("SCSI", &scsi);
+    txtsetup.prepend_lines("HardwareIdsDatabase", &hwid_db);
+    log
 }
 
 // ───────────────────────── 内部解析/合并 ─────────────────────────
 
-fn decode_inf(raw: &[u8]) -> String {
-    if raw.len() >= 2 && raw[0] == 0xFF && raw[1] == 0xFE {
-        let u16s: Vec<u16> = raw[2..]
-            .chunks_exact(2)
-            .map(|c| u16::from_le_bytes([c[0], c[1]]))
+/// 取驱动的服务名、miniport `.sys` 与 `PCI\` 硬件 ID（型号行的硬件 ID 与兼容 ID）。
+fn parse_inf(inf: &InfFile) -> Option<ParsedInf> {
+    let models = inf.models(None);
+
+    // 1) 服务：优先型号安装节对应的 .Services 节；没有型号时回退扫所有 .Services 节。
+    let mut services: Vec<AddService> = models
+        .iter()
+        .filter_map(|m| inf.ddinstall(&m.install_section, None))
+        .flat_map(|dd| dd.services)
+        .collect();
+    if services.is_empty() {
+        let names: Vec<&str> = inf
+            .section_names()
+            .filter(|n| n.to_ascii_lowercase().ends_with(".services"))
             .collect();
-        String::from_utf16_lossy(&u16s)
-    } else {
-        match std::str::from_utf8(raw) {
-            Ok(s) => s.to_string(),
-            Err(_) => crate::encoding::gbk_to_utf8(raw),
-        }
-    }
-}
-
-/// 解析 INF 文本为有序的 (节名, 行集合)。去注释(`;`)、去空行。
-fn parse_sections(text: &str) -> Vec<(String, Vec<String>)> {
-    let mut out: Vec<(String, Vec<String>)> = Vec::new();
-    let mut cur: Option<usize> = None;
-    for raw in text.lines() {
-        let line = match raw.find(';') {
-            Some(i) => &raw[..i],
-            None => raw,
-        };
-        let t = line.trim();
-        if t.is_empty() {
-            continue;
-        }
-        if t.starts_with('[') && t.ends_with(']') {
-            let name = t[1..t.len() - 1].trim().to_string();
-            out.push((name, Vec::new()));
-            cur = Some(out.len() - 1);
-        } else if let Some(i) = cur {
-            out[i].1.push(t.to_string());
-        }
-    }
-    out
-}
-
-fn parse_inf_text(text: &str) -> Option<ParsedInf> {
-    let sections = parse_sections(text);
-    let get = |name: &str| -> Vec<&str> {
-        sections
+        services = names
             .iter()
-            .filter(|(n, _)| n.eq_ignore_ascii_case(name))
-            .flat_map(|(_, ls)| ls.iter().map(|s| s.as_str()))
-            .collect()
-    };
-
-    // 1) 服务名 + 服务安装节：扫所有以 ".Services" 结尾的节里的 AddService。
-    let mut service: Option<String> = None;
-    let mut svc_inst: Option<String> = None;
-    for (name, lines) in &sections {
-        if name.to_ascii_lowercase().ends_with(".services") {
-            for l in lines {
-                if let Some(rhs) = strip_key_ci(l, "addservice") {
-                    let fields: Vec<&str> = rhs.split(',').map(|s| s.trim()).collect();
-                    if !fields.is_empty() && !fields[0].is_empty() {
-                        service = Some(fields[0].to_string());
-                        if fields.len() >= 3 && !fields[2].is_empty() {
-                            svc_inst = Some(fields[2].to_string());
-                        }
-                        break;
-                    }
-                }
-            }
-        }
-        if service.is_some() {
-            break;
-        }
+            .filter_map(|n| inf.ddinstall(&n[..n.len() - ".services".len()], None))
+            .flat_map(|dd| dd.services)
+            .collect();
     }
-    let service = service?;
+    let svc = services.into_iter().next()?;
 
     // 2) miniport .sys：服务安装节里的 ServiceBinary = %12%\xxx.sys；缺则回退 <service>.sys。
-    let mut miniport_sys = format!("{}.sys", service);
-    if let Some(si) = &svc_inst {
-        for l in get(si) {
-            if let Some(rhs) = strip_key_ci(l, "servicebinary") {
-                if let Some(name) = rhs.rsplit(['\\', '/']).next() {
-                    let name = name.trim();
-                    if name.to_ascii_lowercase().ends_with(".sys") {
-                        miniport_sys = name.to_string();
-                    }
-                }
-            }
-        }
-    }
+    let miniport_sys = inf
+        .service_binary(&svc)
+        .filter(|n| n.to_ascii_lowercase().ends_with(".sys"))
+        .unwrap_or_else(|| format!("{}.sys", svc.name));
 
-    // 3) 硬件 ID：据 [Manufacturer] 找型号节，从型号行 RHS 提取 PCI\ 标识。
+    // 3) 硬件 ID：型号行 RHS 里的 PCI\ 标识。
     let mut hwids: Vec<String> = Vec::new();
-    for l in get("Manufacturer") {
-        if let Some((_, rhs)) = l.split_once('=') {
-            let parts: Vec<&str> = rhs.split(',').map(|s| s.trim()).collect();
-            if parts.is_empty() || parts[0].is_empty() {
-                continue;
-            }
-            let base = parts[0];
-            let mut model_secs = vec![base.to_string()];
-            for ext in &parts[1..] {
-                if !ext.is_empty() {
-                    model_secs.push(format!("{}.{}", base, ext));
-                }
-            }
-            for ms in &model_secs {
-                for ml in get(ms) {
-                    if let Some((_, mrhs)) = ml.split_once('=') {
-                        for h in extract_hwids(mrhs) {
-                            if !hwids.iter().any(|x| x.eq_ignore_ascii_case(&h)) {
-                                hwids.push(h);
-                            }
-                        }
-                    }
-                }
+    for m in &models {
+        for h in std::iter::once(&m.hardware_id).chain(&m.compatible_ids) {
+            let is_pci = h.get(..4).is_some_and(|p| p.eq_ignore_ascii_case("pci\\"));
+            if is_pci && !hwids.iter().any(|x| x.eq_ignore_ascii_case(h)) {
+                hwids.push(h.clone());
             }
         }
     }
@@ -295,100 +219,20 @@ fn parse_inf_text(text: &str) -> Option<ParsedInf> {
     }
 
     Some(ParsedInf {
-        service,
+        service: svc.name,
         miniport_sys,
         hwids,
     })
 }
 
-/// 行形如 `Key = Value` 且 Key（去空白、忽略大小写）等于 `key` 时，返回去空白的 Value。
-fn strip_key_ci<'a>(line: &'a str, key: &str) -> Option<&'a str> {
-    let (k, v) = line.split_once('=')?;
-    if k.trim().eq_ignore_ascii_case(key) {
-        Some(v.trim())
-    } else {
-        None
-    }
-}
-
-/// 从一段文本里提取所有 `PCI\....` 形式的硬件 ID（大小写不敏感地定位前缀）。
-fn extract_hwids(s: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    let bytes = s.as_bytes();
-    let lower = s.to_ascii_lowercase();
-    let mut i = 0usize;
-    while let Some(rel) = lower[i..].find("pci\\") {
-        let start = i + rel;
-        let mut end = start + 4;
-        while end < bytes.len() {
-            let c = bytes[end] as char;
-            if c.is_ascii_alphanumeric() || c == '_' || c == '&' || c == '.' {
-                end += 1;
-            } else {
-                break;
-            }
-        }
-        out.push(s[start..end].to_string());
-        i = end.max(start + 4);
-    }
-    out
-}
-
-/// 某 INI 节内是否已有键 `key`（大小写不敏感）。
-fn section_has_key(content: &str, section: &str, key: &str) -> bool {
-    let mut in_sec = false;
-    for line in content.lines() {
-        let t = line.trim();
-        if t.starts_with('[') && t.ends_with(']') {
-            in_sec = t.eq_ignore_ascii_case(section);
-            continue;
-        }
-        if in_sec {
-            if let Some((k, _)) = t.split_once('=') {
-                if k.trim().eq_ignore_ascii_case(key) {
-                    return true;
-                }
-            }
-        }
-    }
-    false
-}
-
-/// 在 `[section]` 节标题行后追加 `lines`（CRLF）。节不存在则在文末新建。
-fn append_to_section(content: &str, section: &str, lines: &[String]) -> String {
-    if lines.is_empty() {
-        return content.to_string();
-    }
-    let mut out = String::with_capacity(content.len() + 256);
-    let mut inserted = false;
-    for line in content.split_inclusive('\n') {
-        out.push_str(line);
-        if !inserted && line.trim().eq_ignore_ascii_case(section) {
-            for l in lines {
-                out.push_str(l);
-                out.push_str("\r\n");
-            }
-            inserted = true;
-        }
-    }
-    if !inserted {
-        if !out.is_empty() && !out.ends_with('\n') {
-            out.push_str("\r\n");
-        }
-        out.push_str(section);
-        out.push_str("\r\n");
-        for l in lines {
-            out.push_str(l);
-            out.push_str("\r\n");
-        }
-    }
-    out
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn parse_inf_text(text: &str) -> Option<ParsedInf> {
+        parse_inf(&InfFile::parse(text))
+    }
+
     const GENAHCI_INF: &str = "\
 [Version]
 Signature=\"$Windows NT$\"
@@ -438,34 +282,83 @@ ServiceBinary  = %12%\\stornvme.sys
     }
 
     #[test]
-    fn extract_hwids_from_rhs() {
-        assert_eq!(extract_hwids("genahci, PCI\\CC_010601"), vec!["PCI\\CC_010601"]);
-        assert_eq!(
-            extract_hwids("x, PCI\\VEN_8086&DEV_2829&CC_0106"),
-            vec!["PCI\\VEN_8086&DEV_2829&CC_0106"]
-        );
-        assert!(extract_hwids("no ids here").is_empty());
+    fn hwids_from_model_rhs() {
+        let inf = "[Manufacturer]\nM = Models\n[Models]\n\
+                   a = x, PCI\\VEN_8086&DEV_2829&CC_0106, PCI\\CC_0106, ACPI\\PNP0A03\n\
+                   b = x, pci\\cc_0106\n\
+                   [x.Services]\nAddService = iastor, 2, S\n";
+        let p = parse_inf_text(inf).expect("should parse");
+        assert_eq!(p.hwids, vec!["PCI\\VEN_8086&DEV_2829&CC_0106", "PCI\\CC_0106"]);
+        assert_eq!(p.miniport_sys, "iastor.sys");
     }
 
     #[test]
-    fn append_inserts_after_existing_header() {
-        let ts = "[SCSI]\r\natapi = \"IDE\"\r\n\r\n[SCSI.Load]\r\natapi = atapi.sys,4\r\n";
-        let out = append_to_section(ts, "[SCSI.Load]", &["genahci = genahci.sys,4".to_string()]);
+    fn integrate_inserts_after_existing_header() {
+        let mut doc = SifDocument::parse(
+            "[SCSI]\r\natapi = \"IDE\"\r\n\r\n[SCSI.Load]\r\natapi = atapi.sys,4\r\n",
+        );
+        let drv = TxtmodeDriver {
+            service: "genahci".into(),
+            miniport_sys: "genahci.sys".into(),
+            desc: "genahci (LetRecovery textmode)".into(),
+            hwids: vec![],
+            sys_files: vec![],
+        };
+        integrate(&mut doc, &[drv], &[]);
+        let out = doc.to_text();
         assert!(out.contains("[SCSI.Load]\r\ngenahci = genahci.sys,4\r\natapi = atapi.sys,4"));
+        // 原有行原样保留在集成行之后
+        assert!(out.starts_with(
+            "[SCSI]\r\ngenahci = \"genahci (LetRecovery textmode)\"\r\natapi = \"IDE\"\r\n\r\n"
+        ));
+    }
+
+    #[test]
+    fn integrate_creates_missing_section() {
+        let mut doc = SifDocument::parse("[Foo]\r\nx=1\r\n");
+        let drv = TxtmodeDriver {
+            service: "b".into(),
+            miniport_sys: "b.sys".into(),
+            desc: "b".into(),
+            hwids: vec!["a".into()],
+            sys_files: vec![],
+        };
+        integrate(&mut doc, &[drv], &[]);
+        assert!(doc.to_text().contains("[HardwareIdsDatabase]\r\na = \"b\"\r\n"));
     }
 
     #[test]
-    fn append_creates_missing_section() {
-        let out = append_to_section("[Foo]\r\nx=1\r\n", "[HardwareIdsDatabase]", &["a = \"b\"".to_string()]);
-        assert!(out.contains("[HardwareIdsDatabase]\r\na = \"b\"\r\n"));
+    fn integrate_skips_existing_source_disks_file() {
+        let mut doc = SifDocument::parse("[SourceDisksFiles]\r\nSTORPORT.SYS = 1,,,\r\n");
+        let drv = TxtmodeDriver {
+            service: "genahci".into(),
+            miniport_sys: "genahci.sys".into(),
+            desc: "d".into(),
+            hwids: vec![],
+            sys_files: vec![PathBuf::from("/nonexistent/storport.sys")],
+        };
+        integrate(&mut doc, &[drv], &[]);
+        assert_eq!(doc.entries("SourceDisksFiles").len(), 1);
     }
 
     #[test]
-    fn section_has_key_detects_dup() {
-        let ts = "[SourceDisksFiles]\r\nstorport.sys = 1,,,\r\n";
-        assert!(section_has_key(ts, "[SourceDisksFiles]", "storport.sys"));
-        assert!(section_has_key(ts, "[SourceDisksFiles]", "STORPORT.SYS"));
-        assert!(!section_has_key(ts, "[SourceDisksFiles]", "genahci.sys"));
+    fn integrate_preserves_gbk_bytes() {
+        // 中文版 txtsetup.sif（GBK）：集成后未改动的中文行必须逐字节不变，不能被改写成 UTF-8
+        let mut raw = b"[SCSI]\r\natapi = \"".to_vec();
+        raw.extend_from_slice(&crate::encoding::utf8_to_gbk("标准双通道 PCI IDE 控制器"));
+        raw.extend_from_slice(b"\"\r\n[SCSI.Load]\r\n");
+        let mut doc = SifDocument::parse_bytes(&raw);
+        let drv = TxtmodeDriver {
+            service: "genahci".into(),
+            miniport_sys: "genahci.sys".into(),
+            desc: "d".into(),
+            hwids: vec![],
+            sys_files: vec![],
+        };
+        integrate(&mut doc, &[drv], &[]);
+        let out = doc.to_bytes();
+        let gbk_line = &raw[b"[SCSI]\r\n".len()..raw.len() - b"[SCSI.Load]\r\n".len()];
+        assert!(out.windows(gbk_line.len()).any(|w| w == gbk_line));
     }
 
     #[test]
@@ -478,7 +371,9 @@ ServiceBinary  = %12%\\stornvme.sys
             hwids: vec!["PCI\\CC_010601".into()],
             sys_files: vec![], // 无文件可拷（测纯合并；拷贝在真实路径做）
         };
-        let (out, _log) = integrate(ts, &[drv], &[Path::new("/nonexistent-source")]);
+        let mut doc = SifDocument::parse(ts);
+        integrate(&mut doc, &[drv], &[Path::new("/nonexistent-source")]);
+        let out = doc.to_text();
         assert!(out.contains("[SCSI.Load]\r\ngenahci = genahci.sys,4"));
         assert!(out.contains("genahci = \"genahci (LetRecovery textmode)\""));
         assert!(out.contains("PCI\\CC_010601 = \"genahci\""));
@@ -503,7 +398,9 @@ ServiceBinary  = %12%\\stornvme.sys
         };
         let ahci = mk("genahci", &["genahci.sys", "ntoskrn8.sys", "storport.sys"]);
         let nvme = mk("stornvme", &["stornvme.sys", "ntoskrn8.sys", "storport.sys"]);
-        let (out, _log) = integrate(ts, &[ahci, nvme], &[Path::new("/nonexistent-dst")]);
+        let mut doc = SifDocument::parse(ts);
+        integrate(&mut doc, &[ahci, nvme], &[Path::new("/nonexistent-dst")]);
+        let out = doc.to_text();
 
         let count_in_sdf = |key: &str| -> usize {
             let mut in_sec = false;
@@ -5,11 +5,16 @@ use std::thread;
 
 use eframe::egui;
 
-use crate::core::config::{ConfigFileManager, OperationType};
+use crate::core::config::{ConfigFileManager, InstallConfig, OperationType};
 use crate::core::dism::DismProgress;
 use crate::tr;
 use crate::ui::progress::{InstallStep, BackupStep, ProgressState, ProgressUI};
 use crate::utils::reboot_pe;
+use lr_core::install_journal::{FileFingerprint, InstallJournal, InstallPhase, ResumePoint};
+use lr_core::plan::{
+    plan_backup, plan_install, BackupPlanInput, CaptureFormat, DriverSource, ExecutionPlan,
+    InstallPlanInput,
+};
 
 /// 递归查找目录中的所有 CAB 文件
 fn find_cab_files_in_directory(dir: &str) -> Vec<PathBuf> {
@@ -53,6 +58,16 @@ pub enum WorkerMessage {
     Failed(String),
 }
 
+/// 上次被中断的安装（等待用户选择续做或重新开始）
+struct ResumeOffer {
+    journal: InstallJournal,
+    point: ResumePoint,
+    shown_at: std::time::Instant,
+}
+
+/// 无人选择时自动续做的等待秒数
+const RESUME_AUTO_SECS: u64 = 30;
+
 pub struct App {
     /// 进度状态
     progress_state: Arc<Mutex<ProgressState>>,
@@ -62,6 +77,10 @@ pub struct App {
     started: bool,
     /// 操作类型
     operation_type: Option<OperationType>,
+    /// 上次被中断的安装（询问期间不启动工作线程）
+    resume_offer: Option<ResumeOffer>,
+    /// 安装续做起点
+    resume_from: Option<InstallPhase>,
 }
 
 impl App {
@@ -79,11 +98,25 @@ impl App {
             None => ProgressState::new_install(),
         }));
 
+        // 上次安装被中断（PE 重启 / 断电）时先询问是否续做
+        let resume_offer = match operation_type {
+            Some(OperationType::Install) => {
+                detect_interrupted_install().map(|(journal, point)| ResumeOffer {
+                    journal,
+                    point,
+                    shown_at: std::time::Instant::now(),
+                })
+            }
+            _ => None,
+        };
+
         Self {
             progress_state,
             message_rx: None,
             started: false,
             operation_type,
+            resume_offer,
+            resume_from: None,
         }
     }
 
@@ -152,11 +185,12 @@ impl App {
         self.message_rx = Some(rx);
 
         let operation_type = self.operation_type;
+        let resume_from = self.resume_from;
 
         thread::spawn(move || {
             match operation_type {
                 Some(OperationType::Install) => {
-                    execute_install_workflow(tx);
+                    execute_install_workflow(tx, resume_from);
                 }
                 Some(OperationType::Backup) => {
                     execute_backup_workflow(tx);
@@ -171,6 +205,63 @@ impl App {
         });
     }
 
+    /// 询问是否续做上次中断的安装；超时无人选择则自动续做
+    fn show_resume_prompt(&mut self, ui: &mut egui::Ui) {
+        let Some(offer) = self.resume_offer.as_ref() else {
+            return;
+        };
+        let elapsed = offer.shown_at.elapsed().as_secs();
+        let remaining = RESUME_AUTO_SECS.saturating_sub(elapsed);
+        let mut choice: Option<bool> = None;
+
+        ui.vertical_centered(|ui| {
+            ui.add_space(40.0);
+            ui.heading(tr!("检测到上次安装被中断"));
+            ui.add_space(20.0);
+        });
+        ui.label(tr!(
+            "上次安装停在「{}」步骤。",
+            tr!(offer.point.interrupted.label())
+        ));
+        ui.label(tr!(
+            "可以从「{}」继续，之前已完成的步骤不会重做。",
+            tr!(offer.point.from.label())
+        ));
+        ui.add_space(10.0);
+        for phase in offer.journal.plan.iter().copied() {
+            let (mark, color) = if phase < offer.point.from {
+                ("✔", egui::Color32::from_rgb(0, 180, 0))
+            } else {
+                ("○", egui::Color32::GRAY)
+            };
+            ui.colored_label(color, format!("{} {}", mark, tr!(phase.label())));
+        }
+        ui.add_space(20.0);
+        ui.horizontal(|ui| {
+            if ui.button(tr!("继续安装")).clicked() {
+                choice = Some(true);
+            }
+            if ui.button(tr!("重新开始")).clicked() {
+                choice = Some(false);
+            }
+        });
+        ui.add_space(10.0);
+        ui.label(tr!("{} 秒后自动继续安装", remaining));
+
+        if remaining == 0 {
+            choice = Some(true);
+        }
+        if let Some(resume) = choice {
+            let from = offer.point.from;
+            log::info!(
+                "[PE安装] 用户选择{}",
+                if resume { "续做上次中断的安装" } else { "重新开始安装" }
+            );
+            self.resume_from = resume.then_some(from);
+            self.resume_offer = None;
+        }
+    }
+
     /// 处理工作线程消息
     fn process_messages(&mut self) {
         if let Some(ref rx) = self.message_rx {
@@ -204,6 +295,15 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &egui::Context, _frame: &mut eframe::Frame) {
+        // 询问是否续做上次中断的安装（选择之前不启动工作线程）
+        if self.resume_offer.is_some() {
+            egui::CentralPanel::default().show(ctx, |ui| {
+                self.show_resume_prompt(ui);
+            });
+            ctx.request_repaint();
+            return;
+        }
+
         // 启动工作线程
         if !self.started {
             self.start_worker();
@@ -224,22 +324,212 @@ impl eframe::App for App {
     }
 }
 
-/// 执行安装工作流
-fn execute_install_workflow(tx: Sender<WorkerMessage>) {
-    use crate::core::bcdedit::BootManager;
-    use crate::core::dism::Dism;
+/// 安装流程各步骤共用的上下文
+struct InstallContext {
+    tx: Sender<WorkerMessage>,
+    config: InstallConfig,
+    data_partition: String,
+    data_dir: String,
+    target_partition: String,
+    image_path: String,
+    apply_dir: String,
+    journal: InstallJournal,
+}
+
+impl InstallContext {
+    /// 立即把安装日志写盘（写不出只记警告，不影响安装本身）
+    fn save_journal(&mut self) {
+        if let Err(e) = self.journal.save(Path::new(&self.data_dir)) {
+            log::warn!("[PE安装] 写入安装日志失败: {}", e);
+        }
+    }
+}
+
+/// 定位本次安装：数据分区、安装配置与完整镜像路径
+fn locate_install() -> Result<(String, InstallConfig, String), String> {
+    let data_partition =
+        ConfigFileManager::find_data_partition().ok_or_else(|| tr!("未找到安装配置文件"))?;
+    let config = ConfigFileManager::read_install_config(&data_partition)
+        .map_err(|e| tr!("读取配置失败: {}", e))?;
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let image_path = format!("{}\\{}", data_dir, config.image_path);
+    Ok((data_partition, config, image_path))
+}
+
+/// 定位目标分区。
+///
+/// 配置里有磁盘/分区标识时，按标识在所有卷中找回目标（盘符在 PE 里会变），
+/// 找不到或不唯一就拒绝，以免格式化错误的分区；旧版本写的配置没有标识，
+/// 沿用安装标记 → 安装日志 → 配置里的盘符。
+fn locate_target(config: &InstallConfig, journal_target: Option<&str>) -> Result<String, String> {
+    let identity = &config.target_identity;
+    if identity.is_known() {
+        return match lr_core::target_identity::resolve_volume(identity) {
+            Ok(letter) => {
+                log::info!("[PE安装] 按标识定位目标分区: {} ({})", letter, identity);
+                Ok(letter)
+            }
+            Err(e) => {
+                log::error!("[PE安装] 按标识定位目标分区失败: {} ({})", e, identity);
+                Err(tr!(
+                    "无法确定目标分区：{}。为避免格式化错误的分区，安装已停止。\n记录的目标：{}（原盘符 {}）",
+                    e,
+                    identity,
+                    config.target_partition
+                ))
+            }
+        };
+    }
+    log::warn!("[PE安装] 安装配置中没有目标分区标识，按安装标记/盘符定位");
+    Ok(ConfigFileManager::find_install_marker_partition()
+        .or_else(|| journal_target.map(str::to_string))
+        .unwrap_or_else(|| config.target_partition.clone()))
+}
+
+/// 本次安装要执行的步骤（未启用的步骤不在计划里）
+fn install_plan(config: &InstallConfig) -> Vec<InstallPhase> {
+    InstallPhase::planned(!config.is_gho, config.run_diskpart_scripts)
+}
+
+/// 按数据分区上的安装配置生成执行计划（只读检查，不执行任何操作）
+pub fn build_install_plan() -> Result<ExecutionPlan, String> {
     use crate::core::disk::DiskManager;
-    use crate::core::ghost::Ghost;
-    use crate::ui::advanced_options::apply_advanced_options;
+    use crate::utils::path;
+
+    let (data_partition, config, image_path) = locate_install()?;
+    let target_partition = locate_target(&config, None)?;
+    let repair_script = path::get_bin_dir().join("repair_boot.txt");
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let input = InstallPlanInput {
+        target_partition,
+        drivers: if config.should_import_drivers() {
+            DriverSource::Dir(Path::new(&data_dir).join("drivers"))
+        } else {
+            DriverSource::None
+        },
+        data_dir,
+        image_path,
+        volume_index: config.volume_index,
+        is_gho: config.is_gho,
+        is_xp: config.is_xp,
+        uefi: Some(DiskManager::detect_uefi_mode()),
+        volume_label: config.volume_label.clone(),
+        run_diskpart_scripts: config.run_diskpart_scripts,
+        driver_match_devices: config.driver_match_devices,
+        install_cab_packages: config.install_cab_packages,
+        tweaks: crate::ui::advanced_options::tweak_options(&config),
+        remove_uwp_apps: config.remove_uwp_apps,
+        import_storage_controller_drivers: config.import_storage_controller_drivers,
+        storage_controller_dir: Some(path::get_exe_dir().join("drivers").join("storage_controller")),
+        unattended: config.unattended,
+        custom_unattend: config.custom_unattend_file.clone(),
+        custom_unattend_merge: config.custom_unattend_merge,
+        custom_username: config.custom_username.clone(),
+        custom_boot_script: repair_script.exists().then_some(repair_script),
+        // PE 中安装完成后总是重启
+        auto_reboot: true,
+        ..Default::default()
+    };
+    Ok(plan_install(&input))
+}
 
+/// 按数据分区上的备份配置生成执行计划
+pub fn build_backup_plan() -> Result<ExecutionPlan, String> {
+    use crate::core::config::BackupFormat;
+
+    let data_partition =
+        ConfigFileManager::find_data_partition().ok_or_else(|| tr!("未找到备份配置文件"))?;
+    let config = ConfigFileManager::read_backup_config(&data_partition)
+        .map_err(|e| tr!("读取配置失败: {}", e))?;
+    let input = BackupPlanInput {
+        source_partition: ConfigFileManager::find_backup_marker_partition()
+            .unwrap_or_else(|| config.source_partition.clone()),
+        save_path: config.save_path.clone(),
+        name: config.name.clone(),
+        format: match config.format {
+            BackupFormat::Wim => CaptureFormat::Wim,
+            BackupFormat::Esd => CaptureFormat::Esd,
+            BackupFormat::Swm => CaptureFormat::Swm,
+            BackupFormat::Gho => CaptureFormat::Gho,
+        },
+        incremental: config.incremental,
+        swm_split_mb: config.swm_split_size,
+        // PE 中备份完成后总是重启
+        auto_reboot: true,
+        runs_in_pe: true,
+        via_pe: None,
+    };
+    Ok(plan_backup(&input))
+}
+
+/// 镜像释放之后的步骤依赖目标分区里有完整系统；系统不在了（被格式化或盘符变了）
+/// 就只能回到格式化重来
+fn safe_resume_point(from: InstallPhase, target_partition: &str) -> InstallPhase {
+    let system32 = format!("{}\\Windows\\System32", target_partition);
+    if from.after_apply() && !Path::new(&system32).exists() {
+        log::warn!("[PE安装] 目标分区 {} 中没有已释放的系统，改为从格式化开始", target_partition);
+        InstallPhase::Format
+    } else {
+        from
+    }
+}
+
+/// 续做前要核对的步骤输入：目标分区、镜像卷索引、驱动目录（与各步骤记入日志的输入对应）
+fn resume_inputs(
+    config: &InstallConfig,
+    data_dir: &str,
+    target_partition: &str,
+) -> Vec<(InstallPhase, &'static str, String)> {
+    vec![
+        (InstallPhase::Format, "target", target_partition.to_string()),
+        (InstallPhase::ApplyImage, "volume_index", config.volume_index.to_string()),
+        (InstallPhase::ImportDrivers, "driver_dir", format!("{}\\drivers", data_dir)),
+    ]
+}
+
+/// 检测上次被中断的安装：日志与当前配置、镜像、各步骤输入都对得上时返回日志与续做点。
+/// PE 启动时据此询问是否续做。
+pub fn detect_interrupted_install() -> Option<(InstallJournal, ResumePoint)> {
+    let (data_partition, config, image_path) = locate_install().ok()?;
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let journal = InstallJournal::load(Path::new(&data_dir))?;
+
+    let config_hash = ConfigFileManager::install_config_hash(&data_partition)?;
+    let image = FileFingerprint::of(Path::new(&image_path)).ok()?;
+    if !journal.matches(&config_hash, &image) {
+        log::info!("[PE安装] 安装日志与当前配置或镜像不符，忽略");
+        return None;
+    }
+    let mut point = journal.resume_point()?;
+    // 定位失败时仍提示续做，真正执行时会再定位并报错
+    let target = locate_target(&config, Some(&journal.target_partition))
+        .unwrap_or_else(|_| journal.target_partition.clone());
+    point.from = safe_resume_point(point.from, &target);
+    if let Err(e) = journal.check_inputs(point.from, &resume_inputs(&config, &data_dir, &target)) {
+        log::info!("[PE安装] 安装日志记录的输入与本次不符（{}），不续做", e);
+        return None;
+    }
+    log::info!(
+        "[PE安装] 检测到中断的安装: 停在「{}」，可从「{}」续做（已续做 {} 次）",
+        point.interrupted.label(),
+        point.from.label(),
+        journal.resumes
+    );
+    Some((journal, point))
+}
+
+/// 执行安装工作流。`resume_from` 为续做起点（`None` 时从头开始，并丢弃旧的安装日志）。
+///
+/// 每个步骤开始、完成、失败都记入数据目录的安装日志（见 `lr_core::install_journal`），
+/// PE 重启或断电后可从最近的安全步骤续做。
+fn execute_install_workflow(tx: Sender<WorkerMessage>, resume_from: Option<InstallPhase>) {
     log::info!("========== 开始PE安装流程 ==========");
     // 注：BitLocker 透传解锁已在 main() 最前面统一执行（早于操作类型检测），这里不再重复。
 
-    // 查找配置文件所在分区
-    let data_partition = match ConfigFileManager::find_data_partition() {
-        Some(p) => p,
-        None => {
-            let _ = tx.send(WorkerMessage::Failed(tr!("未找到安装配置文件")));
+    let (data_partition, config, image_path) = match locate_install() {
+        Ok(v) => v,
+        Err(msg) => {
+            let _ = tx.send(WorkerMessage::Failed(msg));
             return;
         }
     };
@@ -247,29 +537,12 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
     log::info!("数据分区: {}", data_partition);
     let _ = tx.send(WorkerMessage::SetStatus(tr!("数据分区: {}", data_partition)));
 
-    // 读取安装配置
-    let config = match ConfigFileManager::read_install_config(&data_partition) {
-        Ok(c) => c,
-        Err(e) => {
-            let _ = tx.send(WorkerMessage::Failed(tr!("读取配置失败: {}", e)));
-            return;
-        }
-    };
-
     // 切换到正常系统端选定的镜像引擎（随重启传入），使 PE 端使用相同引擎
     lr_core::set_active_engine(lr_core::WimEngine::from_u8(config.wim_engine));
 
     log::info!("目标分区: {}", config.target_partition);
     log::info!("镜像文件: {}", config.image_path);
 
-    // 查找安装标记分区
-    let target_partition = ConfigFileManager::find_install_marker_partition()
-        .unwrap_or_else(|| config.target_partition.clone());
-
-    // 构建完整镜像路径
-    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
-    let image_path = format!("{}\\{}", data_dir, config.image_path);
-
     if !std::path::Path::new(&image_path).exists() {
         let _ = tx.send(WorkerMessage::Failed(tr!("镜像文件不存在: {}", image_path)));
         return;
@@ -277,89 +550,234 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
 
     log::info!("完整镜像路径: {}", image_path);
 
-    // Step 0: 校验镜像完整性（WIM/ESD）。放在格式化之前——镜像损坏就提前失败，
-    // 不会白白格式化目标盘，也能给出明确“镜像损坏”而不是释放到一半才崩。
-    // GHO 不是 WIM，跳过 wimlib 校验。
-    if !config.is_gho {
-        let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::VerifyImage));
-        let _ = tx.send(WorkerMessage::SetStatus(
-            tr!("正在校验系统镜像完整性（可能需要几分钟）..."),
-        ));
-        log::info!("[PE安装] 开始校验镜像: {}", image_path);
-
-        let (verify_tx, verify_rx) = channel::<DismProgress>();
-        let tx_v = tx.clone();
-        let verify_handle = thread::spawn(move || {
-            while let Ok(progress) = verify_rx.recv() {
-                let _ = tx_v.send(WorkerMessage::SetProgress(progress.percentage));
-                let _ = tx_v.send(WorkerMessage::SetStatus(progress.status));
+    let data_dir = ConfigFileManager::get_data_dir(&data_partition);
+    let config_hash = ConfigFileManager::install_config_hash(&data_partition).unwrap_or_default();
+    let image = FileFingerprint::of(Path::new(&image_path)).unwrap_or_default();
+
+    // 续做时沿用旧日志（日志已对不上则从头开始）
+    let resumed = resume_from.and_then(|from| {
+        InstallJournal::load(Path::new(&data_dir))
+            .filter(|j| j.matches(&config_hash, &image))
+            .map(|j| (j, from))
+    });
+
+    // 定位目标分区（无标识时：标记随格式化消失后，续做时用日志里记下的目标分区）
+    let journal_target = resumed.as_ref().map(|(j, _)| j.target_partition.clone());
+    let target_partition = match locate_target(&config, journal_target.as_deref()) {
+        Ok(letter) => letter,
+        Err(msg) => {
+            let _ = tx.send(WorkerMessage::Failed(msg));
+            return;
+        }
+    };
+
+    let plan = install_plan(&config);
+    let (journal, from) = match resumed {
+        Some((mut journal, from)) => {
+            let from = safe_resume_point(from, &target_partition);
+            let inputs = resume_inputs(&config, &data_dir, &target_partition);
+            if let Err(e) = journal.check_inputs(from, &inputs) {
+                log::error!("[PE安装] 拒绝续做: {}", e);
+                let _ = tx.send(WorkerMessage::Failed(tr!(
+                    "无法续做上次中断的安装：{}。请重新开始安装。",
+                    e
+                )));
+                return;
             }
-        });
+            log::info!("[PE安装] 从「{}」续做上次中断的安装", from.label());
+            journal.prepare_resume(from);
+            (journal, from)
+        }
+        None => {
+            InstallJournal::remove(Path::new(&data_dir));
+            let journal = InstallJournal::new(&config_hash, image, &target_partition, &plan);
+            (journal, plan[0])
+        }
+    };
 
-        let verify_result = Dism::new().verify_image(&image_path, Some(verify_tx));
-        let _ = verify_handle.join();
+    let mut ctx = InstallContext {
+        tx: tx.clone(),
+        apply_dir: format!("{}\\", target_partition),
+        config,
+        data_partition,
+        data_dir,
+        target_partition,
+        image_path,
+        journal,
+    };
 
-        if let Err(e) = verify_result {
-            log::error!("[PE安装] 镜像校验失败: {}", e);
-            let _ = tx.send(WorkerMessage::Failed(tr!(
-                "镜像校验失败：镜像可能已损坏或不完整（{}）。请重新获取镜像后重试。",
-                e
-            )));
-            return;
+    for phase in InstallPhase::ALL {
+        if !ctx.journal.should_run(phase, from) {
+            continue;
         }
-        log::info!("[PE安装] 镜像校验通过");
-        let _ = tx.send(WorkerMessage::SetProgress(100));
-    } else {
-        log::info!("[PE安装] GHO 镜像，跳过 wimlib 校验");
-    }
+        ctx.journal.begin(phase, &[]);
+        ctx.save_journal();
 
-    // 装机前运行 diskpart 脚本（分区准备）——来自数据目录暂存的 diskpart\
-    if config.run_diskpart_scripts {
-        let _ = tx.send(WorkerMessage::SetStatus(tr!("正在运行 Diskpart 脚本...")));
-        let scripts_dir = std::path::Path::new(&data_dir).join("diskpart");
-        log::info!("[PE安装] 运行 Diskpart 脚本: {}", scripts_dir.display());
-        match lr_core::diskpart::run_scripts_in_dir(&scripts_dir) {
-            Ok(out) => log::info!("[PE安装] Diskpart 脚本执行完成:\n{}", out),
-            Err(e) => {
-                log::error!("[PE安装] Diskpart 脚本执行失败: {}", e);
-                let _ = tx.send(WorkerMessage::Failed(tr!("Diskpart 脚本执行失败: {}", e)));
+        let result = run_install_phase(&mut ctx, phase);
+        // 清理步骤删掉了数据目录（连同日志），之后不再写日志
+        if phase == InstallPhase::Cleanup {
+            continue;
+        }
+        match result {
+            Ok(()) => {
+                ctx.journal.complete(phase);
+                ctx.save_journal();
+            }
+            Err(msg) => {
+                log::error!("[PE安装] 步骤「{}」失败: {}", phase.label(), msg);
+                ctx.journal.fail(phase, &msg);
+                ctx.save_journal();
+                let _ = tx.send(WorkerMessage::Failed(msg));
                 return;
             }
         }
     }
 
-    // Step 1: 格式化分区
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::FormatPartition));
-    let _ = tx.send(WorkerMessage::SetStatus(tr!("正在格式化目标分区...")));
+    // 完成
+    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Complete));
+    let _ = tx.send(WorkerMessage::Completed);
+
+    log::info!("========== PE安装流程完成 ==========");
+
+    // PE环境下安装完成后强制重启
+    log::info!("即将重启...");
+    std::thread::sleep(std::time::Duration::from_secs(3));
+    reboot_pe();
+}
+
+/// 执行一个安装步骤；返回 `Err` 时安装中止（消息直接展示给用户）
+fn run_install_phase(ctx: &mut InstallContext, phase: InstallPhase) -> Result<(), String> {
+    match phase {
+        InstallPhase::VerifyImage => phase_verify_image(ctx),
+        InstallPhase::Diskpart => phase_diskpart(ctx),
+        InstallPhase::Format => phase_format(ctx),
+        InstallPhase::ApplyImage => phase_apply_image(ctx),
+        InstallPhase::ImportDrivers => phase_import_drivers(ctx),
+        InstallPhase::InstallCab => phase_install_cab(ctx),
+        InstallPhase::RepairBoot => phase_repair_boot(ctx),
+        InstallPhase::AdvancedOptions => phase_advanced_options(ctx),
+        InstallPhase::Unattend => phase_unattend(ctx),
+        InstallPhase::Cleanup => phase_cleanup(ctx),
+    }
+}
+
+/// Step 0: 校验镜像完整性（WIM/ESD）。放在格式化之前——镜像损坏就提前失败，
+/// 不会白白格式化目标盘，也能给出明确“镜像损坏”而不是释放到一半才崩。
+/// GHO 不是 WIM，不在计划里。
+fn phase_verify_image(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::dism::Dism;
+
+    let tx = &ctx.tx;
+    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::VerifyImage));
+    let _ = tx.send(WorkerMessage::SetStatus(
+        tr!("正在校验系统镜像完整性（可能需要几分钟）..."),
+    ));
+    log::info!("[PE安装] 开始校验镜像: {}", ctx.image_path);
+
+    let (verify_tx, verify_rx) = channel::<DismProgress>();
+    let tx_v = tx.clone();
+    let verify_handle = thread::spawn(move || {
+        while let Ok(progress) = verify_rx.recv() {
+            let _ = tx_v.send(WorkerMessage::SetProgress(progress.percentage));
+            let _ = tx_v.send(WorkerMessage::SetStatus(progress.status));
+        }
+    });
+
+    let verify_result = Dism::new().verify_image(&ctx.image_path, Some(verify_tx));
+    let _ = verify_handle.join();
+
+    if let Err(e) = verify_result {
+        log::error!("[PE安装] 镜像校验失败: {}", e);
+        return Err(tr!(
+            "镜像校验失败：镜像可能已损坏或不完整（{}）。请重新获取镜像后重试。",
+            e
+        ));
+    }
+    log::info!("[PE安装] 镜像校验通过");
+    let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
+
+/// 装机前运行 diskpart 脚本（分区准备）——来自数据目录暂存的 diskpart\
+fn phase_diskpart(ctx: &mut InstallContext) -> Result<(), String> {
+    let _ = ctx.tx.send(WorkerMessage::SetStatus(tr!("正在运行 Diskpart 脚本...")));
+    let scripts_dir = std::path::Path::new(&ctx.data_dir).join("diskpart");
+    log::info!("[PE安装] 运行 Diskpart 脚本: {}", scripts_dir.display());
+    match lr_core::diskpart::run_scripts_in_dir(&scripts_dir) {
+        Ok(out) => {
+            log::info!("[PE安装] Diskpart 脚本执行完成:\n{}", out);
+            Ok(())
+        }
+        Err(e) => {
+            log::error!("[PE安装] Diskpart 脚本执行失败: {}", e);
+            Err(tr!("Diskpart 脚本执行失败: {}", e))
+        }
+    }
+}
+
+/// Step 1: 格式化分区
+fn phase_format(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::disk::DiskManager;
+
+    let _ = ctx.tx.send(WorkerMessage::SetInstallStep(InstallStep::FormatPartition));
+    let _ = ctx.tx.send(WorkerMessage::SetStatus(tr!("正在格式化目标分区...")));
+
+    // 格式化前按磁盘/分区标识再确认一次目标（diskpart 脚本可能改动了分区或盘符）
+    if ctx.config.target_identity.is_known() {
+        let letter = locate_target(&ctx.config, None)?;
+        if letter != ctx.target_partition {
+            log::warn!("[PE安装] 目标分区盘符由 {} 变为 {}", ctx.target_partition, letter);
+            ctx.apply_dir = format!("{}\\", letter);
+            ctx.target_partition = letter;
+        }
+    }
 
     // 使用卷标参数（如果有配置的话）
-    let volume_label = if config.volume_label.is_empty() {
+    let volume_label = if ctx.config.volume_label.is_empty() {
         None
     } else {
-        Some(config.volume_label.as_str())
+        Some(ctx.config.volume_label.as_str())
     };
-    
-    match DiskManager::format_partition_with_label(&target_partition, volume_label) {
+    ctx.journal
+        .set_input(InstallPhase::Format, "target", &ctx.target_partition);
+
+    match DiskManager::format_partition_with_label(&ctx.target_partition, volume_label) {
         Ok(_) => {
             log::info!("分区格式化成功");
-            let _ = tx.send(WorkerMessage::SetProgress(100));
+            // 格式化抹掉了目标分区上的安装标记，写回去，中断重启后仍能定位目标分区
+            ConfigFileManager::restore_install_marker(&ctx.target_partition);
+            let _ = ctx.tx.send(WorkerMessage::SetProgress(100));
+            Ok(())
         }
         Err(e) => {
             log::error!("[PE安装] 格式化分区失败: {}", e);
-            let _ = tx.send(WorkerMessage::Failed(tr!("格式化分区失败: {}", e)));
-            return;
+            Err(tr!("格式化分区失败: {}", e))
         }
     }
+}
+
+/// Step 2: 释放镜像
+fn phase_apply_image(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::disk::DiskManager;
+    use crate::core::dism::Dism;
+    use crate::core::ghost::Ghost;
 
-    // Step 2: 释放镜像
+    let tx = &ctx.tx;
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyImage));
     let _ = tx.send(WorkerMessage::SetStatus(tr!("正在释放系统镜像...")));
 
-    let apply_dir = format!("{}\\", target_partition);
+    let config = &ctx.config;
+    let image_path = &ctx.image_path;
+    let apply_dir = &ctx.apply_dir;
     log::info!(
         "[PE安装] 开始释放镜像: 文件={} 卷索引={} is_gho={} -> 目标={}",
         image_path, config.volume_index, config.is_gho, apply_dir
     );
+    ctx.journal.set_input(
+        InstallPhase::ApplyImage,
+        "volume_index",
+        &config.volume_index.to_string(),
+    );
 
     // 创建进度通道
     let (progress_tx, progress_rx) = channel::<DismProgress>();
@@ -376,16 +794,15 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
         // GHO镜像使用Ghost
         let ghost = Ghost::new();
         if !ghost.is_available() {
-            let _ = tx.send(WorkerMessage::Failed(tr!("Ghost工具不可用")));
-            return;
+            return Err(tr!("Ghost工具不可用"));
         }
 
         let partitions = DiskManager::get_partitions().unwrap_or_default();
-        ghost.restore_image_to_letter(&image_path, &target_partition, &partitions, Some(progress_tx))
+        ghost.restore_image_to_letter(image_path, &ctx.target_partition, &partitions, Some(progress_tx))
     } else {
         // WIM/ESD使用DISM
         let dism = Dism::new();
-        dism.apply_image(&image_path, &apply_dir, config.volume_index, Some(progress_tx))
+        dism.apply_image(image_path, apply_dir, config.volume_index, Some(progress_tx))
     };
 
     // 等待进度监控线程结束
@@ -393,27 +810,42 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
 
     if let Err(e) = apply_result {
         log::error!("[PE安装] 释放镜像失败: {}", e);
-        let _ = tx.send(WorkerMessage::Failed(tr!("释放镜像失败: {}", e)));
-        return;
+        return Err(tr!("释放镜像失败: {}", e));
     }
     log::info!("[PE安装] 释放镜像完成");
+    // Ghost 还原会重建分区内容，标记可能再次丢失
+    ConfigFileManager::restore_install_marker(&ctx.target_partition);
     let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
 
-    // Step 3: 导入驱动
+/// Step 3: 导入驱动
+fn phase_import_drivers(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::dism::Dism;
+
+    let tx = ctx.tx.clone();
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ImportDrivers));
 
+    let config = &ctx.config;
+    let data_dir = &ctx.data_dir;
+    let apply_dir = &ctx.apply_dir;
+
     // 根据 driver_action_mode 决定是否导入驱动
     // 0 = 无, 1 = 仅保存（不导入）, 2 = 自动导入
     let driver_path = format!("{}\\drivers", data_dir);
     let driver_path_exists = std::path::Path::new(&driver_path).exists();
-    
+    ctx.journal
+        .set_input(InstallPhase::ImportDrivers, "driver_dir", &driver_path);
+    // 本次实际注入的驱动目录（缺驱动报告用）
+    let mut injected_dir: Option<String> = None;
+
     if config.should_import_drivers() && driver_path_exists {
         let _ = tx.send(WorkerMessage::SetStatus(tr!("正在导入驱动...")));
 
         // 创建进度通道
         let (driver_progress_tx, driver_progress_rx) = channel::<DismProgress>();
         let tx_driver = tx.clone();
-        
+
         // 启动进度监控线程
         let driver_progress_handle = thread::spawn(move || {
             while let Ok(progress) = driver_progress_rx.recv() {
@@ -421,9 +853,18 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                 let _ = tx_driver.send(WorkerMessage::SetStatus(tr!("导入驱动: {}", progress.status)));
             }
         });
-        
+
+        // 仅导入与本机硬件匹配的驱动：先按硬件 ID 挑选并暂存，再按目录注入
+        let inject_path = if config.driver_match_devices {
+            let _ = tx.send(WorkerMessage::SetStatus(tr!("正在按本机硬件匹配驱动...")));
+            crate::core::driver::select_matched_drivers(&driver_path, apply_dir, data_dir)
+        } else {
+            driver_path.clone()
+        };
+
         let dism = Dism::new();
-        match dism.add_drivers_offline_with_progress(&apply_dir, &driver_path, Some(driver_progress_tx)) {
+        injected_dir = Some(inject_path.clone());
+        match dism.add_drivers_offline_with_progress(apply_dir, &inject_path, Some(driver_progress_tx)) {
             Ok(_) => {
                 log::info!("驱动导入成功");
             }
@@ -432,10 +873,25 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                 // 不中断安装流程，继续执行
             }
         }
-        
+
         // 等待进度监控线程结束
         let _ = driver_progress_handle.join();
-        
+
+        // 逐个核实注入结果，写驱动导入报告（目标系统 + 数据分区）
+        let (ok, failed) = crate::core::driver::write_import_report(
+            &inject_path,
+            apply_dir,
+            data_dir,
+            &ConfigFileManager::get_reports_dir(&ctx.data_partition),
+        );
+        if failed > 0 {
+            let _ = tx.send(WorkerMessage::SetStatus(tr!(
+                "驱动导入: 成功 {}，失败 {}（详见驱动导入报告）",
+                ok,
+                failed
+            )));
+        }
+
         // 同时检查驱动目录中是否有 CAB 文件并安装
         let cab_files_in_driver_dir = find_cab_files_in_directory(&driver_path);
         if !cab_files_in_driver_dir.is_empty() {
@@ -444,11 +900,11 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                 "正在安装驱动目录中的 {} 个 CAB 更新包...",
                 cab_files_in_driver_dir.len()
             )));
-            
+
             // 创建进度通道
             let (cab_progress_tx, cab_progress_rx) = channel::<DismProgress>();
             let tx_cab = tx.clone();
-            
+
             // 启动进度监控线程
             let cab_progress_handle = thread::spawn(move || {
                 while let Ok(progress) = cab_progress_rx.recv() {
@@ -456,9 +912,9 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     let _ = tx_cab.send(WorkerMessage::SetStatus(tr!("安装CAB: {}", progress.status)));
                 }
             });
-            
+
             let dism = Dism::new();
-            match dism.add_packages_offline_from_dir(&apply_dir, &driver_path, Some(cab_progress_tx)) {
+            match dism.add_packages_offline_from_dir(apply_dir, &driver_path, Some(cab_progress_tx)) {
                 Ok((success, fail)) => {
                     log::info!("驱动目录中的CAB安装完成: {} 成功, {} 失败", success, fail);
                 }
@@ -466,7 +922,7 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     log::warn!("驱动目录中的CAB安装失败: {}", e);
                 }
             }
-            
+
             let _ = cab_progress_handle.join();
         }
     } else if config.should_import_drivers() && !driver_path_exists {
@@ -480,20 +936,39 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
         let _ = tx.send(WorkerMessage::SetStatus(tr!("跳过驱动导入")));
         log::info!("驱动操作模式为无，跳过驱动导入");
     }
+
+    // 对照正常系统端写入的硬件清单，报告新系统里没有驱动的设备
+    if let Some(missing) =
+        crate::core::driver::report_missing_drivers(injected_dir.as_deref(), apply_dir, data_dir)
+    {
+        if missing > 0 {
+            let _ = tx.send(WorkerMessage::SetStatus(tr!("有 {} 个设备在新系统中缺少驱动", missing)));
+        }
+    }
+    if let Some(dir) = injected_dir {
+        ctx.journal
+            .set_input(InstallPhase::ImportDrivers, "injected_dir", &dir);
+    }
     let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
+
+/// Step 4: 安装CAB更新包
+fn phase_install_cab(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::dism::Dism;
 
-    // Step 4: 安装CAB更新包
+    let tx = &ctx.tx;
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::InstallCabPackages));
 
-    if config.install_cab_packages {
-        let cab_path = format!("{}\\updates", data_dir);
+    if ctx.config.install_cab_packages {
+        let cab_path = format!("{}\\updates", ctx.data_dir);
         if std::path::Path::new(&cab_path).exists() {
             let _ = tx.send(WorkerMessage::SetStatus(tr!("正在安装更新包...")));
-            
+
             // 创建进度通道
             let (cab_progress_tx, cab_progress_rx) = channel::<DismProgress>();
             let tx_cab = tx.clone();
-            
+
             // 启动进度监控线程
             let cab_progress_handle = thread::spawn(move || {
                 while let Ok(progress) = cab_progress_rx.recv() {
@@ -501,9 +976,9 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     let _ = tx_cab.send(WorkerMessage::SetStatus(tr!("安装更新: {}", progress.status)));
                 }
             });
-            
+
             let dism = Dism::new();
-            match dism.add_packages_offline_from_dir(&apply_dir, &cab_path, Some(cab_progress_tx)) {
+            match dism.add_packages_offline_from_dir(&ctx.apply_dir, &cab_path, Some(cab_progress_tx)) {
                 Ok((success, fail)) => {
                     log::info!("CAB更新包安装完成: {} 成功, {} 失败", success, fail);
                     let _ = tx.send(WorkerMessage::SetStatus(
@@ -515,7 +990,7 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
                     // 不中断安装流程，继续执行
                 }
             }
-            
+
             // 等待进度监控线程结束
             let _ = cab_progress_handle.join();
         } else {
@@ -527,8 +1002,16 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
         log::info!("未启用CAB更新包安装");
     }
     let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
+
+/// Step 5: 修复引导
+fn phase_repair_boot(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::bcdedit::BootManager;
+    use crate::core::disk::DiskManager;
 
-    // Step 5: 修复引导
+    let tx = &ctx.tx;
+    let target_partition = &ctx.target_partition;
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::RepairBoot));
     let _ = tx.send(WorkerMessage::SetStatus(tr!("正在修复引导...")));
 
@@ -538,61 +1021,81 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
     // XP/2003 写 ntldr 引导；其余走 bcdboot。
     // XP 判定：配置已标记 或 释放后的系统缺少 \Windows\Boot（该目录仅 Vista+ 才有）。
     let win_boot_dir = format!("{}\\Windows\\Boot", target_partition);
-    let is_xp = config.is_xp || !std::path::Path::new(&win_boot_dir).exists();
+    let is_xp = ctx.config.is_xp || !std::path::Path::new(&win_boot_dir).exists();
     let boot_result = if is_xp {
         if use_uefi {
             log::info!("[PE安装] 识别为 XP/2003 + UEFI，写入 XP UEFI/GPT 引导");
             // UEFI 化映像：用映像自带 bootxp64.efi/BCC 写 UEFI 引导；
             // 失败（如映像非 UEFI 化、缺引导文件）则回退 Legacy(ntldr)。
-            match boot_manager.write_xp_uefi_gpt_boot(&target_partition) {
+            match boot_manager.write_xp_uefi_gpt_boot(target_partition) {
                 Ok(()) => Ok(()),
                 Err(e) => {
                     log::warn!("[PE安装] XP UEFI 引导失败({})，回退 Legacy(ntldr)", e);
                     let _ = tx.send(WorkerMessage::SetStatus(
                         tr!("XP UEFI 引导不可用，回退 Legacy 引导..."),
                     ));
-                    boot_manager.write_xp_boot(&target_partition)
+                    boot_manager.write_xp_boot(target_partition)
                 }
             }
         } else {
             log::info!("[PE安装] 识别为 XP/2003(Legacy)，写入 XP 引导(ntldr/boot.ini)");
-            boot_manager.write_xp_boot(&target_partition)
+            boot_manager.write_xp_boot(target_partition)
         }
     } else {
-        boot_manager.repair_boot_advanced(&target_partition, use_uefi)
+        boot_manager.repair_boot_advanced(target_partition, use_uefi)
     };
+    ctx.journal.set_input(
+        InstallPhase::RepairBoot,
+        "mode",
+        match (is_xp, use_uefi) {
+            (true, true) => "xp_uefi",
+            (true, false) => "xp_legacy",
+            (false, true) => "uefi",
+            (false, false) => "legacy",
+        },
+    );
     if let Err(e) = boot_result {
-        let _ = tx.send(WorkerMessage::Failed(tr!("修复引导失败: {}", e)));
-        return;
+        return Err(tr!("修复引导失败: {}", e));
     }
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+    let _ = ctx.tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
+
+/// Step 6: 应用高级选项
+fn phase_advanced_options(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::ui::advanced_options::apply_advanced_options;
 
-    // Step 6: 应用高级选项
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyAdvancedOptions));
-    let _ = tx.send(WorkerMessage::SetStatus(tr!("正在应用高级选项...")));
+    let _ = ctx.tx.send(WorkerMessage::SetInstallStep(InstallStep::ApplyAdvancedOptions));
+    let _ = ctx.tx.send(WorkerMessage::SetStatus(tr!("正在应用高级选项...")));
 
-    if let Err(e) = apply_advanced_options(&target_partition, &config) {
+    if let Err(e) = apply_advanced_options(&ctx.target_partition, &ctx.config) {
         log::warn!("应用高级选项失败: {}", e);
     }
     // 注入数据分区上的用户驱动（bin/drivers/<版本> 由正常端复制而来）
-    crate::ui::advanced_options::inject_user_drivers_from_data(&target_partition, &data_dir);
-    let _ = tx.send(WorkerMessage::SetProgress(100));
+    crate::ui::advanced_options::inject_user_drivers_from_data(&ctx.target_partition, &ctx.data_dir);
+    let _ = ctx.tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
 
-    // Step 7: 生成无人值守配置
+/// Step 7: 生成无人值守配置
+fn phase_unattend(ctx: &mut InstallContext) -> Result<(), String> {
+    let tx = &ctx.tx;
+    let config = &ctx.config;
+    let target_partition = &ctx.target_partition;
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::GenerateUnattend));
 
     if config.unattended {
         if !config.custom_unattend_file.is_empty() {
-            // 用户提供了自定义无人值守文件：直接复制到目标系统（不再内置生成）
+            // 用户提供了自定义无人值守文件：复制到目标系统（合并模式下先叠加内置生成的配置）
             let _ = tx.send(WorkerMessage::SetStatus(tr!("正在应用自定义无人值守配置...")));
-            let src = format!("{}\\{}", data_dir, config.custom_unattend_file);
-            match apply_custom_unattend(&target_partition, &src) {
-                Ok(_) => log::info!("[UNATTEND] 已应用自定义无人值守文件: {}", src),
-                Err(e) => log::warn!("应用自定义无人值守文件失败: {}", e),
-            }
+            let src = format!("{}\\{}", ctx.data_dir, config.custom_unattend_file);
+            // 失败时停在这一步让用户看到：原样写入用户文件会丢掉内置的账户与部署脚本
+            apply_custom_unattend(target_partition, &src, config)
+                .map_err(|e| tr!("应用自定义无人值守文件失败: {}", e))?;
+            log::info!("[UNATTEND] 已应用自定义无人值守文件: {}", src);
         } else {
             let _ = tx.send(WorkerMessage::SetStatus(tr!("正在生成无人值守配置...")));
-            if let Err(e) = generate_unattend_xml(&target_partition, &config) {
+            if let Err(e) = generate_unattend_xml(target_partition, config) {
                 log::warn!("生成无人值守配置失败: {}", e);
             }
         }
@@ -602,23 +1105,29 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
 
     // 离线登录兜底：放开空密码登录策略 +（已知用户名时）配置空密码自动登录。
     // 解决整盘备份/未 sysprep 镜像下 unattend 不生效、登录界面退化为"其他用户"的问题。
-    if let Err(e) = crate::core::account_fix::ensure_offline_login(&target_partition, &config.custom_username) {
+    if let Err(e) = crate::core::account_fix::ensure_offline_login(target_partition, &config.custom_username) {
         log::warn!("离线登录兜底设置失败（不影响安装）: {}", e);
     } else {
         log::info!("[LOGIN] 已应用离线登录兜底设置");
     }
     let _ = tx.send(WorkerMessage::SetProgress(100));
+    Ok(())
+}
+
+/// Step 8: 清理临时文件（数据目录连同安装日志一并删除）
+fn phase_cleanup(ctx: &mut InstallContext) -> Result<(), String> {
+    use crate::core::disk::DiskManager;
 
-    // Step 8: 清理临时文件
+    let tx = &ctx.tx;
     let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Cleanup));
     let _ = tx.send(WorkerMessage::SetStatus(tr!("正在清理临时文件...")));
 
-    ConfigFileManager::cleanup_all(&data_partition, &target_partition);
+    ConfigFileManager::cleanup_all(&ctx.data_partition, &ctx.target_partition);
     let _ = tx.send(WorkerMessage::SetProgress(50));
 
     // 清理自动创建的数据分区并扩展目标分区
     let _ = tx.send(WorkerMessage::SetStatus(tr!("正在清理自动创建的分区...")));
-    match DiskManager::cleanup_auto_created_partition_and_extend(&target_partition) {
+    match DiskManager::cleanup_auto_created_partition_and_extend(&ctx.target_partition) {
         Ok(_) => {
             log::info!("自动创建分区清理完成");
         }
@@ -628,17 +1137,7 @@ fn execute_install_workflow(tx: Sender<WorkerMessage>) {
         }
     }
     let _ = tx.send(WorkerMessage::SetProgress(100));
-
-    // 完成
-    let _ = tx.send(WorkerMessage::SetInstallStep(InstallStep::Complete));
-    let _ = tx.send(WorkerMessage::Completed);
-
-    log::info!("========== PE安装流程完成 ==========");
-
-    // PE环境下安装完成后强制重启
-    log::info!("即将重启...");
-    std::thread::sleep(std::time::Duration::from_secs(3));
-    reboot_pe();
+    Ok(())
 }
 
 /// 执行无损扩容工作流（无损扩大系统盘，目前仅并入相邻未分配空间）。
@@ -922,10 +1421,24 @@ fn execute_backup_workflow(tx: Sender<WorkerMessage>) {
 /// - specialize pass: 部署脚本执行
 /// - oobeSystem pass: OOBE设置、用户账户、首次登录命令
 /// 应用用户自定义的无人值守文件：复制到目标系统的 Panther 与 Sysprep 目录
-fn apply_custom_unattend(target_partition: &str, src: &str) -> anyhow::Result<()> {
-    let content = std::fs::read(src)
+fn apply_custom_unattend(
+    target_partition: &str,
+    src: &str,
+    config: &crate::core::config::InstallConfig,
+) -> anyhow::Result<()> {
+    let mut content = std::fs::read(src)
         .map_err(|e| anyhow::anyhow!("读取自定义无人值守文件失败 {}: {}", src, e))?;
 
+    // 合并模式：把内置生成的部署脚本/首次登录命令/账户等叠加到用户文件上（用户写了的以用户为准）。
+    // 用户文件按 BOM 解码（UTF-16 常见），合并后统一输出 UTF-8；无法解析时报错。
+    if config.custom_unattend_merge {
+        let ours = deploy_unattend(target_partition, config).build();
+        let merged = lr_core::unattend::merge_xml(&lr_core::unattend::decode_xml(&content), &ours)
+            .map_err(|e| anyhow::anyhow!("{}", tr!("无法解析自定义无人值守文件，合并失败: {}", e)))?;
+        log::info!("[UNATTEND] 已将内置配置合并到自定义无人值守文件");
+        content = merged.into_bytes();
+    }
+
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
     std::fs::write(format!("{}\\unattend.xml", panther_dir), &content)?;
@@ -937,18 +1450,13 @@ fn apply_custom_unattend(target_partition: &str, src: &str) -> anyhow::Result<()
     Ok(())
 }
 
-fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::InstallConfig) -> anyhow::Result<()> {
-    use crate::ui::advanced_options::get_scripts_dir_name;
+/// 已释放到 `target_partition` 的系统的架构（unattend 写法）与代别。
+pub(crate) fn target_system(
+    target_partition: &str,
+) -> (&'static str, lr_core::unattend::WindowsGeneration) {
     use crate::core::system_utils::{get_file_version, get_offline_system_architecture};
+    use lr_core::unattend::WindowsGeneration;
     use std::path::Path;
-    
-    let username = if config.custom_username.is_empty() { 
-        "MyPc".to_string() 
-    } else { 
-        config.custom_username.clone() 
-    };
-
-    let scripts_dir = get_scripts_dir_name();
 
     // 检测目标系统架构
     let arch = get_offline_system_architecture(Path::new(target_partition));
@@ -958,73 +1466,56 @@ fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::I
     // 通过 ntdll.dll 文件版本检测目标系统版本
     // Windows 7: 6.1.x, Windows 8: 6.2.x, Windows 8.1: 6.3.x, Windows 10/11: 10.0.x
     let ntdll_path = Path::new(target_partition).join("Windows").join("System32").join("ntdll.dll");
-    let (is_win7, is_win8) = match get_file_version(&ntdll_path) {
+    let generation = match get_file_version(&ntdll_path) {
         Some((major, minor, build, _)) => {
             log::info!("[UNATTEND] 检测到目标系统版本 (ntdll.dll): {}.{}.{}", major, minor, build);
-            
-            let is_win7 = major == 6 && minor == 1;
-            let is_win8 = major == 6 && (minor == 2 || minor == 3);
-            (is_win7, is_win8)
+            WindowsGeneration::from_nt_version(major, minor)
         }
         None => {
             log::warn!("[UNATTEND] 无法读取 ntdll.dll 版本: {:?}, 默认使用 Win10/11 配置", ntdll_path);
-            (false, false)
+            WindowsGeneration::Win10
         }
     };
+    (arch_str, generation)
+}
 
-    // 构建 FirstLogonCommands
-    let mut first_logon_commands = String::new();
-    let mut order = 1;
-
-    // 首次登录脚本（如果存在）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c if exist %SystemDrive%\{}\firstlogon.bat call %SystemDrive%\{}\firstlogon.bat</CommandLine>
-                    <Description>Run first login script</Description>
-                </SynchronousCommand>"#, order, scripts_dir, scripts_dir));
-    order += 1;
-
-    // 如果需要删除UWP应用（仅 Win10/11 支持）
-    if config.remove_uwp_apps && !is_win7 && !is_win8 {
-        first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>powershell -ExecutionPolicy Bypass -File %SystemDrive%\{}\remove_uwp.ps1</CommandLine>
-                    <Description>Remove preinstalled UWP apps</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-        order += 1;
-    }
-
-    // 清理脚本目录（最后执行）
-    first_logon_commands.push_str(&format!(r#"
-                <SynchronousCommand wcm:action="add">
-                    <Order>{}</Order>
-                    <CommandLine>cmd /c rd /s /q %SystemDrive%\{}</CommandLine>
-                    <Description>Cleanup scripts directory</Description>
-                </SynchronousCommand>"#, order, scripts_dir));
-
-    // 根据系统版本生成不同的 XML 内容
-    let xml_content = if is_win7 {
-        // Windows 7 专用无人值守配置
-        // Win7 不支持: HideOnlineAccountScreens, HideWirelessSetupInOOBE, SkipMachineOOBE, SkipUserOOBE, HideLocalAccountScreen, HideOEMRegistrationScreen(家庭版)
-        generate_win7_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
-    } else if is_win8 {
-        // Windows 8/8.1 无人值守配置
-        // Win8 支持部分 Win10 的选项，但不支持所有
-        generate_win8_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
-    } else {
-        // Windows 10/11 无人值守配置（默认）
-        generate_win10_unattend_xml(&username, &scripts_dir, &first_logon_commands, arch_str)
+/// 按目标系统（架构 / ntdll 版本）与安装配置构造内置部署应答。
+fn deploy_unattend(
+    target_partition: &str,
+    config: &crate::core::config::InstallConfig,
+) -> lr_core::unattend::DeployUnattend {
+    use crate::ui::advanced_options::get_scripts_dir_name;
+    use lr_core::unattend::DeployUnattend;
+    
+    let username = if config.custom_username.is_empty() { 
+        "MyPc".to_string() 
+    } else { 
+        config.custom_username.clone() 
     };
 
+    let scripts_dir = get_scripts_dir_name();
+    let (arch_str, generation) = target_system(target_partition);
+
+    // 部署脚本（specialize 的 deploy.bat、首次登录脚本、UWP 删除与清理）+ 按代别裁剪的 OOBE
+    let mut spec = DeployUnattend::new(arch_str, generation, &username);
+    spec.computer_name = Some("*".to_string());
+    spec.scripts_dir = Some(scripts_dir.to_string());
+    spec.remove_uwp_apps = config.remove_uwp_apps;
+    spec.auto_logon_count = Some(1);
+    spec.custom = config.unattend.clone();
+    spec
+}
+
+fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::InstallConfig) -> anyhow::Result<()> {
+    let spec = deploy_unattend(target_partition, config);
+    let xml_content = spec.to_xml();
+
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
 
     let unattend_path = format!("{}\\unattend.xml", panther_dir);
     std::fs::write(&unattend_path, &xml_content)?;
-    log::info!("[UNATTEND] 已写入: {} ({})", unattend_path, 
-        if is_win7 { "Win7配置" } else if is_win8 { "Win8配置" } else { "Win10/11配置" });
+    log::info!("[UNATTEND] 已写入: {} ({})", unattend_path, spec.generation.label());
 
     // 同时写入到 Sysprep 目录
     let sysprep_dir = format!("{}\\Windows\\System32\\Sysprep", target_partition);
@@ -1036,223 +1527,3 @@ fn generate_unattend_xml(target_partition: &str, config: &crate::core::config::I
 
     Ok(())
 }
-
-/// 生成 Windows 7 专用的无人值守配置
-/// 
-/// Win7 的 OOBE 配置与 Win10/11 有显著差异：
-/// - 不支持 HideOnlineAccountScreens
-/// - 不支持 HideWirelessSetupInOOBE  
-/// - 不支持 SkipMachineOOBE / SkipUserOOBE
-/// - 不支持 HideLocalAccountScreen
-/// - 不支持 HideOEMRegistrationScreen（家庭版不支持）
-/// - 需要设置 NetworkLocation 来跳过网络位置选择
-fn generate_win7_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    // Win7 使用最小化的OOBE配置以确保兼容所有版本（包括家庭版）
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
-}
-
-/// 生成 Windows 8/8.1 专用的无人值守配置
-/// 
-/// Win8/8.1 支持部分 Win10 的选项：
-/// - 支持 HideLocalAccountScreen
-/// - 不支持 HideOnlineAccountScreens
-/// - 不支持 HideWirelessSetupInOOBE
-/// - 不支持 SkipMachineOOBE / SkipUserOOBE
-fn generate_win8_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <ProtectYourPC>3</ProtectYourPC>
-                <NetworkLocation>Home</NetworkLocation>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
-}
-
-/// 生成 Windows 10/11 无人值守配置
-/// 
-/// 通过预置 LocalAccount + 以下 OOBE 选项跳过账户/隐私等屏幕：
-/// - HideLocalAccountScreen
-/// - HideOnlineAccountScreens
-/// - HideWirelessSetupInOOBE
-///
-/// 注：SkipMachineOOBE / SkipUserOOBE 已被微软弃用且在 Win11 上不可靠，故不再使用。
-fn generate_win10_unattend_xml(username: &str, scripts_dir: &str, first_logon_commands: &str, arch: &str) -> String {
-    format!(r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="specialize">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <ComputerName>*</ComputerName>
-        </component>
-        <component name="Microsoft-Windows-Deployment" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <RunSynchronous>
-                <RunSynchronousCommand wcm:action="add">
-                    <Order>1</Order>
-                    <Path>cmd /c if exist %SystemDrive%\{scripts_dir}\deploy.bat call %SystemDrive%\{scripts_dir}\deploy.bat</Path>
-                    <Description>Run custom deploy script</Description>
-                </RunSynchronousCommand>
-            </RunSynchronous>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="{arch}" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{username}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{username}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <LogonCount>1</LogonCount>
-                <Username>{username}</Username>
-            </AutoLogon>
-            <FirstLogonCommands>{first_logon_commands}
-            </FirstLogonCommands>
-        </component>
-    </settings>
-</unattend>"#, arch = arch, scripts_dir = scripts_dir, username = username, first_logon_commands = first_logon_commands)
-}
@@ -3,9 +3,10 @@ use std::path::Path;
 use std::path::PathBuf;
 
 use crate::tr;
-use crate::utils::command::new_command;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
+use lr_core::diskpart_output;
 
 pub struct BootManager {
     bcdedit_path: String,
@@ -32,6 +33,37 @@ impl BootManager {
         }
     }
 
+    /// 运行一段 diskpart 查询脚本，返回标准输出
+    fn diskpart_query(script: &str, file_name: &str) -> Result<String> {
+        let script_path = Self::reliable_temp_dir().join(file_name);
+        std::fs::write(&script_path, script)?;
+
+        let output = command::run("diskpart", ["/s", &script_path.to_string_lossy()])?;
+        let _ = std::fs::remove_file(&script_path);
+        Ok(gbk_to_utf8(&output.stdout))
+    }
+
+    /// 在指定磁盘上找 ESP 的分区号（逐个 `detail partition` 比对类型 GUID，
+    /// 不看本地化的类型列）
+    fn find_esp_partition_number(disk: u32) -> Result<Option<u32>> {
+        let list = Self::diskpart_query(
+            &format!("select disk {}\nlist partition\n", disk),
+            "list_part.txt",
+        )?;
+        let rows = diskpart_output::parse_list_partition(&list);
+
+        for number in diskpart_output::esp_candidates(&rows) {
+            let detail = Self::diskpart_query(
+                &format!("select disk {}\nselect partition {}\ndetail partition\n", disk, number),
+                "detail_part.txt",
+            )?;
+            if diskpart_output::parse_detail_partition(&detail).is_esp() {
+                return Ok(Some(number));
+            }
+        }
+        Ok(None)
+    }
+
     /// 查找目标 Windows 分区所在磁盘的 ESP 分区
     pub fn find_esp_on_same_disk(&self, windows_partition: &str) -> Result<String> {
         log::info!("查找 {} 所在磁盘的 ESP 分区...", windows_partition);
@@ -40,89 +72,27 @@ impl BootManager {
             .trim_end_matches(':')
             .trim_end_matches('\\');
 
-        // Step 1: 使用 diskpart 获取该分区所在的磁盘号
-        let script1 = format!(
-            r#"select volume {}
-detail volume
-"#,
-            drive_letter
-        );
-
-        let script1_path = Self::reliable_temp_dir().join("find_disk.txt");
-        std::fs::write(&script1_path, &script1)?;
-
-        let output = new_command("diskpart")
-            .args(["/s", &script1_path.to_string_lossy()])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
+        // Step 1: 选中该卷后 `list disk` 用 `*` 标出它所在的磁盘
+        let stdout = Self::diskpart_query(
+            &format!("select volume {}\nlist disk\n", drive_letter),
+            "find_disk.txt",
+        )?;
         log::debug!("查找磁盘号:\n{}", stdout);
 
-        let mut disk_num: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("disk") || line_lower.contains("磁盘") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("disk") || *part == "磁盘" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                disk_num = Some(num);
-                                break;
-                            }
-                        }
-                    }
-                }
-            }
-        }
-
-        let disk_num = disk_num.ok_or_else(|| anyhow::anyhow!("{}", tr!("无法确定分区所在磁盘")))?;
+        let disk_num = diskpart_output::parse_list_disk(&stdout)
+            .into_iter()
+            .find(|d| d.selected)
+            .map(|d| d.number)
+            .ok_or_else(|| anyhow::anyhow!("{}", tr!("无法确定分区所在磁盘")))?;
         log::info!("目标分区在磁盘 {}", disk_num);
 
-        // Step 2: 查找该磁盘上的 ESP 分区
-        let script2 = format!(
-            r#"select disk {}
-list partition
-"#,
-            disk_num
-        );
-
-        let script2_path = Self::reliable_temp_dir().join("list_part.txt");
-        std::fs::write(&script2_path, &script2)?;
-
-        let output = new_command("diskpart")
-            .args(["/s", &script2_path.to_string_lossy()])
-            .output()?;
-
-        let stdout = gbk_to_utf8(&output.stdout);
-        log::debug!("分区列表:\n{}", stdout);
-
-        let mut esp_partition: Option<usize> = None;
-        for line in stdout.lines() {
-            let line_lower = line.to_lowercase();
-            if line_lower.contains("system") || line_lower.contains("系统") {
-                let parts: Vec<&str> = line.split_whitespace().collect();
-                for (i, part) in parts.iter().enumerate() {
-                    if part.to_lowercase().contains("partition") || *part == "分区" {
-                        if let Some(num_str) = parts.get(i + 1) {
-                            if let Ok(num) = num_str.parse::<usize>() {
-                                esp_partition = Some(num);
-                                log::info!("找到 ESP: 分区 {}", num);
-                                break;
-                            }
-                        }
-                    }
-                }
-                if esp_partition.is_some() {
-                    break;
-                }
-            }
-        }
-
-        let esp_partition = esp_partition.ok_or_else(|| anyhow::anyhow!("{}", tr!("未找到 ESP 分区")))?;
+        // Step 2: 按分区类型 GUID 查找该磁盘上的 ESP 分区
+        let esp_partition = Self::find_esp_partition_number(disk_num)?
+            .ok_or_else(|| anyhow::anyhow!("{}", tr!("未找到 ESP 分区")))?;
+        log::info!("找到 ESP: 分区 {}", esp_partition);
 
         // Step 3: 为 ESP 分配盘符
-        let _ = new_command("mountvol").args(["S:", "/d"]).output();
+        let _ = command::run("mountvol", ["S:", "/d"]);
         std::thread::sleep(std::time::Duration::from_millis(200));
 
         let script3 = format!(
@@ -136,9 +106,7 @@ assign letter=S
         let script3_path = Self::reliable_temp_dir().join("assign_esp.txt");
         std::fs::write(&script3_path, &script3)?;
 
-        let output = new_command("diskpart")
-            .args(["/s", &script3_path.to_string_lossy()])
-            .output()?;
+        let output = command::run("diskpart", ["/s", &script3_path.to_string_lossy()])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         log::debug!("分配 ESP 盘符:\n{}", stdout);
@@ -165,7 +133,7 @@ assign letter=S
 
         // 方法2: 使用 mountvol /s 挂载 ESP 到 S:
         log::info!("尝试使用 mountvol /s 挂载 ESP");
-        let output = new_command("mountvol").args(["S:", "/s"]).output();
+        let output = command::run("mountvol", ["S:", "/s"]);
         if output.is_ok() {
             std::thread::sleep(std::time::Duration::from_millis(500));
             if Path::new("S:\\").exists() {
@@ -182,57 +150,28 @@ assign letter=S
         log::info!("使用 diskpart 查找 ESP");
 
         for disk in 0..4 {
-            let script = format!(
-                r#"select disk {}
-list partition
-"#,
-                disk
-            );
-
-            let script_path = Self::reliable_temp_dir().join("check_disk.txt");
-            std::fs::write(&script_path, &script)?;
-
-            let output = new_command("diskpart")
-                .args(["/s", script_path.to_str().unwrap()])
-                .output()?;
-
-            let stdout = gbk_to_utf8(&output.stdout);
+            let Some(part_num) = Self::find_esp_partition_number(disk)? else {
+                continue;
+            };
 
-            for line in stdout.lines() {
-                let line_lower = line.to_lowercase();
-                if line_lower.contains("system") || line_lower.contains("系统") {
-                    let parts: Vec<&str> = line.split_whitespace().collect();
-                    for (i, part) in parts.iter().enumerate() {
-                        if part.to_lowercase().contains("partition") || *part == "分区" {
-                            if let Some(num_str) = parts.get(i + 1) {
-                                if let Ok(part_num) = num_str.parse::<usize>() {
-                                    let assign_script = format!(
-                                        r#"select disk {}
+            let assign_script = format!(
+                r#"select disk {}
 select partition {}
 assign letter=S
 "#,
-                                        disk, part_num
-                                    );
+                disk, part_num
+            );
 
-                                    let assign_path =
-                                        Self::reliable_temp_dir().join("assign_esp2.txt");
-                                    std::fs::write(&assign_path, &assign_script)?;
+            let assign_path = Self::reliable_temp_dir().join("assign_esp2.txt");
+            std::fs::write(&assign_path, &assign_script)?;
 
-                                    let _ = new_command("diskpart")
-                                        .args(["/s", &assign_path.to_string_lossy()])
-                                        .output();
+            let _ = command::run("diskpart", ["/s", &assign_path.to_string_lossy()]);
 
-                                    std::thread::sleep(std::time::Duration::from_millis(500));
+            std::thread::sleep(std::time::Duration::from_millis(500));
 
-                                    if Path::new("S:\\").exists() {
-                                        log::info!("找到 ESP: 磁盘 {} 分区 {}", disk, part_num);
-                                        return Ok("S:".to_string());
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
+            if Path::new("S:\\").exists() {
+                log::info!("找到 ESP: 磁盘 {} 分区 {}", disk, part_num);
+                return Ok("S:".to_string());
             }
         }
 
@@ -243,9 +182,7 @@ assign letter=S
     pub fn delete_current_boot_entry(&self) -> Result<()> {
         log::info!("删除当前PE引导项...");
 
-        let output = new_command(&self.bcdedit_path)
-            .args(["/delete", "{current}", "/f"])
-            .output()?;
+        let output = command::run(&self.bcdedit_path, ["/delete", "{current}", "/f"])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         let stderr = gbk_to_utf8(&output.stderr);
@@ -325,17 +262,15 @@ assign letter=S
                         windows_path,
                         esp_letter
                     );
-                    let output = new_command(&self.bcdboot_path)
-                        .args([
-                            &windows_path,
-                            "/s",
-                            &esp_letter,
-                            "/f",
-                            "UEFI",
-                            "/l",
-                            "zh-cn",
-                        ])
-                        .output()?;
+                    let output = command::run(&self.bcdboot_path, [
+                        &windows_path,
+                        "/s",
+                        &esp_letter,
+                        "/f",
+                        "UEFI",
+                        "/l",
+                        "zh-cn",
+                    ])?;
 
                     let stdout = gbk_to_utf8(&output.stdout);
                     let stderr = gbk_to_utf8(&output.stderr);
@@ -345,17 +280,15 @@ assign letter=S
 
                     if !output.status.success() {
                         log::info!("重试：使用 ALL 模式");
-                        let output = new_command(&self.bcdboot_path)
-                            .args([
-                                &windows_path,
-                                "/s",
-                                &esp_letter,
-                                "/f",
-                                "ALL",
-                                "/l",
-                                "zh-cn",
-                            ])
-                            .output()?;
+                        let output = command::run(&self.bcdboot_path, [
+                            &windows_path,
+                            "/s",
+                            &esp_letter,
+                            "/f",
+                            "ALL",
+                            "/l",
+                            "zh-cn",
+                        ])?;
 
                         let stdout = gbk_to_utf8(&output.stdout);
                         let stderr = gbk_to_utf8(&output.stderr);
@@ -364,9 +297,7 @@ assign letter=S
 
                         if !output.status.success() {
                             log::info!("重试：不指定引导类型");
-                            let output = new_command(&self.bcdboot_path)
-                                .args([&windows_path, "/s", &esp_letter, "/l", "zh-cn"])
-                                .output()?;
+                            let output = command::run(&self.bcdboot_path, [&windows_path, "/s", &esp_letter, "/l", "zh-cn"])?;
 
                             let stderr = gbk_to_utf8(&output.stderr);
                             if !output.status.success() {
@@ -395,9 +326,7 @@ assign letter=S
                 Err(e) => {
                     log::warn!("查找 ESP 失败: {}，尝试默认方式", e);
 
-                    let output = new_command(&self.bcdboot_path)
-                        .args([&windows_path, "/f", "UEFI", "/l", "zh-cn"])
-                        .output()?;
+                    let output = command::run(&self.bcdboot_path, [&windows_path, "/f", "UEFI", "/l", "zh-cn"])?;
 
                     let stdout = gbk_to_utf8(&output.stdout);
                     let stderr = gbk_to_utf8(&output.stderr);
@@ -416,9 +345,7 @@ assign letter=S
             let bootsect_path = get_bin_dir().join("bootsect.exe");
             if bootsect_path.exists() {
                 log::info!("使用 bootsect 写入引导扇区");
-                let output = new_command(&bootsect_path)
-                    .args(["/nt60", windows_partition, "/mbr"])
-                    .output()?;
+                let output = command::run(&bootsect_path, ["/nt60", windows_partition, "/mbr"])?;
 
                 let stdout = gbk_to_utf8(&output.stdout);
                 let stderr = gbk_to_utf8(&output.stderr);
@@ -426,9 +353,7 @@ assign letter=S
                 log::debug!("bootsect stderr: {}", stderr);
             }
 
-            let output = new_command(&self.bcdboot_path)
-                .args([&windows_path, "/f", "BIOS", "/l", "zh-cn"])
-                .output()?;
+            let output = command::run(&self.bcdboot_path, [&windows_path, "/f", "BIOS", "/l", "zh-cn"])?;
 
             let stdout = gbk_to_utf8(&output.stdout);
             let stderr = gbk_to_utf8(&output.stderr);
@@ -437,9 +362,7 @@ assign letter=S
             log::debug!("bcdboot stderr: {}", stderr);
 
             if !output.status.success() {
-                let output = new_command(&self.bcdboot_path)
-                    .args([&windows_path, "/l", "zh-cn"])
-                    .output()?;
+                let output = command::run(&self.bcdboot_path, [&windows_path, "/l", "zh-cn"])?;
 
                 let stderr = gbk_to_utf8(&output.stderr);
                 if !output.status.success() {
@@ -43,6 +43,8 @@ pub struct InstallConfig {
     pub restore_drivers: bool,
     /// 驱动操作模式: 0=无, 1=仅保存, 2=自动导入
     pub driver_action_mode: DriverActionMode,
+    /// 仅导入与本机硬件匹配的驱动（按硬件 ID 排名为每个设备选一个 INF）
+    pub driver_match_devices: bool,
     /// 立即重启
     pub auto_reboot: bool,
     /// 原系统引导GUID（用于删除旧引导项）
@@ -85,6 +87,8 @@ pub struct InstallConfig {
     pub volume_label: String,
     /// 自定义无人值守文件（数据目录下的相对文件名，空=使用内置生成）
     pub custom_unattend_file: String,
+    /// 自定义无人值守文件与内置生成的应答合并（false=原样使用用户文件）
+    pub custom_unattend_merge: bool,
     
     // Win7 专用选项
     /// Win7 UEFI 补丁（使用 UefiSeven）
@@ -115,6 +119,12 @@ pub struct InstallConfig {
 
     /// 界面语言代码（如 "en-US"），由正常系统端随重启写入；空=简体中文。
     pub language: String,
+
+    /// 内置无人值守的定制项（INI `[Unattend]` 节；计算机名已由正常系统端展开）。
+    pub unattend: lr_core::unattend::UnattendCustomization,
+
+    /// 目标分区的磁盘/分区标识（INI `[Target]` 节）。旧版本写的配置没有此节，`is_known()` 为假。
+    pub target_identity: lr_core::target_identity::TargetIdentity,
 }
 
 impl InstallConfig {
@@ -203,6 +213,9 @@ impl ConfigFileManager {
     /// 临时数据目录名
     const DATA_DIR: &'static str = "LetRecovery_Data";
 
+    /// 报告目录名（安装后保留，不随数据目录清理）
+    const REPORTS_DIR: &'static str = "LetRecovery_Reports";
+
     /// 查找包含安装标记文件的分区
     pub fn find_install_marker_partition() -> Option<String> {
         for letter in ['C', 'D', 'E', 'F', 'G', 'H', 'I', 'J', 'K'] {
@@ -280,6 +293,14 @@ impl ConfigFileManager {
             }
         }
 
+        // 安装标记在目标分区上，格式化后就没了：数据目录里有未完成的安装日志也算安装
+        if let Some(data_part) = Self::find_data_partition() {
+            if Self::has_install_journal(&data_part) {
+                log::info!("未找到安装标记，但数据目录中有安装日志（上次安装被中断）");
+                return Some(OperationType::Install);
+            }
+        }
+
         // 再检查备份标记
         if Self::find_backup_marker_partition().is_some() {
             if let Some(data_part) = Self::find_data_partition() {
@@ -356,6 +377,34 @@ impl ConfigFileManager {
         Self::deserialize_install_config(&content)
     }
 
+    /// 安装配置文件内容的 SHA-256（安装日志据此判断配置是否改过）
+    pub fn install_config_hash(data_partition: &str) -> Option<String> {
+        let config_path = format!(
+            "{}\\{}\\{}",
+            data_partition,
+            Self::DATA_DIR,
+            Self::INSTALL_CONFIG
+        );
+        lr_core::hash::sha256_file(&config_path, |_| {}).ok()
+    }
+
+    /// 数据目录中是否有安装日志（且安装配置仍在）
+    pub fn has_install_journal(data_partition: &str) -> bool {
+        let data_dir = Self::get_data_dir(data_partition);
+        Path::new(&data_dir)
+            .join(lr_core::install_journal::JOURNAL_FILE)
+            .exists()
+            && Path::new(&data_dir).join(Self::INSTALL_CONFIG).exists()
+    }
+
+    /// 格式化后重新写入安装标记，中断重启后仍能找到目标分区
+    pub fn restore_install_marker(partition: &str) {
+        let marker_path = format!("{}\\{}", partition, Self::INSTALL_MARKER);
+        if let Err(e) = std::fs::write(&marker_path, "LetRecovery Install Marker") {
+            log::warn!("重新写入安装标记失败 {}: {}", marker_path, e);
+        }
+    }
+
     /// 读取备份配置
     pub fn read_backup_config(data_partition: &str) -> Result<BackupConfig> {
         let config_path = format!(
@@ -375,6 +424,11 @@ impl ConfigFileManager {
         format!("{}\\{}", partition, Self::DATA_DIR)
     }
 
+    /// 获取报告目录路径
+    pub fn get_reports_dir(partition: &str) -> String {
+        format!("{}\\{}", partition, Self::REPORTS_DIR)
+    }
+
     /// 获取PE目录路径
     pub fn get_pe_dir(partition: &str) -> String {
         format!("{}\\{}", partition, Self::PE_DIR)
@@ -454,6 +508,7 @@ impl ConfigFileManager {
                         let mode_value: u8 = value.parse().unwrap_or(0);
                         config.driver_action_mode = DriverActionMode::from_u8(mode_value);
                     }
+                    "DriverMatchDevices" => config.driver_match_devices = value.parse().unwrap_or(false),
                     "AutoReboot" => config.auto_reboot = value.parse().unwrap_or(false),
                     "OriginalGUID" => config.original_guid = value.to_string(),
                     "VolumeIndex" => config.volume_index = value.parse().unwrap_or(1),
@@ -492,6 +547,7 @@ impl ConfigFileManager {
                     "CustomUsername" => config.custom_username = value.to_string(),
                     "VolumeLabel" => config.volume_label = value.to_string(),
                     "CustomUnattendFile" => config.custom_unattend_file = value.to_string(),
+                    "CustomUnattendMerge" => config.custom_unattend_merge = value.parse().unwrap_or(false),
                     "Win7UefiPatch" => config.win7_uefi_patch = value.parse().unwrap_or(false),
                     "Win7InjectUsb3Driver" => config.win7_inject_usb3_driver = value.parse().unwrap_or(false),
                     "Win7InjectNvmeDriver" => config.win7_inject_nvme_driver = value.parse().unwrap_or(false),
@@ -499,6 +555,8 @@ impl ConfigFileManager {
                     "Win7FixStorageBsod" => config.win7_fix_storage_bsod = value.parse().unwrap_or(false),
                     "XpInjectUsb3Driver" => config.xp_inject_usb3_driver = value.parse().unwrap_or(false),
                     "XpInjectNvmeDriver" => config.xp_inject_nvme_driver = value.parse().unwrap_or(false),
+                    k if config.unattend.set_ini_key(k, value) => {}
+                    k if config.target_identity.set_ini_key(k, value) => {}
                     _ => {}
                 }
             }
@@ -4,9 +4,11 @@ use windows::core::PCWSTR;
 use windows::Win32::Storage::FileSystem::{GetDiskFreeSpaceExW, GetDriveTypeW, GetVolumeInformationW};
 
 use crate::tr;
-use crate::utils::command::new_command;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 use crate::utils::path::get_bin_dir;
+use lr_core::diskpart_output;
+use lr_core::diskpart_script::DiskpartScript;
 
 const DRIVE_FIXED: u32 = 3;
 
@@ -175,19 +177,23 @@ impl DiskManager {
     }
 
     /// 使用 diskpart 获取分区信息（备用方法）
+    ///
+    /// 选中卷后 `list disk` / `list partition` 会用 `*` 标出它所在的磁盘和分区，
+    /// 磁盘表的 Gpt 列直接给出分区表类型；按列位置解析，与 PE 语言无关。
     fn get_partition_style_diskpart(drive: &str) -> PartitionDetail {
+        let unknown = PartitionDetail {
+            style: PartitionStyle::Unknown,
+            disk_number: None,
+            partition_number: None,
+        };
         let letter = drive.chars().next().unwrap_or('C');
-        let script = format!("select volume {}\ndetail volume", letter);
+        let script = format!("select volume {}\nlist disk\nlist partition", letter);
 
         let temp_dir = Self::reliable_temp_dir();
         let script_path = temp_dir.join("dp_style.txt");
 
         if std::fs::write(&script_path, &script).is_err() {
-            return PartitionDetail {
-                style: PartitionStyle::Unknown,
-                disk_number: None,
-                partition_number: None,
-            };
+            return unknown;
         }
 
         let script_path_str = match script_path.to_str() {
@@ -195,110 +201,39 @@ impl DiskManager {
             None => {
                 log::error!("[disk] 临时脚本路径包含非 UTF-8 字符: {}", script_path.display());
                 let _ = std::fs::remove_file(&script_path);
-                return PartitionDetail {
-                    style: PartitionStyle::Unknown,
-                    disk_number: None,
-                    partition_number: None,
-                };
+                return unknown;
             }
         };
 
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()
-        {
+        let output = match command::run(&get_diskpart_path(), ["/s", script_path_str]) {
             Ok(o) => o,
             Err(_) => {
                 let _ = std::fs::remove_file(&script_path);
-                return PartitionDetail {
-                    style: PartitionStyle::Unknown,
-                    disk_number: None,
-                    partition_number: None,
-                };
+                return unknown;
             }
         };
 
         let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout);
-
-        let mut disk_num: Option<u32> = None;
-        let mut part_num: Option<u32> = None;
-
-        for line in stdout.lines() {
-            let line_upper = line.to_uppercase();
-            if (line_upper.contains("磁盘") || line_upper.contains("DISK"))
-                && !line_upper.contains("磁盘 ID")
-                && !line_upper.contains("DISK ID")
-            {
-                if let Some(num) = line
-                    .split_whitespace()
-                    .find(|s| s.parse::<u32>().is_ok())
-                {
-                    disk_num = num.parse().ok();
-                }
-            }
-            if line_upper.contains("分区") || line_upper.contains("PARTITION") {
-                if let Some(num) = line
-                    .split_whitespace()
-                    .find(|s| s.parse::<u32>().is_ok())
-                {
-                    part_num = num.parse().ok();
-                }
-            }
+        if !diskpart_output::succeeded(&output) {
+            return unknown;
         }
+        let stdout = gbk_to_utf8(&output.stdout);
 
-        let style = if let Some(num) = disk_num {
-            Self::get_disk_partition_style(num)
-        } else {
-            PartitionStyle::Unknown
-        };
+        let disk = diskpart_output::parse_list_disk(&stdout)
+            .into_iter()
+            .find(|d| d.selected);
+        let partition = diskpart_output::parse_list_partition(&stdout)
+            .into_iter()
+            .find(|p| p.selected);
 
         PartitionDetail {
-            style,
-            disk_number: disk_num,
-            partition_number: part_num,
-        }
-    }
-
-    /// 获取指定磁盘的分区表类型
-    fn get_disk_partition_style(disk_number: u32) -> PartitionStyle {
-        let script = format!("select disk {}\ndetail disk", disk_number);
-        let temp_dir = Self::reliable_temp_dir();
-        let script_path = temp_dir.join("dp_disk_style.txt");
-
-        if std::fs::write(&script_path, &script).is_err() {
-            return PartitionStyle::Unknown;
-        }
-
-        let script_path_str = match script_path.to_str() {
-            Some(s) => s,
-            None => {
-                log::error!("[disk] 临时脚本路径包含非 UTF-8 字符: {}", script_path.display());
-                let _ = std::fs::remove_file(&script_path);
-                return PartitionStyle::Unknown;
-            }
-        };
-
-        let output = match new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()
-        {
-            Ok(o) => o,
-            Err(_) => {
-                let _ = std::fs::remove_file(&script_path);
-                return PartitionStyle::Unknown;
-            }
-        };
-
-        let _ = std::fs::remove_file(&script_path);
-        let stdout = gbk_to_utf8(&output.stdout).to_uppercase();
-
-        if stdout.contains("GPT") {
-            PartitionStyle::GPT
-        } else if stdout.contains("MBR") {
-            PartitionStyle::MBR
-        } else {
-            PartitionStyle::Unknown
+            style: match &disk {
+                Some(d) if d.gpt => PartitionStyle::GPT,
+                Some(_) => PartitionStyle::MBR,
+                None => PartitionStyle::Unknown,
+            },
+            disk_number: disk.map(|d| d.number),
+            partition_number: partition.map(|p| p.number),
         }
     }
 
@@ -334,9 +269,7 @@ impl DiskManager {
         
         log::info!("执行命令: cmd /c {}", cmd_args);
 
-        let output = new_command("cmd")
-            .args(["/c", &cmd_args])
-            .output()?;
+        let output = command::run("cmd", ["/c", &cmd_args])?;
 
         let stdout = gbk_to_utf8(&output.stdout);
         let stderr = gbk_to_utf8(&output.stderr);
@@ -382,9 +315,7 @@ impl DiskManager {
         }
 
         // 检查固件类型
-        let output = new_command("cmd")
-            .args(["/c", "bcdedit /enum firmware"])
-            .output();
+        let output = command::run("cmd", ["/c", "bcdedit /enum firmware"]);
 
         if let Ok(output) = output {
             let stdout = gbk_to_utf8(&output.stdout);
@@ -524,10 +455,10 @@ impl DiskManager {
     fn delete_partition_by_letter(letter: char) -> Result<()> {
         log::info!("[CLEANUP] 删除分区 {}:", letter);
 
-        let script_content = format!(
-            "select volume {}\ndelete partition override",
-            letter
-        );
+        let script_content = DiskpartScript::new()
+            .select_volume_letter(letter)
+            .delete_partition(true)
+            .build()?;
 
         let temp_dir = Self::reliable_temp_dir();
         let script_path = temp_dir.join("lr_delete_part.txt");
@@ -536,21 +467,15 @@ impl DiskManager {
         let script_path_str = script_path
             .to_str()
             .ok_or_else(|| anyhow::anyhow!("临时脚本路径包含非 UTF-8 字符"))?;
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path_str])?;
 
         let _ = std::fs::remove_file(&script_path);
 
         let output_text = gbk_to_utf8(&output.stdout);
         log::info!("[CLEANUP] Diskpart 删除输出: {}", output_text);
 
-        // 检查是否有错误（但不要太严格，删除成功也可能包含一些警告）
-        let output_lower = output_text.to_lowercase();
-        let has_error = (output_lower.contains("error") || output_lower.contains("错误"))
-            && !output_lower.contains("成功") && !output_lower.contains("successfully");
-        
-        if has_error {
+        // 成败只看退出码
+        if !diskpart_output::succeeded(&output) {
             anyhow::bail!("删除分区失败: {}", output_text);
         }
 
@@ -587,13 +512,21 @@ impl DiskManager {
         let size_before = Self::get_partition_size_mb(target_letter);
         log::info!("[CLEANUP] 扩展前目标分区大小: {:?} MB", size_before);
 
+        // 改动分区表前先备份（找不到其他磁盘时退回目标分区）
+        let fallback = std::path::PathBuf::from(format!("{}:\\", target_letter));
+        match lr_core::partition_backup::backup_physical_drive(disk_num, "cleanup_partition", Some(&fallback)) {
+            Ok(Some(dir)) => log::info!("[CLEANUP] 原分区表已备份到 {}", dir.display()),
+            Ok(None) => {}
+            Err(e) => anyhow::bail!("备份分区表失败，未做任何改动：{}", e),
+        }
+
         // Step 1: 删除分区
         log::info!("[CLEANUP] Step 1: 删除分区 {}:", auto_letter);
         
-        let delete_script = format!(
-            "select volume {}\ndelete partition override",
-            auto_letter
-        );
+        let delete_script = DiskpartScript::new()
+            .select_volume_letter(auto_letter)
+            .delete_partition(true)
+            .build()?;
 
         let temp_dir = Self::reliable_temp_dir();
         let script_path = temp_dir.join("lr_delete_part.txt");
@@ -602,22 +535,15 @@ impl DiskManager {
         let script_path_str = script_path
             .to_str()
             .ok_or_else(|| anyhow::anyhow!("临时脚本路径包含非 UTF-8 字符"))?;
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path_str])?;
 
         let _ = std::fs::remove_file(&script_path);
 
         let output_text = gbk_to_utf8(&output.stdout);
         log::info!("[CLEANUP] 删除分区输出: {}", output_text);
 
-        // 检查删除是否成功
-        let output_lower = output_text.to_lowercase();
-        let delete_failed = (output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed"))
-            && !output_lower.contains("成功") && !output_lower.contains("successfully");
-            
-        if delete_failed {
+        // 检查删除是否成功（只看退出码）
+        if !diskpart_output::succeeded(&output) {
             anyhow::bail!("删除分区失败: {}", output_text);
         }
 
@@ -708,9 +634,7 @@ impl DiskManager {
                 let _ = std::fs::remove_file(&script_path);
                 return;
             };
-            let output = new_command(&get_diskpart_path())
-                .args(["/s", script_path_str])
-                .output();
+            let output = command::run(&get_diskpart_path(), ["/s", script_path_str]);
 
             let _ = std::fs::remove_file(&script_path);
 
@@ -725,7 +649,10 @@ impl DiskManager {
     /// 先尝试通过卷字母扩展，如果失败则尝试通过磁盘号和分区号扩展
     fn try_extend_volume_enhanced(letter: char, disk_num: u32) -> Result<()> {
         // 方法1：通过卷字母扩展（标准方法）
-        let extend_script = format!("select volume {}\nextend", letter);
+        let extend_script = DiskpartScript::new()
+            .select_volume_letter(letter)
+            .extend(None)
+            .build()?;
         
         let temp_dir = Self::reliable_temp_dir();
         let script_path = temp_dir.join("lr_extend.txt");
@@ -734,46 +661,30 @@ impl DiskManager {
         let script_path_str = script_path
             .to_str()
             .ok_or_else(|| anyhow::anyhow!("临时脚本路径包含非 UTF-8 字符"))?;
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path_str])?;
 
         let _ = std::fs::remove_file(&script_path);
 
         let output_text = gbk_to_utf8(&output.stdout);
-        let output_lower = output_text.to_lowercase();
 
         log::info!("[CLEANUP] diskpart extend (by volume) 输出: {}", output_text);
 
-        // 检查是否成功 - 注意要排除包含失败/错误的情况
-        let has_success = output_lower.contains("成功") || output_lower.contains("successfully") 
-            || output_lower.contains("extended the volume");
-        let has_error = output_lower.contains("error") || output_lower.contains("错误")
-            || output_lower.contains("失败") || output_lower.contains("failed")
-            || output_lower.contains("没有可用") || output_lower.contains("no usable")
-            || output_lower.contains("not enough") || output_lower.contains("无法");
-            
-        if has_success && !has_error {
+        // 成败只看退出码（各语言 PE 的提示文字不同）
+        if diskpart_output::succeeded(&output) {
             return Ok(());
         }
 
-        // 检查是否有明确的错误：没有可用的未分配空间
-        if output_lower.contains("没有可用") || output_lower.contains("no usable") 
-            || output_lower.contains("not enough") || output_lower.contains("空间不足") {
-            // 没有可用的未分配空间，直接失败
-            anyhow::bail!("没有可用的相邻未分配空间: {}", output_text);
-        }
-
         // 方法2：尝试通过磁盘号扩展（备用方法）
         log::info!("[CLEANUP] 尝试备用方法：通过磁盘号和分区号扩展");
         
         // 先获取分区号
         let detail = Self::get_partition_style(&format!("{}:", letter));
         if let Some(part_num) = detail.partition_number {
-            let extend_script2 = format!(
-                "select disk {}\nselect partition {}\nextend",
-                disk_num, part_num
-            );
+            let extend_script2 = DiskpartScript::new()
+                .select_disk(disk_num)
+                .select_partition(part_num)
+                .extend(None)
+                .build()?;
             
             let script_path2 = temp_dir.join("lr_extend2.txt");
             std::fs::write(&script_path2, &extend_script2)?;
@@ -781,40 +692,24 @@ impl DiskManager {
             let script_path2_str = script_path2
                 .to_str()
                 .ok_or_else(|| anyhow::anyhow!("临时脚本路径包含非 UTF-8 字符"))?;
-            let output2 = new_command(&get_diskpart_path())
-                .args(["/s", script_path2_str])
-                .output()?;
+            let output2 = command::run(&get_diskpart_path(), ["/s", script_path2_str])?;
 
             let _ = std::fs::remove_file(&script_path2);
 
             let output_text2 = gbk_to_utf8(&output2.stdout);
-            let output_lower2 = output_text2.to_lowercase();
 
             log::info!("[CLEANUP] diskpart extend (by partition) 输出: {}", output_text2);
 
-            let has_success2 = output_lower2.contains("成功") || output_lower2.contains("successfully")
-                || output_lower2.contains("extended the volume");
-            let has_error2 = output_lower2.contains("error") || output_lower2.contains("错误")
-                || output_lower2.contains("失败") || output_lower2.contains("failed")
-                || output_lower2.contains("没有可用") || output_lower2.contains("no usable");
-                
-            if has_success2 && !has_error2 {
+            if diskpart_output::succeeded(&output2) {
                 return Ok(());
             }
-            
-            // 备用方法也失败了，返回备用方法的错误信息
-            if has_error2 {
-                anyhow::bail!("extend 失败 (备用方法): {}", output_text2);
-            }
-        }
 
-        // 都失败了，返回第一次的错误
-        if has_error {
-            anyhow::bail!("extend 失败: {}", output_text);
+            // 备用方法也失败了，返回备用方法的错误信息
+            anyhow::bail!("extend 失败 (备用方法): {}", output_text2);
         }
 
-        // 不确定状态，假设失败
-        anyhow::bail!("extend 状态不确定: {}", output_text)
+        // 无法取得分区号，返回第一次的错误
+        anyhow::bail!("extend 失败: {}", output_text)
     }
 
     /// 无损扩大分区到指定大小（仅并入紧邻其后的未分配空间；不移动其它分区）。
@@ -837,10 +732,10 @@ impl DiskManager {
             Some(target_size_mb - current_mb)
         };
 
-        let script = match size_arg {
-            Some(delta) => format!("select volume {}\r\nextend size={}\r\n", letter, delta),
-            None => format!("select volume {}\r\nextend\r\n", letter),
-        };
+        let script = DiskpartScript::new()
+            .select_volume_letter(letter)
+            .extend(size_arg)
+            .build()?;
 
         let temp_dir = Self::reliable_temp_dir();
         let script_path = temp_dir.join("lr_expand.txt");
@@ -848,30 +743,25 @@ impl DiskManager {
         let script_path_str = script_path
             .to_str()
             .ok_or_else(|| anyhow::anyhow!("临时脚本路径包含非 UTF-8 字符"))?;
-        let output = new_command(&get_diskpart_path())
-            .args(["/s", script_path_str])
-            .output()?;
+        let output = command::run(&get_diskpart_path(), ["/s", script_path_str])?;
         let _ = std::fs::remove_file(&script_path);
 
         let text = gbk_to_utf8(&output.stdout);
         let lower = text.to_lowercase();
         log::info!("[EXPAND] diskpart 输出: {}", text);
 
-        let has_success = lower.contains("成功")
-            || lower.contains("successfully")
-            || lower.contains("extended the volume");
-        let no_space = lower.contains("没有可用")
-            || lower.contains("no usable")
-            || lower.contains("not enough")
-            || lower.contains("空间不足");
-
-        if no_space {
-            anyhow::bail!(
-                "{}",
-                tr!("C 盘后面没有相邻的未分配空间可并入。若要从后面的分区夺取空间，需要分区移动功能（暂未启用）。")
-            );
-        }
-        if !has_success {
+        // 成败只看退出码；失败原因的关键字只用来给出更友好的提示
+        if !diskpart_output::succeeded(&output) {
+            let no_space = lower.contains("没有可用")
+                || lower.contains("no usable")
+                || lower.contains("not enough")
+                || lower.contains("空间不足");
+            if no_space {
+                anyhow::bail!(
+                    "{}",
+                    tr!("C 盘后面没有相邻的未分配空间可并入。若要从后面的分区夺取空间，需要分区移动功能（暂未启用）。")
+                );
+            }
             anyhow::bail!("{}", tr!("扩容失败: {}", text));
         }
 
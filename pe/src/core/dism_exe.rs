@@ -9,18 +9,14 @@
 
 use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
-use std::process::{Command, Stdio};
 use std::sync::mpsc::Sender;
 
 use anyhow::{bail, Context, Result};
 
 use crate::tr;
+use crate::utils::command;
 use crate::utils::encoding::gbk_to_utf8;
 
-/// Windows CREATE_NO_WINDOW 标志，用于隐藏控制台窗口
-#[cfg(windows)]
-const CREATE_NO_WINDOW: u32 = 0x08000000;
-
 /// DISM 操作进度
 #[derive(Debug, Clone)]
 pub struct DismExeProgress {
@@ -94,18 +90,7 @@ impl DismExe {
         }
 
         // 最后尝试通过 PATH 查找（使用隐藏窗口）
-        let where_result = {
-            let mut cmd = Command::new("where");
-            cmd.arg("dism.exe");
-            
-            #[cfg(windows)]
-            {
-                use std::os::windows::process::CommandExt;
-                cmd.creation_flags(CREATE_NO_WINDOW);
-            }
-            
-            cmd.output()
-        };
+        let where_result = command::run("where", ["dism.exe"]);
         
         if let Ok(output) = where_result {
             let stdout = String::from_utf8_lossy(&output.stdout);
@@ -126,19 +111,6 @@ impl DismExe {
         )
     }
 
-    /// 创建隐藏窗口的 dism.exe 命令
-    fn create_command(&self) -> Command {
-        let mut cmd = Command::new(&self.dism_path);
-
-        #[cfg(windows)]
-        {
-            use std::os::windows::process::CommandExt;
-            cmd.creation_flags(CREATE_NO_WINDOW);
-        }
-
-        cmd
-    }
-
     /// 确保临时目录存在并返回路径
     ///
     /// 在 PE 环境中优先使用 X:\Windows\TEMP，
@@ -193,16 +165,11 @@ impl DismExe {
     ) -> Result<String> {
         log::info!("[DISM.EXE] 执行: {} {}", self.dism_path.display(), args.join(" "));
 
-        let mut child = self
-            .create_command()
-            .args(args)
-            .stdout(Stdio::piped())
-            .stderr(Stdio::piped())
-            .spawn()
-            .context(tr!("启动 dism.exe 失败"))?;
+        let mut child =
+            command::spawn(&self.dism_path, args).context(tr!("启动 dism.exe 失败"))?;
 
-        let stdout = child.stdout.take().context(tr!("无法获取 stdout"))?;
-        let stderr = child.stderr.take().context(tr!("无法获取 stderr"))?;
+        let stdout = child.take_stdout().context(tr!("无法获取 stdout"))?;
+        let stderr = child.take_stderr().context(tr!("无法获取 stderr"))?;
 
         // 读取并解析 stdout
         let progress_tx_clone = progress_tx.clone();
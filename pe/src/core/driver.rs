@@ -1,3 +1,120 @@
 //! Windows 驱动管理模块（实现已移入共享库 lr-core，此处再导出以保持调用方不变）。
 
+use std::path::{Path, PathBuf};
+
+use lr_core::driver_match::{offline_platform, scan_packages, stage_matched};
+use lr_core::driver_report::{log_dir, report_from_driver_store, ImportOutcome};
+use lr_core::hw_manifest::{coverage, inbox_packages, HardwareManifest};
+
 pub use lr_core::driver::*;
+
+/// 按本机硬件挑选驱动：为每个设备选一个最合适的 INF，暂存到 `<data_dir>\drivers_matched`
+/// 并返回该目录，供后续只注入选中的驱动。设备优先取正常系统端写入的硬件清单，
+/// 没有清单时在 PE 里枚举在位设备。失败时回退到完整驱动目录 `driver_path`。
+pub fn select_matched_drivers(driver_path: &str, target_root: &str, data_dir: &str) -> String {
+    let staging = format!("{}\\drivers_matched", data_dir);
+    let result = match HardwareManifest::load(Path::new(data_dir)) {
+        Some(manifest) => {
+            log::info!("[DriverMatch] 使用硬件清单: {} 个设备", manifest.devices.len());
+            let platform = offline_platform(Path::new(target_root));
+            stage_matched(
+                Path::new(driver_path),
+                &manifest.devices,
+                platform.as_ref(),
+                Path::new(&staging),
+            )
+            .map_err(anyhow::Error::from)
+        }
+        None => DriverManager::new().and_then(|m| {
+            m.stage_drivers_for_present_devices(
+                Path::new(driver_path),
+                Path::new(target_root),
+                Path::new(&staging),
+            )
+        }),
+    };
+    match result {
+        Ok(r) => {
+            log::info!(
+                "[DriverMatch] 选中 {} 个驱动，{} 个设备无匹配驱动",
+                r.selected_infs().len(),
+                r.unmatched.len()
+            );
+            staging
+        }
+        Err(e) => {
+            log::warn!("[DriverMatch] 按硬件匹配驱动失败，导入全部驱动: {}", e);
+            driver_path.to_string()
+        }
+    }
+}
+
+/// 对照硬件清单报告新系统里没有驱动的设备：`injected_dir`（本次注入的驱动目录，未导入驱动时
+/// 为 `None`）与目标系统自带 INF 都不匹配的设备，写入目标系统
+/// `Windows\Logs\LetRecovery\missing_drivers.txt`。没有硬件清单时返回 `None`，否则返回缺驱动设备数。
+pub fn report_missing_drivers(
+    injected_dir: Option<&str>,
+    target_root: &str,
+    data_dir: &str,
+) -> Option<usize> {
+    let manifest = HardwareManifest::load(Path::new(data_dir))?;
+    let platform = offline_platform(Path::new(target_root));
+    let repository = injected_dir
+        .map(|d| scan_packages(Path::new(d), platform.as_ref()))
+        .unwrap_or_default();
+    let inbox = inbox_packages(Path::new(target_root), platform.as_ref());
+    let report = coverage(&manifest.devices, &repository, &inbox);
+
+    for d in &report.missing {
+        log::warn!(
+            "[DriverMatch] 新系统缺少驱动: {} ({}) {}",
+            d.description,
+            d.class,
+            d.hardware_ids.first().map(String::as_str).unwrap_or("")
+        );
+    }
+    let log_dir = log_dir(Path::new(target_root));
+    let written = std::fs::create_dir_all(&log_dir)
+        .and_then(|_| std::fs::write(log_dir.join("missing_drivers.txt"), report.to_text()));
+    if let Err(e) = written {
+        log::warn!("[DriverMatch] 写入缺驱动报告失败: {}", e);
+    }
+    Some(report.missing.len())
+}
+
+/// 核实 `injected_dir` 里的驱动逐个进了目标系统的驱动存储，生成驱动导入报告（匹配设备取
+/// 硬件清单，没有清单时取 PE 里的在位设备），写入目标系统 `Windows\Logs\LetRecovery`
+/// 与数据分区的报告目录（数据目录安装后会被清理）。返回（成功数, 失败数）。
+pub fn write_import_report(
+    injected_dir: &str,
+    target_root: &str,
+    data_dir: &str,
+    reports_dir: &str,
+) -> (usize, usize) {
+    let infs = DriverManager::find_inf_files(Path::new(injected_dir)).unwrap_or_default();
+    let mut report =
+        report_from_driver_store(&infs, Path::new(injected_dir), Path::new(target_root));
+
+    let devices = match HardwareManifest::load(Path::new(data_dir)) {
+        Some(manifest) => manifest.devices,
+        None => DriverManager::new()
+            .and_then(|m| m.enumerate_present_devices())
+            .unwrap_or_default(),
+    };
+    let platform = offline_platform(Path::new(target_root));
+    report.annotate_devices(&devices, platform.as_ref());
+
+    for r in report
+        .results
+        .iter()
+        .filter(|r| r.outcome == ImportOutcome::Failed)
+    {
+        log::warn!("[DriverReport] 驱动未导入: {} - {}", r.inf, r.message);
+    }
+    for dir in [log_dir(Path::new(target_root)), PathBuf::from(reports_dir)] {
+        if let Err(e) = report.save(&dir) {
+            log::warn!("[DriverReport] 写入驱动导入报告失败 {:?}: {}", dir, e);
+        }
+    }
+    (report.succeeded(), report.failed())
+}
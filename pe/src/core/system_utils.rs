@@ -9,6 +9,7 @@
 use std::path::{Path, PathBuf};
 
 use crate::tr;
+use crate::utils::command;
 
 // =============================================================================
 // Windows 版本信息
@@ -112,15 +113,12 @@ pub fn get_windows_version() -> WindowsVersion {
     }
 
     // 尝试从注册表获取更详细的信息
-    if let Ok(output) = std::process::Command::new("reg")
-        .args([
-            "query",
-            r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion",
-            "/v",
-            "ProductName",
-        ])
-        .output()
-    {
+    if let Ok(output) = command::run("reg", [
+        "query",
+        r"HKLM\SOFTWARE\Microsoft\Windows NT\CurrentVersion",
+        "/v",
+        "ProductName",
+    ]) {
         let output_str = String::from_utf8_lossy(&output.stdout);
         if let Some(line) = output_str.lines().find(|l| l.contains("ProductName")) {
             if let Some(value) = line.split("REG_SZ").nth(1) {
@@ -163,10 +161,7 @@ pub fn get_offline_windows_version(system_root: &Path) -> Option<WindowsVersion>
         kernel_path.to_string_lossy()
     );
 
-    if let Ok(output) = std::process::Command::new("powershell")
-        .args(["-NoProfile", "-Command", &ps_script])
-        .output()
-    {
+    if let Ok(output) = command::run("powershell", ["-NoProfile", "-Command", &ps_script]) {
         let output_str = String::from_utf8_lossy(&output.stdout);
         
         // 简单解析 JSON
@@ -228,9 +223,7 @@ fn get_file_version_via_powershell(path: &Path) -> Option<(u32, u32, u32, u32)>
         path.to_string_lossy().replace('\'', "''")
     );
     
-    let output = std::process::Command::new("powershell")
-        .args(["-NoProfile", "-NonInteractive", "-Command", &ps_script])
-        .output()
+    let output = command::run("powershell", ["-NoProfile", "-NonInteractive", "-Command", &ps_script])
         .ok()?;
     
     if !output.status.success() {
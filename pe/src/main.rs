@@ -151,6 +151,11 @@ fn main() -> eframe::Result<()> {
         if ui_language.is_empty() { "zh-CN (默认)" } else { ui_language.as_str() }
     );
 
+    // 执行计划模式：只按配置列出将要执行的操作并写成 JSON，不做任何改动
+    if let Some(pos) = args.iter().position(|a| a == "--plan" || a == "/PLAN") {
+        return run_plan_mode(args.get(pos + 1).filter(|a| !a.starts_with('-') && !a.starts_with('/')));
+    }
+
     // 命令行模式（无GUI）
     if args.contains(&"/PEINSTALL".to_string()) || args.contains(&"--pe-install".to_string()) {
         log::info!("检测到PE安装模式（命令行），执行自动安装...");
@@ -321,10 +326,7 @@ fn try_unlock_fveapi(drive_letter: char, recovery_key: &str) -> bool {
 
 /// 回退：manage-bde 解锁（WinPE 可能未含该工具）。失败原因写日志。
 fn try_unlock_manage_bde(drive: &str, recovery_key: &str) -> bool {
-    match std::process::Command::new("manage-bde")
-        .args(["-unlock", drive, "-RecoveryPassword", recovery_key])
-        .output()
-    {
+    match utils::command::run("manage-bde", ["-unlock", drive, "-RecoveryPassword", recovery_key]) {
         Ok(o) => {
             if o.status.success() {
                 true
@@ -347,6 +349,38 @@ fn try_unlock_manage_bde(drive: &str, recovery_key: &str) -> bool {
     }
 }
 
+/// 执行计划模式：按数据分区上的安装（或备份）配置生成执行计划，写入 JSON 文件
+/// （默认程序目录下 LetRecovery_Plan.json），同时记录到日志。不执行任何操作。
+fn run_plan_mode(out: Option<&String>) -> eframe::Result<()> {
+    use core::config::{ConfigFileManager, OperationType};
+
+    let plan = match ConfigFileManager::detect_operation_type() {
+        Some(OperationType::Backup) => app::build_backup_plan(),
+        Some(OperationType::Install) => app::build_install_plan(),
+        _ => Err(tr!("未检测到安装或备份配置文件。\n\n请确保已正确准备配置文件后重试。")),
+    };
+    let plan = match plan {
+        Ok(p) => p,
+        Err(e) => {
+            log::error!("[PLAN] 生成执行计划失败: {}", e);
+            show_error_message(&e);
+            return Ok(());
+        }
+    };
+    log::info!("[PLAN] 执行计划:\n{}", plan.to_text());
+
+    let out_path = out
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(|| utils::path::get_exe_dir().join("LetRecovery_Plan.json"));
+    if let Err(e) = std::fs::write(&out_path, plan.to_json()) {
+        log::error!("[PLAN] 写入执行计划失败 {}: {}", out_path.display(), e);
+        show_error_message(&tr!("写入执行计划失败: {}", e));
+    } else {
+        log::info!("[PLAN] 执行计划已写入: {}", out_path.display());
+    }
+    Ok(())
+}
+
 /// 命令行模式执行
 fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
     use core::bcdedit::BootManager;
@@ -479,16 +513,35 @@ fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
         log::info!("[PE INSTALL] Step 3: 导入驱动");
         let driver_path = format!("{}\\drivers", data_dir);
         let driver_path_exists = std::path::Path::new(&driver_path).exists();
+        // 本次实际注入的驱动目录（缺驱动报告用）
+        let mut injected_dir: Option<String> = None;
         
         if config.should_import_drivers() && driver_path_exists {
+            // 仅导入与本机硬件匹配的驱动：先按硬件 ID 挑选并暂存，再按目录注入
+            let inject_path = if config.driver_match_devices {
+                crate::core::driver::select_matched_drivers(&driver_path, &apply_dir, &data_dir)
+            } else {
+                driver_path.clone()
+            };
+
             let dism = Dism::new();
-            match dism.add_drivers_offline_with_progress(&apply_dir, &driver_path, None) {
+            injected_dir = Some(inject_path.clone());
+            match dism.add_drivers_offline_with_progress(&apply_dir, &inject_path, None) {
                 Ok(_) => log::info!("[PE INSTALL] 驱动导入成功"),
                 Err(e) => {
                     log::warn!("[PE INSTALL] 警告: 驱动导入失败: {} (继续安装)", e);
                     log::warn!("驱动导入失败: {}", e);
                 }
             }
+
+            // 逐个核实注入结果，写驱动导入报告（目标系统 + 数据分区）
+            let (ok, failed) = crate::core::driver::write_import_report(
+                &inject_path,
+                &apply_dir,
+                &data_dir,
+                &ConfigFileManager::get_reports_dir(&data_partition),
+            );
+            log::info!("[PE INSTALL] 驱动导入报告: 成功 {}, 失败 {}", ok, failed);
             
             // 同时检查驱动目录中是否有 CAB 文件并安装
             let cab_files = find_cab_files_in_dir(&driver_path);
@@ -510,6 +563,9 @@ fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
             log::info!("[PE INSTALL] 跳过驱动导入");
         }
 
+        // 对照正常系统端写入的硬件清单，报告新系统里没有驱动的设备
+        crate::core::driver::report_missing_drivers(injected_dir.as_deref(), &apply_dir, &data_dir);
+
         // Step 4: 安装CAB更新包
         log::info!("[PE INSTALL] Step 4: 安装CAB更新包");
         if config.install_cab_packages {
@@ -586,11 +642,29 @@ fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
         if config.unattended {
             log::info!("[PE INSTALL] Step 7: 生成无人值守配置");
             if !config.custom_unattend_file.is_empty() {
-                // 用户自定义无人值守文件：直接复制到目标系统
+                // 用户自定义无人值守文件：复制到目标系统（合并模式下先叠加内置配置）
                 let data_dir = ConfigFileManager::get_data_dir(&data_partition);
                 let src = format!("{}\\{}", data_dir, config.custom_unattend_file);
                 match std::fs::read(&src) {
-                    Ok(content) => {
+                    Ok(mut content) => {
+                        // 合并模式：叠加内置生成的账户/OOBE（用户写了的以用户为准）。
+                        // 用户文件按 BOM 解码（UTF-16 常见）；无法解析时提示用户并停止，
+                        // 原样写入会丢掉内置的账户配置
+                        if config.custom_unattend_merge {
+                            let ours = deploy_unattend(&target_partition, &config).build();
+                            let user = lr_core::unattend::decode_xml(&content);
+                            match lr_core::unattend::merge_xml(&user, &ours) {
+                                Ok(merged) => content = merged.into_bytes(),
+                                Err(e) => {
+                                    log::error!("[PE INSTALL] 合并无人值守失败: {}", e);
+                                    show_error_message(&tr!(
+                                        "无法解析自定义无人值守文件，合并失败: {}",
+                                        e
+                                    ));
+                                    return Ok(());
+                                }
+                            }
+                        }
                         let panther_dir = format!("{}\\Windows\\Panther", target_partition);
                         let _ = std::fs::create_dir_all(&panther_dir);
                         let _ = std::fs::write(format!("{}\\unattend.xml", panther_dir), &content);
@@ -604,7 +678,7 @@ fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
                     Err(e) => log::error!("[PE INSTALL] 读取自定义无人值守文件失败: {}", e),
                 }
             } else {
-                let _ = generate_unattend_xml(&target_partition, &config.custom_username);
+                let _ = generate_unattend_xml(&target_partition, &config);
             }
         }
 
@@ -749,9 +823,24 @@ fn run_cli_mode(is_install: bool) -> eframe::Result<()> {
     Ok(())
 }
 
-/// 生成无人值守XML
-fn generate_unattend_xml(target_partition: &str, username: &str) -> anyhow::Result<()> {
+/// 内置部署应答（PE 端快速路径仅含账户与 OOBE），架构与代别取自已释放到目标分区的系统
+fn deploy_unattend(
+    target_partition: &str,
+    config: &crate::core::config::InstallConfig,
+) -> lr_core::unattend::DeployUnattend {
+    use lr_core::unattend::DeployUnattend;
+
+    let (arch, generation) = app::target_system(target_partition);
+    let mut spec = DeployUnattend::new(arch, generation, &config.custom_username);
+    spec.custom = config.unattend.clone();
+    spec
+}
 
+/// 生成无人值守XML
+fn generate_unattend_xml(
+    target_partition: &str,
+    config: &crate::core::config::InstallConfig,
+) -> anyhow::Result<()> {
     // 检查是否已存在 unattend.xml，如果存在则跳过生成
     let existing_unattend = Path::new(target_partition)
         .join("windows")
@@ -761,58 +850,7 @@ fn generate_unattend_xml(target_partition: &str, username: &str) -> anyhow::Resu
 	log::info!("[UNATTEND] 目标分区已存在 unattend.xml: {}，跳过生成", existing_unattend.display());
         return Ok(());
     }
-    let username = if username.is_empty() { "MyPc" } else { username };
-
-    let xml_content = format!(
-        r#"<?xml version="1.0" encoding="utf-8"?>
-<unattend xmlns="urn:schemas-microsoft-com:unattend" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State">
-    <settings pass="windowsPE">
-        <component name="Microsoft-Windows-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS">
-            <UserData>
-                <ProductKey>
-                    <WillShowUI>OnError</WillShowUI>
-                </ProductKey>
-                <AcceptEula>true</AcceptEula>
-            </UserData>
-        </component>
-    </settings>
-    <settings pass="oobeSystem">
-        <component name="Microsoft-Windows-Shell-Setup" processorArchitecture="amd64" publicKeyToken="31bf3856ad364e35" language="neutral" versionScope="nonSxS" xmlns:wcm="http://schemas.microsoft.com/WMIConfig/2002/State" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">
-            <OOBE>
-                <HideEULAPage>true</HideEULAPage>
-                <HideLocalAccountScreen>true</HideLocalAccountScreen>
-                <HideOEMRegistrationScreen>true</HideOEMRegistrationScreen>
-                <HideOnlineAccountScreens>true</HideOnlineAccountScreens>
-                <HideWirelessSetupInOOBE>true</HideWirelessSetupInOOBE>
-                <ProtectYourPC>3</ProtectYourPC>
-            </OOBE>
-            <UserAccounts>
-                <LocalAccounts>
-                    <LocalAccount wcm:action="add">
-                        <Password>
-                            <Value></Value>
-                            <PlainText>true</PlainText>
-                        </Password>
-                        <Description>Local User</Description>
-                        <DisplayName>{}</DisplayName>
-                        <Group>Administrators</Group>
-                        <Name>{}</Name>
-                    </LocalAccount>
-                </LocalAccounts>
-            </UserAccounts>
-            <AutoLogon>
-                <Password>
-                    <Value></Value>
-                    <PlainText>true</PlainText>
-                </Password>
-                <Enabled>true</Enabled>
-                <Username>{}</Username>
-            </AutoLogon>
-        </component>
-    </settings>
-</unattend>"#,
-        username, username, username
-    );
+    let xml_content = deploy_unattend(target_partition, config).to_xml();
 
     let panther_dir = format!("{}\\Windows\\Panther", target_partition);
     std::fs::create_dir_all(&panther_dir)?;
@@ -2,6 +2,7 @@ use crate::core::config::InstallConfig;
 use crate::core::dism::Dism;
 use crate::core::registry::OfflineRegistry;
 use crate::utils::path;
+use lr_core::offline_tweaks::TweakOptions;
 use std::path::{Path, PathBuf};
 
 /// 脚本目录名称（统一路径，与正常系统端保持一致）
@@ -45,6 +46,20 @@ fn detect_user_driver_version(target_partition: &str) -> Option<&'static str> {
     }
 }
 
+/// 注册表类优化项开关（供 apply_advanced_options 与执行计划共用）
+pub fn tweak_options(config: &InstallConfig) -> TweakOptions {
+    TweakOptions {
+        remove_shortcut_arrow: config.remove_shortcut_arrow,
+        restore_classic_context_menu: config.restore_classic_context_menu,
+        bypass_nro: config.bypass_nro,
+        disable_windows_update: config.disable_windows_update,
+        disable_windows_defender: config.disable_windows_defender,
+        disable_reserved_storage: config.disable_reserved_storage,
+        disable_uac: config.disable_uac,
+        disable_device_encryption: config.disable_device_encryption,
+    }
+}
+
 /// 应用高级选项到目标系统
 /// 
 /// 此函数在PE环境中执行，负责将用户选择的高级选项应用到目标系统。
@@ -77,163 +92,8 @@ pub fn apply_advanced_options(target_partition: &str, config: &InstallConfig) ->
 
     // ============ 系统优化选项 ============
 
-    // 1. 移除快捷方式小箭头
-    if config.remove_shortcut_arrow {
-        log::info!("[ADVANCED] 移除快捷方式小箭头");
-        let _ = OfflineRegistry::set_string(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Explorer\\Shell Icons",
-            "29",
-            "%systemroot%\\system32\\imageres.dll,197",
-        );
-    }
-
-    // 2. Win11恢复经典右键菜单
-    if config.restore_classic_context_menu {
-        log::info!("[ADVANCED] 恢复经典右键菜单");
-        // 在 DEFAULT hive 中设置（影响所有新用户）
-        if default_loaded {
-            // 创建空的 InprocServer32 键，这会禁用新式右键菜单
-            let _ = OfflineRegistry::create_key(
-                "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
-            );
-            // 设置默认值为空字符串
-            let _ = OfflineRegistry::set_string(
-                "HKLM\\pc-default\\Software\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
-                "",
-                "",
-            );
-        }
-        // 同时在 SOFTWARE 中设置（系统级）
-        let _ = OfflineRegistry::create_key(
-            "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32"
-        );
-        let _ = OfflineRegistry::set_string(
-            "HKLM\\pc-soft\\Classes\\CLSID\\{86ca1aa0-34aa-4e8b-a509-50c905bae2a2}\\InprocServer32",
-            "",
-            "",
-        );
-    }
-
-    // 3. OOBE绕过强制联网
-    if config.bypass_nro {
-        log::info!("[ADVANCED] 设置OOBE绕过联网");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\OOBE",
-            "BypassNRO",
-            1,
-        );
-    }
-
-    // 4. 禁用Windows更新
-    if config.disable_windows_update {
-        log::info!("[ADVANCED] 禁用Windows更新服务");
-        // 禁用 Windows Update 服务 (Start=4 表示禁用)
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\wuauserv",
-            "Start",
-            4,
-        );
-        // 禁用 Update Orchestrator Service
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\UsoSvc",
-            "Start",
-            4,
-        );
-        // 设置策略禁用自动更新
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows\\WindowsUpdate\\AU",
-            "NoAutoUpdate",
-            1,
-        );
-    }
-
-    // 5. 禁用Windows安全中心/Defender
-    if config.disable_windows_defender {
-        log::info!("[ADVANCED] 禁用Windows Defender");
-        // 禁用反间谍软件（Defender主开关）
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender",
-            "DisableAntiSpyware",
-            1,
-        );
-        // 禁用实时保护
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\Windows Defender\\Real-Time Protection",
-            "DisableRealtimeMonitoring",
-            1,
-        );
-        // 禁用 Windows Defender 服务 (Start=4 表示禁用)
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\WinDefend",
-            "Start",
-            4,
-        );
-        // 禁用 Defender 网络检查服务
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\WdNisSvc",
-            "Start",
-            4,
-        );
-        // 禁用安全健康服务
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\SecurityHealthService",
-            "Start",
-            4,
-        );
-    }
-
-    // 6. 禁用系统保留空间
-    if config.disable_reserved_storage {
-        log::info!("[ADVANCED] 禁用系统保留空间");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
-            "ShippedWithReserves",
-            0,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\ReserveManager",
-            "PassedPolicy",
-            0,
-        );
-    }
-
-    // 7. 禁用UAC
-    if config.disable_uac {
-        log::info!("[ADVANCED] 禁用UAC");
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
-            "EnableLUA",
-            0,
-        );
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Microsoft\\Windows\\CurrentVersion\\Policies\\System",
-            "ConsentPromptBehaviorAdmin",
-            0,
-        );
-    }
-
-    // 8. 禁用自动设备加密 (BitLocker)
-    if config.disable_device_encryption {
-        log::info!("[ADVANCED] 禁用自动设备加密");
-        // 禁用 BitLocker 自动加密
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Control\\BitLocker",
-            "PreventDeviceEncryption",
-            1,
-        );
-        // 禁用 MBAM (Microsoft BitLocker Administration and Monitoring)
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-soft\\Policies\\Microsoft\\FVE",
-            "OSRecovery",
-            0,
-        );
-        // 禁用 BitLocker 服务
-        let _ = OfflineRegistry::set_dword(
-            "HKLM\\pc-sys\\ControlSet001\\Services\\BDESVC",
-            "Start",
-            4,
-        );
-    }
+    // 1-8. 纯注册表类优化（改动表见 lr_core::offline_tweaks）
+    tweak_options(config).apply(default_loaded);
 
     // 9. 删除预装UWP应用 - 生成PowerShell脚本
     if config.remove_uwp_apps {
@@ -1,3 +1,3 @@
 //! 命令执行辅助（实现已移入共享库 lr-core，此处再导出以保持调用方不变）。
 
-pub use lr_core::command::new_command;
+pub use lr_core::command::{new_command, run, spawn, Process};